@@ -5,7 +5,11 @@
 //! building a driver package with the provided options using the `cargo build`
 //! command.
 
-use std::path::{Path, PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Output,
+};
 
 use anyhow::Result;
 use cargo_metadata::Message;
@@ -14,15 +18,35 @@
 use tracing::debug;
 use wdk_build::CpuArchitecture;
 
-use super::features_to_cargo_args;
+use super::{ProfileOverride, Sanitizer, WdfVersionOverrides, features_to_cargo_args};
 #[double]
 use crate::providers::exec::CommandExec;
 use crate::{
-    actions::{Profile, build::error::BuildTaskError, to_target_triple},
+    actions::{
+        Profile,
+        build::{error::BuildTaskError, tool_log::ToolLog},
+        to_target_triple,
+    },
     providers::error::CommandError,
-    trace,
+    trace::{self, ColorChoice},
 };
 
+/// Environment variable with the flags cargo passes to every `rustc`
+/// invocation
+const RUSTFLAGS_ENV_VAR: &str = "RUSTFLAGS";
+/// Flags driver crates set in `build.rustflags` of their cargo config. Setting
+/// `RUSTFLAGS` makes cargo ignore `build.rustflags`, so they are repeated.
+const CRT_STATIC_RUSTFLAGS: &str = "-C target-feature=+crt-static";
+/// Flags that turn every compiler warning into an error
+const DENY_WARNINGS_RUSTFLAGS: &str = "-D warnings";
+/// Linker argument that replaces the path of the PDB embedded in the binary,
+/// followed by the replacement
+const PDB_ALTPATH_LINK_ARG: &str = "-C link-arg=/PDBALTPATH:";
+/// Flags of the checked variant of a driver, which enable the `checked` cfg
+/// and the runtime assertions of the standard library and integer overflow
+/// checks regardless of the profile
+const CHECKED_RUSTFLAGS: &str = "--cfg checked -C debug-assertions=on -C overflow-checks=on";
+
 /// Parameters for constructing a [`BuildTask`].
 pub struct BuildTaskParams<'a> {
     /// The name of the package to build
@@ -35,10 +59,36 @@ pub struct BuildTaskParams<'a> {
     pub target_arch: Option<CpuArchitecture>,
     /// Whether to forward `--locked` to the `cargo` invocations
     pub locked: bool,
+    /// Number of parallel jobs to forward to `cargo build` as `-j`
+    pub cargo_jobs: Option<u32>,
+    /// Profile settings forwarded to the `cargo` invocations as `--config
+    /// profile.<profile>.<key>=<value>`
+    pub profile_overrides: &'a [ProfileOverride],
+    /// Whether compiler warnings fail the build
+    pub deny_warnings: bool,
+    /// Path of the PDB embedded in the driver binary instead of its absolute
+    /// path, passed to the linker as `/PDBALTPATH`
+    pub pdb_altpath: Option<&'a str>,
+    /// KMDF and UMDF versions the package is compiled against instead of the
+    /// versions in its WDK metadata
+    pub wdf_versions: WdfVersionOverrides,
+    /// Environment variables set for the `cargo` invocations of this package
+    /// only. `RUSTFLAGS` set here takes precedence over the environment.
+    pub env: &'a [(String, String)],
     /// The feature selection to forward to the `cargo` invocations
     pub features: &'a Features,
+    /// Log the output of `cargo build` is recorded in
+    pub tool_log: &'a ToolLog,
     /// The verbosity level for logging
     pub verbosity_level: clap_verbosity_flag::Verbosity,
+    /// Whether the diagnostics rendered by `cargo` are colored
+    pub color: ColorChoice,
+    /// Sanitizer the package is instrumented with
+    pub sanitizer: Option<Sanitizer>,
+    /// Target directory the checked variant of the package is built in,
+    /// forwarded to `cargo build` as `--target-dir` so that its binaries are
+    /// kept apart from those of the package. `None` builds the package itself.
+    pub checked_target_dir: Option<&'a Path>,
 }
 
 /// Builds specified package by running `cargo build`  
@@ -92,9 +142,153 @@ pub fn new(params: BuildTaskParams<'a>, command_exec: &'a CommandExec) -> Self {
     ///   build` command
     pub fn run(
         &self,
-    ) -> Result<impl Iterator<Item = Result<Message, std::io::Error>>, BuildTaskError> {
+    ) -> Result<impl Iterator<Item = Result<Message, std::io::Error>> + use<>, BuildTaskError> {
         debug!("Running cargo build");
-        let mut args = vec!["build".to_string()];
+        let output = self.run_cargo("build", BuildTaskError::CargoBuild)?;
+
+        debug!("cargo build done");
+        Ok(Message::parse_stream(std::io::Cursor::new(output.stdout)))
+    }
+
+    /// Run `cargo clippy` with the same options as `cargo build`, so that the
+    /// lints are checked against the driver build configuration. Clippy
+    /// renders its diagnostics to stderr. Lints at deny level fail the run,
+    /// and so do warnings if warnings are denied.
+    ///
+    /// # Errors
+    /// * `BuildTaskError::EmptyManifestPath` - If the manifest path is empty or
+    ///   not a valid unicode
+    /// * `BuildTaskError::CargoClippy` - If there is an error running the
+    ///   `cargo clippy` command, e.g. because it reported errors
+    pub fn run_clippy(&self) -> Result<(), BuildTaskError> {
+        debug!("Running cargo clippy");
+        self.run_cargo("clippy", BuildTaskError::CargoClippy)?;
+        debug!("cargo clippy done");
+        Ok(())
+    }
+
+    /// Runs the `pre-build` command of the package, i.e. its program and
+    /// arguments, in the package root with the environment of the package and
+    /// records its output in the tool log
+    ///
+    /// # Errors
+    /// * `BuildTaskError::PreBuildCommand` - If the command cannot be run or
+    ///   exits with an error
+    pub fn run_pre_build(&self, command: &[String]) -> Result<(), BuildTaskError> {
+        let Some((program, args)) = command.split_first() else {
+            return Ok(());
+        };
+        let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+        let env_vars = (!self.params.env.is_empty()).then(|| {
+            self.params
+                .env
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect::<HashMap<_, _>>()
+        });
+        debug!("Running pre-build command {program} {args:?}");
+        let result = self.command_exec.run(
+            program,
+            &args,
+            env_vars.as_ref(),
+            Some(self.params.working_dir),
+        );
+        self.params
+            .tool_log
+            .record(self.params.package_name, program, &args, &result);
+        result.map_err(|err| {
+            BuildTaskError::PreBuildCommand(self.params.package_name.to_string(), err)
+        })?;
+        Ok(())
+    }
+
+    /// Renders the `cargo build` invocation of the package, and the
+    /// `cargo clippy` invocation before it if `clippy` is set, as they would
+    /// be run: the command line followed by the working directory and the
+    /// environment variables set for it.
+    ///
+    /// # Errors
+    /// * `BuildTaskError::EmptyManifestPath` - If the manifest path is empty or
+    ///   not a valid unicode
+    pub fn cargo_command_lines(&self, clippy: bool) -> Result<String, BuildTaskError> {
+        let subcommands = if clippy {
+            &["clippy", "build"][..]
+        } else {
+            &["build"][..]
+        };
+        let mut lines = Vec::new();
+        for subcommand in subcommands {
+            let args = self.cargo_args(subcommand)?;
+            let mut command_line = String::from("cargo");
+            for arg in &args {
+                command_line.push(' ');
+                command_line.push_str(&quote_arg(arg));
+            }
+            lines.push(command_line);
+            lines.push(format!(
+                "  working directory: {}",
+                self.params.working_dir.display()
+            ));
+            let mut env_vars = self.cargo_env_vars().into_iter().collect::<Vec<_>>();
+            env_vars.sort();
+            for (name, value) in env_vars {
+                lines.push(format!("  env: {name}={}", quote_arg(&value)));
+            }
+        }
+        Ok(lines.join("\n"))
+    }
+
+    /// Runs the given cargo subcommand for the package with the configured
+    /// options and records its output in the tool log. A failure of cargo is
+    /// wrapped with `to_error`.
+    fn run_cargo(
+        &self,
+        subcommand: &str,
+        to_error: fn(CommandError) -> BuildTaskError,
+    ) -> Result<Output, BuildTaskError> {
+        let args = self.cargo_args(subcommand)?;
+        let args = args
+            .iter()
+            .map(std::string::String::as_str)
+            .collect::<Vec<&str>>();
+
+        // The environment of the package is passed to cargo instead of being
+        // set on the process, so that packages built by the same action do
+        // not see each other's variables
+        let env_vars = self.cargo_env_vars();
+        let env_vars = (!env_vars.is_empty()).then(|| {
+            env_vars
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect::<HashMap<_, _>>()
+        });
+
+        // Run cargo from the provided working directory so that config.toml is
+        // respected
+        let result = self.command_exec.run(
+            "cargo",
+            &args,
+            env_vars.as_ref(),
+            Some(self.params.working_dir),
+        );
+        self.params
+            .tool_log
+            .record_cargo_build(self.params.package_name, &args, &result);
+        result.map_err(|mut err| {
+            // Drop stdout from CommandFailed so the noisy
+            // --message-format=json-render-diagnostics output isn't bubbled up
+            // in the wrapped error.
+            if let CommandError::CommandFailed { stdout, .. } = &mut err {
+                stdout.clear();
+            }
+            to_error(err)
+        })
+    }
+
+    /// Returns the arguments of the given cargo subcommand for the package with
+    /// the configured options.
+    fn cargo_args(&self, subcommand: &str) -> Result<Vec<String>, BuildTaskError> {
+        let mut args = vec![subcommand.to_string()];
         args.push("--message-format=json-render-diagnostics".to_string());
         args.push("-p".to_string());
         args.push(self.params.package_name.to_string());
@@ -115,33 +309,95 @@ pub fn run(
         if self.params.locked {
             args.push("--locked".to_string());
         }
+        if let Some(cargo_jobs) = self.params.cargo_jobs {
+            args.push("-j".to_string());
+            args.push(cargo_jobs.to_string());
+        }
+        if let Some(checked_target_dir) = self.params.checked_target_dir {
+            args.push("--target-dir".to_string());
+            args.push(checked_target_dir.to_string_lossy().to_string());
+        }
+        for profile_override in self.params.profile_overrides {
+            args.push("--config".to_string());
+            args.push(profile_override.config_value(self.params.profile));
+        }
         args.extend(features_to_cargo_args(self.params.features));
         if let Some(flag) = trace::get_cargo_verbose_flags(self.params.verbosity_level) {
             args.push(flag.to_string());
         }
-        let args = args
+        if let Some(color) = trace::get_cargo_color_flag(self.params.color) {
+            args.push("--color".to_string());
+            args.push(color.to_string());
+        }
+        Ok(args)
+    }
+
+    /// Returns the environment variables set for the cargo invocations of the
+    /// package: the package environment, the WDF version overrides and the
+    /// `RUSTFLAGS` if they are changed.
+    fn cargo_env_vars(&self) -> HashMap<String, String> {
+        let mut env_vars = self
+            .params
+            .env
             .iter()
-            .map(std::string::String::as_str)
-            .collect::<Vec<&str>>();
+            .cloned()
+            .chain(
+                self.params
+                    .wdf_versions
+                    .env_vars()
+                    .into_iter()
+                    .map(|(name, value)| (name.to_string(), value)),
+            )
+            .collect::<HashMap<_, _>>();
+        if let Some(rustflags) = self.rustflags() {
+            env_vars.insert(RUSTFLAGS_ENV_VAR.to_string(), rustflags);
+        }
+        env_vars
+    }
 
-        // Run cargo build from the provided working directory so that config.toml
-        // is respected
-        let output = self
-            .command_exec
-            .run("cargo", &args, None, Some(self.params.working_dir))
-            .map_err(|mut err| {
-                // Drop stdout from CommandFailed so the noisy
-                // --message-format=json-render-diagnostics output isn't bubbled up
-                // in the wrapped error.
-                if let CommandError::CommandFailed { stdout, .. } = &mut err {
-                    stdout.clear();
-                }
-                BuildTaskError::CargoBuild(err)
-            })?;
+    /// Returns the `RUSTFLAGS` of the `cargo build` invocation if compiler
+    /// warnings must fail the build, the PDB path is replaced, the package is
+    /// built with a sanitizer or its checked variant is built, or `None` to
+    /// leave them unchanged. The additional flags are appended to the
+    /// `RUSTFLAGS` of the package or process environment if it is set, or to
+    /// the crt-static flags otherwise.
+    fn rustflags(&self) -> Option<String> {
+        let mut additional_rustflags = Vec::new();
+        if self.params.deny_warnings {
+            additional_rustflags.push(DENY_WARNINGS_RUSTFLAGS.to_string());
+        }
+        if let Some(pdb_altpath) = self.params.pdb_altpath {
+            additional_rustflags.push(format!("{PDB_ALTPATH_LINK_ARG}{pdb_altpath}"));
+        }
+        if let Some(sanitizer) = self.params.sanitizer {
+            additional_rustflags.push(sanitizer.rustflag().to_string());
+        }
+        if self.params.checked_target_dir.is_some() {
+            additional_rustflags.push(CHECKED_RUSTFLAGS.to_string());
+        }
+        if additional_rustflags.is_empty() {
+            return None;
+        }
+        let rustflags = self
+            .params
+            .env
+            .iter()
+            .find(|(name, _)| name == RUSTFLAGS_ENV_VAR)
+            .map(|(_, value)| value.clone())
+            .or_else(|| std::env::var(RUSTFLAGS_ENV_VAR).ok())
+            .filter(|rustflags| !rustflags.trim().is_empty())
+            .unwrap_or_else(|| CRT_STATIC_RUSTFLAGS.to_string());
+        Some(format!("{rustflags} {}", additional_rustflags.join(" ")))
+    }
+}
 
-        debug!("cargo build done");
-        Ok(Message::parse_stream(std::io::Cursor::new(output.stdout)))
+/// Quotes an argument of a rendered command line if it is empty or contains
+/// whitespace or quotes, so that it can be pasted into a shell.
+fn quote_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || c == '"') {
+        return arg.to_string();
     }
+    format!("\"{}\"", arg.replace('"', "\\\""))
 }
 
 #[cfg(test)]
@@ -163,6 +419,7 @@ mod tests {
     fn default_build_task_params<'a>(
         working_dir: &'a Path,
         features: &'a Features,
+        tool_log: &'a ToolLog,
     ) -> BuildTaskParams<'a> {
         BuildTaskParams {
             package_name: "my-driver",
@@ -170,14 +427,25 @@ fn default_build_task_params<'a>(
             profile: None,
             target_arch: None,
             locked: false,
+            cargo_jobs: None,
+            profile_overrides: &[],
+            deny_warnings: false,
+            pdb_altpath: None,
+            wdf_versions: WdfVersionOverrides::default(),
+            env: &[],
             features,
+            tool_log,
             verbosity_level: clap_verbosity_flag::Verbosity::default(),
+            color: ColorChoice::Auto,
+            sanitizer: None,
+            checked_target_dir: None,
         }
     }
 
     #[test]
     fn new_succeeds_for_valid_args() {
         let working_dir = PathBuf::from("C:/absolute/path/to/working/dir");
+        let tool_log = ToolLog::default();
         let package_name = "test_package";
         let profile = Profile::Dev;
         let target_arch = Some(CpuArchitecture::Amd64);
@@ -189,7 +457,7 @@ fn new_succeeds_for_valid_args() {
                 package_name,
                 profile: Some(&profile),
                 target_arch,
-                ..default_build_task_params(&working_dir, &features)
+                ..default_build_task_params(&working_dir, &features, &tool_log)
             },
             &command_exec,
         );
@@ -212,11 +480,12 @@ fn new_succeeds_for_valid_args() {
                                relative/path/to/working/dir")]
     fn new_panics_when_working_dir_is_not_absolute() {
         let working_dir = PathBuf::from("relative/path/to/working/dir");
+        let tool_log = ToolLog::default();
         let features = Features::default();
         let command_exec = CommandExec::new();
 
         BuildTask::new(
-            default_build_task_params(&working_dir, &features),
+            default_build_task_params(&working_dir, &features, &tool_log),
             &command_exec,
         );
     }
@@ -224,6 +493,7 @@ fn new_panics_when_working_dir_is_not_absolute() {
     #[test]
     fn run_invokes_cargo_build_with_expected_args_and_returns_output() {
         let working_dir = PathBuf::from("C:/abs/driver");
+        let tool_log = ToolLog::default();
         let manifest_path = working_dir.join("Cargo.toml");
         let manifest_path_string = manifest_path.to_string_lossy().to_string();
         let profile = Profile::Release;
@@ -271,7 +541,7 @@ fn run_invokes_cargo_build_with_expected_args_and_returns_output() {
             BuildTaskParams {
                 profile: Some(&profile),
                 target_arch: Some(target_arch),
-                ..default_build_task_params(&working_dir, &features)
+                ..default_build_task_params(&working_dir, &features, &tool_log)
             },
             &mock,
         );
@@ -294,6 +564,7 @@ fn run_invokes_cargo_build_with_expected_args_and_returns_output() {
     #[test]
     fn run_returns_command_failed_error_with_empty_stdout_when_cargo_build_exits_nonzero() {
         let working_dir = PathBuf::from("C:/abs/driver");
+        let tool_log = ToolLog::default();
         let mut mock = MockCommandExec::new();
         mock.expect_run().return_once(|_, _, _, _| {
             let failure_output = Output {
@@ -309,7 +580,10 @@ fn run_returns_command_failed_error_with_empty_stdout_when_cargo_build_exits_non
         });
 
         let features = Features::default();
-        let task = BuildTask::new(default_build_task_params(&working_dir, &features), &mock);
+        let task = BuildTask::new(
+            default_build_task_params(&working_dir, &features, &tool_log),
+            &mock,
+        );
 
         let err = task.run().err().expect("expected cargo failure");
         let BuildTaskError::CargoBuild(CommandError::CommandFailed {
@@ -334,6 +608,7 @@ fn run_returns_command_failed_error_with_empty_stdout_when_cargo_build_exits_non
     #[test]
     fn run_returns_io_error_when_cargo_build_command_invocation_fails() {
         let working_dir = PathBuf::from("C:/abs/driver");
+        let tool_log = ToolLog::default();
         let mut mock = MockCommandExec::new();
         mock.expect_run().return_once(|_, _, _, _| {
             Err(CommandError::from_io_error(
@@ -344,7 +619,10 @@ fn run_returns_io_error_when_cargo_build_command_invocation_fails() {
         });
 
         let features = Features::default();
-        let task = BuildTask::new(default_build_task_params(&working_dir, &features), &mock);
+        let task = BuildTask::new(
+            default_build_task_params(&working_dir, &features, &tool_log),
+            &mock,
+        );
 
         let err = task
             .run()
@@ -361,9 +639,117 @@ fn run_returns_io_error_when_cargo_build_command_invocation_fails() {
         assert_eq!(io_err.kind(), std::io::ErrorKind::NotFound);
     }
 
+    #[test]
+    fn run_clippy_invokes_cargo_clippy_with_the_build_options() {
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let tool_log = ToolLog::default();
+        let features = Features::default();
+
+        let mut mock = MockCommandExec::new();
+        mock.expect_run()
+            .withf(|command, args, env, _wd| {
+                command == "cargo"
+                    && args.first() == Some(&"clippy")
+                    && args
+                        .windows(2)
+                        .any(|w| w == ["--target", "aarch64-pc-windows-msvc"])
+                    && env.is_some_and(|env| {
+                        env.get(RUSTFLAGS_ENV_VAR)
+                            == Some(&"-C target-feature=+crt-static -D warnings")
+                    })
+            })
+            .return_once(|_, _, _, _| {
+                let failure_output = Output {
+                    status: ExitStatus::from_raw(101),
+                    stdout: Vec::new(),
+                    stderr: b"error: could not compile `my-driver`".to_vec(),
+                };
+                Err(CommandError::from_output(
+                    "cargo",
+                    &["clippy"],
+                    &failure_output,
+                ))
+            });
+
+        let task = BuildTask::new(
+            BuildTaskParams {
+                target_arch: Some(CpuArchitecture::Arm64),
+                deny_warnings: true,
+                ..default_build_task_params(&working_dir, &features, &tool_log)
+            },
+            &mock,
+        );
+
+        crate::test_utils::with_env::<&str, &str, _, _>(&[(RUSTFLAGS_ENV_VAR, None)], || {
+            let err = task
+                .run_clippy()
+                .expect_err("expected cargo clippy failure");
+            assert!(
+                matches!(
+                    err,
+                    BuildTaskError::CargoClippy(CommandError::CommandFailed { .. })
+                ),
+                "expected CargoClippy(CommandFailed) error, got: {err:?}"
+            );
+        });
+    }
+
+    #[test]
+    fn run_pre_build_runs_the_command_in_the_package_root_with_the_package_env() {
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let tool_log = ToolLog::default();
+        let features = Features::default();
+        let env = [("SPEC".to_string(), "spec.json".to_string())];
+        let command = ["codegen".to_string(), "--out".to_string(), "gen.h".to_string()];
+
+        let mut mock = MockCommandExec::new();
+        mock.expect_run()
+            .withf(|command, args, env, working_dir| {
+                command == "codegen"
+                    && args == ["--out", "gen.h"]
+                    && env.is_some_and(|env| env.get("SPEC") == Some(&"spec.json"))
+                    && *working_dir == Some(Path::new("C:/abs/driver"))
+            })
+            .return_once(|command, args, _, _| {
+                Err(CommandError::from_output(
+                    command,
+                    args,
+                    &Output {
+                        status: ExitStatus::from_raw(1),
+                        stdout: Vec::new(),
+                        stderr: b"spec.json not found".to_vec(),
+                    },
+                ))
+            });
+
+        let task = BuildTask::new(
+            BuildTaskParams {
+                env: &env,
+                ..default_build_task_params(&working_dir, &features, &tool_log)
+            },
+            &mock,
+        );
+
+        let err = task
+            .run_pre_build(&command)
+            .expect_err("expected pre-build command failure");
+        assert!(
+            matches!(
+                err,
+                BuildTaskError::PreBuildCommand(
+                    ref package_name,
+                    CommandError::CommandFailed { .. }
+                ) if package_name == "my-driver"
+            ),
+            "expected PreBuildCommand(CommandFailed) error, got: {err:?}"
+        );
+        assert!(task.run_pre_build(&[]).is_ok());
+    }
+
     #[test]
     fn run_forwards_locked_to_cargo_invocation_when_locked_is_set() {
         let working_dir = PathBuf::from("C:/abs/driver");
+        let tool_log = ToolLog::default();
         let features = Features::default();
         let mut expected_stdout = br#"{"reason":"build-finished","success":true}"#.to_vec();
         expected_stdout.push(b'\n');
@@ -383,7 +769,137 @@ fn run_forwards_locked_to_cargo_invocation_when_locked_is_set() {
         let task = BuildTask::new(
             BuildTaskParams {
                 locked: true,
-                ..default_build_task_params(&working_dir, &features)
+                ..default_build_task_params(&working_dir, &features, &tool_log)
+            },
+            &mock,
+        );
+
+        task.run()
+            .expect("expected an iterator over parsed cargo message objects")
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .expect("expected valid cargo messages");
+    }
+
+    #[test]
+    fn run_forwards_color_to_cargo_invocation_unless_it_is_auto() {
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let tool_log = ToolLog::default();
+        let features = Features::default();
+
+        for (color, expected_flag) in [
+            (ColorChoice::Auto, None),
+            (ColorChoice::Always, Some("always")),
+            (ColorChoice::Never, Some("never")),
+        ] {
+            let mut mock = MockCommandExec::new();
+            mock.expect_run()
+                .withf(move |command, args, _env, _wd| {
+                    let forwarded_flag = args
+                        .windows(2)
+                        .find(|w| w[0] == "--color")
+                        .map(|w| w[1]);
+                    command == "cargo"
+                        && forwarded_flag == expected_flag
+                        && args.iter().filter(|arg| **arg == "--color").count()
+                            == usize::from(expected_flag.is_some())
+                })
+                .return_once(|_, _, _, _| {
+                    Ok(Output {
+                        status: ExitStatus::default(),
+                        stdout: br#"{"reason":"build-finished","success":true}"#.to_vec(),
+                        stderr: Vec::new(),
+                    })
+                });
+
+            let task = BuildTask::new(
+                BuildTaskParams {
+                    color,
+                    ..default_build_task_params(&working_dir, &features, &tool_log)
+                },
+                &mock,
+            );
+
+            task.run()
+                .expect("expected an iterator over parsed cargo message objects")
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .expect("expected valid cargo messages");
+        }
+    }
+
+    #[test]
+    fn run_forwards_cargo_jobs_to_cargo_invocation_when_cargo_jobs_is_set() {
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let tool_log = ToolLog::default();
+        let features = Features::default();
+        let mut expected_stdout = br#"{"reason":"build-finished","success":true}"#.to_vec();
+        expected_stdout.push(b'\n');
+        let expected_stdout_for_mock = expected_stdout.clone();
+
+        let mut mock = MockCommandExec::new();
+        mock.expect_run()
+            .withf(|command, args, _env, _wd| {
+                command == "cargo" && args.windows(2).any(|w| w == ["-j", "2"])
+            })
+            .return_once(move |_, _, _, _| {
+                Ok(Output {
+                    status: ExitStatus::default(),
+                    stdout: expected_stdout_for_mock,
+                    stderr: Vec::new(),
+                })
+            });
+
+        let task = BuildTask::new(
+            BuildTaskParams {
+                cargo_jobs: Some(2),
+                ..default_build_task_params(&working_dir, &features, &tool_log)
+            },
+            &mock,
+        );
+
+        task.run()
+            .expect("expected an iterator over parsed cargo message objects")
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .expect("expected valid cargo messages");
+    }
+
+    #[test]
+    fn run_forwards_profile_overrides_as_config_of_the_profile_being_built() {
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let tool_log = ToolLog::default();
+        let features = Features::default();
+        let profile = Profile::Release;
+        let profile_overrides = ["opt-level=3", "debug-assertions=true"].map(|profile_override| {
+            profile_override
+                .parse::<ProfileOverride>()
+                .expect("override should be valid")
+        });
+
+        let mut mock = MockCommandExec::new();
+        mock.expect_run()
+            .withf(|command, args, _env, _wd| {
+                command == "cargo"
+                    && args.windows(4).any(|w| {
+                        w == [
+                            "--config",
+                            "profile.release.opt-level=3",
+                            "--config",
+                            "profile.release.debug-assertions=true",
+                        ]
+                    })
+            })
+            .return_once(|_, _, _, _| {
+                Ok(Output {
+                    status: ExitStatus::default(),
+                    stdout: br#"{"reason":"build-finished","success":true}"#.to_vec(),
+                    stderr: Vec::new(),
+                })
+            });
+
+        let task = BuildTask::new(
+            BuildTaskParams {
+                profile: Some(&profile),
+                profile_overrides: &profile_overrides,
+                ..default_build_task_params(&working_dir, &features, &tool_log)
             },
             &mock,
         );
@@ -394,9 +910,254 @@ fn run_forwards_locked_to_cargo_invocation_when_locked_is_set() {
             .expect("expected valid cargo messages");
     }
 
+    #[test]
+    fn run_sets_rustflags_with_the_sanitizer_merged_with_the_crt_static_flags() {
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let tool_log = ToolLog::default();
+        let features = Features::default();
+
+        let mut mock = MockCommandExec::new();
+        mock.expect_run()
+            .withf(|command, _args, env, _wd| {
+                command == "cargo"
+                    && env.is_some_and(|env| {
+                        env.get(RUSTFLAGS_ENV_VAR).copied()
+                            == Some("-C target-feature=+crt-static -D warnings -Zsanitizer=address")
+                    })
+            })
+            .return_once(|_, _, _, _| {
+                Ok(Output {
+                    status: ExitStatus::default(),
+                    stdout: br#"{"reason":"build-finished","success":true}"#.to_vec(),
+                    stderr: Vec::new(),
+                })
+            });
+
+        let task = BuildTask::new(
+            BuildTaskParams {
+                deny_warnings: true,
+                sanitizer: Some(Sanitizer::Address),
+                ..default_build_task_params(&working_dir, &features, &tool_log)
+            },
+            &mock,
+        );
+
+        crate::test_utils::with_env::<&str, &str, _, _>(&[(RUSTFLAGS_ENV_VAR, None)], || {
+            task.run()
+                .expect("expected an iterator over parsed cargo message objects")
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .expect("expected valid cargo messages");
+        });
+    }
+
+    #[test]
+    fn run_sets_rustflags_denying_warnings_when_deny_warnings_is_set() {
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let tool_log = ToolLog::default();
+        let features = Features::default();
+        let mut expected_stdout = br#"{"reason":"build-finished","success":true}"#.to_vec();
+        expected_stdout.push(b'\n');
+        let expected_stdout_for_mock = expected_stdout.clone();
+
+        let mut mock = MockCommandExec::new();
+        mock.expect_run()
+            .withf(|command, _args, env, _wd| {
+                command == "cargo"
+                    && env.is_some_and(|env| {
+                        env.get(RUSTFLAGS_ENV_VAR)
+                            == Some(&"-C target-feature=+crt-static -D warnings")
+                    })
+            })
+            .return_once(move |_, _, _, _| {
+                Ok(Output {
+                    status: ExitStatus::default(),
+                    stdout: expected_stdout_for_mock,
+                    stderr: Vec::new(),
+                })
+            });
+
+        let task = BuildTask::new(
+            BuildTaskParams {
+                deny_warnings: true,
+                ..default_build_task_params(&working_dir, &features, &tool_log)
+            },
+            &mock,
+        );
+
+        crate::test_utils::with_env::<&str, &str, _, _>(&[(RUSTFLAGS_ENV_VAR, None)], || {
+            task.run()
+                .expect("expected an iterator over parsed cargo message objects")
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .expect("expected valid cargo messages");
+        });
+    }
+
+    #[test]
+    fn run_sets_rustflags_replacing_the_pdb_path_when_pdb_altpath_is_set() {
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let tool_log = ToolLog::default();
+        let features = Features::default();
+        let mut expected_stdout = br#"{"reason":"build-finished","success":true}"#.to_vec();
+        expected_stdout.push(b'\n');
+        let expected_stdout_for_mock = expected_stdout.clone();
+
+        let mut mock = MockCommandExec::new();
+        mock.expect_run()
+            .withf(|command, _args, env, _wd| {
+                command == "cargo"
+                    && env.is_some_and(|env| {
+                        env.get(RUSTFLAGS_ENV_VAR)
+                            == Some(&"-C target-feature=+crt-static -C link-arg=/PDBALTPATH:%_PDB%")
+                    })
+            })
+            .return_once(move |_, _, _, _| {
+                Ok(Output {
+                    status: ExitStatus::default(),
+                    stdout: expected_stdout_for_mock,
+                    stderr: Vec::new(),
+                })
+            });
+
+        let task = BuildTask::new(
+            BuildTaskParams {
+                pdb_altpath: Some("%_PDB%"),
+                ..default_build_task_params(&working_dir, &features, &tool_log)
+            },
+            &mock,
+        );
+
+        crate::test_utils::with_env::<&str, &str, _, _>(&[(RUSTFLAGS_ENV_VAR, None)], || {
+            task.run()
+                .expect("expected an iterator over parsed cargo message objects")
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .expect("expected valid cargo messages");
+        });
+    }
+
+    #[test]
+    fn run_builds_the_checked_variant_in_its_own_target_dir_with_its_rustflags() {
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let checked_target_dir = PathBuf::from("C:/abs/driver/target/checked");
+        let tool_log = ToolLog::default();
+        let features = Features::default();
+        let env = [(
+            RUSTFLAGS_ENV_VAR.to_string(),
+            "-C target-feature=+crt-static --cfg ring=\"test\"".to_string(),
+        )];
+
+        let mut mock = MockCommandExec::new();
+        mock.expect_run()
+            .withf(|command, args, env, _wd| {
+                command == "cargo"
+                    && args.windows(2).any(|w| {
+                        w[0] == "--target-dir" && Path::new(w[1]).ends_with("target/checked")
+                    })
+                    && env.is_some_and(|env| {
+                        env.get(RUSTFLAGS_ENV_VAR).copied()
+                            == Some(
+                                "-C target-feature=+crt-static --cfg ring=\"test\" --cfg checked \
+                                 -C debug-assertions=on -C overflow-checks=on",
+                            )
+                    })
+            })
+            .return_once(|_, _, _, _| {
+                Ok(Output {
+                    status: ExitStatus::default(),
+                    stdout: br#"{"reason":"build-finished","success":true}"#.to_vec(),
+                    stderr: Vec::new(),
+                })
+            });
+
+        let task = BuildTask::new(
+            BuildTaskParams {
+                env: &env,
+                checked_target_dir: Some(&checked_target_dir),
+                ..default_build_task_params(&working_dir, &features, &tool_log)
+            },
+            &mock,
+        );
+
+        task.run()
+            .expect("expected an iterator over parsed cargo message objects")
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .expect("expected valid cargo messages");
+    }
+
+    #[test]
+    fn run_passes_the_package_env_to_cargo_without_setting_it_on_the_process() {
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let tool_log = ToolLog::default();
+        let features = Features::default();
+        let env = [("DRIVER_FLAVOR".to_string(), "checked".to_string())];
+
+        let mut mock = MockCommandExec::new();
+        mock.expect_run()
+            .withf(|command, _args, env, _wd| {
+                command == "cargo"
+                    && env.is_some_and(|env| {
+                        env.len() == 1 && env.get("DRIVER_FLAVOR") == Some(&"checked")
+                    })
+            })
+            .return_once(|_, _, _, _| {
+                Ok(Output {
+                    status: ExitStatus::default(),
+                    stdout: Vec::new(),
+                    stderr: Vec::new(),
+                })
+            });
+
+        let task = BuildTask::new(
+            BuildTaskParams {
+                env: &env,
+                ..default_build_task_params(&working_dir, &features, &tool_log)
+            },
+            &mock,
+        );
+
+        task.run().expect("expected cargo build to succeed");
+        assert!(std::env::var("DRIVER_FLAVOR").is_err());
+    }
+
+    #[test]
+    fn run_passes_the_wdf_version_overrides_to_cargo() {
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let tool_log = ToolLog::default();
+        let features = Features::default();
+
+        let mut mock = MockCommandExec::new();
+        mock.expect_run()
+            .withf(|command, _args, env, _wd| {
+                command == "cargo"
+                    && env.is_some_and(|env| {
+                        env.len() == 1 && env.get("WDK_BUILD_KMDF_VERSION") == Some(&"1.31")
+                    })
+            })
+            .return_once(|_, _, _, _| {
+                Ok(Output {
+                    status: ExitStatus::default(),
+                    stdout: Vec::new(),
+                    stderr: Vec::new(),
+                })
+            });
+
+        let task = BuildTask::new(
+            BuildTaskParams {
+                wdf_versions: WdfVersionOverrides {
+                    kmdf: Some("1.31".parse().unwrap()),
+                    umdf: None,
+                },
+                ..default_build_task_params(&working_dir, &features, &tool_log)
+            },
+            &mock,
+        );
+
+        task.run().expect("expected cargo build to succeed");
+    }
+
     #[test]
     fn run_forwards_features_to_cargo_invocation_when_features_are_set() {
         let working_dir = PathBuf::from("C:/abs/driver");
+        let tool_log = ToolLog::default();
         let mut features = Features::default();
         features.all_features = true;
         features.no_default_features = true;
@@ -422,11 +1183,50 @@ fn run_forwards_features_to_cargo_invocation_when_features_are_set() {
                 })
             });
 
-        let task = BuildTask::new(default_build_task_params(&working_dir, &features), &mock);
+        let task = BuildTask::new(
+            default_build_task_params(&working_dir, &features, &tool_log),
+            &mock,
+        );
 
         task.run()
             .expect("expected cargo build to succeed")
             .collect::<std::result::Result<Vec<_>, _>>()
             .expect("expected valid cargo messages");
     }
+
+    #[test]
+    fn cargo_command_lines_renders_clippy_and_build_with_working_dir_and_env() {
+        let working_dir = PathBuf::from("C:/abs/my driver");
+        let tool_log = ToolLog::default();
+        let features = Features::default();
+        let env = [("DRIVER_FLAVOR".to_string(), "checked build".to_string())];
+        let mock = MockCommandExec::new();
+
+        let task = BuildTask::new(
+            BuildTaskParams {
+                target_arch: Some(CpuArchitecture::Arm64),
+                locked: true,
+                env: &env,
+                ..default_build_task_params(&working_dir, &features, &tool_log)
+            },
+            &mock,
+        );
+
+        let manifest_path = working_dir.join("Cargo.toml");
+        let expected_args = format!(
+            "--message-format=json-render-diagnostics -p my-driver --manifest-path \"{}\" \
+             --target aarch64-pc-windows-msvc --locked",
+            manifest_path.display()
+        );
+        let expected_details =
+            "  working directory: C:/abs/my driver\n  env: DRIVER_FLAVOR=\"checked build\"";
+        assert_eq!(
+            task.cargo_command_lines(true)
+                .expect("expected the cargo command lines to be rendered"),
+            format!(
+                "cargo clippy {expected_args}\n{expected_details}\ncargo build \
+                 {expected_args}\n{expected_details}"
+            )
+        );
+    }
 }
@@ -0,0 +1,108 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module contains the file name patterns of the `catalog-exclude`
+//! packaging setting. Files of a driver package whose name matches one of the
+//! patterns are left out of the catalog files generated by `inf2cat`, e.g.
+//! documentation or the linker map of the driver.
+
+use std::fmt;
+
+/// Characters that are not allowed in the file name patterns. Wildcards are
+/// allowed, path separators are not since patterns match file names only.
+const INVALID_PATTERN_CHARS: [char; 7] = ['<', '>', ':', '"', '/', '\\', '|'];
+
+/// A file name pattern in which `*` matches any sequence of characters and `?`
+/// matches any single character. Patterns match case-insensitively since
+/// Windows file names are case-insensitive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CatalogExcludePattern(String);
+
+impl CatalogExcludePattern {
+    /// Parses a file name pattern, returning `None` if it is empty, contains a
+    /// path separator or another character that is not allowed in file names
+    pub fn parse(pattern: &str) -> Option<Self> {
+        (!pattern.trim().is_empty()
+            && !pattern
+                .chars()
+                .any(|c| c.is_control() || INVALID_PATTERN_CHARS.contains(&c)))
+        .then(|| Self(pattern.to_string()))
+    }
+
+    /// Returns `true` if the given file name matches the pattern
+    pub fn matches(&self, file_name: &str) -> bool {
+        let pattern = self.0.to_lowercase().chars().collect::<Vec<_>>();
+        let file_name = file_name.to_lowercase().chars().collect::<Vec<_>>();
+        // Greedy wildcard matching that backtracks to the last `*` on a
+        // mismatch
+        let (mut pattern_index, mut file_name_index) = (0, 0);
+        let mut last_star = None;
+        while file_name_index < file_name.len() {
+            match pattern.get(pattern_index) {
+                Some('*') => {
+                    last_star = Some((pattern_index, file_name_index));
+                    pattern_index += 1;
+                }
+                Some(&c) if c == '?' || c == file_name[file_name_index] => {
+                    pattern_index += 1;
+                    file_name_index += 1;
+                }
+                _ => {
+                    let Some((star_index, star_file_name_index)) = last_star else {
+                        return false;
+                    };
+                    pattern_index = star_index + 1;
+                    file_name_index = star_file_name_index + 1;
+                    last_star = Some((star_index, file_name_index));
+                }
+            }
+        }
+        pattern[pattern_index..].iter().all(|&c| c == '*')
+    }
+}
+
+impl fmt::Display for CatalogExcludePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rejects_empty_patterns_and_paths() {
+        assert!(CatalogExcludePattern::parse("*.map").is_some());
+        assert!(CatalogExcludePattern::parse("README.md").is_some());
+        for pattern in ["", " ", "docs/*.md", r"docs\*.md", "C:*.txt", "a|b"] {
+            assert_eq!(CatalogExcludePattern::parse(pattern), None, "{pattern}");
+        }
+    }
+
+    #[test]
+    fn matches_wildcards_case_insensitively() {
+        for (pattern, file_name, expected) in [
+            ("*.map", "driver.map", true),
+            ("*.map", "DRIVER.MAP", true),
+            ("*.map", "driver.map.txt", false),
+            ("*.md", "README.md", true),
+            ("readme.*", "README.md", true),
+            ("driver?.txt", "driver1.txt", true),
+            ("driver?.txt", "driver.txt", false),
+            ("*notes*", "release_notes.txt", true),
+            ("*a*b", "aXbXb", true),
+            ("*a*b", "aXbXc", false),
+            ("*", "driver.sys", true),
+            ("driver.sys", "driver.sys", true),
+            ("driver.sys", "driver.sy", false),
+        ] {
+            assert_eq!(
+                CatalogExcludePattern::parse(pattern)
+                    .expect("pattern should be valid")
+                    .matches(file_name),
+                expected,
+                "{pattern} {file_name}"
+            );
+        }
+    }
+}
@@ -0,0 +1,162 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module collects the files of the diagnostic bundle written by the
+//! `--diagnostic-bundle` option of the build action, i.e. the settings of the
+//! build, the WDK it used, the metadata and resolved packaging settings of each
+//! package, the command lines and output of the tools run and the errors and
+//! warnings of the build. The files are written to a single zip archive, also
+//! when the build fails, so that it can be attached to a bug report or kept as
+//! a CI artifact.
+
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    io::{Cursor, Write},
+    path::PathBuf,
+};
+
+use mockall_double::double;
+use tracing::info;
+use zip::{CompressionMethod, DateTime, ZipWriter, write::SimpleFileOptions};
+
+use crate::providers::error::FileError;
+#[double]
+use crate::providers::fs::Fs;
+
+/// Files collected for the diagnostic bundle of a build
+#[derive(Debug, Default)]
+pub struct DiagnosticBundle {
+    // Path of the zip archive. Nothing is collected if it is not set.
+    path: Option<PathBuf>,
+    files: RefCell<BTreeMap<String, String>>,
+}
+
+impl DiagnosticBundle {
+    /// Creates a diagnostic bundle that is written to the zip archive at
+    /// `path`, or that discards the files added to it if `path` is `None`
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            path,
+            files: RefCell::default(),
+        }
+    }
+
+    /// Returns `true` if the diagnostic bundle is written
+    pub const fn is_enabled(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// Adds a file to the bundle, replacing the file of the same name.
+    /// `name` is the path of the file in the archive, separated by `/`.
+    pub fn add_file(&self, name: &str, content: String) {
+        if self.is_enabled() {
+            self.files.borrow_mut().insert(name.to_string(), content);
+        }
+    }
+
+    /// Appends a line to a file of the bundle, adding the file if it does not
+    /// exist yet
+    pub fn append_line(&self, name: &str, line: &str) {
+        if self.is_enabled() {
+            let mut files = self.files.borrow_mut();
+            let content = files.entry(name.to_string()).or_default();
+            content.push_str(line);
+            content.push('\n');
+        }
+    }
+
+    /// Writes the files added to the bundle to its zip archive. Nothing is
+    /// written if no path is set.
+    pub fn write(&self, fs: &Fs) -> Result<(), FileError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs.create_dir_all(parent)?;
+        }
+        let archive = self
+            .zip_archive()
+            .map_err(|e| FileError::WriteError(path.clone(), e.into()))?;
+        fs.write_to_file(path, &archive)?;
+        info!("Diagnostic bundle written to {}", path.display());
+        Ok(())
+    }
+
+    /// Returns a zip archive of the files added to the bundle. The files are
+    /// stored without compression and with a fixed modification time, so that
+    /// the archive is the same for the same files.
+    fn zip_archive(&self) -> zip::result::ZipResult<Vec<u8>> {
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Stored)
+            .last_modified_time(DateTime::default());
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in self.files.borrow().iter() {
+            writer.start_file(name.as_str(), options)?;
+            writer.write_all(content.as_bytes())?;
+        }
+        Ok(writer.finish()?.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Read, path::Path};
+
+    use super::*;
+
+    /// Returns the content of the file `name` of the zip archive `data`
+    fn read_archive_file(data: &[u8], name: &str) -> String {
+        let mut archive = zip::ZipArchive::new(Cursor::new(data)).expect("archive is valid");
+        let mut content = String::new();
+        archive
+            .by_name(name)
+            .expect("file is in the archive")
+            .read_to_string(&mut content)
+            .expect("file is valid UTF-8");
+        content
+    }
+
+    #[test]
+    fn zip_archive_contains_every_file() {
+        let bundle = DiagnosticBundle::new(Some(PathBuf::from("bundle.zip")));
+        bundle.add_file("build.json", "{}".to_string());
+        bundle.append_line("logs/a.log", "> cargo");
+
+        let archive = bundle.zip_archive().expect("archive is written");
+        assert_eq!(
+            zip::ZipArchive::new(Cursor::new(archive.as_slice()))
+                .expect("archive is valid")
+                .len(),
+            2
+        );
+        assert_eq!(read_archive_file(&archive, "build.json"), "{}");
+        assert_eq!(read_archive_file(&archive, "logs/a.log"), "> cargo\n");
+    }
+
+    #[test]
+    fn files_are_written_to_the_archive_only_when_a_path_is_set() {
+        let bundle = DiagnosticBundle::new(Some(PathBuf::from("C:/ci/bundle.zip")));
+        bundle.add_file("build.json", "{}".to_string());
+        bundle.append_line("errors.txt", "driver_a failed");
+        bundle.append_line("errors.txt", "driver_b failed");
+
+        let mut fs = Fs::default();
+        fs.expect_create_dir_all()
+            .withf(|path: &Path| path == Path::new("C:/ci"))
+            .once()
+            .returning(|_| Ok(()));
+        fs.expect_write_to_file()
+            .withf(|path: &Path, data: &[u8]| {
+                path == Path::new("C:/ci/bundle.zip")
+                    && read_archive_file(data, "errors.txt") == "driver_a failed\ndriver_b failed\n"
+            })
+            .once()
+            .returning(|_, _| Ok(()));
+        assert!(bundle.write(&fs).is_ok());
+
+        let bundle = DiagnosticBundle::default();
+        bundle.add_file("build.json", "{}".to_string());
+        assert!(bundle.files.borrow().is_empty());
+        assert!(bundle.write(&Fs::default()).is_ok());
+    }
+}
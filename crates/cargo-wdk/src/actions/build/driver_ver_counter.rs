@@ -0,0 +1,154 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module implements the `auto-increment-driver-ver` packaging setting.
+//! The version of the `DriverVer` of a package is its crate version followed
+//! by a revision that is incremented by every build. The last version stamped
+//! into the package is recorded in the target directory, so the revision
+//! restarts at 1 when the crate version changes or the record is removed, e.g.
+//! by `cargo clean`.
+
+use std::path::{Path, PathBuf};
+
+use cargo_metadata::semver::Version;
+use mockall_double::double;
+use tracing::{debug, info};
+
+use crate::actions::build::error::BuildActionError;
+#[double]
+use crate::providers::fs::Fs;
+
+/// Extension of the file in the target directory that records the last
+/// `DriverVer` version of a package
+const DRIVER_VER_RECORD_EXTENSION: &str = "driver-ver";
+/// Largest value of a part of a `DriverVer` version
+const MAX_DRIVER_VER_PART: u32 = 65535;
+
+/// Returns the path of the file in the given target directory that records
+/// the last `DriverVer` version of the package
+pub fn driver_ver_record_file_path(target_dir: &Path, package_name: &str) -> PathBuf {
+    target_dir.join(format!("{package_name}.{DRIVER_VER_RECORD_EXTENSION}"))
+}
+
+/// Returns the `DriverVer` version of the next build of a package with the
+/// given crate version, i.e. the recorded version with its revision
+/// incremented, or the crate version with revision 1 if no version of the
+/// crate version is recorded.
+///
+/// # Errors
+/// * `BuildActionError::InvalidDriverVerRecord` - If the record does not
+///   contain a four part version.
+/// * `BuildActionError::DriverVerRevisionExhausted` - If the recorded revision
+///   is already the largest `DriverVer` revision.
+/// * `BuildActionError::FileIo` - If there is an IO error reading the record.
+pub fn next_driver_ver_version(
+    fs: &Fs,
+    target_dir: &Path,
+    package_name: &str,
+    package_version: &Version,
+) -> Result<String, BuildActionError> {
+    let record_file_path = driver_ver_record_file_path(target_dir, package_name);
+    let crate_version = format!(
+        "{}.{}.{}",
+        package_version.major, package_version.minor, package_version.patch
+    );
+    let revision = if fs.exists(&record_file_path) {
+        let content = fs.read_file_to_string(&record_file_path)?;
+        let (recorded_crate_version, recorded_revision) = content
+            .trim()
+            .rsplit_once('.')
+            .and_then(|(crate_version, revision)| {
+                Some((crate_version, revision.parse::<u32>().ok()?))
+            })
+            .filter(|(crate_version, _)| {
+                crate_version.split('.').count() == 3
+                    && crate_version
+                        .split('.')
+                        .all(|part| part.parse::<u32>().is_ok())
+            })
+            .ok_or_else(|| {
+                BuildActionError::InvalidDriverVerRecord(record_file_path.clone(), content.clone())
+            })?;
+        debug!(
+            "DriverVer version recorded in {}: {recorded_crate_version}.{recorded_revision}",
+            record_file_path.display()
+        );
+        if recorded_crate_version != crate_version {
+            1
+        } else if recorded_revision >= MAX_DRIVER_VER_PART {
+            return Err(BuildActionError::DriverVerRevisionExhausted(
+                record_file_path,
+            ));
+        } else {
+            recorded_revision + 1
+        }
+    } else {
+        1
+    };
+    Ok(format!("{crate_version}.{revision}"))
+}
+
+/// Records the `DriverVer` version stamped into the package in its record file
+/// in the target directory
+///
+/// # Errors
+/// * `BuildActionError::FileIo` - If there is an IO error writing the record.
+pub fn record_driver_ver_version(
+    fs: &Fs,
+    target_dir: &Path,
+    package_name: &str,
+    version: &str,
+) -> Result<(), BuildActionError> {
+    let record_file_path = driver_ver_record_file_path(target_dir, package_name);
+    info!(
+        "Recording DriverVer version {version} in {}",
+        record_file_path.display()
+    );
+    fs.write_to_file(&record_file_path, version.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn next_version_with_record(record: Option<&'static str>) -> Result<String, BuildActionError> {
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let record_file_path = target_dir.join("driver.driver-ver");
+        let mut fs = Fs::default();
+        let expected_record_file_path = record_file_path.clone();
+        fs.expect_exists()
+            .withf(move |path: &Path| path == expected_record_file_path)
+            .return_const(record.is_some());
+        if let Some(record) = record {
+            fs.expect_read_file_to_string()
+                .withf(move |path: &Path| path == record_file_path)
+                .returning(move |_| Ok(record.to_string()));
+        }
+        next_driver_ver_version(&fs, &target_dir, "driver", &Version::new(1, 2, 3))
+    }
+
+    #[test]
+    fn revision_is_incremented_for_the_recorded_crate_version() {
+        assert_eq!(next_version_with_record(None).unwrap(), "1.2.3.1");
+        assert_eq!(
+            next_version_with_record(Some("1.2.3.41\r\n")).unwrap(),
+            "1.2.3.42"
+        );
+        assert_eq!(
+            next_version_with_record(Some("1.2.2.7")).unwrap(),
+            "1.2.3.1"
+        );
+    }
+
+    #[test]
+    fn invalid_or_exhausted_records_are_rejected() {
+        assert!(matches!(
+            next_version_with_record(Some("1.2.3")),
+            Err(BuildActionError::InvalidDriverVerRecord(..))
+        ));
+        assert!(matches!(
+            next_version_with_record(Some("1.2.3.65535")),
+            Err(BuildActionError::DriverVerRevisionExhausted(..))
+        ));
+    }
+}
@@ -0,0 +1,114 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module contains the four part version of the `DriverVer` directive of
+//! an INF, e.g. `1.2.3.4` in `DriverVer = 10/16/2026,1.2.3.4`. It is used by
+//! the `--min-version` option of the build action, which fails the build of
+//! packages whose stamped version is not greater than the last released
+//! version, since Windows does not upgrade a driver to a package with a lower
+//! or equal version.
+
+use std::{fmt, str::FromStr};
+
+/// `MAJOR.MINOR.BUILD.REVISION` version of the `DriverVer` of an INF. Versions
+/// are ordered by their parts from left to right, as Windows ranks drivers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DriverVerVersion {
+    pub major: u16,
+    pub minor: u16,
+    pub build: u16,
+    pub revision: u16,
+}
+
+impl FromStr for DriverVerVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s
+            .split('.')
+            .map(|part| {
+                part.parse::<u16>()
+                    .ok()
+                    .filter(|_| part.chars().all(|c| c.is_ascii_digit()))
+            })
+            .collect::<Option<Vec<_>>>();
+        match parts.as_deref() {
+            Some(&[major, minor, build, revision]) => Ok(Self {
+                major,
+                minor,
+                build,
+                revision,
+            }),
+            _ => Err(format!(
+                "'{s}' is not a valid DriverVer version, expected four parts from 0 to 65535, e.g. \
+                 '1.2.3.4'"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DriverVerVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}.{}",
+            self.major, self.minor, self.build, self.revision
+        )
+    }
+}
+
+impl DriverVerVersion {
+    /// Returns the version of the values of a `DriverVer` directive, i.e. of
+    /// its second value after the date. `None` if the directive has no valid
+    /// version.
+    #[must_use]
+    pub fn from_driver_ver(driver_ver: &[String]) -> Option<Self> {
+        driver_ver.get(1)?.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DriverVerVersion;
+
+    #[test]
+    fn versions_are_parsed_from_four_parts() {
+        assert_eq!(
+            "1.2.3.65535".parse(),
+            Ok(DriverVerVersion {
+                major: 1,
+                minor: 2,
+                build: 3,
+                revision: 65535
+            })
+        );
+        for version in [
+            "",
+            "1.2.3",
+            "1.2.3.4.5",
+            "1.2.3.65536",
+            "1.2.-3.4",
+            "1.2.+3.4",
+            "a.b.c.d",
+        ] {
+            assert!(version.parse::<DriverVerVersion>().is_err(), "{version}");
+        }
+        assert_eq!(
+            DriverVerVersion::from_driver_ver(&["10/16/2026".to_string(), "1.0.0.2".to_string()])
+                .map(|version| version.to_string()),
+            Some("1.0.0.2".to_string())
+        );
+        assert_eq!(
+            DriverVerVersion::from_driver_ver(&["10/16/2026".to_string()]),
+            None
+        );
+    }
+
+    #[test]
+    fn versions_are_ordered_by_their_parts_from_left_to_right() {
+        let version = |s: &str| s.parse::<DriverVerVersion>().expect("version should be valid");
+        assert!(version("1.2.3.4") == version("1.2.3.4"));
+        assert!(version("1.2.3.4") < version("1.2.3.10"));
+        assert!(version("1.10.0.0") > version("1.9.65535.65535"));
+        assert!(version("2.0.0.0") > version("1.65535.65535.65535"));
+    }
+}
@@ -5,8 +5,20 @@
 use std::{io, path::PathBuf, string::FromUtf8Error};
 
 use thiserror::Error;
+use wdk_build::CpuArchitecture;
 
-use crate::providers::error::{CommandError, FileError};
+use crate::{
+    actions::{
+        DriverType,
+        build::{
+            Sanitizer,
+            driver_ver_version::DriverVerVersion,
+            manifest::ManifestError,
+            signing_pfx::Base64Error,
+        },
+    },
+    providers::error::{CommandError, FileError},
+};
 
 /// Errors for the build action layer
 #[derive(Error, Debug)]
@@ -29,8 +41,8 @@ pub enum BuildActionError {
     NotAWorkspaceMember(PathBuf),
     #[error(transparent)]
     PackageTask(#[from] PackageTaskError),
-    #[error("No valid rust projects in the current working directory: {0}")]
-    NoValidRustProjectsInTheDirectory(PathBuf),
+    #[error("No Rust packages found at {0}")]
+    NoRustPackagesFound(PathBuf),
     #[error("One or more packages failed to build in the emulated workspace: {0}")]
     OneOrMoreRustProjectsFailedToBuild(PathBuf),
     #[error("One or more workspace members failed to build in the workspace: {0}")]
@@ -39,8 +51,139 @@ pub enum BuildActionError {
     UnsupportedArchitecture(String),
     #[error("Failed to detect target arch")]
     CannotDetectTargetArch,
+    #[error(
+        "`--verify-signature` cannot be used for package {package_name}, since signing is turned \
+         off by {disabled_by} and there would be no signature to verify. Enable signing with \
+         `--sign-mode=test` or leave out `--verify-signature`"
+    )]
+    VerifySignatureWithoutSigning {
+        package_name: String,
+        disabled_by: String,
+    },
+    #[error("Environment variable {0} with the base64 encoded signing certificate is not set")]
+    MissingCertEnvVar(String),
+    #[error("Environment variable {0} does not contain a base64 encoded PFX certificate")]
+    InvalidCertEnvVar(String, #[source] Base64Error),
+    #[error("Environment variable {0} with the password of the signing certificate is not set")]
+    MissingCertPasswordEnvVar(String),
     #[error("Could not determine target directory for packaging. Cause: {0}")]
     CannotDetermineTargetDir(String),
+    #[error("Invalid [package.metadata.cargo-wdk] in Cargo.toml: {0}")]
+    InvalidCargoWdkMetadata(String),
+    #[error(
+        "WDK changed since it was recorded in {lock_file_path}: recorded build \
+         {recorded_build_number}, detected build {detected_build_number}. Run with \
+         --update-wdk-lock to record the detected WDK"
+    )]
+    WdkChangedSinceLock {
+        lock_file_path: PathBuf,
+        recorded_build_number: u32,
+        detected_build_number: u32,
+    },
+    #[error("Invalid WDK lock file {0}, expected a WDK build number, found: {1:?}")]
+    InvalidWdkLock(PathBuf, String),
+    #[error("Invalid DriverVer record {0}, expected a four part version, found: {1:?}")]
+    InvalidDriverVerRecord(PathBuf, String),
+    #[error(
+        "DriverVer revision recorded in {0} cannot be incremented further. Bump the crate version \
+         or remove the record to restart the revision at 1"
+    )]
+    DriverVerRevisionExhausted(PathBuf),
+    #[error(
+        "Package {0} sets the `checked` packaging setting, but a checked variant cannot be built \
+         for a multi-architecture package"
+    )]
+    CheckedVariantOfMultiArchPackage(String),
+    #[error("Package {package_name} has no cdylib target {target_name} set in `driver-target`")]
+    DriverTargetNotFound {
+        package_name: String,
+        target_name: String,
+    },
+    #[error(
+        "Package {0} sets `out-dir-files` but cargo did not report an OUT_DIR for it. Files can \
+         only be taken from the OUT_DIR of a build script that ran in this build"
+    )]
+    MissingOutDir(String),
+    #[error("Pre-built driver binary {0} not found")]
+    PrebuiltBinaryNotFound(PathBuf),
+    #[error("Pre-built driver binary {0} must be a .sys file")]
+    UnsupportedPrebuiltBinary(PathBuf),
+    #[error("--{0}-path {1} does not exist")]
+    ToolPathNotFound(&'static str, PathBuf),
+    #[error("--{0}-path {1} must be an .exe file")]
+    ToolPathNotExecutable(&'static str, PathBuf),
+    #[error("Temp directory {0} is not writable")]
+    TempDirNotWritable(PathBuf, #[source] FileError),
+    #[error("Failed to connect to event pipe {0}")]
+    EventPipeConnect(String, #[source] FileError),
+    #[error(
+        "Device ID {device_id} is claimed by the INFs of both {first_package_name} and \
+         {second_package_name}"
+    )]
+    DuplicateDeviceId {
+        device_id: String,
+        first_package_name: String,
+        second_package_name: String,
+    },
+    #[error(
+        "{declared_in} declares more than one driver type in the `driver-model` table of its WDK \
+         metadata: {keys}. Keep only the keys of the driver type of the driver"
+    )]
+    ConflictingDriverTypes { declared_in: String, keys: String },
+    #[error(
+        "--sanitizer={sanitizer} cannot be applied to the {driver_type} driver package \
+         {package_name}. Kernel-mode drivers run without the user-mode runtime of the sanitizer. \
+         Move the logic to test with the sanitizer to a library crate that is built for the host, \
+         or build a user-mode driver"
+    )]
+    SanitizerForKernelModeDriver {
+        package_name: String,
+        driver_type: DriverType,
+        sanitizer: Sanitizer,
+    },
+    #[error(
+        "Package {package_name} sets `manifest`, but manifests can only be embedded into the \
+         binaries of UMDF drivers and it is a {driver_type} driver"
+    )]
+    ManifestForKernelModeDriver {
+        package_name: String,
+        driver_type: DriverType,
+    },
+    #[error("--sanitizer={0} is only supported for amd64, but the packages are built for {1}")]
+    SanitizerUnsupportedArch(Sanitizer, CpuArchitecture),
+    #[error(
+        "Driver type of package {package_name} is {found} in its WDK metadata, but {expected} was \
+         passed in --driver-type"
+    )]
+    DriverTypeMismatch {
+        package_name: String,
+        expected: DriverType,
+        found: DriverType,
+    },
+    #[error(
+        "Dependencies of the {driver_type} driver package {package_name} on the wdk crates are \
+         misconfigured. Fix them in its Cargo.toml:\n{fixes}"
+    )]
+    IncorrectWdkDependencies {
+        package_name: String,
+        driver_type: DriverType,
+        fixes: String,
+    },
+    #[error(
+        "Detected WDK build {detected_build_number} is not supported by package {package_name}, \
+         which supports WDK {supported_wdk}. Pass --allow-unsupported-wdk to build anyway"
+    )]
+    UnsupportedWdk {
+        package_name: String,
+        detected_build_number: u32,
+        supported_wdk: String,
+    },
+    #[error("{framework} {version} is not available in the WDK, available versions: {available}")]
+    UnavailableWdfVersion {
+        framework: &'static str,
+        version: String,
+        available: String,
+    },
 }
 
 /// Errors for the low level build task layer
@@ -50,6 +193,10 @@ pub enum BuildTaskError {
     EmptyManifestPath,
     #[error("Error running cargo build command")]
     CargoBuild(#[source] CommandError),
+    #[error("Error running cargo clippy command")]
+    CargoClippy(#[source] CommandError),
+    #[error("Error running the pre-build command of package {0}")]
+    PreBuildCommand(String, #[source] CommandError),
     #[error(transparent)]
     FileIo(#[from] FileError),
 }
@@ -66,6 +213,11 @@ pub enum PackageTaskError {
     StampinfCommand(#[source] CommandError),
     #[error("Error running inf2cat command")]
     Inf2CatCommand(#[source] CommandError),
+    #[error(
+        "inf2cat did not generate catalog file {0} for INF file {1}, please ensure the INF lists \
+         the driver files in its [SourceDisksFiles] section"
+    )]
+    CatFileNotGenerated(PathBuf, PathBuf),
     #[error("Creating cert file from store using certmgr")]
     CreateCertFileFromStoreCommand(#[source] CommandError),
     #[error("Checking for existence of cert in store using certmgr")]
@@ -76,12 +228,166 @@ pub enum PackageTaskError {
     CertGenerationInStoreCommand(#[source] CommandError),
     #[error("Error while acquiring mutex for generating certificate. HRESULT: {0:#x}")]
     CertMutexError(i32),
+    #[error("Error deleting expired test certificate from store using certmgr")]
+    DeleteCertFromStoreCommand(#[source] CommandError),
+    #[error("Error parsing the validity period of test certificate {0} from the certutil output")]
+    TestCertValidityInvalidCommandOutput(PathBuf),
+    #[error("Test certificate {0} is still expired after regenerating it")]
+    ExpiredTestCert(PathBuf),
     #[error("Error signing driver binary using signtool")]
     DriverBinarySignCommand(#[source] CommandError),
     #[error("Error verifying signed driver binary using signtool")]
     DriverBinarySignVerificationCommand(#[source] CommandError),
+    #[error(
+        "Catalog file {1} does not match the signed driver binary {0}. The catalog is stale, e.g. \
+         it was generated before the final driver binary was copied to the package"
+    )]
+    StaleCatalog(PathBuf, PathBuf, #[source] CommandError),
+    #[error("Error reading the signing certificate {0} using certutil")]
+    CertutilDumpCommand(String, #[source] CommandError),
+    #[error("Error reading stdout while reading the signing certificate using certutil")]
+    CertutilDumpInvalidCommandOutput(#[source] FromUtf8Error),
+    #[error("Error reading the signature of catalog file {0} using signtool")]
+    CatalogSigningReportCommand(PathBuf, #[source] CommandError),
+    #[error("Error parsing the signer details of catalog file {0} from the signtool output")]
+    CatalogSigningReportInvalidCommandOutput(PathBuf),
+    #[error(
+        "Signing certificate {0} lacks the Code Signing enhanced key usage (1.3.6.1.5.5.7.3.3) \
+         required to install drivers, found: {1}"
+    )]
+    MissingCodeSigningEku(String, String),
     #[error("Error verifying inf file using infverif")]
     InfVerificationCommand(#[source] CommandError),
+    #[error("Error listing driver binary imports using dumpbin")]
+    DumpbinImportsCommand(#[source] CommandError),
+    #[error("Error reading stdout while listing driver binary imports using dumpbin")]
+    DumpbinImportsInvalidCommandOutput(#[source] FromUtf8Error),
+    #[error("Error reading driver binary headers using dumpbin")]
+    DumpbinHeadersCommand(#[source] CommandError),
+    #[error("Error reading stdout while reading driver binary headers using dumpbin")]
+    DumpbinHeadersInvalidCommandOutput(#[source] FromUtf8Error),
+    #[error("Driver binary {0} is not a valid PE image")]
+    InvalidDriverBinary(PathBuf),
+    #[error(
+        "Driver binary {path} was built for {found} instead of the target architecture \
+         {expected}, please check the target and linker configuration"
+    )]
+    DriverBinaryArchMismatch {
+        path: PathBuf,
+        expected: CpuArchitecture,
+        found: String,
+    },
+    #[error(
+        "UMDF driver binary {0} does not export the entry points the UMDF host requires: {1}. \
+         Check that it is linked with WdfDriverStubUm.lib, which wdk-build links for UMDF 2 \
+         drivers"
+    )]
+    MissingUmdfExports(PathBuf, String),
+    #[error(
+        "Driver binary {0} has no CodeView debug information to archive its PDB by. Check that \
+         it is linked with /DEBUG"
+    )]
+    MissingPdbDebugId(PathBuf),
+    #[error("INF file {0} is not decorated for a multi-architecture package, missing: {1}")]
+    InfNotArchDecorated(PathBuf, String),
+    #[error(
+        "INF file {path} has no models section for {arch}, which the catalog is generated for \
+         with inf2cat /os:{os}. Decorate the [Manufacturer] section with NT{arch} or NT$ARCH$"
+    )]
+    InfNotDecoratedForCatalogOs {
+        path: PathBuf,
+        arch: CpuArchitecture,
+        os: String,
+    },
+    #[error(
+        "INF file {0} is packaged as a sample but does not use the Sample setup class, found: {1}"
+    )]
+    InfNotSampleClass(PathBuf, String),
+    #[error(
+        "INF file {inf_file_path} uses the provider {provider}, which is not one of the allowed \
+         providers of the package: {allowed_providers}"
+    )]
+    InfProviderNotAllowed {
+        inf_file_path: PathBuf,
+        provider: String,
+        allowed_providers: String,
+    },
+    #[error(
+        "Driver binary imports functions that are unavailable on Windows build \
+         {minimum_build_number}, the oldest build the driver targets, although WDK build \
+         {wdk_build_number} provides them: {imports}. Raise the minimum KMDF or UMDF version or \
+         the OS version decorations of the INF"
+    )]
+    DisallowedImports {
+        minimum_build_number: u32,
+        wdk_build_number: u32,
+        imports: String,
+    },
+    #[error(
+        "Driver binary imports functions that are unavailable to Universal drivers: {0}. Use the \
+         OneCore API sets or pass --target-platform desktop"
+    )]
+    NonUniversalImports(String),
+    #[error(
+        "INF file {0} uses co-installers, which universal INFs cannot use: {1}. Remove them or \
+         set `inf-format = \"legacy\"` in the cargo-wdk metadata"
+    )]
+    InfNotUniversal(PathBuf, String),
+    #[error(
+        "INF file {0} is a legacy INF, which cannot be packaged for --target-platform \
+         windows-driver"
+    )]
+    LegacyInfForWindowsDriver(PathBuf),
+    #[error("INF file {0} violates the rules of universal INFs:\n{1}")]
+    UniversalInfViolations(PathBuf, String),
+    #[error(
+        "WDF verifier is configured in Cargo.toml but INF file {0} has no `HKR, Parameters\\Wdf, \
+         VerifierOn, ...` registry entry"
+    )]
+    WdfVerifierRegistryValueNotFound(PathBuf),
+    #[error("Debug files configured in Cargo.toml are missing: {0}")]
+    MissingDebugFiles(String),
+    #[error("Files configured in Cargo.toml are missing from the OUT_DIR of the build script: {0}")]
+    MissingOutDirFiles(String),
+    #[error("INF file {0} has no DriverVer version to compare against --min-version")]
+    MissingDriverVerVersion(PathBuf),
+    #[error(
+        "DriverVer version {version} of INF file {inf_file_path} is not greater than the last \
+         released version {min_version} passed in --min-version. Windows does not upgrade a \
+         driver to a package with a lower or equal version"
+    )]
+    DriverVerVersionNotGreater {
+        inf_file_path: PathBuf,
+        version: DriverVerVersion,
+        min_version: DriverVerVersion,
+    },
+    #[error("Manifest file {0} configured in Cargo.toml is missing")]
+    MissingManifestFile(PathBuf),
+    #[error("Manifest file {0} is not a well-formed application manifest, {1}")]
+    InvalidManifest(PathBuf, ManifestError),
+    #[error("Error embedding manifest {0} into the driver binary using mt")]
+    EmbedManifestCommand(PathBuf, #[source] CommandError),
+    #[error("Files listed by INF file {0} are missing from the package root: {1}")]
+    MissingInfReferencedFiles(PathBuf, String),
+    #[error(
+        "Catalog exclude pattern `{0}` matches {1}, which must be covered by the catalog of the \
+         package"
+    )]
+    CatalogExcludesRequiredFile(String, PathBuf),
+    #[error(
+        "INF file {0} of the relocatable package contains absolute paths of the build machine: {1}"
+    )]
+    AbsolutePathsInInf(PathBuf, String),
+    #[error(
+        "Catalog file {0} of the relocatable package contains absolute paths of the build \
+         machine: {1}"
+    )]
+    AbsolutePathsInCatalog(PathBuf, String),
+    #[error(
+        "Package folder {0} contains files that are not part of the package: {1}. Run with \
+         --overwrite-package to clear it"
+    )]
+    UnexpectedFilesInPackageFolder(PathBuf, String),
 
     // TODO: We can make this specific error instead of generic one
     #[error(transparent)]
@@ -0,0 +1,274 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module streams the events of a build to the named pipe of the
+//! `--event-pipe` option of the build action, e.g. for an IDE to show the
+//! progress of a long build without parsing its output. Each event is written
+//! as a line of JSON as soon as it happens, in addition to the usual output of
+//! the build.
+
+use std::{cell::RefCell, fmt, io::Write, path::PathBuf};
+
+use mockall_double::double;
+use serde_json::{Value, json};
+use tracing::{debug, warn};
+
+use crate::providers::error::FileError;
+#[double]
+use crate::providers::fs::Fs;
+
+/// Prefix of the paths of named pipes on the local machine
+const PIPE_PATH_PREFIX: &str = r"\\.\pipe\";
+
+/// Phase of the build of a package
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildPhase {
+    /// Compiling the package with `cargo build`
+    Build,
+    /// Creating the driver package from the compiled binary
+    Package,
+}
+
+impl fmt::Display for BuildPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Build => write!(f, "build"),
+            Self::Package => write!(f, "package"),
+        }
+    }
+}
+
+/// Event of a build. Failed steps carry the message of their error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildEvent<'a> {
+    BuildStarted {
+        working_dir: &'a str,
+    },
+    PackageStarted {
+        package: &'a str,
+    },
+    PhaseStarted {
+        package: &'a str,
+        phase: BuildPhase,
+    },
+    PhaseFinished {
+        package: &'a str,
+        phase: BuildPhase,
+        error: Option<String>,
+    },
+    PackageFinished {
+        package: &'a str,
+        error: Option<String>,
+    },
+    BuildFinished {
+        error: Option<String>,
+        artifacts: Vec<PathBuf>,
+    },
+}
+
+impl BuildEvent<'_> {
+    /// Returns the event as a JSON object, whose `event` field names the kind
+    /// of the event
+    fn to_json(&self) -> Value {
+        match self {
+            Self::BuildStarted { working_dir } => json!({
+                "event": "build-started",
+                "working-dir": working_dir,
+            }),
+            Self::PackageStarted { package } => json!({
+                "event": "package-started",
+                "package": package,
+            }),
+            Self::PhaseStarted { package, phase } => json!({
+                "event": "phase-started",
+                "package": package,
+                "phase": phase.to_string(),
+            }),
+            Self::PhaseFinished {
+                package,
+                phase,
+                error,
+            } => json!({
+                "event": "phase-finished",
+                "package": package,
+                "phase": phase.to_string(),
+                "succeeded": error.is_none(),
+                "error": error,
+            }),
+            Self::PackageFinished { package, error } => json!({
+                "event": "package-finished",
+                "package": package,
+                "succeeded": error.is_none(),
+                "error": error,
+            }),
+            Self::BuildFinished { error, artifacts } => json!({
+                "event": "build-finished",
+                "succeeded": error.is_none(),
+                "error": error,
+                "artifacts": artifacts,
+            }),
+        }
+    }
+}
+
+/// Named pipe the events of a build are written to
+pub struct EventPipe {
+    name: String,
+    // `None` once writing to the pipe failed, e.g. because its reader went
+    // away, after which events are dropped
+    writer: RefCell<Option<Box<dyn Write>>>,
+}
+
+impl EventPipe {
+    /// Connects to the named pipe with the given name, e.g. `my-ide` for
+    /// `\\.\pipe\my-ide`, or with the given full pipe path. The pipe must
+    /// have been created by its reader.
+    ///
+    /// # Errors
+    /// * `FileError` - If the pipe cannot be opened for writing.
+    pub fn connect(fs: &Fs, name: &str) -> Result<Self, FileError> {
+        let path = if name.starts_with(PIPE_PATH_PREFIX) {
+            PathBuf::from(name)
+        } else {
+            PathBuf::from(format!("{PIPE_PATH_PREFIX}{name}"))
+        };
+        let pipe = fs.open_for_writing(&path)?;
+        debug!("Connected to event pipe {}", path.display());
+        Ok(Self::new(name, Box::new(pipe)))
+    }
+
+    fn new(name: &str, writer: Box<dyn Write>) -> Self {
+        Self {
+            name: name.to_string(),
+            writer: RefCell::new(Some(writer)),
+        }
+    }
+
+    /// Writes the event to the pipe as a line of JSON. A failure to write is
+    /// warned about once and drops the later events instead of failing the
+    /// build, since the events are only informational.
+    pub fn send(&self, event: &BuildEvent) {
+        let mut writer = self.writer.borrow_mut();
+        let Some(pipe) = writer.as_mut() else {
+            return;
+        };
+        let mut line = event.to_json().to_string();
+        line.push('\n');
+        if let Err(e) = pipe.write_all(line.as_bytes()).and_then(|()| pipe.flush()) {
+            warn!(
+                "Failed to write to event pipe {}, no further events are sent: {e}",
+                self.name
+            );
+            *writer = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{self, Write},
+        path::PathBuf,
+        sync::{Arc, Mutex},
+    };
+
+    use super::{BuildEvent, BuildPhase, EventPipe};
+
+    /// Writer collecting the written bytes, failing after `capacity` writes
+    struct SharedWriter {
+        written: Arc<Mutex<Vec<u8>>>,
+        capacity: usize,
+    }
+
+    impl Write for SharedWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.capacity == 0 {
+                return Err(io::Error::from(io::ErrorKind::BrokenPipe));
+            }
+            self.capacity -= 1;
+            self.written
+                .lock()
+                .expect("lock should not be poisoned")
+                .extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn event_pipe(capacity: usize) -> (EventPipe, Arc<Mutex<Vec<u8>>>) {
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let writer = SharedWriter {
+            written: Arc::clone(&written),
+            capacity,
+        };
+        (EventPipe::new("my-ide", Box::new(writer)), written)
+    }
+
+    fn written_lines(written: &Mutex<Vec<u8>>) -> Vec<serde_json::Value> {
+        String::from_utf8_lossy(&written.lock().expect("lock should not be poisoned"))
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("event should be JSON"))
+            .collect()
+    }
+
+    #[test]
+    fn events_are_written_as_lines_of_json() {
+        let (pipe, written) = event_pipe(usize::MAX);
+        pipe.send(&BuildEvent::PhaseStarted {
+            package: "driver",
+            phase: BuildPhase::Build,
+        });
+        pipe.send(&BuildEvent::PhaseFinished {
+            package: "driver",
+            phase: BuildPhase::Package,
+            error: Some("signtool failed".to_string()),
+        });
+        pipe.send(&BuildEvent::BuildFinished {
+            error: None,
+            artifacts: vec![PathBuf::from("driver_package/driver.sys")],
+        });
+
+        assert_eq!(
+            written_lines(&written),
+            [
+                serde_json::json!({
+                    "event": "phase-started",
+                    "package": "driver",
+                    "phase": "build",
+                }),
+                serde_json::json!({
+                    "event": "phase-finished",
+                    "package": "driver",
+                    "phase": "package",
+                    "succeeded": false,
+                    "error": "signtool failed",
+                }),
+                serde_json::json!({
+                    "event": "build-finished",
+                    "succeeded": true,
+                    "error": null,
+                    "artifacts": ["driver_package/driver.sys"],
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn events_are_dropped_once_writing_to_the_pipe_failed() {
+        let (pipe, written) = event_pipe(1);
+        for package in ["first", "second", "third"] {
+            pipe.send(&BuildEvent::PackageStarted { package });
+        }
+
+        assert_eq!(
+            written_lines(&written),
+            [serde_json::json!({
+                "event": "package-started",
+                "package": "first",
+            })]
+        );
+        assert!(pipe.writer.borrow().is_none());
+    }
+}
@@ -0,0 +1,351 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module contains the helpers used by the `--import-check` option of the
+//! build action. It parses the import table of a driver binary, as printed by
+//! `dumpbin /imports`, and compares it against the set of functions that are
+//! available on the oldest Windows build the driver targets and, for Universal
+//! user mode drivers, against the modules of the OneCore API surface.
+
+use wdk_build::DriverConfig;
+
+/// Functions exported by the Windows kernel along with the first Windows build
+/// that exports them. The builds are taken from the Requirements section of
+/// the reference page of each function on Microsoft Learn, where Windows 7 is
+/// build 7600, Windows 8 build 9200, Windows 8.1 build 9600 and Windows 10,
+/// version 1803 build 17134, for example. Any function that is not listed here
+/// is considered available on every build a driver can target.
+///
+/// The list is intentionally conservative and only contains functions that
+/// are commonly reached for by drivers but are absent from older releases.
+const IMPORT_MINIMUM_BUILD_NUMBERS: &[(&str, u32)] = &[
+    ("KeGetProcessorNumberFromIndex", 7600),
+    ("KeQueryActiveProcessorCountEx", 7600),
+    ("KeSetCoalescableTimer", 7600),
+    ("IoReportInterruptActive", 9200),
+    ("IoReportInterruptInactive", 9200),
+    ("PoFxRegisterDevice", 9200),
+    ("PoFxStartDevicePowerManagement", 9200),
+    ("PoFxUnregisterDevice", 9200),
+    ("ExAllocateTimer", 9600),
+    ("ExCancelTimer", 9600),
+    ("ExDeleteTimer", 9600),
+    ("ExSetTimer", 9600),
+    ("PsSetCreateThreadNotifyRoutineEx", 10240),
+    ("PsSetCreateProcessNotifyRoutineEx2", 15063),
+    ("IoGetDeviceDirectory", 17134),
+    ("IoGetDriverDirectory", 17134),
+    ("ExAllocatePool2", 19041),
+    ("ExAllocatePool3", 19041),
+    ("ExFreePool2", 19041),
+];
+
+/// KMDF minor versions along with the first Windows build that ships them, as
+/// listed by the KMDF version history on Microsoft Learn. A driver fails to
+/// load on builds older than the one shipping the framework version it
+/// requires.
+const KMDF_MINOR_VERSION_BUILD_NUMBERS: &[(u8, u32)] = &[
+    (9, 7600),
+    (11, 9200),
+    (13, 9600),
+    (15, 10240),
+    (17, 10586),
+    (19, 14393),
+    (21, 15063),
+    (23, 16299),
+    (25, 17134),
+    (27, 17763),
+    (29, 18362),
+    (31, 19041),
+    (33, 20348),
+];
+
+/// UMDF 2 minor versions along with the first Windows build that ships them,
+/// as listed by the UMDF version history on Microsoft Learn
+const UMDF_MINOR_VERSION_BUILD_NUMBERS: &[(u8, u32)] = &[
+    (0, 9600),
+    (15, 10240),
+    (17, 10586),
+    (19, 14393),
+    (21, 15063),
+    (23, 16299),
+    (25, 17134),
+    (27, 17763),
+    (29, 18362),
+    (31, 19041),
+    (33, 20348),
+];
+
+/// Build of the first Windows 10 release, assumed as the oldest build targeted
+/// by drivers that declare neither a framework version nor INF OS versions
+const DEFAULT_MINIMUM_BUILD_NUMBER: u32 = 10240;
+
+/// Prefix of the API set contracts that make up the OneCore API surface,
+/// e.g. `api-ms-win-core-synch-l1-2-0.dll`
+const API_SET_MODULE_PREFIX: &str = "api-ms-win-";
+
+/// Modules besides the API sets that Universal user mode drivers may import
+/// from. `OneCoreUAP.lib`, which user mode drivers are linked against, resolves
+/// every other Win32 function to an API set, so imports from e.g.
+/// `kernel32.dll` or `user32.dll` come from desktop-only import libraries.
+const UNIVERSAL_USER_MODE_MODULES: &[&str] = &["ntdll.dll", "WUDFx02000.dll"];
+
+/// A single function imported by a driver binary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Import {
+    /// Name of the module the function is imported from, e.g. `ntoskrnl.exe`
+    pub module: String,
+    /// Name of the imported function
+    pub function: String,
+}
+
+/// A function imported by a driver binary that is not available on the oldest
+/// Windows build the driver targets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisallowedImport {
+    pub import: Import,
+    /// First Windows build that exports the function
+    pub minimum_build_number: u32,
+}
+
+/// Parses the output of `dumpbin /imports` into the list of imported
+/// functions.
+///
+/// Module names are printed on their own line, followed by the import address
+/// table summary and one `<hint> <name>` line per imported function. Imports
+/// by ordinal are skipped since they carry no function name.
+#[must_use]
+pub fn parse_dumpbin_imports(output: &str) -> Vec<Import> {
+    let mut imports = Vec::new();
+    let mut current_module: Option<&str> = None;
+    for line in output.lines() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            [module] if is_module_name(module) => current_module = Some(*module),
+            // The section summary follows the import tables
+            ["Summary"] => current_module = None,
+            [hint, function]
+                if u32::from_str_radix(hint, 16).is_ok() && !is_module_name(function) =>
+            {
+                if let Some(module) = current_module {
+                    imports.push(Import {
+                        module: module.to_string(),
+                        function: (*function).to_string(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    imports
+}
+
+/// Returns the oldest Windows build a driver targets, i.e. the newer of the
+/// build shipping the framework version the driver requires and the oldest
+/// build targeted by its INF, as returned by
+/// `os_version::inf_minimum_build_number`. Drivers that declare neither are
+/// assumed to target the first Windows 10 release.
+#[must_use]
+pub fn minimum_build_number(driver_model: &DriverConfig, inf_build_number: Option<u32>) -> u32 {
+    framework_minimum_build_number(driver_model)
+        .into_iter()
+        .chain(inf_build_number)
+        .max()
+        .unwrap_or(DEFAULT_MINIMUM_BUILD_NUMBER)
+}
+
+/// Returns the imports that are not available on the given Windows build
+#[must_use]
+pub fn find_disallowed_imports(
+    imports: Vec<Import>,
+    minimum_build_number: u32,
+) -> Vec<DisallowedImport> {
+    imports
+        .into_iter()
+        .filter_map(|import| {
+            IMPORT_MINIMUM_BUILD_NUMBERS
+                .iter()
+                .find(|(function, _)| *function == import.function)
+                .filter(|(_, import_build_number)| *import_build_number > minimum_build_number)
+                .map(|(_, import_build_number)| DisallowedImport {
+                    import,
+                    minimum_build_number: *import_build_number,
+                })
+        })
+        .collect()
+}
+
+/// Returns the imports of a user mode driver that are not part of the OneCore
+/// API surface that Universal drivers are restricted to, i.e. the functions
+/// imported from modules other than the API sets, `ntdll.dll` and the UMDF
+/// framework
+#[must_use]
+pub fn find_non_universal_imports(imports: Vec<Import>) -> Vec<Import> {
+    imports
+        .into_iter()
+        .filter(|import| {
+            let module = import.module.to_ascii_lowercase();
+            !module.starts_with(API_SET_MODULE_PREFIX)
+                && !UNIVERSAL_USER_MODE_MODULES
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(&module))
+        })
+        .collect()
+}
+
+/// Returns the first Windows build that ships the framework version a driver
+/// requires, i.e. its minimum KMDF or UMDF version if set and its target
+/// version otherwise. WDM drivers do not require a framework.
+fn framework_minimum_build_number(driver_model: &DriverConfig) -> Option<u32> {
+    let (minor_version, build_numbers) = match driver_model {
+        DriverConfig::Wdm => return None,
+        DriverConfig::Kmdf(kmdf_config) => (
+            kmdf_config
+                .minimum_kmdf_version_minor
+                .unwrap_or(kmdf_config.target_kmdf_version_minor),
+            KMDF_MINOR_VERSION_BUILD_NUMBERS,
+        ),
+        DriverConfig::Umdf(umdf_config) => (
+            umdf_config
+                .minimum_umdf_version_minor
+                .unwrap_or(umdf_config.target_umdf_version_minor),
+            UMDF_MINOR_VERSION_BUILD_NUMBERS,
+        ),
+    };
+    Some(
+        build_numbers
+            .iter()
+            .rev()
+            .find(|(minor, _)| *minor <= minor_version)
+            .map_or(0, |(_, build_number)| *build_number),
+    )
+}
+
+fn is_module_name(token: &str) -> bool {
+    let token = token.to_ascii_lowercase();
+    [".exe", ".dll", ".sys"]
+        .iter()
+        .any(|extension| token.ends_with(extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use wdk_build::{KmdfConfig, UmdfConfig};
+
+    use super::*;
+
+    const DUMPBIN_IMPORTS_OUTPUT: &str = r"
+Dump of file C:\driver\target\debug\driver_package\driver.sys
+
+File Type: DLL
+
+  Section contains the following imports:
+
+    ntoskrnl.exe
+             140003000 Import Address Table
+             140005088 Import Name Table
+                     0 time date stamp
+                     0 Index of first forward reference
+
+                         5F8 ExAllocatePool2
+                         2B1 DbgPrint
+
+    WDFLDR.SYS
+             140003018 Import Address Table
+             1400050A0 Import Name Table
+                     0 time date stamp
+                     0 Index of first forward reference
+
+                          13 WdfVersionBind
+
+  Summary
+
+        1000 .data
+";
+
+    #[test]
+    fn parse_dumpbin_imports_returns_functions_grouped_by_module() {
+        let imports = parse_dumpbin_imports(DUMPBIN_IMPORTS_OUTPUT);
+        let imports: Vec<(&str, &str)> = imports
+            .iter()
+            .map(|i| (i.module.as_str(), i.function.as_str()))
+            .collect();
+        assert_eq!(
+            imports,
+            vec![
+                ("ntoskrnl.exe", "ExAllocatePool2"),
+                ("ntoskrnl.exe", "DbgPrint"),
+                ("WDFLDR.SYS", "WdfVersionBind"),
+            ]
+        );
+    }
+
+    #[test]
+    fn find_disallowed_imports_flags_functions_newer_than_minimum_build() {
+        let imports = parse_dumpbin_imports(DUMPBIN_IMPORTS_OUTPUT);
+        let disallowed = find_disallowed_imports(imports, 18362);
+        assert_eq!(disallowed.len(), 1);
+        assert_eq!(disallowed[0].import.function, "ExAllocatePool2");
+        assert_eq!(disallowed[0].minimum_build_number, 19041);
+    }
+
+    #[test]
+    fn find_disallowed_imports_allows_functions_available_on_minimum_build() {
+        let imports = parse_dumpbin_imports(DUMPBIN_IMPORTS_OUTPUT);
+        assert!(find_disallowed_imports(imports, 19041).is_empty());
+    }
+
+    #[test]
+    fn minimum_build_number_is_the_newer_of_the_framework_and_inf_builds() {
+        let kmdf = DriverConfig::Kmdf(KmdfConfig {
+            kmdf_version_major: 1,
+            target_kmdf_version_minor: 33,
+            minimum_kmdf_version_minor: Some(27),
+        });
+        assert_eq!(minimum_build_number(&kmdf, None), 17763);
+        assert_eq!(minimum_build_number(&kmdf, Some(10240)), 17763);
+        assert_eq!(minimum_build_number(&kmdf, Some(19041)), 19041);
+
+        let umdf = DriverConfig::Umdf(UmdfConfig {
+            umdf_version_major: 2,
+            target_umdf_version_minor: 31,
+            minimum_umdf_version_minor: None,
+        });
+        assert_eq!(minimum_build_number(&umdf, Some(16299)), 19041);
+
+        assert_eq!(minimum_build_number(&DriverConfig::Wdm, Some(9600)), 9600);
+        assert_eq!(minimum_build_number(&DriverConfig::Wdm, None), 10240);
+    }
+
+    #[test]
+    fn find_non_universal_imports_flags_functions_of_desktop_only_modules() {
+        let imports = parse_dumpbin_imports(
+            r"
+    api-ms-win-core-synch-l1-2-0.dll
+             180003000 Import Address Table
+                          2F WaitForSingleObject
+
+    ntdll.dll
+             180003010 Import Address Table
+                         1B3 RtlInitUnicodeString
+
+    WUDFx02000.dll
+             180003020 Import Address Table
+                           7 WudfVersionBind
+
+    USER32.dll
+             180003030 Import Address Table
+                         27E MessageBoxW
+
+  Summary
+",
+        );
+        let non_universal_imports = find_non_universal_imports(imports);
+        assert_eq!(
+            non_universal_imports,
+            [Import {
+                module: "USER32.dll".to_string(),
+                function: "MessageBoxW".to_string(),
+            }]
+        );
+    }
+}
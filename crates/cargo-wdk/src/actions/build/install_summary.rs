@@ -0,0 +1,387 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module summarizes what an INF installs, i.e. the files it copies, the
+//! registry values it adds and the services it creates, as markdown for the
+//! `--emit-install-summary` option of the build action. The summary is read
+//! from the `CopyFiles`, `AddReg` and `AddService` directives of every section
+//! of the INF, so that reviewers can see the footprint of a package without
+//! reading the INF. Like the INF parser, it does not expand `%strkey%` tokens.
+
+use crate::actions::inf::{InfEntry, InfFile, InfSection};
+
+const DESTINATION_DIRS_SECTION_NAME: &str = "DestinationDirs";
+const DEFAULT_DEST_DIR_KEY: &str = "DefaultDestDir";
+const COPY_FILES_KEY: &str = "CopyFiles";
+const ADD_REG_KEY: &str = "AddReg";
+const ADD_SERVICE_KEY: &str = "AddService";
+const SERVICE_BINARY_KEY: &str = "ServiceBinary";
+const START_TYPE_KEY: &str = "StartType";
+/// Directories of the well-known DIRIDs that drivers install files to
+const DIRIDS: [(&str, &str); 4] = [
+    ("10", r"%SystemRoot%"),
+    ("11", r"%SystemRoot%\System32"),
+    ("12", r"%SystemRoot%\System32\drivers"),
+    ("13", "Driver Store"),
+];
+/// Names of the `StartType` values of a service install section
+const START_TYPES: [&str; 5] = ["boot", "system", "auto", "demand", "disabled"];
+
+/// Files, registry values and services installed by an INF
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstallSummary {
+    pub files: Vec<InstalledFile>,
+    pub registry_values: Vec<RegistryValue>,
+    pub services: Vec<Service>,
+}
+
+/// A file copied by a `CopyFiles` directive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstalledFile {
+    pub name: String,
+    /// Directory the file is copied to, from the `[DestinationDirs]` section
+    pub destination: String,
+}
+
+/// A registry value added by an `AddReg` directive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryValue {
+    /// Registry root, e.g. `HKR` or `HKLM`
+    pub root: String,
+    pub key: String,
+    /// Name of the value. Empty for the default value of the key.
+    pub name: String,
+    pub data: String,
+}
+
+/// A service created by an `AddService` directive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Service {
+    pub name: String,
+    pub binary: String,
+    pub start_type: String,
+}
+
+impl InstallSummary {
+    /// Collects the files, registry values and services installed by the
+    /// directives of every section of `inf_file`. Entries listed by several
+    /// sections, e.g. by the install sections of different architectures, are
+    /// returned once.
+    #[must_use]
+    pub fn from_inf(inf_file: &InfFile) -> Self {
+        let mut summary = Self::default();
+        for entry in inf_file
+            .sections
+            .iter()
+            .flat_map(|section| section.entries.iter())
+        {
+            let Some(key) = entry.key.as_deref() else {
+                continue;
+            };
+            if key.eq_ignore_ascii_case(COPY_FILES_KEY) {
+                for file in copied_files(inf_file, entry) {
+                    push_unique(&mut summary.files, file);
+                }
+            } else if key.eq_ignore_ascii_case(ADD_REG_KEY) {
+                for value in referenced_sections(inf_file, entry)
+                    .flat_map(|section| section.entries.iter())
+                    .filter_map(RegistryValue::from_entry)
+                {
+                    push_unique(&mut summary.registry_values, value);
+                }
+            } else if key.eq_ignore_ascii_case(ADD_SERVICE_KEY)
+                && let Some(service) = Service::from_entry(inf_file, entry)
+            {
+                push_unique(&mut summary.services, service);
+            }
+        }
+        summary
+    }
+
+    /// Renders the summary as a markdown document titled with the file name of
+    /// the INF
+    #[must_use]
+    pub fn to_markdown(&self, inf_file_name: &str) -> String {
+        let mut markdown = format!("# Install summary of {inf_file_name}\n");
+        push_table(
+            &mut markdown,
+            "Files",
+            &["File", "Destination"],
+            self.files
+                .iter()
+                .map(|file| vec![file.name.as_str(), file.destination.as_str()]),
+        );
+        push_table(
+            &mut markdown,
+            "Registry",
+            &["Root", "Key", "Value", "Data"],
+            self.registry_values.iter().map(|value| {
+                vec![
+                    value.root.as_str(),
+                    value.key.as_str(),
+                    value.name.as_str(),
+                    value.data.as_str(),
+                ]
+            }),
+        );
+        push_table(
+            &mut markdown,
+            "Services",
+            &["Service", "Binary", "Start type"],
+            self.services.iter().map(|service| {
+                vec![
+                    service.name.as_str(),
+                    service.binary.as_str(),
+                    service.start_type.as_str(),
+                ]
+            }),
+        );
+        markdown
+    }
+}
+
+impl RegistryValue {
+    /// Reads a `reg-root, [subkey], [value-entry-name], [flags], [value]` entry
+    /// of an add-registry section
+    fn from_entry(entry: &InfEntry) -> Option<Self> {
+        if entry.key.is_some() {
+            return None;
+        }
+        let value = |index: usize| entry.values.get(index).cloned().unwrap_or_default();
+        let root = value(0);
+        if root.is_empty() {
+            return None;
+        }
+        Some(Self {
+            root,
+            key: value(1),
+            name: value(2),
+            data: entry.values.get(4..).unwrap_or_default().join(", "),
+        })
+    }
+}
+
+impl Service {
+    /// Reads an `AddService = name, [flags], service-install-section` entry
+    /// along with the binary and start type of its service install section
+    fn from_entry(inf_file: &InfFile, entry: &InfEntry) -> Option<Self> {
+        let name = entry.values.first().filter(|name| !name.is_empty())?;
+        let install_section = entry
+            .values
+            .get(2)
+            .and_then(|section_name| inf_file.section(section_name));
+        let install_value = |key: &str| {
+            install_section
+                .and_then(|section| section.values(key))
+                .and_then(<[String]>::first)
+        };
+        let start_type = install_value(START_TYPE_KEY).map_or_else(String::new, |start_type| {
+            start_type
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| START_TYPES.get(index))
+                .map_or_else(
+                    || start_type.clone(),
+                    |start_type_name| format!("{start_type} ({start_type_name})"),
+                )
+        });
+        Some(Self {
+            name: name.clone(),
+            binary: install_value(SERVICE_BINARY_KEY)
+                .cloned()
+                .unwrap_or_default(),
+            start_type,
+        })
+    }
+}
+
+/// Returns the files copied by a `CopyFiles` entry, which lists file list
+/// sections and single files prefixed with `@`
+fn copied_files(inf_file: &InfFile, entry: &InfEntry) -> Vec<InstalledFile> {
+    let mut files = Vec::new();
+    for value in entry.values.iter().filter(|value| !value.is_empty()) {
+        if let Some(file_name) = value.strip_prefix('@') {
+            files.push(InstalledFile {
+                name: file_name.to_string(),
+                destination: destination(inf_file, None),
+            });
+        } else if let Some(section) = inf_file.section(value) {
+            files.extend(
+                section
+                    .entries
+                    .iter()
+                    .filter_map(|file_entry| file_entry.values.first())
+                    .filter(|file_name| !file_name.is_empty())
+                    .map(|file_name| InstalledFile {
+                        name: file_name.clone(),
+                        destination: destination(inf_file, Some(&section.name)),
+                    }),
+            );
+        }
+    }
+    files
+}
+
+/// Returns the sections named by the values of a directive entry
+fn referenced_sections<'a>(
+    inf_file: &'a InfFile,
+    entry: &'a InfEntry,
+) -> impl Iterator<Item = &'a InfSection> {
+    entry
+        .values
+        .iter()
+        .filter_map(|section_name| inf_file.section(section_name))
+}
+
+/// Returns the directory the files of the given file list section are copied
+/// to. Sections without an entry in `[DestinationDirs]` and single files use
+/// its `DefaultDestDir`.
+fn destination(inf_file: &InfFile, file_list_section_name: Option<&str>) -> String {
+    let destination_dirs = inf_file.section(DESTINATION_DIRS_SECTION_NAME);
+    let Some(values) = destination_dirs.and_then(|section| {
+        file_list_section_name
+            .and_then(|name| section.values(name))
+            .or_else(|| section.values(DEFAULT_DEST_DIR_KEY))
+    }) else {
+        return String::new();
+    };
+    let Some(dirid) = values.first() else {
+        return String::new();
+    };
+    let dir = DIRIDS
+        .iter()
+        .find(|(known_dirid, _)| known_dirid == dirid)
+        .map_or_else(|| format!("DIRID {dirid}"), |(_, dir)| (*dir).to_string());
+    match values.get(1).filter(|subdir| !subdir.is_empty()) {
+        Some(subdir) => format!(r"{dir}\{subdir}"),
+        None => dir,
+    }
+}
+
+fn push_unique<T: PartialEq>(items: &mut Vec<T>, item: T) {
+    if !items.contains(&item) {
+        items.push(item);
+    }
+}
+
+/// Appends a `## title` section with a table of the given rows to `markdown`.
+/// The section reads `None` if there are no rows.
+fn push_table<'a>(
+    markdown: &mut String,
+    title: &str,
+    headers: &[&str],
+    rows: impl Iterator<Item = Vec<&'a str>>,
+) {
+    let mut rows = rows.peekable();
+    markdown.push_str(&format!("\n## {title}\n\n"));
+    if rows.peek().is_none() {
+        markdown.push_str("None\n");
+        return;
+    }
+    markdown.push_str(&format!("| {} |\n", headers.join(" | ")));
+    markdown.push_str(&format!("|{}\n", " --- |".repeat(headers.len())));
+    for row in rows {
+        let cells = row
+            .iter()
+            .map(|cell| cell.replace('|', r"\|"))
+            .collect::<Vec<_>>();
+        markdown.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InstallSummary, InstalledFile, RegistryValue, Service};
+    use crate::actions::inf::InfFile;
+
+    const INF_CONTENT: &str = r#"
+[DestinationDirs]
+DefaultDestDir = 13
+Driver_CopyFiles = 12
+
+[Standard.NTamd64.10.0...16299]
+%Device.DeviceDesc% = Device_Install, Root\Sample
+
+[Device_Install.NT]
+CopyFiles = Driver_CopyFiles, @driver.ini
+
+[Driver_CopyFiles]
+driver.sys
+
+[Device_Install.NT.HW]
+AddReg = Device_AddReg
+
+[Device_AddReg]
+HKR,Parameters,Verbose,0x00010001,1
+HKR,,"Security",,"D:P(A;;GA;;;SY)"
+
+[Device_Install.NT.Services]
+AddService = driver, 0x00000002, Driver_Service_Install
+
+[Driver_Service_Install]
+ServiceType   = 1
+StartType     = 3
+ServiceBinary = %12%\driver.sys
+"#;
+
+    #[test]
+    fn files_registry_values_and_services_are_collected() {
+        let summary = InstallSummary::from_inf(&InfFile::parse(INF_CONTENT));
+
+        assert_eq!(
+            summary.files,
+            [
+                InstalledFile {
+                    name: "driver.sys".to_string(),
+                    destination: r"%SystemRoot%\System32\drivers".to_string(),
+                },
+                InstalledFile {
+                    name: "driver.ini".to_string(),
+                    destination: "Driver Store".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            summary.registry_values,
+            [
+                RegistryValue {
+                    root: "HKR".to_string(),
+                    key: "Parameters".to_string(),
+                    name: "Verbose".to_string(),
+                    data: "1".to_string(),
+                },
+                RegistryValue {
+                    root: "HKR".to_string(),
+                    key: String::new(),
+                    name: "Security".to_string(),
+                    data: "D:P(A;;GA;;;SY)".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            summary.services,
+            [Service {
+                name: "driver".to_string(),
+                binary: r"%12%\driver.sys".to_string(),
+                start_type: "3 (demand)".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn summary_is_rendered_as_markdown_tables() {
+        let summary = InstallSummary {
+            files: vec![InstalledFile {
+                name: "driver.sys".to_string(),
+                destination: r"%SystemRoot%\System32\drivers".to_string(),
+            }],
+            ..InstallSummary::default()
+        };
+
+        assert_eq!(
+            summary.to_markdown("driver.inf"),
+            "# Install summary of driver.inf\n\n## Files\n\n| File | Destination |\n| --- | --- \
+             |\n| driver.sys | %SystemRoot%\\System32\\drivers |\n\n## Registry\n\nNone\n\n## \
+             Services\n\nNone\n"
+        );
+    }
+}
@@ -0,0 +1,398 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module checks the application manifest set in the `manifest`
+//! packaging setting of a UMDF driver before `mt` embeds it into the driver
+//! binary. `mt` reports malformed manifests with errors that do not point at
+//! the problem, so the manifest is checked to be well-formed XML with an
+//! `assembly` root element first and problems are reported with their line.
+
+use std::fmt;
+
+/// Resource ID of the manifest of a DLL. ID 1 is only used by executables.
+pub const DLL_MANIFEST_RESOURCE_ID: u32 = 2;
+/// Local name of the root element of application manifests
+const ASSEMBLY_ELEMENT_NAME: &str = "assembly";
+
+/// Problem that makes a manifest malformed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestError {
+    /// Line of the manifest the problem is on, starting at 1
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Checks that `manifest` is well-formed XML whose root element is an
+/// `assembly` element. Entities declared in a DOCTYPE are not expanded, so
+/// references to them are accepted.
+///
+/// # Errors
+/// * `ManifestError` - The first problem found in the manifest
+pub fn check(manifest: &str) -> Result<(), ManifestError> {
+    let mut reader = Reader {
+        xml: manifest.strip_prefix('\u{feff}').unwrap_or(manifest),
+        position: 0,
+    };
+    reader.prolog()?;
+    let root_position = reader.position;
+    let root = reader.root_element()?;
+    reader.misc()?;
+    if !reader.rest().is_empty() {
+        return Err(reader.error(
+            "only comments and processing instructions may follow the root element",
+        ));
+    }
+    let local_name = root.rsplit_once(':').map_or(root, |(_, local_name)| local_name);
+    if local_name != ASSEMBLY_ELEMENT_NAME {
+        return Err(reader.error_at(
+            root_position,
+            format!("root element must be `{ASSEMBLY_ELEMENT_NAME}`, found `{root}`"),
+        ));
+    }
+    Ok(())
+}
+
+/// Position in the XML being checked
+struct Reader<'a> {
+    xml: &'a str,
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn rest(&self) -> &'a str {
+        &self.xml[self.position..]
+    }
+
+    fn error(&self, message: impl Into<String>) -> ManifestError {
+        self.error_at(self.position, message)
+    }
+
+    fn error_at(&self, position: usize, message: impl Into<String>) -> ManifestError {
+        ManifestError {
+            line: self.xml[..position].matches('\n').count() + 1,
+            message: message.into(),
+        }
+    }
+
+    /// Skips `prefix` if the rest of the XML starts with it
+    fn eat(&mut self, prefix: &str) -> bool {
+        let found = self.rest().starts_with(prefix);
+        if found {
+            self.position += prefix.len();
+        }
+        found
+    }
+
+    fn expect(&mut self, prefix: &str, expected: &str) -> Result<(), ManifestError> {
+        if self.eat(prefix) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected {expected}")))
+        }
+    }
+
+    /// Skips whitespace, returning `true` if there was any
+    fn skip_whitespace(&mut self) -> bool {
+        let rest = self.rest();
+        let skipped = rest.len() - rest.trim_start_matches(is_whitespace).len();
+        self.position += skipped;
+        skipped > 0
+    }
+
+    /// Skips past the next `terminator`, returning what was skipped before it
+    fn skip_past(&mut self, terminator: &str, construct: &str) -> Result<&'a str, ManifestError> {
+        let rest = self.rest();
+        let Some(index) = rest.find(terminator) else {
+            return Err(self.error(format!("{construct} is not closed by `{terminator}`")));
+        };
+        self.position += index + terminator.len();
+        Ok(&rest[..index])
+    }
+
+    fn name(&mut self) -> Result<&'a str, ManifestError> {
+        let rest = self.rest();
+        let length = rest.find(|c| !is_name_char(c)).unwrap_or(rest.len());
+        let name = &rest[..length];
+        if !name.starts_with(is_name_start_char) {
+            return Err(self.error("expected a name"));
+        }
+        self.position += length;
+        Ok(name)
+    }
+
+    /// Skips the XML declaration, DOCTYPE, comments and processing
+    /// instructions before the root element
+    fn prolog(&mut self) -> Result<(), ManifestError> {
+        if self
+            .rest()
+            .strip_prefix("<?xml")
+            .is_some_and(|rest| rest.starts_with(is_whitespace))
+        {
+            let declaration = self.skip_past("?>", "XML declaration")?;
+            if !declaration["<?xml".len()..]
+                .trim_start_matches(is_whitespace)
+                .starts_with("version")
+            {
+                return Err(self.error("XML declaration must start with the version"));
+            }
+        }
+        self.misc()?;
+        if self.eat("<!DOCTYPE") {
+            self.doctype()?;
+            self.misc()?;
+        }
+        Ok(())
+    }
+
+    fn doctype(&mut self) -> Result<(), ManifestError> {
+        let rest = self.rest();
+        match (rest.find('['), rest.find('>')) {
+            (Some(subset_start), Some(end)) if subset_start < end => {
+                self.position += subset_start;
+                self.skip_past("]", "internal subset of the DOCTYPE")?;
+                self.skip_whitespace();
+                self.expect(">", "`>` closing the DOCTYPE")
+            }
+            _ => self.skip_past(">", "DOCTYPE").map(|_| ()),
+        }
+    }
+
+    /// Skips the comments, processing instructions and whitespace allowed
+    /// around the root element
+    fn misc(&mut self) -> Result<(), ManifestError> {
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("<!--") {
+                self.comment()?;
+            } else if rest.starts_with("<?") {
+                self.processing_instruction()?;
+            } else if !self.skip_whitespace() {
+                return Ok(());
+            }
+        }
+    }
+
+    fn comment(&mut self) -> Result<(), ManifestError> {
+        let start = self.position;
+        self.position += "<!--".len();
+        let comment = self.skip_past("-->", "comment")?;
+        if comment.contains("--") {
+            return Err(self.error_at(start, "comments must not contain `--`"));
+        }
+        Ok(())
+    }
+
+    fn processing_instruction(&mut self) -> Result<(), ManifestError> {
+        self.position += "<?".len();
+        if self.name()?.eq_ignore_ascii_case("xml") {
+            return Err(self.error("XML declaration must be at the start of the manifest"));
+        }
+        self.skip_past("?>", "processing instruction").map(|_| ())
+    }
+
+    /// Reads the root element and everything it contains, returning the name
+    /// of the root element. Nested elements are tracked on a stack, so that
+    /// deeply nested manifests do not overflow the call stack.
+    fn root_element(&mut self) -> Result<&'a str, ManifestError> {
+        if !self.rest().starts_with('<') {
+            return Err(self.error("expected the root element"));
+        }
+        let (root, is_empty) = self.start_tag()?;
+        let mut open_elements = Vec::new();
+        if !is_empty {
+            open_elements.push(root);
+        }
+        while let Some(&name) = open_elements.last() {
+            let rest = self.rest();
+            if rest.starts_with("</") {
+                self.position += "</".len();
+                let end_name = self.name()?;
+                if end_name != name {
+                    return Err(self.error(format!(
+                        "end tag `{end_name}` does not match the start tag `{name}`"
+                    )));
+                }
+                self.skip_whitespace();
+                self.expect(">", "`>` closing the end tag")?;
+                open_elements.pop();
+            } else if rest.starts_with("<!--") {
+                self.comment()?;
+            } else if self.eat("<![CDATA[") {
+                self.skip_past("]]>", "CDATA section")?;
+            } else if rest.starts_with("<?") {
+                self.processing_instruction()?;
+            } else if rest.starts_with('<') {
+                let (child, is_empty) = self.start_tag()?;
+                if !is_empty {
+                    open_elements.push(child);
+                }
+            } else if rest.is_empty() {
+                return Err(self.error(format!("element `{name}` is not closed")));
+            } else {
+                let length = rest.find('<').unwrap_or(rest.len());
+                self.check_references(&rest[..length], self.position)?;
+                self.position += length;
+            }
+        }
+        Ok(root)
+    }
+
+    /// Reads a start tag, returning the name of the element and whether it is
+    /// an empty element tag
+    fn start_tag(&mut self) -> Result<(&'a str, bool), ManifestError> {
+        self.expect("<", "`<`")?;
+        let name = self.name()?;
+        let mut attributes = Vec::new();
+        loop {
+            let has_whitespace = self.skip_whitespace();
+            if self.eat("/>") {
+                return Ok((name, true));
+            }
+            if self.eat(">") {
+                return Ok((name, false));
+            }
+            if !has_whitespace {
+                return Err(self.error(format!(
+                    "expected whitespace, `>` or `/>` in the start tag of `{name}`"
+                )));
+            }
+            let attribute_position = self.position;
+            let attribute = self.name()?;
+            if attributes.contains(&attribute) {
+                return Err(self.error_at(
+                    attribute_position,
+                    format!("attribute `{attribute}` is repeated on `{name}`"),
+                ));
+            }
+            attributes.push(attribute);
+            self.skip_whitespace();
+            self.expect("=", format!("`=` after attribute `{attribute}`").as_str())?;
+            self.skip_whitespace();
+            let quote = if self.eat("\"") {
+                "\""
+            } else if self.eat("'") {
+                "'"
+            } else {
+                return Err(self.error(format!("value of attribute `{attribute}` is not quoted")));
+            };
+            let value_position = self.position;
+            let value = self.skip_past(quote, "attribute value")?;
+            if let Some(index) = value.find('<') {
+                return Err(self.error_at(
+                    value_position + index,
+                    "`<` is not allowed in attribute values",
+                ));
+            }
+            self.check_references(value, value_position)?;
+        }
+    }
+
+    /// Checks that every `&` in `text`, found at `position`, starts an entity
+    /// or character reference
+    fn check_references(&self, text: &str, position: usize) -> Result<(), ManifestError> {
+        for (index, _) in text.match_indices('&') {
+            let reference = &text[index + 1..];
+            let is_reference = reference
+                .find(';')
+                .is_some_and(|end| is_reference(&reference[..end]));
+            if !is_reference {
+                return Err(self.error_at(
+                    position + index,
+                    "`&` must start a reference such as `&amp;`",
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns `true` if `reference`, without its `&` and `;`, is a character
+/// reference or the name of an entity
+fn is_reference(reference: &str) -> bool {
+    reference.strip_prefix('#').map_or_else(
+        || reference.starts_with(is_name_start_char) && reference.chars().all(is_name_char),
+        |number| {
+            let (digits, radix) = number.strip_prefix('x').map_or((number, 10), |hex| (hex, 16));
+            !digits.is_empty() && digits.chars().all(|c| c.is_digit(radix))
+        },
+    )
+}
+
+const fn is_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\r' | '\n')
+}
+
+fn is_name_start_char(c: char) -> bool {
+    c.is_alphabetic() || matches!(c, '_' | ':')
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | ':' | '-' | '.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ManifestError, check};
+
+    const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<!-- Requests the compatibility of Windows 10 and later -->
+<assembly xmlns="urn:schemas-microsoft-com:asm.v1" manifestVersion="1.0">
+  <assemblyIdentity type="win32" name="Contoso.Driver.Host" version="1.0.0.0"/>
+  <description>Contoso &amp; Fabrikam &#x2122;</description>
+  <compatibility xmlns="urn:schemas-microsoft-com:compatibility.v1">
+    <application>
+      <supportedOS Id='{8e0f7a12-bfb3-4fe8-b9a5-48fd50a15a9a}' />
+    </application>
+  </compatibility>
+  <trustInfo xmlns="urn:schemas-microsoft-com:asm.v3">
+    <security><requestedPrivileges><![CDATA[<unused>]]></requestedPrivileges></security>
+  </trustInfo>
+</assembly>
+"#;
+
+    #[test]
+    fn well_formed_manifests_pass() {
+        assert_eq!(check(MANIFEST), Ok(()));
+        assert_eq!(check(&format!("\u{feff}{MANIFEST}")), Ok(()));
+        assert_eq!(
+            check("<!DOCTYPE assembly [<!ENTITY v \"1\">]>\n<asmv1:assembly a=\"&v;\"/>"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn malformed_manifests_fail_with_the_line_of_the_problem() {
+        for (manifest, line, message) in [
+            ("", 1, "expected the root element"),
+            ("<assembly>\n<a></b>\n</assembly>", 2, "end tag `b` does not match"),
+            ("<assembly>\n<a>\n", 3, "element `a` is not closed"),
+            ("<assembly a='1' a='2'/>", 1, "attribute `a` is repeated"),
+            ("<assembly a=1/>", 1, "value of attribute `a` is not quoted"),
+            ("<assembly a='<'/>", 1, "`<` is not allowed"),
+            ("<assembly>\nR&D</assembly>", 2, "`&` must start a reference"),
+            ("<assembly/>\n<assembly/>", 2, "only comments and processing"),
+            ("\n<?xml version=\"1.0\"?><assembly/>", 2, "XML declaration must be at"),
+            ("<!-- a -- b --><assembly/>", 1, "comments must not contain"),
+            ("<assembly><!-- </assembly>", 1, "comment is not closed"),
+            ("<?xml version=\"1.0\"?>\n<html/>", 2, "root element must be `assembly`"),
+        ] {
+            let Err(ManifestError {
+                line: error_line,
+                message: error_message,
+            }) = check(manifest)
+            else {
+                panic!("{manifest} should be malformed");
+            };
+            assert_eq!(error_line, line, "{manifest}");
+            assert!(
+                error_message.starts_with(message),
+                "{manifest}: {error_message}"
+            );
+        }
+    }
+}
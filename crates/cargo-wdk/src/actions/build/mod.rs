@@ -8,43 +8,261 @@
 //! the package phase.
 
 mod build_task;
+mod catalog_exclude;
+mod diagnostic_bundle;
+mod driver_ver_counter;
+mod driver_ver_version;
 mod error;
+mod event_pipe;
+mod import_check;
+mod install_summary;
+mod manifest;
+mod nuspec;
+mod os_version;
+mod package_index;
+mod package_settings;
 mod package_task;
+mod pe;
+mod pnp_ids;
+mod profile_override;
+mod reproducible;
+mod signing_pfx;
+mod summary;
+mod supported_wdk;
+mod test_cert;
 #[cfg(test)]
 mod tests;
+mod tool_log;
+mod tool_paths;
+mod uninstall_script;
+mod warnings;
+mod wdf_version;
+mod wdk_dependencies;
+mod wdk_lock;
 use std::{
+    cell::OnceCell,
+    error::Error as _,
+    fmt,
     path::{Path, PathBuf, absolute},
     result::Result::Ok,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::Result;
 use build_task::{BuildTask, BuildTaskParams};
 use cargo_metadata::{CrateType, Message, Metadata as CargoMetadata, Package, TargetKind};
 use clap_cargo::Features;
-use error::BuildActionError;
+use diagnostic_bundle::DiagnosticBundle;
+pub use driver_ver_version::DriverVerVersion;
+pub use error::BuildActionError;
+use event_pipe::{BuildEvent, BuildPhase, EventPipe};
 use mockall_double::double;
-use package_task::{PackageTask, PackageTaskParams};
+use nuspec::NuspecMetadata;
+use package_index::PackageResult;
+pub(crate) use package_settings::PackageSettings;
+use package_task::{InfCatalog, InfFormat, PackageTask, PackageTaskParams};
 pub use package_task::{SignMode, TargetPlatform};
+pub use profile_override::ProfileOverride;
+use reproducible::DriverVer;
+use signing_pfx::SigningPfx;
+use summary::BuildSummary;
+use test_cert::TestCertValidity;
+use tool_log::ToolLog;
+pub use tool_paths::ToolPaths;
 use tracing::{debug, error as err, info, trace, warn};
+use warnings::BuildWarnings;
+pub use wdf_version::WdfVersionOverrides;
+pub use wdk_build::WdfVersion;
 use wdk_build::{
     CpuArchitecture,
+    DriverConfig,
+    TwoPartVersion,
     metadata::{TryFromCargoMetadataError, Wdk},
 };
+pub use wdk_lock::WdkLockMode;
 
-use crate::actions::Profile;
+use crate::{
+    actions::{DriverType, Profile, inf::InfFile, to_target_triple},
+    providers::metadata,
+    trace::ColorChoice,
+};
 #[double]
 use crate::providers::{exec::CommandExec, fs::Fs, metadata::Metadata, wdk_build::WdkBuild};
 
+/// Architectures included in a multi-architecture package
+const MULTI_ARCH_PACKAGE_ARCHITECTURES: [CpuArchitecture; 2] =
+    [CpuArchitecture::Amd64, CpuArchitecture::Arm64];
+
+/// Name of the file written to check that the temp directory is writable
+const TEMP_DIR_PROBE_FILE_NAME: &str = ".cargo-wdk-temp-dir-probe";
+
+/// Folder of the target directory the checked variants of the drivers are
+/// built in, so that their binaries are never packaged by a normal build
+const CHECKED_TARGET_DIR_NAME: &str = "cargo-wdk-checked";
+
+/// Whether the cargo invocations of the build are run after they are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrintCargoCommand {
+    /// Print the cargo invocations and run them.
+    AndRun,
+    /// Print the cargo invocations without running them. Nothing is compiled
+    /// or packaged.
+    Only,
+}
+
+/// Sanitizer the packages are instrumented with through the unstable
+/// `-Zsanitizer` flag of rustc, which requires a nightly toolchain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sanitizer {
+    /// AddressSanitizer, which detects out-of-bounds accesses, use after free
+    /// and other memory errors
+    Address,
+}
+
+impl Sanitizer {
+    /// Returns the `rustc` flag that instruments the code with the sanitizer
+    const fn rustflag(self) -> &'static str {
+        match self {
+            Self::Address => "-Zsanitizer=address",
+        }
+    }
+}
+
+impl fmt::Display for Sanitizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Address => write!(f, "address"),
+        }
+    }
+}
+
 pub struct BuildActionParams<'a> {
     pub working_dir: &'a Path,
     pub profile: Option<&'a Profile>,
     pub target_arch: Option<CpuArchitecture>,
-    pub sign_mode: SignMode,
+    /// Signing mode of the packages. `None` uses the `sign-mode` packaging
+    /// setting of the profile, or test signing if it is not set.
+    pub sign_mode: Option<SignMode>,
+    /// Whether the signatures of the packages are verified whatever their
+    /// signing mode. Packages that are not signed fail instead of passing
+    /// without anything to verify.
+    pub verify_signature: bool,
     pub is_sample_class: bool,
+    /// Driver type the driver packages must have in their WDK metadata. `None`
+    /// accepts any driver type.
+    pub driver_type: Option<DriverType>,
     pub locked: bool,
+    /// Number of parallel jobs forwarded to `cargo build` as `-j`. `None` uses
+    /// cargo's default.
+    pub cargo_jobs: Option<u32>,
+    /// Whether compiler warnings fail the `cargo build` invocations
+    pub deny_compile_warnings: bool,
     pub target_platform: TargetPlatform,
     pub features: &'a Features,
     pub verbosity_level: clap_verbosity_flag::Verbosity,
+    /// Whether the diagnostics rendered by `cargo` are colored
+    pub color: ColorChoice,
+    pub import_check: bool,
+    pub os_version_check: bool,
+    pub multi_arch_package: bool,
+    pub wdk_lock_mode: WdkLockMode,
+    /// Whether packages are built with a WDK outside of their `supported-wdk`
+    /// range
+    pub allow_unsupported_wdk: bool,
+    pub temp_dir: Option<&'a Path>,
+    pub no_package: bool,
+    /// Seconds since the Unix epoch that the `DriverVer` of reproducible
+    /// packages is derived from. `None` creates packages stamped with the time
+    /// of the build.
+    pub source_date_epoch: Option<u64>,
+    /// Driver binary built outside of cargo to package instead of building
+    /// the packages in `working_dir`
+    pub binary_path: Option<&'a Path>,
+    /// Write a `.nuspec` manifest referencing the files of each driver package
+    pub emit_nupkg: bool,
+    /// Write an index of the driver packages produced by the build to the
+    /// target directory
+    pub emit_index: bool,
+    /// Whether the linker map of the driver is copied into the packages.
+    /// `None` uses the `include-map` packaging setting of the profile, or
+    /// includes it if it is not set.
+    pub include_map: Option<bool>,
+    /// Directory the output of the tools run for each package is written to,
+    /// as `<package name>.log`. `None` writes no log files.
+    pub log_dir: Option<&'a Path>,
+    /// Zip archive the settings of the build, the resolved metadata of the
+    /// packages and the output of the tools run are written to, whether or not
+    /// the build succeeds. `None` writes no diagnostic bundle.
+    pub diagnostic_bundle: Option<&'a Path>,
+    /// Executables run instead of the packaging tools found on `PATH`
+    pub tool_paths: &'a ToolPaths,
+    /// Fail if the INF or catalog of a package contains absolute paths of the
+    /// build machine
+    pub relocatable: bool,
+    /// Print the paths of the files of the driver packages to stdout, one per
+    /// line, after a successful build
+    pub print_artifacts: bool,
+    /// Run `cargo clippy` before building each package. Packages may also
+    /// enable it with the `run-clippy` packaging setting.
+    pub clippy: bool,
+    /// Clear existing package folders that contain files which are not part
+    /// of the package instead of failing
+    pub overwrite_package: bool,
+    /// Path of the PDB embedded in the driver binaries instead of its absolute
+    /// path on the build machine, passed to the linker as `/PDBALTPATH`
+    pub pdb_altpath: Option<&'a str>,
+    /// Write a markdown summary of the files, registry values and services
+    /// installed by the INFs to each driver package
+    pub emit_install_summary: bool,
+    /// KMDF and UMDF versions the drivers are built and packaged for instead
+    /// of the versions in their WDK metadata
+    pub wdf_versions: WdfVersionOverrides,
+    /// Write a report of the signer, thumbprint, timestamp and digest
+    /// algorithm of the signed catalogs to each driver package
+    pub emit_catalog_signing_report: bool,
+    /// Write scripts that delete the installed driver packages and their
+    /// services to each driver package
+    pub emit_uninstall: bool,
+    /// Print the cargo invocations of each package to stdout before they are
+    /// run. `None` prints nothing.
+    pub print_cargo_command: Option<PrintCargoCommand>,
+    /// Only stamp the INFs of the packages with `stampinf` and report their
+    /// `DriverVer`, without building, cataloging or signing the packages
+    pub stamp_only: bool,
+    /// Package only the signed driver binaries and their PDBs, without INFs
+    /// and catalogs
+    pub binary_only: bool,
+    /// Directory the PDBs of the driver binaries are archived to in the
+    /// layout of a symbol store. `None` archives no PDBs.
+    pub symbols_archive_dir: Option<&'a Path>,
+    /// Skip the `pre-build` commands of the packages
+    pub no_hooks: bool,
+    /// Sanitizer the packages are built with. Kernel-mode driver packages
+    /// cannot be built with a sanitizer.
+    pub sanitizer: Option<Sanitizer>,
+    /// Last released `DriverVer` version, which the stamped `DriverVer`
+    /// version of every packaged INF must be greater than. `None` does not
+    /// check the version.
+    pub min_version: Option<DriverVerVersion>,
+    /// Number of days generated test certificates are valid for. When set,
+    /// an expired test certificate is regenerated before signing. `None`
+    /// reuses test certificates without checking them.
+    pub cert_validity_days: Option<u32>,
+    /// Environment variable containing a base64 encoded PFX certificate to
+    /// sign the packages with instead of the test certificate
+    pub cert_from_env: Option<&'a str>,
+    /// Environment variable containing the password of the PFX certificate
+    /// of `cert_from_env`
+    pub cert_password_env: Option<&'a str>,
+    /// Profile settings overridden for the `cargo` invocations of the build
+    /// instead of the settings of the profile in the manifest
+    pub profile_overrides: &'a [ProfileOverride],
+    /// Name of the named pipe the events of the build are written to as
+    /// lines of JSON while it runs. `None` writes no events.
+    pub event_pipe: Option<&'a str>,
+    /// Whether a checked variant of every driver, built with the `checked`
+    /// cfg and runtime assertions, is packaged alongside its package
+    pub checked: bool,
 }
 
 /// Action that orchestrates the build and package of a driver project. Build is
@@ -53,12 +271,65 @@ pub struct BuildAction<'a> {
     working_dir: PathBuf,
     profile: Option<&'a Profile>,
     target_arch: Option<CpuArchitecture>,
-    sign_mode: SignMode,
+    sign_mode: Option<SignMode>,
+    verify_signature: bool,
     is_sample_class: bool,
+    driver_type: Option<DriverType>,
     locked: bool,
+    cargo_jobs: Option<u32>,
+    profile_overrides: &'a [ProfileOverride],
+    deny_compile_warnings: bool,
     target_platform: TargetPlatform,
     features: &'a Features,
     verbosity_level: clap_verbosity_flag::Verbosity,
+    color: ColorChoice,
+    import_check: bool,
+    os_version_check: bool,
+    multi_arch_package: bool,
+    wdk_lock_mode: WdkLockMode,
+    allow_unsupported_wdk: bool,
+    temp_dir: Option<PathBuf>,
+    no_package: bool,
+    source_date_epoch: Option<u64>,
+    binary_path: Option<PathBuf>,
+    emit_nupkg: bool,
+    emit_index: bool,
+    include_map: Option<bool>,
+    tool_paths: ToolPaths,
+    relocatable: bool,
+    print_artifacts: bool,
+    clippy: bool,
+    overwrite_package: bool,
+    pdb_altpath: Option<&'a str>,
+    emit_install_summary: bool,
+    wdf_versions: WdfVersionOverrides,
+    emit_catalog_signing_report: bool,
+    emit_uninstall: bool,
+    print_cargo_command: Option<PrintCargoCommand>,
+    stamp_only: bool,
+    binary_only: bool,
+    symbols_archive_dir: Option<PathBuf>,
+    no_hooks: bool,
+    sanitizer: Option<Sanitizer>,
+    min_version: Option<DriverVerVersion>,
+    test_cert_validity: Option<TestCertValidity>,
+    cert_from_env: Option<String>,
+    cert_password_env: Option<String>,
+    // PFX certificate decoded from `cert_from_env` for the duration of the
+    // build
+    signing_pfx: OnceCell<SigningPfx>,
+    event_pipe_name: Option<String>,
+    // Named pipe of `event_pipe_name` for the duration of the build
+    event_pipe: OnceCell<EventPipe>,
+    checked: bool,
+    // Files written to the driver packages, printed at the end of the build
+    summary: BuildSummary,
+    // Warnings printed by the packaging tools, reported at the end of the build
+    warnings: BuildWarnings,
+    // Output of the tools run for each package, written to its log file
+    tool_log: ToolLog,
+    // Files collected to debug the build, written to a zip archive at its end
+    diagnostic_bundle: DiagnosticBundle,
 
     // Injected deps
     wdk_build: &'a WdkBuild,
@@ -97,16 +368,72 @@ pub fn new(
             !params.working_dir.as_os_str().is_empty(),
             "working_dir must not be empty"
         );
+        let diagnostic_bundle =
+            DiagnosticBundle::new(params.diagnostic_bundle.map(absolute).transpose()?);
         Ok(Self {
             working_dir: absolute(params.working_dir)?,
             profile: params.profile,
             target_arch: params.target_arch,
             sign_mode: params.sign_mode,
+            verify_signature: params.verify_signature,
             is_sample_class: params.is_sample_class,
+            driver_type: params.driver_type,
             locked: params.locked,
+            cargo_jobs: params.cargo_jobs,
+            profile_overrides: params.profile_overrides,
+            deny_compile_warnings: params.deny_compile_warnings,
             target_platform: params.target_platform,
             features: params.features,
             verbosity_level: params.verbosity_level,
+            color: params.color,
+            import_check: params.import_check,
+            os_version_check: params.os_version_check,
+            multi_arch_package: params.multi_arch_package,
+            wdk_lock_mode: params.wdk_lock_mode,
+            allow_unsupported_wdk: params.allow_unsupported_wdk,
+            temp_dir: params.temp_dir.map(absolute).transpose()?,
+            no_package: params.no_package,
+            source_date_epoch: params.source_date_epoch,
+            binary_path: params.binary_path.map(absolute).transpose()?,
+            emit_nupkg: params.emit_nupkg,
+            emit_index: params.emit_index,
+            include_map: params.include_map,
+            tool_paths: params.tool_paths.to_absolute()?,
+            relocatable: params.relocatable,
+            print_artifacts: params.print_artifacts,
+            clippy: params.clippy,
+            overwrite_package: params.overwrite_package,
+            pdb_altpath: params.pdb_altpath,
+            emit_install_summary: params.emit_install_summary,
+            wdf_versions: params.wdf_versions,
+            emit_catalog_signing_report: params.emit_catalog_signing_report,
+            emit_uninstall: params.emit_uninstall,
+            print_cargo_command: params.print_cargo_command,
+            stamp_only: params.stamp_only,
+            binary_only: params.binary_only,
+            symbols_archive_dir: params.symbols_archive_dir.map(absolute).transpose()?,
+            no_hooks: params.no_hooks,
+            sanitizer: params.sanitizer,
+            min_version: params.min_version,
+            test_cert_validity: params.cert_validity_days.map(|days| TestCertValidity {
+                days,
+                now: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |duration| duration.as_secs()),
+            }),
+            cert_from_env: params.cert_from_env.map(ToString::to_string),
+            cert_password_env: params.cert_password_env.map(ToString::to_string),
+            signing_pfx: OnceCell::new(),
+            event_pipe_name: params.event_pipe.map(ToString::to_string),
+            event_pipe: OnceCell::new(),
+            checked: params.checked,
+            summary: BuildSummary::default(),
+            warnings: BuildWarnings::default(),
+            tool_log: ToolLog::new(
+                params.log_dir.map(absolute).transpose()?,
+                diagnostic_bundle.is_enabled(),
+            ),
+            diagnostic_bundle,
             wdk_build,
             command_exec,
             fs,
@@ -116,6 +443,11 @@ pub fn new(
 
     /// Entry point method to execute the packaging action flow.
     ///
+    /// Warnings printed by the packaging tools are collected while the
+    /// packages are built and reported in a consolidated section at the end.
+    /// The diagnostic bundle, if requested, is written at the end as well,
+    /// also if the build fails.
+    ///
     /// # Returns
     /// `Result<(), BuildActionError>`
     ///
@@ -135,27 +467,161 @@ pub fn new(
     /// * `BuildActionError::Io` - Wraps all possible IO errors.
     /// * `BuildActionError::CommandExecution` - If there is an error executing
     ///   a command.
-    /// * `BuildActionError::NoValidRustProjectsInTheDirectory` - If no valid
-    ///   Rust projects are found in the working directory.
+    /// * `BuildActionError::NoRustPackagesFound` - If no Rust packages are
+    ///   found in the working directory.
     /// * `BuildActionError::OneOrMoreRustProjectsFailedToBuild` - If one or
     ///   more Rust projects fail to build in an emulated workspace.
     /// * `BuildActionError::OneOrMoreWorkspaceMembersFailedToBuild` - If one or
     ///   more workspace members fail to build inside a workspace.
     /// * `BuildActionError::BuildTask` - If there is an error during the build
     ///   task process.
+    /// * `BuildActionError::WdkChangedSinceLock` - If the WDK lock mode is
+    ///   `WdkLockMode::Locked` and the detected WDK differs from the one
+    ///   recorded in the target directory.
+    /// * `BuildActionError::TempDirNotWritable` - If the temp directory cannot
+    ///   be created or written to.
+    /// * `BuildActionError::ToolPathNotFound` - If the executable set for a
+    ///   packaging tool does not exist.
+    /// * `BuildActionError::ToolPathNotExecutable` - If the executable set for
+    ///   a packaging tool is not an `.exe` file.
+    /// * `BuildActionError::PrebuiltBinaryNotFound` - If the pre-built driver
+    ///   binary to package does not exist.
+    /// * `BuildActionError::UnsupportedPrebuiltBinary` - If the pre-built
+    ///   driver binary to package is not a `.sys` file.
+    /// * `BuildActionError::DuplicateDeviceId` - If two drivers packaged from
+    ///   the same workspace claim the same hardware or compatible ID.
+    /// * `BuildActionError::DriverTypeMismatch` - If the driver type of a
+    ///   driver package differs from the driver type that was passed.
+    /// * `BuildActionError::UnsupportedWdk` - If the detected WDK is outside of
+    ///   the `supported-wdk` range of a driver package and unsupported WDKs are
+    ///   not allowed.
     pub fn run(&self) -> Result<(), BuildActionError> {
+        let result = self.run_build();
+        // The signing certificate must not outlive the build, whether or not
+        // it succeeded
+        let delete_result = self
+            .signing_pfx
+            .get()
+            .map_or(Ok(()), |signing_pfx| signing_pfx.delete(self.fs));
+        // Warnings are reported whether or not the build succeeded since they
+        // may explain a failure
+        self.warnings.report();
+        self.send_event(&BuildEvent::BuildFinished {
+            error: result.as_ref().err().map(error_chain),
+            artifacts: self.summary.artifacts(),
+        });
+        if result.is_ok() && self.print_artifacts {
+            self.summary.print_artifacts();
+        }
+        let write_result = self.write_diagnostic_bundle(result.as_ref().err());
+        result?;
+        delete_result?;
+        write_result?;
+        Ok(())
+    }
+
+    // Adds the settings of the build, its outcome, warnings and artifacts and
+    // the output of the tools run to the diagnostic bundle and writes it
+    fn write_diagnostic_bundle(
+        &self,
+        error: Option<&BuildActionError>,
+    ) -> Result<(), BuildActionError> {
+        if !self.diagnostic_bundle.is_enabled() {
+            return Ok(());
+        }
+        let build = serde_json::json!({
+            "cargo-wdk-version": env!("CARGO_PKG_VERSION"),
+            "command-line": std::env::args().collect::<Vec<_>>(),
+            "working-dir": self.working_dir,
+            "profile": self.profile.map(ToString::to_string),
+            "target-arch": self.target_arch.map(|arch| arch.to_string()),
+            "multi-arch-package": self.multi_arch_package,
+            "sign-mode": self.sign_mode.map(|sign_mode| format!("{sign_mode:?}")),
+            "verify-signature": self.verify_signature,
+            "target-platform": format!("{:?}", self.target_platform),
+            "sample": self.is_sample_class,
+            "driver-type": self.driver_type.map(|driver_type| driver_type.to_string()),
+            "locked": self.locked,
+            "no-package": self.no_package,
+            "min-version": self.min_version.map(|version| version.to_string()),
+            "cert-validity-days": self.test_cert_validity.map(|validity| validity.days),
+            "cert-from-env": self.cert_from_env,
+            "event-pipe": self.event_pipe_name,
+            "checked": self.checked,
+            "profile-overrides": self
+                .profile_overrides
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            "sanitizer": self.sanitizer.map(|sanitizer| sanitizer.to_string()),
+            "succeeded": error.is_none(),
+        });
+        self.diagnostic_bundle.add_file(
+            "build.json",
+            serde_json::to_string_pretty(&build).expect("build settings serialize to JSON"),
+        );
+        if let Some(error) = error {
+            self.diagnostic_bundle
+                .append_line("errors.txt", &error_chain(error));
+        }
+        let warnings = self.warnings.summary_lines();
+        if !warnings.is_empty() {
+            self.diagnostic_bundle
+                .add_file("warnings.txt", warnings.join("\n") + "\n");
+        }
+        let artifacts = self.summary.artifacts();
+        if !artifacts.is_empty() {
+            self.diagnostic_bundle.add_file(
+                "artifacts.txt",
+                artifacts
+                    .iter()
+                    .map(|artifact| format!("{}\n", artifact.display()))
+                    .collect(),
+            );
+        }
+        for (package_name, log) in self.tool_log.logs() {
+            self.diagnostic_bundle
+                .add_file(&format!("logs/{package_name}.log"), log);
+        }
+        Ok(self.diagnostic_bundle.write(self.fs)?)
+    }
+
+    fn run_build(&self) -> Result<(), BuildActionError> {
         debug!(
             "Initialized build for project at: {}",
             self.working_dir.display()
         );
+        self.connect_event_pipe()?;
+        self.send_event(&BuildEvent::BuildStarted {
+            working_dir: &self.working_dir.to_string_lossy(),
+        });
         let build_number = self.wdk_build.detect_wdk_build_number()?;
         debug!("WDK build number: {}", build_number);
+        self.diagnostic_bundle
+            .add_file("wdk.txt", format!("WDK build number: {build_number}\n"));
+        self.ensure_wdf_versions_are_available()?;
         wdk_build::cargo_make::setup_path()?;
+        if let Some(sanitizer) = self.sanitizer {
+            warn!(
+                "--sanitizer={sanitizer} passes `{}` to rustc, which requires a nightly \
+                 toolchain. Kernel-mode driver packages cannot be built with it.",
+                sanitizer.rustflag()
+            );
+        }
         debug!("PATH env variable is set with WDK bin and tools paths");
+        if let Some(temp_dir) = &self.temp_dir {
+            self.ensure_temp_dir_is_writable(temp_dir)?;
+        }
+        self.ensure_tool_paths_are_executable()?;
+        self.write_signing_pfx()?;
+
+        if let Some(binary_path) = &self.binary_path {
+            return self.package_prebuilt_binary(binary_path);
+        }
 
         // Standalone driver/driver workspace support
         if self.fs.exists(&self.working_dir.join("Cargo.toml")) {
-            return self.run_from_workspace_root(&self.working_dir);
+            return self.run_from_workspace_root(&self.working_dir, build_number);
         }
 
         // Emulated workspaces support
@@ -185,9 +651,7 @@ pub fn run(&self) -> Result<(), BuildActionError> {
         }
 
         if !is_valid_dir_with_rust_projects {
-            return Err(BuildActionError::NoValidRustProjectsInTheDirectory(
-                self.working_dir.clone(),
-            ));
+            return Err(BuildActionError::NoRustPackagesFound(self.working_dir.clone()));
         }
 
         info!("Building packages in {}", self.working_dir.display());
@@ -207,12 +671,12 @@ pub fn run(&self) -> Result<(), BuildActionError> {
                 .to_string_lossy();
 
             debug!("Building package(s) in dir {package_dir_name}");
-            if let Err(e) = self.run_from_workspace_root(&cargo_package_path) {
+            if let Err(e) = self.run_from_workspace_root(&cargo_package_path, build_number) {
                 failed_atleast_one_project = true;
-                err!(
-                    "Error building project: {package_dir_name}, error: {:?}",
-                    anyhow::Error::new(e)
-                );
+                let error = format!("{:?}", anyhow::Error::new(e));
+                err!("Error building project: {package_dir_name}, error: {error}");
+                self.diagnostic_bundle
+                    .append_line("errors.txt", &format!("{package_dir_name}: {error}"));
             }
         }
 
@@ -230,11 +694,321 @@ pub fn run(&self) -> Result<(), BuildActionError> {
         Ok(())
     }
 
+    // Connects to the named pipe of `event_pipe_name`, which the events of the
+    // build are sent to
+    fn connect_event_pipe(&self) -> Result<(), BuildActionError> {
+        let Some(event_pipe_name) = &self.event_pipe_name else {
+            return Ok(());
+        };
+        let event_pipe = EventPipe::connect(self.fs, event_pipe_name)
+            .map_err(|e| BuildActionError::EventPipeConnect(event_pipe_name.clone(), e))?;
+        info!("Sending the events of the build to event pipe {event_pipe_name}");
+        assert!(
+            self.event_pipe.set(event_pipe).is_ok(),
+            "event pipe is only connected once per build"
+        );
+        Ok(())
+    }
+
+    // Sends the event to the event pipe, if any
+    fn send_event(&self, event: &BuildEvent) {
+        if let Some(event_pipe) = self.event_pipe.get() {
+            event_pipe.send(event);
+        }
+    }
+
+    // Runs a phase of the build of the given package, sending its start and
+    // its end with the error it failed with, if any, to the event pipe
+    fn run_phase<T, E: std::error::Error>(
+        &self,
+        package_name: &str,
+        phase: BuildPhase,
+        run: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        self.send_event(&BuildEvent::PhaseStarted {
+            package: package_name,
+            phase,
+        });
+        let result = run();
+        self.send_event(&BuildEvent::PhaseFinished {
+            package: package_name,
+            phase,
+            error: result.as_ref().err().map(error_chain),
+        });
+        result
+    }
+
+    // Decodes the base64 PFX certificate of the `cert_from_env` environment
+    // variable into a file in the temp directory, which the packages are
+    // signed with. Neither the certificate nor its password is logged.
+    fn write_signing_pfx(&self) -> Result<(), BuildActionError> {
+        let Some(cert_env_var) = &self.cert_from_env else {
+            return Ok(());
+        };
+        let encoded = std::env::var(cert_env_var)
+            .map_err(|_| BuildActionError::MissingCertEnvVar(cert_env_var.clone()))?;
+        let pfx = signing_pfx::decode_base64(&encoded)
+            .map_err(|e| BuildActionError::InvalidCertEnvVar(cert_env_var.clone(), e))?;
+        let password = self
+            .cert_password_env
+            .as_ref()
+            .map(|password_env_var| {
+                std::env::var(password_env_var).map_err(|_| {
+                    BuildActionError::MissingCertPasswordEnvVar(password_env_var.clone())
+                })
+            })
+            .transpose()?;
+        let pfx_file_path = self
+            .temp_dir
+            .clone()
+            .unwrap_or_else(std::env::temp_dir)
+            .join(format!("cargo-wdk-{}.pfx", std::process::id()));
+        let signing_pfx = SigningPfx::write(self.fs, pfx_file_path, &pfx, password)?;
+        info!("Signing with the certificate of environment variable {cert_env_var}");
+        self.signing_pfx
+            .set(signing_pfx)
+            .expect("signing certificate is only written once per build");
+        Ok(())
+    }
+
+    // Creates the temp directory if needed and checks that files can be
+    // written to it, so that a non-writable directory fails the build before
+    // any of the packaging tools run
+    fn ensure_temp_dir_is_writable(&self, temp_dir: &Path) -> Result<(), BuildActionError> {
+        debug!(
+            "Checking temp directory is writable: {}",
+            temp_dir.display()
+        );
+        let probe_file_path = temp_dir.join(TEMP_DIR_PROBE_FILE_NAME);
+        self.fs
+            .create_dir_all(temp_dir)
+            .and_then(|()| self.fs.write_to_file(&probe_file_path, &[]))
+            .and_then(|()| self.fs.remove_file(&probe_file_path))
+            .map_err(|e| BuildActionError::TempDirNotWritable(temp_dir.to_owned(), e))
+    }
+
+    // Checks that the WDK has the headers of the KMDF and UMDF versions passed
+    // in `--kmdf-version` and `--umdf-version`
+    fn ensure_wdf_versions_are_available(&self) -> Result<(), BuildActionError> {
+        if let Some(version) = self.wdf_versions.kmdf {
+            let available_versions = self.wdk_build.detect_kmdf_versions()?;
+            ensure_wdf_version_is_available("KMDF", version, &available_versions)?;
+        }
+        if let Some(version) = self.wdf_versions.umdf {
+            let available_versions = self.wdk_build.detect_umdf_versions()?;
+            ensure_wdf_version_is_available("UMDF", version, &available_versions)?;
+        }
+        Ok(())
+    }
+
+    // Checks that the executables set for the packaging tools exist, so that
+    // a mistyped path fails the build before anything is compiled
+    fn ensure_tool_paths_are_executable(&self) -> Result<(), BuildActionError> {
+        for (tool, path) in self.tool_paths.overrides() {
+            if !self.fs.exists(path) {
+                return Err(BuildActionError::ToolPathNotFound(tool, path.to_owned()));
+            }
+            if !path
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("exe"))
+            {
+                return Err(BuildActionError::ToolPathNotExecutable(
+                    tool,
+                    path.to_owned(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Packages a driver binary built outside of cargo, e.g. by an MSBuild
+    // pipeline. Nothing is compiled and the .inx file named after the binary
+    // is read from the working directory. The INF must declare the KMDF
+    // version itself since there is no WDK metadata to read it from.
+    fn package_prebuilt_binary(&self, binary_path: &Path) -> Result<(), BuildActionError> {
+        if !self.fs.exists(binary_path) {
+            return Err(BuildActionError::PrebuiltBinaryNotFound(
+                binary_path.to_owned(),
+            ));
+        }
+        let (Some(package_name), Some(target_dir)) = (
+            binary_path
+                .file_stem()
+                .filter(|_| {
+                    binary_path
+                        .extension()
+                        .is_some_and(|extension| extension.eq_ignore_ascii_case("sys"))
+                })
+                .map(|file_stem| file_stem.to_string_lossy()),
+            binary_path.parent(),
+        ) else {
+            return Err(BuildActionError::UnsupportedPrebuiltBinary(
+                binary_path.to_owned(),
+            ));
+        };
+        let target_arch = self
+            .target_arch
+            .ok_or(BuildActionError::CannotDetectTargetArch)?;
+        info!(
+            "Packaging pre-built driver binary {}",
+            binary_path.display()
+        );
+
+        let package_task = PackageTask::new(
+            PackageTaskParams {
+                package_name: &package_name,
+                working_dir: &self.working_dir,
+                target_dir,
+                target_arch: &target_arch,
+                sign_mode: self
+                    .sign_mode_for_packaging(&package_name, &PackageSettings::default())?,
+                sample_class: self.is_sample_class,
+                driver_model: DriverConfig::Wdm,
+                target_platform: self.target_platform,
+                import_check: self.import_check,
+                os_version_check: self.os_version_check,
+                wdf_verifier: None,
+                warnings: &self.warnings,
+                tool_log: &self.tool_log,
+                debug_files: &[],
+                binary_name: None,
+                temp_dir: self.temp_dir.as_deref(),
+                tool_search_paths: &[],
+                tool_paths: &self.tool_paths,
+                include_pdb: true,
+                // Builds outside of cargo are not expected to produce the linker
+                // map at the path cargo does
+                include_map: false,
+                reproducible_driver_ver: None,
+                incremented_driver_ver: None,
+                min_version: self.min_version,
+                test_cert_validity: self.test_cert_validity,
+                signing_pfx: self.signing_pfx.get(),
+                prebuilt_binary_path: Some(binary_path),
+                multi_arch_target_dirs: &[],
+                relocatable: self.relocatable,
+                additional_inx_files: &[],
+                inf_catalog: InfCatalog::default(),
+                inf_format: InfFormat::default(),
+                overwrite_package: self.overwrite_package,
+                pdb_file_name: None,
+                emit_install_summary: self.emit_install_summary,
+                emit_catalog_signing_report: self.emit_catalog_signing_report,
+                emit_uninstall: self.emit_uninstall,
+                env: &[],
+                catalog_exclude: &[],
+                skip_stampinf: false,
+                binary_only: false,
+                symbols_archive_dir: None,
+                out_dir: None,
+                out_dir_files: &[],
+                manifest: None,
+                checked_target_dir: None,
+                allowed_providers: &[],
+            },
+            self.wdk_build,
+            self.command_exec,
+            self.fs,
+        );
+        self.send_event(&BuildEvent::PackageStarted {
+            package: &package_name,
+        });
+        let result = self.run_phase(&package_name, BuildPhase::Package, || package_task.run());
+        self.send_event(&BuildEvent::PackageFinished {
+            package: &package_name,
+            error: result.as_ref().err().map(error_chain),
+        });
+        let write_result = self.tool_log.write(self.fs, &package_name);
+        result?;
+        write_result?;
+        self.summary
+            .record_artifacts(package_task.dest_file_paths());
+
+        info!("Finished packaging {package_name}");
+        Ok(())
+    }
+
+    /// Returns the workspace members cargo builds when it is run from the
+    /// workspace root without `--package`, i.e. the members listed in
+    /// `workspace.default-members`. Cargo reports the root package as the only
+    /// default member of a workspace with a root package and no
+    /// `default-members`. All members are returned if cargo does not report
+    /// the default members, e.g. for cargo versions older than 1.71.
+    fn default_workspace_packages<'m>(
+        cargo_metadata: &CargoMetadata,
+        workspace_packages: Vec<&'m Package>,
+    ) -> Vec<&'m Package> {
+        let default_members = &cargo_metadata.workspace_default_members;
+        if !default_members.is_available() {
+            return workspace_packages;
+        }
+        let (default_packages, skipped_packages): (Vec<_>, Vec<_>) = workspace_packages
+            .into_iter()
+            .partition(|package| default_members.contains(&package.id));
+        for package in skipped_packages {
+            debug!(
+                "Skipping workspace member {} since it is not a default member",
+                package.name
+            );
+        }
+        default_packages
+    }
+
+    /// Checks that the WDK metadata of the workspace and of each of its
+    /// packages declares a single driver type, e.g. that a package copied from
+    /// a UMDF driver does not keep `umdf-version-major` next to
+    /// `driver-type = "KMDF"`. Such metadata fails to parse with an error that
+    /// does not name the package.
+    ///
+    /// # Errors
+    /// - `BuildActionError::ConflictingDriverTypes` - If the `driver-model`
+    ///   table of the workspace or of a package has keys of different driver
+    ///   types.
+    fn check_driver_type_declarations(
+        cargo_metadata: &CargoMetadata,
+    ) -> Result<(), BuildActionError> {
+        let declarations = std::iter::once((
+            "The workspace".to_string(),
+            &cargo_metadata.workspace_metadata,
+        ))
+        .chain(
+            cargo_metadata
+                .workspace_packages()
+                .into_iter()
+                .map(|package| (format!("Package {}", package.name), &package.metadata)),
+        );
+        for (declared_in, metadata_table) in declarations {
+            let keys = metadata::conflicting_driver_type_keys(metadata_table);
+            if !keys.is_empty() {
+                return Err(BuildActionError::ConflictingDriverTypes {
+                    declared_in,
+                    keys: keys.join(", "),
+                });
+            }
+        }
+        Ok(())
+    }
+
     // Runs build for the given working directory and the cargo metadata
-    fn run_from_workspace_root(&self, working_dir: &Path) -> Result<(), BuildActionError> {
+    fn run_from_workspace_root(
+        &self,
+        working_dir: &Path,
+        wdk_build_number: u32,
+    ) -> Result<(), BuildActionError> {
         let cargo_metadata = &self.get_cargo_metadata(working_dir)?;
+        Self::check_driver_type_declarations(cargo_metadata)?;
+        wdk_lock::apply(
+            self.fs,
+            self.wdk_lock_mode,
+            cargo_metadata.target_directory.as_std_path(),
+            wdk_build_number,
+        )?;
         let wdk_metadata = Wdk::try_from(cargo_metadata);
         let workspace_packages = cargo_metadata.workspace_packages();
+        // A virtual manifest without any members has nothing to build
+        if workspace_packages.is_empty() {
+            return Err(BuildActionError::NoRustPackagesFound(working_dir.to_path_buf()));
+        }
         let workspace_root =
             absolute(cargo_metadata.workspace_root.as_std_path()).map_err(|e| {
                 BuildActionError::NotAbsolute(cargo_metadata.workspace_root.clone().into(), e)
@@ -247,7 +1021,8 @@ fn run_from_workspace_root(&self, working_dir: &Path) -> Result<(), BuildActionE
                 working_dir.display()
             );
             let mut failed_atleast_one_workspace_member = false;
-            for package in workspace_packages {
+            let mut package_results = Vec::new();
+            for package in Self::default_workspace_packages(cargo_metadata, workspace_packages) {
                 let package_root_path: PathBuf = package
                     .manifest_path
                     .parent()
@@ -261,13 +1036,27 @@ fn run_from_workspace_root(&self, working_dir: &Path) -> Result<(), BuildActionE
                     package_root_path.display()
                 );
 
-                if let Err(e) = self.build_and_package(&package_root_path, &wdk_metadata, package) {
-                    failed_atleast_one_workspace_member = true;
-                    err!(
-                        "Error building the workspace member project: {}, error: {:?}",
-                        package_root_path.display(),
-                        anyhow::Error::new(e)
-                    );
+                match self.build_and_package(
+                    &package_root_path,
+                    cargo_metadata.target_directory.as_std_path(),
+                    &wdk_metadata,
+                    package,
+                    wdk_build_number,
+                ) {
+                    Ok(Some(package_result)) => package_results.push(package_result),
+                    Ok(None) => {}
+                    Err(e) => {
+                        failed_atleast_one_workspace_member = true;
+                        let error = format!("{:?}", anyhow::Error::new(e));
+                        err!(
+                            "Error building the workspace member project: {}, error: {error}",
+                            package_root_path.display(),
+                        );
+                        self.diagnostic_bundle.append_line(
+                            "errors.txt",
+                            &format!("{}: {error}", package_root_path.display()),
+                        );
+                    }
                 }
             }
             if let Err(e) = wdk_metadata {
@@ -282,6 +1071,11 @@ fn run_from_workspace_root(&self, working_dir: &Path) -> Result<(), BuildActionE
                     working_dir.to_owned(),
                 ));
             }
+
+            self.check_unique_device_ids(&package_results)?;
+            if self.emit_index {
+                self.emit_package_index(cargo_metadata, &package_results)?;
+            }
         } else {
             // If the working directory is a workspace member directory
             debug!(
@@ -303,7 +1097,13 @@ fn run_from_workspace_root(&self, working_dir: &Path) -> Result<(), BuildActionE
             let package = package
                 .ok_or_else(|| BuildActionError::NotAWorkspaceMember(working_dir.to_owned()))?;
 
-            self.build_and_package(working_dir, &wdk_metadata, package)?;
+            let package_result = self.build_and_package(
+                working_dir,
+                cargo_metadata.target_directory.as_std_path(),
+                &wdk_metadata,
+                package,
+                wdk_build_number,
+            )?;
 
             if let Err(e) = wdk_metadata {
                 // Ignore NoWdkConfigurationsDetected but propagate any other error
@@ -311,6 +1111,9 @@ fn run_from_workspace_root(&self, working_dir: &Path) -> Result<(), BuildActionE
                     return Err(BuildActionError::WdkMetadataParse(e));
                 }
             }
+            if self.emit_index {
+                self.emit_package_index(cargo_metadata, package_result.as_slice())?;
+            }
         }
 
         debug!(
@@ -338,42 +1141,909 @@ fn get_cargo_metadata(&self, working_dir: &Path) -> Result<CargoMetadata, BuildA
         Ok(cargo_metadata)
     }
 
-    // Method to perform the build and package tasks on the given package
+    // Method to perform the build and package tasks on the given package and
+    // write the output of the tools run for it to its log file, whether or not
+    // the tasks succeeded
     fn build_and_package(
+        &self,
+        working_dir: &Path,
+        target_directory: &Path,
+        wdk_metadata: &Result<Wdk, TryFromCargoMetadataError>,
+        package: &Package,
+        wdk_build_number: u32,
+    ) -> Result<Option<PackageResult>, BuildActionError> {
+        self.send_event(&BuildEvent::PackageStarted {
+            package: package.name.as_str(),
+        });
+        let result = self.run_build_and_package_tasks(
+            working_dir,
+            target_directory,
+            wdk_metadata,
+            package,
+            wdk_build_number,
+        );
+        self.send_event(&BuildEvent::PackageFinished {
+            package: package.name.as_str(),
+            error: result.as_ref().err().map(error_chain),
+        });
+        let write_result = self.tool_log.write(self.fs, package.name.as_str());
+        let package_result = result?;
+        write_result?;
+        Ok(package_result)
+    }
+
+    // Runs the build and package tasks on the given package
+    fn run_build_and_package_tasks(
+        &self,
+        working_dir: &Path,
+        target_directory: &Path,
+        wdk_metadata: &Result<Wdk, TryFromCargoMetadataError>,
+        package: &Package,
+        wdk_build_number: u32,
+    ) -> Result<Option<PackageResult>, BuildActionError> {
+        self.check_driver_type(wdk_metadata, package)?;
+        self.check_sanitizer(wdk_metadata, package)?;
+        self.check_wdk_dependencies(wdk_metadata, package)?;
+        self.check_supported_wdk(package, wdk_build_number)?;
+        let package_settings = Self::package_settings_for_packaging(package, self.profile)?;
+        Self::check_manifest(wdk_metadata, package, &package_settings)?;
+        self.add_package_to_diagnostic_bundle(package, &package_settings);
+        // Resolved before building so that a package that cannot be signed as
+        // requested fails without being built
+        let sign_mode = self.sign_mode_for_packaging(package.name.as_str(), &package_settings)?;
+        if self.multi_arch_package {
+            if self.package_checked_variant(&package_settings) {
+                return Err(BuildActionError::CheckedVariantOfMultiArchPackage(
+                    package.name.to_string(),
+                ));
+            }
+            return self.build_and_package_multi_arch(
+                working_dir,
+                wdk_metadata,
+                package,
+                &package_settings,
+                sign_mode,
+            );
+        }
+
+        let package_name = package.name.as_str();
+        let output_message_iter = if self.stamp_only {
+            info!("Stamping the INFs of package {package_name}");
+            None
+        } else {
+            info!("Building package {package_name}");
+            let Some(output_message_iter) = self.run_build_task(
+                working_dir,
+                package_name,
+                self.target_arch,
+                &package_settings,
+                None,
+            )?
+            else {
+                return Ok(None);
+            };
+
+            if self.no_package {
+                info!("Finished compiling {package_name}, skipping packaging");
+                return Ok(None);
+            }
+            Some(output_message_iter)
+        };
+
+        let Some(driver_model) = Self::driver_model_for_packaging(package, wdk_metadata) else {
+            return Ok(None);
+        };
+        let driver_model = self.wdf_versions.apply(driver_model)?;
+        let driver_type = DriverType::from(&driver_model);
+        let driver_target = Self::driver_target(package, &package_settings)?;
+
+        debug!("Creating the driver package in the target directory");
+        // Resolve the target architecture for the packaging task
+        let target_arch = if let Some(arch) = self.target_arch {
+            arch
+        } else {
+            self.get_target_arch_from_cargo_rustc(working_dir)?
+        };
+        debug!("Target architecture for package: {package_name} is: {target_arch}");
+        let (target_dir, out_dir) = match output_message_iter {
+            Some(output_message_iter) => {
+                Self::get_target_dir_from_output(package, driver_target, output_message_iter)?
+            }
+            None => (self.profile_target_dir(target_directory), None),
+        };
+        // Stamped and binary-only packages do not include the files
+        if !self.stamp_only && !self.binary_only {
+            Self::check_out_dir(package_name, &package_settings, out_dir.as_deref())?;
+        }
+        debug!(
+            "Target directory for package: {} is: {}",
+            package_name,
+            target_dir.display()
+        );
+
+        let incremented_driver_ver =
+            self.next_driver_ver_version(package, &package_settings, &target_dir)?;
+        let package_task_params = PackageTaskParams {
+            package_name,
+            working_dir,
+            target_dir: &target_dir,
+            target_arch: &target_arch,
+            sign_mode,
+            sample_class: self.is_sample_class,
+            driver_model,
+            target_platform: self.target_platform,
+            import_check: self.import_check,
+            os_version_check: self.os_version_check,
+            wdf_verifier: package_settings.wdf_verifier,
+            warnings: &self.warnings,
+            tool_log: &self.tool_log,
+            debug_files: &package_settings.debug_files,
+            binary_name: package_settings.binary_name.as_deref(),
+            temp_dir: self.temp_dir.as_deref(),
+            tool_search_paths: &package_settings.tool_search_paths,
+            tool_paths: &self.tool_paths,
+            include_pdb: package_settings.include_pdb.unwrap_or(true),
+            include_map: self
+                .include_map
+                .or(package_settings.include_map)
+                .unwrap_or(true),
+            reproducible_driver_ver: self
+                .source_date_epoch
+                .map(|source_date_epoch| DriverVer::new(source_date_epoch, &package.version)),
+            incremented_driver_ver: incremented_driver_ver.as_deref(),
+            min_version: self.min_version,
+            test_cert_validity: self.test_cert_validity,
+            signing_pfx: self.signing_pfx.get(),
+            prebuilt_binary_path: None,
+            multi_arch_target_dirs: &[],
+            relocatable: self.relocatable,
+            additional_inx_files: &package_settings.inf_files,
+            inf_catalog: package_settings.inf_catalog.unwrap_or_default(),
+            inf_format: package_settings.inf_format.unwrap_or_default(),
+            overwrite_package: self.overwrite_package,
+            pdb_file_name: self.packaged_pdb_file_name(),
+            emit_install_summary: self.emit_install_summary,
+            emit_catalog_signing_report: self.emit_catalog_signing_report,
+            emit_uninstall: self.emit_uninstall,
+            env: &package_settings.env,
+            catalog_exclude: &package_settings.catalog_exclude,
+            skip_stampinf: package_settings.skip_stampinf.unwrap_or_default(),
+            binary_only: self.binary_only,
+            symbols_archive_dir: self.symbols_archive_dir.as_deref(),
+            out_dir: out_dir.as_deref(),
+            out_dir_files: &package_settings.out_dir_files,
+            manifest: package_settings.manifest.as_deref(),
+            checked_target_dir: None,
+            allowed_providers: &package_settings.allowed_providers,
+        };
+        let package_task = PackageTask::new(
+            package_task_params.clone(),
+            self.wdk_build,
+            self.command_exec,
+            self.fs,
+        );
+        if self.stamp_only {
+            // The version is not recorded, so that the next build of the
+            // package is stamped with the same version
+            self.run_phase(package_name, BuildPhase::Package, || {
+                package_task.run_stamp_only()
+            })?;
+            info!("Finished stamping {package_name}");
+            return Ok(None);
+        }
+        self.run_phase(package_name, BuildPhase::Package, || package_task.run())?;
+        if self.package_checked_variant(&package_settings) {
+            self.build_and_package_checked(
+                target_directory,
+                package,
+                driver_target,
+                &package_settings,
+                package_task_params,
+            )?;
+        }
+        if self.binary_only {
+            // Without an INF, the version is not stamped and the package has no
+            // device IDs to check against the other packages
+            self.summary
+                .record_artifacts(package_task.dest_file_paths());
+            info!("Finished building {package_name}");
+            return Ok(None);
+        }
+        if let Some(version) = &incremented_driver_ver {
+            driver_ver_counter::record_driver_ver_version(
+                self.fs,
+                &target_dir,
+                package_name,
+                version,
+            )?;
+        }
+        self.summary
+            .record_artifacts(package_task.dest_file_paths());
+        if self.emit_nupkg {
+            let nuspec_path = self.emit_nuspec(package, package_task.dest_root_package_folder())?;
+            self.summary.record_artifacts([nuspec_path]);
+        }
+
+        info!("Finished building {package_name}");
+        Ok(Some(PackageResult {
+            package_name: package_name.to_string(),
+            driver_type,
+            target_archs: vec![target_arch],
+            version: package.version.to_string(),
+            package_folder: package_task.dest_root_package_folder().to_path_buf(),
+            inf_file_path: package_task.dest_inf_file_path().to_path_buf(),
+        }))
+    }
+
+    // Builds the checked variant of the package, with the `checked` cfg and
+    // runtime assertions, and packages it with the same settings in
+    // `<name>_package_checked` next to the package. The checked variant is
+    // built in a target directory of its own, so that its binaries do not
+    // replace those of the normal build.
+    fn build_and_package_checked(
+        &self,
+        target_directory: &Path,
+        package: &Package,
+        driver_target: Option<&str>,
+        package_settings: &PackageSettings,
+        package_task_params: PackageTaskParams<'_>,
+    ) -> Result<(), BuildActionError> {
+        let package_name = package.name.as_str();
+        info!("Building the checked variant of package {package_name}");
+        let checked_target_directory = target_directory.join(CHECKED_TARGET_DIR_NAME);
+        let Some(output_message_iter) = self.run_build_task(
+            package_task_params.working_dir,
+            package_name,
+            self.target_arch,
+            package_settings,
+            Some(&checked_target_directory),
+        )?
+        else {
+            return Ok(());
+        };
+        let (checked_target_dir, out_dir) =
+            Self::get_target_dir_from_output(package, driver_target, output_message_iter)?;
+        if !self.binary_only {
+            Self::check_out_dir(package_name, package_settings, out_dir.as_deref())?;
+        }
+        let package_task = PackageTask::new(
+            PackageTaskParams {
+                out_dir: out_dir.as_deref(),
+                checked_target_dir: Some(&checked_target_dir),
+                ..package_task_params
+            },
+            self.wdk_build,
+            self.command_exec,
+            self.fs,
+        );
+        self.run_phase(package_name, BuildPhase::Package, || package_task.run())?;
+        self.summary
+            .record_artifacts(package_task.dest_file_paths());
+        info!("Finished building the checked variant of {package_name}");
+        Ok(())
+    }
+
+    // Method to build the given package for every architecture in
+    // `MULTI_ARCH_PACKAGE_ARCHITECTURES` and package the binaries in a single
+    // package with one catalog file covering all of them
+    fn build_and_package_multi_arch(
         &self,
         working_dir: &Path,
         wdk_metadata: &Result<Wdk, TryFromCargoMetadataError>,
         package: &Package,
+        package_settings: &PackageSettings,
+        sign_mode: SignMode,
+    ) -> Result<Option<PackageResult>, BuildActionError> {
+        let package_name = package.name.as_str();
+        info!("Building package {package_name} for a multi-architecture package");
+
+        let mut output_message_iters = Vec::new();
+        for arch in MULTI_ARCH_PACKAGE_ARCHITECTURES {
+            debug!("Building package: {package_name} for architecture: {arch}");
+            let Some(output_message_iter) = self.run_build_task(
+                working_dir,
+                package_name,
+                Some(arch),
+                package_settings,
+                None,
+            )?
+            else {
+                continue;
+            };
+            output_message_iters.push((arch, output_message_iter));
+        }
+        if output_message_iters.is_empty() {
+            return Ok(None);
+        }
+
+        if self.no_package {
+            info!("Finished compiling {package_name} for all architectures, skipping packaging");
+            return Ok(None);
+        }
+
+        let Some(driver_model) = Self::driver_model_for_packaging(package, wdk_metadata) else {
+            return Ok(None);
+        };
+        let driver_model = self.wdf_versions.apply(driver_model)?;
+        let driver_type = DriverType::from(&driver_model);
+        let driver_target = Self::driver_target(package, package_settings)?;
+
+        let mut multi_arch_target_dirs = Vec::new();
+        // The files generated by the build script are taken from the build of
+        // the first architecture
+        let mut first_arch_out_dir = None;
+        for (arch, output_message_iter) in output_message_iters {
+            let (target_dir, out_dir) =
+                Self::get_target_dir_from_output(package, driver_target, output_message_iter)?;
+            if multi_arch_target_dirs.is_empty() {
+                first_arch_out_dir = out_dir;
+            }
+            debug!(
+                "Target directory for package: {} and architecture: {} is: {}",
+                package_name,
+                arch,
+                target_dir.display()
+            );
+            multi_arch_target_dirs.push((arch, target_dir));
+        }
+
+        // Architecture specific target directories are of the form
+        // `<target>/<triple>/<profile>`. The multi-architecture package is
+        // placed in `<target>/<profile>`.
+        let target_archs = multi_arch_target_dirs
+            .iter()
+            .map(|(arch, _)| *arch)
+            .collect::<Vec<_>>();
+        let (_, first_arch_target_dir) = &multi_arch_target_dirs[0];
+        Self::check_out_dir(package_name, package_settings, first_arch_out_dir.as_deref())?;
+        let combined_target_dir = first_arch_target_dir
+            .parent()
+            .and_then(Path::parent)
+            .zip(first_arch_target_dir.file_name())
+            .map(|(target_root, profile_dir)| target_root.join(profile_dir))
+            .ok_or_else(|| {
+                BuildActionError::CannotDetermineTargetDir(format!(
+                    "Cannot determine multi-architecture package directory from {}",
+                    first_arch_target_dir.display()
+                ))
+            })?;
+        debug!(
+            "Target directory for multi-architecture package: {} is: {}",
+            package_name,
+            combined_target_dir.display()
+        );
+
+        let incremented_driver_ver =
+            self.next_driver_ver_version(package, package_settings, &combined_target_dir)?;
+        let package_task = PackageTask::new(
+            PackageTaskParams {
+                package_name,
+                working_dir,
+                target_dir: &combined_target_dir,
+                target_arch: &MULTI_ARCH_PACKAGE_ARCHITECTURES[0],
+                sign_mode,
+                sample_class: self.is_sample_class,
+                driver_model,
+                target_platform: self.target_platform,
+                import_check: self.import_check,
+                os_version_check: self.os_version_check,
+                wdf_verifier: package_settings.wdf_verifier,
+                warnings: &self.warnings,
+                tool_log: &self.tool_log,
+                debug_files: &package_settings.debug_files,
+                binary_name: package_settings.binary_name.as_deref(),
+                temp_dir: self.temp_dir.as_deref(),
+                tool_search_paths: &package_settings.tool_search_paths,
+                tool_paths: &self.tool_paths,
+                include_pdb: package_settings.include_pdb.unwrap_or(true),
+                include_map: self
+                    .include_map
+                    .or(package_settings.include_map)
+                    .unwrap_or(true),
+                reproducible_driver_ver: self
+                    .source_date_epoch
+                    .map(|source_date_epoch| DriverVer::new(source_date_epoch, &package.version)),
+                incremented_driver_ver: incremented_driver_ver.as_deref(),
+                min_version: self.min_version,
+                test_cert_validity: self.test_cert_validity,
+                signing_pfx: self.signing_pfx.get(),
+                prebuilt_binary_path: None,
+                multi_arch_target_dirs: &multi_arch_target_dirs,
+                relocatable: self.relocatable,
+                additional_inx_files: &package_settings.inf_files,
+                inf_catalog: package_settings.inf_catalog.unwrap_or_default(),
+                inf_format: package_settings.inf_format.unwrap_or_default(),
+                overwrite_package: self.overwrite_package,
+                pdb_file_name: self.packaged_pdb_file_name(),
+                emit_install_summary: self.emit_install_summary,
+                emit_catalog_signing_report: self.emit_catalog_signing_report,
+                emit_uninstall: self.emit_uninstall,
+                env: &package_settings.env,
+                catalog_exclude: &package_settings.catalog_exclude,
+                skip_stampinf: package_settings.skip_stampinf.unwrap_or_default(),
+                binary_only: false,
+                symbols_archive_dir: self.symbols_archive_dir.as_deref(),
+                out_dir: first_arch_out_dir.as_deref(),
+                out_dir_files: &package_settings.out_dir_files,
+                manifest: package_settings.manifest.as_deref(),
+                checked_target_dir: None,
+                allowed_providers: &package_settings.allowed_providers,
+            },
+            self.wdk_build,
+            self.command_exec,
+            self.fs,
+        );
+        self.run_phase(package_name, BuildPhase::Package, || package_task.run())?;
+        if let Some(version) = &incremented_driver_ver {
+            driver_ver_counter::record_driver_ver_version(
+                self.fs,
+                &combined_target_dir,
+                package_name,
+                version,
+            )?;
+        }
+        self.summary
+            .record_artifacts(package_task.dest_file_paths());
+        if self.emit_nupkg {
+            let nuspec_path = self.emit_nuspec(package, package_task.dest_root_package_folder())?;
+            self.summary.record_artifacts([nuspec_path]);
+        }
+
+        info!("Finished building multi-architecture package {package_name}");
+        Ok(Some(PackageResult {
+            package_name: package_name.to_string(),
+            driver_type,
+            target_archs,
+            version: package.version.to_string(),
+            package_folder: package_task.dest_root_package_folder().to_path_buf(),
+            inf_file_path: package_task.dest_inf_file_path().to_path_buf(),
+        }))
+    }
+
+    // Checks that no two of the packaged drivers of a workspace claim the same
+    // hardware or compatible ID in their INF files
+    fn check_unique_device_ids(
+        &self,
+        package_results: &[PackageResult],
     ) -> Result<(), BuildActionError> {
+        if package_results.len() < 2 {
+            return Ok(());
+        }
+        let mut package_device_ids = Vec::new();
+        for PackageResult {
+            package_name,
+            inf_file_path,
+            ..
+        } in package_results
+        {
+            let inf_content = self.fs.read_file_to_string(inf_file_path)?;
+            let device_ids = InfFile::parse(&inf_content).device_ids();
+            debug!("Device IDs of package {package_name}: {device_ids:?}");
+            package_device_ids.push((package_name.clone(), device_ids));
+        }
+        if let Some(duplicate) = pnp_ids::find_duplicate_device_id(&package_device_ids) {
+            return Err(BuildActionError::DuplicateDeviceId {
+                device_id: duplicate.device_id,
+                first_package_name: duplicate.first_package_name,
+                second_package_name: duplicate.second_package_name,
+            });
+        }
+        Ok(())
+    }
+
+    // Writes the index of the driver packages produced by the build to the
+    // target directory and records it as an artifact of the build
+    fn emit_package_index(
+        &self,
+        cargo_metadata: &CargoMetadata,
+        package_results: &[PackageResult],
+    ) -> Result<(), BuildActionError> {
+        let index_file_path = package_index::write_package_index(
+            self.fs,
+            cargo_metadata.target_directory.as_std_path(),
+            package_results,
+        )?;
+        self.summary.record_artifacts([index_file_path]);
+        Ok(())
+    }
+
+    // Writes the NuGet manifest of the driver package of the given package
+    // from its cargo metadata and returns its path
+    fn emit_nuspec(
+        &self,
+        package: &Package,
+        package_folder: &Path,
+    ) -> Result<PathBuf, BuildActionError> {
+        let metadata = NuspecMetadata {
+            id: package.name.as_str(),
+            version: package.version.to_string(),
+            authors: if package.authors.is_empty() {
+                package.name.to_string()
+            } else {
+                package.authors.join(", ")
+            },
+            description: package
+                .description
+                .clone()
+                .unwrap_or_else(|| format!("{} driver package", package.name)),
+        };
+        let nuspec_path = nuspec::write_nuspec(self.fs, &metadata, package_folder)?;
+        info!("Wrote NuGet manifest {}", nuspec_path.display());
+        Ok(nuspec_path)
+    }
+
+    // Adds the metadata of the package and its resolved packaging settings to
+    // the diagnostic bundle
+    fn add_package_to_diagnostic_bundle(
+        &self,
+        package: &Package,
+        package_settings: &PackageSettings,
+    ) {
+        if !self.diagnostic_bundle.is_enabled() {
+            return;
+        }
         let package_name = package.name.as_str();
-        info!("Building package {package_name}");
+        self.diagnostic_bundle.add_file(
+            &format!("packages/{package_name}/metadata.json"),
+            serde_json::to_string_pretty(&package.metadata).expect("package metadata is JSON"),
+        );
+        self.diagnostic_bundle.add_file(
+            &format!("packages/{package_name}/settings.txt"),
+            format!("{package_settings:#?}\n"),
+        );
+    }
+
+    // Resolves the packaging settings of the package for the profile being
+    // built
+    fn package_settings_for_packaging(
+        package: &Package,
+        profile: Option<&Profile>,
+    ) -> Result<PackageSettings, BuildActionError> {
+        let package_settings = PackageSettings::from_package_metadata(&package.metadata, profile)?;
+        debug!(
+            "Packaging settings for package: {} are: {:?}",
+            package.name, package_settings
+        );
+        Ok(package_settings)
+    }
+
+    // Returns the next `DriverVer` version of a package that enables the
+    // `auto-increment-driver-ver` setting. Reproducible packages are stamped
+    // with the crate version instead, so their version is not incremented.
+    fn next_driver_ver_version(
+        &self,
+        package: &Package,
+        package_settings: &PackageSettings,
+        target_dir: &Path,
+    ) -> Result<Option<String>, BuildActionError> {
+        if self.source_date_epoch.is_some()
+            || package_settings.auto_increment_driver_ver != Some(true)
+        {
+            return Ok(None);
+        }
+        driver_ver_counter::next_driver_ver_version(
+            self.fs,
+            target_dir,
+            &package.name,
+            &package.version,
+        )
+        .map(Some)
+    }
+
+    // Resolves the signing mode of a package. An explicit signing mode takes
+    // precedence over the packaging setting of the profile. If signature
+    // verification is requested, the package must be signed, since verifying
+    // a package that is not signed would pass without checking anything.
+    fn sign_mode_for_packaging(
+        &self,
+        package_name: &str,
+        package_settings: &PackageSettings,
+    ) -> Result<SignMode, BuildActionError> {
+        let sign_mode = self
+            .sign_mode
+            .or(package_settings.sign_mode)
+            .unwrap_or(SignMode::Test {
+                verify_signature: false,
+            });
+        match sign_mode {
+            SignMode::Off if self.verify_signature => {
+                Err(BuildActionError::VerifySignatureWithoutSigning {
+                    package_name: package_name.to_string(),
+                    disabled_by: if self.sign_mode.is_some() {
+                        "`--sign-mode=off`".to_string()
+                    } else {
+                        format!(
+                            "the `sign-mode` packaging setting of the {} profile",
+                            self.profile.unwrap_or(&Profile::Dev)
+                        )
+                    },
+                })
+            }
+            SignMode::Off => Ok(SignMode::Off),
+            SignMode::Test { verify_signature } => Ok(SignMode::Test {
+                verify_signature: verify_signature || self.verify_signature,
+            }),
+        }
+    }
+
+    // Returns the file name of the PDB the driver binaries reference if
+    // `/PDBALTPATH` is set to the path of a `.pdb` file, which the PDB is
+    // packaged under so that debuggers find it. Linker macros such as `%_PDB%`
+    // keep the name of the PDB.
+    fn packaged_pdb_file_name(&self) -> Option<&'a str> {
+        self.pdb_altpath
+            .and_then(|pdb_altpath| pdb_altpath.rsplit(['\\', '/']).next())
+            .filter(|file_name| {
+                !file_name.contains('%')
+                    && Path::new(file_name)
+                        .extension()
+                        .is_some_and(|extension| extension.eq_ignore_ascii_case("pdb"))
+            })
+    }
+
+    // Resolves whether `cargo clippy` is run for a package with the given
+    // packaging settings. The `--clippy` flag enables it for every package, the
+    // `run-clippy` packaging setting for a single one.
+    fn run_clippy_for_package(&self, package_settings: &PackageSettings) -> bool {
+        self.clippy || package_settings.run_clippy.unwrap_or(false)
+    }
+
+    // Resolves whether the checked variant of a package with the given
+    // packaging settings is built and packaged. The `--checked` flag enables
+    // it for every package, the `checked` packaging setting for a single one.
+    fn package_checked_variant(&self, package_settings: &PackageSettings) -> bool {
+        self.checked || package_settings.checked.unwrap_or(false)
+    }
 
+    // Runs `cargo build`, preceded by the `pre-build` command of the package
+    // unless hooks are skipped and by `cargo clippy` if the package requests
+    // it, for the given package, or its checked variant built in
+    // `checked_target_dir` if set, with its environment and returns the parsed
+    // cargo messages of the build. The cargo invocations are printed first if
+    // requested, and `None` is returned without running them if they must
+    // only be printed.
+    fn run_build_task(
+        &self,
+        working_dir: &Path,
+        package_name: &str,
+        target_arch: Option<CpuArchitecture>,
+        package_settings: &PackageSettings,
+        checked_target_dir: Option<&Path>,
+    ) -> Result<Option<impl Iterator<Item = Result<Message, std::io::Error>>>, BuildActionError>
+    {
+        let target_arch = self.sanitizer_target_arch(working_dir, target_arch)?;
         let build_task = BuildTask::new(
             BuildTaskParams {
                 package_name,
                 working_dir,
                 profile: self.profile,
-                target_arch: self.target_arch,
+                target_arch,
                 locked: self.locked,
+                cargo_jobs: self.cargo_jobs,
+                profile_overrides: self.profile_overrides,
+                deny_warnings: self.deny_compile_warnings,
+                pdb_altpath: self.pdb_altpath,
+                wdf_versions: self.wdf_versions,
+                env: &package_settings.env,
                 features: self.features,
+                tool_log: &self.tool_log,
                 verbosity_level: self.verbosity_level,
+                color: self.color,
+                sanitizer: self.sanitizer,
+                checked_target_dir,
             },
             self.command_exec,
         );
-        let output_message_iter = build_task.run()?;
+        let clippy = self.run_clippy_for_package(package_settings);
+        if let Some(print_cargo_command) = self.print_cargo_command {
+            println!("{}", build_task.cargo_command_lines(clippy)?);
+            if print_cargo_command == PrintCargoCommand::Only {
+                info!("Skipping the build of {package_name}");
+                return Ok(None);
+            }
+        }
+        let output_message_iter = self.run_phase(package_name, BuildPhase::Build, || {
+            if !package_settings.pre_build.is_empty() {
+                if self.no_hooks {
+                    info!("Skipping the pre-build command of {package_name}");
+                } else {
+                    info!("Running the pre-build command of {package_name}");
+                    build_task.run_pre_build(&package_settings.pre_build)?;
+                }
+            }
+            if clippy {
+                info!("Running clippy on {package_name}");
+                build_task.run_clippy()?;
+            }
+            build_task.run()
+        })?;
+        Ok(Some(output_message_iter))
+    }
+
+    // Logs the driver type of a driver package resolved from the WDK metadata
+    // and checks it against the driver type passed in `--driver-type`, if any.
+    // Packages without valid WDK metadata are left to the packaging step.
+    fn check_driver_type(
+        &self,
+        wdk_metadata: &Result<Wdk, TryFromCargoMetadataError>,
+        package: &Package,
+    ) -> Result<(), BuildActionError> {
+        let Ok(wdk_metadata) = wdk_metadata else {
+            return Ok(());
+        };
+        if package.metadata.get("wdk").is_none() {
+            return Ok(());
+        }
+        let driver_type = DriverType::from(&wdk_metadata.driver_model);
+        info!("Driver type of package {} is {driver_type}", package.name);
+        match self.driver_type {
+            Some(expected) if expected != driver_type => {
+                Err(BuildActionError::DriverTypeMismatch {
+                    package_name: package.name.to_string(),
+                    expected,
+                    found: driver_type,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Checks that a sanitizer is only applied to user-mode code. Kernel-mode
+    // drivers run without the user-mode runtime that reports the errors found
+    // by the instrumentation, so they would fail to link or load.
+    fn check_sanitizer(
+        &self,
+        wdk_metadata: &Result<Wdk, TryFromCargoMetadataError>,
+        package: &Package,
+    ) -> Result<(), BuildActionError> {
+        let (Some(sanitizer), Ok(wdk_metadata)) = (self.sanitizer, wdk_metadata) else {
+            return Ok(());
+        };
+        if package.metadata.get("wdk").is_none() {
+            return Ok(());
+        }
+        let driver_type = DriverType::from(&wdk_metadata.driver_model);
+        if driver_type == DriverType::Umdf {
+            return Ok(());
+        }
+        Err(BuildActionError::SanitizerForKernelModeDriver {
+            package_name: package.name.to_string(),
+            driver_type,
+            sanitizer,
+        })
+    }
+
+    // Checks that a manifest is only embedded into the binaries of user-mode
+    // drivers. Kernel-mode drivers are not loaded by the loader that reads
+    // application manifests.
+    fn check_manifest(
+        wdk_metadata: &Result<Wdk, TryFromCargoMetadataError>,
+        package: &Package,
+        package_settings: &PackageSettings,
+    ) -> Result<(), BuildActionError> {
+        let (Some(_), Ok(wdk_metadata)) = (&package_settings.manifest, wdk_metadata) else {
+            return Ok(());
+        };
+        if package.metadata.get("wdk").is_none() {
+            return Ok(());
+        }
+        let driver_type = DriverType::from(&wdk_metadata.driver_model);
+        if driver_type == DriverType::Umdf {
+            return Ok(());
+        }
+        Err(BuildActionError::ManifestForKernelModeDriver {
+            package_name: package.name.to_string(),
+            driver_type,
+        })
+    }
+
+    /// Returns the architecture the package is built for with a sanitizer.
+    /// The architecture of the host toolchain is detected if none is given,
+    /// so that `--target` is passed to cargo and the sanitizer is not applied
+    /// to build scripts and proc-macros, which run on the host. Without a
+    /// sanitizer, the given architecture is returned as is.
+    ///
+    /// # Errors
+    /// - `BuildActionError::SanitizerUnsupportedArch` - If the sanitizer is
+    ///   not supported for the architecture. rustc supports AddressSanitizer
+    ///   only for `x86_64-pc-windows-msvc` among the Windows targets.
+    fn sanitizer_target_arch(
+        &self,
+        working_dir: &Path,
+        target_arch: Option<CpuArchitecture>,
+    ) -> Result<Option<CpuArchitecture>, BuildActionError> {
+        let Some(sanitizer) = self.sanitizer else {
+            return Ok(target_arch);
+        };
+        let target_arch = match target_arch {
+            Some(target_arch) => target_arch,
+            None => self.get_target_arch_from_cargo_rustc(working_dir)?,
+        };
+        if target_arch != CpuArchitecture::Amd64 {
+            return Err(BuildActionError::SanitizerUnsupportedArch(
+                sanitizer,
+                target_arch,
+            ));
+        }
+        Ok(Some(target_arch))
+    }
+
+    // Checks that a driver package depends on the wdk crates its driver type
+    // needs. Dependencies that are usually needed but may be replaced by the
+    // driver are reported as warnings.
+    fn check_wdk_dependencies(
+        &self,
+        wdk_metadata: &Result<Wdk, TryFromCargoMetadataError>,
+        package: &Package,
+    ) -> Result<(), BuildActionError> {
+        let Ok(wdk_metadata) = wdk_metadata else {
+            return Ok(());
+        };
+        if package.metadata.get("wdk").is_none() {
+            return Ok(());
+        }
+        let driver_type = DriverType::from(&wdk_metadata.driver_model);
+        let problems = wdk_dependencies::check(package, driver_type);
+        for warning in problems.warnings {
+            self.warnings
+                .record(package.name.as_str(), "wdk-dependency-check", warning);
+        }
+        if problems.errors.is_empty() {
+            return Ok(());
+        }
+        Err(BuildActionError::IncorrectWdkDependencies {
+            package_name: package.name.to_string(),
+            driver_type,
+            fixes: problems
+                .errors
+                .iter()
+                .map(|fix| format!("  - {fix}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        })
+    }
+
+    // Checks that the detected WDK is in the `supported-wdk` range of a driver
+    // package, if it has one
+    fn check_supported_wdk(
+        &self,
+        package: &Package,
+        wdk_build_number: u32,
+    ) -> Result<(), BuildActionError> {
+        if package.metadata.get("wdk").is_none() {
+            return Ok(());
+        }
+        let package_settings = Self::package_settings_for_packaging(package, self.profile)?;
+        let Some(supported_wdk) = package_settings.supported_wdk else {
+            return Ok(());
+        };
+        supported_wdk::check(
+            package.name.as_str(),
+            supported_wdk,
+            wdk_build_number,
+            self.allow_unsupported_wdk,
+        )
+    }
 
+    // Returns the driver model of the package if it must be packaged, i.e. it
+    // is a driver package with valid WDK metadata and a cdylib target
+    fn driver_model_for_packaging(
+        package: &Package,
+        wdk_metadata: &Result<Wdk, TryFromCargoMetadataError>,
+    ) -> Option<DriverConfig> {
+        let package_name = package.name.as_str();
         let wdk_metadata = if let Ok(wdk_metadata) = wdk_metadata {
             debug!("Found wdk metadata in package: {}", package_name);
             wdk_metadata
         } else {
             debug!("Invalid WDK metadata. Skipping package task");
-            return Ok(());
+            return None;
         };
 
         // Identifying non driver packages
         if package.metadata.get("wdk").is_none() {
             debug!("Packaging task skipped for non-driver package");
-            return Ok(());
+            return None;
         }
 
         if !package
@@ -382,53 +2052,75 @@ fn build_and_package(
             .any(|t| t.kind.contains(&TargetKind::CDyLib))
         {
             warn!("No cdylib target found. Skipping package task");
-            return Ok(());
+            return None;
         }
 
-        debug!("Creating the driver package in the target directory");
-        let driver_model = wdk_metadata.driver_model.clone();
-        // Resolve the target architecture for the packaging task
-        let target_arch = if let Some(arch) = self.target_arch {
-            arch
-        } else {
-            self.get_target_arch_from_cargo_rustc(working_dir)?
-        };
-        debug!("Target architecture for package: {package_name} is: {target_arch}");
-        let target_dir = Self::get_target_dir_from_output(package, output_message_iter)?;
-        debug!(
-            "Target directory for package: {} is: {}",
-            package_name,
-            target_dir.display()
-        );
+        Some(wdk_metadata.driver_model.clone())
+    }
 
-        PackageTask::new(
-            PackageTaskParams {
-                package_name,
-                working_dir,
-                target_dir: &target_dir,
-                target_arch: &target_arch,
-                sign_mode: self.sign_mode,
-                sample_class: self.is_sample_class,
-                driver_model,
-                target_platform: self.target_platform,
-            },
-            self.wdk_build,
-            self.command_exec,
-            self.fs,
-        )
-        .run()?;
+    /// Returns the name of the cdylib target packaged as the driver if the
+    /// `driver-target` packaging setting selects one, or `None` to package the
+    /// cdylib target of the package.
+    ///
+    /// # Errors
+    /// - `BuildActionError::DriverTargetNotFound` - If the package has no
+    ///   cdylib target of the given name.
+    fn driver_target<'p>(
+        package: &'p Package,
+        package_settings: &PackageSettings,
+    ) -> Result<Option<&'p str>, BuildActionError> {
+        let Some(target_name) = package_settings.driver_target.as_deref() else {
+            return Ok(None);
+        };
+        package
+            .targets
+            .iter()
+            .find(|t| t.name == target_name && t.kind.contains(&TargetKind::CDyLib))
+            .map(|t| Some(t.name.as_str()))
+            .ok_or_else(|| BuildActionError::DriverTargetNotFound {
+                package_name: package.name.to_string(),
+                target_name: target_name.to_string(),
+            })
+    }
 
-        info!("Finished building {package_name}");
+    /// Checks that a package whose `out-dir-files` packaging setting lists
+    /// files generated by its build script has an `OUT_DIR` to take them from
+    ///
+    /// # Errors
+    /// - `BuildActionError::MissingOutDir` - If the package lists files but
+    ///   cargo did not report the execution of its build script.
+    fn check_out_dir(
+        package_name: &str,
+        package_settings: &PackageSettings,
+        out_dir: Option<&Path>,
+    ) -> Result<(), BuildActionError> {
+        if out_dir.is_none() && !package_settings.out_dir_files.is_empty() {
+            return Err(BuildActionError::MissingOutDir(package_name.to_string()));
+        }
         Ok(())
     }
 
+    /// Returns the directory cargo emits the binaries of the profile to, for
+    /// packaging steps that run without building the package
+    fn profile_target_dir(&self, target_directory: &Path) -> PathBuf {
+        let mut target_dir = target_directory.to_owned();
+        if let Some(target_arch) = self.target_arch {
+            target_dir.push(to_target_triple(target_arch));
+        }
+        target_dir.push(self.profile.map_or("debug", Profile::target_dir_name));
+        target_dir
+    }
+
     /// Determines the target directory (i.e. path where binaries are emitted)
     /// for a cdylib package by scanning the output of the
     /// `cargo build --message-format json` command.
     ///
-    /// Works by locating the cdylib artifact matching the package, finding
-    /// the DLL file in it, and returning the DLL's parent folder as an
-    /// absolute path.
+    /// Works by locating the cdylib artifact matching the package, and the
+    /// driver target if one is given, finding the DLL file in it, and
+    /// returning the DLL's parent folder as an absolute path. The `OUT_DIR` of
+    /// the build script of the package is returned with it, or `None` if the
+    /// package has no build script. Cargo reports the execution of the build
+    /// script before the artifacts of the package.
     ///
     /// # Errors
     /// - `BuildActionError::CannotDetermineTargetDir` - If:
@@ -437,11 +2129,19 @@ fn build_and_package(
     ///   - a cargo message could not be parsed.
     fn get_target_dir_from_output(
         package: &Package,
+        driver_target: Option<&str>,
         cargo_build_output: impl Iterator<Item = Result<Message, std::io::Error>>,
-    ) -> Result<PathBuf, BuildActionError> {
+    ) -> Result<(PathBuf, Option<PathBuf>), BuildActionError> {
+        let mut out_dir = None;
         for message in cargo_build_output {
             let artifact = match message {
                 Ok(Message::CompilerArtifact(artifact)) => artifact,
+                Ok(Message::BuildScriptExecuted(script)) => {
+                    if script.package_id == package.id {
+                        out_dir = Some(script.out_dir.into_std_path_buf());
+                    }
+                    continue;
+                }
                 Ok(_) => continue,
                 Err(err) => {
                     return Err(BuildActionError::CannotDetermineTargetDir(format!(
@@ -450,7 +2150,8 @@ fn get_target_dir_from_output(
                 }
             };
 
-            let package_matches = artifact.package_id == package.id;
+            let package_matches = artifact.package_id == package.id
+                && driver_target.is_none_or(|name| artifact.target.name == name);
             let is_cdylib = artifact.target.crate_types.contains(&CrateType::CDyLib)
                 && artifact.target.kind.contains(&TargetKind::CDyLib);
 
@@ -493,7 +2194,7 @@ fn get_target_dir_from_output(
             })?;
 
             if parent.is_absolute() {
-                return Ok(parent.to_path_buf());
+                return Ok((parent.to_path_buf(), out_dir));
             }
 
             let abs_parent = std::path::absolute(parent).map_err(|err| {
@@ -502,7 +2203,7 @@ fn get_target_dir_from_output(
                     parent.display()
                 ))
             })?;
-            return Ok(abs_parent);
+            return Ok((abs_parent, out_dir));
         }
 
         Err(BuildActionError::CannotDetermineTargetDir(String::from(
@@ -512,6 +2213,11 @@ fn get_target_dir_from_output(
 
     /// Invokes `cargo rustc -- --print cfg` and finds the `target_arch` value
     ///
+    /// The command runs through `cargo` from the directory of the package
+    /// rather than as a bare `rustc`, so that rustup resolves the toolchain
+    /// pinned by a `rust-toolchain.toml` of the package or its workspace and
+    /// the detected architecture is the one the package is built with.
+    ///
     /// # Arguments
     /// * `working_dir` - Working directory from which the command must be
     ///   executed
@@ -555,6 +2261,44 @@ fn get_target_arch_from_cargo_rustc(
     }
 }
 
+/// Returns an error listing the versions of the framework installed with the
+/// WDK if `version` is not one of them
+fn ensure_wdf_version_is_available(
+    framework: &'static str,
+    version: WdfVersion,
+    available_versions: &[TwoPartVersion],
+) -> Result<(), BuildActionError> {
+    if available_versions.contains(&version.into()) {
+        return Ok(());
+    }
+    let available = if available_versions.is_empty() {
+        "none".to_string()
+    } else {
+        available_versions
+            .iter()
+            .map(|TwoPartVersion(major, minor)| format!("{major}.{minor}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    Err(BuildActionError::UnavailableWdfVersion {
+        framework,
+        version: version.to_string(),
+        available,
+    })
+}
+
+/// Returns the message of the error followed by the messages of its causes,
+/// separated by `: `
+fn error_chain(error: &impl std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(&format!(": {cause}"));
+        source = cause.source();
+    }
+    message
+}
+
 /// Returns the `cargo` CLI arguments equivalent to the given
 /// [`clap_cargo::Features`] selection.
 #[must_use]
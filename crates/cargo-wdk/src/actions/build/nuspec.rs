@@ -0,0 +1,204 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module generates the `.nuspec` manifest used to distribute a driver
+//! package through a NuGet feed. The manifest is written next to the driver
+//! package folder and references the packaged files in place, so that
+//! `nuget pack` can be run on it directly.
+
+use std::path::{Path, PathBuf};
+
+use mockall_double::double;
+
+use crate::providers::error::FileError;
+#[double]
+use crate::providers::fs::Fs;
+
+/// Folder of the NuGet package the files of the driver package are placed in
+const NUPKG_CONTENT_FOLDER_NAME: &str = "content";
+
+/// Package metadata written to the `.nuspec` manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NuspecMetadata<'a> {
+    pub id: &'a str,
+    pub version: String,
+    pub authors: String,
+    pub description: String,
+}
+
+/// Writes `<id>.nuspec` next to the driver package folder, referencing every
+/// file in the folder, and returns its path.
+///
+/// # Errors
+/// * `FileError` - If the package folder cannot be read or the manifest cannot
+///   be written.
+pub fn write_nuspec(
+    fs: &Fs,
+    metadata: &NuspecMetadata<'_>,
+    package_folder: &Path,
+) -> Result<PathBuf, FileError> {
+    let nuspec_dir = package_folder
+        .parent()
+        .expect("driver package folder is always inside the target directory");
+    let mut files = Vec::new();
+    collect_files(fs, package_folder, Path::new(""), &mut files)?;
+    files.sort();
+    let package_folder_name = package_folder
+        .file_name()
+        .expect("driver package folder always has a name");
+    let files: Vec<(PathBuf, PathBuf)> = files
+        .into_iter()
+        .map(|relative_path| {
+            (
+                Path::new(package_folder_name).join(&relative_path),
+                Path::new(NUPKG_CONTENT_FOLDER_NAME).join(relative_path),
+            )
+        })
+        .collect();
+    let nuspec_path = nuspec_dir.join(format!("{}.nuspec", metadata.id));
+    fs.write_to_file(&nuspec_path, nuspec_content(metadata, &files).as_bytes())?;
+    Ok(nuspec_path)
+}
+
+/// Recursively collects the paths of the files in `dir`, relative to the
+/// package folder. `relative_dir` is the path of `dir` relative to it.
+fn collect_files(
+    fs: &Fs,
+    dir: &Path,
+    relative_dir: &Path,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), FileError> {
+    for entry in fs.read_dir_entries(dir)? {
+        let Some(file_name) = entry.path.file_name() else {
+            continue;
+        };
+        let relative_path = relative_dir.join(file_name);
+        if entry.is_dir {
+            collect_files(fs, &entry.path, &relative_path, files)?;
+        } else {
+            files.push(relative_path);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the `.nuspec` manifest of a package with the given `(source,
+/// target)` files. Source paths are relative to the manifest.
+fn nuspec_content(metadata: &NuspecMetadata<'_>, files: &[(PathBuf, PathBuf)]) -> String {
+    let file_elements: String = files
+        .iter()
+        .map(|(src, target)| {
+            format!(
+                "    <file src=\"{}\" target=\"{}\" />\n",
+                escape_xml(&windows_path(src)),
+                escape_xml(&windows_path(target))
+            )
+        })
+        .collect();
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<package xmlns="http://schemas.microsoft.com/packaging/2013/05/nuspec.xsd">
+  <metadata>
+    <id>{}</id>
+    <version>{}</version>
+    <authors>{}</authors>
+    <description>{}</description>
+  </metadata>
+  <files>
+{file_elements}  </files>
+</package>
+"#,
+        escape_xml(metadata.id),
+        escape_xml(&metadata.version),
+        escape_xml(&metadata.authors),
+        escape_xml(&metadata.description),
+    )
+}
+
+/// Formats `path` with `\` separators as used by `nuget pack`
+fn windows_path(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("\\")
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use mockall::predicate::eq;
+
+    use super::*;
+    use crate::providers::fs::DirEntryInfo;
+
+    #[test]
+    fn write_nuspec_references_every_packaged_file() {
+        let package_folder = PathBuf::from("C:/target/debug/my_driver_package");
+        let debug_folder = package_folder.join("debug");
+        let mut fs = Fs::default();
+        let package_entries = vec![
+            DirEntryInfo {
+                path: package_folder.join("my_driver.sys"),
+                is_dir: false,
+            },
+            DirEntryInfo {
+                path: debug_folder.clone(),
+                is_dir: true,
+            },
+            DirEntryInfo {
+                path: package_folder.join("my_driver.inf"),
+                is_dir: false,
+            },
+        ];
+        let debug_entries = vec![DirEntryInfo {
+            path: debug_folder.join("my_driver.natvis"),
+            is_dir: false,
+        }];
+        fs.expect_read_dir_entries()
+            .with(eq(package_folder.clone()))
+            .returning(move |_| Ok(package_entries.clone()));
+        fs.expect_read_dir_entries()
+            .with(eq(debug_folder))
+            .returning(move |_| Ok(debug_entries.clone()));
+        fs.expect_write_to_file()
+            .withf(|path, data| {
+                let content = String::from_utf8_lossy(data);
+                path == Path::new("C:/target/debug/my_driver.nuspec")
+                    && content.contains("<id>my_driver</id>")
+                    && content.contains("<authors>A &amp; B</authors>")
+                    && content.contains(
+                        "<file src=\"my_driver_package\\debug\\my_driver.natvis\" \
+                         target=\"content\\debug\\my_driver.natvis\" />",
+                    )
+                    && content.contains(
+                        "<file src=\"my_driver_package\\my_driver.sys\" \
+                         target=\"content\\my_driver.sys\" />",
+                    )
+            })
+            .once()
+            .returning(|_, _| Ok(()));
+
+        let nuspec_path = write_nuspec(
+            &fs,
+            &NuspecMetadata {
+                id: "my_driver",
+                version: "1.2.3".to_string(),
+                authors: "A & B".to_string(),
+                description: "My driver".to_string(),
+            },
+            &package_folder,
+        )
+        .expect("nuspec should be written");
+        assert_eq!(
+            nuspec_path,
+            PathBuf::from("C:/target/debug/my_driver.nuspec")
+        );
+    }
+}
@@ -0,0 +1,185 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module contains the helpers used by the `--os-version-check` option of
+//! the build action. It reads the minimum OS version a driver binary is linked
+//! for from its PE headers, as printed by `dumpbin /headers`, and the OS
+//! versions the INF of the driver targets from its `[Manufacturer]`
+//! decorations. The `--import-check` option reads the oldest build the INF
+//! targets from the same decorations.
+
+use std::fmt;
+
+use crate::actions::inf::InfFile;
+
+/// Label of the PE optional header field Windows checks before loading a
+/// binary
+const DUMPBIN_SUBSYSTEM_VERSION_LABEL: &str = "subsystem version";
+
+/// A `major.minor` Windows version, e.g. `10.0`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct OsVersion {
+    pub major: u16,
+    pub minor: u16,
+}
+
+impl OsVersion {
+    /// Returns the build number of the first release of the Windows version,
+    /// e.g. 7600 for Windows 7, i.e. 6.1, and 10240 for Windows 10. Versions
+    /// older than Windows Vista are returned as `0`.
+    const fn first_build_number(self) -> u32 {
+        match (self.major, self.minor) {
+            (6, 0) => 6000,
+            (6, 1) => 7600,
+            (6, 2) => 9200,
+            (6, 3) => 9600,
+            (major, _) if major >= 10 => 10240,
+            _ => 0,
+        }
+    }
+}
+
+impl fmt::Display for OsVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// Parses the subsystem version of the optional header from the output of
+/// `dumpbin /headers`. dumpbin prints it as `<major>.<minor> subsystem
+/// version`, with a two digit minor version, e.g. `10.00 subsystem version`.
+/// Returns `None` if the output has no subsystem version.
+#[must_use]
+pub fn parse_dumpbin_subsystem_version(output: &str) -> Option<OsVersion> {
+    output.lines().find_map(|line| {
+        let version = line
+            .trim()
+            .strip_suffix(DUMPBIN_SUBSYSTEM_VERSION_LABEL)?
+            .trim();
+        let (major, minor) = version.split_once('.')?;
+        Some(OsVersion {
+            major: major.parse().ok()?,
+            minor: minor.parse().ok()?,
+        })
+    })
+}
+
+/// Returns the OS versions targeted by the `[Manufacturer]` decorations of an
+/// INF. Decorations have the form
+/// `NT<arch>[.<major>[.<minor>[.<product type>[.<suite mask>[.<build>]]]]]`,
+/// e.g. `NTamd64.10.0...16299`. Decorations without a major version are
+/// skipped and a missing minor version is read as `0`. Duplicates are
+/// returned once.
+#[must_use]
+pub fn inf_os_versions(inf: &InfFile) -> Vec<OsVersion> {
+    let mut os_versions: Vec<OsVersion> = Vec::new();
+    for decoration in inf.manufacturer_decorations() {
+        let mut fields = decoration.split('.').skip(1);
+        let Some(Ok(major)) = fields.next().map(str::parse) else {
+            continue;
+        };
+        let minor = fields
+            .next()
+            .and_then(|minor| minor.parse().ok())
+            .unwrap_or_default();
+        let os_version = OsVersion { major, minor };
+        if !os_versions.contains(&os_version) {
+            os_versions.push(os_version);
+        }
+    }
+    os_versions
+}
+
+/// Returns the oldest Windows build targeted by the `[Manufacturer]`
+/// decorations of an INF. The build number of a decoration is used if it has
+/// one, e.g. `16299` for `NTamd64.10.0...16299`, and the first build of its OS
+/// version otherwise, e.g. `10240` for `NTamd64.10.0`. Returns `None` if no
+/// decoration has an OS version.
+#[must_use]
+pub fn inf_minimum_build_number(inf: &InfFile) -> Option<u32> {
+    inf.manufacturer_decorations()
+        .into_iter()
+        .filter_map(|decoration| {
+            let mut fields = decoration.split('.').skip(1);
+            let major = fields.next()?.parse().ok()?;
+            let minor = fields
+                .next()
+                .and_then(|minor| minor.parse().ok())
+                .unwrap_or_default();
+            // The product type and suite mask precede the build number
+            fields
+                .nth(2)
+                .and_then(|build_number| build_number.parse().ok())
+                .or_else(|| Some(OsVersion { major, minor }.first_build_number()))
+        })
+        .min()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subsystem_version_is_parsed_from_dumpbin_headers() {
+        let output = r"
+OPTIONAL HEADER VALUES
+             20B magic # (PE32+)
+           14.38 linker version
+           10.00 operating system version
+            0.00 image version
+            6.01 subsystem version
+               0 Win32 version
+";
+        assert_eq!(
+            parse_dumpbin_subsystem_version(output),
+            Some(OsVersion { major: 6, minor: 1 })
+        );
+        assert_eq!(parse_dumpbin_subsystem_version("Summary"), None);
+    }
+
+    #[test]
+    fn os_versions_are_read_from_manufacturer_decorations() {
+        let inf = InfFile::parse(
+            r"
+[Manufacturer]
+%StdMfg%=Standard,NT$ARCH$.10.0...16299,NTamd64.6.1,NTarm64
+",
+        );
+        assert_eq!(
+            inf_os_versions(&inf),
+            vec![
+                OsVersion {
+                    major: 10,
+                    minor: 0
+                },
+                OsVersion { major: 6, minor: 1 }
+            ]
+        );
+    }
+
+    #[test]
+    fn minimum_build_number_is_read_from_manufacturer_decorations() {
+        let inf = InfFile::parse(
+            r"
+[Manufacturer]
+%StdMfg%=Standard,NTamd64.10.0...19041,NTarm64.10.0...16299
+",
+        );
+        assert_eq!(inf_minimum_build_number(&inf), Some(16299));
+
+        let inf = InfFile::parse(
+            r"
+[Manufacturer]
+%StdMfg%=Standard,NTamd64.10.0...19041,NTamd64.6.3
+",
+        );
+        assert_eq!(inf_minimum_build_number(&inf), Some(9600));
+
+        let inf = InfFile::parse(
+            r"
+[Manufacturer]
+%StdMfg%=Standard,NTamd64
+",
+        );
+        assert_eq!(inf_minimum_build_number(&inf), None);
+    }
+}
@@ -0,0 +1,197 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module writes the package index emitted by the `--emit-index` option
+//! of the build action. The index is a single `packages.json` file in the
+//! target directory that lists every driver package the build produced, so
+//! that downstream tooling can enumerate them without globbing the target
+//! directory.
+
+use std::path::{Path, PathBuf};
+
+use mockall_double::double;
+use serde_json::json;
+use tracing::info;
+use wdk_build::CpuArchitecture;
+
+use crate::{actions::DriverType, providers::error::FileError};
+#[double]
+use crate::providers::fs::Fs;
+
+/// File name of the package index in the target directory
+pub const PACKAGE_INDEX_FILE_NAME: &str = "packages.json";
+
+/// Driver package produced by the build of a package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageResult {
+    pub package_name: String,
+    pub driver_type: DriverType,
+    /// Architectures of the driver binaries in the package, more than one for
+    /// multi-architecture packages
+    pub target_archs: Vec<CpuArchitecture>,
+    /// Crate version of the package
+    pub version: String,
+    pub package_folder: PathBuf,
+    pub inf_file_path: PathBuf,
+}
+
+/// Writes the package index listing the given packages to `target_dir` and
+/// returns its path. Paths in the index are relative to `target_dir` and
+/// separated by `/`.
+///
+/// # Errors
+/// * `FileError` - If the index cannot be written.
+pub fn write_package_index(
+    fs: &Fs,
+    target_dir: &Path,
+    package_results: &[PackageResult],
+) -> Result<PathBuf, FileError> {
+    let index_file_path = target_dir.join(PACKAGE_INDEX_FILE_NAME);
+    fs.write_to_file(
+        &index_file_path,
+        package_index_content(target_dir, package_results).as_bytes(),
+    )?;
+    info!(
+        "Package index of {} packages written to {}",
+        package_results.len(),
+        index_file_path.display()
+    );
+    Ok(index_file_path)
+}
+
+/// Renders the package index, with the packages sorted by name
+fn package_index_content(target_dir: &Path, package_results: &[PackageResult]) -> String {
+    let mut package_results = package_results.iter().collect::<Vec<_>>();
+    package_results.sort_by(|a, b| a.package_name.cmp(&b.package_name));
+    let packages = package_results
+        .into_iter()
+        .map(|result| {
+            json!({
+                "name": result.package_name,
+                "type": result.driver_type.to_string(),
+                "arch": result
+                    .target_archs
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>(),
+                "version": result.version,
+                "path": index_path(target_dir, &result.package_folder),
+                "inf": index_path(target_dir, &result.inf_file_path),
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut content = serde_json::to_string_pretty(&json!({ "packages": packages }))
+        .expect("package index is JSON");
+    content.push('\n');
+    content
+}
+
+/// Returns `path` relative to the target directory with `/` separators, or
+/// as is if it is outside of the target directory
+fn index_path(target_dir: &Path, path: &Path) -> String {
+    path.strip_prefix(target_dir).map_or_else(
+        |_| path.display().to_string(),
+        |relative_path| {
+            relative_path
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/")
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package_result(
+        package_name: &str,
+        driver_type: DriverType,
+        target_archs: Vec<CpuArchitecture>,
+        package_folder: &str,
+    ) -> PackageResult {
+        let package_folder = PathBuf::from(package_folder);
+        PackageResult {
+            package_name: package_name.to_string(),
+            driver_type,
+            target_archs,
+            version: "0.1.0".to_string(),
+            inf_file_path: package_folder.join(format!("{package_name}.inf")),
+            package_folder,
+        }
+    }
+
+    #[test]
+    fn index_lists_the_packages_by_name_with_paths_relative_to_the_target_dir() {
+        let target_dir = PathBuf::from("C:/ws/target");
+        let package_results = [
+            package_result(
+                "umdf_driver",
+                DriverType::Umdf,
+                vec![CpuArchitecture::Amd64],
+                "C:/ws/target/x86_64-pc-windows-msvc/debug/umdf_driver_package",
+            ),
+            package_result(
+                "kmdf_driver",
+                DriverType::Kmdf,
+                vec![CpuArchitecture::Amd64, CpuArchitecture::Arm64],
+                "C:/ws/target/debug/kmdf_driver_package",
+            ),
+        ];
+
+        let index: serde_json::Value =
+            serde_json::from_str(&package_index_content(&target_dir, &package_results))
+                .expect("index should be JSON");
+        assert_eq!(
+            index,
+            json!({
+                "packages": [
+                    {
+                        "name": "kmdf_driver",
+                        "type": "kmdf",
+                        "arch": ["amd64", "arm64"],
+                        "version": "0.1.0",
+                        "path": "debug/kmdf_driver_package",
+                        "inf": "debug/kmdf_driver_package/kmdf_driver.inf"
+                    },
+                    {
+                        "name": "umdf_driver",
+                        "type": "umdf",
+                        "arch": ["amd64"],
+                        "version": "0.1.0",
+                        "path": "x86_64-pc-windows-msvc/debug/umdf_driver_package",
+                        "inf": "x86_64-pc-windows-msvc/debug/umdf_driver_package/umdf_driver.inf"
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn index_is_written_to_the_target_dir() {
+        let target_dir = PathBuf::from("C:/ws/target");
+        let mut fs = Fs::default();
+        fs.expect_write_to_file()
+            .withf(|path: &Path, data: &[u8]| {
+                path == Path::new("C:/ws/target/packages.json")
+                    && String::from_utf8_lossy(data).contains("/elsewhere/wdm_package")
+            })
+            .once()
+            .returning(|_, _| Ok(()));
+
+        let result = write_package_index(
+            &fs,
+            &target_dir,
+            &[package_result(
+                "wdm",
+                DriverType::Wdm,
+                vec![CpuArchitecture::Amd64],
+                "/elsewhere/wdm_package",
+            )],
+        );
+        assert_eq!(
+            result.expect("index should be written"),
+            target_dir.join(PACKAGE_INDEX_FILE_NAME)
+        );
+    }
+}
@@ -0,0 +1,1087 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module reads the packaging settings from the
+//! `[package.metadata.cargo-wdk]` table of a driver package manifest. The
+//! settings may be overridden per profile in a
+//! `[package.metadata.cargo-wdk.profile.<name>]` table, e.g. to enable WDF
+//! verifier only for the `dev` profile or to leave the PDB out of release
+//! packages.
+
+use std::path::{Component, PathBuf};
+
+use serde_json::{Map, Value, json};
+
+use crate::actions::{
+    Profile,
+    build::{
+        catalog_exclude::CatalogExcludePattern,
+        error::BuildActionError,
+        package_task::{InfCatalog, InfFormat, SignMode},
+        supported_wdk::SupportedWdk,
+    },
+};
+
+/// Key of the cargo-wdk table in the package metadata
+const CARGO_WDK_METADATA_KEY: &str = "cargo-wdk";
+/// Key of the per profile override tables in the cargo-wdk table
+const PROFILE_OVERRIDES_KEY: &str = "profile";
+const WDF_VERIFIER_KEY: &str = "wdf-verifier";
+const POOL_TAG_KEY: &str = "pool-tag";
+const DEBUG_FILES_KEY: &str = "debug-files";
+const BINARY_NAME_KEY: &str = "binary-name";
+const SIGN_MODE_KEY: &str = "sign-mode";
+const INCLUDE_PDB_KEY: &str = "include-pdb";
+const INCLUDE_MAP_KEY: &str = "include-map";
+const TOOL_SEARCH_PATHS_KEY: &str = "tool-search-paths";
+const SUPPORTED_WDK_KEY: &str = "supported-wdk";
+const RUN_CLIPPY_KEY: &str = "run-clippy";
+const INF_FILES_KEY: &str = "inf-files";
+const INF_CATALOG_KEY: &str = "inf-catalog";
+const DRIVER_TARGET_KEY: &str = "driver-target";
+const ENV_KEY: &str = "env";
+const AUTO_INCREMENT_DRIVER_VER_KEY: &str = "auto-increment-driver-ver";
+const INF_FORMAT_KEY: &str = "inf-format";
+const CATALOG_EXCLUDE_KEY: &str = "catalog-exclude";
+const SKIP_STAMPINF_KEY: &str = "skip-stampinf";
+const PRE_BUILD_KEY: &str = "pre-build";
+const OUT_DIR_FILES_KEY: &str = "out-dir-files";
+const MANIFEST_KEY: &str = "manifest";
+const CHECKED_KEY: &str = "checked";
+const ALLOWED_PROVIDERS_KEY: &str = "allowed-providers";
+/// Shell a `pre-build` command given as a single string is run by
+const PRE_BUILD_SHELL: [&str; 2] = ["cmd", "/C"];
+/// Extension of the INF templates that are stamped into the packaged INFs
+const INX_EXTENSION: &str = "inx";
+/// Name of the override table that also applies to the `dev` profile, since
+/// its artifacts are placed in the `debug` directory
+const DEV_PROFILE_ALIAS: &str = "debug";
+/// Characters that are not allowed in Windows file names
+const INVALID_FILE_NAME_CHARS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+/// Device names that Windows reserves regardless of the extension
+const RESERVED_FILE_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+/// Characters that cannot appear in an entry of the `PATH` environment
+/// variable
+const INVALID_SEARCH_PATH_CHARS: [char; 2] = [';', '"'];
+/// Pool tags are four character codes
+const POOL_TAG_LENGTH: usize = 4;
+
+/// Packaging settings for the profile being built
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackageSettings {
+    /// Whether WDF verifier is enabled in the packaged INF. `None` leaves the
+    /// INF untouched.
+    pub wdf_verifier: Option<bool>,
+    /// Debugging collateral, e.g. `.natvis` files and debugger scripts, copied
+    /// to the `debug` folder of the package. Relative paths are relative to
+    /// the package root.
+    pub debug_files: Vec<PathBuf>,
+    /// Name, without extension, of the driver binary, INF and catalog files in
+    /// the package. `None` uses the crate name.
+    pub binary_name: Option<String>,
+    /// Signing mode of the package. `None` uses the `--sign-mode` default.
+    pub sign_mode: Option<SignMode>,
+    /// Whether the PDB of the driver is copied into the package. `None`
+    /// includes it.
+    pub include_pdb: Option<bool>,
+    /// Whether the linker map of the driver is copied into the package. `None`
+    /// includes it.
+    pub include_map: Option<bool>,
+    /// Directories prepended to `PATH` when running the packaging tools, e.g.
+    /// for split WDK installations. Relative paths are relative to the package
+    /// root.
+    pub tool_search_paths: Vec<PathBuf>,
+    /// Range of WDK builds the package is validated against. `None` accepts
+    /// any WDK.
+    pub supported_wdk: Option<SupportedWdk>,
+    /// Whether `cargo clippy` is run before the driver is built. `None` does
+    /// not run it.
+    pub run_clippy: Option<bool>,
+    /// `.inx` files of additional INFs packaged against the same driver
+    /// binary. Relative paths are relative to the package root.
+    pub inf_files: Vec<PathBuf>,
+    /// Catalog files generated for the additional INFs. `None` generates one
+    /// per INF.
+    pub inf_catalog: Option<InfCatalog>,
+    /// Name of the cdylib target packaged as the driver. `None` packages the
+    /// cdylib target of the package.
+    pub driver_target: Option<String>,
+    /// Environment variables set for the cargo and packaging tool invocations
+    /// of the package only, sorted by name
+    pub env: Vec<(String, String)>,
+    /// Whether the revision of the `DriverVer` version is incremented by every
+    /// build. `None` stamps the version of the build.
+    pub auto_increment_driver_ver: Option<bool>,
+    /// Format of the INF of the package. `None` validates it as a universal
+    /// INF.
+    pub inf_format: Option<InfFormat>,
+    /// File name patterns of the files of the package that are left out of
+    /// the catalogs
+    pub catalog_exclude: Vec<CatalogExcludePattern>,
+    /// Whether the INF is packaged as checked in instead of being stamped by
+    /// `stampinf`. `None` stamps it.
+    pub skip_stampinf: Option<bool>,
+    /// Program and arguments of the command run in the package root before
+    /// the driver is built, e.g. to generate code. Empty if no command is run.
+    pub pre_build: Vec<String>,
+    /// Files generated by the build script of the package that are copied to
+    /// the package, as paths relative to its `OUT_DIR`
+    pub out_dir_files: Vec<PathBuf>,
+    /// Application manifest embedded into the binary of a UMDF driver.
+    /// Relative paths are relative to the package root. `None` embeds no
+    /// manifest.
+    pub manifest: Option<PathBuf>,
+    /// Whether a checked variant of the driver, built with the `checked` cfg
+    /// and runtime assertions, is packaged alongside the package. `None`
+    /// packages only the normal build.
+    pub checked: Option<bool>,
+    /// `Provider` strings the INF of the package may use, e.g. the approved
+    /// names of the organization. Empty if any provider is allowed.
+    pub allowed_providers: Vec<String>,
+}
+
+impl PackageSettings {
+    /// Resolves the packaging settings of a package for the given profile.
+    /// Values in the profile's override table take precedence over the
+    /// values at the top level of the cargo-wdk table. The override table of
+    /// the `dev` profile may also be named `debug`.
+    ///
+    /// The pool tag is compiled into the driver by the code `new` scaffolds, so
+    /// it is only validated here.
+    ///
+    /// # Errors
+    /// * `BuildActionError::InvalidCargoWdkMetadata` - If a setting has the
+    ///   wrong type, the pool tag is not a four character ASCII code, a tool
+    ///   search path contains a character that is not allowed in `PATH`, the
+    ///   supported WDK range is invalid, a catalog exclude pattern is not a
+    ///   file name pattern, `auto-increment-driver-ver` is enabled for an INF
+    ///   that is not stamped, the pre-build command is empty or an `OUT_DIR`
+    ///   file is not a relative path inside `OUT_DIR`.
+    pub fn from_package_metadata(
+        package_metadata: &Value,
+        profile: Option<&Profile>,
+    ) -> Result<Self, BuildActionError> {
+        let Some(cargo_wdk_metadata) = package_metadata.get(CARGO_WDK_METADATA_KEY) else {
+            return Ok(Self::default());
+        };
+        let profile_name = profile.map_or_else(|| Profile::Dev.to_string(), ToString::to_string);
+        let profile_overrides =
+            cargo_wdk_metadata
+                .get(PROFILE_OVERRIDES_KEY)
+                .and_then(|profiles| {
+                    profiles.get(&profile_name).or_else(|| {
+                        (profile_name == Profile::Dev.to_string())
+                            .then(|| profiles.get(DEV_PROFILE_ALIAS))
+                            .flatten()
+                    })
+                });
+        let lookup = |key: &str| {
+            profile_overrides
+                .and_then(|overrides| overrides.get(key))
+                .or_else(|| cargo_wdk_metadata.get(key))
+        };
+
+        let lookup_bool = |key: &str| {
+            lookup(key)
+                .map(|value| {
+                    value.as_bool().ok_or_else(|| {
+                        BuildActionError::InvalidCargoWdkMetadata(format!(
+                            "`{key}` must be a boolean, found: {value}"
+                        ))
+                    })
+                })
+                .transpose()
+        };
+
+        let wdf_verifier = lookup_bool(WDF_VERIFIER_KEY)?;
+        if let Some(value) = lookup(POOL_TAG_KEY)
+            && !value
+                .as_str()
+                .is_some_and(|tag| tag.len() == POOL_TAG_LENGTH && tag.is_ascii())
+        {
+            return Err(BuildActionError::InvalidCargoWdkMetadata(format!(
+                "`{POOL_TAG_KEY}` must be a {POOL_TAG_LENGTH} character ASCII string, found: \
+                 {value}"
+            )));
+        }
+        let debug_files = lookup(DEBUG_FILES_KEY)
+            .map(|value| {
+                value
+                    .as_array()
+                    .and_then(|paths| {
+                        paths
+                            .iter()
+                            .map(|path| path.as_str().map(PathBuf::from))
+                            .collect::<Option<Vec<_>>>()
+                    })
+                    .ok_or_else(|| {
+                        BuildActionError::InvalidCargoWdkMetadata(format!(
+                            "`{DEBUG_FILES_KEY}` must be an array of paths, found: {value}"
+                        ))
+                    })
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let binary_name = lookup(BINARY_NAME_KEY)
+            .map(|value| {
+                value
+                    .as_str()
+                    .filter(|name| is_valid_file_name(name))
+                    .map(ToString::to_string)
+                    .ok_or_else(|| {
+                        BuildActionError::InvalidCargoWdkMetadata(format!(
+                            "`{BINARY_NAME_KEY}` must be a valid file name, found: {value}"
+                        ))
+                    })
+            })
+            .transpose()?;
+        let sign_mode = lookup(SIGN_MODE_KEY)
+            .map(|value| match value.as_str() {
+                Some("off") => Ok(SignMode::Off),
+                Some("test") => Ok(SignMode::Test {
+                    verify_signature: false,
+                }),
+                _ => Err(BuildActionError::InvalidCargoWdkMetadata(format!(
+                    "`{SIGN_MODE_KEY}` must be \"off\" or \"test\", found: {value}"
+                ))),
+            })
+            .transpose()?;
+        let include_pdb = lookup_bool(INCLUDE_PDB_KEY)?;
+        let include_map = lookup_bool(INCLUDE_MAP_KEY)?;
+        let tool_search_paths = lookup(TOOL_SEARCH_PATHS_KEY)
+            .map(|value| {
+                value
+                    .as_array()
+                    .and_then(|paths| {
+                        paths
+                            .iter()
+                            .map(|path| {
+                                path.as_str()
+                                    .filter(|path| !path.contains(INVALID_SEARCH_PATH_CHARS))
+                                    .map(PathBuf::from)
+                            })
+                            .collect::<Option<Vec<_>>>()
+                    })
+                    .ok_or_else(|| {
+                        BuildActionError::InvalidCargoWdkMetadata(format!(
+                            "`{TOOL_SEARCH_PATHS_KEY}` must be an array of paths without `;` or \
+                             `\"`, found: {value}"
+                        ))
+                    })
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let supported_wdk = lookup(SUPPORTED_WDK_KEY)
+            .map(|value| SupportedWdk::from_metadata(SUPPORTED_WDK_KEY, value))
+            .transpose()?;
+        let run_clippy = lookup_bool(RUN_CLIPPY_KEY)?;
+        let inf_files = lookup(INF_FILES_KEY)
+            .map(|value| {
+                value
+                    .as_array()
+                    .and_then(|paths| {
+                        paths
+                            .iter()
+                            .map(|path| {
+                                path.as_str().map(PathBuf::from).filter(|path| {
+                                    path.file_stem().is_some()
+                                        && path.extension().is_some_and(|extension| {
+                                            extension.eq_ignore_ascii_case(INX_EXTENSION)
+                                        })
+                                })
+                            })
+                            .collect::<Option<Vec<_>>>()
+                    })
+                    .ok_or_else(|| {
+                        BuildActionError::InvalidCargoWdkMetadata(format!(
+                            "`{INF_FILES_KEY}` must be an array of paths of .{INX_EXTENSION} \
+                             files, found: {value}"
+                        ))
+                    })
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let inf_catalog = lookup(INF_CATALOG_KEY)
+            .map(|value| match value.as_str() {
+                Some("per-inf") => Ok(InfCatalog::PerInf),
+                Some("combined") => Ok(InfCatalog::Combined),
+                _ => Err(BuildActionError::InvalidCargoWdkMetadata(format!(
+                    "`{INF_CATALOG_KEY}` must be \"per-inf\" or \"combined\", found: {value}"
+                ))),
+            })
+            .transpose()?;
+        let driver_target = lookup(DRIVER_TARGET_KEY)
+            .map(|value| {
+                value
+                    .as_str()
+                    .filter(|name| !name.is_empty())
+                    .map(ToString::to_string)
+                    .ok_or_else(|| {
+                        BuildActionError::InvalidCargoWdkMetadata(format!(
+                            "`{DRIVER_TARGET_KEY}` must be a target name, found: {value}"
+                        ))
+                    })
+            })
+            .transpose()?;
+        let env = lookup(ENV_KEY)
+            .map(|value| {
+                value
+                    .as_object()
+                    .and_then(|vars| {
+                        vars.iter()
+                            .map(|(name, value)| {
+                                let value =
+                                    value.as_str().filter(|_| is_valid_env_var_name(name))?;
+                                Some((name.clone(), value.to_string()))
+                            })
+                            .collect::<Option<Vec<_>>>()
+                    })
+                    .ok_or_else(|| {
+                        BuildActionError::InvalidCargoWdkMetadata(format!(
+                            "`{ENV_KEY}` must be a table of environment variable names and string \
+                             values, found: {value}"
+                        ))
+                    })
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let auto_increment_driver_ver = lookup_bool(AUTO_INCREMENT_DRIVER_VER_KEY)?;
+        let inf_format = lookup(INF_FORMAT_KEY)
+            .map(|value| match value.as_str() {
+                Some("universal") => Ok(InfFormat::Universal),
+                Some("legacy") => Ok(InfFormat::Legacy),
+                _ => Err(BuildActionError::InvalidCargoWdkMetadata(format!(
+                    "`{INF_FORMAT_KEY}` must be \"universal\" or \"legacy\", found: {value}"
+                ))),
+            })
+            .transpose()?;
+        let catalog_exclude = lookup(CATALOG_EXCLUDE_KEY)
+            .map(|value| {
+                value
+                    .as_array()
+                    .and_then(|patterns| {
+                        patterns
+                            .iter()
+                            .map(|pattern| pattern.as_str().and_then(CatalogExcludePattern::parse))
+                            .collect::<Option<Vec<_>>>()
+                    })
+                    .ok_or_else(|| {
+                        BuildActionError::InvalidCargoWdkMetadata(format!(
+                            "`{CATALOG_EXCLUDE_KEY}` must be an array of file name patterns, \
+                             found: {value}"
+                        ))
+                    })
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let skip_stampinf = lookup_bool(SKIP_STAMPINF_KEY)?;
+        // A single string is a command line run by the shell, an array the
+        // program and its arguments
+        let pre_build = lookup(PRE_BUILD_KEY)
+            .map(|value| {
+                let pre_build = match value {
+                    Value::String(command_line) if !command_line.trim().is_empty() => Some(
+                        PRE_BUILD_SHELL
+                            .iter()
+                            .map(ToString::to_string)
+                            .chain([command_line.clone()])
+                            .collect(),
+                    ),
+                    Value::Array(argv) => argv
+                        .iter()
+                        .map(|arg| arg.as_str().map(ToString::to_string))
+                        .collect::<Option<Vec<_>>>()
+                        .filter(|argv| argv.first().is_some_and(|program| !program.is_empty())),
+                    _ => None,
+                };
+                pre_build.ok_or_else(|| {
+                    BuildActionError::InvalidCargoWdkMetadata(format!(
+                        "`{PRE_BUILD_KEY}` must be a command line or a non-empty array of a \
+                         program and its arguments, found: {value}"
+                    ))
+                })
+            })
+            .transpose()?
+            .unwrap_or_default();
+        if skip_stampinf == Some(true) && auto_increment_driver_ver == Some(true) {
+            return Err(BuildActionError::InvalidCargoWdkMetadata(format!(
+                "`{AUTO_INCREMENT_DRIVER_VER_KEY}` cannot be enabled with `{SKIP_STAMPINF_KEY}`, \
+                 since the `DriverVer` is only incremented by stamping the INF"
+            )));
+        }
+        let out_dir_files = lookup(OUT_DIR_FILES_KEY)
+            .map(|value| {
+                value
+                    .as_array()
+                    .and_then(|paths| {
+                        paths
+                            .iter()
+                            .map(|path| {
+                                path.as_str().map(PathBuf::from).filter(|path| {
+                                    path.file_name().is_some()
+                                        && path.components().all(|component| {
+                                            matches!(component, Component::Normal(_))
+                                        })
+                                })
+                            })
+                            .collect::<Option<Vec<_>>>()
+                    })
+                    .ok_or_else(|| {
+                        BuildActionError::InvalidCargoWdkMetadata(format!(
+                            "`{OUT_DIR_FILES_KEY}` must be an array of paths relative to the \
+                             `OUT_DIR` of the build script, found: {value}"
+                        ))
+                    })
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let manifest = lookup(MANIFEST_KEY)
+            .map(|value| {
+                value
+                    .as_str()
+                    .filter(|path| !path.is_empty())
+                    .map(PathBuf::from)
+                    .ok_or_else(|| {
+                        BuildActionError::InvalidCargoWdkMetadata(format!(
+                            "`{MANIFEST_KEY}` must be the path of a manifest file, found: {value}"
+                        ))
+                    })
+            })
+            .transpose()?;
+        let checked = lookup_bool(CHECKED_KEY)?;
+        let allowed_providers = lookup(ALLOWED_PROVIDERS_KEY)
+            .map(|value| {
+                value
+                    .as_array()
+                    .and_then(|providers| {
+                        providers
+                            .iter()
+                            .map(|provider| {
+                                provider
+                                    .as_str()
+                                    .filter(|provider| !provider.trim().is_empty())
+                                    .map(ToString::to_string)
+                            })
+                            .collect::<Option<Vec<_>>>()
+                    })
+                    .ok_or_else(|| {
+                        BuildActionError::InvalidCargoWdkMetadata(format!(
+                            "`{ALLOWED_PROVIDERS_KEY}` must be an array of provider names, found: \
+                             {value}"
+                        ))
+                    })
+            })
+            .transpose()?
+            .unwrap_or_default();
+        Ok(Self {
+            wdf_verifier,
+            debug_files,
+            binary_name,
+            sign_mode,
+            include_pdb,
+            include_map,
+            tool_search_paths,
+            supported_wdk,
+            run_clippy,
+            inf_files,
+            inf_catalog,
+            driver_target,
+            env,
+            auto_increment_driver_ver,
+            inf_format,
+            catalog_exclude,
+            skip_stampinf,
+            pre_build,
+            out_dir_files,
+            manifest,
+            checked,
+            allowed_providers,
+        })
+    }
+
+    /// Returns the JSON schema of the cargo-wdk table, including the per
+    /// profile override tables, for editors to validate and complete the
+    /// packaging settings
+    #[must_use]
+    pub fn json_schema() -> Value {
+        let boolean = || json!({ "type": "boolean" });
+        let paths = || json!({ "type": "array", "items": { "type": "string" } });
+        let mut settings = Map::new();
+        for (key, schema) in [
+            (WDF_VERIFIER_KEY, boolean()),
+            (
+                POOL_TAG_KEY,
+                json!({
+                    "type": "string",
+                    "minLength": POOL_TAG_LENGTH,
+                    "maxLength": POOL_TAG_LENGTH
+                }),
+            ),
+            (DEBUG_FILES_KEY, paths()),
+            (BINARY_NAME_KEY, json!({ "type": "string", "minLength": 1 })),
+            (SIGN_MODE_KEY, json!({ "enum": ["off", "test"] })),
+            (INCLUDE_PDB_KEY, boolean()),
+            (INCLUDE_MAP_KEY, boolean()),
+            (TOOL_SEARCH_PATHS_KEY, paths()),
+            (SUPPORTED_WDK_KEY, SupportedWdk::json_schema()),
+            (RUN_CLIPPY_KEY, boolean()),
+            (
+                INF_FILES_KEY,
+                json!({
+                    "type": "array",
+                    "items": { "type": "string", "pattern": "\\.[iI][nN][xX]$" }
+                }),
+            ),
+            (INF_CATALOG_KEY, json!({ "enum": ["per-inf", "combined"] })),
+            (
+                DRIVER_TARGET_KEY,
+                json!({ "type": "string", "minLength": 1 }),
+            ),
+            (
+                ENV_KEY,
+                json!({ "type": "object", "additionalProperties": { "type": "string" } }),
+            ),
+            (AUTO_INCREMENT_DRIVER_VER_KEY, boolean()),
+            (INF_FORMAT_KEY, json!({ "enum": ["universal", "legacy"] })),
+            (
+                CATALOG_EXCLUDE_KEY,
+                json!({ "type": "array", "items": { "type": "string", "minLength": 1 } }),
+            ),
+            (SKIP_STAMPINF_KEY, boolean()),
+            (
+                PRE_BUILD_KEY,
+                json!({
+                    "oneOf": [
+                        { "type": "string", "minLength": 1 },
+                        { "type": "array", "items": { "type": "string" }, "minItems": 1 }
+                    ]
+                }),
+            ),
+            (OUT_DIR_FILES_KEY, paths()),
+            (MANIFEST_KEY, json!({ "type": "string", "minLength": 1 })),
+            (CHECKED_KEY, boolean()),
+            (
+                ALLOWED_PROVIDERS_KEY,
+                json!({ "type": "array", "items": { "type": "string", "minLength": 1 } }),
+            ),
+        ] {
+            settings.insert(key.to_string(), schema);
+        }
+        let profile_settings = json!({ "type": "object", "properties": settings.clone() });
+        settings.insert(
+            PROFILE_OVERRIDES_KEY.to_string(),
+            json!({ "type": "object", "additionalProperties": profile_settings }),
+        );
+        json!({ "type": "object", "properties": settings })
+    }
+}
+
+/// Returns `true` if `name` can be set as an environment variable
+fn is_valid_env_var_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['=', '\0'])
+}
+
+/// Returns `true` if `name` can be used as a Windows file name
+fn is_valid_file_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or_default();
+    !name.is_empty()
+        && !name.ends_with(['.', ' '])
+        && !name
+            .chars()
+            .any(|c| c.is_control() || INVALID_FILE_NAME_CHARS.contains(&c))
+        && !RESERVED_FILE_NAMES
+            .iter()
+            .any(|reserved| reserved.eq_ignore_ascii_case(stem.trim_end()))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn package_metadata() -> Value {
+        json!({
+            "wdk": {},
+            "cargo-wdk": {
+                "wdf-verifier": false,
+                "pool-tag": "Drv0",
+                "profile": {
+                    "dev": { "wdf-verifier": true }
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn profile_override_takes_precedence_over_top_level_setting() {
+        let settings = PackageSettings::from_package_metadata(&package_metadata(), None)
+            .expect("metadata should be valid");
+        assert_eq!(settings.wdf_verifier, Some(true));
+    }
+
+    #[test]
+    fn top_level_setting_applies_to_profiles_without_overrides() {
+        let settings =
+            PackageSettings::from_package_metadata(&package_metadata(), Some(&Profile::Release))
+                .expect("metadata should be valid");
+        assert_eq!(settings.wdf_verifier, Some(false));
+    }
+
+    #[test]
+    fn missing_cargo_wdk_table_yields_default_settings() {
+        let settings = PackageSettings::from_package_metadata(&json!({ "wdk": {} }), None)
+            .expect("metadata should be valid");
+        assert_eq!(settings, PackageSettings::default());
+    }
+
+    #[test]
+    fn debug_files_are_read_as_paths() {
+        let metadata = json!({
+            "cargo-wdk": { "debug-files": ["debug/driver.natvis", "debug/driver.js"] }
+        });
+        let settings = PackageSettings::from_package_metadata(&metadata, None)
+            .expect("metadata should be valid");
+        assert_eq!(
+            settings.debug_files,
+            vec![
+                PathBuf::from("debug/driver.natvis"),
+                PathBuf::from("debug/driver.js")
+            ]
+        );
+    }
+
+    #[test]
+    fn binary_name_must_be_a_valid_file_name() {
+        for (binary_name, is_valid) in [
+            ("Foo", true),
+            ("Foo.Driver", true),
+            ("", false),
+            ("Foo/Bar", false),
+            ("Foo?", false),
+            ("Foo.", false),
+            ("nul", false),
+        ] {
+            let metadata = json!({ "cargo-wdk": { "binary-name": binary_name } });
+            let settings = PackageSettings::from_package_metadata(&metadata, None);
+            assert_eq!(settings.is_ok(), is_valid, "binary name: {binary_name:?}");
+        }
+    }
+
+    #[test]
+    fn invalid_pool_tag_is_rejected() {
+        let metadata = json!({ "cargo-wdk": { "pool-tag": "TooLong" } });
+        assert!(matches!(
+            PackageSettings::from_package_metadata(&metadata, None),
+            Err(BuildActionError::InvalidCargoWdkMetadata(_))
+        ));
+    }
+
+    #[test]
+    fn debug_profile_table_applies_to_dev_profile() {
+        let metadata = json!({
+            "cargo-wdk": {
+                "profile": {
+                    "debug": { "sign-mode": "test" },
+                    "release": { "sign-mode": "off", "include-pdb": false, "include-map": false }
+                }
+            }
+        });
+        let dev_settings = PackageSettings::from_package_metadata(&metadata, Some(&Profile::Dev))
+            .expect("metadata should be valid");
+        assert_eq!(
+            dev_settings.sign_mode,
+            Some(SignMode::Test {
+                verify_signature: false
+            })
+        );
+        assert_eq!(dev_settings.include_pdb, None);
+
+        let release_settings =
+            PackageSettings::from_package_metadata(&metadata, Some(&Profile::Release))
+                .expect("metadata should be valid");
+        assert_eq!(release_settings.sign_mode, Some(SignMode::Off));
+        assert_eq!(release_settings.include_pdb, Some(false));
+        assert_eq!(release_settings.include_map, Some(false));
+    }
+
+    #[test]
+    fn inf_files_must_be_inx_files() {
+        let metadata = json!({
+            "cargo-wdk": { "inf-files": ["infs/filter.inx"], "inf-catalog": "combined" }
+        });
+        let settings = PackageSettings::from_package_metadata(&metadata, None)
+            .expect("metadata should be valid");
+        assert_eq!(settings.inf_files, vec![PathBuf::from("infs/filter.inx")]);
+        assert_eq!(settings.inf_catalog, Some(InfCatalog::Combined));
+
+        let metadata = json!({ "cargo-wdk": { "inf-files": ["infs/filter.inf"] } });
+        assert!(matches!(
+            PackageSettings::from_package_metadata(&metadata, None),
+            Err(BuildActionError::InvalidCargoWdkMetadata(_))
+        ));
+        let metadata = json!({ "cargo-wdk": { "inf-catalog": "single" } });
+        assert!(matches!(
+            PackageSettings::from_package_metadata(&metadata, None),
+            Err(BuildActionError::InvalidCargoWdkMetadata(_))
+        ));
+    }
+
+    #[test]
+    fn inf_format_must_be_universal_or_legacy() {
+        let metadata = json!({
+            "cargo-wdk": {
+                "inf-format": "legacy",
+                "profile": { "release": { "inf-format": "universal" } }
+            }
+        });
+        let dev_settings = PackageSettings::from_package_metadata(&metadata, None)
+            .expect("metadata should be valid");
+        assert_eq!(dev_settings.inf_format, Some(InfFormat::Legacy));
+        let release_settings =
+            PackageSettings::from_package_metadata(&metadata, Some(&Profile::Release))
+                .expect("metadata should be valid");
+        assert_eq!(release_settings.inf_format, Some(InfFormat::Universal));
+
+        let metadata = json!({ "cargo-wdk": { "inf-format": "desktop" } });
+        assert!(matches!(
+            PackageSettings::from_package_metadata(&metadata, None),
+            Err(BuildActionError::InvalidCargoWdkMetadata(_))
+        ));
+    }
+
+    #[test]
+    fn catalog_exclude_must_be_file_name_patterns() {
+        let metadata = json!({
+            "cargo-wdk": {
+                "catalog-exclude": ["*.md", "*.map"],
+                "profile": { "release": { "catalog-exclude": ["*.md"] } }
+            }
+        });
+        let dev_settings = PackageSettings::from_package_metadata(&metadata, None)
+            .expect("metadata should be valid");
+        assert_eq!(
+            dev_settings
+                .catalog_exclude
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["*.md", "*.map"]
+        );
+        let release_settings =
+            PackageSettings::from_package_metadata(&metadata, Some(&Profile::Release))
+                .expect("metadata should be valid");
+        assert_eq!(release_settings.catalog_exclude.len(), 1);
+
+        for catalog_exclude in [json!("*.md"), json!(["docs/*.md"]), json!([""]), json!([1])] {
+            let metadata = json!({ "cargo-wdk": { "catalog-exclude": catalog_exclude } });
+            assert!(matches!(
+                PackageSettings::from_package_metadata(&metadata, None),
+                Err(BuildActionError::InvalidCargoWdkMetadata(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn run_clippy_must_be_a_boolean() {
+        let metadata = json!({
+            "cargo-wdk": { "run-clippy": true, "profile": { "release": { "run-clippy": false } } }
+        });
+        let dev_settings = PackageSettings::from_package_metadata(&metadata, None)
+            .expect("metadata should be valid");
+        assert_eq!(dev_settings.run_clippy, Some(true));
+        let release_settings =
+            PackageSettings::from_package_metadata(&metadata, Some(&Profile::Release))
+                .expect("metadata should be valid");
+        assert_eq!(release_settings.run_clippy, Some(false));
+
+        let metadata = json!({ "cargo-wdk": { "run-clippy": "deny" } });
+        assert!(matches!(
+            PackageSettings::from_package_metadata(&metadata, None),
+            Err(BuildActionError::InvalidCargoWdkMetadata(_))
+        ));
+    }
+
+    #[test]
+    fn driver_target_must_be_a_target_name() {
+        let metadata = json!({ "cargo-wdk": { "driver-target": "my_driver" } });
+        let settings = PackageSettings::from_package_metadata(&metadata, None)
+            .expect("metadata should be valid");
+        assert_eq!(settings.driver_target.as_deref(), Some("my_driver"));
+
+        for driver_target in [json!(""), json!(["my_driver"])] {
+            let metadata = json!({ "cargo-wdk": { "driver-target": driver_target } });
+            assert!(matches!(
+                PackageSettings::from_package_metadata(&metadata, None),
+                Err(BuildActionError::InvalidCargoWdkMetadata(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn auto_increment_driver_ver_must_be_a_boolean() {
+        let metadata = json!({
+            "cargo-wdk": { "profile": { "dev": { "auto-increment-driver-ver": true } } }
+        });
+        let settings = PackageSettings::from_package_metadata(&metadata, None)
+            .expect("metadata should be valid");
+        assert_eq!(settings.auto_increment_driver_ver, Some(true));
+        let release_settings =
+            PackageSettings::from_package_metadata(&metadata, Some(&Profile::Release))
+                .expect("metadata should be valid");
+        assert_eq!(release_settings.auto_increment_driver_ver, None);
+
+        let metadata = json!({ "cargo-wdk": { "auto-increment-driver-ver": "yes" } });
+        assert!(matches!(
+            PackageSettings::from_package_metadata(&metadata, None),
+            Err(BuildActionError::InvalidCargoWdkMetadata(_))
+        ));
+    }
+
+    #[test]
+    fn skip_stampinf_cannot_be_combined_with_auto_increment_driver_ver() {
+        let metadata = json!({ "cargo-wdk": { "skip-stampinf": true } });
+        let settings = PackageSettings::from_package_metadata(&metadata, None)
+            .expect("metadata should be valid");
+        assert_eq!(settings.skip_stampinf, Some(true));
+
+        let metadata = json!({
+            "cargo-wdk": {
+                "skip-stampinf": true,
+                "profile": { "dev": { "auto-increment-driver-ver": true } }
+            }
+        });
+        assert!(matches!(
+            PackageSettings::from_package_metadata(&metadata, None),
+            Err(BuildActionError::InvalidCargoWdkMetadata(_))
+        ));
+        let release_settings =
+            PackageSettings::from_package_metadata(&metadata, Some(&Profile::Release))
+                .expect("metadata should be valid");
+        assert_eq!(release_settings.skip_stampinf, Some(true));
+    }
+
+    #[test]
+    fn env_is_read_as_a_table_of_strings() {
+        let metadata = json!({
+            "cargo-wdk": {
+                "env": { "DRIVER_FLAVOR": "retail", "BINDGEN_EXTRA_CLANG_ARGS": "-DFOO" },
+                "profile": { "dev": { "env": { "DRIVER_FLAVOR": "checked" } } }
+            }
+        });
+        let release_settings =
+            PackageSettings::from_package_metadata(&metadata, Some(&Profile::Release))
+                .expect("metadata should be valid");
+        assert_eq!(
+            release_settings.env,
+            [
+                ("BINDGEN_EXTRA_CLANG_ARGS".to_string(), "-DFOO".to_string()),
+                ("DRIVER_FLAVOR".to_string(), "retail".to_string()),
+            ]
+        );
+        let dev_settings = PackageSettings::from_package_metadata(&metadata, None)
+            .expect("metadata should be valid");
+        assert_eq!(
+            dev_settings.env,
+            [("DRIVER_FLAVOR".to_string(), "checked".to_string())]
+        );
+
+        for env in [
+            json!({ "DRIVER_FLAVOR": 1 }),
+            json!({ "A=B": "c" }),
+            json!(["A"]),
+        ] {
+            let metadata = json!({ "cargo-wdk": { "env": env } });
+            assert!(matches!(
+                PackageSettings::from_package_metadata(&metadata, None),
+                Err(BuildActionError::InvalidCargoWdkMetadata(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn invalid_sign_mode_is_rejected() {
+        let metadata = json!({ "cargo-wdk": { "sign-mode": "production" } });
+        assert!(matches!(
+            PackageSettings::from_package_metadata(&metadata, None),
+            Err(BuildActionError::InvalidCargoWdkMetadata(_))
+        ));
+    }
+
+    #[test]
+    fn tool_search_paths_must_be_valid_path_entries() {
+        let metadata = json!({
+            "cargo-wdk": { "tool-search-paths": ["tools/bin", "C:/WDK/bin/x64"] }
+        });
+        let settings = PackageSettings::from_package_metadata(&metadata, None)
+            .expect("metadata should be valid");
+        assert_eq!(
+            settings.tool_search_paths,
+            vec![PathBuf::from("tools/bin"), PathBuf::from("C:/WDK/bin/x64")]
+        );
+
+        let metadata = json!({ "cargo-wdk": { "tool-search-paths": ["tools;bin"] } });
+        assert!(matches!(
+            PackageSettings::from_package_metadata(&metadata, None),
+            Err(BuildActionError::InvalidCargoWdkMetadata(_))
+        ));
+    }
+
+    #[test]
+    fn pre_build_is_read_as_a_shell_command_line_or_an_argv() {
+        for (pre_build, expected) in [
+            (
+                json!("codegen.cmd spec.json > out.h"),
+                vec!["cmd", "/C", "codegen.cmd spec.json > out.h"],
+            ),
+            (
+                json!(["cargo", "run", "-p", "codegen"]),
+                vec!["cargo", "run", "-p", "codegen"],
+            ),
+        ] {
+            let metadata = json!({ "cargo-wdk": { "pre-build": pre_build } });
+            let settings = PackageSettings::from_package_metadata(&metadata, None)
+                .expect("metadata should be valid");
+            assert_eq!(settings.pre_build, expected);
+        }
+
+        for pre_build in [json!(""), json!([]), json!([""]), json!(["codegen", 1]), json!(true)] {
+            let metadata = json!({ "cargo-wdk": { "pre-build": pre_build } });
+            assert!(
+                matches!(
+                    PackageSettings::from_package_metadata(&metadata, None),
+                    Err(BuildActionError::InvalidCargoWdkMetadata(_))
+                ),
+                "{pre_build}"
+            );
+        }
+    }
+
+    #[test]
+    fn out_dir_files_must_be_relative_paths_inside_out_dir() {
+        let metadata = json!({
+            "cargo-wdk": { "out-dir-files": ["driver_fragment.inf", "gen/strings.txt"] }
+        });
+        let settings = PackageSettings::from_package_metadata(&metadata, None)
+            .expect("metadata should be valid");
+        assert_eq!(
+            settings.out_dir_files,
+            vec![
+                PathBuf::from("driver_fragment.inf"),
+                PathBuf::from("gen/strings.txt")
+            ]
+        );
+
+        for path in ["", "../driver.inf", "C:/gen/driver.inf", "/driver.inf", "./driver.inf"] {
+            let metadata = json!({ "cargo-wdk": { "out-dir-files": [path] } });
+            assert!(
+                matches!(
+                    PackageSettings::from_package_metadata(&metadata, None),
+                    Err(BuildActionError::InvalidCargoWdkMetadata(_))
+                ),
+                "{path}"
+            );
+        }
+    }
+
+    #[test]
+    fn manifest_must_be_a_path() {
+        let metadata = json!({ "cargo-wdk": { "manifest": "host.manifest" } });
+        let settings = PackageSettings::from_package_metadata(&metadata, None)
+            .expect("metadata should be valid");
+        assert_eq!(settings.manifest, Some(PathBuf::from("host.manifest")));
+
+        for manifest in [json!(""), json!(["host.manifest"]), json!(true)] {
+            let metadata = json!({ "cargo-wdk": { "manifest": manifest } });
+            assert!(
+                matches!(
+                    PackageSettings::from_package_metadata(&metadata, None),
+                    Err(BuildActionError::InvalidCargoWdkMetadata(_))
+                ),
+                "{manifest}"
+            );
+        }
+    }
+
+    #[test]
+    fn checked_must_be_a_boolean() {
+        let metadata = json!({
+            "cargo-wdk": { "profile": { "release": { "checked": true } } }
+        });
+        let dev_settings = PackageSettings::from_package_metadata(&metadata, None)
+            .expect("metadata should be valid");
+        assert_eq!(dev_settings.checked, None);
+        let release_settings =
+            PackageSettings::from_package_metadata(&metadata, Some(&Profile::Release))
+                .expect("metadata should be valid");
+        assert_eq!(release_settings.checked, Some(true));
+
+        let metadata = json!({ "cargo-wdk": { "checked": "yes" } });
+        assert!(matches!(
+            PackageSettings::from_package_metadata(&metadata, None),
+            Err(BuildActionError::InvalidCargoWdkMetadata(_))
+        ));
+    }
+
+    #[test]
+    fn allowed_providers_must_be_an_array_of_names() {
+        let metadata = json!({ "cargo-wdk": { "allowed-providers": ["Contoso", "Fabrikam"] } });
+        let settings = PackageSettings::from_package_metadata(&metadata, None)
+            .expect("metadata should be valid");
+        assert_eq!(settings.allowed_providers, ["Contoso", "Fabrikam"]);
+
+        for allowed_providers in [json!("Contoso"), json!([""]), json!([1])] {
+            let metadata = json!({ "cargo-wdk": { "allowed-providers": allowed_providers } });
+            assert!(
+                matches!(
+                    PackageSettings::from_package_metadata(&metadata, None),
+                    Err(BuildActionError::InvalidCargoWdkMetadata(_))
+                ),
+                "{allowed_providers}"
+            );
+        }
+    }
+
+    #[test]
+    fn json_schema_describes_every_setting_and_the_profile_overrides() {
+        let schema = PackageSettings::json_schema();
+        let properties = schema["properties"]
+            .as_object()
+            .expect("schema should have properties");
+        for key in [
+            INF_FORMAT_KEY,
+            CATALOG_EXCLUDE_KEY,
+            SKIP_STAMPINF_KEY,
+            ENV_KEY,
+            PRE_BUILD_KEY,
+            OUT_DIR_FILES_KEY,
+            MANIFEST_KEY,
+            CHECKED_KEY,
+            ALLOWED_PROVIDERS_KEY,
+        ] {
+            assert!(properties.contains_key(key), "{key}");
+        }
+        let profile_properties =
+            &properties[PROFILE_OVERRIDES_KEY]["additionalProperties"]["properties"];
+        assert_eq!(profile_properties[SIGN_MODE_KEY], properties[SIGN_MODE_KEY]);
+        assert!(profile_properties.get(PROFILE_OVERRIDES_KEY).is_none());
+    }
+
+    #[test]
+    fn supported_wdk_is_read_from_wdk_versions() {
+        let metadata = json!({
+            "cargo-wdk": { "supported-wdk": { "min": "10.0.22621.0", "max": "10.0.26100.0" } }
+        });
+        let settings = PackageSettings::from_package_metadata(&metadata, None)
+            .expect("metadata should be valid");
+        assert_eq!(
+            settings.supported_wdk,
+            Some(SupportedWdk {
+                min: Some(22621),
+                max: Some(26100)
+            })
+        );
+    }
+}
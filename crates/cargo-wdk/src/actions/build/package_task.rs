@@ -8,10 +8,13 @@
 //! validating, verifying and generating artefacts for the driver package.
 
 use std::{
+    cell::RefCell,
+    collections::HashMap,
     ffi::{CStr, CString},
     marker::PhantomData,
     ops::RangeFrom,
     path::{Path, PathBuf},
+    process::Output,
     result::Result,
 };
 
@@ -28,7 +31,31 @@
 
 #[double]
 use crate::providers::{exec::CommandExec, fs::Fs, wdk_build::WdkBuild};
-use crate::{actions::build::error::PackageTaskError, providers::error::FileError};
+use crate::{
+    actions::{
+        build::{
+            catalog_exclude::CatalogExcludePattern,
+            driver_ver_version::DriverVerVersion,
+            error::PackageTaskError,
+            import_check,
+            install_summary::InstallSummary,
+            manifest::{self, DLL_MANIFEST_RESOURCE_ID},
+            os_version,
+            pe,
+            reproducible::DriverVer,
+            signing_pfx::SigningPfx,
+            test_cert::{self, TestCertValidity},
+            tool_log::ToolLog,
+            tool_paths::ToolPaths,
+            uninstall_script::{UNINSTALL_CMD_FILE_NAME, UNINSTALL_PS1_FILE_NAME, UninstallScript},
+            warnings::BuildWarnings,
+        },
+        eku,
+        inf::{self, InfFile},
+        signtool::{self, SigningCertificate},
+    },
+    providers::error::{CommandError, FileError},
+};
 
 // FIXME: This range is inclusive of 25798. Update with range end after
 // `/samples` flag is added to InfVerif CLI
@@ -36,6 +63,20 @@
 const WDR_TEST_CERT_STORE: &str = "WDRTestCertStore";
 const WDR_LOCAL_TEST_CERT: &str = "WDRLocalTestCert";
 const STAMPINF_VERSION_ENV_VAR: &str = "STAMPINF_VERSION";
+/// Name of the package sub folder the debugging collateral is copied to
+const DEBUG_FOLDER_NAME: &str = "debug";
+/// Target OS decoration of INF sections that `stampinf` resolves to the
+/// architecture being packaged
+const INF_ARCH_PLACEHOLDER_DECORATION: &str = "NT$ARCH$";
+/// Name of the WDF registry value that turns WDF verifier on
+const WDF_VERIFIER_REGISTRY_VALUE_NAME: &str = "VerifierOn";
+/// Extension of the files listed by the INF, e.g. localized string tables,
+/// that are copied into the package from the package root
+const INF_REFERENCED_FILE_EXTENSION: &str = "ini";
+/// Entry points the UMDF 2 host process looks up in a driver binary when it
+/// loads the driver. `FxDriverEntryUm` is provided by the `WdfDriverStubUm`
+/// library and calls the `DriverEntry` of the driver.
+const UMDF_REQUIRED_EXPORTS: [&str; 1] = ["FxDriverEntryUm"];
 
 /// Signing mode.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -45,11 +86,33 @@ pub enum SignMode {
     /// Sign with an auto-generated self-signed certificate.
     Test {
         /// When `true`, run `signtool verify` on the signed driver binary and
-        /// catalog file after signing.
+        /// catalog file after signing, and check that the signing certificate
+        /// has the code signing EKU.
         verify_signature: bool,
     },
 }
 
+/// Catalog files generated for the additional INF files of a package
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InfCatalog {
+    /// Every additional INF gets a catalog file named after it
+    #[default]
+    PerInf,
+    /// The additional INFs are covered by the catalog file of the package
+    Combined,
+}
+
+/// Format of the INF of a package. See <https://learn.microsoft.com/en-us/windows-hardware/drivers/install/using-a-universal-inf-file>
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InfFormat {
+    /// Universal INF, which may not use co-installers
+    #[default]
+    Universal,
+    /// Legacy INF, e.g. with co-installers, which is validated by `infverif`
+    /// for the desktop target platform
+    Legacy,
+}
+
 /// Platform at which the device driver is targeted. See <https://learn.microsoft.com/en-us/windows-hardware/drivers/develop/target-platforms>
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TargetPlatform {
@@ -69,7 +132,7 @@ const fn as_infverif_flag(self) -> &'static str {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PackageTaskParams<'a> {
     pub package_name: &'a str,
     pub working_dir: &'a Path,
@@ -79,21 +142,188 @@ pub struct PackageTaskParams<'a> {
     pub sample_class: bool,
     pub driver_model: DriverConfig,
     pub target_platform: TargetPlatform,
+    pub import_check: bool,
+    /// Whether the minimum OS version of the driver binary is reported and
+    /// compared against the OS versions targeted by the INF
+    pub os_version_check: bool,
+    /// Value of the WDF verifier registry setting in the packaged INF. `None`
+    /// leaves the value in the .inx file unchanged.
+    pub wdf_verifier: Option<bool>,
+    /// Collection the warnings printed by the packaging tools are recorded in
+    pub warnings: &'a BuildWarnings,
+    /// Log the output of the packaging tools is recorded in
+    pub tool_log: &'a ToolLog,
+    /// Debugging collateral to copy to the `debug` folder of the package.
+    /// Relative paths are relative to `working_dir`.
+    pub debug_files: &'a [PathBuf],
+    /// Name, without extension, of the driver binary, INF and catalog files in
+    /// the package. `None` uses the package name.
+    pub binary_name: Option<&'a str>,
+    /// Directory the packaging tools create their intermediate files in.
+    /// `None` leaves the `TMP` and `TEMP` environment variables unchanged.
+    pub temp_dir: Option<&'a Path>,
+    /// Directories prepended to `PATH` when running the packaging tools.
+    /// Relative paths are relative to `working_dir`.
+    pub tool_search_paths: &'a [PathBuf],
+    /// Executables run instead of the packaging tools found on `PATH`
+    pub tool_paths: &'a ToolPaths,
+    /// Whether the PDB of the driver is copied into the package
+    pub include_pdb: bool,
+    /// Whether the linker map of the driver is copied into the package
+    pub include_map: bool,
+    /// Fixed `DriverVer` of a reproducible package. When set, signatures are
+    /// not timestamped either. `None` stamps the INF with the current date and
+    /// time.
+    pub reproducible_driver_ver: Option<DriverVer>,
+    /// `DriverVer` version incremented by every build, stamped with the
+    /// current date unless the package is reproducible
+    pub incremented_driver_ver: Option<&'a str>,
+    /// Last released `DriverVer` version, which the stamped `DriverVer`
+    /// version of every INF must be greater than. `None` does not check the
+    /// version.
+    pub min_version: Option<DriverVerVersion>,
+    /// Validity of the test certificate. When set, a reused test certificate
+    /// that has expired is regenerated before signing and generated test
+    /// certificates are valid for the given number of days. `None` reuses
+    /// test certificates without checking them.
+    pub test_cert_validity: Option<TestCertValidity>,
+    /// PFX certificate to sign with instead of the test certificate, which is
+    /// then neither generated nor copied into the package
+    pub signing_pfx: Option<&'a SigningPfx>,
+    /// Driver binary built outside of cargo to package instead of the `.dll`
+    /// in `target_dir`. The binary is not renamed.
+    pub prebuilt_binary_path: Option<&'a Path>,
+    /// Target directories of every architecture to include in a single
+    /// multi-architecture package. Empty for a single architecture package.
+    pub multi_arch_target_dirs: &'a [(CpuArchitecture, PathBuf)],
+    /// Whether the INF and catalog of the package are checked for absolute
+    /// paths of the build machine
+    pub relocatable: bool,
+    /// `.inx` files of additional INFs packaged against the same driver
+    /// binary, e.g. one per device class. Relative paths are relative to
+    /// `working_dir`.
+    pub additional_inx_files: &'a [PathBuf],
+    /// Catalog files generated for the additional INFs
+    pub inf_catalog: InfCatalog,
+    /// Format of the INF. A universal INF may not use co-installers and must
+    /// pass the universal INF rules of `infverif`, while a legacy INF is
+    /// validated for the desktop target platform.
+    pub inf_format: InfFormat,
+    /// Whether an existing package folder that contains files which are not
+    /// part of the package is cleared instead of failing the packaging
+    pub overwrite_package: bool,
+    /// File name of the PDB in the package, e.g. the name the driver binary
+    /// references through `/PDBALTPATH`. `None` uses the package name.
+    pub pdb_file_name: Option<&'a str>,
+    /// Whether a markdown summary of the files, registry values and services
+    /// installed by the INFs is written to the package
+    pub emit_install_summary: bool,
+    /// Whether a report of the signer, thumbprint, timestamp and digest
+    /// algorithm of the signed catalogs is written to the package
+    pub emit_catalog_signing_report: bool,
+    /// Whether scripts that delete the installed driver packages and their
+    /// services are written to the package
+    pub emit_uninstall: bool,
+    /// Environment variables set for the packaging tools of this package only
+    pub env: &'a [(String, String)],
+    /// File name patterns of the files of the package that are left out of
+    /// the catalogs
+    pub catalog_exclude: &'a [CatalogExcludePattern],
+    /// Whether the INFs are packaged as checked in instead of being stamped
+    /// by `stampinf`
+    pub skip_stampinf: bool,
+    /// Whether only the signed driver binary and its PDB are packaged, without
+    /// the INFs and catalogs
+    pub binary_only: bool,
+    /// Folder the PDBs of the driver binaries are archived to in the layout
+    /// of a symbol store, if any
+    pub symbols_archive_dir: Option<&'a Path>,
+    /// `OUT_DIR` of the build script of the package, if it has one
+    pub out_dir: Option<&'a Path>,
+    /// Files generated by the build script to copy to the package root, as
+    /// paths relative to `out_dir`. Ignored without `out_dir`.
+    pub out_dir_files: &'a [PathBuf],
+    /// Application manifest embedded into the driver binary with `mt`, if
+    /// any. A relative path is relative to `working_dir`.
+    pub manifest: Option<&'a Path>,
+    /// Target directory the checked variant of the driver was built in. When
+    /// set, the package is the checked variant, which is created from the
+    /// binaries of this directory in `<name>_package_checked` next to
+    /// `<name>_package`. `None` packages the binaries of `target_dir`.
+    pub checked_target_dir: Option<&'a Path>,
+    /// `Provider` strings the INF may use. Empty if any provider is allowed.
+    pub allowed_providers: &'a [String],
+}
+
+/// Source and destination paths of an additional INF of a package
+#[derive(Debug)]
+struct AdditionalInfPaths {
+    src_inx_file_path: PathBuf,
+    dest_inf_file_path: PathBuf,
+    dest_cat_file_path: PathBuf,
+}
+
+/// Source and destination paths of the driver binary and its companion files
+/// for one architecture of a multi-architecture package
+#[derive(Debug)]
+struct ArchBinaryPaths {
+    arch: CpuArchitecture,
+    src_driver_binary_file_path: PathBuf,
+    src_renamed_driver_binary_file_path: PathBuf,
+    src_pdb_file_path: PathBuf,
+    src_map_file_path: PathBuf,
+    dest_arch_folder: PathBuf,
+    dest_driver_binary_path: PathBuf,
+    dest_pdb_file_path: PathBuf,
+    dest_map_file_path: PathBuf,
 }
 
 /// Supports low level driver packaging operations
 pub struct PackageTask<'a> {
     package_name: String,
+    binary_name: String,
     sign_mode: SignMode,
     sample_class: bool,
+    import_check: bool,
+    os_version_check: bool,
+    wdf_verifier: Option<bool>,
+    warnings: &'a BuildWarnings,
+    tool_log: &'a ToolLog,
+    temp_dir: Option<String>,
+    tool_path: Option<String>,
+    tool_paths: &'a ToolPaths,
+    include_pdb: bool,
+    include_map: bool,
+    reproducible_driver_ver: Option<DriverVer>,
+    incremented_driver_ver: Option<&'a str>,
+    min_version: Option<DriverVerVersion>,
+    test_cert_validity: Option<TestCertValidity>,
+    signing_pfx: Option<&'a SigningPfx>,
+    prebuilt_binary: bool,
+    relocatable: bool,
+    overwrite_package: bool,
+    emit_install_summary: bool,
+    emit_catalog_signing_report: bool,
+    emit_uninstall: bool,
+    env: &'a [(String, String)],
+    catalog_exclude: &'a [CatalogExcludePattern],
+    skip_stampinf: bool,
+    binary_only: bool,
+    symbols_archive_dir: Option<&'a Path>,
+    allowed_providers: &'a [String],
 
     // src paths
+    src_root_folder: &'a Path,
     src_inx_file_path: PathBuf,
     src_driver_binary_file_path: PathBuf,
     src_renamed_driver_binary_file_path: PathBuf,
     src_pdb_file_path: PathBuf,
     src_map_file_path: PathBuf,
     src_cert_file_path: PathBuf,
+    src_debug_file_paths: Vec<PathBuf>,
+    // files generated by the build script with the path they are copied to
+    out_dir_file_paths: Vec<(PathBuf, PathBuf)>,
+    src_manifest_file_path: Option<PathBuf>,
 
     // destination paths
     dest_root_package_folder: PathBuf,
@@ -102,12 +332,30 @@ pub struct PackageTask<'a> {
     dest_pdb_file_path: PathBuf,
     dest_map_file_path: PathBuf,
     dest_cert_file_path: PathBuf,
+    dest_install_summary_file_path: PathBuf,
+    dest_catalog_signing_report_file_path: PathBuf,
+    dest_uninstall_ps1_file_path: PathBuf,
+    dest_uninstall_cmd_file_path: PathBuf,
     dest_cat_file_path: PathBuf,
+    dest_debug_folder: PathBuf,
+    // folder the files excluded from the catalogs are moved to while inf2cat
+    // runs
+    catalog_excluded_folder: PathBuf,
+
+    // binaries of a multi-architecture package, empty otherwise
+    multi_arch_binaries: Vec<ArchBinaryPaths>,
+    // INFs packaged in addition to the INF of the package
+    additional_infs: Vec<AdditionalInfPaths>,
+    // files listed by the INF that are copied from the package root, as
+    // paths relative to the package root. Collected from the .inx file by
+    // `run`.
+    inf_referenced_files: RefCell<Vec<PathBuf>>,
 
     arch: &'a CpuArchitecture,
-    os_mapping: &'a str,
+    os_mapping: String,
     driver_model: DriverConfig,
     target_platform: TargetPlatform,
+    inf_format: InfFormat,
 
     // Injected deps
     wdk_build: &'a WdkBuild,
@@ -153,20 +401,30 @@ pub fn new(
             params.target_dir.display()
         );
         let package_name = params.package_name.replace('-', "_");
+        let binary_name = params
+            .binary_name
+            .map_or_else(|| package_name.clone(), ToString::to_string);
         // src paths
         let src_driver_binary_extension = "dll";
         let src_inx_file_path = params.working_dir.join(format!("{package_name}.inx"));
 
-        // all paths inside target directory
-        let src_driver_binary_file_path = params
-            .target_dir
-            .join(format!("{package_name}.{src_driver_binary_extension}"));
-        let src_pdb_file_path = params.target_dir.join(format!("{package_name}.pdb"));
-        let src_map_file_path = params
-            .target_dir
+        // all paths inside target directory. The binaries of the checked
+        // variant are read from, and renamed in, its own target directory.
+        let binary_target_dir = params.checked_target_dir.unwrap_or(params.target_dir);
+        let src_driver_binary_file_path = params.prebuilt_binary_path.map_or_else(
+            || binary_target_dir.join(format!("{package_name}.{src_driver_binary_extension}")),
+            Path::to_path_buf,
+        );
+        let src_pdb_file_path = binary_target_dir.join(format!("{package_name}.pdb"));
+        let src_map_file_path = binary_target_dir
             .join("deps")
             .join(format!("{package_name}.map"));
         let src_cert_file_path = params.target_dir.join(format!("{WDR_LOCAL_TEST_CERT}.cer"));
+        let src_debug_file_paths = params
+            .debug_files
+            .iter()
+            .map(|path| params.working_dir.join(path))
+            .collect();
 
         // destination paths
         let dest_driver_binary_extension = match params.driver_model {
@@ -174,52 +432,318 @@ pub fn new(
             DriverConfig::Umdf(_) => "dll",
         };
 
-        let src_renamed_driver_binary_file_path = params
+        let src_renamed_driver_binary_file_path = params.prebuilt_binary_path.map_or_else(
+            || binary_target_dir.join(format!("{package_name}.{dest_driver_binary_extension}")),
+            Path::to_path_buf,
+        );
+        let package_folder_suffix = if params.checked_target_dir.is_some() {
+            "_checked"
+        } else {
+            ""
+        };
+        let dest_root_package_folder: PathBuf = params
             .target_dir
-            .join(format!("{package_name}.{dest_driver_binary_extension}"));
-        let dest_root_package_folder: PathBuf =
-            params.target_dir.join(format!("{package_name}_package"));
-        let dest_inf_file_path = dest_root_package_folder.join(format!("{package_name}.inf"));
+            .join(format!("{package_name}_package{package_folder_suffix}"));
+        let dest_inf_file_path = dest_root_package_folder.join(format!("{binary_name}.inf"));
         let dest_driver_binary_path =
-            dest_root_package_folder.join(format!("{package_name}.{dest_driver_binary_extension}"));
-        let dest_pdb_file_path = dest_root_package_folder.join(format!("{package_name}.pdb"));
+            dest_root_package_folder.join(format!("{binary_name}.{dest_driver_binary_extension}"));
+        let pdb_file_name = params
+            .pdb_file_name
+            .map_or_else(|| format!("{package_name}.pdb"), ToString::to_string);
+        let dest_pdb_file_path = dest_root_package_folder.join(&pdb_file_name);
         let dest_map_file_path = dest_root_package_folder.join(format!("{package_name}.map"));
+        let dest_install_summary_file_path =
+            dest_root_package_folder.join(format!("{binary_name}.install.md"));
+        let dest_catalog_signing_report_file_path =
+            dest_root_package_folder.join(format!("{binary_name}.cat-signing.md"));
+        let dest_uninstall_ps1_file_path = dest_root_package_folder.join(UNINSTALL_PS1_FILE_NAME);
+        let dest_uninstall_cmd_file_path = dest_root_package_folder.join(UNINSTALL_CMD_FILE_NAME);
         let dest_cert_file_path =
             dest_root_package_folder.join(format!("{WDR_LOCAL_TEST_CERT}.cer"));
-        let dest_cat_file_path = dest_root_package_folder.join(format!("{package_name}.cat"));
+        let dest_cat_file_path = dest_root_package_folder.join(format!("{binary_name}.cat"));
+        let dest_debug_folder = dest_root_package_folder.join(DEBUG_FOLDER_NAME);
+        let out_dir_file_paths = params.out_dir.map_or_else(Vec::new, |out_dir| {
+            params
+                .out_dir_files
+                .iter()
+                .map(|file| (out_dir.join(file), dest_root_package_folder.join(file)))
+                .collect()
+        });
+        let src_manifest_file_path = params
+            .manifest
+            .map(|manifest| params.working_dir.join(manifest));
+        let catalog_excluded_folder = params
+            .target_dir
+            .join(format!("{package_name}_catalog_excluded"));
+
+        let multi_arch_binaries: Vec<ArchBinaryPaths> = params
+            .multi_arch_target_dirs
+            .iter()
+            .map(|(arch, arch_target_dir)| {
+                let dest_arch_folder = dest_root_package_folder.join(arch.to_string());
+                ArchBinaryPaths {
+                    arch: *arch,
+                    src_driver_binary_file_path: arch_target_dir
+                        .join(format!("{package_name}.{src_driver_binary_extension}")),
+                    src_renamed_driver_binary_file_path: arch_target_dir
+                        .join(format!("{package_name}.{dest_driver_binary_extension}")),
+                    src_pdb_file_path: arch_target_dir.join(format!("{package_name}.pdb")),
+                    src_map_file_path: arch_target_dir
+                        .join("deps")
+                        .join(format!("{package_name}.map")),
+                    dest_driver_binary_path: dest_arch_folder
+                        .join(format!("{binary_name}.{dest_driver_binary_extension}")),
+                    dest_pdb_file_path: dest_arch_folder.join(&pdb_file_name),
+                    dest_map_file_path: dest_arch_folder.join(format!("{package_name}.map")),
+                    dest_arch_folder,
+                }
+            })
+            .collect();
 
-        let os_mapping = match params.target_arch {
-            CpuArchitecture::Amd64 => "10_x64",
-            CpuArchitecture::Arm64 => "Server10_arm64",
+        let additional_infs = params
+            .additional_inx_files
+            .iter()
+            .map(|inx_file_path| {
+                let inf_name = inx_file_path
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy();
+                AdditionalInfPaths {
+                    src_inx_file_path: params.working_dir.join(inx_file_path),
+                    dest_inf_file_path: dest_root_package_folder.join(format!("{inf_name}.inf")),
+                    dest_cat_file_path: match params.inf_catalog {
+                        InfCatalog::PerInf => {
+                            dest_root_package_folder.join(format!("{inf_name}.cat"))
+                        }
+                        InfCatalog::Combined => dest_cat_file_path.clone(),
+                    },
+                }
+            })
+            .collect();
+
+        let os_mapping = if multi_arch_binaries.is_empty() {
+            inf2cat_os(*params.target_arch).to_string()
+        } else {
+            multi_arch_binaries
+                .iter()
+                .map(|binary| inf2cat_os(binary.arch))
+                .collect::<Vec<_>>()
+                .join(",")
         };
 
         Self {
             package_name,
+            binary_name,
             sign_mode: params.sign_mode,
             sample_class: params.sample_class,
+            import_check: params.import_check,
+            os_version_check: params.os_version_check,
+            wdf_verifier: params.wdf_verifier,
+            warnings: params.warnings,
+            tool_log: params.tool_log,
+            temp_dir: params
+                .temp_dir
+                .map(|temp_dir| temp_dir.to_string_lossy().into_owned()),
+            tool_path: tool_path(params.working_dir, params.tool_search_paths),
+            tool_paths: params.tool_paths,
+            include_pdb: params.include_pdb,
+            include_map: params.include_map,
+            reproducible_driver_ver: params.reproducible_driver_ver,
+            incremented_driver_ver: params.incremented_driver_ver,
+            min_version: params.min_version,
+            test_cert_validity: params.test_cert_validity,
+            signing_pfx: params.signing_pfx,
+            prebuilt_binary: params.prebuilt_binary_path.is_some(),
+            relocatable: params.relocatable,
+            overwrite_package: params.overwrite_package,
+            emit_install_summary: params.emit_install_summary,
+            emit_catalog_signing_report: params.emit_catalog_signing_report,
+            emit_uninstall: params.emit_uninstall,
+            env: params.env,
+            catalog_exclude: params.catalog_exclude,
+            skip_stampinf: params.skip_stampinf,
+            binary_only: params.binary_only,
+            symbols_archive_dir: params.symbols_archive_dir,
+            allowed_providers: params.allowed_providers,
+            src_root_folder: params.working_dir,
             src_inx_file_path,
             src_driver_binary_file_path,
             src_renamed_driver_binary_file_path,
             src_pdb_file_path,
             src_map_file_path,
             src_cert_file_path,
+            src_debug_file_paths,
+            out_dir_file_paths,
+            src_manifest_file_path,
             dest_root_package_folder,
             dest_inf_file_path,
             dest_driver_binary_path,
             dest_pdb_file_path,
             dest_map_file_path,
             dest_cert_file_path,
+            dest_install_summary_file_path,
+            dest_catalog_signing_report_file_path,
+            dest_uninstall_ps1_file_path,
+            dest_uninstall_cmd_file_path,
             dest_cat_file_path,
+            dest_debug_folder,
+            catalog_excluded_folder,
+            multi_arch_binaries,
+            additional_infs,
+            inf_referenced_files: RefCell::default(),
             arch: params.target_arch,
             os_mapping,
             driver_model: params.driver_model,
             target_platform: params.target_platform,
+            inf_format: params.inf_format,
             wdk_build,
             command_exec,
             fs,
         }
     }
 
+    /// Returns the path of the driver package folder
+    pub fn dest_root_package_folder(&self) -> &Path {
+        &self.dest_root_package_folder
+    }
+
+    /// Returns the path of the INF file in the driver package
+    pub fn dest_inf_file_path(&self) -> &Path {
+        &self.dest_inf_file_path
+    }
+
+    /// Returns the paths of the files written to the driver package folder by
+    /// [`PackageTask::run`], i.e. the driver binaries with their enabled debug
+    /// symbols, the INF and catalog files including those of the additional
+    /// INFs, the test certificate when signing with it, the configured debug
+    /// files and the files listed by the INF that were collected by the run.
+    /// Binary only packages contain the driver binary, its PDB and the test
+    /// certificate.
+    pub fn dest_file_paths(&self) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        if self.binary_only {
+            paths.push(self.dest_driver_binary_path.clone());
+            if self.include_pdb {
+                paths.push(self.dest_pdb_file_path.clone());
+            }
+            if self.packages_test_cert() {
+                paths.push(self.dest_cert_file_path.clone());
+            }
+            return paths;
+        }
+        let binaries: Vec<(&Path, &Path, &Path)> = if self.multi_arch_binaries.is_empty() {
+            vec![(
+                &self.dest_driver_binary_path,
+                &self.dest_pdb_file_path,
+                &self.dest_map_file_path,
+            )]
+        } else {
+            self.multi_arch_binaries
+                .iter()
+                .map(|binary| {
+                    (
+                        binary.dest_driver_binary_path.as_path(),
+                        binary.dest_pdb_file_path.as_path(),
+                        binary.dest_map_file_path.as_path(),
+                    )
+                })
+                .collect()
+        };
+        for (driver_binary_path, pdb_file_path, map_file_path) in binaries {
+            paths.push(driver_binary_path.to_path_buf());
+            if self.include_pdb {
+                paths.push(pdb_file_path.to_path_buf());
+            }
+            if self.include_map {
+                paths.push(map_file_path.to_path_buf());
+            }
+        }
+        paths.extend(self.dest_inf_file_paths().map(Path::to_path_buf));
+        paths.extend(
+            self.dest_cat_file_paths()
+                .into_iter()
+                .map(Path::to_path_buf),
+        );
+        if self.packages_test_cert() {
+            paths.push(self.dest_cert_file_path.clone());
+        }
+        if matches!(self.sign_mode, SignMode::Test { .. }) && self.emit_catalog_signing_report {
+            paths.push(self.dest_catalog_signing_report_file_path.clone());
+        }
+        if self.emit_install_summary {
+            paths.push(self.dest_install_summary_file_path.clone());
+        }
+        if self.emit_uninstall {
+            paths.push(self.dest_uninstall_ps1_file_path.clone());
+            paths.push(self.dest_uninstall_cmd_file_path.clone());
+        }
+        paths.extend(self.src_debug_file_paths.iter().filter_map(|path| {
+            path.file_name()
+                .map(|file_name| self.dest_debug_folder.join(file_name))
+        }));
+        paths.extend(
+            self.out_dir_file_paths
+                .iter()
+                .map(|(_, dest_file_path)| dest_file_path.clone()),
+        );
+        paths.extend(
+            self.inf_referenced_files
+                .borrow()
+                .iter()
+                .map(|file| self.dest_root_package_folder.join(file)),
+        );
+        paths
+    }
+
+    /// Returns the paths of the packaged driver binaries, one per architecture
+    /// for a multi-architecture package
+    fn dest_driver_binary_paths(&self) -> Vec<&Path> {
+        if self.multi_arch_binaries.is_empty() {
+            return vec![self.dest_driver_binary_path.as_path()];
+        }
+        self.multi_arch_binaries
+            .iter()
+            .map(|binary| binary.dest_driver_binary_path.as_path())
+            .collect()
+    }
+
+    /// Returns the paths of the packaged INF files, the INF of the package
+    /// first
+    fn dest_inf_file_paths(&self) -> impl Iterator<Item = &Path> {
+        std::iter::once(self.dest_inf_file_path.as_path()).chain(
+            self.additional_infs
+                .iter()
+                .map(|inf| inf.dest_inf_file_path.as_path()),
+        )
+    }
+
+    /// Returns the paths of the packaged INF files along with the catalog file
+    /// covering each of them
+    fn dest_inf_and_cat_file_paths(&self) -> impl Iterator<Item = (&Path, &Path)> {
+        std::iter::once((
+            self.dest_inf_file_path.as_path(),
+            self.dest_cat_file_path.as_path(),
+        ))
+        .chain(self.additional_infs.iter().map(|inf| {
+            (
+                inf.dest_inf_file_path.as_path(),
+                inf.dest_cat_file_path.as_path(),
+            )
+        }))
+    }
+
+    /// Returns the paths of the catalog files of the package, the catalog of
+    /// the package first. A combined catalog is only listed once.
+    fn dest_cat_file_paths(&self) -> Vec<&Path> {
+        let mut cat_file_paths: Vec<&Path> = Vec::new();
+        for (_, cat_file_path) in self.dest_inf_and_cat_file_paths() {
+            if !cat_file_paths.contains(&cat_file_path) {
+                cat_file_paths.push(cat_file_path);
+            }
+        }
+        cat_file_paths
+    }
+
     /// Entry point method to run the low level driver packaging operations.
     /// # Returns
     /// * `Result<(), PackageTaskError>` - A result indicating success or
@@ -231,18 +755,67 @@ pub fn new(
     ///   error generating a certificate in the store.
     /// * `PackageTaskError::CreateCertFileFromStoreCommand` - If there is an
     ///   error creating a certificate file from the store.
+    /// * `PackageTaskError::DeleteCertFromStoreCommand` - If there is an error
+    ///   deleting an expired test certificate from the store.
+    /// * `PackageTaskError::TestCertValidityInvalidCommandOutput` - If the
+    ///   validity period of the test certificate cannot be read.
+    /// * `PackageTaskError::ExpiredTestCert` - If the test certificate is
+    ///   still expired after regenerating it.
     /// * `PackageTaskError::DriverBinarySignCommand` - If there is an error
     ///   signing the driver binary.
     /// * `PackageTaskError::DriverBinarySignVerificationCommand` - If there is
     ///   an error verifying the driver binary signature.
+    /// * `PackageTaskError::CertutilDumpCommand` - If there is an error reading
+    ///   the signing certificate when verifying the signatures.
+    /// * `PackageTaskError::CertutilDumpInvalidCommandOutput` - If the certutil
+    ///   output is not valid UTF-8.
+    /// * `PackageTaskError::MissingCodeSigningEku` - If the signing certificate
+    ///   lacks the code signing EKU.
+    /// * `PackageTaskError::InvalidDriverBinary` - If the driver binary is not
+    ///   a PE image.
+    /// * `PackageTaskError::DriverBinaryArchMismatch` - If the driver binary
+    ///   was built for a different architecture than the package.
+    /// * `PackageTaskError::MissingUmdfExports` - If a UMDF driver binary does
+    ///   not export the entry points the UMDF host process requires.
     /// * `PackageTaskError::Inf2CatCommand` - If there is an error running the
     ///   inf2cat command to generate the cat file.
+    /// * `PackageTaskError::CatFileNotGenerated` - If inf2cat succeeds without
+    ///   generating the cat file.
     /// * `PackageTaskError::InfVerificationCommand` - If there is an error
     ///   verifying the inf file.
+    /// * `PackageTaskError::UniversalInfViolations` - If a universal INF
+    ///   violates the rules of universal INFs checked by infverif.
+    /// * `PackageTaskError::DumpbinImportsCommand` - If there is an error
+    ///   listing the imports of the driver binary when the import check is
+    ///   enabled.
+    /// * `PackageTaskError::DumpbinImportsInvalidCommandOutput` - If the
+    ///   dumpbin output is not valid UTF-8.
+    /// * `PackageTaskError::DisallowedImports` - If the driver binary imports
+    ///   functions that are unavailable on the oldest Windows build the driver
+    ///   targets.
+    /// * `PackageTaskError::DumpbinHeadersCommand` - If there is an error
+    ///   reading the headers of the driver binary when the OS version check is
+    ///   enabled.
+    /// * `PackageTaskError::DumpbinHeadersInvalidCommandOutput` - If the
+    ///   dumpbin output is not valid UTF-8.
+    /// * `PackageTaskError::WdfVerifierRegistryValueNotFound` - If a WDF
+    ///   verifier setting is configured but the inf file has no `VerifierOn`
+    ///   registry entry to apply it to.
+    /// * `PackageTaskError::MissingDebugFiles` - If any of the configured debug
+    ///   files does not exist.
     /// * `PackageTaskError::MissingInxSrcFile` - If the .inx source file is
     ///   missing.
+    /// * `PackageTaskError::CatalogExcludesRequiredFile` - If a catalog exclude
+    ///   pattern matches a driver binary, INF or catalog file of the package.
+    /// * `PackageTaskError::UnexpectedFilesInPackageFolder` - If the existing
+    ///   package folder contains files that are not part of the package and
+    ///   `overwrite_package` is not set.
     /// * `PackageTaskError::StampinfCommand` - If there is an error running the
     ///   stampinf command to generate the inf file from the .inx template file.
+    /// * `PackageTaskError::MissingDriverVerVersion` - If a minimum version is
+    ///   given and a stamped INF has no `DriverVer` version.
+    /// * `PackageTaskError::DriverVerVersionNotGreater` - If the stamped
+    ///   `DriverVer` version of an INF is not greater than the minimum version.
     /// * `PackageTaskError::VerifyCertExistsInStoreCommand` - If there is an
     ///   error verifying if the certificate exists in the store.
     /// * `PackageTaskError::VerifyCertExistsInStoreInvalidCommandOutput`
@@ -252,692 +825,4693 @@ pub fn new(
     ///   the WDK build number.
     /// * `PackageTaskError::Io` - Wraps all possible IO errors.
     pub fn run(&self) -> Result<(), PackageTaskError> {
+        if !self.multi_arch_binaries.is_empty() {
+            return self.run_multi_arch();
+        }
+        if self.binary_only {
+            return self.run_binary_only();
+        }
         self.check_inx_exists()?;
-        debug!("Creating final package directory if it doesn't exist");
-        if !self.fs.exists(&self.dest_root_package_folder) {
-            self.fs.create_dir(&self.dest_root_package_folder)?;
+        let inx_file = self.read_inx_file()?;
+        self.check_inx_catalog_os_decoration(&inx_file)?;
+        self.check_inx_setup_class(&inx_file)?;
+        self.check_inx_format(&inx_file)?;
+        self.check_inx_provider(&inx_file)?;
+        self.collect_inf_referenced_files(&inx_file)?;
+        self.check_catalog_exclude()?;
+        if self.skip_stampinf {
+            self.check_inx_driver_ver(&inx_file);
         }
+        self.check_driver_binary(&self.src_driver_binary_file_path, *self.arch)?;
+        self.check_manifest()?;
+        debug!("Creating final package directory if it doesn't exist");
+        self.prepare_package_folder()?;
         info!(
             "Copying files to target package folder: {}",
             self.dest_root_package_folder.to_string_lossy()
         );
-        self.rename_driver_binary_extension()?;
+        if !self.prebuilt_binary {
+            self.rename_driver_binary_extension()?;
+        }
         self.copy(
             &self.src_renamed_driver_binary_file_path,
             &self.dest_driver_binary_path,
         )?;
-        self.copy(&self.src_pdb_file_path, &self.dest_pdb_file_path)?;
+        self.embed_manifest(&self.dest_driver_binary_path)?;
+        if self.include_pdb {
+            self.copy(&self.src_pdb_file_path, &self.dest_pdb_file_path)?;
+        }
+        self.archive_symbols(&self.dest_driver_binary_path, &self.src_pdb_file_path)?;
         self.copy(&self.src_inx_file_path, &self.dest_inf_file_path)?;
-        self.copy(&self.src_map_file_path, &self.dest_map_file_path)?;
+        self.copy_additional_infs()?;
+        if self.include_map {
+            self.copy(&self.src_map_file_path, &self.dest_map_file_path)?;
+        }
+        self.copy_debug_files()?;
+        self.copy_out_dir_files()?;
+        self.copy_inf_referenced_files()?;
+        if self.import_check {
+            self.run_import_check(&self.dest_driver_binary_path)?;
+        }
+        if self.os_version_check {
+            self.run_os_version_check(&self.dest_driver_binary_path)?;
+        }
+        if self.binary_name != self.package_name {
+            self.rename_inf_binary_references()?;
+        }
         self.run_stampinf()?;
+        self.check_min_version()?;
+        if let Some(wdf_verifier) = self.wdf_verifier {
+            self.apply_wdf_verifier_setting(wdf_verifier)?;
+        }
+        if self.relocatable {
+            self.check_inf_is_relocatable()?;
+        }
         self.run_inf2cat()?;
+        if self.relocatable {
+            self.check_catalog_is_relocatable()?;
+        }
         self.run_infverif()?;
+        if self.emit_install_summary {
+            self.write_install_summary()?;
+        }
+        if self.emit_uninstall {
+            self.write_uninstall_scripts()?;
+        }
         self.sign_and_verify()?;
         Ok(())
     }
 
-    /// Signs the driver binary and catalog file according to `self.sign_mode`
-    /// and optionally verifies the resulting signatures. Returns a variant of
-    /// `PackageTaskError` if any step of the process fails.
-    fn sign_and_verify(&self) -> Result<(), PackageTaskError> {
-        let SignMode::Test { verify_signature } = self.sign_mode else {
-            info!("Sign mode is 'off'; skipping signing");
-            return Ok(());
-        };
-
-        self.generate_certificate()?;
-        self.copy(&self.src_cert_file_path, &self.dest_cert_file_path)?;
-        self.run_signtool_sign(
-            &self.dest_driver_binary_path,
-            WDR_TEST_CERT_STORE,
-            WDR_LOCAL_TEST_CERT,
-        )?;
-        self.run_signtool_sign(
-            &self.dest_cat_file_path,
-            WDR_TEST_CERT_STORE,
-            WDR_LOCAL_TEST_CERT,
-        )?;
-
-        if verify_signature {
-            info!("Verifying signatures for driver binary and cat file using signtool");
-            self.run_signtool_verify(&self.dest_driver_binary_path)?;
-            self.run_signtool_verify(&self.dest_cat_file_path)?;
+    /// Copies the .inx files of the package to its package folder as INFs and
+    /// stamps them with `stampinf`, the same way `run` does, and reports the
+    /// stamped `DriverVer` of each INF. The driver binaries are not packaged
+    /// and no catalog is generated or signed.
+    /// # Errors
+    /// * `PackageTaskError::MissingInxSrcFile` - If the .inx source file is
+    ///   missing.
+    /// * `PackageTaskError::UnexpectedFilesInPackageFolder` - If the existing
+    ///   package folder contains files that are not part of the package and
+    ///   `overwrite_package` is not set.
+    /// * `PackageTaskError::StampinfCommand` - If there is an error running the
+    ///   stampinf command.
+    /// * `PackageTaskError::DriverVerVersionNotGreater` - If the stamped
+    ///   `DriverVer` version of an INF is not greater than the minimum version.
+    /// * `PackageTaskError::Io` - Wraps all possible IO errors.
+    pub fn run_stamp_only(&self) -> Result<(), PackageTaskError> {
+        self.check_inx_exists()?;
+        debug!("Creating final package directory if it doesn't exist");
+        self.prepare_package_folder()?;
+        self.copy(&self.src_inx_file_path, &self.dest_inf_file_path)?;
+        self.copy_additional_infs()?;
+        if self.binary_name != self.package_name {
+            self.rename_inf_binary_references()?;
+        }
+        self.run_stampinf()?;
+        self.check_min_version()?;
+        for dest_inf_file_path in self.dest_inf_file_paths() {
+            let inf_file = InfFile::parse(&self.fs.read_file_to_string(dest_inf_file_path)?);
+            match inf_file.driver_ver() {
+                Some(driver_ver) => info!(
+                    "Stamped {} with DriverVer = {}",
+                    dest_inf_file_path.display(),
+                    driver_ver.join(",")
+                ),
+                None => warn!("{} has no DriverVer", dest_inf_file_path.display()),
+            }
         }
-
         Ok(())
     }
 
-    fn check_inx_exists(&self) -> Result<(), PackageTaskError> {
-        debug!(
-            "Checking for .inx file, path: {}",
-            self.src_inx_file_path.to_string_lossy()
+    /// Packages the driver binary, and its PDB if `include_pdb` is set, without
+    /// INFs or catalogs and signs it. The signature of the binary is verified
+    /// on its own, since there is no catalog it is a member of.
+    fn run_binary_only(&self) -> Result<(), PackageTaskError> {
+        self.check_driver_binary(&self.src_driver_binary_file_path, *self.arch)?;
+        self.check_manifest()?;
+        debug!("Creating final package directory if it doesn't exist");
+        self.prepare_package_folder()?;
+        info!(
+            "Copying the driver binary to target package folder: {}",
+            self.dest_root_package_folder.to_string_lossy()
         );
-        if !self.fs.exists(&self.src_inx_file_path) {
-            return Err(PackageTaskError::MissingInxSrcFile(
-                self.src_inx_file_path.clone(),
-            ));
+        if !self.prebuilt_binary {
+            self.rename_driver_binary_extension()?;
         }
-        Ok(())
-    }
-
-    fn rename_driver_binary_extension(&self) -> Result<(), FileError> {
-        debug!("Renaming driver binary extension from .dll to .sys");
-        self.fs.rename(
-            &self.src_driver_binary_file_path,
+        self.copy(
             &self.src_renamed_driver_binary_file_path,
-        )
+            &self.dest_driver_binary_path,
+        )?;
+        self.embed_manifest(&self.dest_driver_binary_path)?;
+        if self.include_pdb {
+            self.copy(&self.src_pdb_file_path, &self.dest_pdb_file_path)?;
+        }
+        self.archive_symbols(&self.dest_driver_binary_path, &self.src_pdb_file_path)?;
+        if self.import_check {
+            self.run_import_check(&self.dest_driver_binary_path)?;
+        }
+        if self.os_version_check {
+            self.run_os_version_check(&self.dest_driver_binary_path)?;
+        }
+        self.sign_and_verify()
     }
 
-    fn copy(&self, src_file_path: &'a Path, dest_file_path: &'a Path) -> Result<u64, FileError> {
-        debug!(
-            "Copying src file {} to dest folder {}",
-            src_file_path.to_string_lossy(),
-            dest_file_path.to_string_lossy()
-        );
-        self.fs.copy(src_file_path, dest_file_path)
+    /// Runs the packaging operations for a multi-architecture package. The
+    /// binaries of every architecture are copied to their own sub folder of
+    /// the package folder and a single catalog file covering all of them is
+    /// generated and signed.
+    fn run_multi_arch(&self) -> Result<(), PackageTaskError> {
+        self.check_inx_exists()?;
+        let inx_file = self.read_inx_file()?;
+        self.check_inx_arch_decorations(&inx_file)?;
+        self.check_inx_setup_class(&inx_file)?;
+        self.check_inx_format(&inx_file)?;
+        self.check_inx_provider(&inx_file)?;
+        self.collect_inf_referenced_files(&inx_file)?;
+        self.check_catalog_exclude()?;
+        if self.skip_stampinf {
+            self.check_inx_driver_ver(&inx_file);
+        }
+        for binary in &self.multi_arch_binaries {
+            self.check_driver_binary(&binary.src_driver_binary_file_path, binary.arch)?;
+        }
+        self.check_manifest()?;
+        debug!("Creating final multi-architecture package directory if it doesn't exist");
+        self.prepare_package_folder()?;
+        for binary in &self.multi_arch_binaries {
+            info!(
+                "Copying {} files to target package folder: {}",
+                binary.arch,
+                binary.dest_arch_folder.to_string_lossy()
+            );
+            if !self.fs.exists(&binary.dest_arch_folder) {
+                self.fs.create_dir(&binary.dest_arch_folder)?;
+            }
+            self.fs.rename(
+                &binary.src_driver_binary_file_path,
+                &binary.src_renamed_driver_binary_file_path,
+            )?;
+            self.copy(
+                &binary.src_renamed_driver_binary_file_path,
+                &binary.dest_driver_binary_path,
+            )?;
+            self.embed_manifest(&binary.dest_driver_binary_path)?;
+            if self.include_pdb {
+                self.copy(&binary.src_pdb_file_path, &binary.dest_pdb_file_path)?;
+            }
+            self.archive_symbols(&binary.dest_driver_binary_path, &binary.src_pdb_file_path)?;
+            if self.include_map {
+                self.copy(&binary.src_map_file_path, &binary.dest_map_file_path)?;
+            }
+        }
+        self.copy(&self.src_inx_file_path, &self.dest_inf_file_path)?;
+        self.copy_additional_infs()?;
+        self.copy_debug_files()?;
+        self.copy_out_dir_files()?;
+        self.copy_inf_referenced_files()?;
+        if self.import_check {
+            for binary in &self.multi_arch_binaries {
+                self.run_import_check(&binary.dest_driver_binary_path)?;
+            }
+        }
+        if self.os_version_check {
+            for binary in &self.multi_arch_binaries {
+                self.run_os_version_check(&binary.dest_driver_binary_path)?;
+            }
+        }
+        if self.binary_name != self.package_name {
+            self.rename_inf_binary_references()?;
+        }
+        self.run_stampinf()?;
+        self.check_min_version()?;
+        if let Some(wdf_verifier) = self.wdf_verifier {
+            self.apply_wdf_verifier_setting(wdf_verifier)?;
+        }
+        if self.relocatable {
+            self.check_inf_is_relocatable()?;
+        }
+        self.run_inf2cat()?;
+        if self.relocatable {
+            self.check_catalog_is_relocatable()?;
+        }
+        self.run_infverif()?;
+        if self.emit_install_summary {
+            self.write_install_summary()?;
+        }
+        if self.emit_uninstall {
+            self.write_uninstall_scripts()?;
+        }
+        self.sign_and_verify()
     }
 
-    fn run_stampinf(&self) -> Result<(), PackageTaskError> {
-        info!("Running stampinf");
-        let wdf_version_flags = match self.driver_model {
-            DriverConfig::Kmdf(kmdf_config) => {
-                vec![
-                    "-k".to_string(),
-                    format!(
-                        "{}.{}",
-                        kmdf_config.kmdf_version_major, kmdf_config.target_kmdf_version_minor
-                    ),
-                ]
+    /// Creates the package folder if it does not exist. An existing package
+    /// folder may only contain the files of the package, e.g. from a previous
+    /// build with the same configuration, which are overwritten. Otherwise
+    /// packaging fails, or the folder is cleared if `overwrite_package` is set,
+    /// so that stale or foreign files are not mixed into the package.
+    fn prepare_package_folder(&self) -> Result<(), PackageTaskError> {
+        if self.fs.exists(&self.dest_root_package_folder) {
+            let package_files = self.dest_file_paths();
+            let mut unexpected_files = Vec::new();
+            self.collect_unexpected_files(
+                &self.dest_root_package_folder,
+                &package_files,
+                &mut unexpected_files,
+            )?;
+            if unexpected_files.is_empty() {
+                return Ok(());
             }
-            DriverConfig::Umdf(umdf_config) => vec![
-                "-u".to_string(),
-                format!(
-                    "{}.{}.0",
-                    umdf_config.umdf_version_major, umdf_config.target_umdf_version_minor
-                ),
-            ],
-            DriverConfig::Wdm => vec![],
-        };
-        // TODO: Does it generate cat file relative to inf file path or we need to
-        // provide the absolute path?
-        let cat_file_path = format!("{}.cat", self.package_name);
-        let dest_inf_file_path = self.dest_inf_file_path.to_string_lossy();
-        let arch = self.arch.to_string();
-        let mut args: Vec<&str> = vec![
-            "-f",
-            &dest_inf_file_path,
-            "-d",
-            "*",
-            "-a",
-            &arch,
-            "-c",
-            &cat_file_path,
-        ];
-
-        match std::env::var(STAMPINF_VERSION_ENV_VAR) {
-            Ok(version) if !version.trim().is_empty() => {
-                // When STAMPINF_VERSION is set to a non-empty, non-whitespace value, we
-                // intentionally omit -v so stampinf reads it and populates
-                // DriverVer. (Whitespace-only values are ignored.)
-                debug!(
-                    DriverVer = version,
-                    "Using {STAMPINF_VERSION_ENV_VAR} env var to set DriverVer"
-                );
+            let unexpected_files = unexpected_files
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            if !self.overwrite_package {
+                return Err(PackageTaskError::UnexpectedFilesInPackageFolder(
+                    self.dest_root_package_folder.clone(),
+                    unexpected_files,
+                ));
             }
-            _ => {
-                args.extend(["-v", "*"]);
+            warn!(
+                "Clearing package folder {}, which contains files that are not part of the \
+                 package: {unexpected_files}",
+                self.dest_root_package_folder.display()
+            );
+            self.fs.remove_dir_all(&self.dest_root_package_folder)?;
+        }
+        self.fs.create_dir(&self.dest_root_package_folder)?;
+        Ok(())
+    }
+
+    /// Recursively collects the files in `dir` that are not in
+    /// `package_files`
+    fn collect_unexpected_files(
+        &self,
+        dir: &Path,
+        package_files: &[PathBuf],
+        unexpected_files: &mut Vec<PathBuf>,
+    ) -> Result<(), FileError> {
+        for entry in self.fs.read_dir_entries(dir)? {
+            if entry.is_dir {
+                self.collect_unexpected_files(&entry.path, package_files, unexpected_files)?;
+            } else if !package_files.contains(&entry.path) {
+                unexpected_files.push(entry.path);
             }
         }
+        Ok(())
+    }
+
+    /// Copies the debugging collateral configured for the package to the
+    /// `debug` folder of the package. The files are not signed.
+    fn copy_debug_files(&self) -> Result<(), PackageTaskError> {
+        if self.src_debug_file_paths.is_empty() {
+            return Ok(());
+        }
+        let missing_files: Vec<String> = self
+            .src_debug_file_paths
+            .iter()
+            .filter(|path| path.file_name().is_none() || !self.fs.exists(path))
+            .map(|path| path.display().to_string())
+            .collect();
+        if !missing_files.is_empty() {
+            return Err(PackageTaskError::MissingDebugFiles(
+                missing_files.join(", "),
+            ));
+        }
 
-        if !wdf_version_flags.is_empty() {
-            args.append(&mut wdf_version_flags.iter().map(String::as_str).collect());
+        info!(
+            "Copying debug files to: {}",
+            self.dest_debug_folder.to_string_lossy()
+        );
+        if !self.fs.exists(&self.dest_debug_folder) {
+            self.fs.create_dir(&self.dest_debug_folder)?;
         }
-        if let Err(e) = self.command_exec.run("stampinf", &args, None, None) {
-            return Err(PackageTaskError::StampinfCommand(e));
+        for src_file_path in &self.src_debug_file_paths {
+            let file_name = src_file_path
+                .file_name()
+                .expect("debug file paths without a file name are rejected above");
+            self.copy(src_file_path, &self.dest_debug_folder.join(file_name))?;
         }
         Ok(())
     }
 
-    fn run_inf2cat(&self) -> Result<(), PackageTaskError> {
-        info!("Running inf2cat");
-        let args = [
-            &format!(
-                "/driver:{}",
-                self.dest_root_package_folder
-                    .to_string_lossy()
-                    .trim_start_matches("\\\\?\\")
-            ),
-            &format!("/os:{}", self.os_mapping),
-            "/uselocaltime",
-        ];
-
-        if let Err(e) = self.command_exec.run("inf2cat", &args, None, None) {
-            return Err(PackageTaskError::Inf2CatCommand(e));
+    /// Copies the files generated by the build script of the package from its
+    /// `OUT_DIR` to the same relative paths in the package root, so that they
+    /// are covered by the catalogs
+    fn copy_out_dir_files(&self) -> Result<(), PackageTaskError> {
+        if self.out_dir_file_paths.is_empty() {
+            return Ok(());
+        }
+        let missing_files: Vec<String> = self
+            .out_dir_file_paths
+            .iter()
+            .filter(|(src_file_path, _)| !self.fs.exists(src_file_path))
+            .map(|(src_file_path, _)| src_file_path.display().to_string())
+            .collect();
+        if !missing_files.is_empty() {
+            return Err(PackageTaskError::MissingOutDirFiles(
+                missing_files.join(", "),
+            ));
         }
 
+        info!(
+            "Copying files generated by the build script to: {}",
+            self.dest_root_package_folder.to_string_lossy()
+        );
+        for (src_file_path, dest_file_path) in &self.out_dir_file_paths {
+            if let Some(dest_folder) = dest_file_path.parent()
+                && !self.fs.exists(dest_folder)
+            {
+                self.fs.create_dir_all(dest_folder)?;
+            }
+            self.copy(src_file_path, dest_file_path)?;
+        }
         Ok(())
     }
 
-    fn generate_certificate(&self) -> Result<(), PackageTaskError> {
-        debug!("Generating certificate");
-        if self.fs.exists(&self.src_cert_file_path) {
+    /// Checks that the stamped `DriverVer` version of every packaged INF is
+    /// greater than the last released version, so that the package can
+    /// upgrade the released driver
+    fn check_min_version(&self) -> Result<(), PackageTaskError> {
+        let Some(min_version) = self.min_version else {
             return Ok(());
+        };
+        for dest_inf_file_path in self.dest_inf_file_paths() {
+            let inf_file = InfFile::parse(&self.fs.read_file_to_string(dest_inf_file_path)?);
+            let Some(version) = inf_file
+                .driver_ver()
+                .and_then(DriverVerVersion::from_driver_ver)
+            else {
+                return Err(PackageTaskError::MissingDriverVerVersion(
+                    dest_inf_file_path.to_path_buf(),
+                ));
+            };
+            if version <= min_version {
+                return Err(PackageTaskError::DriverVerVersionNotGreater {
+                    inf_file_path: dest_inf_file_path.to_path_buf(),
+                    version,
+                    min_version,
+                });
+            }
+            debug!(
+                "DriverVer version {version} of {} is greater than {min_version}",
+                dest_inf_file_path.display()
+            );
         }
-        if self.is_self_signed_certificate_in_store()? {
-            self.create_cert_file_from_store()?;
-        } else {
-            // This mutex prevents multiple instances of this app from racing to
-            // create a cert in the store. It is not a correctness problem. We
-            // just don't want to litter the store with certs especially during
-            // tests when there are lots of parallel runs
-            let mutex_name = CString::new("WDRCertStoreMutex_bd345cf9330") // Unique enough
-                .expect("string is a valid C string");
-            let mutex = NamedMutex::acquire(&mutex_name)
-                .map_err(|e| PackageTaskError::CertMutexError(e.code().0))?;
-            debug!("Acquired cert store mutex");
+        Ok(())
+    }
 
-            // Check again for an existing cert. Another instance might have
-            // created it while we waited for the mutex
-            if self.is_self_signed_certificate_in_store()? {
-                drop(mutex);
-                self.create_cert_file_from_store()?;
-            } else {
-                self.create_self_signed_cert_in_store()?;
-            }
+    /// Checks that the manifest to embed into the driver binary exists and is
+    /// a well-formed application manifest, before anything is copied to the
+    /// package
+    fn check_manifest(&self) -> Result<(), PackageTaskError> {
+        let Some(manifest_file_path) = &self.src_manifest_file_path else {
+            return Ok(());
+        };
+        if !self.fs.exists(manifest_file_path) {
+            return Err(PackageTaskError::MissingManifestFile(
+                manifest_file_path.clone(),
+            ));
         }
+        let manifest = self.fs.read_file_to_string(manifest_file_path)?;
+        manifest::check(&manifest)
+            .map_err(|e| PackageTaskError::InvalidManifest(manifest_file_path.clone(), e))
+    }
 
+    /// Embeds the manifest into a driver binary copied to the package using
+    /// `mt`. The binary is changed, so this runs before the catalog is
+    /// generated and the binary is signed.
+    fn embed_manifest(&self, driver_binary_file_path: &Path) -> Result<(), PackageTaskError> {
+        let Some(manifest_file_path) = &self.src_manifest_file_path else {
+            return Ok(());
+        };
+        info!(
+            "Embedding manifest {} into {}",
+            manifest_file_path.display(),
+            driver_binary_file_path.display()
+        );
+        let manifest_arg = manifest_file_path.to_string_lossy();
+        let output_resource_arg = format!(
+            "-outputresource:{};#{DLL_MANIFEST_RESOURCE_ID}",
+            driver_binary_file_path.to_string_lossy()
+        );
+        let args = ["-nologo", "-manifest", &manifest_arg, &output_resource_arg];
+        self.run_tool("mt", &args, None, None)
+            .map_err(|e| PackageTaskError::EmbedManifestCommand(manifest_file_path.clone(), e))?;
         Ok(())
     }
 
-    fn is_self_signed_certificate_in_store(&self) -> Result<bool, PackageTaskError> {
-        debug!("Checking if self signed certificate exists in WDRTestCertStore store");
-        let args = ["-s", WDR_TEST_CERT_STORE];
+    /// Warns if the .inx file of a package that is not stamped by `stampinf`
+    /// has no `DriverVer` directive, which `stampinf` would otherwise add
+    fn check_inx_driver_ver(&self, inf_file: &InfFile) {
+        if inf_file.driver_ver().is_some() {
+            return;
+        }
+        self.warnings.record(
+            &self.package_name,
+            "inf-driver-ver-check",
+            format!(
+                "{} has no DriverVer in its [Version] section and is not stamped since \
+                 skip-stampinf is set",
+                self.src_inx_file_path.display()
+            ),
+        );
+    }
 
-        match self.command_exec.run("certmgr.exe", &args, None, None) {
-            Ok(output) if output.status.success() => String::from_utf8(output.stdout).map_or_else(
-                |e| Err(PackageTaskError::VerifyCertExistsInStoreInvalidCommandOutput(e)),
-                |stdout| Ok(stdout.contains(WDR_LOCAL_TEST_CERT)),
+    /// Checks that the .inx file conforms to the INF format of the package. A
+    /// universal INF may not use co-installers, while a legacy INF cannot be
+    /// validated for the Windows Driver target platform, which requires a
+    /// universal INF.
+    ///
+    /// # Errors
+    /// * `PackageTaskError::InfNotUniversal` - If a universal INF uses
+    ///   co-installer directives.
+    /// * `PackageTaskError::LegacyInfForWindowsDriver` - If a legacy INF is
+    ///   packaged for the Windows Driver target platform.
+    fn check_inx_format(&self, inf_file: &InfFile) -> Result<(), PackageTaskError> {
+        debug!(
+            "Checking .inx file for the {:?} INF format",
+            self.inf_format
+        );
+        match self.inf_format {
+            InfFormat::Universal => {
+                let co_installer_directives = inf_file.co_installer_directives();
+                if co_installer_directives.is_empty() {
+                    return Ok(());
+                }
+                Err(PackageTaskError::InfNotUniversal(
+                    self.src_inx_file_path.clone(),
+                    co_installer_directives.join(", "),
+                ))
+            }
+            InfFormat::Legacy if self.target_platform == TargetPlatform::WindowsDriver => Err(
+                PackageTaskError::LegacyInfForWindowsDriver(self.src_inx_file_path.clone()),
             ),
-            Ok(_) => Ok(false),
-            Err(e) => Err(PackageTaskError::VerifyCertExistsInStoreCommand(e)),
+            InfFormat::Legacy => Ok(()),
         }
     }
 
-    fn create_self_signed_cert_in_store(&self) -> Result<(), PackageTaskError> {
-        info!("Creating self signed certificate in WDRTestCertStore store using makecert");
-        let cert_path = self.src_cert_file_path.to_string_lossy();
-        let args = [
-            "-r",
-            "-pe",
-            "-a",
-            "SHA256",
-            "-eku",
-            "1.3.6.1.5.5.7.3.3",
-            "-ss",
-            WDR_TEST_CERT_STORE, // FIXME: this should be a parameter
-            "-n",
-            &format!("CN={WDR_LOCAL_TEST_CERT}"), // FIXME: this should be a parameter
-            &cert_path,
-        ];
-        if let Err(e) = self.command_exec.run("makecert", &args, None, None) {
-            return Err(PackageTaskError::CertGenerationInStoreCommand(e));
+    /// Checks that the `Provider` of the .inx file is one of the allowed
+    /// providers of the package, if the package restricts them. `stampinf`
+    /// does not change the provider, so the packaged INF has the same one.
+    ///
+    /// # Errors
+    /// * `PackageTaskError::InfProviderNotAllowed` - If the .inx file has no
+    ///   provider or a provider that is not allowed.
+    fn check_inx_provider(&self, inf_file: &InfFile) -> Result<(), PackageTaskError> {
+        if self.allowed_providers.is_empty() {
+            return Ok(());
         }
-        Ok(())
+        debug!("Checking .inx file for an allowed provider");
+        let provider = inf_file.provider();
+        if provider.is_some_and(|provider| {
+            self.allowed_providers
+                .iter()
+                .any(|allowed_provider| allowed_provider == provider)
+        }) {
+            return Ok(());
+        }
+        Err(PackageTaskError::InfProviderNotAllowed {
+            inf_file_path: self.src_inx_file_path.clone(),
+            provider: provider.unwrap_or("<none>").to_string(),
+            allowed_providers: self.allowed_providers.join(", "),
+        })
     }
 
-    fn create_cert_file_from_store(&self) -> Result<(), PackageTaskError> {
-        info!("Creating certificate file from WDRTestCertStore store using certmgr");
-        let cert_path = self.src_cert_file_path.to_string_lossy();
-        let args = [
-            "-put",
-            "-s",
-            WDR_TEST_CERT_STORE,
-            "-c",
-            "-n",
-            WDR_LOCAL_TEST_CERT,
-            &cert_path,
-        ];
-        if let Err(e) = self.command_exec.run("certmgr.exe", &args, None, None) {
-            return Err(PackageTaskError::CreateCertFileFromStoreCommand(e));
+    /// Returns the target platform `infverif` validates the INFs for. Legacy
+    /// INFs cannot pass the universal validation, so they are validated for
+    /// the desktop target platform instead.
+    fn infverif_target_platform(&self) -> TargetPlatform {
+        match (self.inf_format, self.target_platform) {
+            (InfFormat::Legacy, TargetPlatform::Universal) => TargetPlatform::Desktop,
+            (_, target_platform) => target_platform,
         }
-        Ok(())
     }
 
-    /// Signs the specified file using signtool command using certificate from
-    /// certificate store.
-    ///
-    /// # Arguments
+    /// Collects the `.ini` files, e.g. localized string tables, listed in the
+    /// `[SourceDisksFiles]` sections of the .inx file. They are copied into
+    /// the package from the same path relative to the package root, so that
+    /// they are covered by the catalog.
     ///
-    /// * `file_path` - The path to the file to be signed.
-    /// * `cert_store` - The certificate store to use for signing.
-    /// * `cert_name` - The name of the certificate to use for signing. TODO:
-    ///   Add parameters for certificate store and name
-    fn run_signtool_sign(
-        &self,
-        file_path: &Path,
-        cert_store: &str,
-        cert_name: &str,
-    ) -> Result<(), PackageTaskError> {
-        info!(
-            "Signing {} using signtool",
-            file_path
-                .file_name()
-                .expect("Unable to read file name from the path")
-                .to_string_lossy()
-        );
-        let driver_binary_file_path = file_path.to_string_lossy();
-        let args = [
-            "sign",
-            "/v",
-            "/s",
-            cert_store,
-            "/n",
-            cert_name,
-            "/t",
-            "http://timestamp.digicert.com",
-            "/fd",
-            "SHA256",
-            &driver_binary_file_path,
-        ];
-        if let Err(e) = self.command_exec.run("signtool", &args, None, None) {
-            return Err(PackageTaskError::DriverBinarySignCommand(e));
+    /// # Errors
+    /// * `PackageTaskError::MissingInfReferencedFiles` - If a listed file does
+    ///   not exist in the package root.
+    fn collect_inf_referenced_files(&self, inf_file: &InfFile) -> Result<(), PackageTaskError> {
+        debug!("Collecting files listed by the .inx file");
+        let files: Vec<PathBuf> = inf_file
+            .source_disks_files()
+            .into_iter()
+            .map(PathBuf::from)
+            .filter(|file| {
+                file.extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case(INF_REFERENCED_FILE_EXTENSION))
+            })
+            .collect();
+        let missing_files: Vec<String> = files
+            .iter()
+            .filter(|file| !self.fs.exists(&self.src_root_folder.join(file)))
+            .map(|file| file.display().to_string())
+            .collect();
+        if !missing_files.is_empty() {
+            return Err(PackageTaskError::MissingInfReferencedFiles(
+                self.src_inx_file_path.clone(),
+                missing_files.join(", "),
+            ));
         }
+        *self.inf_referenced_files.borrow_mut() = files;
         Ok(())
     }
 
-    fn run_signtool_verify(&self, file_path: &Path) -> Result<(), PackageTaskError> {
-        info!(
-            "Verifying {} using signtool",
-            file_path
-                .file_name()
-                .expect("Unable to read file name from the path")
-                .to_string_lossy()
-        );
-        let driver_binary_file_path = file_path.to_string_lossy();
-        let args = ["verify", "/v", "/pa", &driver_binary_file_path];
-        // TODO: Differentiate between command exec failure and signature verification
-        // failure
-        if let Err(e) = self.command_exec.run("signtool", &args, None, None) {
-            return Err(PackageTaskError::DriverBinarySignVerificationCommand(e));
+    /// Copies the files listed by the .inx file that were collected by
+    /// `collect_inf_referenced_files` into the package
+    fn copy_inf_referenced_files(&self) -> Result<(), FileError> {
+        for file in self.inf_referenced_files.borrow().iter() {
+            let dest_file_path = self.dest_root_package_folder.join(file);
+            // The file can be nested several folders deep, e.g.
+            // `loc\en-US\strings.ini`
+            if let Some(dest_folder) = dest_file_path.parent() {
+                self.fs.create_dir_all(dest_folder)?;
+            }
+            self.copy(&self.src_root_folder.join(file), &dest_file_path)?;
         }
         Ok(())
     }
 
-    fn run_infverif(&self) -> Result<(), PackageTaskError> {
-        let additional_args = if self.sample_class {
-            let wdk_build_number = self.wdk_build.detect_wdk_build_number()?;
-            if MISSING_SAMPLE_FLAG_WDK_BUILD_NUMBER_RANGE.contains(&wdk_build_number) {
-                debug!(
-                    "InfVerif in WDK Build {wdk_build_number} is bugged and does not contain the \
-                     /samples flag."
-                );
-                warn!("InfVerif skipped for samples class. WDK Build: {wdk_build_number}");
-                return Ok(());
+    /// Checks that no catalog exclude pattern matches a driver binary, INF or
+    /// catalog file of the package, which have to be covered by the catalogs
+    /// for the driver to install.
+    ///
+    /// # Errors
+    /// * `PackageTaskError::CatalogExcludesRequiredFile` - If a pattern matches
+    ///   one of these files.
+    fn check_catalog_exclude(&self) -> Result<(), PackageTaskError> {
+        if self.catalog_exclude.is_empty() {
+            return Ok(());
+        }
+        let required_file_paths = self
+            .dest_driver_binary_paths()
+            .into_iter()
+            .chain(self.dest_inf_file_paths())
+            .chain(self.dest_cat_file_paths());
+        for file_path in required_file_paths {
+            let file_name = file_path.file_name().unwrap_or_default().to_string_lossy();
+            if let Some(pattern) = self
+                .catalog_exclude
+                .iter()
+                .find(|pattern| pattern.matches(&file_name))
+            {
+                return Err(PackageTaskError::CatalogExcludesRequiredFile(
+                    pattern.to_string(),
+                    file_path.to_path_buf(),
+                ));
             }
-            "/msft"
-        } else {
-            ""
-        };
+        }
+        Ok(())
+    }
 
-        info!("Running infverif");
+    /// Moves the files of the package whose name matches a catalog exclude
+    /// pattern out of the package folder, so that inf2cat does not catalog
+    /// them. Returns the package path of each moved file along with the path
+    /// it was moved to.
+    fn move_catalog_excluded_files(&self) -> Result<Vec<(PathBuf, PathBuf)>, FileError> {
+        let mut excluded_files = Vec::new();
+        if self.catalog_exclude.is_empty() {
+            return Ok(excluded_files);
+        }
+        for file_path in self.dest_file_paths() {
+            let file_name = file_path.file_name().unwrap_or_default().to_string_lossy();
+            let Some(pattern) = self
+                .catalog_exclude
+                .iter()
+                .find(|pattern| pattern.matches(&file_name))
+            else {
+                continue;
+            };
+            // The test certificate and the reports are only written after the
+            // catalogs are generated
+            if !self.fs.exists(&file_path) {
+                continue;
+            }
+            let excluded_file_path = self.catalog_excluded_folder.join(
+                file_path
+                    .strip_prefix(&self.dest_root_package_folder)
+                    .expect("package files are in the package folder"),
+            );
+            if let Some(excluded_folder) = excluded_file_path.parent()
+                && !self.fs.exists(excluded_folder)
+            {
+                self.fs.create_dir_all(excluded_folder)?;
+            }
+            info!(
+                "Excluding {} from the catalog, it matches `{pattern}`",
+                file_path.display()
+            );
+            self.fs.rename(&file_path, &excluded_file_path)?;
+            excluded_files.push((file_path, excluded_file_path));
+        }
+        if excluded_files.is_empty() {
+            debug!("No files of the package match the catalog exclude patterns");
+        }
+        Ok(excluded_files)
+    }
 
-        let mode_flag = self.target_platform.as_infverif_flag();
+    /// Moves the files moved out by `move_catalog_excluded_files` back into
+    /// the package folder
+    fn restore_catalog_excluded_files(
+        &self,
+        excluded_files: &[(PathBuf, PathBuf)],
+    ) -> Result<(), FileError> {
+        if excluded_files.is_empty() {
+            return Ok(());
+        }
+        for (file_path, excluded_file_path) in excluded_files {
+            self.fs.rename(excluded_file_path, file_path)?;
+        }
+        self.fs.remove_dir_all(&self.catalog_excluded_folder)
+    }
 
-        let mut args = vec!["/v", mode_flag];
-        let inf_path = self.dest_inf_file_path.to_string_lossy();
+    /// Reads and parses the .inx file of the driver
+    fn read_inx_file(&self) -> Result<InfFile, PackageTaskError> {
+        Ok(InfFile::parse(
+            &self.fs.read_file_to_string(&self.src_inx_file_path)?,
+        ))
+    }
 
-        if self.sample_class {
-            args.push(additional_args);
+    /// Checks that the .inx file has architecture decorated sections for
+    /// every architecture of a multi-architecture package. An undecorated INF
+    /// cannot describe binaries of more than one architecture.
+    fn check_inx_arch_decorations(&self, inf_file: &InfFile) -> Result<(), PackageTaskError> {
+        debug!("Checking .inx file for architecture decorated sections");
+        let manufacturer_decorations = inf_file.manufacturer_decorations();
+        let mut missing_decorations: Vec<String> = Vec::new();
+        for binary in &self.multi_arch_binaries {
+            let os_decoration = format!("NT{}", binary.arch);
+            if !manufacturer_decorations.iter().any(|decoration| {
+                decoration
+                    .get(..os_decoration.len())
+                    .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&os_decoration))
+            }) {
+                missing_decorations.push(os_decoration);
+            }
+            let source_disks_files_section_name = format!("SourceDisksFiles.{}", binary.arch);
+            if inf_file.section(&source_disks_files_section_name).is_none() {
+                missing_decorations.push(format!("[{source_disks_files_section_name}]"));
+            }
         }
-        args.push(&inf_path);
-
-        if let Err(e) = self.command_exec.run("infverif", &args, None, None) {
-            return Err(PackageTaskError::InfVerificationCommand(e));
+        if !missing_decorations.is_empty() {
+            return Err(PackageTaskError::InfNotArchDecorated(
+                self.src_inx_file_path.clone(),
+                missing_decorations.join(", "),
+            ));
         }
-
         Ok(())
     }
-}
-
-/// An RAII wrapper over a Win API named mutex
-struct NamedMutex {
-    handle: HANDLE,
-    // `ReleaseMutex` requires that it is called
-    // only by threads that own the mutex handle.
-    // Being `!Send` ensures that's always the case.
-    _not_send: PhantomData<*const ()>,
-}
 
-impl NamedMutex {
-    /// Acquires named mutex
-    pub fn acquire(name: &CStr) -> Result<Self, WinError> {
-        fn get_last_error() -> WinError {
-            // SAFETY: We have to just assume this function is safe to call
-            // because the windows crate has no documentation for it and
-            // the MSDN documentation does not specify any preconditions
-            // for calling it
-            unsafe { GetLastError().into() }
+    /// Checks that a models section listed by the `[Manufacturer]` section of
+    /// the .inx file is decorated for the target architecture, i.e. with
+    /// `NT$ARCH$` or e.g. `NTamd64` when packaging for `amd64`. `inf2cat` only
+    /// warns when the INF does not cover the OS the catalog is generated for,
+    /// and the packaged INF would not install on the target architecture.
+    fn check_inx_catalog_os_decoration(&self, inf_file: &InfFile) -> Result<(), PackageTaskError> {
+        debug!("Checking .inx file for a section of the target architecture");
+        let arch_decorations = [
+            INF_ARCH_PLACEHOLDER_DECORATION.to_string(),
+            format!("NT{}", self.arch),
+        ];
+        if inf_file
+            .manufacturer_decorations()
+            .iter()
+            .any(|decoration| {
+                let os_decoration = decoration.split('.').next().unwrap_or_default();
+                arch_decorations
+                    .iter()
+                    .any(|arch_decoration| arch_decoration.eq_ignore_ascii_case(os_decoration))
+            })
+        {
+            return Ok(());
         }
+        Err(PackageTaskError::InfNotDecoratedForCatalogOs {
+            path: self.src_inx_file_path.clone(),
+            arch: *self.arch,
+            os: self.os_mapping.clone(),
+        })
+    }
 
-        // SAFETY: The name ptr is valid because it comes from a CStr
-        let handle = unsafe { CreateMutexA(None, false, PCSTR(name.as_ptr().cast()))? };
-        if handle.is_invalid() {
-            return Err(get_last_error());
+    /// Checks that the setup class of the .inx file matches the kind of
+    /// driver being packaged. Packaging as a sample, i.e. with `/msft` passed
+    /// to `infverif`, requires the `Sample` setup class, while a driver
+    /// packaged as a production driver is warned about when it still uses it.
+    fn check_inx_setup_class(&self, inf_file: &InfFile) -> Result<(), PackageTaskError> {
+        debug!("Checking .inx file for the setup class of the driver");
+        if self.sample_class == inf_file.is_sample_class() {
+            return Ok(());
         }
-
-        // SAFETY: The handle is valid since it was created right above
-        match unsafe { WaitForSingleObject(handle, INFINITE) } {
-            res if res == WAIT_OBJECT_0 || res == WAIT_ABANDONED => Ok(Self {
-                handle,
-                _not_send: PhantomData,
-            }),
-            _ => {
-                // SAFETY: The handle is valid since it was created right above
-                unsafe { CloseHandle(handle)? };
-                Err(get_last_error())
-            }
+        let setup_class = format!(
+            "Class = {}, ClassGuid = {}",
+            inf_file.class().unwrap_or("<none>"),
+            inf_file.class_guid().unwrap_or("<none>"),
+        );
+        if self.sample_class {
+            return Err(PackageTaskError::InfNotSampleClass(
+                self.src_inx_file_path.clone(),
+                setup_class,
+            ));
         }
+        self.warnings.record(
+            &self.package_name,
+            "inf-class-check",
+            format!(
+                "{} uses the sample setup class ({setup_class}) but is not packaged as a sample. \
+                 Pass --sample or declare the setup class of the device in the [Version] section",
+                self.src_inx_file_path.display(),
+            ),
+        );
+        Ok(())
     }
-}
-
-impl Drop for NamedMutex {
-    fn drop(&mut self) {
-        // SAFETY: the handle is guaranteed to be valid
-        // because this type itself created it and it
-        // was never exposed outside. Also the requirement
-        // that the calling thread must own the handle
-        // is upheld because this type is `!Send`
-        let _ = unsafe { ReleaseMutex(self.handle) };
 
-        // SAFETY: the handle is valid as explained above.
-        let _ = unsafe { CloseHandle(self.handle) };
+    /// Runs a packaging tool, or the executable set for it in
+    /// `self.tool_paths`, and records its output in the tool log. The
+    /// environment of the package is passed along with `env_vars`, which take
+    /// precedence over it.
+    fn run_tool(
+        &self,
+        command: &str,
+        args: &[&str],
+        env_vars: Option<&HashMap<&str, &str>>,
+        working_dir: Option<&Path>,
+    ) -> Result<Output, CommandError> {
+        let command = self.tool_paths.command(command);
+        let package_env_vars;
+        let env_vars = if self.env.is_empty() {
+            env_vars
+        } else {
+            let mut vars = self
+                .env
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect::<HashMap<_, _>>();
+            vars.extend(env_vars.into_iter().flatten());
+            package_env_vars = vars;
+            Some(&package_env_vars)
+        };
+        let result = self.command_exec.run(&command, args, env_vars, working_dir);
+        self.tool_log
+            .record(&self.package_name, &command, args, &result);
+        result
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::{
-        path::PathBuf,
-        process::{ExitStatus, Output},
-    };
+    /// Signs the driver binaries and catalog files according to
+    /// `self.sign_mode` and optionally verifies the resulting signatures.
+    /// Returns a variant of `PackageTaskError` if any step of the process
+    /// fails.
+    fn sign_and_verify(&self) -> Result<(), PackageTaskError> {
+        let SignMode::Test { verify_signature } = self.sign_mode else {
+            info!("Sign mode is 'off'; skipping signing");
+            return Ok(());
+        };
 
-    use wdk_build::{CpuArchitecture, KmdfConfig, UmdfConfig};
+        if self.packages_test_cert() {
+            self.generate_certificate()?;
+            self.copy(&self.src_cert_file_path, &self.dest_cert_file_path)?;
+        }
+        let driver_binary_paths = self.dest_driver_binary_paths();
+        for driver_binary_path in &driver_binary_paths {
+            self.run_signtool_sign(driver_binary_path, WDR_TEST_CERT_STORE, WDR_LOCAL_TEST_CERT)?;
+        }
+        let cat_file_paths = if self.binary_only {
+            Vec::new()
+        } else {
+            self.dest_cat_file_paths()
+        };
+        for cat_file_path in &cat_file_paths {
+            self.run_signtool_sign(cat_file_path, WDR_TEST_CERT_STORE, WDR_LOCAL_TEST_CERT)?;
+        }
+        if self.emit_catalog_signing_report && !self.binary_only {
+            self.write_catalog_signing_report()?;
+        }
 
-    use super::*;
+        if verify_signature {
+            info!("Verifying signatures for driver binaries and cat files using signtool");
+            for driver_binary_path in &driver_binary_paths {
+                self.run_signtool_verify(driver_binary_path)?;
+            }
+            for cat_file_path in &cat_file_paths {
+                self.run_signtool_verify(cat_file_path)?;
+            }
+            if !self.binary_only {
+                for driver_binary_path in &driver_binary_paths {
+                    self.run_signtool_verify_catalog_member(driver_binary_path)?;
+                }
+            }
+            self.run_eku_check()?;
+        }
 
-    #[test]
-    fn new_succeeds_for_valid_args() {
-        let package_name = "test_package";
+        Ok(())
+    }
+
+    fn check_inx_exists(&self) -> Result<(), PackageTaskError> {
+        debug!(
+            "Checking for .inx file, path: {}",
+            self.src_inx_file_path.to_string_lossy()
+        );
+        if !self.fs.exists(&self.src_inx_file_path) {
+            return Err(PackageTaskError::MissingInxSrcFile(
+                self.src_inx_file_path.clone(),
+            ));
+        }
+        for inf in &self.additional_infs {
+            debug!(
+                "Checking for additional .inx file, path: {}",
+                inf.src_inx_file_path.display()
+            );
+            if !self.fs.exists(&inf.src_inx_file_path) {
+                return Err(PackageTaskError::MissingInxSrcFile(
+                    inf.src_inx_file_path.clone(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies the .inx files of the additional INFs of the package to the
+    /// package folder
+    fn copy_additional_infs(&self) -> Result<(), FileError> {
+        for inf in &self.additional_infs {
+            self.copy(&inf.src_inx_file_path, &inf.dest_inf_file_path)?;
+        }
+        Ok(())
+    }
+
+    fn rename_driver_binary_extension(&self) -> Result<(), FileError> {
+        debug!("Renaming driver binary extension from .dll to .sys");
+        self.fs.rename(
+            &self.src_driver_binary_file_path,
+            &self.src_renamed_driver_binary_file_path,
+        )
+    }
+
+    fn copy(&self, src_file_path: &'a Path, dest_file_path: &'a Path) -> Result<u64, FileError> {
+        debug!(
+            "Copying src file {} to dest folder {}",
+            src_file_path.to_string_lossy(),
+            dest_file_path.to_string_lossy()
+        );
+        self.fs.copy(src_file_path, dest_file_path)
+    }
+
+    /// Copies the PDB of a driver binary to the symbols archive folder, if one
+    /// is set, at the path a symbol server looks it up by, i.e.
+    /// `<pdb name>/<GUID><age>/<pdb name>`. The GUID and age are read from the
+    /// debug directory of the binary, so that the archived PDB matches the
+    /// binary that is packaged.
+    fn archive_symbols(
+        &self,
+        driver_binary_path: &Path,
+        pdb_file_path: &Path,
+    ) -> Result<(), PackageTaskError> {
+        let Some(symbols_archive_dir) = self.symbols_archive_dir else {
+            return Ok(());
+        };
+        let pdb_debug_id = pe::read_pdb_debug_id(&self.fs.read_file(driver_binary_path)?)
+            .ok_or_else(|| PackageTaskError::MissingPdbDebugId(driver_binary_path.to_owned()))?;
+        let pdb_file_name = pdb_debug_id.pdb_file_name();
+        let dest_pdb_folder = symbols_archive_dir
+            .join(pdb_file_name)
+            .join(pdb_debug_id.symstore_key());
+        self.fs.create_dir_all(&dest_pdb_folder)?;
+        let dest_pdb_file_path = dest_pdb_folder.join(pdb_file_name);
+        info!(
+            "Archiving {} to {}",
+            pdb_file_path.display(),
+            dest_pdb_file_path.display()
+        );
+        self.fs.copy(pdb_file_path, &dest_pdb_file_path)?;
+        Ok(())
+    }
+
+    /// Replaces the references to the driver binary in the packaged INFs,
+    /// which the .inx files name after the package, with the configured binary
+    /// name.
+    fn rename_inf_binary_references(&self) -> Result<(), PackageTaskError> {
+        let driver_binary_extension = self
+            .dest_driver_binary_path
+            .extension()
+            .expect("driver binary path always has an extension")
+            .to_string_lossy();
+        for dest_inf_file_path in self.dest_inf_file_paths() {
+            info!(
+                "Renaming references to {}.{driver_binary_extension} in {} to \
+                 {}.{driver_binary_extension}",
+                self.package_name,
+                dest_inf_file_path.display(),
+                self.binary_name
+            );
+            let inf_content = self.fs.read_file_to_string(dest_inf_file_path)?;
+            let renamed_inf_content = inf_content.replace(
+                &format!("{}.{driver_binary_extension}", self.package_name),
+                &format!("{}.{driver_binary_extension}", self.binary_name),
+            );
+            self.fs
+                .write_to_file(dest_inf_file_path, renamed_inf_content.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Sets the `VerifierOn` WDF registry value in the packaged INF to reflect
+    /// the `wdf-verifier` setting of the profile being built. The INF must be
+    /// rewritten before the catalog file is generated since the catalog hashes
+    /// it.
+    fn apply_wdf_verifier_setting(&self, wdf_verifier: bool) -> Result<(), PackageTaskError> {
+        info!(
+            "{} WDF verifier in {}",
+            if wdf_verifier {
+                "Enabling"
+            } else {
+                "Disabling"
+            },
+            self.dest_inf_file_path.display()
+        );
+        let inf_content = self.fs.read_file_to_string(&self.dest_inf_file_path)?;
+        let mut found_verifier_value = false;
+        let updated_inf_content: String = inf_content
+            .split_inclusive('\n')
+            .map(|line_with_ending| {
+                let line = line_with_ending.trim_end_matches(['\r', '\n']);
+                let line_ending = &line_with_ending[line.len()..];
+                let (entry, comment) = line
+                    .find(';')
+                    .map_or((line, ""), |index| line.split_at(index));
+                let mut fields: Vec<&str> = entry.split(',').collect();
+                let is_verifier_value = fields.len() == 5
+                    && fields[0].trim().eq_ignore_ascii_case("HKR")
+                    && fields[2]
+                        .trim()
+                        .eq_ignore_ascii_case(WDF_VERIFIER_REGISTRY_VALUE_NAME);
+                if !is_verifier_value {
+                    return line_with_ending.to_string();
+                }
+                found_verifier_value = true;
+                fields[4] = if wdf_verifier { " 1" } else { " 0" };
+                let separator = if comment.is_empty() { "" } else { " " };
+                format!("{}{separator}{comment}{line_ending}", fields.join(","))
+            })
+            .collect();
+        if !found_verifier_value {
+            return Err(PackageTaskError::WdfVerifierRegistryValueNotFound(
+                self.dest_inf_file_path.clone(),
+            ));
+        }
+        self.fs
+            .write_to_file(&self.dest_inf_file_path, updated_inf_content.as_bytes())?;
+        Ok(())
+    }
+
+    // Environment variables pointing the packaging tools at the configured
+    // temp directory and tool search paths, if any
+    fn tool_env_vars(&self) -> Option<HashMap<&str, &str>> {
+        let mut env_vars = HashMap::new();
+        if let Some(temp_dir) = self.temp_dir.as_deref() {
+            env_vars.extend([("TMP", temp_dir), ("TEMP", temp_dir)]);
+        }
+        if let Some(tool_path) = self.tool_path.as_deref() {
+            env_vars.insert("PATH", tool_path);
+        }
+        (!env_vars.is_empty()).then_some(env_vars)
+    }
+
+    fn run_stampinf(&self) -> Result<(), PackageTaskError> {
+        if self.skip_stampinf {
+            info!("Skipping stampinf, the INFs are packaged as checked in");
+            return Ok(());
+        }
+        for (dest_inf_file_path, dest_cat_file_path) in self.dest_inf_and_cat_file_paths() {
+            self.run_stampinf_on(dest_inf_file_path, dest_cat_file_path)?;
+        }
+        Ok(())
+    }
+
+    /// Runs stampinf on a packaged INF, setting its `CatalogFile` directive to
+    /// the file name of `dest_cat_file_path`
+    fn run_stampinf_on(
+        &self,
+        dest_inf_file_path: &Path,
+        dest_cat_file_path: &Path,
+    ) -> Result<(), PackageTaskError> {
+        info!("Running stampinf on {}", dest_inf_file_path.display());
+        let wdf_version_flags = match self.driver_model {
+            DriverConfig::Kmdf(kmdf_config) => {
+                vec![
+                    "-k".to_string(),
+                    format!(
+                        "{}.{}",
+                        kmdf_config.kmdf_version_major, kmdf_config.target_kmdf_version_minor
+                    ),
+                ]
+            }
+            DriverConfig::Umdf(umdf_config) => vec![
+                "-u".to_string(),
+                format!(
+                    "{}.{}.0",
+                    umdf_config.umdf_version_major, umdf_config.target_umdf_version_minor
+                ),
+            ],
+            DriverConfig::Wdm => vec![],
+        };
+        // TODO: Does it generate cat file relative to inf file path or we need to
+        // provide the absolute path?
+        let cat_file_path = dest_cat_file_path
+            .file_name()
+            .expect("catalog file path always has a file name")
+            .to_string_lossy();
+        let dest_inf_file_path = dest_inf_file_path.to_string_lossy();
+        let arch = self.arch.to_string();
+        let driver_ver_date = self
+            .reproducible_driver_ver
+            .as_ref()
+            .map_or("*", |driver_ver| driver_ver.date.as_str());
+        let mut args: Vec<&str> = vec!["-f", &dest_inf_file_path, "-d", driver_ver_date];
+        // A multi-architecture INF is explicitly decorated for every
+        // architecture, so there is no single architecture to stamp
+        if self.multi_arch_binaries.is_empty() {
+            args.extend(["-a", arch.as_str()]);
+        }
+        args.extend(["-c", &cat_file_path]);
+
+        match std::env::var(STAMPINF_VERSION_ENV_VAR) {
+            Ok(version) if !version.trim().is_empty() => {
+                // When STAMPINF_VERSION is set to a non-empty, non-whitespace value, we
+                // intentionally omit -v so stampinf reads it and populates
+                // DriverVer. (Whitespace-only values are ignored.)
+                debug!(
+                    DriverVer = version,
+                    "Using {STAMPINF_VERSION_ENV_VAR} env var to set DriverVer"
+                );
+            }
+            _ => {
+                let driver_ver_version = self
+                    .reproducible_driver_ver
+                    .as_ref()
+                    .map(|driver_ver| driver_ver.version.as_str())
+                    .or(self.incremented_driver_ver)
+                    .unwrap_or("*");
+                args.extend(["-v", driver_ver_version]);
+            }
+        }
+
+        if !wdf_version_flags.is_empty() {
+            args.append(&mut wdf_version_flags.iter().map(String::as_str).collect());
+        }
+        let env_vars = self.tool_env_vars();
+        let output = self
+            .run_tool("stampinf", &args, env_vars.as_ref(), None)
+            .map_err(PackageTaskError::StampinfCommand)?;
+        self.warnings
+            .record_from_output(&self.package_name, "stampinf", &output.stdout);
+        Ok(())
+    }
+
+    /// Fails if the stamped INF contains absolute paths, e.g. a
+    /// `[SourceDisksNames]` entry pointing to the target directory, that tie
+    /// the package to the build machine
+    fn check_inf_is_relocatable(&self) -> Result<(), PackageTaskError> {
+        for dest_inf_file_path in self.dest_inf_file_paths() {
+            debug!(
+                "Checking {} for absolute paths",
+                dest_inf_file_path.display()
+            );
+            let inf_content = self.fs.read_file_to_string(dest_inf_file_path)?;
+            let absolute_paths = InfFile::parse(&inf_content).absolute_paths().join(", ");
+            if !absolute_paths.is_empty() {
+                return Err(PackageTaskError::AbsolutePathsInInf(
+                    dest_inf_file_path.to_path_buf(),
+                    absolute_paths,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a markdown summary of the files, registry values and services
+    /// installed by the stamped INFs of the package, one section per INF
+    fn write_install_summary(&self) -> Result<(), PackageTaskError> {
+        info!(
+            "Writing install summary {}",
+            self.dest_install_summary_file_path.display()
+        );
+        let mut summary = String::new();
+        for dest_inf_file_path in self.dest_inf_file_paths() {
+            if !summary.is_empty() {
+                summary.push('\n');
+            }
+            let inf_content = self.fs.read_file_to_string(dest_inf_file_path)?;
+            let inf_file_name = dest_inf_file_path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy();
+            summary.push_str(
+                &InstallSummary::from_inf(&InfFile::parse(&inf_content))
+                    .to_markdown(&inf_file_name),
+            );
+        }
+        self.fs
+            .write_to_file(&self.dest_install_summary_file_path, summary.as_bytes())?;
+        Ok(())
+    }
+
+    /// Writes the scripts that delete the driver packages installed from the
+    /// stamped INFs of the package and the services they created
+    fn write_uninstall_scripts(&self) -> Result<(), PackageTaskError> {
+        info!(
+            "Writing uninstall scripts {}",
+            self.dest_uninstall_ps1_file_path.display()
+        );
+        let mut script = UninstallScript::default();
+        for dest_inf_file_path in self.dest_inf_file_paths() {
+            let inf_content = self.fs.read_file_to_string(dest_inf_file_path)?;
+            let inf_file_name = dest_inf_file_path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy();
+            script.add_inf(&inf_file_name, &InfFile::parse(&inf_content));
+        }
+        self.fs.write_to_file(
+            &self.dest_uninstall_ps1_file_path,
+            script.to_powershell().as_bytes(),
+        )?;
+        self.fs.write_to_file(
+            &self.dest_uninstall_cmd_file_path,
+            UninstallScript::to_cmd().as_bytes(),
+        )?;
+        Ok(())
+    }
+
+    /// Writes a markdown report of the signer, thumbprint, timestamp and
+    /// digest algorithm of every signed catalog file, as printed by
+    /// `signtool verify /v`. Catalog signatures are audited separately from
+    /// the signature of the driver binary.
+    fn write_catalog_signing_report(&self) -> Result<(), PackageTaskError> {
+        info!(
+            "Writing catalog signing report {}",
+            self.dest_catalog_signing_report_file_path.display()
+        );
+        let mut report = String::new();
+        for dest_cat_file_path in self.dest_cat_file_paths() {
+            let cat_file_path = dest_cat_file_path.to_string_lossy();
+            let output = self
+                .run_tool(
+                    "signtool",
+                    &signtool::verify_args(&cat_file_path),
+                    None,
+                    None,
+                )
+                .map_err(|e| {
+                    PackageTaskError::CatalogSigningReportCommand(
+                        dest_cat_file_path.to_path_buf(),
+                        e,
+                    )
+                })?;
+            let details = String::from_utf8(output.stdout)
+                .ok()
+                .and_then(|stdout| signtool::parse_verify_output(&stdout))
+                .ok_or_else(|| {
+                    PackageTaskError::CatalogSigningReportInvalidCommandOutput(
+                        dest_cat_file_path.to_path_buf(),
+                    )
+                })?;
+            let cat_file_name = dest_cat_file_path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy();
+            let timestamp = details.timestamp.as_deref().unwrap_or("not timestamped");
+            info!(
+                "{cat_file_name} is signed by {} ({}), timestamp: {timestamp}",
+                details.signer, details.thumbprint
+            );
+            if !report.is_empty() {
+                report.push('\n');
+            }
+            report.push_str(&format!(
+                "# {cat_file_name}\n\n- Signer: {}\n- Thumbprint: {}\n- Timestamp: {timestamp}\n- \
+                 Digest algorithm: {}\n",
+                details.signer, details.thumbprint, details.digest_algorithm
+            ));
+        }
+        self.fs.write_to_file(
+            &self.dest_catalog_signing_report_file_path,
+            report.as_bytes(),
+        )?;
+        Ok(())
+    }
+
+    /// Fails if the generated catalog contains absolute paths of the build
+    /// machine
+    fn check_catalog_is_relocatable(&self) -> Result<(), PackageTaskError> {
+        for dest_cat_file_path in self.dest_cat_file_paths() {
+            debug!(
+                "Checking {} for absolute paths",
+                dest_cat_file_path.display()
+            );
+            let catalog = self.fs.read_file(dest_cat_file_path)?;
+            let absolute_paths = absolute_paths_in_catalog(&catalog).join(", ");
+            if !absolute_paths.is_empty() {
+                return Err(PackageTaskError::AbsolutePathsInCatalog(
+                    dest_cat_file_path.to_path_buf(),
+                    absolute_paths,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn run_inf2cat(&self) -> Result<(), PackageTaskError> {
+        info!("Running inf2cat");
+        let args = [
+            &format!(
+                "/driver:{}",
+                self.dest_root_package_folder
+                    .to_string_lossy()
+                    .trim_start_matches("\\\\?\\")
+            ),
+            &format!("/os:{}", self.os_mapping),
+            "/uselocaltime",
+        ];
+
+        let env_vars = self.tool_env_vars();
+        let excluded_files = self.move_catalog_excluded_files()?;
+        let output = self.run_tool("inf2cat", &args, env_vars.as_ref(), None);
+        self.restore_catalog_excluded_files(&excluded_files)?;
+        let output = output.map_err(PackageTaskError::Inf2CatCommand)?;
+        self.warnings
+            .record_from_output(&self.package_name, "inf2cat", &output.stdout);
+
+        // inf2cat succeeds without generating a catalog when the INF does not
+        // reference any files to catalog
+        for (dest_inf_file_path, dest_cat_file_path) in self.dest_inf_and_cat_file_paths() {
+            if !self.fs.exists(dest_cat_file_path) {
+                return Err(PackageTaskError::CatFileNotGenerated(
+                    dest_cat_file_path.to_path_buf(),
+                    dest_inf_file_path.to_path_buf(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn generate_certificate(&self) -> Result<(), PackageTaskError> {
+        debug!("Generating certificate");
+        if !self.fs.exists(&self.src_cert_file_path) {
+            self.generate_certificate_file()?;
+        }
+        let Some(validity) = self.test_cert_validity else {
+            return Ok(());
+        };
+        if !self.is_test_certificate_expired(validity)? {
+            return Ok(());
+        }
+        warn!(
+            "Test certificate {} has expired, regenerating it",
+            self.src_cert_file_path.display()
+        );
+        self.delete_self_signed_cert_from_store()?;
+        self.fs.remove_file(&self.src_cert_file_path)?;
+        self.generate_certificate_file()?;
+        if self.is_test_certificate_expired(validity)? {
+            return Err(PackageTaskError::ExpiredTestCert(self.src_cert_file_path.clone()));
+        }
+        Ok(())
+    }
+
+    /// Creates the test certificate file from the certificate in the store,
+    /// creating the certificate in the store first if it does not exist
+    fn generate_certificate_file(&self) -> Result<(), PackageTaskError> {
+        if self.is_self_signed_certificate_in_store()? {
+            self.create_cert_file_from_store()?;
+        } else {
+            // This mutex prevents multiple instances of this app from racing to
+            // create a cert in the store. It is not a correctness problem. We
+            // just don't want to litter the store with certs especially during
+            // tests when there are lots of parallel runs
+            let mutex_name = CString::new("WDRCertStoreMutex_bd345cf9330") // Unique enough
+                .expect("string is a valid C string");
+            let mutex = NamedMutex::acquire(&mutex_name)
+                .map_err(|e| PackageTaskError::CertMutexError(e.code().0))?;
+            debug!("Acquired cert store mutex");
+
+            // Check again for an existing cert. Another instance might have
+            // created it while we waited for the mutex
+            if self.is_self_signed_certificate_in_store()? {
+                drop(mutex);
+                self.create_cert_file_from_store()?;
+            } else {
+                self.create_self_signed_cert_in_store()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_self_signed_certificate_in_store(&self) -> Result<bool, PackageTaskError> {
+        debug!("Checking if self signed certificate exists in WDRTestCertStore store");
+        let args = ["-s", WDR_TEST_CERT_STORE];
+
+        match self.run_tool("certmgr.exe", &args, None, None) {
+            Ok(output) if output.status.success() => String::from_utf8(output.stdout).map_or_else(
+                |e| Err(PackageTaskError::VerifyCertExistsInStoreInvalidCommandOutput(e)),
+                |stdout| Ok(stdout.contains(WDR_LOCAL_TEST_CERT)),
+            ),
+            Ok(_) => Ok(false),
+            Err(e) => Err(PackageTaskError::VerifyCertExistsInStoreCommand(e)),
+        }
+    }
+
+    fn create_self_signed_cert_in_store(&self) -> Result<(), PackageTaskError> {
+        info!("Creating self signed certificate in WDRTestCertStore store using makecert");
+        let cert_path = self.src_cert_file_path.to_string_lossy();
+        let subject = format!("CN={WDR_LOCAL_TEST_CERT}"); // FIXME: this should be a parameter
+        let end_date = self.test_cert_validity.map(|validity| validity.end_date());
+        let mut args = vec![
+            "-r",
+            "-pe",
+            "-a",
+            "SHA256",
+            "-eku",
+            "1.3.6.1.5.5.7.3.3",
+            "-ss",
+            WDR_TEST_CERT_STORE, // FIXME: this should be a parameter
+            "-n",
+            &subject,
+        ];
+        if let Some(end_date) = &end_date {
+            args.extend(["-e", end_date.as_str()]);
+        }
+        args.push(&cert_path);
+        if let Err(e) = self.run_tool("makecert", &args, None, None) {
+            return Err(PackageTaskError::CertGenerationInStoreCommand(e));
+        }
+        Ok(())
+    }
+
+    fn create_cert_file_from_store(&self) -> Result<(), PackageTaskError> {
+        info!("Creating certificate file from WDRTestCertStore store using certmgr");
+        let cert_path = self.src_cert_file_path.to_string_lossy();
+        let args = [
+            "-put",
+            "-s",
+            WDR_TEST_CERT_STORE,
+            "-c",
+            "-n",
+            WDR_LOCAL_TEST_CERT,
+            &cert_path,
+        ];
+        if let Err(e) = self.run_tool("certmgr.exe", &args, None, None) {
+            return Err(PackageTaskError::CreateCertFileFromStoreCommand(e));
+        }
+        Ok(())
+    }
+
+    fn delete_self_signed_cert_from_store(&self) -> Result<(), PackageTaskError> {
+        info!("Deleting self signed certificate from WDRTestCertStore store using certmgr");
+        let args = [
+            "-del",
+            "-s",
+            WDR_TEST_CERT_STORE,
+            "-c",
+            "-n",
+            WDR_LOCAL_TEST_CERT,
+        ];
+        if let Err(e) = self.run_tool("certmgr.exe", &args, None, None) {
+            return Err(PackageTaskError::DeleteCertFromStoreCommand(e));
+        }
+        Ok(())
+    }
+
+    /// Reads the end of the validity period of the test certificate file
+    /// using `certutil -dump` and returns `true` if it has expired
+    fn is_test_certificate_expired(
+        &self,
+        validity: TestCertValidity,
+    ) -> Result<bool, PackageTaskError> {
+        debug!("Checking validity period of the test certificate using certutil");
+        let cert_file_path = self.src_cert_file_path.to_string_lossy();
+        let output = self
+            .run_tool("certutil", &["-dump", &cert_file_path], None, None)
+            .map_err(|e| {
+                PackageTaskError::CertutilDumpCommand(
+                    self.src_cert_file_path.display().to_string(),
+                    e,
+                )
+            })?;
+        let stdout = String::from_utf8(output.stdout)
+            .map_err(PackageTaskError::CertutilDumpInvalidCommandOutput)?;
+        let not_after = test_cert::parse_certutil_not_after(&stdout).ok_or_else(|| {
+            PackageTaskError::TestCertValidityInvalidCommandOutput(self.src_cert_file_path.clone())
+        })?;
+        debug!("Test certificate is valid until {not_after:?}");
+        Ok(validity.is_expired(not_after))
+    }
+
+    /// Returns `true` if the package is signed with the test certificate,
+    /// which is then copied into the package, rather than with a PFX
+    /// certificate
+    const fn packages_test_cert(&self) -> bool {
+        matches!(self.sign_mode, SignMode::Test { .. }) && self.signing_pfx.is_none()
+    }
+
+    /// Returns the certificate files are signed with, the PFX certificate at
+    /// `pfx_path` if one is set, or else the certificate named `cert_name` in
+    /// the `cert_store` certificate store
+    fn signing_certificate<'c>(
+        &'c self,
+        pfx_path: Option<&'c str>,
+        cert_store: &'c str,
+        cert_name: &'c str,
+    ) -> SigningCertificate<'c> {
+        self.signing_pfx.zip(pfx_path).map_or(
+            SigningCertificate::Named {
+                store: cert_store,
+                name: cert_name,
+            },
+            |(pfx, path)| SigningCertificate::Pfx {
+                path,
+                password: pfx.password.as_deref(),
+            },
+        )
+    }
+
+    /// Signs the specified file using signtool command using the PFX
+    /// certificate if one is set, or else the certificate from certificate
+    /// store.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_path` - The path to the file to be signed.
+    /// * `cert_store` - The certificate store to use for signing.
+    /// * `cert_name` - The name of the certificate to use for signing. TODO:
+    ///   Add parameters for certificate store and name
+    fn run_signtool_sign(
+        &self,
+        file_path: &Path,
+        cert_store: &str,
+        cert_name: &str,
+    ) -> Result<(), PackageTaskError> {
+        info!(
+            "Signing {} using signtool",
+            file_path
+                .file_name()
+                .expect("Unable to read file name from the path")
+                .to_string_lossy()
+        );
+        let file_path = file_path.to_string_lossy();
+        let pfx_path = self.signing_pfx.map(|pfx| pfx.path.to_string_lossy());
+        let args = signtool::sign_args(
+            &file_path,
+            self.signing_certificate(pfx_path.as_deref(), cert_store, cert_name),
+            // A trusted timestamp records the time of signing, which makes the
+            // signatures of reproducible packages differ between builds
+            self.reproducible_driver_ver.is_none(),
+        );
+        if let Err(e) = self.run_tool("signtool", &args, None, None) {
+            return Err(PackageTaskError::DriverBinarySignCommand(e));
+        }
+        Ok(())
+    }
+
+    fn run_signtool_verify(&self, file_path: &Path) -> Result<(), PackageTaskError> {
+        info!(
+            "Verifying {} using signtool",
+            file_path
+                .file_name()
+                .expect("Unable to read file name from the path")
+                .to_string_lossy()
+        );
+        // TODO: Differentiate between command exec failure and signature verification
+        // failure
+        let file_path = file_path.to_string_lossy();
+        if let Err(e) = self.run_tool("signtool", &signtool::verify_args(&file_path), None, None) {
+            return Err(PackageTaskError::DriverBinarySignVerificationCommand(e));
+        }
+        Ok(())
+    }
+
+    /// Verifies that the catalog file lists the hash of the signed driver
+    /// binary using `signtool verify /c`. The signatures of both files can be
+    /// valid while the catalog is stale, e.g. if it was generated before the
+    /// final driver binary was copied to the package.
+    fn run_signtool_verify_catalog_member(&self, file_path: &Path) -> Result<(), PackageTaskError> {
+        info!(
+            "Verifying {} matches the catalog file using signtool",
+            file_path
+                .file_name()
+                .expect("Unable to read file name from the path")
+                .to_string_lossy()
+        );
+        let driver_binary_file_path = file_path.to_string_lossy();
+        for dest_cat_file_path in self.dest_cat_file_paths() {
+            let cat_file_path = dest_cat_file_path.to_string_lossy();
+            let args =
+                signtool::verify_catalog_member_args(&cat_file_path, &driver_binary_file_path);
+            if let Err(e) = self.run_tool("signtool", &args, None, None) {
+                return Err(PackageTaskError::StaleCatalog(
+                    file_path.to_owned(),
+                    dest_cat_file_path.to_path_buf(),
+                    e,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the enhanced key usages of the certificate the package was signed
+    /// with using `certutil`, i.e. the test certificate in the certificate
+    /// store or the PFX certificate, and fails if they do not include code
+    /// signing. Such a certificate produces signatures that `signtool verify`
+    /// accepts, but the driver fails to install on production machines.
+    fn run_eku_check(&self) -> Result<(), PackageTaskError> {
+        info!("Checking enhanced key usages of the signing certificate using certutil");
+        let pfx_path = self.signing_pfx.map(|pfx| pfx.path.to_string_lossy());
+        let certificate =
+            self.signing_certificate(pfx_path.as_deref(), WDR_TEST_CERT_STORE, WDR_LOCAL_TEST_CERT);
+        let output = self
+            .run_tool("certutil", &eku::certutil_dump_args(certificate), None, None)
+            .map_err(|e| PackageTaskError::CertutilDumpCommand(certificate.to_string(), e))?;
+        let stdout = String::from_utf8(output.stdout)
+            .map_err(PackageTaskError::CertutilDumpInvalidCommandOutput)?;
+        eku::check_driver_signing(&stdout)
+            .map_err(|ekus| PackageTaskError::MissingCodeSigningEku(certificate.to_string(), ekus))
+    }
+
+    /// Lists the imports of the packaged driver binary at `driver_binary_path`
+    /// using dumpbin and fails if any of them is unavailable on the oldest
+    /// Windows build the driver targets, as declared by its framework version
+    /// and the `[Manufacturer]` decorations of its INF, or, for Universal user
+    /// mode drivers, outside the OneCore API surface. The WDK build detected
+    /// by `WdkBuild` is only reported since the WDK that links the driver
+    /// provides every function it imports.
+    fn run_import_check(&self, driver_binary_path: &Path) -> Result<(), PackageTaskError> {
+        info!(
+            "Checking imports of {} using dumpbin",
+            driver_binary_path.to_string_lossy()
+        );
+        let wdk_build_number = self.wdk_build.detect_wdk_build_number()?;
+        let inf = InfFile::parse(&self.fs.read_file_to_string(&self.dest_inf_file_path)?);
+        let minimum_build_number = import_check::minimum_build_number(
+            &self.driver_model,
+            os_version::inf_minimum_build_number(&inf),
+        );
+        debug!(
+            "Driver targets Windows build {minimum_build_number} and is built with WDK build \
+             {wdk_build_number}"
+        );
+        let driver_binary_file_path = driver_binary_path.to_string_lossy();
+        let args = ["/nologo", "/imports", &driver_binary_file_path];
+        let output = self
+            .run_tool("dumpbin", &args, None, None)
+            .map_err(PackageTaskError::DumpbinImportsCommand)?;
+        let stdout = String::from_utf8(output.stdout)
+            .map_err(PackageTaskError::DumpbinImportsInvalidCommandOutput)?;
+
+        let imports = import_check::parse_dumpbin_imports(&stdout);
+        debug!("Driver binary imports {} functions", imports.len());
+        let disallowed_imports =
+            import_check::find_disallowed_imports(imports.clone(), minimum_build_number);
+        if disallowed_imports.is_empty() {
+            return self.check_universal_imports(imports);
+        }
+
+        let disallowed_imports = disallowed_imports
+            .iter()
+            .map(|disallowed| {
+                format!(
+                    "{}!{} (requires build {})",
+                    disallowed.import.module,
+                    disallowed.import.function,
+                    disallowed.minimum_build_number
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        Err(PackageTaskError::DisallowedImports {
+            minimum_build_number,
+            wdk_build_number,
+            imports: disallowed_imports,
+        })
+    }
+
+    /// Checks that a user mode driver packaged for the Universal target
+    /// platform only imports functions of the OneCore API surface. Kernel mode
+    /// drivers link against the same libraries for every target platform, so
+    /// they are not checked.
+    fn check_universal_imports(
+        &self,
+        imports: Vec<import_check::Import>,
+    ) -> Result<(), PackageTaskError> {
+        if self.infverif_target_platform() != TargetPlatform::Universal
+            || !matches!(self.driver_model, DriverConfig::Umdf(_))
+        {
+            return Ok(());
+        }
+        debug!("Checking driver binary imports against the OneCore API surface");
+        let non_universal_imports = import_check::find_non_universal_imports(imports);
+        if non_universal_imports.is_empty() {
+            return Ok(());
+        }
+        Err(PackageTaskError::NonUniversalImports(
+            non_universal_imports
+                .iter()
+                .map(|import| format!("{}!{}", import.module, import.function))
+                .collect::<Vec<_>>()
+                .join(", "),
+        ))
+    }
+
+    /// Reads the PE image of the driver binary and checks its architecture
+    /// and, for UMDF drivers, its exports
+    fn check_driver_binary(
+        &self,
+        driver_binary_path: &Path,
+        expected_arch: CpuArchitecture,
+    ) -> Result<(), PackageTaskError> {
+        let image = self.fs.read_file(driver_binary_path)?;
+        Self::check_driver_binary_arch(driver_binary_path, &image, expected_arch)?;
+        self.check_umdf_exports(driver_binary_path, &image)
+    }
+
+    /// Checks that the machine type in the PE header of the driver binary
+    /// matches the architecture the package is created for. A mismatch means
+    /// the toolchain built the binary for a different target than requested.
+    fn check_driver_binary_arch(
+        driver_binary_path: &Path,
+        image: &[u8],
+        expected_arch: CpuArchitecture,
+    ) -> Result<(), PackageTaskError> {
+        debug!(
+            "Checking architecture of driver binary: {}",
+            driver_binary_path.display()
+        );
+        let machine = pe::read_machine(image)
+            .ok_or_else(|| PackageTaskError::InvalidDriverBinary(driver_binary_path.to_owned()))?;
+        match pe::machine_architecture(machine) {
+            Some(arch) if arch == expected_arch => Ok(()),
+            arch => Err(PackageTaskError::DriverBinaryArchMismatch {
+                path: driver_binary_path.to_owned(),
+                expected: expected_arch,
+                found: arch.map_or_else(
+                    || format!("machine type {machine:#06x}"),
+                    |arch| arch.to_string(),
+                ),
+            }),
+        }
+    }
+
+    /// Checks that a UMDF 2 driver binary exports the entry points the UMDF
+    /// host process looks up when it loads the driver. A missing export only
+    /// fails when the driver is loaded, without naming the export.
+    fn check_umdf_exports(
+        &self,
+        driver_binary_path: &Path,
+        image: &[u8],
+    ) -> Result<(), PackageTaskError> {
+        let DriverConfig::Umdf(umdf_config) = self.driver_model else {
+            return Ok(());
+        };
+        if umdf_config.umdf_version_major < 2 {
+            return Ok(());
+        }
+        debug!(
+            "Checking exports of UMDF driver binary: {}",
+            driver_binary_path.display()
+        );
+        let exports = pe::read_export_names(image)
+            .ok_or_else(|| PackageTaskError::InvalidDriverBinary(driver_binary_path.to_owned()))?;
+        let missing_exports = UMDF_REQUIRED_EXPORTS
+            .into_iter()
+            .filter(|required| !exports.iter().any(|export| export == required))
+            .collect::<Vec<_>>();
+        if missing_exports.is_empty() {
+            return Ok(());
+        }
+        Err(PackageTaskError::MissingUmdfExports(
+            driver_binary_path.to_owned(),
+            missing_exports.join(", "),
+        ))
+    }
+
+    /// Reports the minimum OS version the driver binary is linked for, i.e.
+    /// the subsystem version of its PE optional header, and records a warning
+    /// if the `[Manufacturer]` decorations of the INF target other OS
+    /// versions. INFs without OS version decorations are not compared.
+    fn run_os_version_check(&self, driver_binary_path: &Path) -> Result<(), PackageTaskError> {
+        debug!("Reading driver binary headers using dumpbin");
+        let driver_binary_file_path = driver_binary_path.to_string_lossy();
+        let args = ["/nologo", "/headers", &driver_binary_file_path];
+        let output = self
+            .run_tool("dumpbin", &args, None, None)
+            .map_err(PackageTaskError::DumpbinHeadersCommand)?;
+        let stdout = String::from_utf8(output.stdout)
+            .map_err(PackageTaskError::DumpbinHeadersInvalidCommandOutput)?;
+
+        let Some(binary_os_version) = os_version::parse_dumpbin_subsystem_version(&stdout) else {
+            warn!("Could not read the subsystem version of {driver_binary_file_path}");
+            return Ok(());
+        };
+        info!(
+            "{} requires Windows {binary_os_version} or later",
+            driver_binary_path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+        );
+
+        let inf = InfFile::parse(&self.fs.read_file_to_string(&self.dest_inf_file_path)?);
+        let inf_os_versions = os_version::inf_os_versions(&inf);
+        if inf_os_versions.is_empty() || inf_os_versions.contains(&binary_os_version) {
+            return Ok(());
+        }
+        let inf_os_versions = inf_os_versions
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.warnings.record(
+            &self.package_name,
+            "os-version-check",
+            format!(
+                "{driver_binary_file_path} requires Windows {binary_os_version} but the INF \
+                 targets Windows {inf_os_versions}"
+            ),
+        );
+        Ok(())
+    }
+
+    fn run_infverif(&self) -> Result<(), PackageTaskError> {
+        let additional_args = if self.sample_class {
+            let wdk_build_number = self.wdk_build.detect_wdk_build_number()?;
+            if MISSING_SAMPLE_FLAG_WDK_BUILD_NUMBER_RANGE.contains(&wdk_build_number) {
+                debug!(
+                    "InfVerif in WDK Build {wdk_build_number} is bugged and does not contain the \
+                     /samples flag."
+                );
+                warn!("InfVerif skipped for samples class. WDK Build: {wdk_build_number}");
+                return Ok(());
+            }
+            "/msft"
+        } else {
+            ""
+        };
+
+        let mode_flag = self.infverif_target_platform().as_infverif_flag();
+        let env_vars = self.tool_env_vars();
+        for dest_inf_file_path in self.dest_inf_file_paths() {
+            info!("Running infverif on {}", dest_inf_file_path.display());
+
+            let mut args = vec!["/v", mode_flag];
+            let inf_path = dest_inf_file_path.to_string_lossy();
+
+            if self.sample_class {
+                args.push(additional_args);
+            }
+            args.push(&inf_path);
+            self.run_infverif_on(dest_inf_file_path, &args, env_vars.as_ref())?;
+        }
+
+        // Validating for the desktop target platform does not check the rules of
+        // universal INFs, so universal INFs are also validated for them
+        if self.inf_format == InfFormat::Universal
+            && self.infverif_target_platform() == TargetPlatform::Desktop
+        {
+            for dest_inf_file_path in self.dest_inf_file_paths() {
+                info!(
+                    "Running infverif universal INF checks on {}",
+                    dest_inf_file_path.display()
+                );
+                let inf_path = dest_inf_file_path.to_string_lossy();
+                let args = [
+                    "/v",
+                    TargetPlatform::Universal.as_infverif_flag(),
+                    &inf_path,
+                ];
+                self.run_infverif_on(dest_inf_file_path, &args, env_vars.as_ref())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs infverif with the given arguments on a packaged INF. When a
+    /// universal INF fails the validation, the errors infverif reports for the
+    /// violated INF rules are returned.
+    ///
+    /// # Errors
+    /// * `PackageTaskError::UniversalInfViolations` - If infverif reports rule
+    ///   violations of a universal INF.
+    /// * `PackageTaskError::InfVerificationCommand` - If infverif fails
+    ///   otherwise.
+    fn run_infverif_on(
+        &self,
+        dest_inf_file_path: &Path,
+        args: &[&str],
+        env_vars: Option<&HashMap<&str, &str>>,
+    ) -> Result<(), PackageTaskError> {
+        let output = match self.run_tool("infverif", args, env_vars, None) {
+            Ok(output) => output,
+            Err(CommandError::CommandFailed { ref stdout, .. })
+                if self.inf_format == InfFormat::Universal
+                    && !infverif_rule_violations(stdout).is_empty() =>
+            {
+                return Err(PackageTaskError::UniversalInfViolations(
+                    dest_inf_file_path.to_path_buf(),
+                    infverif_rule_violations(stdout).join("\n"),
+                ));
+            }
+            Err(err) => return Err(PackageTaskError::InfVerificationCommand(err)),
+        };
+        self.warnings
+            .record_from_output(&self.package_name, "infverif", &output.stdout);
+        Ok(())
+    }
+}
+
+/// Returns the absolute paths embedded in a catalog file. Catalogs store
+/// strings as ASCII or UTF-16, so both encodings are searched for runs of
+/// printable characters that contain a path such as `C:\build`.
+fn absolute_paths_in_catalog(catalog: &[u8]) -> Vec<String> {
+    // UTF-16 code units in the ASCII range, starting at an even or odd offset,
+    // with every other code unit mapped to NUL
+    let utf16_as_ascii = |offset: usize| -> Vec<u8> {
+        catalog
+            .get(offset..)
+            .unwrap_or_default()
+            .chunks_exact(2)
+            .map(|code_unit| if code_unit[1] == 0 { code_unit[0] } else { 0 })
+            .collect()
+    };
+    let mut absolute_paths = Vec::new();
+    for bytes in [catalog.to_vec(), utf16_as_ascii(0), utf16_as_ascii(1)] {
+        for run in bytes.split(|byte| !(byte.is_ascii_graphic() || *byte == b' ')) {
+            let run = String::from_utf8_lossy(run);
+            if let Some(start) = (0..run.len()).find(|&index| inf::is_absolute_path(&run[index..]))
+            {
+                absolute_paths.push(run[start..].to_string());
+            }
+        }
+    }
+    absolute_paths.sort();
+    absolute_paths.dedup();
+    absolute_paths
+}
+
+/// Returns the errors infverif reports for violated INF rules, e.g.
+/// `ERROR(1324) in driver.inf, line 12: ...`
+fn infverif_rule_violations(stdout: &str) -> Vec<&str> {
+    stdout
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("ERROR("))
+        .collect()
+}
+
+/// Returns the `PATH` the packaging tools are run with: the tool search
+/// paths, resolved against `working_dir`, followed by the current `PATH`.
+/// Returns `None` if there are no tool search paths, leaving `PATH` unchanged.
+fn tool_path(working_dir: &Path, tool_search_paths: &[PathBuf]) -> Option<String> {
+    if tool_search_paths.is_empty() {
+        return None;
+    }
+    let current_path = std::env::var_os("PATH").unwrap_or_default();
+    let search_paths = tool_search_paths
+        .iter()
+        .map(|path| working_dir.join(path))
+        .chain(std::env::split_paths(&current_path));
+    // Search paths containing the path separator are rejected when the
+    // package settings are read, so joining only fails for a malformed `PATH`
+    std::env::join_paths(search_paths)
+        .ok()
+        .map(|path| path.to_string_lossy().into_owned())
+}
+
+/// Returns the inf2cat OS identifier for the given architecture
+const fn inf2cat_os(arch: CpuArchitecture) -> &'static str {
+    match arch {
+        CpuArchitecture::Amd64 => "10_x64",
+        CpuArchitecture::Arm64 => "Server10_arm64",
+    }
+}
+
+/// An RAII wrapper over a Win API named mutex
+struct NamedMutex {
+    handle: HANDLE,
+    // `ReleaseMutex` requires that it is called
+    // only by threads that own the mutex handle.
+    // Being `!Send` ensures that's always the case.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl NamedMutex {
+    /// Acquires named mutex
+    pub fn acquire(name: &CStr) -> Result<Self, WinError> {
+        fn get_last_error() -> WinError {
+            // SAFETY: We have to just assume this function is safe to call
+            // because the windows crate has no documentation for it and
+            // the MSDN documentation does not specify any preconditions
+            // for calling it
+            unsafe { GetLastError().into() }
+        }
+
+        // SAFETY: The name ptr is valid because it comes from a CStr
+        let handle = unsafe { CreateMutexA(None, false, PCSTR(name.as_ptr().cast()))? };
+        if handle.is_invalid() {
+            return Err(get_last_error());
+        }
+
+        // SAFETY: The handle is valid since it was created right above
+        match unsafe { WaitForSingleObject(handle, INFINITE) } {
+            res if res == WAIT_OBJECT_0 || res == WAIT_ABANDONED => Ok(Self {
+                handle,
+                _not_send: PhantomData,
+            }),
+            _ => {
+                // SAFETY: The handle is valid since it was created right above
+                unsafe { CloseHandle(handle)? };
+                Err(get_last_error())
+            }
+        }
+    }
+}
+
+impl Drop for NamedMutex {
+    fn drop(&mut self) {
+        // SAFETY: the handle is guaranteed to be valid
+        // because this type itself created it and it
+        // was never exposed outside. Also the requirement
+        // that the calling thread must own the handle
+        // is upheld because this type is `!Send`
+        let _ = unsafe { ReleaseMutex(self.handle) };
+
+        // SAFETY: the handle is valid as explained above.
+        let _ = unsafe { CloseHandle(self.handle) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        os::windows::process::ExitStatusExt,
+        path::PathBuf,
+        process::{ExitStatus, Output},
+        sync::{Arc, Mutex},
+    };
+
+    use mockall::{Sequence, predicate::eq};
+    use wdk_build::{CpuArchitecture, KmdfConfig, UmdfConfig};
+
+    use super::*;
+    use crate::providers::fs::DirEntryInfo;
+
+    /// Owns the values borrowed by the package task params of a test
+    #[derive(Default)]
+    struct TestParams {
+        warnings: BuildWarnings,
+        tool_log: ToolLog,
+        tool_paths: ToolPaths,
+    }
+
+    impl TestParams {
+        /// Returns the params of an unsigned KMDF package with every optional
+        /// step turned off. Tests override the fields they exercise using
+        /// struct update syntax.
+        fn get<'a>(
+            &'a self,
+            package_name: &'a str,
+            working_dir: &'a Path,
+            target_dir: &'a Path,
+            target_arch: &'a CpuArchitecture,
+        ) -> PackageTaskParams<'a> {
+            PackageTaskParams {
+                package_name,
+                working_dir,
+                target_dir,
+                target_arch,
+                driver_model: DriverConfig::Kmdf(KmdfConfig::default()),
+                sample_class: false,
+                sign_mode: SignMode::Off,
+                target_platform: TargetPlatform::Universal,
+                import_check: false,
+                os_version_check: false,
+                wdf_verifier: None,
+                warnings: &self.warnings,
+                tool_log: &self.tool_log,
+                debug_files: &[],
+                binary_name: None,
+                temp_dir: None,
+                tool_search_paths: &[],
+                tool_paths: &self.tool_paths,
+                include_pdb: true,
+                include_map: true,
+                reproducible_driver_ver: None,
+                incremented_driver_ver: None,
+                min_version: None,
+                test_cert_validity: None,
+                signing_pfx: None,
+                prebuilt_binary_path: None,
+                multi_arch_target_dirs: &[],
+                relocatable: false,
+                additional_inx_files: &[],
+                inf_catalog: InfCatalog::PerInf,
+                inf_format: InfFormat::Universal,
+                overwrite_package: false,
+                pdb_file_name: None,
+                emit_install_summary: false,
+                emit_catalog_signing_report: false,
+                emit_uninstall: false,
+                env: &[],
+                catalog_exclude: &[],
+                skip_stampinf: false,
+                binary_only: false,
+                symbols_archive_dir: None,
+                out_dir: None,
+                out_dir_files: &[],
+                manifest: None,
+                checked_target_dir: None,
+                allowed_providers: &[],
+            }
+        }
+    }
+
+    #[test]
+    fn new_succeeds_for_valid_args() {
+        let package_name = "test_package";
+        let working_dir = PathBuf::from("D:/absolute/path/to/working/dir");
+        let target_dir = PathBuf::from("C:/absolute/path/to/target/dir");
+        let arch = CpuArchitecture::Amd64;
+
+        let test_params = TestParams::default();
+        let package_task_params = PackageTaskParams {
+            sign_mode: SignMode::Test {
+                verify_signature: false,
+            },
+            ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+        };
+        let dest_root = target_dir.join(format!("{package_name}_package"));
+
+        let command_exec = CommandExec::default();
+        let wdk_build = WdkBuild::default();
+        let fs = Fs::default();
+        let task = PackageTask::new(package_task_params, &wdk_build, &command_exec, &fs);
+        assert_eq!(task.package_name, package_name.replace('-', "_"));
+        assert_eq!(
+            task.sign_mode,
+            SignMode::Test {
+                verify_signature: false,
+            }
+        );
+        assert!(!task.sample_class);
+        assert_eq!(task.src_inx_file_path, working_dir.join("test_package.inx"));
+        assert_eq!(
+            task.src_driver_binary_file_path,
+            target_dir.join("test_package.dll")
+        );
+        assert_eq!(
+            task.src_renamed_driver_binary_file_path,
+            target_dir.join("test_package.sys")
+        );
+        assert_eq!(task.src_pdb_file_path, target_dir.join("test_package.pdb"));
+        assert_eq!(
+            task.src_map_file_path,
+            target_dir.join("deps").join("test_package.map")
+        );
+        assert_eq!(
+            task.src_cert_file_path,
+            target_dir.join("WDRLocalTestCert.cer")
+        );
+        assert_eq!(task.dest_root_package_folder, dest_root);
+        assert_eq!(task.dest_inf_file_path, dest_root.join("test_package.inf"));
+        assert_eq!(
+            task.dest_driver_binary_path,
+            dest_root.join("test_package.sys")
+        );
+        assert_eq!(task.dest_pdb_file_path, dest_root.join("test_package.pdb"));
+        assert_eq!(task.dest_map_file_path, dest_root.join("test_package.map"));
+        assert_eq!(
+            task.dest_cert_file_path,
+            dest_root.join("WDRLocalTestCert.cer")
+        );
+        assert_eq!(task.dest_cat_file_path, dest_root.join("test_package.cat"));
+        assert_eq!(*task.arch, arch);
+        assert_eq!(task.os_mapping, "10_x64");
+        assert!(matches!(task.driver_model, DriverConfig::Kmdf(_)));
+    }
+
+    #[test]
+    fn temp_dir_is_passed_to_packaging_tools_as_tmp_and_temp() {
+        let working_dir = PathBuf::from("D:/absolute/path/to/working/dir");
+        let target_dir = PathBuf::from("C:/absolute/path/to/target/dir");
+        let temp_dir = PathBuf::from("E:/absolute/path/to/temp");
+        let arch = CpuArchitecture::Amd64;
+
+        let test_params = TestParams::default();
+        let package_task_params = PackageTaskParams {
+            temp_dir: Some(&temp_dir),
+            ..test_params.get("test_package", &working_dir, &target_dir, &arch)
+        };
+
+        let command_exec = CommandExec::default();
+        let wdk_build = WdkBuild::default();
+        let fs = Fs::default();
+        let task = PackageTask::new(package_task_params, &wdk_build, &command_exec, &fs);
+        let temp_dir = temp_dir.to_string_lossy();
+        assert_eq!(
+            task.tool_env_vars(),
+            Some(HashMap::from([
+                ("TMP", temp_dir.as_ref()),
+                ("TEMP", temp_dir.as_ref())
+            ]))
+        );
+    }
+
+    #[test]
+    fn tool_search_paths_are_prepended_to_path_of_packaging_tools() {
+        let working_dir = PathBuf::from("D:/absolute/path/to/working/dir");
+        let target_dir = PathBuf::from("C:/absolute/path/to/target/dir");
+        let tool_search_paths = [
+            PathBuf::from("tools/bin"),
+            PathBuf::from("E:/absolute/path/to/wdk/tools"),
+        ];
+        let arch = CpuArchitecture::Amd64;
+
+        let test_params = TestParams::default();
+        let package_task_params = PackageTaskParams {
+            tool_search_paths: &tool_search_paths,
+            ..test_params.get("test_package", &working_dir, &target_dir, &arch)
+        };
+
+        let command_exec = CommandExec::default();
+        let wdk_build = WdkBuild::default();
+        let fs = Fs::default();
+        let task = PackageTask::new(package_task_params, &wdk_build, &command_exec, &fs);
+        let env_vars = task.tool_env_vars().expect("PATH should be set");
+        assert_eq!(env_vars.len(), 1);
+        let path = env_vars.get("PATH").expect("PATH should be set");
+        let search_paths: Vec<PathBuf> = std::env::split_paths(path).take(2).collect();
+        assert_eq!(
+            search_paths,
+            vec![
+                working_dir.join("tools/bin"),
+                PathBuf::from("E:/absolute/path/to/wdk/tools")
+            ]
+        );
+    }
+
+    #[test]
+    fn prebuilt_binary_is_packaged_without_renaming() {
+        let working_dir = PathBuf::from("D:/absolute/path/to/working/dir");
+        let target_dir = PathBuf::from("C:/absolute/path/to/msbuild/output");
+        let binary_path = target_dir.join("prebuilt.sys");
+        let arch = CpuArchitecture::Amd64;
+
+        let test_params = TestParams::default();
+        let package_task_params = PackageTaskParams {
+            driver_model: DriverConfig::Wdm,
+            prebuilt_binary_path: Some(&binary_path),
+            ..test_params.get("prebuilt", &working_dir, &target_dir, &arch)
+        };
+
+        let command_exec = CommandExec::default();
+        let wdk_build = WdkBuild::default();
+        let fs = Fs::default();
+        let task = PackageTask::new(package_task_params, &wdk_build, &command_exec, &fs);
+        let dest_root = target_dir.join("prebuilt_package");
+        assert!(task.prebuilt_binary);
+        assert_eq!(task.src_driver_binary_file_path, binary_path);
+        assert_eq!(task.src_renamed_driver_binary_file_path, binary_path);
+        assert_eq!(task.src_inx_file_path, working_dir.join("prebuilt.inx"));
+        assert_eq!(task.dest_driver_binary_path, dest_root.join("prebuilt.sys"));
+        assert_eq!(task.dest_inf_file_path, dest_root.join("prebuilt.inf"));
+    }
+
+    #[test]
+    fn dest_file_paths_lists_only_the_files_written_to_the_package() {
+        let working_dir = PathBuf::from("D:/absolute/path/to/working/dir");
+        let target_dir = PathBuf::from("C:/absolute/path/to/target/dir");
+        let debug_files = [working_dir.join("symbols").join("driver.tmf")];
+        let out_dir = target_dir.join("build/driver-0123456789abcdef/out");
+        let out_dir_files = [PathBuf::from("gen/driver_fragment.inf")];
+        let arch = CpuArchitecture::Amd64;
+
+        let test_params = TestParams::default();
+        let package_task_params = PackageTaskParams {
+            debug_files: &debug_files,
+            include_map: false,
+            out_dir: Some(&out_dir),
+            out_dir_files: &out_dir_files,
+            ..test_params.get("driver", &working_dir, &target_dir, &arch)
+        };
+
+        let command_exec = CommandExec::default();
+        let wdk_build = WdkBuild::default();
+        let fs = Fs::default();
+        let task = PackageTask::new(package_task_params, &wdk_build, &command_exec, &fs);
+        let dest_root = target_dir.join("driver_package");
+        assert_eq!(
+            task.dest_file_paths(),
+            [
+                dest_root.join("driver.sys"),
+                dest_root.join("driver.pdb"),
+                dest_root.join("driver.inf"),
+                dest_root.join("driver.cat"),
+                dest_root.join(DEBUG_FOLDER_NAME).join("driver.tmf"),
+                dest_root.join("gen/driver_fragment.inf"),
+            ]
+        );
+    }
+
+    #[test]
+    fn copy_out_dir_files_fails_when_the_build_script_did_not_generate_a_file() {
+        let working_dir = PathBuf::from("D:/absolute/path/to/working/dir");
+        let target_dir = PathBuf::from("C:/absolute/path/to/target/dir");
+        let out_dir = target_dir.join("build/driver-0123456789abcdef/out");
+        let out_dir_files = [
+            PathBuf::from("gen/driver_fragment.inf"),
+            PathBuf::from("strings.txt"),
+        ];
+        let arch = CpuArchitecture::Amd64;
+
+        for strings_generated in [true, false] {
+            let test_params = TestParams::default();
+            let package_task_params = PackageTaskParams {
+                include_map: false,
+                out_dir: Some(&out_dir),
+                out_dir_files: &out_dir_files,
+                ..test_params.get("driver", &working_dir, &target_dir, &arch)
+            };
+
+            let command_exec = CommandExec::default();
+            let wdk_build = WdkBuild::default();
+            let mut fs = Fs::default();
+            let dest_root = target_dir.join("driver_package");
+            let src_fragment = out_dir.join("gen/driver_fragment.inf");
+            let src_strings = out_dir.join("strings.txt");
+            // The `gen` folder does not exist in the package yet
+            fs.expect_exists().returning(move |path: &Path| {
+                path == src_fragment || (path == src_strings && strings_generated)
+            });
+            if strings_generated {
+                fs.expect_create_dir_all()
+                    .with(eq(dest_root.join("gen")))
+                    .once()
+                    .returning(|_| Ok(()));
+                fs.expect_copy()
+                    .with(
+                        eq(out_dir.join("gen/driver_fragment.inf")),
+                        eq(dest_root.join("gen/driver_fragment.inf")),
+                    )
+                    .once()
+                    .returning(|_, _| Ok(0));
+                fs.expect_copy()
+                    .with(
+                        eq(out_dir.join("strings.txt")),
+                        eq(dest_root.join("strings.txt")),
+                    )
+                    .once()
+                    .returning(|_, _| Ok(0));
+            }
+            let task = PackageTask::new(package_task_params, &wdk_build, &command_exec, &fs);
+
+            let result = task.copy_out_dir_files();
+            if strings_generated {
+                assert!(result.is_ok(), "{result:?}");
+            } else {
+                assert!(matches!(
+                    result,
+                    Err(PackageTaskError::MissingOutDirFiles(ref files))
+                        if files.ends_with("strings.txt") && !files.contains("driver_fragment")
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn check_min_version_requires_the_stamped_driver_ver_version_to_be_greater() {
+        let working_dir = PathBuf::from("D:/absolute/path/to/working/dir");
+        let target_dir = PathBuf::from("C:/absolute/path/to/target/dir");
+        let arch = CpuArchitecture::Amd64;
+
+        for (inf_content, min_version, expected_version) in [
+            ("[Version]\r\nDriverVer = 10/16/2026,1.2.3.4\r\n", "1.2.3.3", None),
+            ("[Version]\r\nDriverVer = 10/16/2026,1.2.3.4\r\n", "1.2.3.4", Some("1.2.3.4")),
+            ("[Version]\r\nDriverVer = 10/16/2026,1.2.3.4\r\n", "1.10.0.0", Some("1.2.3.4")),
+            ("[Version]\r\nClass = System\r\n", "1.0.0.0", None),
+        ] {
+            let min_version = min_version
+                .parse::<DriverVerVersion>()
+                .expect("version should be valid");
+            let test_params = TestParams::default();
+            let package_task_params = PackageTaskParams {
+                include_map: false,
+                min_version: Some(min_version),
+                ..test_params.get("driver", &working_dir, &target_dir, &arch)
+            };
+
+            let command_exec = CommandExec::default();
+            let wdk_build = WdkBuild::default();
+            let mut fs = Fs::default();
+            fs.expect_read_file_to_string()
+                .with(eq(target_dir.join("driver_package").join("driver.inf")))
+                .once()
+                .returning(move |_| Ok(inf_content.to_string()));
+            let task = PackageTask::new(package_task_params, &wdk_build, &command_exec, &fs);
+
+            let result = task.check_min_version();
+            if !inf_content.contains("DriverVer") {
+                assert!(
+                    matches!(result, Err(PackageTaskError::MissingDriverVerVersion(_))),
+                    "{result:?}"
+                );
+                continue;
+            }
+            match expected_version {
+                None => assert!(result.is_ok(), "{min_version}: {result:?}"),
+                Some(expected_version) => assert!(
+                    matches!(
+                        result,
+                        Err(PackageTaskError::DriverVerVersionNotGreater {
+                            version,
+                            min_version: error_min_version,
+                            ..
+                        }) if version.to_string() == expected_version
+                            && error_min_version == min_version
+                    ),
+                    "{min_version}: {result:?}"
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn manifest_is_validated_and_embedded_into_the_driver_binary_using_mt() {
+        let working_dir = PathBuf::from("D:/absolute/path/to/working/dir");
+        let target_dir = PathBuf::from("C:/absolute/path/to/target/dir");
+        let manifest = PathBuf::from("host.manifest");
+        let manifest_file_path = working_dir.join(&manifest);
+        let arch = CpuArchitecture::Amd64;
+
+        for (manifest_content, is_well_formed) in [
+            (None, false),
+            (Some("<assembly>\n<trustInfo></assembly>"), false),
+            (Some("<assembly manifestVersion=\"1.0\"/>"), true),
+        ] {
+            let test_params = TestParams::default();
+            let package_task_params = PackageTaskParams {
+                driver_model: DriverConfig::Umdf(UmdfConfig::default()),
+                include_map: false,
+                manifest: Some(&manifest),
+                ..test_params.get("driver", &working_dir, &target_dir, &arch)
+            };
+
+            let mut command_exec = CommandExec::default();
+            let wdk_build = WdkBuild::default();
+            let mut fs = Fs::default();
+            let expected_manifest_file_path = manifest_file_path.clone();
+            fs.expect_exists()
+                .withf(move |path: &Path| path == expected_manifest_file_path)
+                .returning(move |_| manifest_content.is_some());
+            if let Some(manifest_content) = manifest_content {
+                fs.expect_read_file_to_string()
+                    .with(eq(manifest_file_path.clone()))
+                    .returning(move |_| Ok(manifest_content.to_string()));
+            }
+            let dest_driver_binary_path = target_dir.join("driver_package").join("driver.dll");
+            if is_well_formed {
+                let expected_args = [
+                    "-nologo".to_string(),
+                    "-manifest".to_string(),
+                    manifest_file_path.to_string_lossy().to_string(),
+                    format!("-outputresource:{};#2", dest_driver_binary_path.display()),
+                ];
+                command_exec
+                    .expect_run()
+                    .withf(move |command: &str, args: &[&str], _, _| {
+                        command == "mt" && args.iter().eq(expected_args.iter())
+                    })
+                    .once()
+                    .returning(|_, _, _, _| {
+                        Ok(Output {
+                            status: ExitStatus::default(),
+                            stdout: vec![],
+                            stderr: vec![],
+                        })
+                    });
+            }
+            let task = PackageTask::new(package_task_params, &wdk_build, &command_exec, &fs);
+
+            let result = task.check_manifest();
+            if is_well_formed {
+                assert!(result.is_ok(), "{result:?}");
+                let result = task.embed_manifest(&dest_driver_binary_path);
+                assert!(result.is_ok(), "{result:?}");
+            } else if manifest_content.is_none() {
+                assert!(matches!(
+                    result,
+                    Err(PackageTaskError::MissingManifestFile(ref path))
+                        if *path == manifest_file_path
+                ));
+            } else {
+                assert!(matches!(
+                    result,
+                    Err(PackageTaskError::InvalidManifest(_, ref error)) if error.line == 2
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn pdb_is_packaged_under_the_given_pdb_file_name() {
+        let working_dir = PathBuf::from("D:/absolute/path/to/working/dir");
+        let target_dir = PathBuf::from("C:/absolute/path/to/target/dir");
+        let arch = CpuArchitecture::Amd64;
+
+        let test_params = TestParams::default();
+        let package_task_params = PackageTaskParams {
+            include_map: false,
+            pdb_file_name: Some("my_driver.pdb"),
+            ..test_params.get("driver", &working_dir, &target_dir, &arch)
+        };
+
+        let command_exec = CommandExec::default();
+        let wdk_build = WdkBuild::default();
+        let fs = Fs::default();
+        let task = PackageTask::new(package_task_params, &wdk_build, &command_exec, &fs);
+        let dest_root = target_dir.join("driver_package");
+        assert_eq!(
+            task.dest_file_paths(),
+            [
+                dest_root.join("driver.sys"),
+                dest_root.join("my_driver.pdb"),
+                dest_root.join("driver.inf"),
+                dest_root.join("driver.cat"),
+            ]
+        );
+    }
+
+    #[test]
+    fn checked_variant_is_packaged_next_to_the_package_in_its_own_folder() {
         let working_dir = PathBuf::from("D:/absolute/path/to/working/dir");
         let target_dir = PathBuf::from("C:/absolute/path/to/target/dir");
+        let checked_target_dir = PathBuf::from("C:/absolute/path/to/target/checked/dir");
+        let arch = CpuArchitecture::Amd64;
+
+        let test_params = TestParams::default();
+        let package_task_params = PackageTaskParams {
+            include_map: false,
+            checked_target_dir: Some(&checked_target_dir),
+            ..test_params.get("driver", &working_dir, &target_dir, &arch)
+        };
+
+        let command_exec = CommandExec::default();
+        let wdk_build = WdkBuild::default();
+        let fs = Fs::default();
+        let task = PackageTask::new(package_task_params, &wdk_build, &command_exec, &fs);
+        let dest_root = target_dir.join("driver_package_checked");
+        assert_eq!(
+            task.dest_file_paths(),
+            [
+                dest_root.join("driver.sys"),
+                dest_root.join("driver.pdb"),
+                dest_root.join("driver.inf"),
+                dest_root.join("driver.cat"),
+            ]
+        );
+        // The binaries of the checked build never enter the target directory
+        // of the package
+        assert_eq!(
+            task.src_driver_binary_file_path,
+            checked_target_dir.join("driver.dll")
+        );
+        assert_eq!(
+            task.src_renamed_driver_binary_file_path,
+            checked_target_dir.join("driver.sys")
+        );
+        assert_eq!(
+            task.src_pdb_file_path,
+            checked_target_dir.join("driver.pdb")
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Target directory path must be absolute. Input path: \
+                               ../relative/path/to/target/dir")]
+    fn new_panics_when_target_dir_is_not_absolute() {
+        let package_name = "test_package";
+        let working_dir = PathBuf::from("C:/absolute/path/to/working/dir");
+        let target_dir = PathBuf::from("../relative/path/to/target/dir");
+        let arch = CpuArchitecture::Amd64;
+
+        let test_params = TestParams::default();
+        let package_task_params = PackageTaskParams {
+            sign_mode: SignMode::Test {
+                verify_signature: false,
+            },
+            ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+        };
+
+        let command_exec = CommandExec::default();
+        let wdk_build = WdkBuild::default();
+        let fs = Fs::default();
+
+        PackageTask::new(package_task_params, &wdk_build, &command_exec, &fs);
+    }
+
+    #[test]
+    #[should_panic(expected = "Working directory path must be absolute. Input path: \
+                               relative/path/to/working/dir")]
+    fn new_panics_when_working_dir_is_not_absolute() {
+        let package_name = "test_package";
+        let working_dir = PathBuf::from("relative/path/to/working/dir");
+        let target_dir = PathBuf::from("E:/absolute/path/to/target/dir");
+        let arch = CpuArchitecture::Amd64;
+
+        let test_params = TestParams::default();
+        let package_task_params = PackageTaskParams {
+            sign_mode: SignMode::Test {
+                verify_signature: false,
+            },
+            ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+        };
+
+        let command_exec = CommandExec::default();
+        let wdk_build = WdkBuild::default();
+        let fs = Fs::default();
+
+        PackageTask::new(package_task_params, &wdk_build, &command_exec, &fs);
+    }
+
+    #[test]
+    fn stampinf_version_overrides_with_env_var() {
+        // verify both with and without the env var set scenarios
+        let scenarios = [
+            ("env_set", Some("1.2.3.4"), true),
+            ("env_empty", Some(""), false),
+            ("env_spaces", Some("  "), false),
+            ("env_unset", None, false),
+        ];
+
+        for (name, env_val, expect_skip_v) in scenarios {
+            let result =
+                crate::test_utils::with_env(&[(STAMPINF_VERSION_ENV_VAR, env_val)], || {
+                    let package_name = "driver";
+                    let working_dir = PathBuf::from("C:/abs/driver");
+                    let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+                    let arch = CpuArchitecture::Amd64;
+
+                    let test_params = TestParams::default();
+                    let params = PackageTaskParams {
+                        sign_mode: SignMode::Test {
+                            verify_signature: false,
+                        },
+                        ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+                    };
+
+                    let wdk_build = WdkBuild::default();
+                    let fs = Fs::default();
+                    let mut command_exec = CommandExec::default();
+
+                    command_exec
+                        .expect_run()
+                        .withf(move |cmd: &str, args: &[&str], _, _| {
+                            if cmd != "stampinf" {
+                                return false;
+                            }
+                            let has_v = args.contains(&"-v");
+                            if expect_skip_v {
+                                !has_v
+                            } else {
+                                args.windows(2).any(|w| w == ["-v", "*"])
+                            }
+                        })
+                        .once()
+                        .return_once(|_, _, _, _| {
+                            Ok(Output {
+                                status: ExitStatus::default(),
+                                stdout: vec![],
+                                stderr: vec![],
+                            })
+                        });
+
+                    let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+                    task.run_stampinf()
+                });
+
+            assert!(
+                result.is_ok(),
+                "scenario {name} failed (env_set={env_val:?})"
+            );
+        }
+    }
+
+    #[test]
+    fn additional_infs_are_stamped_with_the_catalog_file_covering_them() {
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let additional_inx_files = [PathBuf::from("infs/filter.inx")];
+        let arch = CpuArchitecture::Amd64;
+        let dest_root = target_dir.join("driver_package");
+
+        for (inf_catalog, expected_cat_file_name) in [
+            (InfCatalog::PerInf, "filter.cat"),
+            (InfCatalog::Combined, "driver.cat"),
+        ] {
+            let test_params = TestParams::default();
+            let params = PackageTaskParams {
+                driver_model: DriverConfig::Wdm,
+                include_pdb: false,
+                include_map: false,
+                additional_inx_files: &additional_inx_files,
+                inf_catalog,
+                ..test_params.get("driver", &working_dir, &target_dir, &arch)
+            };
+
+            let wdk_build = WdkBuild::default();
+            let fs = Fs::default();
+            let mut command_exec = CommandExec::default();
+            let expected_inf_file_path = dest_root.join("driver.inf").to_string_lossy().to_string();
+            command_exec
+                .expect_run()
+                .withf(move |cmd: &str, args: &[&str], _, _| {
+                    cmd == "stampinf"
+                        && args
+                            .windows(2)
+                            .any(|w| w == ["-f", expected_inf_file_path.as_str()])
+                        && args.windows(2).any(|w| w == ["-c", "driver.cat"])
+                })
+                .once()
+                .returning(|_, _, _, _| {
+                    Ok(Output {
+                        status: ExitStatus::default(),
+                        stdout: vec![],
+                        stderr: vec![],
+                    })
+                });
+            let expected_inf_file_path = dest_root.join("filter.inf").to_string_lossy().to_string();
+            command_exec
+                .expect_run()
+                .withf(move |cmd: &str, args: &[&str], _, _| {
+                    cmd == "stampinf"
+                        && args
+                            .windows(2)
+                            .any(|w| w == ["-f", expected_inf_file_path.as_str()])
+                        && args.windows(2).any(|w| w == ["-c", expected_cat_file_name])
+                })
+                .once()
+                .returning(|_, _, _, _| {
+                    Ok(Output {
+                        status: ExitStatus::default(),
+                        stdout: vec![],
+                        stderr: vec![],
+                    })
+                });
+
+            let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+            assert_eq!(
+                task.additional_infs[0].src_inx_file_path,
+                working_dir.join("infs/filter.inx")
+            );
+            task.run_stampinf()
+                .expect("stampinf should run on every INF");
+            let expected_cat_file_paths = match inf_catalog {
+                InfCatalog::PerInf => {
+                    vec![dest_root.join("driver.cat"), dest_root.join("filter.cat")]
+                }
+                InfCatalog::Combined => vec![dest_root.join("driver.cat")],
+            };
+            assert_eq!(task.dest_cat_file_paths(), expected_cat_file_paths);
+        }
+    }
+
+    fn assert_infverif_mode_flag(
+        driver_model: DriverConfig,
+        target_platform: TargetPlatform,
+        expected_mode_flag: &'static str,
+    ) {
+        assert_infverif_mode_flag_for_inf_format(
+            driver_model,
+            target_platform,
+            InfFormat::Universal,
+            expected_mode_flag,
+        );
+    }
+
+    fn assert_infverif_mode_flag_for_inf_format(
+        driver_model: DriverConfig,
+        target_platform: TargetPlatform,
+        inf_format: InfFormat,
+        expected_mode_flag: &'static str,
+    ) {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let arch = CpuArchitecture::Amd64;
+
+        let test_params = TestParams::default();
+        let params = PackageTaskParams {
+            driver_model,
+            target_platform,
+            inf_format,
+            ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+        };
+
+        let fs = Fs::default();
+        let wdk_build = WdkBuild::default();
+
+        let mut command_exec = CommandExec::default();
+        command_exec
+            .expect_run()
+            .withf(move |cmd: &str, args: &[&str], _, _| {
+                cmd == "infverif"
+                    && args.len() >= 2
+                    && args[0] == "/v"
+                    && args[1] == expected_mode_flag
+            })
+            .once()
+            .returning(|_, _, _, _| {
+                Ok(Output {
+                    status: ExitStatus::default(),
+                    stdout: vec![],
+                    stderr: vec![],
+                })
+            });
+        // Universal INFs validated for the desktop target platform are also
+        // checked against the rules of universal INFs
+        if inf_format == InfFormat::Universal && target_platform == TargetPlatform::Desktop {
+            command_exec
+                .expect_run()
+                .withf(|cmd: &str, args: &[&str], _, _| {
+                    cmd == "infverif" && args.len() == 3 && args[1] == "/u"
+                })
+                .once()
+                .returning(|_, _, _, _| {
+                    Ok(Output {
+                        status: ExitStatus::default(),
+                        stdout: vec![],
+                        stderr: vec![],
+                    })
+                });
+        }
+
+        let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+        assert!(task.run_infverif().is_ok());
+    }
+
+    #[test]
+    fn run_infverif_defaults_to_universal_for_all_driver_models() {
+        assert_infverif_mode_flag(
+            DriverConfig::Kmdf(KmdfConfig::default()),
+            TargetPlatform::Universal,
+            "/u",
+        );
+        assert_infverif_mode_flag(DriverConfig::Wdm, TargetPlatform::Universal, "/u");
+        assert_infverif_mode_flag(
+            DriverConfig::Umdf(UmdfConfig::default()),
+            TargetPlatform::Universal,
+            "/u",
+        );
+    }
+
+    #[test]
+    fn run_infverif_uses_target_platform_mode_flag() {
+        assert_infverif_mode_flag(
+            DriverConfig::Kmdf(KmdfConfig::default()),
+            TargetPlatform::Universal,
+            "/u",
+        );
+        assert_infverif_mode_flag(
+            DriverConfig::Kmdf(KmdfConfig::default()),
+            TargetPlatform::Desktop,
+            "/h",
+        );
+        assert_infverif_mode_flag(
+            DriverConfig::Kmdf(KmdfConfig::default()),
+            TargetPlatform::WindowsDriver,
+            "/w",
+        );
+    }
+
+    #[test]
+    fn run_infverif_reports_the_violated_rules_of_universal_infs() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let arch = CpuArchitecture::Amd64;
+
+        for (inf_format, stdout, expect_violations) in [
+            (
+                InfFormat::Universal,
+                "INFVERIF\r\nERROR(1324) in driver.inf, line 12: Found legacy Copyfiles \
+                 operation\r\nERROR(1205) in driver.inf, line 20: Section [Foo] referenced \
+                 twice\r\n",
+                true,
+            ),
+            (InfFormat::Universal, "Could not open driver.inf\r\n", false),
+            (
+                InfFormat::Legacy,
+                "ERROR(1324) in driver.inf, line 12: Found legacy Copyfiles operation\r\n",
+                false,
+            ),
+        ] {
+            let test_params = TestParams::default();
+            let params = PackageTaskParams {
+                inf_format,
+                ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+            };
+
+            let fs = Fs::default();
+            let wdk_build = WdkBuild::default();
+            let mut command_exec = CommandExec::default();
+            command_exec
+                .expect_run()
+                .withf(|cmd: &str, _, _, _| cmd == "infverif")
+                .once()
+                .returning(move |cmd, args, _, _| {
+                    Err(CommandError::from_output(
+                        cmd,
+                        args,
+                        &Output {
+                            status: ExitStatus::from_raw(1),
+                            stdout: stdout.as_bytes().to_vec(),
+                            stderr: vec![],
+                        },
+                    ))
+                });
+
+            let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+            let result = task.run_infverif();
+            if expect_violations {
+                assert!(matches!(
+                    result,
+                    Err(PackageTaskError::UniversalInfViolations(ref inf, ref violations))
+                        if inf.ends_with("driver_package/driver.inf")
+                            && violations.lines().count() == 2
+                            && violations.starts_with("ERROR(1324)")
+                ));
+            } else {
+                assert!(matches!(
+                    result,
+                    Err(PackageTaskError::InfVerificationCommand(_))
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn run_infverif_validates_legacy_infs_for_the_desktop_target_platform() {
+        for (target_platform, expected_mode_flag) in [
+            (TargetPlatform::Universal, "/h"),
+            (TargetPlatform::Desktop, "/h"),
+        ] {
+            assert_infverif_mode_flag_for_inf_format(
+                DriverConfig::Kmdf(KmdfConfig::default()),
+                target_platform,
+                InfFormat::Legacy,
+                expected_mode_flag,
+            );
+        }
+    }
+
+    #[test]
+    fn new_sets_per_arch_paths_for_multi_arch_package() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let multi_arch_target_dirs = [
+            (
+                CpuArchitecture::Amd64,
+                PathBuf::from("C:/abs/driver/target/x86_64-pc-windows-msvc/debug"),
+            ),
+            (
+                CpuArchitecture::Arm64,
+                PathBuf::from("C:/abs/driver/target/aarch64-pc-windows-msvc/debug"),
+            ),
+        ];
+
+        let test_params = TestParams::default();
+        let params = PackageTaskParams {
+            multi_arch_target_dirs: &multi_arch_target_dirs,
+            ..test_params.get(package_name, &working_dir, &target_dir, &CpuArchitecture::Amd64)
+        };
+
+        let command_exec = CommandExec::default();
+        let wdk_build = WdkBuild::default();
+        let fs = Fs::default();
+        let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+
+        let dest_root = target_dir.join("driver_package");
+        assert_eq!(task.dest_root_package_folder, dest_root);
+        assert_eq!(task.dest_inf_file_path, dest_root.join("driver.inf"));
+        assert_eq!(task.dest_cat_file_path, dest_root.join("driver.cat"));
+        assert_eq!(task.os_mapping, "10_x64,Server10_arm64");
+        assert_eq!(task.multi_arch_binaries.len(), 2);
+        for (binary, (arch, arch_target_dir)) in
+            task.multi_arch_binaries.iter().zip(&multi_arch_target_dirs)
+        {
+            assert_eq!(binary.arch, *arch);
+            assert_eq!(
+                binary.src_driver_binary_file_path,
+                arch_target_dir.join("driver.dll")
+            );
+            assert_eq!(
+                binary.dest_driver_binary_path,
+                dest_root.join(arch.to_string()).join("driver.sys")
+            );
+        }
+    }
+
+    #[test]
+    fn check_inx_arch_decorations_fails_when_inx_is_not_decorated_for_every_arch() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let multi_arch_target_dirs = [
+            (
+                CpuArchitecture::Amd64,
+                PathBuf::from("C:/abs/driver/target/x86_64-pc-windows-msvc/debug"),
+            ),
+            (
+                CpuArchitecture::Arm64,
+                PathBuf::from("C:/abs/driver/target/aarch64-pc-windows-msvc/debug"),
+            ),
+        ];
+
+        let test_params = TestParams::default();
+        let params = PackageTaskParams {
+            multi_arch_target_dirs: &multi_arch_target_dirs,
+            ..test_params.get(package_name, &working_dir, &target_dir, &CpuArchitecture::Amd64)
+        };
+
+        let command_exec = CommandExec::default();
+        let wdk_build = WdkBuild::default();
+        let mut fs = Fs::default();
+        fs.expect_read_file_to_string()
+            .withf(|path: &Path| path.ends_with("driver.inx"))
+            .once()
+            .returning(|_| {
+                Ok(r"[Manufacturer]
+%StdMfg%=Standard,NTamd64
+
+[SourceDisksFiles.amd64]
+driver.sys = 1,amd64
+"
+                .to_string())
+            });
+        let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+
+        let result = task
+            .read_inx_file()
+            .and_then(|inx_file| task.check_inx_arch_decorations(&inx_file));
+        assert!(matches!(
+            result,
+            Err(PackageTaskError::InfNotArchDecorated(_, ref missing))
+                if missing == "NTarm64, [SourceDisksFiles.arm64]"
+        ));
+    }
+
+    #[test]
+    fn check_inx_catalog_os_decoration_fails_when_inx_has_no_section_for_target_arch() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let arch = CpuArchitecture::Arm64;
+
+        for (inx_content, expected_to_fail) in [
+            (
+                "[Manufacturer]\r\n%StdMfg%=Standard,NT$ARCH$.10.0...16299\r\n",
+                false,
+            ),
+            (
+                "[Manufacturer]\r\n%StdMfg%=Standard,NTamd64,ntarm64.10.0\r\n",
+                false,
+            ),
+            (
+                "[Manufacturer]\r\n%StdMfg%=Standard,NTamd64.10.0...16299\r\n",
+                true,
+            ),
+            ("[Manufacturer]\r\n%StdMfg%=Standard\r\n", true),
+        ] {
+            let test_params = TestParams::default();
+            let params = test_params.get(package_name, &working_dir, &target_dir, &arch);
+
+            let command_exec = CommandExec::default();
+            let wdk_build = WdkBuild::default();
+            let mut fs = Fs::default();
+            fs.expect_read_file_to_string()
+                .withf(|path: &Path| path.ends_with("driver.inx"))
+                .once()
+                .returning(move |_| Ok(inx_content.to_string()));
+            let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+
+            let result = task
+                .read_inx_file()
+                .and_then(|inx_file| task.check_inx_catalog_os_decoration(&inx_file));
+            if expected_to_fail {
+                assert!(
+                    matches!(
+                        result,
+                        Err(PackageTaskError::InfNotDecoratedForCatalogOs {
+                            arch: CpuArchitecture::Arm64,
+                            ref os,
+                            ..
+                        }) if os == "Server10_arm64"
+                    ),
+                    "{inx_content}"
+                );
+            } else {
+                assert!(result.is_ok(), "{inx_content}");
+            }
+        }
+    }
+
+    #[test]
+    fn inx_without_driver_ver_is_warned_about_and_not_stamped_when_skipping_stampinf() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let arch = CpuArchitecture::Amd64;
+
+        for (inx_content, expected_warnings) in [
+            (
+                "[Version]\r\nDriverVer = 01/02/2024,1.2.3.0\r\nCatalogFile = driver.cat\r\n",
+                0,
+            ),
+            ("[Version]\r\nCatalogFile = driver.cat\r\n", 1),
+        ] {
+            let warnings = BuildWarnings::default();
+            let test_params = TestParams::default();
+            let params = PackageTaskParams {
+                warnings: &warnings,
+                skip_stampinf: true,
+                ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+            };
+
+            // stampinf is not run, so no command is expected
+            let command_exec = CommandExec::default();
+            let wdk_build = WdkBuild::default();
+            let mut fs = Fs::default();
+            fs.expect_read_file_to_string()
+                .withf(|path: &Path| path.ends_with("driver.inx"))
+                .once()
+                .returning(move |_| Ok(inx_content.to_string()));
+            let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+
+            let inx_file = task.read_inx_file().expect("inx file should be read");
+            task.check_inx_driver_ver(&inx_file);
+            assert_eq!(warnings.len(), expected_warnings, "{inx_content}");
+            assert!(task.run_stampinf().is_ok());
+        }
+    }
+
+    #[test]
+    fn run_stamp_only_stamps_the_inf_without_packaging_the_driver() {
+        const STAMPED_INF: &str =
+            "[Version]\r\nDriverVer = 10/16/2026,1.2.3.4\r\nCatalogFile = driver.cat\r\n";
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let arch = CpuArchitecture::Amd64;
+        let test_params = TestParams::default();
+        let params = PackageTaskParams {
+            sign_mode: SignMode::Test {
+                verify_signature: true,
+            },
+            incremented_driver_ver: Some("1.2.3.4"),
+            ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+        };
+
+        let mut fs = Fs::default();
+        fs.expect_exists()
+            .returning(|path: &Path| path.ends_with("driver.inx"));
+        fs.expect_create_dir()
+            .withf(|path: &Path| path.ends_with("driver_package"))
+            .once()
+            .returning(|_| Ok(()));
+        fs.expect_copy()
+            .withf(|src: &Path, dest: &Path| {
+                src.ends_with("driver/driver.inx") && dest.ends_with("driver_package/driver.inf")
+            })
+            .once()
+            .returning(|_, _| Ok(0));
+        fs.expect_read_file_to_string()
+            .withf(|path: &Path| path.ends_with("driver_package/driver.inf"))
+            .once()
+            .returning(|_| Ok(STAMPED_INF.to_string()));
+        let wdk_build = WdkBuild::default();
+        // Only stampinf is run, the driver is neither cataloged nor signed
+        let mut command_exec = CommandExec::default();
+        command_exec
+            .expect_run()
+            .withf(|cmd: &str, args: &[&str], _, _| {
+                cmd == "stampinf" && args.windows(2).any(|w| w == ["-c", "driver.cat"])
+            })
+            .once()
+            .returning(|_, _, _, _| {
+                Ok(Output {
+                    status: ExitStatus::default(),
+                    stdout: vec![],
+                    stderr: vec![],
+                })
+            });
+
+        let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+        assert!(task.run_stamp_only().is_ok());
+    }
+
+    #[test]
+    fn signing_pfx_is_signed_with_instead_of_the_test_certificate() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let arch = CpuArchitecture::Amd64;
+        let tool_log = ToolLog::new(None, true);
+        let signing_pfx = SigningPfx::write(
+            &{
+                let mut fs = Fs::default();
+                fs.expect_write_to_file().returning(|_, _| Ok(()));
+                fs
+            },
+            PathBuf::from("C:/temp/cargo-wdk-1.pfx"),
+            b"pfx",
+            Some("secret".to_string()),
+        )
+        .expect("PFX should be written");
+        let test_params = TestParams::default();
+        let params = PackageTaskParams {
+            sign_mode: SignMode::Test {
+                verify_signature: false,
+            },
+            tool_log: &tool_log,
+            signing_pfx: Some(&signing_pfx),
+            binary_only: true,
+            ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+        };
+
+        let mut fs = Fs::default();
+        fs.expect_read_file()
+            .withf(|path: &Path| path.ends_with("debug/driver.dll"))
+            .once()
+            .returning(|_| {
+                let mut image = vec![0u8; 0x40];
+                image[..2].copy_from_slice(b"MZ");
+                image[0x3C..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+                image.extend_from_slice(b"PE\0\0");
+                image.extend_from_slice(&0x8664u16.to_le_bytes());
+                Ok(image)
+            });
+        fs.expect_exists().returning(|_| false);
+        fs.expect_create_dir()
+            .withf(|path: &Path| path.ends_with("driver_package"))
+            .once()
+            .returning(|_| Ok(()));
+        fs.expect_rename()
+            .withf(|src: &Path, dest: &Path| {
+                src.ends_with("debug/driver.dll") && dest.ends_with("debug/driver.sys")
+            })
+            .once()
+            .returning(|_, _| Ok(()));
+        // The test certificate is not copied into the package
+        for file_name in ["driver.sys", "driver.pdb"] {
+            fs.expect_copy()
+                .withf(move |src: &Path, dest: &Path| {
+                    src.ends_with(Path::new("debug").join(file_name))
+                        && dest.ends_with(Path::new("driver_package").join(file_name))
+                })
+                .once()
+                .returning(|_, _| Ok(0));
+        }
+        let wdk_build = WdkBuild::default();
+        // No test certificate is looked up or generated
+        let mut command_exec = CommandExec::default();
+        command_exec
+            .expect_run()
+            .withf(|cmd: &str, args: &[&str], _, _| {
+                cmd == "signtool"
+                    && args[..6] == ["sign", "/v", "/f", "C:/temp/cargo-wdk-1.pfx", "/p", "secret"]
+                    && args.last().is_some_and(|arg| arg.ends_with("driver.sys"))
+            })
+            .once()
+            .returning(|_, _, _, _| {
+                Ok(Output {
+                    status: ExitStatus::default(),
+                    stdout: vec![],
+                    stderr: vec![],
+                })
+            });
+
+        let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+        assert!(task.run().is_ok());
+        let dest_root = target_dir.join("driver_package");
+        assert_eq!(
+            task.dest_file_paths(),
+            [dest_root.join("driver.sys"), dest_root.join("driver.pdb")]
+        );
+        // The password is redacted from the recorded command line
+        let logs = tool_log.logs();
+        assert_eq!(logs.len(), 1);
+        assert!(logs[0].1.contains("/p <redacted>"));
+        assert!(!logs[0].1.contains("secret"));
+    }
+
+    #[test]
+    fn run_binary_only_packages_the_signed_binary_without_inf_or_catalog() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let arch = CpuArchitecture::Amd64;
+        let test_params = TestParams::default();
+        let params = PackageTaskParams {
+            sign_mode: SignMode::Test {
+                verify_signature: true,
+            },
+            binary_only: true,
+            ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+        };
+
+        let mut fs = Fs::default();
+        fs.expect_read_file()
+            .withf(|path: &Path| path.ends_with("debug/driver.dll"))
+            .once()
+            .returning(|_| {
+                let mut image = vec![0u8; 0x40];
+                image[..2].copy_from_slice(b"MZ");
+                image[0x3C..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+                image.extend_from_slice(b"PE\0\0");
+                image.extend_from_slice(&0x8664u16.to_le_bytes());
+                Ok(image)
+            });
+        // The test certificate was generated by a previous build
+        fs.expect_exists()
+            .returning(|path: &Path| path.ends_with("debug/WDRLocalTestCert.cer"));
+        fs.expect_create_dir()
+            .withf(|path: &Path| path.ends_with("driver_package"))
+            .once()
+            .returning(|_| Ok(()));
+        fs.expect_rename()
+            .withf(|src: &Path, dest: &Path| {
+                src.ends_with("debug/driver.dll") && dest.ends_with("debug/driver.sys")
+            })
+            .once()
+            .returning(|_, _| Ok(()));
+        for file_name in ["driver.sys", "driver.pdb", "WDRLocalTestCert.cer"] {
+            fs.expect_copy()
+                .withf(move |src: &Path, dest: &Path| {
+                    src.ends_with(Path::new("debug").join(file_name))
+                        && dest.ends_with(Path::new("driver_package").join(file_name))
+                })
+                .once()
+                .returning(|_, _| Ok(0));
+        }
+        let wdk_build = WdkBuild::default();
+        // Only the driver binary is signed and verified, no INF is stamped and
+        // no catalog is generated, signed or checked for the binary
+        let mut command_exec = CommandExec::default();
+        for subcommand in ["sign", "verify"] {
+            command_exec
+                .expect_run()
+                .withf(move |cmd: &str, args: &[&str], _, _| {
+                    cmd == "signtool"
+                        && args[0] == subcommand
+                        && !args.contains(&"/c")
+                        && args.last().is_some_and(|arg| arg.ends_with("driver.sys"))
+                })
+                .once()
+                .returning(|_, _, _, _| {
+                    Ok(Output {
+                        status: ExitStatus::default(),
+                        stdout: vec![],
+                        stderr: vec![],
+                    })
+                });
+        }
+        command_exec
+            .expect_run()
+            .withf(|cmd: &str, args: &[&str], _, _| {
+                cmd == "certutil"
+                    && args == ["-user", "-v", "-store", "WDRTestCertStore", "WDRLocalTestCert"]
+            })
+            .once()
+            .returning(|_, _, _, _| {
+                Ok(Output {
+                    status: ExitStatus::default(),
+                    stdout: b"    Enhanced Key Usage\r\n        Code Signing \
+                              (1.3.6.1.5.5.7.3.3)\r\n"
+                        .to_vec(),
+                    stderr: vec![],
+                })
+            });
+
+        let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+        assert!(task.run().is_ok());
+        let dest_root = target_dir.join("driver_package");
+        assert_eq!(
+            task.dest_file_paths(),
+            [
+                dest_root.join("driver.sys"),
+                dest_root.join("driver.pdb"),
+                dest_root.join("WDRLocalTestCert.cer"),
+            ]
+        );
+    }
+
+    #[test]
+    fn sign_and_verify_signs_every_binary_of_a_multi_arch_package() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let multi_arch_target_dirs = [
+            (
+                CpuArchitecture::Amd64,
+                PathBuf::from("C:/abs/driver/target/x86_64-pc-windows-msvc/debug"),
+            ),
+            (
+                CpuArchitecture::Arm64,
+                PathBuf::from("C:/abs/driver/target/aarch64-pc-windows-msvc/debug"),
+            ),
+        ];
+        let test_params = TestParams::default();
+        let params = PackageTaskParams {
+            sign_mode: SignMode::Test {
+                verify_signature: true,
+            },
+            multi_arch_target_dirs: &multi_arch_target_dirs,
+            ..test_params.get(package_name, &working_dir, &target_dir, &CpuArchitecture::Amd64)
+        };
+
+        let mut fs = Fs::default();
+        // The test certificate was generated by a previous build
+        fs.expect_exists()
+            .returning(|path: &Path| path.ends_with("debug/WDRLocalTestCert.cer"));
+        fs.expect_copy()
+            .withf(|src: &Path, dest: &Path| {
+                src.ends_with("debug/WDRLocalTestCert.cer")
+                    && dest.ends_with("driver_package/WDRLocalTestCert.cer")
+            })
+            .once()
+            .returning(|_, _| Ok(0));
+        let wdk_build = WdkBuild::default();
+        // The binary of each architecture is signed, verified and checked
+        // against the single catalog of the package
+        let mut command_exec = CommandExec::default();
+        let signed_files = [
+            "amd64/driver.sys",
+            "arm64/driver.sys",
+            "driver_package/driver.cat",
+        ];
+        for subcommand in ["sign", "verify"] {
+            for signed_file in signed_files {
+                command_exec
+                    .expect_run()
+                    .withf(move |cmd: &str, args: &[&str], _, _| {
+                        cmd == "signtool"
+                            && args[0] == subcommand
+                            && !args.contains(&"/c")
+                            && args
+                                .last()
+                                .is_some_and(|arg| Path::new(arg).ends_with(signed_file))
+                    })
+                    .once()
+                    .returning(|_, _, _, _| {
+                        Ok(Output {
+                            status: ExitStatus::default(),
+                            stdout: vec![],
+                            stderr: vec![],
+                        })
+                    });
+            }
+        }
+        for arch_folder in ["amd64", "arm64"] {
+            command_exec
+                .expect_run()
+                .withf(move |cmd: &str, args: &[&str], _, _| {
+                    cmd == "signtool"
+                        && args.contains(&"/c")
+                        && args.last().is_some_and(|arg| {
+                            Path::new(arg).ends_with(Path::new(arch_folder).join("driver.sys"))
+                        })
+                })
+                .once()
+                .returning(|_, _, _, _| {
+                    Ok(Output {
+                        status: ExitStatus::default(),
+                        stdout: vec![],
+                        stderr: vec![],
+                    })
+                });
+        }
+        command_exec
+            .expect_run()
+            .withf(|cmd: &str, args: &[&str], _, _| {
+                cmd == "certutil"
+                    && args == ["-user", "-v", "-store", "WDRTestCertStore", "WDRLocalTestCert"]
+            })
+            .once()
+            .returning(|_, _, _, _| {
+                Ok(Output {
+                    status: ExitStatus::default(),
+                    stdout: b"    Enhanced Key Usage\r\n        Code Signing \
+                              (1.3.6.1.5.5.7.3.3)\r\n"
+                        .to_vec(),
+                    stderr: vec![],
+                })
+            });
+
+        let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+        assert!(task.sign_and_verify().is_ok());
+    }
+
+    #[test]
+    fn check_inx_setup_class_rejects_samples_without_the_sample_class() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let arch = CpuArchitecture::Amd64;
+        let sample_inx =
+            "[Version]\r\nClass=Sample\r\nClassGuid={78A1C341-4539-11d3-B88D-00C04FAD5171}\r\n";
+        let system_inx =
+            "[Version]\r\nClass=System\r\nClassGuid={4d36e97d-e325-11ce-bfc1-08002be10318}\r\n";
+
+        for (sample_class, inx_content, expected_ok, expected_warnings) in [
+            (true, sample_inx, true, 0),
+            (true, system_inx, false, 0),
+            (
+                true,
+                "[Version]\r\nSignature=\"$WINDOWS NT$\"\r\n",
+                false,
+                0,
+            ),
+            (false, system_inx, true, 0),
+            (false, sample_inx, true, 1),
+        ] {
+            let warnings = BuildWarnings::default();
+            let test_params = TestParams::default();
+            let params = PackageTaskParams {
+                sample_class,
+                warnings: &warnings,
+                ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+            };
+
+            let command_exec = CommandExec::default();
+            let wdk_build = WdkBuild::default();
+            let mut fs = Fs::default();
+            fs.expect_read_file_to_string()
+                .withf(|path: &Path| path.ends_with("driver.inx"))
+                .once()
+                .returning(move |_| Ok(inx_content.to_string()));
+            let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+
+            let inx_file = task.read_inx_file().expect("inx file should be read");
+            let result = task.check_inx_setup_class(&inx_file);
+            if expected_ok {
+                assert!(result.is_ok(), "{inx_content}");
+            } else {
+                assert!(
+                    matches!(result, Err(PackageTaskError::InfNotSampleClass(..))),
+                    "{inx_content}"
+                );
+            }
+            assert_eq!(warnings.len(), expected_warnings, "{inx_content}");
+        }
+    }
+
+    #[test]
+    fn inf_referenced_ini_files_are_collected_or_reported_missing() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let arch = CpuArchitecture::Amd64;
+        let inx_content = concat!(
+            "[SourceDisksFiles]\r\ndriver.sys = 1,,\r\nstrings.ini = 1,,\r\n",
+            "[SourceDisksFiles.amd64]\r\nstrings.ini = 1,lang\\de-DE\r\n",
+        );
+
+        for de_strings_exist in [false, true] {
+            let test_params = TestParams::default();
+            let params = test_params.get(package_name, &working_dir, &target_dir, &arch);
+
+            let command_exec = CommandExec::default();
+            let wdk_build = WdkBuild::default();
+            let mut fs = Fs::default();
+            fs.expect_read_file_to_string()
+                .withf(|path: &Path| path.ends_with("driver.inx"))
+                .once()
+                .returning(move |_| Ok(inx_content.to_string()));
+            let expected_strings_path = working_dir.join("strings.ini");
+            let expected_de_strings_path = working_dir.join(r"lang\de-DE\strings.ini");
+            fs.expect_exists()
+                .withf(move |path: &Path| path == expected_strings_path)
+                .once()
+                .return_const(true);
+            fs.expect_exists()
+                .withf(move |path: &Path| path == expected_de_strings_path)
+                .once()
+                .return_const(de_strings_exist);
+            let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+
+            let inx_file = task.read_inx_file().expect("inx file should be read");
+            let result = task.collect_inf_referenced_files(&inx_file);
+            let package_folder = target_dir.join("driver_package");
+            if de_strings_exist {
+                assert!(result.is_ok());
+                let dest_file_paths = task.dest_file_paths();
+                assert!(dest_file_paths.contains(&package_folder.join("strings.ini")));
+                assert!(dest_file_paths.contains(&package_folder.join(r"lang\de-DE\strings.ini")));
+            } else {
+                assert!(matches!(
+                    result,
+                    Err(PackageTaskError::MissingInfReferencedFiles(_, missing_files))
+                        if missing_files == r"lang\de-DE\strings.ini"
+                ));
+                assert!(
+                    !task
+                        .dest_file_paths()
+                        .contains(&package_folder.join("strings.ini"))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn inf_referenced_files_are_copied_into_nested_package_folders() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let arch = CpuArchitecture::Amd64;
+
+        let test_params = TestParams::default();
+        let params = test_params.get(package_name, &working_dir, &target_dir, &arch);
+
+        let command_exec = CommandExec::default();
+        let wdk_build = WdkBuild::default();
+        let mut fs = Fs::default();
+        fs.expect_create_dir_all()
+            .withf(|path: &Path| path.ends_with("driver_package/loc/en-US"))
+            .once()
+            .returning(|_| Ok(()));
+        fs.expect_copy()
+            .withf(|src: &Path, dest: &Path| {
+                src.ends_with("driver/loc/en-US/strings.ini")
+                    && dest.ends_with("driver_package/loc/en-US/strings.ini")
+            })
+            .once()
+            .returning(|_, _| Ok(0));
+        let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+        *task.inf_referenced_files.borrow_mut() = vec![PathBuf::from(r"loc\en-US\strings.ini")];
+
+        assert!(task.copy_inf_referenced_files().is_ok());
+    }
+
+    #[test]
+    fn check_inx_format_rejects_co_installers_in_universal_infs() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let arch = CpuArchitecture::Amd64;
+        let co_installer_inx = concat!(
+            "[Device_Install.NT.CoInstallers]\r\nAddReg=CoInstaller_AddReg\r\n",
+            "[CoInstaller_AddReg]\r\n",
+            "HKR,,CoInstallers32,0x00010000,\"WdfCoInstaller01011.dll\"\r\n",
+        );
+
+        for (inf_format, target_platform, expected_result) in [
+            (
+                InfFormat::Universal,
+                TargetPlatform::Universal,
+                Some("universal"),
+            ),
+            (InfFormat::Legacy, TargetPlatform::Universal, None),
+            (InfFormat::Legacy, TargetPlatform::Desktop, None),
+            (
+                InfFormat::Legacy,
+                TargetPlatform::WindowsDriver,
+                Some("legacy"),
+            ),
+        ] {
+            let test_params = TestParams::default();
+            let params = PackageTaskParams {
+                target_platform,
+                inf_format,
+                ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+            };
+
+            let command_exec = CommandExec::default();
+            let wdk_build = WdkBuild::default();
+            let fs = Fs::default();
+            let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+
+            let result = task.check_inx_format(&InfFile::parse(co_installer_inx));
+            match expected_result {
+                None => assert!(result.is_ok(), "{inf_format:?} {target_platform:?}"),
+                Some("universal") => assert!(matches!(
+                    result,
+                    Err(PackageTaskError::InfNotUniversal(_, ref directives))
+                        if directives
+                            == "[Device_Install.NT.CoInstallers], CoInstallers32 in \
+                                [CoInstaller_AddReg]"
+                )),
+                Some(_) => assert!(matches!(
+                    result,
+                    Err(PackageTaskError::LegacyInfForWindowsDriver(_))
+                )),
+            }
+        }
+    }
+
+    #[test]
+    fn check_inx_provider_rejects_providers_that_are_not_allowed() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let arch = CpuArchitecture::Amd64;
+        let contoso_inx = concat!(
+            "[Version]\r\nProvider = %ProviderName%\r\n",
+            "[Strings]\r\nProviderName = \"Contoso\"\r\n",
+        );
+        let fabrikam = vec!["Fabrikam".to_string()];
+
+        for (inx, allowed_providers, expected_provider) in [
+            (contoso_inx, vec![], None),
+            (
+                contoso_inx,
+                vec!["Fabrikam".to_string(), "Contoso".to_string()],
+                None,
+            ),
+            (contoso_inx, fabrikam.clone(), Some("Contoso")),
+            (contoso_inx, vec!["contoso".to_string()], Some("Contoso")),
+            ("[Version]\r\nClass = System\r\n", fabrikam, Some("<none>")),
+        ] {
+            let test_params = TestParams::default();
+            let params = PackageTaskParams {
+                allowed_providers: &allowed_providers,
+                ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+            };
+
+            let command_exec = CommandExec::default();
+            let wdk_build = WdkBuild::default();
+            let fs = Fs::default();
+            let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+
+            let result = task.check_inx_provider(&InfFile::parse(inx));
+            match expected_provider {
+                None => assert!(result.is_ok(), "{allowed_providers:?}"),
+                Some(expected_provider) => assert!(
+                    matches!(
+                        result,
+                        Err(PackageTaskError::InfProviderNotAllowed { ref provider, .. })
+                            if provider == expected_provider
+                    ),
+                    "{allowed_providers:?}"
+                ),
+            }
+        }
+    }
+
+    fn run_import_check_with_dumpbin_output(
+        driver_model: DriverConfig,
+        target_platform: TargetPlatform,
+        wdk_build_number: u32,
+        inf_content: &'static str,
+        dumpbin_stdout: &'static str,
+    ) -> Result<(), PackageTaskError> {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
         let arch = CpuArchitecture::Amd64;
 
-        let package_task_params = PackageTaskParams {
-            package_name,
-            working_dir: &working_dir,
-            target_dir: &target_dir,
-            target_arch: &arch,
-            driver_model: DriverConfig::Kmdf(KmdfConfig::default()),
-            sample_class: false,
-            sign_mode: SignMode::Test {
-                verify_signature: false,
-            },
-            target_platform: TargetPlatform::Universal,
+        let test_params = TestParams::default();
+        let params = PackageTaskParams {
+            driver_model,
+            target_platform,
+            import_check: true,
+            ..test_params.get(package_name, &working_dir, &target_dir, &arch)
         };
-        let dest_root = target_dir.join(format!("{package_name}_package"));
 
-        let command_exec = CommandExec::default();
-        let wdk_build = WdkBuild::default();
-        let fs = Fs::default();
-        let task = PackageTask::new(package_task_params, &wdk_build, &command_exec, &fs);
-        assert_eq!(task.package_name, package_name.replace('-', "_"));
-        assert_eq!(
-            task.sign_mode,
-            SignMode::Test {
-                verify_signature: false,
-            }
-        );
-        assert!(!task.sample_class);
-        assert_eq!(task.src_inx_file_path, working_dir.join("test_package.inx"));
-        assert_eq!(
-            task.src_driver_binary_file_path,
-            target_dir.join("test_package.dll")
+        let mut fs = Fs::default();
+        fs.expect_read_file_to_string()
+            .withf(|path: &Path| path.ends_with("driver_package/driver.inf"))
+            .once()
+            .returning(move |_| Ok(inf_content.to_string()));
+        let mut wdk_build = WdkBuild::default();
+        wdk_build
+            .expect_detect_wdk_build_number()
+            .once()
+            .returning(move || Ok(wdk_build_number));
+
+        let mut command_exec = CommandExec::default();
+        command_exec
+            .expect_run()
+            .withf(|cmd: &str, args: &[&str], _, _| {
+                cmd == "dumpbin"
+                    && args.len() == 3
+                    && args[0] == "/nologo"
+                    && args[1] == "/imports"
+                    && (args[2].ends_with("driver.sys") || args[2].ends_with("driver.dll"))
+            })
+            .once()
+            .returning(move |_, _, _, _| {
+                Ok(Output {
+                    status: ExitStatus::default(),
+                    stdout: dumpbin_stdout.as_bytes().to_vec(),
+                    stderr: vec![],
+                })
+            });
+
+        let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+        task.run_import_check(&task.dest_driver_binary_path)
+    }
+
+    const DUMPBIN_IMPORTS_OUTPUT: &str = "
+    ntoskrnl.exe
+             140003000 Import Address Table
+                         5F8 ExAllocatePool2
+                         2B1 DbgPrint
+";
+
+    #[test]
+    fn run_import_check_succeeds_when_imports_are_available_on_the_targeted_build() {
+        assert!(
+            run_import_check_with_dumpbin_output(
+                DriverConfig::Kmdf(KmdfConfig::default()),
+                TargetPlatform::Universal,
+                26100,
+                "[Manufacturer]\r\n%StdMfg%=Standard,NT$ARCH$.10.0...22000\r\n",
+                DUMPBIN_IMPORTS_OUTPUT
+            )
+            .is_ok()
         );
-        assert_eq!(
-            task.src_renamed_driver_binary_file_path,
-            target_dir.join("test_package.sys")
+    }
+
+    #[test]
+    fn run_import_check_fails_when_a_newer_wdk_builds_a_driver_for_an_older_os() {
+        let kmdf_config = KmdfConfig {
+            minimum_kmdf_version_minor: Some(27),
+            ..KmdfConfig::default()
+        };
+        let result = run_import_check_with_dumpbin_output(
+            DriverConfig::Kmdf(kmdf_config),
+            TargetPlatform::Universal,
+            26100,
+            "[Manufacturer]\r\n%StdMfg%=Standard,NT$ARCH$.10.0...17763\r\n",
+            DUMPBIN_IMPORTS_OUTPUT,
         );
-        assert_eq!(task.src_pdb_file_path, target_dir.join("test_package.pdb"));
-        assert_eq!(
-            task.src_map_file_path,
-            target_dir.join("deps").join("test_package.map")
+        assert!(matches!(
+            result,
+            Err(PackageTaskError::DisallowedImports {
+                minimum_build_number: 17763,
+                wdk_build_number: 26100,
+                ref imports,
+            }) if imports == "ntoskrnl.exe!ExAllocatePool2 (requires build 19041)"
+        ));
+
+        // Without OS version decorations the minimum KMDF version decides
+        let result = run_import_check_with_dumpbin_output(
+            DriverConfig::Kmdf(kmdf_config),
+            TargetPlatform::Universal,
+            26100,
+            "[Manufacturer]\r\n%StdMfg%=Standard,NT$ARCH$\r\n",
+            DUMPBIN_IMPORTS_OUTPUT,
         );
-        assert_eq!(
-            task.src_cert_file_path,
-            target_dir.join("WDRLocalTestCert.cer")
+        assert!(matches!(
+            result,
+            Err(PackageTaskError::DisallowedImports {
+                minimum_build_number: 17763,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn run_import_check_fails_when_universal_user_mode_driver_imports_desktop_only_functions() {
+        const UMDF_DUMPBIN_IMPORTS_OUTPUT: &str = "
+    api-ms-win-core-synch-l1-2-0.dll
+             180003000 Import Address Table
+                          2F WaitForSingleObject
+    USER32.dll
+             180003030 Import Address Table
+                         27E MessageBoxW
+";
+        const UMDF_INF_CONTENT: &str = "[Manufacturer]\r\n%StdMfg%=Standard,NT$ARCH$\r\n";
+        let result = run_import_check_with_dumpbin_output(
+            DriverConfig::Umdf(UmdfConfig::default()),
+            TargetPlatform::Universal,
+            26100,
+            UMDF_INF_CONTENT,
+            UMDF_DUMPBIN_IMPORTS_OUTPUT,
         );
-        assert_eq!(task.dest_root_package_folder, dest_root);
-        assert_eq!(task.dest_inf_file_path, dest_root.join("test_package.inf"));
-        assert_eq!(
-            task.dest_driver_binary_path,
-            dest_root.join("test_package.sys")
+        assert!(matches!(
+            result,
+            Err(PackageTaskError::NonUniversalImports(ref imports))
+                if imports == "USER32.dll!MessageBoxW"
+        ));
+
+        assert!(
+            run_import_check_with_dumpbin_output(
+                DriverConfig::Umdf(UmdfConfig::default()),
+                TargetPlatform::Desktop,
+                26100,
+                UMDF_INF_CONTENT,
+                UMDF_DUMPBIN_IMPORTS_OUTPUT,
+            )
+            .is_ok()
         );
-        assert_eq!(task.dest_pdb_file_path, dest_root.join("test_package.pdb"));
-        assert_eq!(task.dest_map_file_path, dest_root.join("test_package.map"));
+    }
+
+    #[test]
+    fn check_driver_binary_fails_when_umdf_dll_does_not_export_its_entry_points() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let arch = CpuArchitecture::Amd64;
+
+        let cases: [(DriverConfig, &[&str], Option<&str>); 3] = [
+            (
+                DriverConfig::Umdf(UmdfConfig::default()),
+                &["DriverEntry", "FxDriverEntryUm"],
+                None,
+            ),
+            (
+                DriverConfig::Umdf(UmdfConfig::default()),
+                &["DriverEntry"],
+                Some("FxDriverEntryUm"),
+            ),
+            // Kernel mode drivers are not loaded by the UMDF host
+            (DriverConfig::Kmdf(KmdfConfig::default()), &[], None),
+        ];
+        for (driver_model, exports, expected_missing_exports) in cases {
+            let test_params = TestParams::default();
+            let params = PackageTaskParams {
+                driver_model,
+                ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+            };
+
+            let command_exec = CommandExec::default();
+            let wdk_build = WdkBuild::default();
+            let mut fs = Fs::default();
+            fs.expect_read_file()
+                .withf(|path: &Path| path.ends_with("debug/driver.dll"))
+                .once()
+                .returning(move |_| Ok(pe::test_image(0x8664, exports, None)));
+            let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+
+            let result = task.check_driver_binary(&task.src_driver_binary_file_path, arch);
+            match expected_missing_exports {
+                None => assert!(result.is_ok(), "{exports:?}: {result:?}"),
+                Some(expected) => assert!(
+                    matches!(
+                        result,
+                        Err(PackageTaskError::MissingUmdfExports(ref path, ref missing))
+                            if path.ends_with("debug/driver.dll") && missing == expected
+                    ),
+                    "{exports:?}: {result:?}"
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn archive_symbols_copies_pdb_to_the_folder_of_its_debug_id() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let symbols_archive_dir = PathBuf::from("C:/abs/symbols");
+        let arch = CpuArchitecture::Amd64;
+        let pdb_debug_id = pe::PdbDebugId {
+            guid: [
+                0x78, 0x56, 0x34, 0x12, 0xBC, 0x9A, 0xF0, 0xDE, 0x01, 0x23, 0x45, 0x67, 0x89, 0xAB,
+                0xCD, 0xEF,
+            ],
+            age: 1,
+            pdb_path: "driver.pdb".to_string(),
+        };
+
+        for pdb_debug_id in [Some(pdb_debug_id), None] {
+            let test_params = TestParams::default();
+            let params = PackageTaskParams {
+                symbols_archive_dir: Some(&symbols_archive_dir),
+                ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+            };
+
+            let command_exec = CommandExec::default();
+            let wdk_build = WdkBuild::default();
+            let mut fs = Fs::default();
+            let has_pdb_debug_id = pdb_debug_id.is_some();
+            fs.expect_read_file()
+                .withf(|path: &Path| path.ends_with("driver_package/driver.sys"))
+                .once()
+                .returning(move |_| Ok(pe::test_image(0x8664, &[], pdb_debug_id.as_ref())));
+            let expected_dest_folder =
+                symbols_archive_dir.join("driver.pdb/12345678DEF09ABC0123456789ABCDEF1");
+            let expected_dest_pdb_file_path = expected_dest_folder.join("driver.pdb");
+            fs.expect_create_dir_all()
+                .withf(move |path: &Path| path == expected_dest_folder)
+                .times(usize::from(has_pdb_debug_id))
+                .returning(|_| Ok(()));
+            fs.expect_copy()
+                .withf(move |src: &Path, dest: &Path| {
+                    src.ends_with("debug/driver.pdb") && dest == expected_dest_pdb_file_path
+                })
+                .times(usize::from(has_pdb_debug_id))
+                .returning(|_, _| Ok(0));
+            let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+
+            let result =
+                task.archive_symbols(&task.dest_driver_binary_path, &task.src_pdb_file_path);
+            if has_pdb_debug_id {
+                assert!(result.is_ok(), "{result:?}");
+            } else {
+                assert!(matches!(
+                    result,
+                    Err(PackageTaskError::MissingPdbDebugId(ref path))
+                        if path.ends_with("driver_package/driver.sys")
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn run_os_version_check_warns_when_inf_targets_other_os_version() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let arch = CpuArchitecture::Amd64;
+        let warnings = BuildWarnings::default();
+
+        let test_params = TestParams::default();
+        let params = PackageTaskParams {
+            os_version_check: true,
+            warnings: &warnings,
+            ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+        };
+
+        let mut fs = Fs::default();
+        fs.expect_read_file_to_string()
+            .withf(|path: &Path| path.ends_with("driver_package/driver.inf"))
+            .once()
+            .returning(|_| {
+                Ok("[Manufacturer]\r\n%StdMfg%=Standard,NT$ARCH$.10.0...16299\r\n".to_string())
+            });
+        let wdk_build = WdkBuild::default();
+        let mut command_exec = CommandExec::default();
+        command_exec
+            .expect_run()
+            .withf(|cmd: &str, args: &[&str], _, _| {
+                cmd == "dumpbin"
+                    && args.len() == 3
+                    && args[1] == "/headers"
+                    && args[2].ends_with("driver.sys")
+            })
+            .once()
+            .returning(|_, _, _, _| {
+                Ok(Output {
+                    status: ExitStatus::default(),
+                    stdout: b"           6.01 subsystem version\r\n".to_vec(),
+                    stderr: vec![],
+                })
+            });
+
+        let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+        let driver_binary_path = target_dir.join("driver_package/driver.sys");
+        assert!(task.run_os_version_check(&driver_binary_path).is_ok());
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn signtool_verify_catalog_member_fails_when_driver_binary_was_swapped_after_cataloging() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let arch = CpuArchitecture::Amd64;
+
+        let test_params = TestParams::default();
+        let params = PackageTaskParams {
+            sign_mode: SignMode::Test {
+                verify_signature: true,
+            },
+            ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+        };
+
+        // The driver binary in the package was replaced after inf2cat hashed
+        // it, so signtool does not find its hash in the catalog
+        let fs = Fs::default();
+        let wdk_build = WdkBuild::default();
+        let mut command_exec = CommandExec::default();
+        command_exec
+            .expect_run()
+            .withf(|cmd: &str, args: &[&str], _, _| {
+                cmd == "signtool"
+                    && args.len() == 6
+                    && args[3] == "/c"
+                    && args[4].ends_with("driver.cat")
+                    && args[5].ends_with("driver.sys")
+            })
+            .once()
+            .returning(|cmd, args, _, _| {
+                Err(CommandError::from_output(
+                    cmd,
+                    args,
+                    &Output {
+                        status: ExitStatus::from_raw(1),
+                        stdout: b"SignTool Error: The file is not signed by the catalog\r\n"
+                            .to_vec(),
+                        stderr: vec![],
+                    },
+                ))
+            });
+
+        let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+        let result = task.run_signtool_verify_catalog_member(&task.dest_driver_binary_path);
+        assert!(matches!(
+            result,
+            Err(PackageTaskError::StaleCatalog(ref binary, ref cat, _))
+                if binary.ends_with("driver_package/driver.sys")
+                    && cat.ends_with("driver_package/driver.cat")
+        ));
+    }
+
+    #[test]
+    fn signing_certificate_without_code_signing_eku_is_rejected() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let arch = CpuArchitecture::Amd64;
+
+        let test_params = TestParams::default();
+        let params = PackageTaskParams {
+            sign_mode: SignMode::Test {
+                verify_signature: true,
+            },
+            ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+        };
+
+        // The certificate in the store is valid for TLS servers only, which
+        // signtool verify accepts but driver installation does not
+        let fs = Fs::default();
+        let wdk_build = WdkBuild::default();
+        let mut command_exec = CommandExec::default();
+        command_exec
+            .expect_run()
+            .withf(|cmd: &str, args: &[&str], _, _| {
+                cmd == "certutil"
+                    && args == ["-user", "-v", "-store", "WDRTestCertStore", "WDRLocalTestCert"]
+            })
+            .once()
+            .returning(|_, _, _, _| {
+                Ok(Output {
+                    status: ExitStatus::from_raw(0),
+                    stdout: b"Certificate Extensions: 1\r\n    2.5.29.37: Flags = 0, Length = \
+                              c\r\n    Enhanced Key Usage\r\n        Server Authentication \
+                              (1.3.6.1.5.5.7.3.1)\r\n\r\n"
+                        .to_vec(),
+                    stderr: vec![],
+                })
+            });
+
+        let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+        let result = task.run_eku_check();
+        assert!(matches!(
+            result,
+            Err(PackageTaskError::MissingCodeSigningEku(ref cert, ref found))
+                if cert == "WDRLocalTestCert in the WDRTestCertStore store"
+                    && found == "1.3.6.1.5.5.7.3.1"
+        ));
+    }
+
+    #[test]
+    fn signing_pfx_without_code_signing_eku_is_rejected() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let arch = CpuArchitecture::Amd64;
+        let signing_pfx = SigningPfx::write(
+            &{
+                let mut fs = Fs::default();
+                fs.expect_write_to_file().returning(|_, _| Ok(()));
+                fs
+            },
+            PathBuf::from("C:/temp/cargo-wdk-1.pfx"),
+            b"pfx",
+            Some("secret".to_string()),
+        )
+        .expect("PFX should be written");
+
+        let test_params = TestParams::default();
+        let params = PackageTaskParams {
+            sign_mode: SignMode::Test {
+                verify_signature: true,
+            },
+            signing_pfx: Some(&signing_pfx),
+            ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+        };
+
+        // The PFX certificate is dumped rather than the test certificate
+        let fs = Fs::default();
+        let wdk_build = WdkBuild::default();
+        let mut command_exec = CommandExec::default();
+        command_exec
+            .expect_run()
+            .withf(|cmd: &str, args: &[&str], _, _| {
+                cmd == "certutil" && args == ["-dump", "/p", "secret", "C:/temp/cargo-wdk-1.pfx"]
+            })
+            .once()
+            .returning(|_, _, _, _| {
+                Ok(Output {
+                    status: ExitStatus::from_raw(0),
+                    stdout: b"Certificate Extensions: 1\r\n    2.5.29.37: Flags = 0, Length = \
+                              c\r\n    Enhanced Key Usage\r\n        Server Authentication \
+                              (1.3.6.1.5.5.7.3.1)\r\n\r\n"
+                        .to_vec(),
+                    stderr: vec![],
+                })
+            });
+
+        let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+        let result = task.run_eku_check();
+        assert!(matches!(
+            result,
+            Err(PackageTaskError::MissingCodeSigningEku(ref cert, ref found))
+                if cert == "C:/temp/cargo-wdk-1.pfx" && found == "1.3.6.1.5.5.7.3.1"
+        ));
+    }
+
+    #[test]
+    fn expired_test_certificate_is_regenerated_before_signing() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let arch = CpuArchitecture::Amd64;
+
+        let test_params = TestParams::default();
+        let params = PackageTaskParams {
+            sign_mode: SignMode::Test {
+                verify_signature: false,
+            },
+            // 2026-10-16 12:00:00 UTC
+            test_cert_validity: Some(TestCertValidity {
+                days: 30,
+                now: 1_792_152_000,
+            }),
+            ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+        };
+
+        let output = |stdout: &str| Output {
+            status: ExitStatus::from_raw(0),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: vec![],
+        };
+        // The test certificate of a previous build expired, so it is deleted
+        // from the store and regenerated with the configured validity
+        let mut fs = Fs::default();
+        fs.expect_exists()
+            .withf(|path: &Path| path.ends_with("debug/WDRLocalTestCert.cer"))
+            .once()
+            .returning(|_| true);
+        fs.expect_remove_file()
+            .withf(|path: &Path| path.ends_with("debug/WDRLocalTestCert.cer"))
+            .once()
+            .returning(|_| Ok(()));
+        let wdk_build = WdkBuild::default();
+        let mut command_exec = CommandExec::default();
+        let mut seq = Sequence::new();
+        for (expected_cmd, first_arg, expected_args, stdout) in [
+            ("certutil", "-dump", "", " NotAfter: 1/5/2026 3:04 PM\r\n"),
+            ("certmgr.exe", "-del", "", ""),
+            ("certmgr.exe", "-s", "", ""),
+            ("certmgr.exe", "-s", "", ""),
+            ("makecert", "-r", "-e 11/15/2026", ""),
+            ("certutil", "-dump", "", " NotAfter: 11/15/2026 3:04 PM\r\n"),
+        ] {
+            command_exec
+                .expect_run()
+                .withf(move |cmd: &str, args: &[&str], _, _| {
+                    cmd == expected_cmd
+                        && args[0] == first_arg
+                        && args.join(" ").contains(expected_args)
+                })
+                .once()
+                .in_sequence(&mut seq)
+                .returning(move |_, _, _, _| Ok(output(stdout)));
+        }
+
+        let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+        assert!(task.generate_certificate().is_ok());
+    }
+
+    #[test]
+    fn catalog_signing_report_lists_the_signer_of_the_catalog() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let arch = CpuArchitecture::Amd64;
+
+        let test_params = TestParams::default();
+        let params = PackageTaskParams {
+            sign_mode: SignMode::Test {
+                verify_signature: false,
+            },
+            emit_catalog_signing_report: true,
+            ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+        };
+
+        let written_report = Arc::new(Mutex::new(String::new()));
+        let mut fs = Fs::default();
+        let written = written_report.clone();
+        fs.expect_write_to_file()
+            .withf(|path: &Path, _| path.ends_with("driver_package/driver.cat-signing.md"))
+            .once()
+            .returning(move |_, content| {
+                *written.lock().unwrap() = String::from_utf8_lossy(content).into_owned();
+                Ok(())
+            });
+        let wdk_build = WdkBuild::default();
+        let mut command_exec = CommandExec::default();
+        command_exec
+            .expect_run()
+            .withf(|cmd: &str, args: &[&str], _, _| {
+                cmd == "signtool"
+                    && args[..3] == ["verify", "/v", "/pa"]
+                    && args[3].ends_with("driver_package/driver.cat")
+            })
+            .once()
+            .returning(|_, _, _, _| {
+                Ok(Output {
+                    status: ExitStatus::from_raw(0),
+                    stdout: b"Signature Index: 0 (Primary Signature)\r\nHash of file (sha256): \
+                              AB\r\n\r\nSigning Certificate Chain:\r\n    Issued to: \
+                              WDRLocalTestCert\r\n    SHA1 hash: \
+                              0123456789ABCDEF0123456789ABCDEF01234567\r\n\r\nFile is not \
+                              timestamped.\r\n"
+                        .to_vec(),
+                    stderr: vec![],
+                })
+            });
+
+        let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+        assert!(task.write_catalog_signing_report().is_ok());
         assert_eq!(
-            task.dest_cert_file_path,
-            dest_root.join("WDRLocalTestCert.cer")
+            *written_report.lock().unwrap(),
+            "# driver.cat\n\n- Signer: WDRLocalTestCert\n- Thumbprint: \
+             0123456789ABCDEF0123456789ABCDEF01234567\n- Timestamp: not timestamped\n- Digest \
+             algorithm: sha256\n"
         );
-        assert_eq!(task.dest_cat_file_path, dest_root.join("test_package.cat"));
-        assert_eq!(*task.arch, arch);
-        assert_eq!(task.os_mapping, "10_x64");
-        assert!(matches!(task.driver_model, DriverConfig::Kmdf(_)));
     }
 
-    #[test]
-    #[should_panic(expected = "Target directory path must be absolute. Input path: \
-                               ../relative/path/to/target/dir")]
-    fn new_panics_when_target_dir_is_not_absolute() {
-        let package_name = "test_package";
-        let working_dir = PathBuf::from("C:/absolute/path/to/working/dir");
-        let target_dir = PathBuf::from("../relative/path/to/target/dir");
+    fn apply_wdf_verifier_setting_with_inf(
+        wdf_verifier: bool,
+        inf_content: &'static str,
+    ) -> (Result<(), PackageTaskError>, Arc<Mutex<String>>) {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
         let arch = CpuArchitecture::Amd64;
 
-        let package_task_params = PackageTaskParams {
-            package_name,
-            working_dir: &working_dir,
-            target_dir: &target_dir,
-            target_arch: &arch,
-            driver_model: DriverConfig::Kmdf(KmdfConfig::default()),
-            sample_class: false,
-            sign_mode: SignMode::Test {
-                verify_signature: false,
-            },
-            target_platform: TargetPlatform::Universal,
+        let test_params = TestParams::default();
+        let params = PackageTaskParams {
+            wdf_verifier: Some(wdf_verifier),
+            ..test_params.get(package_name, &working_dir, &target_dir, &arch)
         };
 
-        let command_exec = CommandExec::default();
+        let written_inf_content = Arc::new(Mutex::new(String::new()));
+        let mut fs = Fs::default();
+        fs.expect_read_file_to_string()
+            .withf(|path: &Path| path.ends_with("driver_package/driver.inf"))
+            .once()
+            .returning(move |_| Ok(inf_content.to_string()));
+        let written = written_inf_content.clone();
+        fs.expect_write_to_file()
+            .withf(|path: &Path, _| path.ends_with("driver_package/driver.inf"))
+            .returning(move |_, content| {
+                *written.lock().unwrap() = String::from_utf8_lossy(content).into_owned();
+                Ok(())
+            });
         let wdk_build = WdkBuild::default();
-        let fs = Fs::default();
+        let command_exec = CommandExec::default();
 
-        PackageTask::new(package_task_params, &wdk_build, &command_exec, &fs);
+        let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+        (
+            task.apply_wdf_verifier_setting(wdf_verifier),
+            written_inf_content,
+        )
     }
 
     #[test]
-    #[should_panic(expected = "Working directory path must be absolute. Input path: \
-                               relative/path/to/working/dir")]
-    fn new_panics_when_working_dir_is_not_absolute() {
-        let package_name = "test_package";
-        let working_dir = PathBuf::from("relative/path/to/working/dir");
-        let target_dir = PathBuf::from("E:/absolute/path/to/target/dir");
+    fn apply_wdf_verifier_setting_rewrites_verifier_registry_value() {
+        let inf_content = "[driver_Service_Inst]\r\nAddReg = \
+                           driver_Wdf_AddReg\r\n\r\n[driver_Wdf_AddReg]\r\nHKR, Parameters\\Wdf, \
+                           VerifierOn, 0x00010001, 0 ; Set by cargo-wdk\r\n";
+        let (result, written_inf_content) = apply_wdf_verifier_setting_with_inf(true, inf_content);
+        assert!(result.is_ok());
+        assert_eq!(
+            *written_inf_content.lock().unwrap(),
+            "[driver_Service_Inst]\r\nAddReg = \
+             driver_Wdf_AddReg\r\n\r\n[driver_Wdf_AddReg]\r\nHKR, Parameters\\Wdf, VerifierOn, \
+             0x00010001, 1 ; Set by cargo-wdk\r\n"
+        );
+    }
+
+    #[test]
+    fn apply_wdf_verifier_setting_fails_when_inf_has_no_verifier_registry_value() {
+        let (result, _) = apply_wdf_verifier_setting_with_inf(
+            false,
+            "[driver_Service_Inst]\r\nStartType = 3\r\n",
+        );
+        assert!(matches!(
+            result,
+            Err(PackageTaskError::WdfVerifierRegistryValueNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn check_inf_is_relocatable_fails_with_the_absolute_paths_of_the_inf() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
         let arch = CpuArchitecture::Amd64;
 
-        let package_task_params = PackageTaskParams {
-            package_name,
-            working_dir: &working_dir,
-            target_dir: &target_dir,
-            target_arch: &arch,
-            driver_model: DriverConfig::Kmdf(KmdfConfig::default()),
-            sample_class: false,
-            sign_mode: SignMode::Test {
-                verify_signature: false,
-            },
-            target_platform: TargetPlatform::Universal,
+        let test_params = TestParams::default();
+        let params = PackageTaskParams {
+            relocatable: true,
+            ..test_params.get(package_name, &working_dir, &target_dir, &arch)
         };
 
-        let command_exec = CommandExec::default();
+        let mut fs = Fs::default();
+        fs.expect_read_file_to_string()
+            .withf(|path: &Path| path.ends_with("driver_package/driver.inf"))
+            .once()
+            .returning(|_| {
+                Ok(concat!(
+                    "[SourceDisksNames]\r\n",
+                    "1 = %DiskName%,,,\"C:\\abs\\driver\\target\\debug\"\r\n\r\n",
+                    "[SourceDisksFiles]\r\ndriver.sys = 1,,\r\n",
+                )
+                .to_string())
+            });
         let wdk_build = WdkBuild::default();
-        let fs = Fs::default();
+        let command_exec = CommandExec::default();
 
-        PackageTask::new(package_task_params, &wdk_build, &command_exec, &fs);
+        let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+        let result = task.check_inf_is_relocatable();
+        assert!(
+            matches!(
+                result,
+                Err(PackageTaskError::AbsolutePathsInInf(_, ref paths))
+                    if paths == r"C:\abs\driver\target\debug"
+            ),
+            "expected absolute paths in INF error, found: {result:?}"
+        );
     }
 
     #[test]
-    fn stampinf_version_overrides_with_env_var() {
-        // verify both with and without the env var set scenarios
-        let scenarios = [
-            ("env_set", Some("1.2.3.4"), true),
-            ("env_empty", Some(""), false),
-            ("env_spaces", Some("  "), false),
-            ("env_unset", None, false),
-        ];
-
-        for (name, env_val, expect_skip_v) in scenarios {
-            let result =
-                crate::test_utils::with_env(&[(STAMPINF_VERSION_ENV_VAR, env_val)], || {
-                    let package_name = "driver";
-                    let working_dir = PathBuf::from("C:/abs/driver");
-                    let target_dir = PathBuf::from("C:/abs/driver/target/debug");
-                    let arch = CpuArchitecture::Amd64;
+    fn absolute_paths_in_catalog_are_found_in_ascii_and_utf16_strings() {
+        let mut catalog = vec![0x30, 0x82, 0x05, 0x3A, 0x06, 0x09];
+        catalog.extend_from_slice(br"C:\build\driver.sys");
+        catalog.push(0);
+        for path in ["driver.sys", r"D:\out\driver_package"] {
+            catalog.extend(path.encode_utf16().flat_map(u16::to_le_bytes));
+            catalog.extend([0, 0]);
+        }
 
-                    let params = PackageTaskParams {
-                        package_name,
-                        working_dir: &working_dir,
-                        target_dir: &target_dir,
-                        target_arch: &arch,
-                        driver_model: DriverConfig::Kmdf(KmdfConfig::default()),
-                        sample_class: false,
-                        sign_mode: SignMode::Test {
-                            verify_signature: false,
-                        },
-                        target_platform: TargetPlatform::Universal,
-                    };
+        assert_eq!(
+            absolute_paths_in_catalog(&catalog),
+            [r"C:\build\driver.sys", r"D:\out\driver_package"]
+        );
+        assert!(absolute_paths_in_catalog(b"\x30\x82driver.sys\x00").is_empty());
+    }
 
-                    let wdk_build = WdkBuild::default();
-                    let fs = Fs::default();
-                    let mut command_exec = CommandExec::default();
+    #[test]
+    fn catalog_exclude_patterns_must_not_match_required_files() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let arch = CpuArchitecture::Amd64;
 
-                    command_exec
-                        .expect_run()
-                        .withf(move |cmd: &str, args: &[&str], _, _| {
-                            if cmd != "stampinf" {
-                                return false;
-                            }
-                            let has_v = args.contains(&"-v");
-                            if expect_skip_v {
-                                !has_v
-                            } else {
-                                args.windows(2).any(|w| w == ["-v", "*"])
-                            }
-                        })
-                        .once()
-                        .return_once(|_, _, _, _| {
-                            Ok(Output {
-                                status: ExitStatus::default(),
-                                stdout: vec![],
-                                stderr: vec![],
-                            })
-                        });
+        for (pattern, is_valid) in [("*.map", true), ("*.sys", false), ("DRIVER.*", false)] {
+            let catalog_exclude =
+                [CatalogExcludePattern::parse(pattern).expect("pattern should be valid")];
+            let test_params = TestParams::default();
+            let params = PackageTaskParams {
+                catalog_exclude: &catalog_exclude,
+                ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+            };
 
-                    let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
-                    task.run_stampinf()
-                });
+            let fs = Fs::default();
+            let wdk_build = WdkBuild::default();
+            let command_exec = CommandExec::default();
 
-            assert!(
-                result.is_ok(),
-                "scenario {name} failed (env_set={env_val:?})"
-            );
+            let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+            let result = task.check_catalog_exclude();
+            if is_valid {
+                assert!(result.is_ok(), "pattern: {pattern}");
+            } else {
+                assert!(
+                    matches!(
+                        result,
+                        Err(PackageTaskError::CatalogExcludesRequiredFile(ref excluded, _))
+                            if excluded == pattern
+                    ),
+                    "pattern: {pattern}"
+                );
+            }
         }
     }
 
-    fn assert_infverif_mode_flag(
-        driver_model: DriverConfig,
-        target_platform: TargetPlatform,
-        expected_mode_flag: &'static str,
-    ) {
+    #[test]
+    fn catalog_excluded_files_are_moved_out_of_the_package_while_inf2cat_runs() {
         let package_name = "driver";
         let working_dir = PathBuf::from("C:/abs/driver");
         let target_dir = PathBuf::from("C:/abs/driver/target/debug");
         let arch = CpuArchitecture::Amd64;
+        let catalog_exclude =
+            [CatalogExcludePattern::parse("*.map").expect("pattern should be valid")];
 
+        let test_params = TestParams::default();
         let params = PackageTaskParams {
-            package_name,
-            working_dir: &working_dir,
-            target_dir: &target_dir,
-            target_arch: &arch,
-            driver_model,
-            sample_class: false,
-            sign_mode: SignMode::Off,
-            target_platform,
+            catalog_exclude: &catalog_exclude,
+            ..test_params.get(package_name, &working_dir, &target_dir, &arch)
         };
 
-        let fs = Fs::default();
-        let wdk_build = WdkBuild::default();
-
+        let mut fs = Fs::default();
+        fs.expect_exists().returning(|_| true);
+        let mut sequence = Sequence::new();
+        fs.expect_rename()
+            .withf(|src: &Path, dest: &Path| {
+                src.ends_with("driver_package/driver.map")
+                    && dest.ends_with("driver_catalog_excluded/driver.map")
+            })
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|_, _| Ok(()));
         let mut command_exec = CommandExec::default();
         command_exec
             .expect_run()
-            .withf(move |cmd: &str, args: &[&str], _, _| {
-                cmd == "infverif"
-                    && args.len() >= 2
-                    && args[0] == "/v"
-                    && args[1] == expected_mode_flag
-            })
+            .withf(|cmd: &str, _, _, _| cmd == "inf2cat")
             .once()
+            .in_sequence(&mut sequence)
             .returning(|_, _, _, _| {
                 Ok(Output {
-                    status: ExitStatus::default(),
+                    status: ExitStatus::from_raw(0),
                     stdout: vec![],
                     stderr: vec![],
                 })
             });
+        fs.expect_rename()
+            .withf(|src: &Path, dest: &Path| {
+                src.ends_with("driver_catalog_excluded/driver.map")
+                    && dest.ends_with("driver_package/driver.map")
+            })
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|_, _| Ok(()));
+        fs.expect_remove_dir_all()
+            .withf(|path: &Path| path.ends_with("driver_catalog_excluded"))
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|_| Ok(()));
+        let wdk_build = WdkBuild::default();
 
         let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
-        assert!(task.run_infverif().is_ok());
+        assert!(task.run_inf2cat().is_ok());
     }
 
     #[test]
-    fn run_infverif_defaults_to_universal_for_all_driver_models() {
-        assert_infverif_mode_flag(
-            DriverConfig::Kmdf(KmdfConfig::default()),
-            TargetPlatform::Universal,
-            "/u",
-        );
-        assert_infverif_mode_flag(DriverConfig::Wdm, TargetPlatform::Universal, "/u");
-        assert_infverif_mode_flag(
-            DriverConfig::Umdf(UmdfConfig::default()),
-            TargetPlatform::Universal,
-            "/u",
-        );
+    fn copy_debug_files_fails_with_the_paths_of_missing_files() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let arch = CpuArchitecture::Amd64;
+        let debug_files = [
+            PathBuf::from("debug/driver.natvis"),
+            PathBuf::from("debug/missing.js"),
+        ];
+
+        let test_params = TestParams::default();
+        let params = PackageTaskParams {
+            debug_files: &debug_files,
+            ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+        };
+
+        let mut fs = Fs::default();
+        fs.expect_exists()
+            .returning(|path: &Path| path.ends_with("driver.natvis"));
+        let wdk_build = WdkBuild::default();
+        let command_exec = CommandExec::default();
+
+        let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+        assert!(matches!(
+            task.copy_debug_files(),
+            Err(PackageTaskError::MissingDebugFiles(ref missing_files))
+                if missing_files.ends_with("missing.js")
+        ));
     }
 
     #[test]
-    fn run_infverif_uses_target_platform_mode_flag() {
-        assert_infverif_mode_flag(
-            DriverConfig::Kmdf(KmdfConfig::default()),
-            TargetPlatform::Universal,
-            "/u",
+    fn copy_debug_files_copies_files_to_debug_folder() {
+        let package_name = "driver";
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let arch = CpuArchitecture::Amd64;
+        let debug_files = [PathBuf::from("debug/driver.natvis")];
+
+        let test_params = TestParams::default();
+        let params = PackageTaskParams {
+            debug_files: &debug_files,
+            ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+        };
+
+        let mut fs = Fs::default();
+        fs.expect_exists()
+            .returning(|path: &Path| path.ends_with("driver.natvis"));
+        fs.expect_create_dir()
+            .withf(|path: &Path| path.ends_with("driver_package/debug"))
+            .once()
+            .returning(|_| Ok(()));
+        fs.expect_copy()
+            .withf(|src: &Path, dest: &Path| {
+                src.ends_with("driver/debug/driver.natvis")
+                    && dest.ends_with("driver_package/debug/driver.natvis")
+            })
+            .once()
+            .returning(|_, _| Ok(0));
+        let wdk_build = WdkBuild::default();
+        let command_exec = CommandExec::default();
+
+        let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+        assert!(task.copy_debug_files().is_ok());
+    }
+
+    #[test]
+    fn existing_package_folder_with_files_of_another_configuration_is_rejected_or_cleared() {
+        let working_dir = PathBuf::from("C:/abs/driver");
+        let target_dir = PathBuf::from("C:/abs/driver/target/debug");
+        let dest_root = target_dir.join("driver_package");
+        let arch = CpuArchitecture::Amd64;
+
+        for overwrite_package in [false, true] {
+            let test_params = TestParams::default();
+            let params = PackageTaskParams {
+                include_pdb: false,
+                include_map: false,
+                overwrite_package,
+                ..test_params.get("driver", &working_dir, &target_dir, &arch)
+            };
+
+            let mut fs = Fs::default();
+            fs.expect_exists()
+                .with(eq(dest_root.clone()))
+                .returning(|_| true);
+            let package_folder_entries = vec![
+                DirEntryInfo {
+                    path: dest_root.join("driver.sys"),
+                    is_dir: false,
+                },
+                DirEntryInfo {
+                    path: dest_root.join("driver.pdb"),
+                    is_dir: false,
+                },
+            ];
+            fs.expect_read_dir_entries()
+                .with(eq(dest_root.clone()))
+                .once()
+                .returning(move |_| Ok(package_folder_entries.clone()));
+            if overwrite_package {
+                fs.expect_remove_dir_all()
+                    .with(eq(dest_root.clone()))
+                    .once()
+                    .returning(|_| Ok(()));
+                fs.expect_create_dir()
+                    .with(eq(dest_root.clone()))
+                    .once()
+                    .returning(|_| Ok(()));
+            }
+            let wdk_build = WdkBuild::default();
+            let command_exec = CommandExec::default();
+
+            let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+            let result = task.prepare_package_folder();
+            if overwrite_package {
+                assert!(result.is_ok(), "expected the package folder to be cleared");
+            } else {
+                assert!(
+                    matches!(
+                        result,
+                        Err(PackageTaskError::UnexpectedFilesInPackageFolder(ref folder, ref files))
+                            if folder == &dest_root
+                                && files == &dest_root.join("driver.pdb").display().to_string()
+                    ),
+                    "expected unexpected files error, found: {result:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn binary_name_overrides_packaged_file_names_and_inf_references() {
+        let package_name = "foo-driver";
+        let working_dir = PathBuf::from("C:/abs/foo-driver");
+        let target_dir = PathBuf::from("C:/abs/foo-driver/target/debug");
+        let arch = CpuArchitecture::Amd64;
+
+        let test_params = TestParams::default();
+        let params = PackageTaskParams {
+            binary_name: Some("Foo"),
+            ..test_params.get(package_name, &working_dir, &target_dir, &arch)
+        };
+
+        let mut fs = Fs::default();
+        fs.expect_read_file_to_string()
+            .withf(|path: &Path| path.ends_with("foo_driver_package/Foo.inf"))
+            .once()
+            .returning(|_| {
+                Ok("[SourceDisksFiles]\r\nfoo_driver.sys = \
+                    1,,\r\n\r\n[File_Copy]\r\nfoo_driver.sys\r\n"
+                    .to_string())
+            });
+        fs.expect_write_to_file()
+            .withf(|path: &Path, content: &[u8]| {
+                path.ends_with("foo_driver_package/Foo.inf")
+                    && content
+                        == b"[SourceDisksFiles]\r\nFoo.sys = 1,,\r\n\r\n[File_Copy]\r\nFoo.sys\r\n"
+            })
+            .once()
+            .returning(|_, _| Ok(()));
+        let wdk_build = WdkBuild::default();
+        let command_exec = CommandExec::default();
+
+        let task = PackageTask::new(params, &wdk_build, &command_exec, &fs);
+        assert!(
+            task.dest_driver_binary_path
+                .ends_with("foo_driver_package/Foo.sys")
         );
-        assert_infverif_mode_flag(
-            DriverConfig::Kmdf(KmdfConfig::default()),
-            TargetPlatform::Desktop,
-            "/h",
+        assert!(
+            task.dest_cat_file_path
+                .ends_with("foo_driver_package/Foo.cat")
         );
-        assert_infverif_mode_flag(
-            DriverConfig::Kmdf(KmdfConfig::default()),
-            TargetPlatform::WindowsDriver,
-            "/w",
+        assert!(
+            task.src_inx_file_path
+                .ends_with("foo-driver/foo_driver.inx")
         );
+        assert!(task.rename_inf_binary_references().is_ok());
     }
 
     mod named_mutex {
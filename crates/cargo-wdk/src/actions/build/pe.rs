@@ -0,0 +1,457 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module reads the machine type of a PE image, e.g. a driver binary,
+//! from its COFF file header, the names of the functions it exports from its
+//! export directory and the identifier of its PDB from its debug directory.
+//! The build action uses them to check that cargo produced a driver binary
+//! for the requested target architecture that exports the entry points its
+//! driver model requires, and to archive the PDB of the driver for symbol
+//! servers.
+
+use wdk_build::CpuArchitecture;
+
+/// Offset of the field holding the file offset of the PE signature
+const PE_SIGNATURE_OFFSET_FIELD: usize = 0x3C;
+const PE_SIGNATURE: &[u8; 4] = b"PE\0\0";
+const IMAGE_FILE_MACHINE_AMD64: u16 = 0x8664;
+const IMAGE_FILE_MACHINE_ARM64: u16 = 0xAA64;
+/// Size of the COFF file header that follows the PE signature
+const COFF_FILE_HEADER_SIZE: usize = 20;
+/// Offset of the `NumberOfSections` field in the COFF file header
+const NUMBER_OF_SECTIONS_OFFSET: usize = 2;
+/// Offset of the `SizeOfOptionalHeader` field in the COFF file header
+const SIZE_OF_OPTIONAL_HEADER_OFFSET: usize = 16;
+/// Magic number of the optional header of PE32 images
+const PE32_MAGIC: u16 = 0x10B;
+/// Magic number of the optional header of PE32+ images
+const PE32_PLUS_MAGIC: u16 = 0x20B;
+/// Size of a section header of the section table
+const SECTION_HEADER_SIZE: usize = 40;
+/// Index of the export directory in the data directories
+const EXPORT_DIRECTORY_INDEX: u32 = 0;
+/// Index of the debug directory in the data directories
+const DEBUG_DIRECTORY_INDEX: u32 = 6;
+/// Offset of the `NumberOfNames` field in the export directory table
+const EXPORT_NUMBER_OF_NAMES_OFFSET: usize = 24;
+/// Offset of the `AddressOfNames` field in the export directory table
+const EXPORT_ADDRESS_OF_NAMES_OFFSET: usize = 32;
+/// Size of an entry of the debug directory
+const DEBUG_DIRECTORY_ENTRY_SIZE: usize = 28;
+/// Debug directory entry type of CodeView debug information
+const IMAGE_DEBUG_TYPE_CODEVIEW: u32 = 2;
+/// Signature of the CodeView record of PDB 7.0 files
+const CODEVIEW_PDB70_SIGNATURE: &[u8; 4] = b"RSDS";
+
+/// Identifier of the PDB of a PE image, read from the CodeView record of its
+/// debug directory. The debugger only loads a PDB with the same GUID and age.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdbDebugId {
+    /// GUID of the PDB as stored in the image
+    pub guid: [u8; 16],
+    /// Number of times the PDB was written
+    pub age: u32,
+    /// Path of the PDB recorded by the linker, e.g. the `/PDBALTPATH`
+    pub pdb_path: String,
+}
+
+impl PdbDebugId {
+    /// Returns the key symbol servers index the PDB by, i.e. the GUID as
+    /// uppercase hex digits without separators followed by the age in hex
+    #[must_use]
+    pub fn symstore_key(&self) -> String {
+        let data1 = u32::from_le_bytes([self.guid[0], self.guid[1], self.guid[2], self.guid[3]]);
+        let data2 = u16::from_le_bytes([self.guid[4], self.guid[5]]);
+        let data3 = u16::from_le_bytes([self.guid[6], self.guid[7]]);
+        let data4: String = self.guid[8..]
+            .iter()
+            .map(|byte| format!("{byte:02X}"))
+            .collect();
+        format!("{data1:08X}{data2:04X}{data3:04X}{data4}{:X}", self.age)
+    }
+
+    /// Returns the file name of the recorded PDB path, which symbol servers
+    /// look the PDB up by
+    #[must_use]
+    pub fn pdb_file_name(&self) -> &str {
+        self.pdb_path
+            .rsplit(['\\', '/'])
+            .next()
+            .unwrap_or(&self.pdb_path)
+    }
+}
+
+/// Returns the `Machine` field of the COFF file header that follows the PE
+/// signature. Returns `None` if the image is not a PE image.
+#[must_use]
+pub fn read_machine(image: &[u8]) -> Option<u16> {
+    let signature_offset = image
+        .get(PE_SIGNATURE_OFFSET_FIELD..PE_SIGNATURE_OFFSET_FIELD + 4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_le_bytes)?;
+    let signature_offset = usize::try_from(signature_offset).ok()?;
+    let machine_offset = signature_offset.checked_add(PE_SIGNATURE.len())?;
+    if image.get(signature_offset..machine_offset)? != PE_SIGNATURE {
+        return None;
+    }
+    image
+        .get(machine_offset..machine_offset + 2)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u16::from_le_bytes)
+}
+
+/// Returns the names of the functions exported by name from the export
+/// directory of a PE image. Returns an empty list if the image has no export
+/// directory and `None` if the image is not a valid PE image.
+#[must_use]
+pub fn read_export_names(image: &[u8]) -> Option<Vec<String>> {
+    let headers = Headers::read(image)?;
+    let Some((export_directory_rva, _)) = headers.data_directory(image, EXPORT_DIRECTORY_INDEX)
+    else {
+        return Some(Vec::new());
+    };
+    let export_directory_offset = headers.file_offset(export_directory_rva)?;
+    let number_of_names = read_u32(image, export_directory_offset + EXPORT_NUMBER_OF_NAMES_OFFSET)?;
+    let names_offset = headers.file_offset(read_u32(
+        image,
+        export_directory_offset + EXPORT_ADDRESS_OF_NAMES_OFFSET,
+    )?)?;
+    (0..usize::try_from(number_of_names).ok()?)
+        .map(|index| {
+            let name_offset = headers.file_offset(read_u32(image, names_offset + index * 4)?)?;
+            read_c_string(image, name_offset)
+        })
+        .collect()
+}
+
+/// Returns the identifier of the PDB of a PE image from the CodeView record
+/// of its debug directory. Returns `None` if the image is not a valid PE image
+/// or has no PDB 7.0 CodeView record, e.g. because it was linked without
+/// `/DEBUG`.
+#[must_use]
+pub fn read_pdb_debug_id(image: &[u8]) -> Option<PdbDebugId> {
+    let headers = Headers::read(image)?;
+    let (debug_directory_rva, debug_directory_size) =
+        headers.data_directory(image, DEBUG_DIRECTORY_INDEX)?;
+    let debug_directory_offset = headers.file_offset(debug_directory_rva)?;
+    let number_of_entries =
+        usize::try_from(debug_directory_size).ok()? / DEBUG_DIRECTORY_ENTRY_SIZE;
+    (0..number_of_entries).find_map(|index| {
+        let entry_offset = debug_directory_offset + index * DEBUG_DIRECTORY_ENTRY_SIZE;
+        if read_u32(image, entry_offset + 12)? != IMAGE_DEBUG_TYPE_CODEVIEW {
+            return None;
+        }
+        // `PointerToRawData` is the file offset of the CodeView record
+        let record_offset = usize::try_from(read_u32(image, entry_offset + 24)?).ok()?;
+        if image.get(record_offset..record_offset.checked_add(4)?)? != CODEVIEW_PDB70_SIGNATURE {
+            return None;
+        }
+        Some(PdbDebugId {
+            guid: image
+                .get(record_offset + 4..record_offset + 20)?
+                .try_into()
+                .ok()?,
+            age: read_u32(image, record_offset + 20)?,
+            pdb_path: read_c_string(image, record_offset + 24)?,
+        })
+    })
+}
+
+/// Returns the architecture of a PE machine type, or `None` for machine types
+/// drivers cannot be built for
+#[must_use]
+pub const fn machine_architecture(machine: u16) -> Option<CpuArchitecture> {
+    match machine {
+        IMAGE_FILE_MACHINE_AMD64 => Some(CpuArchitecture::Amd64),
+        IMAGE_FILE_MACHINE_ARM64 => Some(CpuArchitecture::Arm64),
+        _ => None,
+    }
+}
+
+/// A section of a PE image, as described by its section header
+struct Section {
+    virtual_size: u32,
+    virtual_address: u32,
+    size_of_raw_data: u32,
+    pointer_to_raw_data: u32,
+}
+
+impl Section {
+    /// Returns the file offset of a relative virtual address, or `None` if the
+    /// address is not in the raw data of the section
+    fn file_offset(&self, rva: u32) -> Option<usize> {
+        let offset_in_section = rva.checked_sub(self.virtual_address)?;
+        if offset_in_section >= self.virtual_size.max(self.size_of_raw_data) {
+            return None;
+        }
+        usize::try_from(self.pointer_to_raw_data.checked_add(offset_in_section)?).ok()
+    }
+}
+
+/// Headers of a PE image that locate its data directories and sections
+struct Headers {
+    // File offset of the `NumberOfRvaAndSizes` field of the optional header,
+    // which is followed by the data directories
+    number_of_rva_and_sizes_offset: usize,
+    sections: Vec<Section>,
+}
+
+impl Headers {
+    /// Reads the headers of a PE image. Returns `None` if the image is not a
+    /// valid PE image.
+    fn read(image: &[u8]) -> Option<Self> {
+        let signature_offset = usize::try_from(read_u32(image, PE_SIGNATURE_OFFSET_FIELD)?).ok()?;
+        if image.get(signature_offset..signature_offset.checked_add(PE_SIGNATURE.len())?)?
+            != PE_SIGNATURE
+        {
+            return None;
+        }
+        let coff_header_offset = signature_offset + PE_SIGNATURE.len();
+        let number_of_sections = read_u16(image, coff_header_offset + NUMBER_OF_SECTIONS_OFFSET)?;
+        let size_of_optional_header =
+            read_u16(image, coff_header_offset + SIZE_OF_OPTIONAL_HEADER_OFFSET)?;
+        let optional_header_offset = coff_header_offset + COFF_FILE_HEADER_SIZE;
+        // The offset of the `NumberOfRvaAndSizes` field depends on the format
+        // of the optional header
+        let number_of_rva_and_sizes_offset = optional_header_offset
+            + match read_u16(image, optional_header_offset)? {
+                PE32_MAGIC => 92,
+                PE32_PLUS_MAGIC => 108,
+                _ => return None,
+            };
+
+        let section_table_offset = optional_header_offset + usize::from(size_of_optional_header);
+        let sections = (0..usize::from(number_of_sections))
+            .map(|index| {
+                let offset = section_table_offset + index * SECTION_HEADER_SIZE;
+                Some(Section {
+                    virtual_size: read_u32(image, offset + 8)?,
+                    virtual_address: read_u32(image, offset + 12)?,
+                    size_of_raw_data: read_u32(image, offset + 16)?,
+                    pointer_to_raw_data: read_u32(image, offset + 20)?,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self {
+            number_of_rva_and_sizes_offset,
+            sections,
+        })
+    }
+
+    /// Returns the relative virtual address and size of a data directory, or
+    /// `None` if the image does not have it
+    fn data_directory(&self, image: &[u8], index: u32) -> Option<(u32, u32)> {
+        if read_u32(image, self.number_of_rva_and_sizes_offset)? <= index {
+            return None;
+        }
+        let offset = self.number_of_rva_and_sizes_offset + 4 + usize::try_from(index).ok()? * 8;
+        let rva = read_u32(image, offset)?;
+        let size = read_u32(image, offset + 4)?;
+        (rva != 0).then_some((rva, size))
+    }
+
+    /// Returns the file offset of a relative virtual address, or `None` if the
+    /// address is not in a section of the image
+    fn file_offset(&self, rva: u32) -> Option<usize> {
+        self.sections
+            .iter()
+            .find_map(|section| section.file_offset(rva))
+    }
+}
+
+/// Reads the null-terminated string at `offset`
+fn read_c_string(image: &[u8], offset: usize) -> Option<String> {
+    let bytes = image.get(offset..)?;
+    let bytes = &bytes[..bytes.iter().position(|byte| *byte == 0)?];
+    Some(String::from_utf8_lossy(bytes).into_owned())
+}
+
+fn read_u16(image: &[u8], offset: usize) -> Option<u16> {
+    image
+        .get(offset..offset.checked_add(2)?)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u16::from_le_bytes)
+}
+
+fn read_u32(image: &[u8], offset: usize) -> Option<u32> {
+    image
+        .get(offset..offset.checked_add(4)?)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_le_bytes)
+}
+
+/// Returns a PE32+ image of the given machine type with a single section
+/// holding an export directory that exports the given function names and a
+/// debug directory with the CodeView record of the given PDB, if any
+#[cfg(test)]
+pub fn test_image(machine: u16, exports: &[&str], pdb_debug_id: Option<&PdbDebugId>) -> Vec<u8> {
+    const OPTIONAL_HEADER_SIZE: u16 = 240;
+    const NUMBER_OF_DATA_DIRECTORIES: usize = 16;
+    const SECTION_FILE_OFFSET: usize = 0x200;
+    const SECTION_RVA: u32 = 0x1000;
+    const EXPORT_DIRECTORY_SIZE: usize = 40;
+
+    let to_u32 = |value: usize| u32::try_from(value).expect("test image is small");
+    let mut section_data = Vec::new();
+    let mut data_directories = [(0u32, 0u32); NUMBER_OF_DATA_DIRECTORIES];
+    if !exports.is_empty() {
+        // Export directory, followed by the name pointer table and the names
+        let names_rva = SECTION_RVA + to_u32(EXPORT_DIRECTORY_SIZE);
+        let mut export_data = vec![0u8; EXPORT_DIRECTORY_SIZE];
+        export_data[20..24].copy_from_slice(&to_u32(exports.len()).to_le_bytes());
+        export_data[24..28].copy_from_slice(&to_u32(exports.len()).to_le_bytes());
+        export_data[32..36].copy_from_slice(&names_rva.to_le_bytes());
+        let mut name_rva = names_rva + to_u32(exports.len() * 4);
+        let mut names = Vec::new();
+        for export in exports {
+            export_data.extend_from_slice(&name_rva.to_le_bytes());
+            names.extend_from_slice(export.as_bytes());
+            names.push(0);
+            name_rva += to_u32(export.len() + 1);
+        }
+        export_data.extend_from_slice(&names);
+        data_directories[0] = (SECTION_RVA, to_u32(export_data.len()));
+        section_data.extend_from_slice(&export_data);
+    }
+    if let Some(pdb_debug_id) = pdb_debug_id {
+        // Debug directory with a single entry, followed by its CodeView record
+        let mut codeview_record = CODEVIEW_PDB70_SIGNATURE.to_vec();
+        codeview_record.extend_from_slice(&pdb_debug_id.guid);
+        codeview_record.extend_from_slice(&pdb_debug_id.age.to_le_bytes());
+        codeview_record.extend_from_slice(pdb_debug_id.pdb_path.as_bytes());
+        codeview_record.push(0);
+        let debug_directory_offset = section_data.len();
+        let codeview_record_offset = debug_directory_offset + DEBUG_DIRECTORY_ENTRY_SIZE;
+        let mut entry = [0u8; DEBUG_DIRECTORY_ENTRY_SIZE];
+        entry[12..16].copy_from_slice(&IMAGE_DEBUG_TYPE_CODEVIEW.to_le_bytes());
+        entry[16..20].copy_from_slice(&to_u32(codeview_record.len()).to_le_bytes());
+        entry[20..24]
+            .copy_from_slice(&(SECTION_RVA + to_u32(codeview_record_offset)).to_le_bytes());
+        entry[24..28].copy_from_slice(
+            &to_u32(SECTION_FILE_OFFSET + codeview_record_offset).to_le_bytes(),
+        );
+        data_directories[6] = (
+            SECTION_RVA + to_u32(debug_directory_offset),
+            to_u32(DEBUG_DIRECTORY_ENTRY_SIZE),
+        );
+        section_data.extend_from_slice(&entry);
+        section_data.extend_from_slice(&codeview_record);
+    }
+
+    let mut image = vec![0u8; 0x40];
+    image[..2].copy_from_slice(b"MZ");
+    image[PE_SIGNATURE_OFFSET_FIELD..PE_SIGNATURE_OFFSET_FIELD + 4]
+        .copy_from_slice(&0x40u32.to_le_bytes());
+    image.extend_from_slice(PE_SIGNATURE);
+    let mut coff_header = [0u8; COFF_FILE_HEADER_SIZE];
+    coff_header[..2].copy_from_slice(&machine.to_le_bytes());
+    coff_header[2..4].copy_from_slice(&1u16.to_le_bytes());
+    coff_header[16..18].copy_from_slice(&OPTIONAL_HEADER_SIZE.to_le_bytes());
+    image.extend_from_slice(&coff_header);
+    let mut optional_header = vec![0u8; 112];
+    optional_header[..2].copy_from_slice(&PE32_PLUS_MAGIC.to_le_bytes());
+    optional_header[108..112]
+        .copy_from_slice(&to_u32(NUMBER_OF_DATA_DIRECTORIES).to_le_bytes());
+    for (rva, size) in data_directories {
+        optional_header.extend_from_slice(&rva.to_le_bytes());
+        optional_header.extend_from_slice(&size.to_le_bytes());
+    }
+    image.extend_from_slice(&optional_header);
+    let mut section_header = [0u8; SECTION_HEADER_SIZE];
+    section_header[..6].copy_from_slice(b".rdata");
+    section_header[8..12].copy_from_slice(&to_u32(section_data.len()).to_le_bytes());
+    section_header[12..16].copy_from_slice(&SECTION_RVA.to_le_bytes());
+    section_header[16..20].copy_from_slice(&to_u32(section_data.len()).to_le_bytes());
+    section_header[20..24].copy_from_slice(&to_u32(SECTION_FILE_OFFSET).to_le_bytes());
+    image.extend_from_slice(&section_header);
+    image.resize(SECTION_FILE_OFFSET, 0);
+    image.extend_from_slice(&section_data);
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a minimal PE image with the PE signature right after the DOS
+    /// header
+    fn pe_image(machine: u16) -> Vec<u8> {
+        let mut image = vec![0u8; 0x40];
+        image[..2].copy_from_slice(b"MZ");
+        image[PE_SIGNATURE_OFFSET_FIELD..PE_SIGNATURE_OFFSET_FIELD + 4]
+            .copy_from_slice(&0x40u32.to_le_bytes());
+        image.extend_from_slice(PE_SIGNATURE);
+        image.extend_from_slice(&machine.to_le_bytes());
+        image
+    }
+
+    #[test]
+    fn machine_is_read_from_coff_file_header() {
+        for (machine, arch) in [
+            (IMAGE_FILE_MACHINE_AMD64, CpuArchitecture::Amd64),
+            (IMAGE_FILE_MACHINE_ARM64, CpuArchitecture::Arm64),
+        ] {
+            let machine = read_machine(&pe_image(machine));
+            assert_eq!(machine.and_then(machine_architecture), Some(arch));
+        }
+    }
+
+    #[test]
+    fn export_names_are_read_from_export_directory() {
+        assert_eq!(
+            read_export_names(&test_image(
+                IMAGE_FILE_MACHINE_AMD64,
+                &["DriverEntry", "FxDriverEntryUm"],
+                None
+            )),
+            Some(vec!["DriverEntry".to_string(), "FxDriverEntryUm".to_string()])
+        );
+        assert_eq!(
+            read_export_names(&test_image(IMAGE_FILE_MACHINE_ARM64, &[], None)),
+            Some(Vec::new())
+        );
+        assert_eq!(read_export_names(b"not a PE image"), None);
+        // The machine type alone does not describe the export directory
+        assert_eq!(read_export_names(&pe_image(IMAGE_FILE_MACHINE_AMD64)), None);
+    }
+
+    #[test]
+    fn pdb_debug_id_is_read_from_codeview_record_of_debug_directory() {
+        let pdb_debug_id = PdbDebugId {
+            guid: [
+                0x78, 0x56, 0x34, 0x12, 0xBC, 0x9A, 0xF0, 0xDE, 0x01, 0x23, 0x45, 0x67, 0x89, 0xAB,
+                0xCD, 0xEF,
+            ],
+            age: 0x1A,
+            pdb_path: r"C:\target\debug\deps\driver.pdb".to_string(),
+        };
+        let image = test_image(
+            IMAGE_FILE_MACHINE_AMD64,
+            &["FxDriverEntryUm"],
+            Some(&pdb_debug_id),
+        );
+
+        assert_eq!(read_pdb_debug_id(&image), Some(pdb_debug_id.clone()));
+        assert_eq!(
+            pdb_debug_id.symstore_key(),
+            "12345678DEF09ABC0123456789ABCDEF1A"
+        );
+        assert_eq!(pdb_debug_id.pdb_file_name(), "driver.pdb");
+        assert_eq!(
+            read_export_names(&image),
+            Some(vec!["FxDriverEntryUm".to_string()])
+        );
+
+        assert_eq!(
+            read_pdb_debug_id(&test_image(IMAGE_FILE_MACHINE_AMD64, &[], None)),
+            None
+        );
+    }
+
+    #[test]
+    fn non_pe_image_has_no_machine() {
+        assert_eq!(read_machine(b"not a PE image"), None);
+        let mut image = pe_image(IMAGE_FILE_MACHINE_AMD64);
+        image[0x40] = b'N';
+        assert_eq!(read_machine(&image), None);
+    }
+}
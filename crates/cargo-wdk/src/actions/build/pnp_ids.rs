@@ -0,0 +1,72 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module contains the helpers used to check that the drivers of a
+//! workspace do not claim the same hardware or compatible ID. Only one of the
+//! drivers matching a device can be installed for it, so such a collision
+//! otherwise only shows up when the drivers are installed. The device IDs are
+//! read from the packaged INFs by `InfFile::device_ids`.
+
+/// A device ID claimed by the packages of two different drivers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateDeviceId {
+    pub device_id: String,
+    pub first_package_name: String,
+    pub second_package_name: String,
+}
+
+/// Returns the first device ID that is claimed by more than one package.
+/// Device IDs are compared case-insensitively.
+#[must_use]
+pub fn find_duplicate_device_id(
+    package_device_ids: &[(String, Vec<String>)],
+) -> Option<DuplicateDeviceId> {
+    for (index, (first_package_name, first_device_ids)) in package_device_ids.iter().enumerate() {
+        for (second_package_name, second_device_ids) in &package_device_ids[index + 1..] {
+            if let Some(device_id) = first_device_ids.iter().find(|first_device_id| {
+                second_device_ids
+                    .iter()
+                    .any(|second_device_id| second_device_id.eq_ignore_ascii_case(first_device_id))
+            }) {
+                return Some(DuplicateDeviceId {
+                    device_id: device_id.clone(),
+                    first_package_name: first_package_name.clone(),
+                    second_package_name: second_package_name.clone(),
+                });
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_duplicate_device_id_reports_both_packages() {
+        let package_device_ids = [
+            ("driver_a".to_string(), vec![r"Root\DriverA".to_string()]),
+            (
+                "driver_b".to_string(),
+                vec![r"Root\DriverB".to_string(), r"ROOT\DRIVERA".to_string()],
+            ),
+        ];
+        assert_eq!(
+            find_duplicate_device_id(&package_device_ids),
+            Some(DuplicateDeviceId {
+                device_id: r"Root\DriverA".to_string(),
+                first_package_name: "driver_a".to_string(),
+                second_package_name: "driver_b".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn find_duplicate_device_id_allows_unique_ids() {
+        let package_device_ids = [
+            ("driver_a".to_string(), vec![r"Root\DriverA".to_string()]),
+            ("driver_b".to_string(), vec![r"Root\DriverB".to_string()]),
+        ];
+        assert_eq!(find_duplicate_device_id(&package_device_ids), None);
+    }
+}
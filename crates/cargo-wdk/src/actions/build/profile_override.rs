@@ -0,0 +1,201 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module contains the profile settings overridden by the
+//! `--profile-override` option of the build action. Each override is passed
+//! to cargo as `--config profile.<profile>.<key>=<value>`, so that a profile
+//! setting can be changed for a single build, e.g. for a quick performance
+//! experiment, without editing the committed manifest.
+
+use std::{fmt, str::FromStr};
+
+use crate::actions::Profile;
+
+/// Keys of the `[profile]` settings that can be overridden
+const KNOWN_KEYS: [&str; 11] = [
+    "opt-level",
+    "debug",
+    "split-debuginfo",
+    "strip",
+    "debug-assertions",
+    "overflow-checks",
+    "lto",
+    "panic",
+    "incremental",
+    "codegen-units",
+    "rpath",
+];
+
+/// Setting of a cargo profile overridden for a build, e.g. `opt-level=3`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileOverride {
+    /// Key of the setting in the `[profile.<name>]` table
+    pub key: String,
+    /// Value of the setting as it was passed
+    pub value: String,
+    /// Value of the setting as a TOML value, e.g. `3` or `"s"`
+    toml_value: String,
+}
+
+impl ProfileOverride {
+    /// Returns the `--config` value of cargo that overrides the setting for
+    /// the given profile, or for the `dev` profile if it is `None`
+    pub fn config_value(&self, profile: Option<&Profile>) -> String {
+        format!(
+            "profile.{}.{}={}",
+            profile.map_or_else(|| Profile::Dev.to_string(), ToString::to_string),
+            self.key,
+            self.toml_value
+        )
+    }
+}
+
+impl FromStr for ProfileOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((key, value)) = s.split_once('=') else {
+            return Err(format!(
+                "'{s}' is not a valid profile override, expected KEY=VALUE, e.g. 'opt-level=3'"
+            ));
+        };
+        let (key, value) = (key.trim(), value.trim());
+        if !KNOWN_KEYS.contains(&key) {
+            return Err(format!(
+                "'{key}' is not a known profile setting, expected one of: {}",
+                KNOWN_KEYS.join(", ")
+            ));
+        }
+        let toml_value = toml_value(key, value).ok_or_else(|| {
+            format!("'{value}' is not a valid value of the `{key}` profile setting")
+        })?;
+        Ok(Self {
+            key: key.to_string(),
+            value: value.to_string(),
+            toml_value,
+        })
+    }
+}
+
+impl fmt::Display for ProfileOverride {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.key, self.value)
+    }
+}
+
+/// Returns `value` of the `key` profile setting as a TOML value, with the
+/// booleans and integers unquoted and the strings quoted as cargo expects
+/// them. `None` if the value is not valid for the setting.
+fn toml_value(key: &str, value: &str) -> Option<String> {
+    let is_bool = matches!(value, "true" | "false");
+    let string_values: &[&str] = match key {
+        "debug-assertions" | "overflow-checks" | "incremental" | "rpath" => {
+            return is_bool.then(|| value.to_string());
+        }
+        "codegen-units" => {
+            return value
+                .parse::<u32>()
+                .ok()
+                .filter(|units| *units > 0 && !value.starts_with('+'))
+                .map(|units| units.to_string());
+        }
+        "opt-level" => {
+            if matches!(value, "0" | "1" | "2" | "3") {
+                return Some(value.to_string());
+            }
+            &["s", "z"]
+        }
+        "debug" => {
+            if is_bool || matches!(value, "0" | "1" | "2") {
+                return Some(value.to_string());
+            }
+            &[
+                "none",
+                "line-directives-only",
+                "line-tables-only",
+                "limited",
+                "full",
+            ]
+        }
+        "lto" => {
+            if is_bool {
+                return Some(value.to_string());
+            }
+            &["fat", "thin", "off"]
+        }
+        "strip" => {
+            if is_bool {
+                return Some(value.to_string());
+            }
+            &["none", "debuginfo", "symbols"]
+        }
+        "split-debuginfo" => &["off", "packed", "unpacked"],
+        "panic" => &["unwind", "abort"],
+        _ => return None,
+    };
+    string_values
+        .contains(&value)
+        .then(|| format!("\"{value}\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ProfileOverride;
+    use crate::actions::Profile;
+
+    #[test]
+    fn overrides_are_passed_as_toml_values_of_the_profile_table() {
+        for (profile_override, profile, config_value) in [
+            ("opt-level=3", None, "profile.dev.opt-level=3"),
+            (
+                "opt-level=s",
+                Some(Profile::Release),
+                "profile.release.opt-level=\"s\"",
+            ),
+            (
+                " debug-assertions = true ",
+                Some(Profile::Custom("perf".to_string())),
+                "profile.perf.debug-assertions=true",
+            ),
+            ("codegen-units=16", None, "profile.dev.codegen-units=16"),
+            ("lto=thin", None, "profile.dev.lto=\"thin\""),
+            ("debug=false", None, "profile.dev.debug=false"),
+            (
+                "debug=line-tables-only",
+                None,
+                "profile.dev.debug=\"line-tables-only\"",
+            ),
+        ] {
+            let parsed = profile_override
+                .parse::<ProfileOverride>()
+                .expect("override should be valid");
+            assert_eq!(parsed.config_value(profile.as_ref()), config_value);
+        }
+    }
+
+    #[test]
+    fn unknown_keys_and_invalid_values_are_rejected() {
+        for (profile_override, error) in [
+            ("opt-level", "expected KEY=VALUE"),
+            (
+                "opt-levels=3",
+                "'opt-levels' is not a known profile setting",
+            ),
+            (
+                "opt-level=4",
+                "'4' is not a valid value of the `opt-level` profile",
+            ),
+            ("debug-assertions=yes", "'yes' is not a valid value"),
+            ("codegen-units=0", "'0' is not a valid value"),
+            ("codegen-units=+4", "'+4' is not a valid value"),
+            ("panic=\"abort\"", "'\"abort\"' is not a valid value"),
+        ] {
+            let parsed = profile_override.parse::<ProfileOverride>();
+            assert!(
+                parsed
+                    .as_ref()
+                    .is_err_and(|parsed_error| parsed_error.contains(error)),
+                "{profile_override}: {parsed:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,83 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module contains the helpers used to create reproducible driver
+//! packages. By default `stampinf` stamps the INF with the date and time of the
+//! build, so the `DriverVer` of a reproducible package is instead derived from
+//! a fixed point in time, e.g. the `SOURCE_DATE_EPOCH` of the build, and the
+//! version of the package.
+
+use cargo_metadata::semver::Version;
+
+/// Number of seconds in a day
+pub const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Fixed `DriverVer` date and version that `stampinf` writes to the INF of a
+/// reproducible package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DriverVer {
+    /// Date in the `MM/DD/YYYY` format expected by `stampinf -d`
+    pub date: String,
+    /// Version in the `w.x.y.z` format expected by `stampinf -v`
+    pub version: String,
+}
+
+impl DriverVer {
+    /// Creates the `DriverVer` of a package with the given version from the
+    /// number of seconds since the Unix epoch of the build.
+    pub fn new(source_date_epoch: u64, package_version: &Version) -> Self {
+        let (year, month, day) = civil_date_from_unix_time(source_date_epoch);
+        Self {
+            date: format!("{month:02}/{day:02}/{year:04}"),
+            version: format!(
+                "{}.{}.{}.0",
+                package_version.major, package_version.minor, package_version.patch
+            ),
+        }
+    }
+}
+
+/// Converts a number of seconds since the Unix epoch to a UTC `(year, month,
+/// day)` date of the proleptic Gregorian calendar
+pub fn civil_date_from_unix_time(seconds: u64) -> (u64, u64, u64) {
+    // Days are counted from 0000-03-01 so that leap days are at the end of a
+    // year. Eras are the 400 year cycles of the Gregorian calendar.
+    let days = seconds / SECONDS_PER_DAY + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn civil_date_from_unix_time_converts_to_utc_dates() {
+        assert_eq!(civil_date_from_unix_time(0), (1970, 1, 1));
+        assert_eq!(civil_date_from_unix_time(951_782_400), (2000, 2, 29));
+        assert_eq!(civil_date_from_unix_time(1_700_000_000), (2023, 11, 14));
+    }
+
+    #[test]
+    fn driver_ver_uses_epoch_date_and_package_version() {
+        let driver_ver = DriverVer::new(1_700_000_000, &Version::new(1, 2, 3));
+        assert_eq!(
+            driver_ver,
+            DriverVer {
+                date: "11/14/2023".to_string(),
+                version: "1.2.3.0".to_string(),
+            }
+        );
+    }
+}
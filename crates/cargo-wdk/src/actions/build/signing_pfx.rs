@@ -0,0 +1,166 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module contains the signing certificate read by the `--cert-from-env`
+//! option of the build action. CI systems provide the certificate as a base64
+//! encoded PFX in a secret environment variable, which is decoded into a
+//! temporary file for `signtool` and deleted after the build. Neither the
+//! certificate nor its password is ever logged.
+
+use std::{fmt, path::PathBuf};
+
+use base64::{
+    DecodeError,
+    Engine,
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+};
+use mockall_double::double;
+use tracing::debug;
+
+use crate::providers::error::FileError;
+#[double]
+use crate::providers::fs::Fs;
+
+/// Base64 engine of the standard alphabet that accepts input with and
+/// without the `=` padding
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Error decoding a base64 string
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("invalid base64 at offset {offset}")]
+pub struct Base64Error {
+    /// Offset of the first invalid character, not counting whitespace, or the
+    /// length of the input if it ends in the middle of a byte. The character
+    /// itself is not reported since the input is secret.
+    pub offset: usize,
+}
+
+/// PFX certificate decoded into a temporary file to sign with
+pub struct SigningPfx {
+    /// Path of the temporary PFX file
+    pub path: PathBuf,
+    /// Password of the PFX file, `None` if it is not protected by one
+    pub password: Option<String>,
+    /// Size of the PFX file, which is overwritten before it is deleted
+    len: usize,
+}
+
+// The password is redacted so that the certificate can be debug logged
+impl fmt::Debug for SigningPfx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningPfx")
+            .field("path", &self.path)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .finish_non_exhaustive()
+    }
+}
+
+impl SigningPfx {
+    /// Writes the decoded `pfx` to `path`
+    ///
+    /// # Errors
+    /// * `FileError` - If the PFX file cannot be written.
+    pub fn write(
+        fs: &Fs,
+        path: PathBuf,
+        pfx: &[u8],
+        password: Option<String>,
+    ) -> Result<Self, FileError> {
+        fs.write_to_file(&path, pfx)?;
+        debug!("Signing certificate written to {}", path.display());
+        Ok(Self {
+            path,
+            password,
+            len: pfx.len(),
+        })
+    }
+
+    /// Overwrites the PFX file with zeros before deleting it, so that the
+    /// certificate does not linger on the disk of the build machine
+    ///
+    /// # Errors
+    /// * `FileError` - If the PFX file cannot be overwritten or deleted.
+    pub fn delete(&self, fs: &Fs) -> Result<(), FileError> {
+        fs.write_to_file(&self.path, &vec![0; self.len])?;
+        fs.remove_file(&self.path)?;
+        debug!("Signing certificate {} deleted", self.path.display());
+        Ok(())
+    }
+}
+
+/// Decodes a base64 string of the standard alphabet. ASCII whitespace, e.g.
+/// the line breaks of wrapped output, is ignored and the `=` padding is
+/// optional.
+pub fn decode_base64(encoded: &str) -> Result<Vec<u8>, Base64Error> {
+    let encoded = encoded.split_ascii_whitespace().collect::<String>();
+    // The errors of the base64 crate contain the invalid character, so only
+    // its offset is kept
+    BASE64.decode(&encoded).map_err(|e| Base64Error {
+        offset: match e {
+            DecodeError::InvalidByte(offset, _) | DecodeError::InvalidLastSymbol(offset, _) => {
+                offset
+            }
+            DecodeError::InvalidLength(length) => length,
+            DecodeError::InvalidPadding => encoded.len(),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn base64_is_decoded_with_and_without_padding_and_whitespace() {
+        for (encoded, decoded) in [
+            ("", b"".as_slice()),
+            ("TWFu", b"Man".as_slice()),
+            ("TWE=", b"Ma".as_slice()),
+            ("TWE", b"Ma".as_slice()),
+            ("TQ==", b"M".as_slice()),
+            ("TW\r\nFu\n", b"Man".as_slice()),
+            ("/+8=", [0xFF, 0xEF].as_slice()),
+        ] {
+            assert_eq!(decode_base64(encoded).as_deref(), Ok(decoded), "{encoded}");
+        }
+        for (encoded, offset) in [("TW.u", 2), ("TQ==TQ==", 2), ("TWFuT", 5), ("/+9=", 2)] {
+            assert_eq!(
+                decode_base64(encoded),
+                Err(Base64Error { offset }),
+                "{encoded}"
+            );
+        }
+    }
+
+    #[test]
+    fn pfx_is_written_and_overwritten_before_it_is_deleted() {
+        let path = PathBuf::from("C:/temp/cargo-wdk-1.pfx");
+        let mut fs = Fs::default();
+        let mut seq = mockall::Sequence::new();
+        fs.expect_write_to_file()
+            .withf(|path: &Path, data: &[u8]| path.ends_with("cargo-wdk-1.pfx") && *data == *b"Man")
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|_, _| Ok(()));
+        fs.expect_write_to_file()
+            .withf(|path: &Path, data: &[u8]| path.ends_with("cargo-wdk-1.pfx") && *data == [0; 3])
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|_, _| Ok(()));
+        fs.expect_remove_file()
+            .withf(|path: &Path| path.ends_with("cargo-wdk-1.pfx"))
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|_| Ok(()));
+
+        let pfx = SigningPfx::write(&fs, path, b"Man", Some("secret".to_string()))
+            .expect("PFX should be written");
+        assert!(!format!("{pfx:?}").contains("secret"));
+        assert!(pfx.delete(&fs).is_ok());
+    }
+}
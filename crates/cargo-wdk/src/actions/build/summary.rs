@@ -0,0 +1,59 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module collects the files produced by a build, so that they can be
+//! listed once the build has finished, e.g. for `--print-artifacts`.
+
+use std::{cell::RefCell, path::PathBuf};
+
+/// Summary of the outcome of a build
+#[derive(Debug, Default)]
+pub struct BuildSummary {
+    artifacts: RefCell<Vec<PathBuf>>,
+}
+
+impl BuildSummary {
+    /// Records files written to a driver package, in the order they are
+    /// given
+    pub fn record_artifacts(&self, artifacts: impl IntoIterator<Item = PathBuf>) {
+        self.artifacts.borrow_mut().extend(artifacts);
+    }
+
+    /// Returns the recorded files in the order they were recorded
+    pub fn artifacts(&self) -> Vec<PathBuf> {
+        self.artifacts.borrow().clone()
+    }
+
+    /// Prints the path of every recorded file to stdout, one per line and
+    /// nothing else, so the output can be piped into other tools
+    pub fn print_artifacts(&self) {
+        for artifact in self.artifacts.borrow().iter() {
+            println!("{}", artifact.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::BuildSummary;
+
+    #[test]
+    fn artifacts_are_listed_in_the_order_they_were_recorded() {
+        let summary = BuildSummary::default();
+        summary.record_artifacts([
+            PathBuf::from("C:/target/debug/driver_package/driver.sys"),
+            PathBuf::from("C:/target/debug/driver_package/driver.inf"),
+        ]);
+        summary.record_artifacts([PathBuf::from("C:/target/debug/driver_package/driver.cat")]);
+
+        assert_eq!(
+            summary.artifacts(),
+            [
+                PathBuf::from("C:/target/debug/driver_package/driver.sys"),
+                PathBuf::from("C:/target/debug/driver_package/driver.inf"),
+                PathBuf::from("C:/target/debug/driver_package/driver.cat"),
+            ]
+        );
+    }
+}
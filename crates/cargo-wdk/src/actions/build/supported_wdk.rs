@@ -0,0 +1,217 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module implements the supported WDK range of the build action. A
+//! driver package may declare the range of WDK releases it is validated
+//! against in the `supported-wdk` packaging setting. Builds with a detected
+//! WDK outside of the range fail unless `--allow-unsupported-wdk` is passed.
+
+use std::fmt;
+
+use serde_json::{Value, json};
+use tracing::{debug, warn};
+
+use crate::actions::build::error::BuildActionError;
+
+/// Key of the lower bound in the `supported-wdk` table
+const MIN_KEY: &str = "min";
+/// Key of the upper bound in the `supported-wdk` table
+const MAX_KEY: &str = "max";
+
+/// Inclusive range of WDK build numbers, e.g. `22621` for WDK `10.0.22621.0`,
+/// that a driver package supports. A missing bound leaves that side of the
+/// range open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SupportedWdk {
+    pub min: Option<u32>,
+    pub max: Option<u32>,
+}
+
+impl SupportedWdk {
+    /// Reads the supported WDK range from a `{ min = "...", max = "..." }`
+    /// table. Each bound is either a full WDK version, e.g. `10.0.22621.0`,
+    /// or a WDK build number, e.g. `22621`.
+    ///
+    /// # Errors
+    /// * `BuildActionError::InvalidCargoWdkMetadata` - If the value is not a
+    ///   table, a bound is not a WDK version, the table has no bounds or `min`
+    ///   is greater than `max`.
+    pub fn from_metadata(key: &str, value: &Value) -> Result<Self, BuildActionError> {
+        let invalid = || {
+            BuildActionError::InvalidCargoWdkMetadata(format!(
+                "`{key}` must be a table with a `{MIN_KEY}` and/or `{MAX_KEY}` WDK version, e.g. \
+                 {{ {MIN_KEY} = \"10.0.22621.0\" }}, found: {value}"
+            ))
+        };
+        let table = value.as_object().ok_or_else(invalid)?;
+        if table
+            .keys()
+            .any(|bound| bound != MIN_KEY && bound != MAX_KEY)
+        {
+            return Err(invalid());
+        }
+        let bound = |bound_key: &str| {
+            table
+                .get(bound_key)
+                .map(|bound| {
+                    bound
+                        .as_str()
+                        .and_then(parse_wdk_build_number)
+                        .ok_or_else(invalid)
+                })
+                .transpose()
+        };
+        let supported_wdk = Self {
+            min: bound(MIN_KEY)?,
+            max: bound(MAX_KEY)?,
+        };
+        match (supported_wdk.min, supported_wdk.max) {
+            (None, None) => Err(invalid()),
+            (Some(min), Some(max)) if min > max => Err(invalid()),
+            _ => Ok(supported_wdk),
+        }
+    }
+
+    /// Returns the JSON schema of the `{ min = "...", max = "..." }` table
+    #[must_use]
+    pub fn json_schema() -> Value {
+        let bound = json!({ "type": "string", "pattern": "^(10\\.[0-9]+\\.)?[0-9]+(\\.[0-9]+)?$" });
+        json!({
+            "type": "object",
+            "properties": { MIN_KEY: bound, MAX_KEY: bound },
+            "additionalProperties": false,
+            "minProperties": 1
+        })
+    }
+
+    /// Returns `true` if the WDK with the given build number is in the range
+    #[must_use]
+    pub fn contains(&self, wdk_build_number: u32) -> bool {
+        self.min.is_none_or(|min| min <= wdk_build_number)
+            && self.max.is_none_or(|max| wdk_build_number <= max)
+    }
+}
+
+impl fmt::Display for SupportedWdk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) => write!(f, "builds {min} to {max}"),
+            (Some(min), None) => write!(f, "build {min} or newer"),
+            (None, Some(max)) => write!(f, "build {max} or older"),
+            (None, None) => write!(f, "any build"),
+        }
+    }
+}
+
+/// Parses the build number from a full WDK version, e.g. `22621` from
+/// `10.0.22621.0`, or a bare build number
+fn parse_wdk_build_number(version: &str) -> Option<u32> {
+    let version = version.trim();
+    if !version.contains('.') {
+        return version.parse().ok();
+    }
+    match version.split('.').collect::<Vec<_>>().as_slice() {
+        ["10", minor, build_number, revision]
+            if minor.parse::<u32>().is_ok() && revision.parse::<u32>().is_ok() =>
+        {
+            build_number.parse().ok()
+        }
+        _ => None,
+    }
+}
+
+/// Checks that the detected WDK is in the range supported by a package. With
+/// `allow_unsupported_wdk` a WDK outside of the range only logs a warning.
+///
+/// # Errors
+/// * `BuildActionError::UnsupportedWdk` - If the detected WDK is outside of the
+///   supported range and `allow_unsupported_wdk` is `false`.
+pub fn check(
+    package_name: &str,
+    supported_wdk: SupportedWdk,
+    wdk_build_number: u32,
+    allow_unsupported_wdk: bool,
+) -> Result<(), BuildActionError> {
+    debug!(
+        "Detected WDK build {wdk_build_number}, package {package_name} supports WDK \
+         {supported_wdk}"
+    );
+    if supported_wdk.contains(wdk_build_number) {
+        return Ok(());
+    }
+    if allow_unsupported_wdk {
+        warn!(
+            "Detected WDK build {wdk_build_number} is not supported by package {package_name}, \
+             which supports WDK {supported_wdk}. Building anyway since --allow-unsupported-wdk \
+             was passed"
+        );
+        return Ok(());
+    }
+    Err(BuildActionError::UnsupportedWdk {
+        package_name: package_name.to_string(),
+        detected_build_number: wdk_build_number,
+        supported_wdk: supported_wdk.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn bounds_are_read_from_wdk_versions_and_build_numbers() {
+        let supported_wdk = SupportedWdk::from_metadata(
+            "supported-wdk",
+            &json!({ "min": "10.0.22621.0", "max": "26100" }),
+        )
+        .expect("supported WDK range should be valid");
+        assert_eq!(
+            supported_wdk,
+            SupportedWdk {
+                min: Some(22621),
+                max: Some(26100)
+            }
+        );
+        assert!(supported_wdk.contains(22621));
+        assert!(supported_wdk.contains(26100));
+        assert!(!supported_wdk.contains(22000));
+        assert!(!supported_wdk.contains(26200));
+    }
+
+    #[test]
+    fn invalid_ranges_are_rejected() {
+        for value in [
+            json!("10.0.22621.0"),
+            json!({}),
+            json!({ "min": "11.0.22621.0" }),
+            json!({ "min": "26100", "max": "22621" }),
+            json!({ "minimum": "22621" }),
+        ] {
+            assert!(
+                matches!(
+                    SupportedWdk::from_metadata("supported-wdk", &value),
+                    Err(BuildActionError::InvalidCargoWdkMetadata(_))
+                ),
+                "value: {value}"
+            );
+        }
+    }
+
+    #[test]
+    fn wdk_outside_of_range_fails_unless_allowed() {
+        let supported_wdk = SupportedWdk {
+            min: Some(22621),
+            max: None,
+        };
+        assert!(matches!(
+            check("driver", supported_wdk, 22000, false),
+            Err(BuildActionError::UnsupportedWdk {
+                detected_build_number: 22000,
+                ..
+            })
+        ));
+        assert!(check("driver", supported_wdk, 22000, true).is_ok());
+        assert!(check("driver", supported_wdk, 26100, false).is_ok());
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module contains the helpers used by the `--cert-validity-days` option
+//! of the build action to check the validity window of the test certificate
+//! before signing with it. An expired test certificate still signs the driver
+//! package, but the package then fails to install, so an expired certificate
+//! is regenerated instead of being reused.
+
+use crate::actions::build::reproducible::{SECONDS_PER_DAY, civil_date_from_unix_time};
+
+/// Label of the end of the validity window in the output of `certutil -dump`
+const NOT_AFTER_LABEL: &str = "NotAfter:";
+
+/// Validity of the test certificates generated by a build
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TestCertValidity {
+    /// Number of days a generated test certificate is valid for
+    pub days: u32,
+    /// Time of the build in seconds since the Unix epoch, which test
+    /// certificates must not have expired at
+    pub now: u64,
+}
+
+impl TestCertValidity {
+    /// Returns the end date of a test certificate generated now, in the
+    /// `MM/DD/YYYY` format expected by `makecert -e`
+    pub fn end_date(&self) -> String {
+        let (year, month, day) =
+            civil_date_from_unix_time(self.now + u64::from(self.days) * SECONDS_PER_DAY);
+        format!("{month:02}/{day:02}/{year:04}")
+    }
+
+    /// Returns `true` if a certificate that is valid until the `(year, month,
+    /// day)` date has expired, i.e. is not valid for the whole of today
+    pub fn is_expired(&self, not_after: (u64, u64, u64)) -> bool {
+        not_after <= civil_date_from_unix_time(self.now)
+    }
+}
+
+/// Parses the `(year, month, day)` date at the end of the validity window of
+/// a certificate from the output of `certutil -dump`, e.g. `NotAfter:
+/// 10/16/2027 3:04 PM`. The date is in the `M/D/YYYY` format `certutil` prints
+/// for the `en-US` locale.
+///
+/// Returns `None` if the output has no such date.
+pub fn parse_certutil_not_after(certutil_output: &str) -> Option<(u64, u64, u64)> {
+    let date = certutil_output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix(NOT_AFTER_LABEL))?
+        .split_whitespace()
+        .next()?;
+    let mut parts = date.split('/').map(str::parse::<u64>);
+    let (Some(Ok(month)), Some(Ok(day)), Some(Ok(year)), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return None;
+    };
+    ((1..=12).contains(&month) && (1..=31).contains(&day)).then_some((year, month, day))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TestCertValidity, parse_certutil_not_after};
+
+    #[test]
+    fn not_after_date_is_parsed_from_the_certutil_output() {
+        let output = "X509 Certificate:\r\nVersion: 3\r\n NotBefore: 10/16/2025 3:04 PM\r\n \
+                      NotAfter: 1/5/2027 3:04 PM\r\n\r\nSubject:\r\n    CN=WDRLocalTestCert\r\n";
+        assert_eq!(parse_certutil_not_after(output), Some((2027, 1, 5)));
+        for output in [
+            "",
+            " NotBefore: 10/16/2025 3:04 PM",
+            " NotAfter: 2027-01-05 15:04",
+            " NotAfter: 13/5/2027 3:04 PM",
+        ] {
+            assert_eq!(parse_certutil_not_after(output), None, "{output}");
+        }
+    }
+
+    #[test]
+    fn certificates_expire_at_the_end_of_their_last_valid_day() {
+        // 2026-10-16 12:00:00 UTC
+        let validity = TestCertValidity {
+            days: 30,
+            now: 1_792_152_000,
+        };
+        assert_eq!(validity.end_date(), "11/15/2026");
+        assert!(validity.is_expired((2025, 12, 31)));
+        assert!(validity.is_expired((2026, 10, 16)));
+        assert!(!validity.is_expired((2026, 10, 17)));
+        assert!(!validity.is_expired((2027, 1, 1)));
+    }
+}
@@ -4,6 +4,7 @@
 #![allow(clippy::ref_option_ref)] // This is suppressed for mockall as it generates mocks with env_vars: &Option
 use std::{
     collections::HashMap,
+    io::{Cursor, Read},
     os::windows::process::ExitStatusExt,
     path::{Path, PathBuf},
     process::{ExitStatus, Output},
@@ -17,6 +18,7 @@
 use wdk_build::{
     CpuArchitecture,
     DriverConfig,
+    TwoPartVersion,
     metadata::{TryFromCargoMetadataError, Wdk},
 };
 
@@ -29,17 +31,25 @@
 };
 use crate::{
     actions::{
+        DriverType,
         Profile,
         build::{
             BuildAction,
             BuildActionParams,
+            Sanitizer,
             SignMode,
             TargetPlatform,
-            error::BuildActionError,
+            ToolPaths,
+            WdfVersionOverrides,
+            WdkLockMode,
+            error::{BuildActionError, BuildTaskError},
+            package_settings::PackageSettings,
+            pe,
         },
         to_target_triple,
     },
     providers::error::{CommandError, FileError},
+    trace::ColorChoice,
 };
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -66,9 +76,9 @@ pub fn given_a_driver_project_when_default_values_are_provided_then_it_builds_su
         get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
 
     let cargo_build_output =
-        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile);
+        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile.clone());
 
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class)
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
         .set_up_standalone_driver_project((workspace_member, package))
         .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
         .expect_probe_target_arch_using_cargo_rustc(&cwd, target_arch, None)
@@ -84,6 +94,244 @@ pub fn given_a_driver_project_when_default_values_are_provided_then_it_builds_su
     );
 }
 
+#[test]
+pub fn given_a_driver_project_when_no_package_is_true_then_it_builds_without_packaging() {
+    // Input CLI args
+    let cwd = PathBuf::from("C:\\tmp");
+    let profile = None;
+    let verify_signature = false;
+    let sample_class = false;
+    // Driver project data
+    let driver_type = "KMDF";
+    let driver_name = "sample-kmdf";
+    let driver_version = "0.0.1";
+    let wdk_metadata = get_cargo_metadata_wdk_metadata(driver_type, 1, 33);
+    let (workspace_member, package) =
+        get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
+
+    let cargo_build_output =
+        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile.clone());
+
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
+        .with_no_package(true)
+        .set_up_standalone_driver_project((workspace_member, package))
+        .expect_default_build_task_steps(driver_name, Some(cargo_build_output));
+
+    assert_build_action_run_with_env_is_success(
+        &cwd,
+        profile,
+        None,
+        verify_signature,
+        sample_class,
+        test_build_action,
+    );
+}
+
+#[test]
+pub fn given_a_driver_project_when_log_dir_is_set_then_the_tool_output_is_written_to_the_package_log()
+ {
+    // Input CLI args
+    let cwd = PathBuf::from("C:\\tmp");
+    let log_dir = cwd.join("logs");
+    let profile = None;
+    let verify_signature = false;
+    let sample_class = false;
+    // Driver project data
+    let driver_type = "KMDF";
+    let driver_name = "sample-kmdf";
+    let driver_version = "0.0.1";
+    let wdk_metadata = get_cargo_metadata_wdk_metadata(driver_type, 1, 33);
+    let (workspace_member, package) =
+        get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
+
+    let cargo_build_output =
+        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile.clone());
+
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
+        .with_no_package(true)
+        .with_log_dir(log_dir.clone())
+        .set_up_standalone_driver_project((workspace_member, package))
+        .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
+        .expect_write_package_log(&log_dir, driver_name);
+
+    assert_build_action_run_with_env_is_success(
+        &cwd,
+        profile,
+        None,
+        verify_signature,
+        sample_class,
+        test_build_action,
+    );
+}
+
+#[test]
+pub fn given_a_prebuilt_binary_path_when_the_binary_does_not_exist_then_it_fails_without_building()
+{
+    // Input CLI args
+    let cwd = PathBuf::from("C:\\tmp");
+    let binary_path = cwd.join("msbuild").join("prebuilt.sys");
+    let verify_signature = false;
+    let sample_class = false;
+
+    let test_build_action = &TestBuildAction::new(cwd.clone(), None, None, sample_class)
+        .with_binary_path(binary_path.clone())
+        .expect_detect_wdk_build_number(25100u32)
+        .expect_file_exists(&binary_path, false);
+
+    let build_action = initialize_build_action(
+        &cwd,
+        None,
+        Some(CpuArchitecture::Amd64),
+        verify_signature,
+        sample_class,
+        test_build_action,
+    );
+    let run_result = run_build_action(build_action);
+    assert!(
+        matches!(
+            run_result,
+            Err(BuildActionError::PrebuiltBinaryNotFound(ref path)) if path == &binary_path
+        ),
+        "expected pre-built binary not found error, found: {run_result:?}"
+    );
+}
+
+#[test]
+pub fn given_a_diagnostic_bundle_path_when_the_build_fails_then_the_bundle_is_still_written() {
+    // Input CLI args
+    let cwd = PathBuf::from("C:\\tmp");
+    let binary_path = cwd.join("msbuild").join("prebuilt.sys");
+    let bundle_path = cwd.join("diagnostics").join("bundle.zip");
+    let verify_signature = false;
+    let sample_class = false;
+
+    let test_build_action = &TestBuildAction::new(cwd.clone(), None, None, sample_class)
+        .with_binary_path(binary_path.clone())
+        .with_diagnostic_bundle(bundle_path.clone())
+        .expect_detect_wdk_build_number(25100u32)
+        .expect_file_exists(&binary_path, false)
+        .expect_write_diagnostic_bundle(
+            &bundle_path,
+            &[
+                ("build.json", "\"succeeded\": false"),
+                ("wdk.txt", "WDK build number: 25100"),
+                ("errors.txt", "Pre-built driver binary"),
+            ],
+        );
+
+    let build_action = initialize_build_action(
+        &cwd,
+        None,
+        Some(CpuArchitecture::Amd64),
+        verify_signature,
+        sample_class,
+        test_build_action,
+    );
+    let run_result = run_build_action(build_action);
+    assert!(
+        matches!(run_result, Err(BuildActionError::PrebuiltBinaryNotFound(_))),
+        "expected pre-built binary not found error, found: {run_result:?}"
+    );
+}
+
+#[test]
+pub fn given_a_signtool_path_when_it_does_not_exist_then_it_fails_without_building() {
+    // Input CLI args
+    let cwd = PathBuf::from("C:\\tmp");
+    let signtool_path = PathBuf::from("C:\\tools\\signtool.exe");
+    let verify_signature = false;
+    let sample_class = false;
+
+    let test_build_action = &TestBuildAction::new(cwd.clone(), None, None, sample_class)
+        .with_tool_paths(ToolPaths {
+            signtool: Some(signtool_path.clone()),
+            ..ToolPaths::default()
+        })
+        .expect_detect_wdk_build_number(25100u32)
+        .expect_file_exists(&signtool_path, false);
+
+    let build_action = initialize_build_action(
+        &cwd,
+        None,
+        None,
+        verify_signature,
+        sample_class,
+        test_build_action,
+    );
+    let run_result = run_build_action(build_action);
+    assert!(
+        matches!(
+            run_result,
+            Err(BuildActionError::ToolPathNotFound("signtool", ref path)) if path == &signtool_path
+        ),
+        "expected tool path not found error, found: {run_result:?}"
+    );
+}
+
+#[test]
+pub fn given_a_stampinf_path_when_it_is_not_an_exe_then_it_fails_without_building() {
+    // Input CLI args
+    let cwd = PathBuf::from("C:\\tmp");
+    let stampinf_path = PathBuf::from("C:\\tools\\bin");
+    let verify_signature = false;
+    let sample_class = false;
+
+    let test_build_action = &TestBuildAction::new(cwd.clone(), None, None, sample_class)
+        .with_tool_paths(ToolPaths {
+            stampinf: Some(stampinf_path.clone()),
+            ..ToolPaths::default()
+        })
+        .expect_detect_wdk_build_number(25100u32)
+        .expect_file_exists(&stampinf_path, true);
+
+    let build_action = initialize_build_action(
+        &cwd,
+        None,
+        None,
+        verify_signature,
+        sample_class,
+        test_build_action,
+    );
+    let run_result = run_build_action(build_action);
+    assert!(
+        matches!(
+            run_result,
+            Err(BuildActionError::ToolPathNotExecutable("stampinf", ref path)) if path == &stampinf_path
+        ),
+        "expected tool path not executable error, found: {run_result:?}"
+    );
+}
+
+#[test]
+pub fn given_a_kmdf_version_when_it_is_not_installed_with_the_wdk_then_it_fails_without_building() {
+    // Input CLI args
+    let cwd = PathBuf::from("C:\\tmp");
+    let verify_signature = false;
+    let sample_class = false;
+
+    let test_build_action = &TestBuildAction::new(cwd.clone(), None, None, sample_class)
+        .with_wdf_versions(WdfVersionOverrides {
+            kmdf: Some("1.35".parse().unwrap()),
+            umdf: None,
+        })
+        .expect_detect_wdk_build_number(25100u32)
+        .expect_detect_kmdf_versions(&[(1, 31), (1, 33)]);
+
+    let build_action = initialize_build_action(
+        &cwd,
+        None,
+        None,
+        verify_signature,
+        sample_class,
+        test_build_action,
+    );
+    let run_result = run_build_action(build_action);
+    assert_eq!(
+        run_result.unwrap_err().to_string(),
+        "KMDF 1.35 is not available in the WDK, available versions: 1.31, 1.33"
+    );
+}
+
 #[test]
 pub fn given_a_driver_project_when_profile_is_release_then_it_builds_successfully() {
     // Input CLI args
@@ -102,8 +350,80 @@ pub fn given_a_driver_project_when_profile_is_release_then_it_builds_successfull
         get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
 
     let cargo_build_output =
-        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile);
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class)
+        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile.clone());
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
+        .set_up_standalone_driver_project((workspace_member, package))
+        .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
+        .expect_probe_target_arch_using_cargo_rustc(&cwd, target_arch, None)
+        .expect_default_package_task_steps(driver_name, target_arch, verify_signature);
+
+    assert_build_action_run_with_env_is_success(
+        &cwd,
+        profile,
+        None,
+        verify_signature,
+        sample_class,
+        test_build_action,
+    );
+}
+
+#[test]
+pub fn given_a_driver_project_when_profile_is_dev_then_artifacts_are_located_in_debug_dir_and_it_builds_successfully()
+ {
+    // Input CLI args
+    let cwd = PathBuf::from("C:\\tmp");
+    let profile = Some(Profile::Dev);
+    let target_arch = CpuArchitecture::Amd64;
+    let verify_signature = false;
+    let sample_class = false;
+
+    // Driver project data
+    let driver_type = "KMDF";
+    let driver_name = "sample-kmdf";
+    let driver_version = "0.0.1";
+    let wdk_metadata = get_cargo_metadata_wdk_metadata(driver_type, 1, 33);
+    let (workspace_member, package) =
+        get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
+
+    let cargo_build_output =
+        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile.clone());
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
+        .set_up_standalone_driver_project((workspace_member, package))
+        .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
+        .expect_probe_target_arch_using_cargo_rustc(&cwd, target_arch, None)
+        .expect_default_package_task_steps(driver_name, target_arch, verify_signature);
+
+    assert_build_action_run_with_env_is_success(
+        &cwd,
+        profile,
+        None,
+        verify_signature,
+        sample_class,
+        test_build_action,
+    );
+}
+
+#[test]
+pub fn given_a_driver_project_when_profile_is_custom_then_artifacts_are_located_in_profile_dir_and_it_builds_successfully()
+ {
+    // Input CLI args
+    let cwd = PathBuf::from("C:\\tmp");
+    let profile = Some(Profile::Custom("release-lto".to_string()));
+    let target_arch = CpuArchitecture::Amd64;
+    let verify_signature = false;
+    let sample_class = false;
+
+    // Driver project data
+    let driver_type = "KMDF";
+    let driver_name = "sample-kmdf";
+    let driver_version = "0.0.1";
+    let wdk_metadata = get_cargo_metadata_wdk_metadata(driver_type, 1, 33);
+    let (workspace_member, package) =
+        get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
+
+    let cargo_build_output =
+        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile.clone());
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
         .set_up_standalone_driver_project((workspace_member, package))
         .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
         .expect_probe_target_arch_using_cargo_rustc(&cwd, target_arch, None)
@@ -141,13 +461,17 @@ pub fn given_a_driver_project_when_target_arch_is_arm64_then_it_builds_successfu
         driver_version,
         &cwd,
         Some(&to_target_triple(target_arch)),
-        profile,
+        profile.clone(),
     );
-    let test_build_action =
-        &TestBuildAction::new(cwd.clone(), profile, Some(target_arch), sample_class)
-            .set_up_standalone_driver_project((workspace_member, package))
-            .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
-            .expect_default_package_task_steps(driver_name, target_arch, verify_signature);
+    let test_build_action = &TestBuildAction::new(
+        cwd.clone(),
+        profile.clone(),
+        Some(target_arch),
+        sample_class,
+    )
+    .set_up_standalone_driver_project((workspace_member, package))
+    .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
+    .expect_default_package_task_steps(driver_name, target_arch, verify_signature);
 
     assert_build_action_run_with_env_is_success(
         &cwd,
@@ -182,14 +506,18 @@ pub fn given_a_driver_project_when_profile_is_release_and_target_arch_is_arm64_t
         driver_version,
         &cwd,
         Some(&to_target_triple(target_arch)),
-        profile,
+        profile.clone(),
     );
 
-    let test_build_action =
-        &TestBuildAction::new(cwd.clone(), profile, Some(target_arch), sample_class)
-            .set_up_standalone_driver_project((workspace_member, package))
-            .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
-            .expect_default_package_task_steps(driver_name, target_arch, verify_signature);
+    let test_build_action = &TestBuildAction::new(
+        cwd.clone(),
+        profile.clone(),
+        Some(target_arch),
+        sample_class,
+    )
+    .set_up_standalone_driver_project((workspace_member, package))
+    .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
+    .expect_default_package_task_steps(driver_name, target_arch, verify_signature);
 
     assert_build_action_run_with_env_is_success(
         &cwd,
@@ -219,8 +547,8 @@ pub fn given_a_driver_project_when_sample_class_is_true_then_it_builds_successfu
         get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
 
     let cargo_build_output =
-        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile);
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class)
+        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile.clone());
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
         .set_up_standalone_driver_project((workspace_member, package))
         .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
         .expect_probe_target_arch_using_cargo_rustc(&cwd, target_arch, None)
@@ -255,8 +583,8 @@ pub fn given_a_driver_project_when_verify_signature_is_true_then_it_builds_succe
         get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
 
     let cargo_build_output =
-        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile);
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class)
+        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile.clone());
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
         .set_up_standalone_driver_project((workspace_member, package))
         .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
         .expect_probe_target_arch_using_cargo_rustc(&cwd, target_arch, None)
@@ -291,9 +619,9 @@ pub fn given_a_driver_project_when_sign_mode_is_off_then_signing_and_verificatio
         get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
 
     let cargo_build_output =
-        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile);
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class)
-        .with_sign_mode(SignMode::Off)
+        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile.clone());
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
+        .with_sign_mode(Some(SignMode::Off))
         .set_up_standalone_driver_project((workspace_member, package))
         .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
         .expect_probe_target_arch_using_cargo_rustc(&cwd, target_arch, None)
@@ -327,9 +655,9 @@ pub fn given_a_driver_project_when_locked_is_set_then_it_is_forwarded_to_cargo_i
         get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
 
     let cargo_build_output =
-        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile);
+        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile.clone());
 
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class)
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
         .with_locked(true)
         .set_up_standalone_driver_project((workspace_member, package))
         .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
@@ -347,12 +675,11 @@ pub fn given_a_driver_project_when_locked_is_set_then_it_is_forwarded_to_cargo_i
 }
 
 #[test]
-pub fn given_a_driver_project_when_self_signed_exists_then_it_should_skip_calling_makecert() {
+pub fn given_a_driver_project_when_clippy_fails_then_it_fails_without_building() {
     // Input CLI args
     let cwd = PathBuf::from("C:\\tmp");
     let profile = None;
-    let target_arch = CpuArchitecture::Amd64;
-    let verify_signature = true;
+    let verify_signature = false;
     let sample_class = false;
 
     // Driver project data
@@ -362,21 +689,214 @@ pub fn given_a_driver_project_when_self_signed_exists_then_it_should_skip_callin
     let wdk_metadata = get_cargo_metadata_wdk_metadata(driver_type, 1, 33);
     let (workspace_member, package) =
         get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
-    let expected_certmgr_output = Output {
-        status: ExitStatus::default(),
-        stdout: r"==============Certificate # 1 ==========
-                    Subject::
-                    [0,0] 2.5.4.3 (CN) WDRLocalTestCert
-                    Issuer::
-                    [0,0] 2.5.4.3 (CN) WDRLocalTestCert
-                    SerialNumber::
-                    5E 04 0D 63 35 20 76 A5 4A E1 96 BF CF 01 0F 96
-                    SHA1 Thumbprint::
-                        FB972842 C63CD369 E07D0C71 88E17921 B5813C71
-                    MD5 Thumbprint::
-                        832B3F18 707EA3F6 54465207 345A93F1
-                    Provider Type:: 1 Provider Name:: Microsoft Strong Cryptographic Provider Container: 68f79a6e-6afa-4ec7-be5b-16d6656edd3f KeySpec: 2
-                    NotBefore::
+
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
+        .with_clippy(true)
+        .set_up_standalone_driver_project((workspace_member, package))
+        .expect_detect_wdk_build_number(25100u32)
+        .expect_root_manifest_exists(&cwd, true)
+        .expect_failing_cargo_clippy(driver_name, &cwd);
+
+    let build_action = initialize_build_action(
+        &cwd,
+        profile.as_ref(),
+        None,
+        verify_signature,
+        sample_class,
+        test_build_action,
+    );
+    let run_result = run_build_action(build_action);
+    assert!(
+        matches!(
+            run_result,
+            Err(BuildActionError::BuildTask(BuildTaskError::CargoClippy(_)))
+        ),
+        "expected cargo clippy error, found: {run_result:?}"
+    );
+}
+
+#[test]
+pub fn given_a_driver_project_when_its_pre_build_command_fails_then_it_fails_without_building() {
+    // Input CLI args
+    let cwd = PathBuf::from("C:\\tmp");
+    let profile = None;
+    let verify_signature = false;
+    let sample_class = false;
+
+    // Driver project data
+    let driver_type = "KMDF";
+    let driver_name = "sample-kmdf";
+    let driver_version = "0.0.1";
+    let wdk_metadata = get_cargo_metadata_wdk_metadata(driver_type, 1, 33);
+    let mut package_metadata: serde_json::Value =
+        serde_json::from_str(&wdk_metadata.0).expect("Failed to parse wdk metadata");
+    package_metadata["cargo-wdk"] = serde_json::json!({ "pre-build": ["codegen", "spec.json"] });
+    let package_metadata = TestWdkMetadata(package_metadata.to_string());
+
+    for no_hooks in [false, true] {
+        let (workspace_member, package) = get_cargo_metadata_package(
+            &cwd,
+            driver_name,
+            driver_version,
+            Some(&package_metadata),
+        );
+        let mut test_build_action =
+            TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
+                .with_no_hooks(no_hooks)
+                .set_up_standalone_driver_project((workspace_member, package))
+                .expect_detect_wdk_build_number(25100u32)
+                .expect_root_manifest_exists(&cwd, true);
+        if no_hooks {
+            // Clippy fails the build right after the skipped pre-build command
+            test_build_action = test_build_action
+                .with_clippy(true)
+                .expect_failing_cargo_clippy(driver_name, &cwd);
+        } else {
+            test_build_action
+                .mock_run_command
+                .expect_run()
+                .withf(
+                    move |command: &str,
+                          args: &[&str],
+                          _env_vars: &Option<&HashMap<&str, &str>>,
+                          _working_dir: &Option<&Path>|
+                          -> bool { command == "codegen" && args == ["spec.json"] },
+                )
+                .once()
+                .returning(|command, args, _, _| {
+                    Err(CommandError::from_output(
+                        command,
+                        args,
+                        &Output {
+                            status: ExitStatus::from_raw(1),
+                            stdout: vec![],
+                            stderr: b"spec.json not found".to_vec(),
+                        },
+                    ))
+                });
+        }
+
+        let build_action = initialize_build_action(
+            &cwd,
+            profile.as_ref(),
+            None,
+            verify_signature,
+            sample_class,
+            &test_build_action,
+        );
+        let run_result = run_build_action(build_action);
+        if no_hooks {
+            assert!(
+                matches!(
+                    run_result,
+                    Err(BuildActionError::BuildTask(BuildTaskError::CargoClippy(_)))
+                ),
+                "expected cargo clippy error, found: {run_result:?}"
+            );
+        } else {
+            assert!(
+                matches!(
+                    run_result,
+                    Err(BuildActionError::BuildTask(BuildTaskError::PreBuildCommand(
+                        ref package_name,
+                        _
+                    ))) if package_name == driver_name
+                ),
+                "expected pre-build command error, found: {run_result:?}"
+            );
+        }
+    }
+}
+
+#[test]
+pub fn given_verify_signature_when_signing_is_turned_off_by_any_source_then_it_fails() {
+    let cwd = PathBuf::from("C:\\tmp");
+    let profile = Some(Profile::Release);
+    let package_name = "sample-kmdf";
+    let test_sign_mode = SignMode::Test {
+        verify_signature: false,
+    };
+    let verified_test_sign_mode = SignMode::Test {
+        verify_signature: true,
+    };
+
+    // (--sign-mode, --verify-signature, `sign-mode` setting, expected result)
+    let cases = [
+        (None, false, None, Ok(test_sign_mode)),
+        (None, false, Some(SignMode::Off), Ok(SignMode::Off)),
+        (None, true, None, Ok(verified_test_sign_mode)),
+        (None, true, Some(test_sign_mode), Ok(verified_test_sign_mode)),
+        (None, true, Some(SignMode::Off), Err("packaging setting of the release profile")),
+        (Some(test_sign_mode), true, Some(SignMode::Off), Ok(verified_test_sign_mode)),
+        (Some(SignMode::Off), false, Some(test_sign_mode), Ok(SignMode::Off)),
+        (Some(SignMode::Off), true, None, Err("`--sign-mode=off`")),
+    ];
+    for (sign_mode, verify_signature, setting, expected) in cases {
+        let test_build_action = TestBuildAction::new(cwd.clone(), profile.clone(), None, false)
+            .with_sign_mode(sign_mode);
+        let build_action = initialize_build_action(
+            &cwd,
+            profile.as_ref(),
+            None,
+            verify_signature,
+            false,
+            &test_build_action,
+        )
+        .expect("Failed to init build action");
+        let package_settings = PackageSettings {
+            sign_mode: setting,
+            ..PackageSettings::default()
+        };
+
+        let result = build_action.sign_mode_for_packaging(package_name, &package_settings);
+        let case = format!("{sign_mode:?}, {verify_signature}, {setting:?}");
+        match expected {
+            Ok(expected) => assert_eq!(result.ok(), Some(expected), "{case}"),
+            Err(expected_disabled_by) => assert!(
+                matches!(
+                    &result,
+                    Err(BuildActionError::VerifySignatureWithoutSigning {
+                        package_name: name,
+                        disabled_by,
+                    }) if name == package_name && disabled_by.contains(expected_disabled_by)
+                ),
+                "{case}: {result:?}"
+            ),
+        }
+    }
+}
+
+#[test]
+pub fn given_a_driver_project_when_self_signed_exists_then_it_should_skip_calling_makecert() {
+    // Input CLI args
+    let cwd = PathBuf::from("C:\\tmp");
+    let profile = None;
+    let target_arch = CpuArchitecture::Amd64;
+    let verify_signature = true;
+    let sample_class = false;
+
+    // Driver project data
+    let driver_type = "KMDF";
+    let driver_name = "sample-kmdf";
+    let driver_version = "0.0.1";
+    let wdk_metadata = get_cargo_metadata_wdk_metadata(driver_type, 1, 33);
+    let (workspace_member, package) =
+        get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
+    let expected_certmgr_output = Output {
+        status: ExitStatus::default(),
+        stdout: r"==============Certificate # 1 ==========
+                    Subject::
+                    [0,0] 2.5.4.3 (CN) WDRLocalTestCert
+                    Issuer::
+                    [0,0] 2.5.4.3 (CN) WDRLocalTestCert
+                    SerialNumber::
+                    5E 04 0D 63 35 20 76 A5 4A E1 96 BF CF 01 0F 96
+                    SHA1 Thumbprint::
+                        FB972842 C63CD369 E07D0C71 88E17921 B5813C71
+                    MD5 Thumbprint::
+                        832B3F18 707EA3F6 54465207 345A93F1
+                    Provider Type:: 1 Provider Name:: Microsoft Strong Cryptographic Provider Container: 68f79a6e-6afa-4ec7-be5b-16d6656edd3f KeySpec: 2
+                    NotBefore::
                     Tue Jan 28 13:51:04 2025
                     NotAfter::
                     Sun Jan 01 05:29:59 2040
@@ -394,14 +914,16 @@ pub fn given_a_driver_project_when_self_signed_exists_then_it_should_skip_callin
     };
 
     let cargo_build_output =
-        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile);
+        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile.clone());
 
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class)
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
         .set_up_standalone_driver_project((workspace_member, package))
         .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
         .expect_probe_target_arch_using_cargo_rustc(&cwd, target_arch, None)
         .expect_final_package_dir_exists(driver_name, &cwd, true)
         .expect_inx_file_exists(driver_name, &cwd, true)
+        .expect_read_inx_file(driver_name, &cwd)
+        .expect_read_driver_binary(driver_name, &cwd, target_arch)
         .expect_rename_driver_binary_dll_to_sys(driver_name, &cwd)
         .expect_copy_driver_binary_sys_to_package_folder(driver_name, &cwd, true)
         .expect_copy_pdb_file_to_package_folder(driver_name, &cwd, true)
@@ -409,6 +931,7 @@ pub fn given_a_driver_project_when_self_signed_exists_then_it_should_skip_callin
         .expect_copy_map_file_to_package_folder(driver_name, &cwd, true)
         .expect_stampinf(driver_name, &cwd, target_arch, None)
         .expect_inf2cat(driver_name, &cwd, target_arch, None)
+        .expect_cat_file_exists(driver_name, &cwd, true)
         .expect_self_signed_cert_file_exists(&cwd, false)
         .expect_certmgr_exists_check(Some(expected_certmgr_output))
         .expect_certmgr_create_cert_from_store(&cwd, Some(expected_create_cert_output))
@@ -417,7 +940,9 @@ pub fn given_a_driver_project_when_self_signed_exists_then_it_should_skip_callin
         .expect_signtool_sign_cat_file(driver_name, &cwd, None)
         .expect_infverif(driver_name, &cwd, None)
         .expect_signtool_verify_driver_binary_sys_file(driver_name, &cwd, None)
-        .expect_signtool_verify_cat_file(driver_name, &cwd, None);
+        .expect_signtool_verify_cat_file(driver_name, &cwd, None)
+        .expect_signtool_verify_catalog_member(driver_name, &cwd, None)
+        .expect_certutil_dump_cert();
 
     assert_build_action_run_with_env_is_success(
         &cwd,
@@ -448,15 +973,17 @@ pub fn given_a_driver_project_when_final_package_dir_exists_then_it_should_skip_
     let expected_certmgr_output = get_certmgr_success_output();
 
     let cargo_build_output =
-        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile);
+        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile.clone());
 
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class)
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
         .set_up_standalone_driver_project((workspace_member, package))
         .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
         .expect_probe_target_arch_using_cargo_rustc(&cwd, target_arch, None)
         .expect_final_package_dir_exists(driver_name, &cwd, false)
         .expect_dir_created(driver_name, &cwd, true)
         .expect_inx_file_exists(driver_name, &cwd, true)
+        .expect_read_inx_file(driver_name, &cwd)
+        .expect_read_driver_binary(driver_name, &cwd, target_arch)
         .expect_rename_driver_binary_dll_to_sys(driver_name, &cwd)
         .expect_copy_driver_binary_sys_to_package_folder(driver_name, &cwd, true)
         .expect_copy_pdb_file_to_package_folder(driver_name, &cwd, true)
@@ -464,6 +991,7 @@ pub fn given_a_driver_project_when_final_package_dir_exists_then_it_should_skip_
         .expect_copy_map_file_to_package_folder(driver_name, &cwd, true)
         .expect_stampinf(driver_name, &cwd, target_arch, None)
         .expect_inf2cat(driver_name, &cwd, target_arch, None)
+        .expect_cat_file_exists(driver_name, &cwd, true)
         .expect_self_signed_cert_file_exists(&cwd, false)
         .expect_certmgr_exists_check(Some(expected_certmgr_output))
         .expect_makecert(&cwd, None)
@@ -500,9 +1028,9 @@ pub fn given_a_driver_project_when_inx_file_do_not_exist_then_package_should_fai
         get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
 
     let cargo_build_output =
-        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile);
+        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile.clone());
 
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class)
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
         .set_up_standalone_driver_project((workspace_member, package))
         .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
         .expect_probe_target_arch_using_cargo_rustc(&cwd, target_arch, None)
@@ -516,22 +1044,429 @@ pub fn given_a_driver_project_when_inx_file_do_not_exist_then_package_should_fai
         sample_class,
         test_build_action,
     );
-    assert!(build_action.is_ok());
-    let run_result = build_action.expect("Failed to init build action").run();
-
-    assert!(matches!(
-        run_result.as_ref().expect_err("expected error"),
-        BuildActionError::OneOrMoreWorkspaceMembersFailedToBuild(_)
-    ));
+    assert!(build_action.is_ok());
+    let run_result = build_action.expect("Failed to init build action").run();
+
+    assert!(matches!(
+        run_result.as_ref().expect_err("expected error"),
+        BuildActionError::OneOrMoreWorkspaceMembersFailedToBuild(_)
+    ));
+}
+
+#[test]
+pub fn given_a_driver_project_when_copy_of_an_artifact_fails_then_the_package_should_fail() {
+    // Input CLI args
+    let cwd = PathBuf::from("C:\\tmp");
+    let profile = None;
+    let target_arch = CpuArchitecture::Amd64;
+    let verify_signature = true;
+    let sample_class = false;
+
+    // Driver project data
+    let driver_type = "KMDF";
+    let driver_name = "sample-kmdf";
+    let driver_version = "0.0.1";
+    let wdk_metadata = get_cargo_metadata_wdk_metadata(driver_type, 1, 33);
+    let (workspace_member, package) =
+        get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
+
+    let cargo_build_output = create_cargo_build_output_json(
+        driver_name,
+        driver_version,
+        &cwd,
+        Some(&to_target_triple(target_arch)),
+        profile.clone(),
+    );
+
+    let test_build_action = &TestBuildAction::new(
+        cwd.clone(),
+        profile.clone(),
+        Some(target_arch),
+        sample_class,
+    )
+    .set_up_standalone_driver_project((workspace_member, package))
+    .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
+    .expect_final_package_dir_exists(driver_name, &cwd, true)
+    .expect_inx_file_exists(driver_name, &cwd, true)
+    .expect_read_inx_file(driver_name, &cwd)
+    .expect_read_driver_binary(driver_name, &cwd, target_arch)
+    .expect_rename_driver_binary_dll_to_sys(driver_name, &cwd)
+    .expect_copy_driver_binary_sys_to_package_folder(driver_name, &cwd, false);
+
+    let build_action = initialize_build_action(
+        &cwd,
+        profile.as_ref(),
+        Some(target_arch),
+        verify_signature,
+        sample_class,
+        test_build_action,
+    );
+    assert!(build_action.is_ok());
+    let run_result = build_action.expect("Failed to init build action").run();
+
+    assert!(matches!(
+        run_result.as_ref().expect_err("expected error"),
+        BuildActionError::OneOrMoreWorkspaceMembersFailedToBuild(_)
+    ));
+}
+
+#[test]
+pub fn given_a_driver_project_when_stampinf_command_execution_fails_then_package_should_fail() {
+    // Input CLI args
+    let cwd = PathBuf::from("C:\\tmp");
+    let profile = None;
+    let target_arch = CpuArchitecture::Amd64;
+    let verify_signature = true;
+    let sample_class = false;
+
+    // Driver project data
+    let driver_type = "KMDF";
+    let driver_name = "sample-kmdf";
+    let driver_version = "0.0.1";
+    let wdk_metadata = get_cargo_metadata_wdk_metadata(driver_type, 1, 33);
+    let (workspace_member, package) =
+        get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
+
+    let expected_stampinf_output = Output {
+        status: ExitStatus::from_raw(1),
+        stdout: vec![],
+        stderr: vec![],
+    };
+
+    let cargo_build_output =
+        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile.clone());
+
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
+        .set_up_standalone_driver_project((workspace_member, package))
+        .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
+        .expect_probe_target_arch_using_cargo_rustc(&cwd, target_arch, None)
+        .expect_final_package_dir_exists(driver_name, &cwd, true)
+        .expect_inx_file_exists(driver_name, &cwd, true)
+        .expect_read_inx_file(driver_name, &cwd)
+        .expect_read_driver_binary(driver_name, &cwd, target_arch)
+        .expect_rename_driver_binary_dll_to_sys(driver_name, &cwd)
+        .expect_copy_driver_binary_sys_to_package_folder(driver_name, &cwd, true)
+        .expect_copy_pdb_file_to_package_folder(driver_name, &cwd, true)
+        .expect_copy_inx_file_to_package_folder(driver_name, &cwd, true, &cwd)
+        .expect_copy_map_file_to_package_folder(driver_name, &cwd, true)
+        .expect_stampinf(
+            driver_name,
+            &cwd,
+            target_arch,
+            Some(expected_stampinf_output),
+        );
+
+    let build_action = initialize_build_action(
+        &cwd,
+        profile.as_ref(),
+        None,
+        verify_signature,
+        sample_class,
+        test_build_action,
+    );
+    assert!(build_action.is_ok());
+    let run_result = run_build_action(build_action);
+
+    assert!(matches!(
+        run_result.as_ref().expect_err("expected error"),
+        BuildActionError::OneOrMoreWorkspaceMembersFailedToBuild(_)
+    ));
+}
+
+#[test]
+pub fn given_a_driver_project_when_inf2cat_command_execution_fails_then_package_should_fail() {
+    // Input CLI args
+    let cwd = PathBuf::from("C:\\tmp");
+    let profile = None;
+    let target_arch = CpuArchitecture::Amd64;
+    let verify_signature = true;
+    let sample_class = false;
+
+    // Driver project data
+    let driver_type = "KMDF";
+    let driver_name = "sample-kmdf";
+    let driver_version = "0.0.1";
+    let wdk_metadata = get_cargo_metadata_wdk_metadata(driver_type, 1, 33);
+    let (workspace_member, package) =
+        get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
+
+    let expected_inf2cat_output = Output {
+        status: ExitStatus::from_raw(1),
+        stdout: vec![],
+        stderr: vec![],
+    };
+
+    let cargo_build_output =
+        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile.clone());
+
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
+        .set_up_standalone_driver_project((workspace_member, package))
+        .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
+        .expect_probe_target_arch_using_cargo_rustc(&cwd, target_arch, None)
+        .expect_final_package_dir_exists(driver_name, &cwd, true)
+        .expect_inx_file_exists(driver_name, &cwd, true)
+        .expect_read_inx_file(driver_name, &cwd)
+        .expect_read_driver_binary(driver_name, &cwd, target_arch)
+        .expect_rename_driver_binary_dll_to_sys(driver_name, &cwd)
+        .expect_copy_driver_binary_sys_to_package_folder(driver_name, &cwd, true)
+        .expect_copy_pdb_file_to_package_folder(driver_name, &cwd, true)
+        .expect_copy_inx_file_to_package_folder(driver_name, &cwd, true, &cwd)
+        .expect_copy_map_file_to_package_folder(driver_name, &cwd, true)
+        .expect_stampinf(driver_name, &cwd, target_arch, None)
+        .expect_inf2cat(
+            driver_name,
+            &cwd,
+            target_arch,
+            Some(expected_inf2cat_output),
+        );
+
+    let build_action = initialize_build_action(
+        &cwd,
+        profile.as_ref(),
+        None,
+        verify_signature,
+        sample_class,
+        test_build_action,
+    );
+    assert!(build_action.is_ok());
+    let run_result = run_build_action(build_action);
+
+    assert!(matches!(
+        run_result.as_ref().expect_err("expected error"),
+        BuildActionError::OneOrMoreWorkspaceMembersFailedToBuild(_)
+    ));
+}
+
+#[test]
+pub fn given_a_driver_project_when_inf2cat_generates_no_cat_file_then_package_should_fail() {
+    // Input CLI args
+    let cwd = PathBuf::from("C:\\tmp");
+    let profile = None;
+    let target_arch = CpuArchitecture::Amd64;
+    let verify_signature = true;
+    let sample_class = false;
+
+    // Driver project data
+    let driver_type = "KMDF";
+    let driver_name = "sample-kmdf";
+    let driver_version = "0.0.1";
+    let wdk_metadata = get_cargo_metadata_wdk_metadata(driver_type, 1, 33);
+    let (workspace_member, package) =
+        get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
+
+    let cargo_build_output =
+        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile.clone());
+
+    // inf2cat succeeds but, e.g. for an INF without files to catalog, does not
+    // generate the cat file
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
+        .set_up_standalone_driver_project((workspace_member, package))
+        .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
+        .expect_probe_target_arch_using_cargo_rustc(&cwd, target_arch, None)
+        .expect_final_package_dir_exists(driver_name, &cwd, true)
+        .expect_inx_file_exists(driver_name, &cwd, true)
+        .expect_read_inx_file(driver_name, &cwd)
+        .expect_read_driver_binary(driver_name, &cwd, target_arch)
+        .expect_rename_driver_binary_dll_to_sys(driver_name, &cwd)
+        .expect_copy_driver_binary_sys_to_package_folder(driver_name, &cwd, true)
+        .expect_copy_pdb_file_to_package_folder(driver_name, &cwd, true)
+        .expect_copy_inx_file_to_package_folder(driver_name, &cwd, true, &cwd)
+        .expect_copy_map_file_to_package_folder(driver_name, &cwd, true)
+        .expect_stampinf(driver_name, &cwd, target_arch, None)
+        .expect_inf2cat(driver_name, &cwd, target_arch, None)
+        .expect_cat_file_exists(driver_name, &cwd, false);
+
+    let build_action = initialize_build_action(
+        &cwd,
+        profile.as_ref(),
+        None,
+        verify_signature,
+        sample_class,
+        test_build_action,
+    );
+    assert!(build_action.is_ok());
+    let run_result = run_build_action(build_action);
+
+    assert!(matches!(
+        run_result.as_ref().expect_err("expected error"),
+        BuildActionError::OneOrMoreWorkspaceMembersFailedToBuild(_)
+    ));
+}
+
+#[test]
+pub fn given_a_driver_project_when_driver_type_does_not_match_wdk_metadata_then_it_fails_without_building()
+ {
+    // Input CLI args
+    let workspace_root_dir = PathBuf::from("C:\\tmp");
+    let cwd = workspace_root_dir.join("sample-kmdf");
+    let profile = None;
+    let verify_signature = false;
+    let sample_class = false;
+
+    // Driver project data
+    let driver_type = "KMDF";
+    let driver_name = "sample-kmdf";
+    let driver_version = "0.0.1";
+    let wdk_metadata = get_cargo_metadata_wdk_metadata(driver_type, 1, 33);
+    let (workspace_member, package) =
+        get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
+
+    // The driver type is checked before cargo build runs
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
+        .with_driver_type(DriverType::Umdf)
+        .set_up_workspace_with_multiple_driver_projects(
+            &workspace_root_dir,
+            Some(wdk_metadata),
+            vec![(workspace_member, package)],
+        )
+        .expect_detect_wdk_build_number(25100u32)
+        .expect_root_manifest_exists(&cwd, true);
+
+    let build_action = initialize_build_action(
+        &cwd,
+        profile.as_ref(),
+        None,
+        verify_signature,
+        sample_class,
+        test_build_action,
+    );
+    let run_result = run_build_action(build_action);
+    assert!(
+        matches!(
+            run_result,
+            Err(BuildActionError::DriverTypeMismatch {
+                ref package_name,
+                expected: DriverType::Umdf,
+                found: DriverType::Kmdf,
+            }) if package_name == driver_name
+        ),
+        "expected driver type mismatch error, found: {run_result:?}"
+    );
+}
+
+#[test]
+pub fn given_a_driver_project_when_its_wdk_metadata_declares_kmdf_and_umdf_then_it_fails_without_building()
+ {
+    // Input CLI args
+    let workspace_root_dir = PathBuf::from("C:\\tmp");
+    let cwd = workspace_root_dir.join("sample-kmdf");
+    let profile = None;
+    let verify_signature = false;
+    let sample_class = false;
+
+    // Driver project data, with the UMDF keys of the driver it was copied from
+    let driver_name = "sample-kmdf";
+    let driver_version = "0.0.1";
+    let wdk_metadata = TestWdkMetadata(
+        r#"
+        {
+            "wdk": {
+                "driver-model": {
+                    "driver-type": "KMDF",
+                    "kmdf-version-major": 1,
+                    "target-kmdf-version-minor": 33,
+                    "umdf-version-major": 2
+                }
+            }
+        }
+    "#
+        .to_string(),
+    );
+    let (workspace_member, package) =
+        get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
+
+    // The declarations are checked before the WDK metadata is parsed and
+    // cargo build runs
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
+        .set_up_workspace_with_multiple_driver_projects(
+            &workspace_root_dir,
+            None,
+            vec![(workspace_member, package)],
+        )
+        .expect_detect_wdk_build_number(25100u32)
+        .expect_root_manifest_exists(&cwd, true);
+
+    let build_action = initialize_build_action(
+        &cwd,
+        profile.as_ref(),
+        None,
+        verify_signature,
+        sample_class,
+        test_build_action,
+    );
+    let run_result = run_build_action(build_action);
+    assert!(
+        matches!(
+            run_result,
+            Err(BuildActionError::ConflictingDriverTypes {
+                ref declared_in,
+                ref keys,
+            }) if declared_in == "Package sample-kmdf"
+                && keys == "driver-type = \"KMDF\", kmdf-version-major, \
+                            target-kmdf-version-minor, umdf-version-major"
+        ),
+        "expected conflicting driver types error, found: {run_result:?}"
+    );
+}
+
+#[test]
+pub fn given_a_kernel_mode_driver_project_when_a_sanitizer_is_given_then_it_fails_without_building()
+ {
+    // Input CLI args
+    let workspace_root_dir = PathBuf::from("C:\\tmp");
+    let cwd = workspace_root_dir.join("sample-kmdf");
+    let profile = None;
+    let verify_signature = false;
+    let sample_class = false;
+
+    // Driver project data
+    let driver_type = "KMDF";
+    let driver_name = "sample-kmdf";
+    let driver_version = "0.0.1";
+    let wdk_metadata = get_cargo_metadata_wdk_metadata(driver_type, 1, 33);
+    let (workspace_member, package) =
+        get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
+
+    // The driver type is checked before cargo build runs
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
+        .with_sanitizer(Sanitizer::Address)
+        .set_up_workspace_with_multiple_driver_projects(
+            &workspace_root_dir,
+            Some(wdk_metadata),
+            vec![(workspace_member, package)],
+        )
+        .expect_detect_wdk_build_number(25100u32)
+        .expect_root_manifest_exists(&cwd, true);
+
+    let build_action = initialize_build_action(
+        &cwd,
+        profile.as_ref(),
+        None,
+        verify_signature,
+        sample_class,
+        test_build_action,
+    );
+    let run_result = run_build_action(build_action);
+    assert!(
+        matches!(
+            run_result,
+            Err(BuildActionError::SanitizerForKernelModeDriver {
+                ref package_name,
+                driver_type: DriverType::Kmdf,
+                sanitizer: Sanitizer::Address,
+            }) if package_name == driver_name
+        ),
+        "expected sanitizer for kernel-mode driver error, found: {run_result:?}"
+    );
 }
 
 #[test]
-pub fn given_a_driver_project_when_copy_of_an_artifact_fails_then_the_package_should_fail() {
+pub fn given_a_multi_arch_package_when_a_checked_variant_is_set_then_it_fails_without_building() {
     // Input CLI args
-    let cwd = PathBuf::from("C:\\tmp");
+    let workspace_root_dir = PathBuf::from("C:\\tmp");
+    let cwd = workspace_root_dir.join("sample-kmdf");
     let profile = None;
-    let target_arch = CpuArchitecture::Amd64;
-    let verify_signature = true;
+    let verify_signature = false;
     let sample_class = false;
 
     // Driver project data
@@ -539,50 +1474,53 @@ pub fn given_a_driver_project_when_copy_of_an_artifact_fails_then_the_package_sh
     let driver_name = "sample-kmdf";
     let driver_version = "0.0.1";
     let wdk_metadata = get_cargo_metadata_wdk_metadata(driver_type, 1, 33);
-    let (workspace_member, package) =
-        get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
-
-    let cargo_build_output = create_cargo_build_output_json(
+    let mut package_metadata: serde_json::Value =
+        serde_json::from_str(&wdk_metadata.0).expect("Failed to parse wdk metadata");
+    package_metadata["cargo-wdk"] = serde_json::json!({ "checked": true });
+    let (workspace_member, package) = get_cargo_metadata_package(
+        &cwd,
         driver_name,
         driver_version,
-        &cwd,
-        Some(&to_target_triple(target_arch)),
-        profile,
+        Some(&TestWdkMetadata(package_metadata.to_string())),
     );
 
-    let test_build_action =
-        &TestBuildAction::new(cwd.clone(), profile, Some(target_arch), sample_class)
-            .set_up_standalone_driver_project((workspace_member, package))
-            .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
-            .expect_final_package_dir_exists(driver_name, &cwd, true)
-            .expect_inx_file_exists(driver_name, &cwd, true)
-            .expect_rename_driver_binary_dll_to_sys(driver_name, &cwd)
-            .expect_copy_driver_binary_sys_to_package_folder(driver_name, &cwd, false);
+    // The checked variant is resolved before cargo build runs
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
+        .with_multi_arch_package(true)
+        .set_up_workspace_with_multiple_driver_projects(
+            &workspace_root_dir,
+            Some(wdk_metadata),
+            vec![(workspace_member, package)],
+        )
+        .expect_detect_wdk_build_number(25100u32)
+        .expect_root_manifest_exists(&cwd, true);
 
     let build_action = initialize_build_action(
         &cwd,
         profile.as_ref(),
-        Some(target_arch),
+        None,
         verify_signature,
         sample_class,
         test_build_action,
     );
-    assert!(build_action.is_ok());
-    let run_result = build_action.expect("Failed to init build action").run();
-
-    assert!(matches!(
-        run_result.as_ref().expect_err("expected error"),
-        BuildActionError::OneOrMoreWorkspaceMembersFailedToBuild(_)
-    ));
+    let run_result = run_build_action(build_action);
+    assert!(
+        matches!(
+            run_result,
+            Err(BuildActionError::CheckedVariantOfMultiArchPackage(ref package_name))
+                if package_name == driver_name
+        ),
+        "expected checked variant of multi-arch package error, found: {run_result:?}"
+    );
 }
 
 #[test]
-pub fn given_a_driver_project_when_stampinf_command_execution_fails_then_package_should_fail() {
+pub fn given_a_driver_project_when_it_does_not_depend_on_wdk_sys_then_it_fails_without_building() {
     // Input CLI args
-    let cwd = PathBuf::from("C:\\tmp");
+    let workspace_root_dir = PathBuf::from("C:\\tmp");
+    let cwd = workspace_root_dir.join("sample-kmdf");
     let profile = None;
-    let target_arch = CpuArchitecture::Amd64;
-    let verify_signature = true;
+    let verify_signature = false;
     let sample_class = false;
 
     // Driver project data
@@ -592,33 +1530,17 @@ pub fn given_a_driver_project_when_stampinf_command_execution_fails_then_package
     let wdk_metadata = get_cargo_metadata_wdk_metadata(driver_type, 1, 33);
     let (workspace_member, package) =
         get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
+    let package = TestMetadataPackage(package.0.replace("\"wdk-sys\"", "\"wdk\""));
 
-    let expected_stampinf_output = Output {
-        status: ExitStatus::from_raw(1),
-        stdout: vec![],
-        stderr: vec![],
-    };
-
-    let cargo_build_output =
-        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile);
-
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class)
-        .set_up_standalone_driver_project((workspace_member, package))
-        .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
-        .expect_probe_target_arch_using_cargo_rustc(&cwd, target_arch, None)
-        .expect_final_package_dir_exists(driver_name, &cwd, true)
-        .expect_inx_file_exists(driver_name, &cwd, true)
-        .expect_rename_driver_binary_dll_to_sys(driver_name, &cwd)
-        .expect_copy_driver_binary_sys_to_package_folder(driver_name, &cwd, true)
-        .expect_copy_pdb_file_to_package_folder(driver_name, &cwd, true)
-        .expect_copy_inx_file_to_package_folder(driver_name, &cwd, true, &cwd)
-        .expect_copy_map_file_to_package_folder(driver_name, &cwd, true)
-        .expect_stampinf(
-            driver_name,
-            &cwd,
-            target_arch,
-            Some(expected_stampinf_output),
-        );
+    // The dependencies are checked before cargo build runs
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
+        .set_up_workspace_with_multiple_driver_projects(
+            &workspace_root_dir,
+            Some(wdk_metadata),
+            vec![(workspace_member, package)],
+        )
+        .expect_detect_wdk_build_number(25100u32)
+        .expect_root_manifest_exists(&cwd, true);
 
     let build_action = initialize_build_action(
         &cwd,
@@ -628,17 +1550,23 @@ pub fn given_a_driver_project_when_stampinf_command_execution_fails_then_package
         sample_class,
         test_build_action,
     );
-    assert!(build_action.is_ok());
     let run_result = run_build_action(build_action);
-
-    assert!(matches!(
-        run_result.as_ref().expect_err("expected error"),
-        BuildActionError::OneOrMoreWorkspaceMembersFailedToBuild(_)
-    ));
+    assert!(
+        matches!(
+            run_result,
+            Err(BuildActionError::IncorrectWdkDependencies {
+                ref package_name,
+                driver_type: DriverType::Kmdf,
+                ref fixes,
+            }) if package_name == driver_name && fixes.contains("add `wdk-sys` to [dependencies]")
+        ),
+        "expected incorrect wdk dependencies error, found: {run_result:?}"
+    );
 }
 
 #[test]
-pub fn given_a_driver_project_when_inf2cat_command_execution_fails_then_package_should_fail() {
+pub fn given_a_driver_project_when_driver_binary_is_built_for_another_arch_then_package_should_fail()
+ {
     // Input CLI args
     let cwd = PathBuf::from("C:\\tmp");
     let profile = None;
@@ -654,33 +1582,18 @@ pub fn given_a_driver_project_when_inf2cat_command_execution_fails_then_package_
     let (workspace_member, package) =
         get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
 
-    let expected_inf2cat_output = Output {
-        status: ExitStatus::from_raw(1),
-        stdout: vec![],
-        stderr: vec![],
-    };
-
     let cargo_build_output =
-        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile);
+        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile.clone());
 
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class)
+    // The toolchain produced an arm64 binary for the amd64 target, so nothing
+    // is packaged
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
         .set_up_standalone_driver_project((workspace_member, package))
         .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
         .expect_probe_target_arch_using_cargo_rustc(&cwd, target_arch, None)
-        .expect_final_package_dir_exists(driver_name, &cwd, true)
         .expect_inx_file_exists(driver_name, &cwd, true)
-        .expect_rename_driver_binary_dll_to_sys(driver_name, &cwd)
-        .expect_copy_driver_binary_sys_to_package_folder(driver_name, &cwd, true)
-        .expect_copy_pdb_file_to_package_folder(driver_name, &cwd, true)
-        .expect_copy_inx_file_to_package_folder(driver_name, &cwd, true, &cwd)
-        .expect_copy_map_file_to_package_folder(driver_name, &cwd, true)
-        .expect_stampinf(driver_name, &cwd, target_arch, None)
-        .expect_inf2cat(
-            driver_name,
-            &cwd,
-            target_arch,
-            Some(expected_inf2cat_output),
-        );
+        .expect_read_inx_file(driver_name, &cwd)
+        .expect_read_driver_binary(driver_name, &cwd, CpuArchitecture::Arm64);
 
     let build_action = initialize_build_action(
         &cwd,
@@ -723,14 +1636,16 @@ pub fn given_a_driver_project_when_certmgr_command_execution_fails_then_package_
     };
 
     let cargo_build_output =
-        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile);
+        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile.clone());
 
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class)
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
         .set_up_standalone_driver_project((workspace_member, package))
         .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
         .expect_probe_target_arch_using_cargo_rustc(&cwd, target_arch, None)
         .expect_final_package_dir_exists(driver_name, &cwd, true)
         .expect_inx_file_exists(driver_name, &cwd, true)
+        .expect_read_inx_file(driver_name, &cwd)
+        .expect_read_driver_binary(driver_name, &cwd, target_arch)
         .expect_rename_driver_binary_dll_to_sys(driver_name, &cwd)
         .expect_copy_driver_binary_sys_to_package_folder(driver_name, &cwd, true)
         .expect_copy_pdb_file_to_package_folder(driver_name, &cwd, true)
@@ -738,6 +1653,7 @@ pub fn given_a_driver_project_when_certmgr_command_execution_fails_then_package_
         .expect_copy_map_file_to_package_folder(driver_name, &cwd, true)
         .expect_stampinf(driver_name, &cwd, target_arch, None)
         .expect_inf2cat(driver_name, &cwd, target_arch, None)
+        .expect_cat_file_exists(driver_name, &cwd, true)
         .expect_infverif(driver_name, &cwd, None)
         .expect_self_signed_cert_file_exists(&cwd, false)
         .expect_certmgr_exists_check(Some(expected_output));
@@ -783,14 +1699,16 @@ pub fn given_a_driver_project_when_makecert_command_execution_fails_then_package
     };
 
     let cargo_build_output =
-        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile);
+        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile.clone());
 
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class)
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
         .set_up_standalone_driver_project((workspace_member, package))
         .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
         .expect_probe_target_arch_using_cargo_rustc(&cwd, target_arch, None)
         .expect_final_package_dir_exists(driver_name, &cwd, true)
         .expect_inx_file_exists(driver_name, &cwd, true)
+        .expect_read_inx_file(driver_name, &cwd)
+        .expect_read_driver_binary(driver_name, &cwd, target_arch)
         .expect_rename_driver_binary_dll_to_sys(driver_name, &cwd)
         .expect_copy_driver_binary_sys_to_package_folder(driver_name, &cwd, true)
         .expect_copy_pdb_file_to_package_folder(driver_name, &cwd, true)
@@ -798,6 +1716,7 @@ pub fn given_a_driver_project_when_makecert_command_execution_fails_then_package
         .expect_copy_map_file_to_package_folder(driver_name, &cwd, true)
         .expect_stampinf(driver_name, &cwd, target_arch, None)
         .expect_inf2cat(driver_name, &cwd, target_arch, None)
+        .expect_cat_file_exists(driver_name, &cwd, true)
         .expect_infverif(driver_name, &cwd, None)
         .expect_self_signed_cert_file_exists(&cwd, false)
         .expect_certmgr_exists_check(None)
@@ -838,7 +1757,7 @@ pub fn given_a_driver_project_when_signtool_command_execution_fails_then_package
         get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
 
     let cargo_build_output =
-        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile);
+        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile.clone());
 
     let expected_output = Output {
         status: ExitStatus::from_raw(1),
@@ -846,12 +1765,14 @@ pub fn given_a_driver_project_when_signtool_command_execution_fails_then_package
         stderr: vec![],
     };
 
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class)
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
         .set_up_standalone_driver_project((workspace_member, package))
         .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
         .expect_probe_target_arch_using_cargo_rustc(&cwd, target_arch, None)
         .expect_final_package_dir_exists(driver_name, &cwd, true)
         .expect_inx_file_exists(driver_name, &cwd, true)
+        .expect_read_inx_file(driver_name, &cwd)
+        .expect_read_driver_binary(driver_name, &cwd, target_arch)
         .expect_rename_driver_binary_dll_to_sys(driver_name, &cwd)
         .expect_copy_driver_binary_sys_to_package_folder(driver_name, &cwd, true)
         .expect_copy_pdb_file_to_package_folder(driver_name, &cwd, true)
@@ -859,6 +1780,7 @@ pub fn given_a_driver_project_when_signtool_command_execution_fails_then_package
         .expect_copy_map_file_to_package_folder(driver_name, &cwd, true)
         .expect_stampinf(driver_name, &cwd, target_arch, None)
         .expect_inf2cat(driver_name, &cwd, target_arch, None)
+        .expect_cat_file_exists(driver_name, &cwd, true)
         .expect_infverif(driver_name, &cwd, None)
         .expect_self_signed_cert_file_exists(&cwd, false)
         .expect_certmgr_exists_check(None)
@@ -901,7 +1823,7 @@ pub fn given_a_driver_project_when_infverif_command_execution_fails_then_package
         get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
 
     let cargo_build_output =
-        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile);
+        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile.clone());
 
     let expected_output = Output {
         status: ExitStatus::from_raw(1),
@@ -909,12 +1831,14 @@ pub fn given_a_driver_project_when_infverif_command_execution_fails_then_package
         stderr: vec![],
     };
 
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class)
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
         .set_up_standalone_driver_project((workspace_member, package))
         .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
         .expect_probe_target_arch_using_cargo_rustc(&cwd, target_arch, None)
         .expect_final_package_dir_exists(driver_name, &cwd, true)
         .expect_inx_file_exists(driver_name, &cwd, true)
+        .expect_read_inx_file(driver_name, &cwd)
+        .expect_read_driver_binary(driver_name, &cwd, target_arch)
         .expect_rename_driver_binary_dll_to_sys(driver_name, &cwd)
         .expect_copy_driver_binary_sys_to_package_folder(driver_name, &cwd, true)
         .expect_copy_pdb_file_to_package_folder(driver_name, &cwd, true)
@@ -922,6 +1846,7 @@ pub fn given_a_driver_project_when_infverif_command_execution_fails_then_package
         .expect_copy_map_file_to_package_folder(driver_name, &cwd, true)
         .expect_stampinf(driver_name, &cwd, target_arch, None)
         .expect_inf2cat(driver_name, &cwd, target_arch, None)
+        .expect_cat_file_exists(driver_name, &cwd, true)
         .expect_infverif(driver_name, &cwd, Some(expected_output));
 
     let build_action = initialize_build_action(
@@ -956,7 +1881,7 @@ pub fn given_a_non_driver_project_when_default_values_are_provided_with_no_wdk_m
     let (workspace_member, package) =
         get_cargo_metadata_package(&cwd, driver_name, driver_version, None);
 
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class)
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
         .set_up_standalone_driver_project((workspace_member, package))
         .expect_default_build_task_steps(driver_name, None);
 
@@ -983,7 +1908,7 @@ pub fn given_a_invalid_driver_project_with_partial_wdk_metadata_when_valid_defau
     let driver_name = "sample-driver";
     let cargo_toml_metadata = invalid_driver_cargo_toml();
 
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class)
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
         .set_up_with_custom_toml(&cargo_toml_metadata)
         .expect_default_build_task_steps(driver_name, None);
 
@@ -1022,45 +1947,239 @@ pub fn given_a_driver_project_when_target_arch_is_not_provided_and_probing_cargo
     let (workspace_member, package) =
         get_cargo_metadata_package(&cwd, driver_name, driver_version, Some(&wdk_metadata));
 
-    let cargo_build_output =
-        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile);
+    let cargo_build_output =
+        create_cargo_build_output_json(driver_name, driver_version, &cwd, None, profile.clone());
+
+    let cargo_rustc_output = Output {
+        status: ExitStatus::from_raw(1),
+        stdout: vec![],
+        stderr: vec![],
+    };
+
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
+        .set_up_standalone_driver_project((workspace_member, package))
+        .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
+        .expect_probe_target_arch_using_cargo_rustc(
+            &cwd,
+            CpuArchitecture::Amd64,
+            Some(cargo_rustc_output),
+        );
+
+    let build_action = initialize_build_action(
+        &cwd,
+        profile.as_ref(),
+        None,
+        verify_signature,
+        sample_class,
+        test_build_action,
+    );
+    assert!(build_action.is_ok());
+    let run_result = build_action.expect("Failed to init build action").run();
+    assert!(matches!(
+        run_result.as_ref().expect_err("expected error"),
+        BuildActionError::OneOrMoreWorkspaceMembersFailedToBuild(_)
+    ));
+}
+
+////////////////////////////////////////////////////////////////////////////////
+/// Workspace tests
+////////////////////////////////////////////////////////////////////////////////
+#[test]
+pub fn given_a_workspace_with_multiple_driver_and_non_driver_projects_when_default_values_are_provided_then_it_packages_successfully()
+ {
+    // Input CLI args
+    let cwd = PathBuf::from("C:\\tmp");
+    let profile = None;
+    let target_arch = CpuArchitecture::Amd64;
+    let verify_signature = true;
+    let sample_class = false;
+
+    // Driver project data
+    let driver_type = "KMDF";
+    let driver_name_1 = "sample-kmdf-1";
+    let driver_version_1 = "0.0.1";
+    let driver_name_2 = "sample-kmdf-2";
+    let driver_version_2 = "0.0.2";
+    let non_driver = "non-driver";
+    let non_driver_version = "0.0.3";
+
+    // Create artifact outputs for workspace packages
+    let artifact_1 = create_cargo_build_output_json_with_manifest(
+        driver_name_1,
+        driver_version_1,
+        &cwd,
+        &cwd.join(driver_name_1).join("Cargo.toml"),
+        None,
+        profile.clone(),
+        true,
+    );
+    let artifact_2 = create_cargo_build_output_json_with_manifest(
+        driver_name_2,
+        driver_version_2,
+        &cwd,
+        &cwd.join(driver_name_2).join("Cargo.toml"),
+        None,
+        profile.clone(),
+        true,
+    );
+    let artifact_non_driver = create_cargo_build_output_json_with_manifest(
+        non_driver,
+        non_driver_version,
+        &cwd,
+        &cwd.join(non_driver).join("Cargo.toml"),
+        None,
+        profile.clone(),
+        false,
+    );
+    let wdk_metadata = get_cargo_metadata_wdk_metadata(driver_type, 1, 33);
+    let (workspace_member_1, package_1) = get_cargo_metadata_package(
+        &cwd.join(driver_name_1),
+        driver_name_1,
+        driver_version_1,
+        Some(&wdk_metadata),
+    );
+    let (workspace_member_2, package_2) = get_cargo_metadata_package(
+        &cwd.join(driver_name_2),
+        driver_name_2,
+        driver_version_2,
+        Some(&wdk_metadata),
+    );
+    let (workspace_member_3, package_3) =
+        get_cargo_metadata_package(&cwd.join(non_driver), non_driver, non_driver_version, None);
+
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
+        .set_up_workspace_with_multiple_driver_projects(
+            &cwd,
+            Some(wdk_metadata),
+            vec![
+                (workspace_member_1, package_1),
+                (workspace_member_2, package_2),
+                (workspace_member_3, package_3),
+            ],
+        )
+        .expect_detect_wdk_build_number(25100u32)
+        .expect_root_manifest_exists(&cwd, true)
+        .expect_cargo_build(driver_name_1, &cwd.join(driver_name_1), Some(artifact_1))
+        .expect_probe_target_arch_using_cargo_rustc(&cwd.join(driver_name_1), target_arch, None)
+        .expect_default_package_task_steps_for_workspace(
+            driver_name_1,
+            target_arch,
+            verify_signature,
+        )
+        // Second driver project
+        .expect_cargo_build(driver_name_2, &cwd.join(driver_name_2), Some(artifact_2))
+        .expect_probe_target_arch_using_cargo_rustc(&cwd.join(driver_name_2), target_arch, None)
+        .expect_default_package_task_steps_for_workspace(
+            driver_name_2,
+            target_arch,
+            verify_signature,
+        )
+        // Non-driver project
+        .expect_cargo_build(non_driver, &cwd.join(non_driver), Some(artifact_non_driver))
+        .expect_read_packaged_inf_file(driver_name_1, "Root\\SampleKmdf1")
+        .expect_read_packaged_inf_file(driver_name_2, "Root\\SampleKmdf2");
+
+    assert_build_action_run_with_env_is_success(
+        &cwd,
+        profile,
+        None,
+        verify_signature,
+        sample_class,
+        test_build_action,
+    );
+}
+
+#[test]
+pub fn given_a_workspace_with_default_members_when_cwd_is_workspace_root_then_it_builds_only_default_members()
+ {
+    // Input CLI args
+    let cwd = PathBuf::from("C:\\tmp");
+    let profile = None;
+    let target_arch = CpuArchitecture::Amd64;
+    let verify_signature = true;
+    let sample_class = false;
+
+    // Driver project data
+    let driver_type = "KMDF";
+    let driver_name_1 = "sample-kmdf-1";
+    let driver_version_1 = "0.0.1";
+    let driver_name_2 = "sample-kmdf-2";
+    let driver_version_2 = "0.0.2";
+    let non_driver = "non-driver";
+    let non_driver_version = "0.0.3";
 
-    let cargo_rustc_output = Output {
-        status: ExitStatus::from_raw(1),
-        stdout: vec![],
-        stderr: vec![],
-    };
+    // Create artifact outputs for the default members
+    let artifact_1 = create_cargo_build_output_json_with_manifest(
+        driver_name_1,
+        driver_version_1,
+        &cwd,
+        &cwd.join(driver_name_1).join("Cargo.toml"),
+        None,
+        profile.clone(),
+        true,
+    );
+    let artifact_non_driver = create_cargo_build_output_json_with_manifest(
+        non_driver,
+        non_driver_version,
+        &cwd,
+        &cwd.join(non_driver).join("Cargo.toml"),
+        None,
+        profile.clone(),
+        false,
+    );
+    let wdk_metadata = get_cargo_metadata_wdk_metadata(driver_type, 1, 33);
+    let (workspace_member_1, package_1) = get_cargo_metadata_package(
+        &cwd.join(driver_name_1),
+        driver_name_1,
+        driver_version_1,
+        Some(&wdk_metadata),
+    );
+    let (workspace_member_2, package_2) = get_cargo_metadata_package(
+        &cwd.join(driver_name_2),
+        driver_name_2,
+        driver_version_2,
+        Some(&wdk_metadata),
+    );
+    let (workspace_member_3, package_3) =
+        get_cargo_metadata_package(&cwd.join(non_driver), non_driver, non_driver_version, None);
 
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class)
-        .set_up_standalone_driver_project((workspace_member, package))
-        .expect_default_build_task_steps(driver_name, Some(cargo_build_output))
-        .expect_probe_target_arch_using_cargo_rustc(
+    // The second driver project is not a default member, so no build or
+    // packaging steps are expected for it
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
+        .with_default_members(vec![workspace_member_1.clone(), workspace_member_3.clone()])
+        .set_up_workspace_with_multiple_driver_projects(
             &cwd,
-            CpuArchitecture::Amd64,
-            Some(cargo_rustc_output),
-        );
+            Some(wdk_metadata),
+            vec![
+                (workspace_member_1, package_1),
+                (workspace_member_2, package_2),
+                (workspace_member_3, package_3),
+            ],
+        )
+        .expect_detect_wdk_build_number(25100u32)
+        .expect_root_manifest_exists(&cwd, true)
+        .expect_cargo_build(driver_name_1, &cwd.join(driver_name_1), Some(artifact_1))
+        .expect_probe_target_arch_using_cargo_rustc(&cwd.join(driver_name_1), target_arch, None)
+        .expect_default_package_task_steps_for_workspace(
+            driver_name_1,
+            target_arch,
+            verify_signature,
+        )
+        // Non-driver project
+        .expect_cargo_build(non_driver, &cwd.join(non_driver), Some(artifact_non_driver));
 
-    let build_action = initialize_build_action(
+    assert_build_action_run_with_env_is_success(
         &cwd,
-        profile.as_ref(),
+        profile,
         None,
         verify_signature,
         sample_class,
         test_build_action,
     );
-    assert!(build_action.is_ok());
-    let run_result = build_action.expect("Failed to init build action").run();
-    assert!(matches!(
-        run_result.as_ref().expect_err("expected error"),
-        BuildActionError::OneOrMoreWorkspaceMembersFailedToBuild(_)
-    ));
 }
 
-////////////////////////////////////////////////////////////////////////////////
-/// Workspace tests
-////////////////////////////////////////////////////////////////////////////////
 #[test]
-pub fn given_a_workspace_with_multiple_driver_and_non_driver_projects_when_default_values_are_provided_then_it_packages_successfully()
+pub fn given_a_workspace_with_multiple_driver_projects_when_their_infs_share_a_device_id_then_it_fails()
  {
     // Input CLI args
     let cwd = PathBuf::from("C:\\tmp");
@@ -1085,7 +2204,7 @@ pub fn given_a_workspace_with_multiple_driver_and_non_driver_projects_when_defau
         &cwd,
         &cwd.join(driver_name_1).join("Cargo.toml"),
         None,
-        profile,
+        profile.clone(),
         true,
     );
     let artifact_2 = create_cargo_build_output_json_with_manifest(
@@ -1094,7 +2213,7 @@ pub fn given_a_workspace_with_multiple_driver_and_non_driver_projects_when_defau
         &cwd,
         &cwd.join(driver_name_2).join("Cargo.toml"),
         None,
-        profile,
+        profile.clone(),
         true,
     );
     let artifact_non_driver = create_cargo_build_output_json_with_manifest(
@@ -1103,7 +2222,7 @@ pub fn given_a_workspace_with_multiple_driver_and_non_driver_projects_when_defau
         &cwd,
         &cwd.join(non_driver).join("Cargo.toml"),
         None,
-        profile,
+        profile.clone(),
         false,
     );
     let wdk_metadata = get_cargo_metadata_wdk_metadata(driver_type, 1, 33);
@@ -1122,7 +2241,7 @@ pub fn given_a_workspace_with_multiple_driver_and_non_driver_projects_when_defau
     let (workspace_member_3, package_3) =
         get_cargo_metadata_package(&cwd.join(non_driver), non_driver, non_driver_version, None);
 
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class)
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
         .set_up_workspace_with_multiple_driver_projects(
             &cwd,
             Some(wdk_metadata),
@@ -1150,16 +2269,32 @@ pub fn given_a_workspace_with_multiple_driver_and_non_driver_projects_when_defau
             verify_signature,
         )
         // Non-driver project
-        .expect_cargo_build(non_driver, &cwd.join(non_driver), Some(artifact_non_driver));
+        .expect_cargo_build(non_driver, &cwd.join(non_driver), Some(artifact_non_driver))
+        .expect_read_packaged_inf_file(driver_name_1, "Root\\SampleKmdf1")
+        .expect_read_packaged_inf_file(driver_name_2, "ROOT\\SAMPLEKMDF1");
 
-    assert_build_action_run_with_env_is_success(
+    let build_action = initialize_build_action(
         &cwd,
-        profile,
+        profile.as_ref(),
         None,
         verify_signature,
         sample_class,
         test_build_action,
     );
+    let run_result = run_build_action(build_action);
+    assert!(
+        matches!(
+            run_result,
+            Err(BuildActionError::DuplicateDeviceId {
+                ref device_id,
+                ref first_package_name,
+                ref second_package_name,
+            }) if device_id == "Root\\SampleKmdf1"
+                && first_package_name == driver_name_1
+                && second_package_name == driver_name_2
+        ),
+        "expected duplicate device ID error, found: {run_result:?}"
+    );
 }
 
 #[test]
@@ -1209,11 +2344,11 @@ pub fn given_a_workspace_with_multiple_driver_and_non_driver_projects_when_cwd_i
         &workspace_root_dir,
         &workspace_root_dir.join(driver_name_1).join("Cargo.toml"),
         None,
-        profile,
+        profile.clone(),
         true,
     );
 
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class) // Even when cwd is changed to driver project inside the workspace, cargo metadata read
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class) // Even when cwd is changed to driver project inside the workspace, cargo metadata read
         // is going to be for the whole workspace
         .set_up_workspace_with_multiple_driver_projects(
             &workspace_root_dir,
@@ -1230,6 +2365,8 @@ pub fn given_a_workspace_with_multiple_driver_and_non_driver_projects_when_cwd_i
         .expect_probe_target_arch_using_cargo_rustc(&cwd, target_arch, None)
         .expect_final_package_dir_exists(driver_name_1, &workspace_root_dir, true)
         .expect_inx_file_exists(driver_name_1, &cwd, true)
+        .expect_read_inx_file(driver_name_1, &cwd)
+        .expect_read_driver_binary(driver_name_1, &workspace_root_dir, target_arch)
         .expect_rename_driver_binary_dll_to_sys(driver_name_1, &workspace_root_dir)
         .expect_copy_driver_binary_sys_to_package_folder(driver_name_1, &workspace_root_dir, true)
         .expect_copy_pdb_file_to_package_folder(driver_name_1, &workspace_root_dir, true)
@@ -1237,6 +2374,7 @@ pub fn given_a_workspace_with_multiple_driver_and_non_driver_projects_when_cwd_i
         .expect_copy_map_file_to_package_folder(driver_name_1, &workspace_root_dir, true)
         .expect_stampinf(driver_name_1, &workspace_root_dir, target_arch, None)
         .expect_inf2cat(driver_name_1, &workspace_root_dir, target_arch, None)
+        .expect_cat_file_exists(driver_name_1, &workspace_root_dir, true)
         .expect_self_signed_cert_file_exists(&workspace_root_dir, false)
         .expect_certmgr_exists_check(Some(expected_certmgr_output))
         .expect_makecert(&workspace_root_dir, None)
@@ -1249,6 +2387,8 @@ pub fn given_a_workspace_with_multiple_driver_and_non_driver_projects_when_cwd_i
         .expect_signtool_sign_cat_file(driver_name_1, &workspace_root_dir, None)
         .expect_signtool_verify_driver_binary_sys_file(driver_name_1, &workspace_root_dir, None)
         .expect_signtool_verify_cat_file(driver_name_1, &workspace_root_dir, None)
+        .expect_signtool_verify_catalog_member(driver_name_1, &workspace_root_dir, None)
+        .expect_certutil_dump_cert()
         .expect_infverif(driver_name_1, &workspace_root_dir, None);
 
     assert_build_action_run_with_env_is_success(
@@ -1287,7 +2427,7 @@ pub fn given_a_workspace_with_multiple_driver_and_non_driver_projects_when_verif
         &cwd,
         &cwd.join(driver_name_1).join("Cargo.toml"),
         None,
-        profile,
+        profile.clone(),
         true,
     );
     let artifact_2 = create_cargo_build_output_json_with_manifest(
@@ -1296,7 +2436,7 @@ pub fn given_a_workspace_with_multiple_driver_and_non_driver_projects_when_verif
         &cwd,
         &cwd.join(driver_name_2).join("Cargo.toml"),
         None,
-        profile,
+        profile.clone(),
         true,
     );
     let artifact_non_driver = create_cargo_build_output_json_with_manifest(
@@ -1305,7 +2445,7 @@ pub fn given_a_workspace_with_multiple_driver_and_non_driver_projects_when_verif
         &cwd,
         &cwd.join(non_driver).join("Cargo.toml"),
         None,
-        profile,
+        profile.clone(),
         false, // NOT a driver - will use "lib" instead of "cdylib"
     );
     let wdk_metadata = get_cargo_metadata_wdk_metadata(driver_type, 1, 33);
@@ -1324,7 +2464,7 @@ pub fn given_a_workspace_with_multiple_driver_and_non_driver_projects_when_verif
     let (workspace_member_3, package_3) =
         get_cargo_metadata_package(&cwd.join(non_driver), non_driver, non_driver_version, None);
 
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class)
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
         .set_up_workspace_with_multiple_driver_projects(
             &cwd,
             Some(wdk_metadata),
@@ -1352,7 +2492,9 @@ pub fn given_a_workspace_with_multiple_driver_and_non_driver_projects_when_verif
             verify_signature,
         )
         // Non-driver project
-        .expect_cargo_build(non_driver, &cwd.join(non_driver), Some(artifact_non_driver));
+        .expect_cargo_build(non_driver, &cwd.join(non_driver), Some(artifact_non_driver))
+        .expect_read_packaged_inf_file(driver_name_1, "Root\\SampleKmdf1")
+        .expect_read_packaged_inf_file(driver_name_2, "Root\\SampleKmdf2");
 
     assert_build_action_run_with_env_is_success(
         &cwd,
@@ -1402,7 +2544,7 @@ pub fn given_a_workspace_with_multiple_driver_and_non_driver_projects_when_cwd_i
         None,
     );
 
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class) // Even when cwd is changed to driver project inside the workspace, cargo metadata read
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class) // Even when cwd is changed to driver project inside the workspace, cargo metadata read
         // is going to be for the whole workspace
         .set_up_workspace_with_multiple_driver_projects(
             &workspace_root_dir,
@@ -1458,7 +2600,7 @@ pub fn given_a_workspace_with_multiple_distinct_wdk_configurations_at_each_works
         Some(&wdk_metadata_2),
     );
 
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class)
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
         .set_up_workspace_with_multiple_driver_projects(
             &cwd,
             Some(wdk_metadata_1),
@@ -1524,7 +2666,7 @@ pub fn given_a_workspace_with_multiple_distinct_wdk_configurations_at_root_and_w
         Some(&wdk_metadata_1),
     );
 
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class)
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
         .set_up_workspace_with_multiple_driver_projects(
             &cwd,
             Some(wdk_metadata_2),
@@ -1559,6 +2701,77 @@ pub fn given_a_workspace_with_multiple_distinct_wdk_configurations_at_root_and_w
     ));
 }
 
+#[test]
+pub fn given_a_workspace_with_packages_setting_the_same_env_var_when_cwd_is_workspace_root_then_each_package_builds_with_its_own_value()
+ {
+    // Input CLI args
+    let cwd = PathBuf::from("C:\\tmp");
+    let profile = None;
+    let verify_signature = false;
+    let sample_class = false;
+
+    // Driver project data
+    let driver_type = "KMDF";
+    let driver_name_1 = "sample-kmdf-1";
+    let driver_version_1 = "0.0.1";
+    let driver_name_2 = "sample-kmdf-2";
+    let driver_version_2 = "0.0.2";
+    let wdk_metadata = get_cargo_metadata_wdk_metadata(driver_type, 1, 33);
+    let package_metadata = |driver_flavor: &str| {
+        let mut metadata: serde_json::Value =
+            serde_json::from_str(&wdk_metadata.0).expect("Failed to parse wdk metadata");
+        metadata["cargo-wdk"] = serde_json::json!({ "env": { "DRIVER_FLAVOR": driver_flavor } });
+        TestWdkMetadata(metadata.to_string())
+    };
+    let (workspace_member_1, package_1) = get_cargo_metadata_package(
+        &cwd.join(driver_name_1),
+        driver_name_1,
+        driver_version_1,
+        Some(&package_metadata("checked")),
+    );
+    let (workspace_member_2, package_2) = get_cargo_metadata_package(
+        &cwd.join(driver_name_2),
+        driver_name_2,
+        driver_version_2,
+        Some(&package_metadata("retail")),
+    );
+
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class)
+        .with_no_package(true)
+        .set_up_workspace_with_multiple_driver_projects(
+            &cwd,
+            Some(wdk_metadata.clone()),
+            vec![
+                (workspace_member_1, package_1),
+                (workspace_member_2, package_2),
+            ],
+        )
+        .expect_detect_wdk_build_number(25100u32)
+        .expect_root_manifest_exists(&cwd, true)
+        .expect_cargo_build_with_env(
+            driver_name_1,
+            &cwd.join(driver_name_1),
+            None,
+            Some(("DRIVER_FLAVOR", "checked")),
+        )
+        .expect_cargo_build_with_env(
+            driver_name_2,
+            &cwd.join(driver_name_2),
+            None,
+            Some(("DRIVER_FLAVOR", "retail")),
+        );
+
+    assert_build_action_run_with_env_is_success(
+        &cwd,
+        profile,
+        None,
+        verify_signature,
+        sample_class,
+        test_build_action,
+    );
+    assert!(std::env::var("DRIVER_FLAVOR").is_err());
+}
+
 #[test]
 pub fn given_a_workspace_only_with_non_driver_projects_when_cwd_is_workspace_root_then_build_should_be_successful()
  {
@@ -1574,7 +2787,7 @@ pub fn given_a_workspace_only_with_non_driver_projects_when_cwd_is_workspace_roo
     let (workspace_member_3, package_3) =
         get_cargo_metadata_package(&cwd.join(non_driver), non_driver, non_driver_version, None);
 
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class) // Even when cwd is changed to driver project inside the workspace, cargo metadata read
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class) // Even when cwd is changed to driver project inside the workspace, cargo metadata read
         // is going to be for the whole workspace
         .set_up_workspace_with_multiple_driver_projects(
             &cwd,
@@ -1615,7 +2828,7 @@ pub fn given_a_workspace_only_with_non_driver_projects_when_cwd_is_workspace_mem
         None,
     );
 
-    let test_build_action = &TestBuildAction::new(cwd.clone(), profile, None, sample_class) // Even when cwd is changed to driver project inside the workspace, cargo metadata read
+    let test_build_action = &TestBuildAction::new(cwd.clone(), profile.clone(), None, sample_class) // Even when cwd is changed to driver project inside the workspace, cargo metadata read
         // is going to be for the whole workspace
         .set_up_workspace_with_multiple_driver_projects(
             &workspace_root_dir,
@@ -1668,21 +2881,63 @@ fn initialize_build_action<'a>(
     sample_class: bool,
     test_build_action: &'a TestBuildAction,
 ) -> Result<BuildAction<'a>, anyhow::Error> {
-    let sign_mode = match test_build_action.sign_mode {
+    let sign_mode = test_build_action.sign_mode.map(|sign_mode| match sign_mode {
         SignMode::Off => SignMode::Off,
         SignMode::Test { .. } => SignMode::Test { verify_signature },
-    };
+    });
     BuildAction::new(
         &BuildActionParams {
             working_dir: cwd,
             profile,
             target_arch,
             sign_mode,
+            verify_signature,
             is_sample_class: sample_class,
+            driver_type: test_build_action.driver_type,
             locked: test_build_action.locked,
+            cargo_jobs: None,
+            deny_compile_warnings: false,
             target_platform: TargetPlatform::Universal,
             features: &test_build_action.features,
             verbosity_level: clap_verbosity_flag::Verbosity::new(1, 0),
+            color: ColorChoice::Auto,
+            import_check: false,
+            os_version_check: false,
+            multi_arch_package: test_build_action.multi_arch_package,
+            wdk_lock_mode: WdkLockMode::Off,
+            allow_unsupported_wdk: false,
+            temp_dir: None,
+            no_package: test_build_action.no_package,
+            source_date_epoch: None,
+            binary_path: test_build_action.binary_path.as_deref(),
+            emit_nupkg: false,
+            emit_index: false,
+            include_map: None,
+            log_dir: test_build_action.log_dir.as_deref(),
+            diagnostic_bundle: test_build_action.diagnostic_bundle.as_deref(),
+            tool_paths: &test_build_action.tool_paths,
+            relocatable: false,
+            print_artifacts: false,
+            clippy: test_build_action.clippy,
+            overwrite_package: false,
+            pdb_altpath: None,
+            emit_install_summary: false,
+            wdf_versions: test_build_action.wdf_versions,
+            emit_catalog_signing_report: false,
+            emit_uninstall: false,
+            print_cargo_command: None,
+            stamp_only: false,
+            binary_only: false,
+            symbols_archive_dir: None,
+            no_hooks: test_build_action.no_hooks,
+            sanitizer: test_build_action.sanitizer,
+            min_version: None,
+            cert_validity_days: None,
+            cert_from_env: None,
+            cert_password_env: None,
+            profile_overrides: &[],
+            event_pipe: None,
+            checked: false,
         },
         test_build_action.mock_wdk_build_provider(),
         test_build_action.mock_run_command(),
@@ -1743,9 +2998,21 @@ struct TestBuildAction {
     profile: Option<Profile>,
     target_arch: Option<CpuArchitecture>,
     sample_class: bool,
-    sign_mode: SignMode,
+    sign_mode: Option<SignMode>,
     locked: bool,
+    no_package: bool,
+    binary_path: Option<PathBuf>,
+    log_dir: Option<PathBuf>,
+    diagnostic_bundle: Option<PathBuf>,
+    tool_paths: ToolPaths,
+    clippy: bool,
+    no_hooks: bool,
+    sanitizer: Option<Sanitizer>,
+    multi_arch_package: bool,
+    wdf_versions: WdfVersionOverrides,
+    driver_type: Option<DriverType>,
     features: Features,
+    default_members: Option<Vec<TestMetadataWorkspaceMemberId>>,
 
     cargo_metadata: Option<CargoMetadata>,
     // mocks
@@ -1772,11 +3039,23 @@ fn new(
             profile,
             target_arch,
             sample_class,
-            sign_mode: SignMode::Test {
+            sign_mode: Some(SignMode::Test {
                 verify_signature: false,
-            },
+            }),
             locked: false,
+            no_package: false,
+            binary_path: None,
+            log_dir: None,
+            diagnostic_bundle: None,
+            tool_paths: ToolPaths::default(),
+            clippy: false,
+            no_hooks: false,
+            sanitizer: None,
+            multi_arch_package: false,
+            wdf_versions: WdfVersionOverrides::default(),
+            driver_type: None,
             features: Features::default(),
+            default_members: None,
             mock_run_command,
             mock_wdk_build_provider,
             mock_fs_provider,
@@ -1785,7 +3064,7 @@ fn new(
         }
     }
 
-    fn with_sign_mode(mut self, sign_mode: SignMode) -> Self {
+    fn with_sign_mode(mut self, sign_mode: Option<SignMode>) -> Self {
         self.sign_mode = sign_mode;
         self
     }
@@ -1795,11 +3074,71 @@ fn with_locked(mut self, locked: bool) -> Self {
         self
     }
 
+    fn with_no_package(mut self, no_package: bool) -> Self {
+        self.no_package = no_package;
+        self
+    }
+
+    fn with_binary_path(mut self, binary_path: PathBuf) -> Self {
+        self.binary_path = Some(binary_path);
+        self
+    }
+
+    fn with_log_dir(mut self, log_dir: PathBuf) -> Self {
+        self.log_dir = Some(log_dir);
+        self
+    }
+
+    fn with_diagnostic_bundle(mut self, diagnostic_bundle: PathBuf) -> Self {
+        self.diagnostic_bundle = Some(diagnostic_bundle);
+        self
+    }
+
+    fn with_tool_paths(mut self, tool_paths: ToolPaths) -> Self {
+        self.tool_paths = tool_paths;
+        self
+    }
+
+    fn with_wdf_versions(mut self, wdf_versions: WdfVersionOverrides) -> Self {
+        self.wdf_versions = wdf_versions;
+        self
+    }
+
+    fn with_clippy(mut self, clippy: bool) -> Self {
+        self.clippy = clippy;
+        self
+    }
+
+    fn with_no_hooks(mut self, no_hooks: bool) -> Self {
+        self.no_hooks = no_hooks;
+        self
+    }
+
+    fn with_sanitizer(mut self, sanitizer: Sanitizer) -> Self {
+        self.sanitizer = Some(sanitizer);
+        self
+    }
+
+    fn with_multi_arch_package(mut self, multi_arch_package: bool) -> Self {
+        self.multi_arch_package = multi_arch_package;
+        self
+    }
+
+    fn with_driver_type(mut self, driver_type: DriverType) -> Self {
+        self.driver_type = Some(driver_type);
+        self
+    }
+
     fn with_features(mut self, features: Features) -> Self {
         self.features = features;
         self
     }
 
+    fn with_default_members(mut self, default_members: Vec<TestMetadataWorkspaceMemberId>) -> Self {
+        self.default_members = Some(default_members);
+        self
+    }
+
     fn set_up_standalone_driver_project(
         mut self,
         package_metadata: (TestMetadataWorkspaceMemberId, TestMetadataPackage),
@@ -1808,6 +3147,7 @@ fn set_up_standalone_driver_project(
             &self.cwd,
             vec![package_metadata.1],
             &[package_metadata.0],
+            self.default_members.as_deref(),
             None,
         );
         let cargo_toml_metadata =
@@ -1847,6 +3187,7 @@ fn set_up_workspace_with_multiple_driver_projects(
                 .map(|p| p.0)
                 .collect::<Vec<_>>()
                 .as_slice(),
+            self.default_members.as_deref(),
             workspace_additional_metadata,
         );
         let cargo_toml_metadata = serde_json::from_str::<cargo_metadata::Metadata>(
@@ -1899,10 +3240,10 @@ fn set_up_with_custom_toml(mut self, cargo_toml_metadata: &str) -> Self {
 
     fn setup_target_dir(&self, dir_path: &Path) -> PathBuf {
         let mut base = dir_path.join("target");
-        let profile_dir_name = match self.profile {
-            Some(Profile::Release) => "release",
-            _ => "debug",
-        };
+        let profile_dir_name = self
+            .profile
+            .as_ref()
+            .map_or("debug", Profile::target_dir_name);
         if let Some(target_arch) = self.target_arch {
             let triple = to_target_triple(target_arch);
             base = base.join(triple);
@@ -1940,6 +3281,8 @@ fn expect_default_package_task_steps(
         let expectations = self
             .expect_final_package_dir_exists(driver_name, &cwd, true)
             .expect_inx_file_exists(driver_name, &cwd, true)
+            .expect_read_inx_file(driver_name, &cwd)
+            .expect_read_driver_binary(driver_name, &cwd, target_arch)
             .expect_rename_driver_binary_dll_to_sys(driver_name, &cwd)
             .expect_copy_driver_binary_sys_to_package_folder(driver_name, &cwd, true)
             .expect_copy_pdb_file_to_package_folder(driver_name, &cwd, true)
@@ -1947,6 +3290,7 @@ fn expect_default_package_task_steps(
             .expect_copy_map_file_to_package_folder(driver_name, &cwd, true)
             .expect_stampinf(driver_name, &cwd, target_arch, None)
             .expect_inf2cat(driver_name, &cwd, target_arch, None)
+            .expect_cat_file_exists(driver_name, &cwd, true)
             .expect_self_signed_cert_file_exists(&cwd, false)
             .expect_certmgr_exists_check(Some(expected_certmgr_output))
             .expect_makecert(&cwd, None)
@@ -1960,6 +3304,8 @@ fn expect_default_package_task_steps(
         expectations
             .expect_signtool_verify_driver_binary_sys_file(driver_name, &cwd, None)
             .expect_signtool_verify_cat_file(driver_name, &cwd, None)
+            .expect_signtool_verify_catalog_member(driver_name, &cwd, None)
+            .expect_certutil_dump_cert()
     }
 
     /// Sets up package-task expectations for `SignMode::Off`: stampinf,
@@ -1973,6 +3319,8 @@ fn expect_package_task_steps_with_sign_mode_off(
         let cwd = self.cwd.clone();
         self.expect_final_package_dir_exists(driver_name, &cwd, true)
             .expect_inx_file_exists(driver_name, &cwd, true)
+            .expect_read_inx_file(driver_name, &cwd)
+            .expect_read_driver_binary(driver_name, &cwd, target_arch)
             .expect_rename_driver_binary_dll_to_sys(driver_name, &cwd)
             .expect_copy_driver_binary_sys_to_package_folder(driver_name, &cwd, true)
             .expect_copy_pdb_file_to_package_folder(driver_name, &cwd, true)
@@ -1980,6 +3328,7 @@ fn expect_package_task_steps_with_sign_mode_off(
             .expect_copy_map_file_to_package_folder(driver_name, &cwd, true)
             .expect_stampinf(driver_name, &cwd, target_arch, None)
             .expect_inf2cat(driver_name, &cwd, target_arch, None)
+            .expect_cat_file_exists(driver_name, &cwd, true)
             .expect_infverif(driver_name, &cwd, None)
     }
 
@@ -1994,6 +3343,8 @@ fn expect_default_package_task_steps_for_workspace(
         let expectations = self
             .expect_final_package_dir_exists(driver_name, &cwd, true)
             .expect_inx_file_exists(driver_name, &cwd.join(driver_name), true)
+            .expect_read_inx_file(driver_name, &cwd.join(driver_name))
+            .expect_read_driver_binary(driver_name, &cwd, target_arch)
             .expect_rename_driver_binary_dll_to_sys(driver_name, &cwd)
             .expect_copy_driver_binary_sys_to_package_folder(driver_name, &cwd, true)
             .expect_copy_pdb_file_to_package_folder(driver_name, &cwd, true)
@@ -2001,6 +3352,7 @@ fn expect_default_package_task_steps_for_workspace(
             .expect_copy_map_file_to_package_folder(driver_name, &cwd, true)
             .expect_stampinf(driver_name, &cwd, target_arch, None)
             .expect_inf2cat(driver_name, &cwd, target_arch, None)
+            .expect_cat_file_exists(driver_name, &cwd, true)
             .expect_self_signed_cert_file_exists(&cwd, false)
             .expect_certmgr_exists_check(Some(expected_certmgr_output))
             .expect_makecert(&cwd, None)
@@ -2014,6 +3366,69 @@ fn expect_default_package_task_steps_for_workspace(
         expectations
             .expect_signtool_verify_driver_binary_sys_file(driver_name, &cwd, None)
             .expect_signtool_verify_cat_file(driver_name, &cwd, None)
+            .expect_signtool_verify_catalog_member(driver_name, &cwd, None)
+            .expect_certutil_dump_cert()
+    }
+
+    fn expect_write_package_log(mut self, log_dir: &Path, package_name: &str) -> Self {
+        self.mock_fs_provider
+            .expect_create_dir_all()
+            .with(eq(log_dir.to_path_buf()))
+            .once()
+            .returning(|_| Ok(()));
+        let expected_log_file_path = log_dir.join(format!("{package_name}.log"));
+        self.mock_fs_provider
+            .expect_write_to_file()
+            .withf(move |path: &Path, data: &[u8]| {
+                path == expected_log_file_path && data.starts_with(b"> cargo build ")
+            })
+            .once()
+            .returning(|_, _| Ok(()));
+        self
+    }
+
+    fn expect_write_diagnostic_bundle(
+        mut self,
+        bundle_path: &Path,
+        expected_contents: &'static [(&'static str, &'static str)],
+    ) -> Self {
+        let expected_bundle_dir = bundle_path
+            .parent()
+            .expect("bundle path has a parent")
+            .to_path_buf();
+        self.mock_fs_provider
+            .expect_create_dir_all()
+            .with(eq(expected_bundle_dir))
+            .once()
+            .returning(|_| Ok(()));
+        let expected_bundle_path = bundle_path.to_path_buf();
+        self.mock_fs_provider
+            .expect_write_to_file()
+            .withf(move |path: &Path, data: &[u8]| {
+                let Ok(mut archive) = zip::ZipArchive::new(Cursor::new(data)) else {
+                    return false;
+                };
+                path == expected_bundle_path
+                    && expected_contents.iter().all(|(file_name, expected_content)| {
+                        let mut content = String::new();
+                        archive
+                            .by_name(file_name)
+                            .is_ok_and(|mut file| file.read_to_string(&mut content).is_ok())
+                            && content.contains(expected_content)
+                    })
+            })
+            .once()
+            .returning(|_, _| Ok(()));
+        self
+    }
+
+    fn expect_file_exists(mut self, file_path: &Path, does_exist: bool) -> Self {
+        self.mock_fs_provider
+            .expect_exists()
+            .with(eq(file_path.to_owned()))
+            .once()
+            .returning(move |_| does_exist);
+        self
     }
 
     fn expect_root_manifest_exists(mut self, root_dir: &Path, does_exist: bool) -> Self {
@@ -2048,9 +3463,18 @@ fn expect_final_package_dir_exists(
             expected_target_dir.join(format!("{expected_driver_name_underscored}_package"));
         self.mock_fs_provider
             .expect_exists()
-            .with(eq(expected_final_package_dir_path))
+            .with(eq(expected_final_package_dir_path.clone()))
             .once()
             .returning(move |_| does_exist);
+        if does_exist {
+            // An existing package folder is checked for files that are not
+            // part of the package
+            self.mock_fs_provider
+                .expect_read_dir_entries()
+                .with(eq(expected_final_package_dir_path))
+                .once()
+                .returning(|_| Ok(vec![]));
+        }
         self
     }
 
@@ -2077,10 +3501,22 @@ fn expect_dir_created(mut self, driver_name: &str, cwd: &Path, created: bool) ->
     }
 
     fn expect_cargo_build(
+        self,
+        driver_name: &str,
+        cwd: &Path,
+        override_output: Option<Output>,
+    ) -> Self {
+        self.expect_cargo_build_with_env(driver_name, cwd, override_output, None)
+    }
+
+    /// Expects `cargo build` on the package, with `expected_env_var` set in
+    /// the environment passed to cargo if given
+    fn expect_cargo_build_with_env(
         mut self,
         driver_name: &str,
         cwd: &Path,
         override_output: Option<Output>,
+        expected_env_var: Option<(&'static str, &'static str)>,
     ) -> Self {
         // cargo build on the package
         let expected_cargo_command: &'static str = "cargo";
@@ -2100,7 +3536,7 @@ fn expect_cargo_build(
         .into_iter()
         .map(ToString::to_string)
         .collect();
-        if let Some(profile) = self.profile {
+        if let Some(profile) = &self.profile {
             expected_cargo_build_args.push("--profile".to_string());
             expected_cargo_build_args.push(profile.to_string());
         }
@@ -2120,6 +3556,44 @@ fn expect_cargo_build(
             stdout: vec![],
             stderr: vec![],
         });
+        self.mock_run_command
+            .expect_run()
+            .withf(
+                move |command: &str,
+                      args: &[&str],
+                      env_vars: &Option<&HashMap<&str, &str>>,
+                      _working_dir: &Option<&Path>|
+                      -> bool {
+                    command == expected_cargo_command
+                        && args == expected_cargo_build_args
+                        && expected_env_var.is_none_or(|(name, value)| {
+                            env_vars.is_some_and(|env_vars| env_vars.get(name) == Some(&value))
+                        })
+                },
+            )
+            .once()
+            .returning(move |_, _, _, _| Ok(expected_output.clone()));
+        self
+    }
+
+    fn expect_failing_cargo_clippy(mut self, driver_name: &str, cwd: &Path) -> Self {
+        let manifest_path = cwd
+            .join("Cargo.toml")
+            .to_string_lossy()
+            .trim_start_matches("\\\\?\\")
+            .to_string();
+        let expected_cargo_clippy_args: Vec<String> = vec![
+            "clippy",
+            "--message-format=json-render-diagnostics",
+            "-p",
+            driver_name,
+            "--manifest-path",
+            &manifest_path,
+            "-v",
+        ]
+        .into_iter()
+        .map(ToString::to_string)
+        .collect();
         self.mock_run_command
             .expect_run()
             .withf(
@@ -2128,11 +3602,21 @@ fn expect_cargo_build(
                       _env_vars: &Option<&HashMap<&str, &str>>,
                       _working_dir: &Option<&Path>|
                       -> bool {
-                    command == expected_cargo_command && args == expected_cargo_build_args
+                    command == "cargo" && args == expected_cargo_clippy_args
                 },
             )
             .once()
-            .returning(move |_, _, _, _| Ok(expected_output.clone()));
+            .returning(|command, args, _, _| {
+                Err(CommandError::from_output(
+                    command,
+                    args,
+                    &Output {
+                        status: ExitStatus::from_raw(101),
+                        stdout: vec![],
+                        stderr: b"error: could not compile `sample-kmdf`".to_vec(),
+                    },
+                ))
+            });
         self
     }
 
@@ -2212,6 +3696,44 @@ fn expect_inx_file_exists(
         self
     }
 
+    fn expect_read_inx_file(mut self, driver_name: &str, driver_dir: &Path) -> Self {
+        let expected_driver_name_underscored = driver_name.replace('-', "_");
+        let expected_inx_file_path =
+            driver_dir.join(format!("{expected_driver_name_underscored}.inx"));
+        let inx_content = if self.sample_class {
+            concat!(
+                "[Version]\r\nClass=Sample\r\nClassGuid={78A1C341-4539-11d3-B88D-00C04FAD5171}\r\n",
+                "[Manufacturer]\r\n%StdMfg%=Standard,NT$ARCH$.10.0...16299\r\n",
+            )
+        } else {
+            "[Manufacturer]\r\n%StdMfg%=Standard,NT$ARCH$.10.0...16299\r\n"
+        };
+        self.mock_fs_provider
+            .expect_read_file_to_string()
+            .with(eq(expected_inx_file_path))
+            .once()
+            .returning(move |_| Ok(inx_content.to_string()));
+        self
+    }
+
+    fn expect_read_driver_binary(
+        mut self,
+        driver_name: &str,
+        driver_dir: &Path,
+        binary_arch: CpuArchitecture,
+    ) -> Self {
+        let expected_driver_name_underscored = driver_name.replace('-', "_");
+        let expected_target_dir = self.setup_target_dir(driver_dir);
+        let expected_src_driver_dll_path =
+            expected_target_dir.join(format!("{expected_driver_name_underscored}.dll"));
+        self.mock_fs_provider
+            .expect_read_file()
+            .with(eq(expected_src_driver_dll_path))
+            .once()
+            .returning(move |_| Ok(get_pe_image(binary_arch)));
+        self
+    }
+
     fn expect_rename_driver_binary_dll_to_sys(
         mut self,
         driver_name: &str,
@@ -2349,6 +3871,24 @@ fn expect_copy_inx_file_to_package_folder(
         self
     }
 
+    fn expect_read_packaged_inf_file(mut self, driver_name: &str, device_id: &str) -> Self {
+        let expected_driver_name_underscored = driver_name.replace('-', "_");
+        let expected_target_dir = self.setup_target_dir(&self.cwd);
+        let expected_dest_driver_inf_path = expected_target_dir
+            .join(format!("{expected_driver_name_underscored}_package"))
+            .join(format!("{expected_driver_name_underscored}.inf"));
+        let inf_content = format!(
+            "[Manufacturer]\n%StdMfg% = Standard,NTamd64\n\n[Standard.NTamd64]\n%DeviceDesc% = \
+             {expected_driver_name_underscored}_Device, {device_id}\n"
+        );
+        self.mock_fs_provider
+            .expect_read_file_to_string()
+            .with(eq(expected_dest_driver_inf_path))
+            .once()
+            .returning(move |_| Ok(inf_content.clone()));
+        self
+    }
+
     fn expect_copy_map_file_to_package_folder(
         mut self,
         driver_name: &str,
@@ -2567,6 +4107,25 @@ fn expect_inf2cat(
         self
     }
 
+    fn expect_cat_file_exists(
+        mut self,
+        driver_name: &str,
+        driver_dir: &Path,
+        does_exist: bool,
+    ) -> Self {
+        let expected_driver_name_underscored = driver_name.replace('-', "_");
+        let expected_target_dir = self.setup_target_dir(driver_dir);
+        let expected_dest_driver_cat_file_path = expected_target_dir
+            .join(format!("{expected_driver_name_underscored}_package"))
+            .join(format!("{expected_driver_name_underscored}.cat"));
+        self.mock_fs_provider
+            .expect_exists()
+            .with(eq(expected_dest_driver_cat_file_path))
+            .once()
+            .returning(move |_| does_exist);
+        self
+    }
+
     fn expect_certmgr_exists_check(mut self, override_output: Option<Output>) -> Self {
         // check for cert in cert store using certmgr
         let expected_certmgr_command: &'static str = "certmgr.exe";
@@ -2928,6 +4487,94 @@ fn expect_signtool_verify_cat_file(
         self
     }
 
+    fn expect_certutil_dump_cert(mut self) -> Self {
+        // read the enhanced key usages of the test certificate in the store it
+        // signs from using certutil
+        self.mock_run_command
+            .expect_run()
+            .withf(
+                move |command: &str,
+                      args: &[&str],
+                      _env_vars: &Option<&HashMap<&str, &str>>,
+                      _working_dir: &Option<&Path>|
+                      -> bool {
+                    command == "certutil"
+                        && args == ["-user", "-v", "-store", "WDRTestCertStore", "WDRLocalTestCert"]
+                },
+            )
+            .once()
+            .returning(|_, _, _, _| {
+                Ok(Output {
+                    status: ExitStatus::default(),
+                    stdout: b"    Enhanced Key Usage\r\n        Code Signing \
+                              (1.3.6.1.5.5.7.3.3)\r\n"
+                        .to_vec(),
+                    stderr: vec![],
+                })
+            });
+        self
+    }
+
+    fn expect_signtool_verify_catalog_member(
+        mut self,
+        driver_name: &str,
+        driver_dir: &Path,
+        override_output: Option<Output>,
+    ) -> Self {
+        let expected_driver_name_underscored = driver_name.replace('-', "_");
+        let expected_target_dir = self.setup_target_dir(driver_dir);
+        let expected_final_package_dir_path =
+            expected_target_dir.join(format!("{expected_driver_name_underscored}_package"));
+        let expected_signtool_command: &'static str = "signtool";
+
+        // verify the cat file lists the hash of the signed driver binary using
+        // signtool
+        let expected_dest_driver_cat_file_path =
+            expected_final_package_dir_path.join(format!("{expected_driver_name_underscored}.cat"));
+        let expected_dest_driver_binary_path =
+            expected_final_package_dir_path.join(format!("{expected_driver_name_underscored}.sys"));
+        let expected_signtool_verify_args: Vec<String> = vec![
+            "verify".to_string(),
+            "/v".to_string(),
+            "/pa".to_string(),
+            "/c".to_string(),
+            expected_dest_driver_cat_file_path
+                .to_string_lossy()
+                .to_string(),
+            expected_dest_driver_binary_path
+                .to_string_lossy()
+                .to_string(),
+        ];
+        self.mock_run_command
+            .expect_run()
+            .withf(
+                move |command: &str,
+                      args: &[&str],
+                      _env_vars: &Option<&HashMap<&str, &str>>,
+                      _working_dir: &Option<&Path>|
+                      -> bool {
+                    command == expected_signtool_command && args == expected_signtool_verify_args
+                },
+            )
+            .once()
+            .returning(move |_, _, _, _| match override_output.clone() {
+                Some(output) => match output.status.code() {
+                    Some(0) => Ok(Output {
+                        status: ExitStatus::from_raw(0),
+                        stdout: vec![],
+                        stderr: vec![],
+                    }),
+                    _ => Err(CommandError::from_output("signtool", &[], &output)),
+                },
+                None => Ok(Output {
+                    status: ExitStatus::default(),
+                    stdout: vec![],
+                    stderr: vec![],
+                }),
+            });
+        self
+    }
+
     fn expect_detect_wdk_build_number(mut self, expected_wdk_build_number: u32) -> Self {
         self.mock_wdk_build_provider
             .expect_detect_wdk_build_number()
@@ -2936,6 +4583,18 @@ fn expect_detect_wdk_build_number(mut self, expected_wdk_build_number: u32) -> S
         self
     }
 
+    fn expect_detect_kmdf_versions(mut self, versions: &[(u32, u32)]) -> Self {
+        let versions = versions
+            .iter()
+            .map(|&(major, minor)| TwoPartVersion(major, minor))
+            .collect::<Vec<_>>();
+        self.mock_wdk_build_provider
+            .expect_detect_kmdf_versions()
+            .once()
+            .returning(move || Ok(versions.clone()));
+        self
+    }
+
     fn expect_infverif(
         mut self,
         driver_name: &str,
@@ -3095,16 +4754,42 @@ fn invalid_driver_cargo_toml() -> String {
 #[derive(Clone)]
 struct TestWdkMetadata(String);
 
+/// Returns a minimal PE image of a binary built for the given architecture
+fn get_pe_image(arch: CpuArchitecture) -> Vec<u8> {
+    let machine: u16 = match arch {
+        CpuArchitecture::Amd64 => 0x8664,
+        CpuArchitecture::Arm64 => 0xAA64,
+    };
+    // UMDF driver binaries must export the entry point the UMDF host calls,
+    // the exports of kernel mode driver binaries are not checked
+    pe::test_image(machine, &["FxDriverEntryUm"], None)
+}
+
 fn get_cargo_metadata(
     root_dir: &Path,
     package_list: Vec<TestMetadataPackage>,
     workspace_member_list: &[TestMetadataWorkspaceMemberId],
+    default_member_list: Option<&[TestMetadataWorkspaceMemberId]>,
     metadata: Option<TestWdkMetadata>,
 ) -> String {
     let metadata_section = match metadata {
         Some(metadata) => metadata.0,
         None => String::from("null"),
     };
+    // Require quotes around each member
+    let quote_members = |members: &[TestMetadataWorkspaceMemberId]| {
+        members
+            .iter()
+            .map(|s| format!("\"{}\"", s.0))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+    let default_members_section = default_member_list.map_or_else(String::new, |members| {
+        format!(
+            "\"workspace_default_members\": [{}],",
+            quote_members(members)
+        )
+    });
     format!(
         r#"
     {{
@@ -3114,6 +4799,7 @@ fn get_cargo_metadata(
             {}
             ],
         "workspace_members": [{}],
+        {}
         "metadata": {},
         "version": 1
     }}"#,
@@ -3124,12 +4810,8 @@ fn get_cargo_metadata(
             .map(|p| p.0)
             .collect::<Vec<String>>()
             .join(", "),
-        // Require quotes around each member
-        workspace_member_list
-            .iter()
-            .map(|s| format!("\"{}\"", s.0))
-            .collect::<Vec<String>>()
-            .join(", "),
+        quote_members(workspace_member_list),
+        default_members_section,
         metadata_section
     )
 }
@@ -3153,6 +4835,34 @@ fn get_cargo_metadata_package(
     } else {
         ("lib", "lib", "lib.rs")
     };
+    // Driver packages depend on the wdk crates of their driver type
+    let dependencies = if has_metadata {
+        let mut dependencies = vec![("wdk-sys", "null"), ("wdk-build", "\"build\"")];
+        if !metadata_section.contains("\"UMDF\"") {
+            dependencies.extend([("wdk-alloc", "null"), ("wdk-panic", "null")]);
+        }
+        dependencies
+            .iter()
+            .map(|(name, kind)| {
+                format!(
+                    r#"{{
+                    "name": "{name}",
+                    "source": null,
+                    "req": "*",
+                    "kind": {kind},
+                    "optional": false,
+                    "uses_default_features": true,
+                    "features": [],
+                    "target": null,
+                    "rename": null
+                }}"#
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    } else {
+        String::new()
+    };
     let manifest_path = root_dir
         .join("Cargo.toml")
         .to_string_lossy()
@@ -3172,7 +4882,7 @@ fn get_cargo_metadata_package(
             "name": "{default_package_name}",
             "version": "{default_package_version}",
             "id": "{package_id}",
-            "dependencies": [],
+            "dependencies": [{dependencies}],
             "targets": [
                 {{
                     "kind": [
@@ -3273,10 +4983,7 @@ fn create_cargo_build_output_json_with_manifest(
     let normalized_name = package_name.replace('-', "_");
 
     // Determine profile directory name
-    let profile_dir = match profile {
-        Some(Profile::Release) => "release",
-        _ => "debug",
-    };
+    let profile_dir = profile.as_ref().map_or("debug", Profile::target_dir_name);
 
     // For non-driver projects, use "lib" instead of "cdylib" to ensure BuildTask
     // returns DllNotFound
@@ -3368,7 +5075,7 @@ fn unparsable_output_fails() {
             io::Error::new(io::ErrorKind::InvalidData, "unparsable cargo message"),
         ));
 
-        let result = BuildAction::get_target_dir_from_output(&package, cargo_build_output);
+        let result = BuildAction::get_target_dir_from_output(&package, None, cargo_build_output);
         assert!(
             matches!(
                 result,
@@ -3403,7 +5110,7 @@ fn no_matching_artifact_fails() {
         );
         let cargo_build_output = Message::parse_stream(io::Cursor::new(output.stdout));
 
-        let result = BuildAction::get_target_dir_from_output(&package, cargo_build_output);
+        let result = BuildAction::get_target_dir_from_output(&package, None, cargo_build_output);
         assert!(
             matches!(
                 result,
@@ -3453,7 +5160,7 @@ fn matching_artifact_without_dll_fails() {
         let artifact_json = format!("{artifact_value}\n");
         let cargo_build_output = Message::parse_stream(io::Cursor::new(artifact_json.into_bytes()));
 
-        let result = BuildAction::get_target_dir_from_output(&package, cargo_build_output);
+        let result = BuildAction::get_target_dir_from_output(&package, None, cargo_build_output);
         assert!(
             matches!(
                 result,
@@ -3498,8 +5205,9 @@ fn matching_dll_resolves_target_dir() {
 
         let cargo_build_output = Message::parse_stream(io::Cursor::new(output.stdout));
 
-        let result = BuildAction::get_target_dir_from_output(&package, cargo_build_output)
-            .expect("expected target dir to be resolved");
+        let (result, out_dir) =
+            BuildAction::get_target_dir_from_output(&package, None, cargo_build_output)
+                .expect("expected target dir to be resolved");
 
         let expected_target_dir = std::path::absolute(
             PathBuf::from(dll_path)
@@ -3509,6 +5217,154 @@ fn matching_dll_resolves_target_dir() {
         .expect("absolute path failed");
 
         assert_eq!(result, expected_target_dir);
+        assert_eq!(out_dir, None);
+    }
+
+    #[test]
+    fn out_dir_of_the_build_script_of_the_package_is_resolved() {
+        let workspace_root_dir = PathBuf::from(r"C:\tmp\sample-kmdf");
+        let wdk_metadata = super::get_cargo_metadata_wdk_metadata("KMDF", 1, 0);
+        let (_workspace_member, package_json) = super::get_cargo_metadata_package(
+            &workspace_root_dir,
+            "sample-kmdf",
+            "0.0.1",
+            Some(&wdk_metadata),
+        );
+        let package = serde_json::from_str::<cargo_metadata::Package>(&package_json.0)
+            .expect("Failed to parse package json");
+
+        let build_script_executed = |package_id: &str, out_dir: &str| {
+            serde_json::json!({
+                "reason": "build-script-executed",
+                "package_id": package_id,
+                "linked_libs": [],
+                "linked_paths": [],
+                "cfgs": [],
+                "env": [],
+                "out_dir": out_dir,
+            })
+            .to_string()
+        };
+        let output = super::create_cargo_build_output_json_with_manifest(
+            "sample-kmdf",
+            "0.0.1",
+            &workspace_root_dir,
+            &workspace_root_dir.join("Cargo.toml"),
+            None,
+            None,
+            true,
+        );
+        let mut messages = [
+            build_script_executed(
+                package.id.repr.as_str(),
+                r"C:\tmp\sample-kmdf\target\debug\build\sample-kmdf-0123\out",
+            ),
+            build_script_executed(
+                "path+file:///C:/tmp/other#0.0.1",
+                r"C:\tmp\sample-kmdf\target\debug\build\other-4567\out",
+            ),
+        ]
+        .join("\n")
+        .into_bytes();
+        messages.push(b'\n');
+        messages.extend_from_slice(&output.stdout);
+        let cargo_build_output = Message::parse_stream(io::Cursor::new(messages));
+
+        let (_, out_dir) =
+            BuildAction::get_target_dir_from_output(&package, None, cargo_build_output)
+                .expect("expected target dir to be resolved");
+
+        assert_eq!(
+            out_dir,
+            Some(PathBuf::from(
+                r"C:\tmp\sample-kmdf\target\debug\build\sample-kmdf-0123\out"
+            ))
+        );
+    }
+
+    #[test]
+    fn artifact_of_another_target_than_the_driver_target_fails() {
+        let workspace_root_dir = PathBuf::from(r"C:\tmp\sample-kmdf");
+        let wdk_metadata = super::get_cargo_metadata_wdk_metadata("KMDF", 1, 0);
+        let (_workspace_member, package_json) = super::get_cargo_metadata_package(
+            &workspace_root_dir,
+            "sample-kmdf",
+            "0.0.1",
+            Some(&wdk_metadata),
+        );
+        let package = serde_json::from_str::<cargo_metadata::Package>(&package_json.0)
+            .expect("Failed to parse package json");
+
+        let output = super::create_cargo_build_output_json_with_manifest(
+            "sample-kmdf",
+            "0.0.1",
+            &workspace_root_dir,
+            &workspace_root_dir.join("Cargo.toml"),
+            None,
+            None,
+            true,
+        );
+        let cargo_build_output = Message::parse_stream(io::Cursor::new(output.stdout));
+
+        let result = BuildAction::get_target_dir_from_output(
+            &package,
+            Some("other_driver"),
+            cargo_build_output,
+        );
+        assert!(
+            matches!(
+                result,
+                Err(BuildActionError::CannotDetermineTargetDir(ref message))
+                if message.contains("Could not find matching cdylib artifact")
+            ),
+            "Expected CannotDetermineTargetDir no-matching-artifact error, got: {result:?}"
+        );
+    }
+}
+
+mod driver_target {
+    use std::path::PathBuf;
+
+    use super::{BuildAction, BuildActionError};
+    use crate::actions::build::package_settings::PackageSettings;
+
+    #[test]
+    fn driver_target_must_be_a_cdylib_target_of_the_package() {
+        let workspace_root_dir = PathBuf::from(r"C:\tmp\sample-kmdf");
+        let wdk_metadata = super::get_cargo_metadata_wdk_metadata("KMDF", 1, 0);
+        let (_workspace_member, package_json) = super::get_cargo_metadata_package(
+            &workspace_root_dir,
+            "sample-kmdf",
+            "0.0.1",
+            Some(&wdk_metadata),
+        );
+        let package = serde_json::from_str::<cargo_metadata::Package>(&package_json.0)
+            .expect("Failed to parse package json");
+
+        let settings = PackageSettings::default();
+        assert_eq!(
+            BuildAction::driver_target(&package, &settings).ok(),
+            Some(None)
+        );
+
+        let settings = PackageSettings {
+            driver_target: Some("sample-kmdf".to_string()),
+            ..PackageSettings::default()
+        };
+        assert_eq!(
+            BuildAction::driver_target(&package, &settings).ok(),
+            Some(Some("sample-kmdf"))
+        );
+
+        let settings = PackageSettings {
+            driver_target: Some("helper".to_string()),
+            ..PackageSettings::default()
+        };
+        assert!(matches!(
+            BuildAction::driver_target(&package, &settings),
+            Err(BuildActionError::DriverTargetNotFound { ref target_name, .. })
+                if target_name == "helper"
+        ));
     }
 }
 
@@ -3560,6 +5416,27 @@ fn parses_arm64_with_internal_whitespace() {
         );
     }
 
+    #[test]
+    fn runs_from_the_package_dir_to_use_its_pinned_toolchain() {
+        let cwd = PathBuf::from(r"C:\tmp\workspace");
+        let package_dir = cwd.join("crates").join("driver");
+        let mut test_build_action = TestBuildAction::new(cwd.clone(), None, None, false);
+        expect_cargo_rustc_print_cfg(
+            &mut test_build_action,
+            package_dir.clone(),
+            b"target_arch=\"aarch64\"\n".to_vec(),
+        );
+
+        let build_action =
+            super::initialize_build_action(&cwd, None, None, true, false, &test_build_action)
+                .expect("Failed to init build action");
+
+        let arch = build_action
+            .get_target_arch_from_cargo_rustc(&package_dir)
+            .expect("Expected target arch to be detected");
+        assert_eq!(arch, CpuArchitecture::Arm64);
+    }
+
     #[test]
     fn parses_target_when_features_specified() {
         let cwd = PathBuf::from(r"C:\tmp");
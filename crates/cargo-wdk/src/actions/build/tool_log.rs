@@ -0,0 +1,253 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module captures the output of the tools run while a driver is built
+//! and packaged, so that it can be written to a log file per package, e.g. to
+//! retain the log of every driver of a workspace as a CI artifact.
+
+use std::{cell::RefCell, collections::HashMap, io::Cursor, path::PathBuf, process::Output};
+
+use cargo_metadata::Message;
+use mockall_double::double;
+use tracing::info;
+
+use crate::providers::{
+    error::{CommandError, FileError},
+    exec::redact_args,
+};
+#[double]
+use crate::providers::fs::Fs;
+
+/// Output of the tools run for each package of a build
+#[derive(Debug, Default)]
+pub struct ToolLog {
+    // Directory the log files are written to. Nothing is recorded if it is
+    // not set, unless the output is captured.
+    log_dir: Option<PathBuf>,
+    // Whether the output is recorded without a log directory, e.g. for the
+    // diagnostic bundle of the build
+    capture: bool,
+    logs: RefCell<HashMap<String, String>>,
+}
+
+impl ToolLog {
+    /// Creates a tool log that writes the log files to `log_dir`. If
+    /// `log_dir` is `None`, the output of the tools is only recorded if
+    /// `capture` is set.
+    pub fn new(log_dir: Option<PathBuf>, capture: bool) -> Self {
+        Self {
+            log_dir,
+            capture,
+            logs: RefCell::default(),
+        }
+    }
+
+    /// Returns the output recorded for every package as `(package name, log)`
+    /// pairs, sorted by package name
+    pub fn logs(&self) -> Vec<(String, String)> {
+        let mut logs: Vec<(String, String)> = self
+            .logs
+            .borrow()
+            .iter()
+            .map(|(package_name, log)| (package_name.clone(), log.clone()))
+            .collect();
+        logs.sort();
+        logs
+    }
+
+    /// Records the command line and the output of a tool run for the package
+    pub fn record(
+        &self,
+        package_name: &str,
+        command: &str,
+        args: &[&str],
+        result: &Result<Output, CommandError>,
+    ) {
+        self.record_rendered(package_name, command, args, result, |stdout| {
+            String::from_utf8_lossy(stdout).into_owned()
+        });
+    }
+
+    /// Records the command line and the compiler diagnostics of a
+    /// `cargo build` run for the package. The stdout of `cargo build` consists
+    /// of JSON messages, of which only the rendered diagnostics are recorded.
+    pub fn record_cargo_build(
+        &self,
+        package_name: &str,
+        args: &[&str],
+        result: &Result<Output, CommandError>,
+    ) {
+        self.record_rendered(package_name, "cargo", args, result, |stdout| {
+            Message::parse_stream(Cursor::new(stdout))
+                .filter_map(|message| match message {
+                    Ok(Message::CompilerMessage(message)) => message.message.rendered,
+                    _ => None,
+                })
+                .collect()
+        });
+    }
+
+    /// Writes the output recorded for the package to
+    /// `<log dir>/<package name>.log`. Nothing is written if no log directory
+    /// is set.
+    pub fn write(&self, fs: &Fs, package_name: &str) -> Result<(), FileError> {
+        let Some(log_dir) = &self.log_dir else {
+            return Ok(());
+        };
+        let log = self
+            .logs
+            .borrow()
+            .get(package_name)
+            .cloned()
+            .unwrap_or_default();
+        fs.create_dir_all(log_dir)?;
+        let log_file_path = log_dir.join(format!("{package_name}.log"));
+        fs.write_to_file(&log_file_path, log.as_bytes())?;
+        info!(
+            "Build log of {package_name} written to {}",
+            log_file_path.display()
+        );
+        Ok(())
+    }
+
+    fn record_rendered(
+        &self,
+        package_name: &str,
+        command: &str,
+        args: &[&str],
+        result: &Result<Output, CommandError>,
+        render_stdout: impl Fn(&[u8]) -> String,
+    ) {
+        if self.log_dir.is_none() && !self.capture {
+            return;
+        }
+        let mut entry = format!("> {command} {}\n", redact_args(args).join(" "));
+        match result {
+            Ok(output) => {
+                entry.push_str(&render_stdout(&output.stdout));
+                entry.push_str(&String::from_utf8_lossy(&output.stderr));
+            }
+            Err(CommandError::CommandFailed { stdout, .. }) => {
+                entry.push_str(&render_stdout(stdout.as_bytes()));
+                entry.push_str(&format!("{command} failed\n"));
+            }
+            Err(CommandError::IoError(_, _, e)) => {
+                entry.push_str(&format!("{command} could not be run: {e}\n"));
+            }
+            Err(CommandError::Timeout(_, _, timeout)) => {
+                entry.push_str(&format!("{command} timed out after {timeout:?}\n"));
+            }
+        }
+        if !entry.ends_with('\n') {
+            entry.push('\n');
+        }
+        self.logs
+            .borrow_mut()
+            .entry(package_name.to_string())
+            .or_default()
+            .push_str(&entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(windows))]
+    use std::os::unix::process::ExitStatusExt;
+    #[cfg(windows)]
+    use std::os::windows::process::ExitStatusExt;
+    use std::{path::Path, process::ExitStatus};
+
+    use super::*;
+
+    #[test]
+    fn output_of_each_package_is_written_to_its_log_file() {
+        let tool_log = ToolLog::new(Some(PathBuf::from("C:/logs")), false);
+        tool_log.record(
+            "driver_a",
+            "inf2cat",
+            &["/os:10_x64"],
+            &Ok(Output {
+                status: ExitStatus::default(),
+                stdout: b"Signability test complete.".to_vec(),
+                stderr: vec![],
+            }),
+        );
+        tool_log.record(
+            "driver_b",
+            "stampinf",
+            &["-f", "driver_b.inf"],
+            &Err(CommandError::from_output(
+                "stampinf",
+                &["-f", "driver_b.inf"],
+                &Output {
+                    status: ExitStatus::from_raw(1),
+                    stdout: b"error: invalid section\n".to_vec(),
+                    stderr: vec![],
+                },
+            )),
+        );
+
+        let mut fs = Fs::default();
+        fs.expect_create_dir_all()
+            .withf(|path: &Path| path == Path::new("C:/logs"))
+            .once()
+            .returning(|_| Ok(()));
+        fs.expect_write_to_file()
+            .withf(|path: &Path, data: &[u8]| {
+                path == Path::new("C:/logs/driver_a.log")
+                    && data == b"> inf2cat /os:10_x64\nSignability test complete.\n"
+            })
+            .once()
+            .returning(|_, _| Ok(()));
+
+        assert!(tool_log.write(&fs, "driver_a").is_ok());
+        assert_eq!(
+            tool_log.logs.borrow().get("driver_b").map(String::as_str),
+            Some("> stampinf -f driver_b.inf\nerror: invalid section\nstampinf failed\n")
+        );
+    }
+
+    #[test]
+    fn output_is_discarded_without_log_dir() {
+        let tool_log = ToolLog::default();
+        tool_log.record(
+            "driver",
+            "inf2cat",
+            &[],
+            &Ok(Output {
+                status: ExitStatus::default(),
+                stdout: b"Signability test complete.".to_vec(),
+                stderr: vec![],
+            }),
+        );
+        assert!(tool_log.logs.borrow().is_empty());
+        assert!(tool_log.write(&Fs::default(), "driver").is_ok());
+    }
+
+    #[test]
+    fn output_is_captured_without_log_dir_when_requested() {
+        let tool_log = ToolLog::new(None, true);
+        for package_name in ["driver_b", "driver_a"] {
+            tool_log.record(
+                package_name,
+                "inf2cat",
+                &["/os:10_x64"],
+                &Ok(Output {
+                    status: ExitStatus::default(),
+                    stdout: b"Signability test complete.".to_vec(),
+                    stderr: vec![],
+                }),
+            );
+        }
+
+        // No log file is written without a log directory
+        assert!(tool_log.write(&Fs::default(), "driver_a").is_ok());
+        let log = "> inf2cat /os:10_x64\nSignability test complete.\n".to_string();
+        assert_eq!(
+            tool_log.logs(),
+            [
+                ("driver_a".to_string(), log.clone()),
+                ("driver_b".to_string(), log)
+            ]
+        );
+    }
+}
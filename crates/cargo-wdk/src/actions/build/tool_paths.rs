@@ -0,0 +1,92 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module defines the explicit executable paths of the packaging tools,
+//! which override the tools found on `PATH` when the WDK installation is
+//! incomplete or the wrong tool is picked up.
+
+use std::{
+    borrow::Cow,
+    io,
+    path::{Path, PathBuf, absolute},
+};
+
+/// Executables that are run instead of the packaging tools found on `PATH`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ToolPaths {
+    pub signtool: Option<PathBuf>,
+    pub certmgr: Option<PathBuf>,
+    pub stampinf: Option<PathBuf>,
+    pub inf2cat: Option<PathBuf>,
+}
+
+impl ToolPaths {
+    /// Returns the tool paths with relative paths resolved against the
+    /// current directory
+    ///
+    /// # Errors
+    /// * `io::Error` - If the current directory cannot be read
+    pub fn to_absolute(&self) -> io::Result<Self> {
+        let to_absolute = |path: &Option<PathBuf>| path.as_deref().map(absolute).transpose();
+        Ok(Self {
+            signtool: to_absolute(&self.signtool)?,
+            certmgr: to_absolute(&self.certmgr)?,
+            stampinf: to_absolute(&self.stampinf)?,
+            inf2cat: to_absolute(&self.inf2cat)?,
+        })
+    }
+
+    /// Returns the command that runs `tool`, which is its explicit executable
+    /// path if one was set and `tool` itself otherwise
+    pub fn command<'a>(&'a self, tool: &'a str) -> Cow<'a, str> {
+        let path = match tool {
+            "signtool" => &self.signtool,
+            "certmgr" | "certmgr.exe" => &self.certmgr,
+            "stampinf" => &self.stampinf,
+            "inf2cat" => &self.inf2cat,
+            _ => &None,
+        };
+        path.as_deref()
+            .map_or(Cow::Borrowed(tool), Path::to_string_lossy)
+    }
+
+    /// Returns the explicit executable paths that were set, along with the
+    /// name of the tool they replace
+    pub fn overrides(&self) -> impl Iterator<Item = (&'static str, &Path)> {
+        [
+            ("signtool", &self.signtool),
+            ("certmgr", &self.certmgr),
+            ("stampinf", &self.stampinf),
+            ("inf2cat", &self.inf2cat),
+        ]
+        .into_iter()
+        .filter_map(|(tool, path)| Some((tool, path.as_deref()?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::ToolPaths;
+
+    #[test]
+    fn explicit_tool_paths_replace_the_tools_found_on_path() {
+        let tool_paths = ToolPaths {
+            signtool: Some(PathBuf::from("C:/tools/signtool.exe")),
+            certmgr: Some(PathBuf::from("C:/tools/certmgr.exe")),
+            ..ToolPaths::default()
+        };
+
+        assert_eq!(tool_paths.command("signtool"), "C:/tools/signtool.exe");
+        assert_eq!(tool_paths.command("certmgr.exe"), "C:/tools/certmgr.exe");
+        assert_eq!(tool_paths.command("stampinf"), "stampinf");
+        assert_eq!(tool_paths.command("infverif"), "infverif");
+        assert_eq!(
+            tool_paths
+                .overrides()
+                .map(|(tool, _)| tool)
+                .collect::<Vec<_>>(),
+            ["signtool", "certmgr"]
+        );
+    }
+}
@@ -0,0 +1,176 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module generates the scripts written to a driver package by the
+//! `--emit-uninstall` option of the build action. The PowerShell script looks
+//! up the published names, e.g. `oem42.inf`, that the INFs of the package were
+//! given when they were installed, deletes the driver packages with `pnputil`
+//! and removes the services created by the `AddService` directives of the INFs.
+//! The command script runs the PowerShell script, so that it can be started
+//! with a double click.
+
+use crate::actions::{build::install_summary::InstallSummary, inf::InfFile};
+
+/// File name of the PowerShell uninstall script
+pub const UNINSTALL_PS1_FILE_NAME: &str = "uninstall.ps1";
+/// File name of the command script that runs the PowerShell uninstall script
+pub const UNINSTALL_CMD_FILE_NAME: &str = "uninstall.cmd";
+const STRINGS_SECTION_NAME: &str = "Strings";
+
+/// INFs and services removed by the uninstall script of a driver package
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UninstallScript {
+    /// File names of the INFs of the package
+    pub inf_file_names: Vec<String>,
+    /// Names of the services created by the INFs
+    pub services: Vec<String>,
+}
+
+impl UninstallScript {
+    /// Adds an INF of the package and the services it creates. `%strkey%`
+    /// service names are looked up in the `[Strings]` section of the INF.
+    pub fn add_inf(&mut self, inf_file_name: &str, inf_file: &InfFile) {
+        self.inf_file_names.push(inf_file_name.to_string());
+        for service in InstallSummary::from_inf(inf_file).services {
+            let name = expand_string_key(inf_file, &service.name);
+            if !self.services.contains(&name) {
+                self.services.push(name);
+            }
+        }
+    }
+
+    /// Renders the PowerShell uninstall script. Drivers installed from INFs
+    /// with the same file name are deleted whatever their version.
+    #[must_use]
+    pub fn to_powershell(&self) -> String {
+        let inf_file_names = format!("$infFileNames = {}", powershell_array(&self.inf_file_names));
+        let services = format!("$services = {}", powershell_array(&self.services));
+        windows_lines(&[
+            "#Requires -RunAsAdministrator",
+            "# Deletes the driver packages installed from the INFs below and the services they \
+             created",
+            "$ErrorActionPreference = 'Stop'",
+            "",
+            &inf_file_names,
+            &services,
+            "",
+            "$drivers = Get-WindowsDriver -Online | Where-Object {",
+            "    $infFileNames -contains (Split-Path -Leaf $_.OriginalFileName)",
+            "}",
+            "foreach ($driver in $drivers) {",
+            "    Write-Host \"Deleting driver package $($driver.Driver)\"",
+            "    pnputil /delete-driver $driver.Driver /uninstall /force",
+            "    # 3010 means that a reboot is required to complete the removal",
+            "    if ($LASTEXITCODE -ne 0 -and $LASTEXITCODE -ne 3010) {",
+            "        throw \"pnputil failed to delete $($driver.Driver)\"",
+            "    }",
+            "}",
+            "foreach ($service in $services) {",
+            "    if (Get-Service -Name $service -ErrorAction SilentlyContinue) {",
+            "        Write-Host \"Deleting service $service\"",
+            "        sc.exe stop $service | Out-Null",
+            "        sc.exe delete $service",
+            "    }",
+            "}",
+        ])
+    }
+
+    /// Renders the command script that runs the PowerShell uninstall script
+    /// next to it
+    #[must_use]
+    pub fn to_cmd() -> String {
+        let run_script = format!(
+            "powershell.exe -NoProfile -ExecutionPolicy Bypass -File \
+             \"%~dp0{UNINSTALL_PS1_FILE_NAME}\" %*"
+        );
+        windows_lines(&["@echo off", &run_script, "exit /b %ERRORLEVEL%"])
+    }
+}
+
+/// Returns the value of a `%strkey%` token from the `[Strings]` section of the
+/// INF, or the value itself if it is not a token or the key is not defined
+fn expand_string_key(inf_file: &InfFile, value: &str) -> String {
+    value
+        .strip_prefix('%')
+        .and_then(|value| value.strip_suffix('%'))
+        .filter(|key| !key.is_empty())
+        .and_then(|key| inf_file.values(STRINGS_SECTION_NAME, key))
+        .and_then(<[String]>::first)
+        .cloned()
+        .unwrap_or_else(|| value.to_string())
+}
+
+/// Renders the values as a PowerShell array of single-quoted strings
+fn powershell_array(values: &[String]) -> String {
+    let items = values
+        .iter()
+        .map(|value| format!("'{}'", value.replace('\'', "''")))
+        .collect::<Vec<_>>();
+    format!("@({})", items.join(", "))
+}
+
+/// Joins the lines with the CRLF line endings of Windows scripts
+fn windows_lines(lines: &[&str]) -> String {
+    lines.iter().map(|line| format!("{line}\r\n")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UninstallScript;
+    use crate::actions::inf::InfFile;
+
+    const INF_CONTENT: &str = r#"
+[Device_Install.NT.Services]
+AddService = %ServiceName%, 0x00000002, Driver_Service_Install
+AddService = driver_filter, , Filter_Service_Install
+
+[Driver_Service_Install]
+ServiceBinary = %12%\driver.sys
+
+[Strings]
+ServiceName = "driver"
+"#;
+
+    #[test]
+    fn services_of_every_inf_are_collected_with_their_string_keys_expanded() {
+        let inf_file = InfFile::parse(INF_CONTENT);
+        let mut script = UninstallScript::default();
+        script.add_inf("driver.inf", &inf_file);
+        script.add_inf("driver_filter.inf", &inf_file);
+
+        assert_eq!(
+            script,
+            UninstallScript {
+                inf_file_names: vec!["driver.inf".to_string(), "driver_filter.inf".to_string()],
+                services: vec!["driver".to_string(), "driver_filter".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn powershell_script_deletes_the_driver_packages_and_services() {
+        let script = UninstallScript {
+            inf_file_names: vec!["driver.inf".to_string()],
+            services: vec!["it's".to_string()],
+        }
+        .to_powershell();
+
+        assert!(script.starts_with("#Requires -RunAsAdministrator\r\n"));
+        assert!(script.contains("$infFileNames = @('driver.inf')\r\n"));
+        assert!(script.contains("$services = @('it''s')\r\n"));
+        assert!(script.contains("pnputil /delete-driver $driver.Driver /uninstall /force\r\n"));
+        assert!(
+            script
+                .split_inclusive('\n')
+                .all(|line| line.ends_with("\r\n"))
+        );
+    }
+
+    #[test]
+    fn cmd_script_runs_the_powershell_script_next_to_it() {
+        assert_eq!(
+            UninstallScript::to_cmd(),
+            "@echo off\r\npowershell.exe -NoProfile -ExecutionPolicy Bypass -File \
+             \"%~dp0uninstall.ps1\" %*\r\nexit /b %ERRORLEVEL%\r\n"
+        );
+    }
+}
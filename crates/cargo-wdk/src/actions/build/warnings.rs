@@ -0,0 +1,139 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module collects the warnings that the WDK tools print while a driver
+//! is packaged. The output of these tools is only logged at debug level, so
+//! the collected warnings are reprinted in a consolidated section at the end
+//! of the build.
+
+use std::cell::RefCell;
+
+use tracing::warn;
+
+/// A warning printed by a tool while packaging a driver
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildWarning {
+    /// Name of the package being packaged when the warning was printed
+    pub package_name: String,
+    /// Name of the tool that printed the warning, e.g. `inf2cat`
+    pub tool: String,
+    /// The warning as printed by the tool
+    pub message: String,
+}
+
+/// Collection of the warnings printed during a build
+#[derive(Debug, Default)]
+pub struct BuildWarnings {
+    warnings: RefCell<Vec<BuildWarning>>,
+}
+
+impl BuildWarnings {
+    /// Records every line of `stdout` that reports a warning. The WDK tools
+    /// prefix warnings with `warning` in various casings, e.g. `WARNING(1324)`
+    /// for `infverif` and `Warning 22.9.1` for `inf2cat`.
+    pub fn record_from_output(&self, package_name: &str, tool: &str, stdout: &[u8]) {
+        let stdout = String::from_utf8_lossy(stdout);
+        let mut warnings = self.warnings.borrow_mut();
+        for line in stdout.lines().map(str::trim) {
+            if line.to_ascii_lowercase().contains("warning") {
+                warnings.push(BuildWarning {
+                    package_name: package_name.to_string(),
+                    tool: tool.to_string(),
+                    message: line.to_string(),
+                });
+            }
+        }
+    }
+
+    /// Records a warning raised by a check of cargo-wdk itself rather than
+    /// printed by a tool
+    pub fn record(&self, package_name: &str, tool: &str, message: String) {
+        self.warnings.borrow_mut().push(BuildWarning {
+            package_name: package_name.to_string(),
+            tool: tool.to_string(),
+            message,
+        });
+    }
+
+    /// Returns the number of recorded warnings
+    pub fn len(&self) -> usize {
+        self.warnings.borrow().len()
+    }
+
+    /// Returns `true` if no warnings were recorded
+    pub fn is_empty(&self) -> bool {
+        self.warnings.borrow().is_empty()
+    }
+
+    /// Logs the recorded warnings grouped by package and tool. Nothing is
+    /// logged if there are no warnings.
+    pub fn report(&self) {
+        if self.is_empty() {
+            return;
+        }
+        for line in self.summary_lines() {
+            warn!("{line}");
+        }
+    }
+
+    /// Returns the lines of the consolidated warnings section. Groups are
+    /// listed in the order their first warning was recorded.
+    pub fn summary_lines(&self) -> Vec<String> {
+        let warnings = self.warnings.borrow();
+        let mut groups: Vec<((&str, &str), Vec<&str>)> = Vec::new();
+        for warning in warnings.iter() {
+            let key = (warning.package_name.as_str(), warning.tool.as_str());
+            if let Some((_, messages)) = groups.iter_mut().find(|(group_key, _)| *group_key == key)
+            {
+                messages.push(&warning.message);
+            } else {
+                groups.push((key, vec![&warning.message]));
+            }
+        }
+
+        let count = warnings.len();
+        let mut lines = vec![format!(
+            "{count} warning{}",
+            if count == 1 { "" } else { "s" }
+        )];
+        for ((package_name, tool), messages) in groups {
+            lines.push(format!("  {package_name} ({tool}):"));
+            lines.extend(messages.iter().map(|message| format!("    {message}")));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_from_output_keeps_only_warning_lines() {
+        let warnings = BuildWarnings::default();
+        warnings.record_from_output(
+            "driver",
+            "infverif",
+            b"INF is VALID\r\nWARNING(1324) in driver.inf, line 12: Section not used\r\n",
+        );
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn summary_lines_group_warnings_by_package_and_tool() {
+        let warnings = BuildWarnings::default();
+        warnings.record_from_output("driver_a", "inf2cat", b"Warning 22.9.1: first\n");
+        warnings.record_from_output("driver_b", "stampinf", b"Warning: second\n");
+        warnings.record_from_output("driver_a", "inf2cat", b"Warning 22.9.1: third\n");
+        assert_eq!(
+            warnings.summary_lines(),
+            vec![
+                "3 warnings",
+                "  driver_a (inf2cat):",
+                "    Warning 22.9.1: first",
+                "    Warning 22.9.1: third",
+                "  driver_b (stampinf):",
+                "    Warning: second",
+            ]
+        );
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module contains the framework versions passed to the build action in
+//! `--kmdf-version` and `--umdf-version`. They override the KMDF or UMDF
+//! version of the `driver-model` in the WDK metadata, both for the headers the
+//! driver is compiled against and for the version `stampinf` writes to the
+//! INF.
+
+use wdk_build::{
+    ConfigError,
+    DriverConfig,
+    KMDF_VERSION_OVERRIDE_ENV_VAR,
+    UMDF_VERSION_OVERRIDE_ENV_VAR,
+    WdfVersion,
+};
+
+/// KMDF and UMDF versions overriding the driver model of the WDK metadata
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WdfVersionOverrides {
+    pub kmdf: Option<WdfVersion>,
+    pub umdf: Option<WdfVersion>,
+}
+
+impl WdfVersionOverrides {
+    /// Returns the driver model with the KMDF or UMDF version replaced by the
+    /// overriding version of its framework, if any, the same way `wdk-build`
+    /// applies it when the driver is compiled
+    pub fn apply(self, driver_model: DriverConfig) -> Result<DriverConfig, ConfigError> {
+        driver_model.with_wdf_version_overrides(self.kmdf, self.umdf)
+    }
+
+    /// Returns the environment variables through which `wdk-build` applies
+    /// the overriding versions when the driver is compiled
+    pub fn env_vars(self) -> Vec<(&'static str, String)> {
+        [
+            (KMDF_VERSION_OVERRIDE_ENV_VAR, self.kmdf),
+            (UMDF_VERSION_OVERRIDE_ENV_VAR, self.umdf),
+        ]
+        .into_iter()
+        .filter_map(|(env_var, version)| Some((env_var, version?.to_string())))
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wdk_build::{ConfigError, DriverConfig, KmdfConfig, UmdfConfig, WdfVersion};
+
+    use super::WdfVersionOverrides;
+
+    #[test]
+    fn only_the_version_of_the_driver_framework_is_overridden() {
+        let overrides = WdfVersionOverrides {
+            kmdf: Some(WdfVersion {
+                major: 1,
+                minor: 31,
+            }),
+            umdf: None,
+        };
+        let umdf_config = UmdfConfig {
+            umdf_version_major: 2,
+            target_umdf_version_minor: 33,
+            minimum_umdf_version_minor: None,
+        };
+
+        assert_eq!(
+            overrides
+                .apply(DriverConfig::Kmdf(KmdfConfig {
+                    kmdf_version_major: 1,
+                    target_kmdf_version_minor: 33,
+                    minimum_kmdf_version_minor: None,
+                }))
+                .ok(),
+            Some(DriverConfig::Kmdf(KmdfConfig {
+                kmdf_version_major: 1,
+                target_kmdf_version_minor: 31,
+                minimum_kmdf_version_minor: None,
+            }))
+        );
+        assert_eq!(
+            overrides.apply(DriverConfig::Umdf(umdf_config)).ok(),
+            Some(DriverConfig::Umdf(umdf_config))
+        );
+        assert_eq!(
+            overrides.apply(DriverConfig::Wdm).ok(),
+            Some(DriverConfig::Wdm)
+        );
+        assert_eq!(
+            overrides.env_vars(),
+            [("WDK_BUILD_KMDF_VERSION", "1.31".to_string())]
+        );
+    }
+
+    #[test]
+    fn version_below_the_minimum_of_the_driver_is_rejected() {
+        let overrides = WdfVersionOverrides {
+            kmdf: Some(WdfVersion {
+                major: 1,
+                minor: 29,
+            }),
+            umdf: None,
+        };
+
+        assert!(matches!(
+            overrides.apply(DriverConfig::Kmdf(KmdfConfig {
+                kmdf_version_major: 1,
+                target_kmdf_version_minor: 33,
+                minimum_kmdf_version_minor: Some(31),
+            })),
+            Err(ConfigError::WdfVersionBelowMinimum {
+                minimum_minor: 31,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,227 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module checks the dependencies of a driver package on the `wdk`
+//! crates against the driver type in its WDK metadata, so that a
+//! misconfigured `Cargo.toml` is reported with the change that fixes it
+//! before the driver is compiled, instead of failing with errors of the
+//! linker or the `wdk-sys` build script.
+//!
+//! Every driver depends on `wdk-sys`, whose bindings are generated for the
+//! driver type by the `wdk-build` build dependency. Kernel-mode drivers are
+//! `no_std` and use `wdk-alloc` and `wdk-panic` for their global allocator and
+//! panic handler, while user-mode drivers use those of `std`, which conflict
+//! with them.
+
+use cargo_metadata::{DependencyKind, Package};
+
+use crate::actions::DriverType;
+
+const WDK_SYS_CRATE_NAME: &str = "wdk-sys";
+const WDK_BUILD_CRATE_NAME: &str = "wdk-build";
+const WDK_ALLOC_CRATE_NAME: &str = "wdk-alloc";
+const WDK_PANIC_CRATE_NAME: &str = "wdk-panic";
+/// Feature of `wdk-sys` that stubs the WDK functions for unit tests
+const TEST_STUBS_FEATURE: &str = "test-stubs";
+
+/// Problems found in the `wdk` crate dependencies of a driver package, each
+/// described by the change to `Cargo.toml` that fixes it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WdkDependencyProblems {
+    /// Misconfigurations that fail the build of the driver
+    pub errors: Vec<String>,
+    /// Dependencies a driver usually needs, unless it provides their
+    /// functionality itself
+    pub warnings: Vec<String>,
+}
+
+/// Checks the dependencies of `package` on the `wdk` crates for a driver of
+/// the given driver type
+#[must_use]
+pub fn check(package: &Package, driver_type: DriverType) -> WdkDependencyProblems {
+    let mut problems = WdkDependencyProblems::default();
+    let dependency = |name: &str, kind: DependencyKind| {
+        package
+            .dependencies
+            .iter()
+            .find(|dependency| dependency.name == name && dependency.kind == kind)
+    };
+
+    match dependency(WDK_SYS_CRATE_NAME, DependencyKind::Normal) {
+        None => problems.errors.push(format!(
+            "add `{WDK_SYS_CRATE_NAME}` to [dependencies], it provides the WDK bindings of the \
+             driver"
+        )),
+        Some(wdk_sys) if wdk_sys.features.iter().any(|f| f == TEST_STUBS_FEATURE) => {
+            problems.errors.push(format!(
+                "remove the `{TEST_STUBS_FEATURE}` feature from `{WDK_SYS_CRATE_NAME}` in \
+                 [dependencies] and enable it in [dev-dependencies] instead, it replaces the WDK \
+                 functions with stubs for unit tests"
+            ));
+        }
+        Some(_) => {}
+    }
+    if dependency(WDK_BUILD_CRATE_NAME, DependencyKind::Build).is_none() {
+        problems.errors.push(format!(
+            "add `{WDK_BUILD_CRATE_NAME}` to [build-dependencies], it configures the build of \
+             {driver_type} drivers in build.rs"
+        ));
+    }
+
+    for crate_name in [WDK_ALLOC_CRATE_NAME, WDK_PANIC_CRATE_NAME] {
+        let has_dependency = dependency(crate_name, DependencyKind::Normal).is_some();
+        match driver_type {
+            DriverType::Umdf if has_dependency => problems.errors.push(format!(
+                "remove `{crate_name}` from [dependencies], it is only for kernel-mode drivers and \
+                 conflicts with std in {driver_type} drivers"
+            )),
+            DriverType::Kmdf | DriverType::Wdm if !has_dependency => {
+                problems.warnings.push(format!(
+                    "add `{crate_name}` to [dependencies], {driver_type} drivers are no_std and \
+                     need it unless they provide their own {}",
+                    if crate_name == WDK_ALLOC_CRATE_NAME {
+                        "global allocator"
+                    } else {
+                        "panic handler"
+                    }
+                ));
+            }
+            _ => {}
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use cargo_metadata::Package;
+    use serde_json::json;
+
+    use super::{WdkDependencyProblems, check};
+    use crate::actions::DriverType;
+
+    /// Returns a package with the given `(name, kind, features)` dependencies
+    fn package_with_dependencies(dependencies: &[(&str, Option<&str>, &[&str])]) -> Package {
+        let dependencies = dependencies
+            .iter()
+            .map(|(name, kind, features)| {
+                json!({
+                    "name": name,
+                    "source": null,
+                    "req": "*",
+                    "kind": kind,
+                    "optional": false,
+                    "uses_default_features": true,
+                    "features": features,
+                    "target": null,
+                    "rename": null,
+                    "registry": null,
+                    "path": null
+                })
+            })
+            .collect::<Vec<_>>();
+        serde_json::from_value(json!({
+            "name": "driver",
+            "version": "0.1.0",
+            "id": "path+file:///C:/driver#0.1.0",
+            "dependencies": dependencies,
+            "targets": [],
+            "features": {},
+            "manifest_path": "C:/driver/Cargo.toml",
+            "authors": [],
+            "categories": [],
+            "keywords": [],
+            "edition": "2021",
+            "metadata": null
+        }))
+        .expect("package should deserialize")
+    }
+
+    const KERNEL_MODE_DEPENDENCIES: &[(&str, Option<&str>, &[&str])] = &[
+        ("wdk-sys", None, &[]),
+        ("wdk-alloc", None, &[]),
+        ("wdk-panic", None, &[]),
+        ("wdk-build", Some("build"), &[]),
+    ];
+    const USER_MODE_DEPENDENCIES: &[(&str, Option<&str>, &[&str])] = &[
+        ("wdk-sys", None, &[]),
+        ("wdk-sys", Some("dev"), &["test-stubs"]),
+        ("wdk-build", Some("build"), &[]),
+    ];
+
+    #[test]
+    fn dependencies_matching_the_driver_type_pass() {
+        for (driver_type, dependencies) in [
+            (DriverType::Kmdf, KERNEL_MODE_DEPENDENCIES),
+            (DriverType::Wdm, KERNEL_MODE_DEPENDENCIES),
+            (DriverType::Umdf, USER_MODE_DEPENDENCIES),
+        ] {
+            assert_eq!(
+                check(&package_with_dependencies(dependencies), driver_type),
+                WdkDependencyProblems::default(),
+                "{driver_type}"
+            );
+        }
+    }
+
+    #[test]
+    fn missing_wdk_sys_and_wdk_build_dependencies_are_errors_for_every_driver_type() {
+        // `wdk-build` as a normal dependency does not configure the build
+        let package = package_with_dependencies(&[
+            ("wdk-build", None, &[]),
+            ("wdk-alloc", None, &[]),
+            ("wdk-panic", None, &[]),
+        ]);
+        for driver_type in [DriverType::Kmdf, DriverType::Wdm] {
+            let problems = check(&package, driver_type);
+            assert_eq!(problems.errors.len(), 2, "{driver_type}");
+            assert!(problems.errors[0].starts_with("add `wdk-sys` to [dependencies]"));
+            assert!(problems.errors[1].starts_with("add `wdk-build` to [build-dependencies]"));
+            assert!(problems.warnings.is_empty());
+        }
+        let problems = check(
+            &package_with_dependencies(&[("wdk-build", Some("build"), &[])]),
+            DriverType::Umdf,
+        );
+        assert_eq!(problems.errors.len(), 1);
+        assert!(problems.errors[0].starts_with("add `wdk-sys` to [dependencies]"));
+    }
+
+    #[test]
+    fn test_stubs_feature_of_wdk_sys_is_an_error_in_the_dependencies() {
+        let package = package_with_dependencies(&[
+            ("wdk-sys", None, &["usb", "test-stubs"]),
+            ("wdk-build", Some("build"), &[]),
+        ]);
+        let problems = check(&package, DriverType::Umdf);
+        assert_eq!(problems.errors.len(), 1);
+        assert!(problems.errors[0].starts_with("remove the `test-stubs` feature"));
+    }
+
+    #[test]
+    fn kernel_mode_crates_are_errors_for_umdf_and_warnings_when_missing_for_kmdf_and_wdm() {
+        let problems = check(
+            &package_with_dependencies(KERNEL_MODE_DEPENDENCIES),
+            DriverType::Umdf,
+        );
+        assert_eq!(
+            problems.errors,
+            [
+                "remove `wdk-alloc` from [dependencies], it is only for kernel-mode drivers and \
+                 conflicts with std in umdf drivers",
+                "remove `wdk-panic` from [dependencies], it is only for kernel-mode drivers and \
+                 conflicts with std in umdf drivers",
+            ]
+        );
+
+        for driver_type in [DriverType::Kmdf, DriverType::Wdm] {
+            let problems = check(
+                &package_with_dependencies(USER_MODE_DEPENDENCIES),
+                driver_type,
+            );
+            assert!(problems.errors.is_empty(), "{driver_type}");
+            assert_eq!(problems.warnings.len(), 2, "{driver_type}");
+            assert!(problems.warnings[0].contains("own global allocator"));
+            assert!(problems.warnings[1].contains("own panic handler"));
+        }
+    }
+}
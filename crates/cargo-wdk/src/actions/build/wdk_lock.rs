@@ -0,0 +1,171 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module implements the WDK lock used by the `--locked-wdk` and
+//! `--update-wdk-lock` options of the build action. The lock records the
+//! build number of the WDK that was detected when the lock was written in the
+//! target directory, so that later builds can detect that the WDK installed on
+//! the machine changed.
+
+use std::path::{Path, PathBuf};
+
+use mockall_double::double;
+use tracing::{debug, info};
+
+use crate::actions::build::error::BuildActionError;
+#[double]
+use crate::providers::fs::Fs;
+
+/// Name of the file in the target directory that records the WDK build number
+const WDK_LOCK_FILE_NAME: &str = "wdk.lock";
+
+/// How the build action uses the WDK lock
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WdkLockMode {
+    /// The WDK lock is neither read nor written.
+    #[default]
+    Off,
+    /// Fail if the detected WDK differs from the one recorded in the lock. The
+    /// lock is written if it does not exist yet.
+    Locked,
+    /// Record the detected WDK in the lock, replacing any previous record.
+    Update,
+}
+
+/// Returns the path of the WDK lock file in the given target directory
+pub fn wdk_lock_file_path(target_dir: &Path) -> PathBuf {
+    target_dir.join(WDK_LOCK_FILE_NAME)
+}
+
+/// Applies the WDK lock `mode` for the WDK with build number
+/// `wdk_build_number` to the lock file in `target_dir`.
+///
+/// # Errors
+/// * `BuildActionError::WdkChangedSinceLock` - If the mode is
+///   [`WdkLockMode::Locked`] and the recorded WDK build number differs from the
+///   detected one.
+/// * `BuildActionError::InvalidWdkLock` - If the lock file does not contain a
+///   WDK build number.
+/// * `BuildActionError::FileIo` - If there is an IO error reading or writing
+///   the lock file.
+pub fn apply(
+    fs: &Fs,
+    mode: WdkLockMode,
+    target_dir: &Path,
+    wdk_build_number: u32,
+) -> Result<(), BuildActionError> {
+    let lock_file_path = wdk_lock_file_path(target_dir);
+    match mode {
+        WdkLockMode::Off => Ok(()),
+        WdkLockMode::Locked if fs.exists(&lock_file_path) => {
+            let content = fs.read_file_to_string(&lock_file_path)?;
+            let recorded_build_number = content.trim().parse::<u32>().map_err(|_| {
+                BuildActionError::InvalidWdkLock(lock_file_path.clone(), content.clone())
+            })?;
+            debug!(
+                "WDK build number recorded in {}: {recorded_build_number}, detected: \
+                 {wdk_build_number}",
+                lock_file_path.display()
+            );
+            if recorded_build_number != wdk_build_number {
+                return Err(BuildActionError::WdkChangedSinceLock {
+                    lock_file_path,
+                    recorded_build_number,
+                    detected_build_number: wdk_build_number,
+                });
+            }
+            Ok(())
+        }
+        WdkLockMode::Locked | WdkLockMode::Update => {
+            info!(
+                "Recording WDK build {wdk_build_number} in {}",
+                lock_file_path.display()
+            );
+            if !fs.exists(target_dir) {
+                fs.create_dir_all(target_dir)?;
+            }
+            fs.write_to_file(&lock_file_path, format!("{wdk_build_number}\n").as_bytes())?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mockall::predicate::eq;
+
+    use super::*;
+
+    const TARGET_DIR: &str = "C:\\driver\\target";
+
+    fn mock_lock_file(fs: &mut Fs, content: Option<&'static str>) {
+        let lock_file_path = wdk_lock_file_path(Path::new(TARGET_DIR));
+        fs.expect_exists()
+            .with(eq(lock_file_path.clone()))
+            .returning(move |_| content.is_some());
+        if let Some(content) = content {
+            fs.expect_read_file_to_string()
+                .with(eq(lock_file_path))
+                .once()
+                .returning(move |_| Ok(content.to_string()));
+        }
+    }
+
+    fn expect_lock_file_written(fs: &mut Fs, expected_content: &'static str) {
+        fs.expect_exists()
+            .with(eq(PathBuf::from(TARGET_DIR)))
+            .returning(|_| true);
+        let lock_file_path = wdk_lock_file_path(Path::new(TARGET_DIR));
+        fs.expect_write_to_file()
+            .withf(move |path, content| {
+                path == lock_file_path && content == expected_content.as_bytes()
+            })
+            .once()
+            .returning(|_, _| Ok(()));
+    }
+
+    #[test]
+    fn locked_succeeds_when_recorded_wdk_matches() {
+        let mut fs = Fs::default();
+        mock_lock_file(&mut fs, Some("26100\n"));
+        assert!(apply(&fs, WdkLockMode::Locked, Path::new(TARGET_DIR), 26100).is_ok());
+    }
+
+    #[test]
+    fn locked_fails_when_recorded_wdk_differs() {
+        let mut fs = Fs::default();
+        mock_lock_file(&mut fs, Some("22621\n"));
+        assert!(matches!(
+            apply(&fs, WdkLockMode::Locked, Path::new(TARGET_DIR), 26100),
+            Err(BuildActionError::WdkChangedSinceLock {
+                recorded_build_number: 22621,
+                detected_build_number: 26100,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn locked_records_wdk_when_lock_file_is_missing() {
+        let mut fs = Fs::default();
+        mock_lock_file(&mut fs, None);
+        expect_lock_file_written(&mut fs, "26100\n");
+        assert!(apply(&fs, WdkLockMode::Locked, Path::new(TARGET_DIR), 26100).is_ok());
+    }
+
+    #[test]
+    fn update_replaces_recorded_wdk() {
+        let mut fs = Fs::default();
+        expect_lock_file_written(&mut fs, "26100\n");
+        assert!(apply(&fs, WdkLockMode::Update, Path::new(TARGET_DIR), 26100).is_ok());
+    }
+
+    #[test]
+    fn locked_fails_when_lock_file_is_invalid() {
+        let mut fs = Fs::default();
+        mock_lock_file(&mut fs, Some("not a build number"));
+        assert!(matches!(
+            apply(&fs, WdkLockMode::Locked, Path::new(TARGET_DIR), 26100),
+            Err(BuildActionError::InvalidWdkLock(..))
+        ));
+    }
+}
@@ -19,4 +19,6 @@ pub enum CleanActionError {
     OneOrMoreRustProjectsFailedToClean(PathBuf),
     #[error(transparent)]
     CargoClean(#[from] CommandError),
+    #[error("Error Parsing Cargo.toml, not a valid rust project/workspace")]
+    CargoMetadataParse(#[from] cargo_metadata::Error),
 }
@@ -1,39 +1,67 @@
 // Copyright (c) Microsoft Corporation
 // License: MIT OR Apache-2.0
 //! This module contains the `CleanAction` struct and its associated methods
-//! for cleaning build artifacts produced by the `build` command.
+//! for cleaning build artifacts produced by the `build` command. Either all
+//! build artifacts are removed with `cargo clean`, or only the driver package
+//! folders, so that the compiled dependencies are kept.
 mod error;
 
 use std::path::{Path, PathBuf, absolute};
 
 use anyhow::Result;
+use cargo_metadata::{Package, TargetKind};
+use clap_cargo::Features;
 use error::CleanActionError;
 use mockall_double::double;
 use tracing::{debug, error as err, info};
 
+use crate::actions::{AARCH64_TARGET_TRIPLE_NAME, Profile, X86_64_TARGET_TRIPLE_NAME};
 #[double]
-use crate::providers::{exec::CommandExec, fs::Fs};
+use crate::providers::{exec::CommandExec, fs::Fs, metadata::Metadata};
 use crate::trace;
 
+/// Suffixes of the names of the folders `build` creates the driver package and
+/// its checked variant in, following the name of the package
+const PACKAGE_FOLDER_SUFFIXES: [&str; 2] = ["_package", "_package_checked"];
+
+/// Parameters of the clean action
+pub struct CleanActionParams<'a> {
+    /// The working directory for the clean action
+    pub working_dir: &'a Path,
+    /// The profile whose artifacts are removed. `None` removes the artifacts
+    /// of all profiles with `cargo clean` and the driver package folders of
+    /// the `dev` profile.
+    pub profile: Option<&'a Profile>,
+    /// Whether only the driver package folders are removed instead of running
+    /// `cargo clean`
+    pub packages_only: bool,
+    /// The verbosity level for logging
+    pub verbosity_level: clap_verbosity_flag::Verbosity,
+}
+
 /// Action that removes build artifacts produced by the `build` command for a
 /// driver project or emulated workspace.
 pub struct CleanAction<'a> {
     working_dir: PathBuf,
+    profile: Option<&'a Profile>,
+    packages_only: bool,
     verbosity_level: clap_verbosity_flag::Verbosity,
 
     // Injected deps
     command_exec: &'a CommandExec,
     fs: &'a Fs,
+    metadata: &'a Metadata,
 }
 
 impl<'a> CleanAction<'a> {
     /// Creates a new instance of `CleanAction`.
     ///
     /// # Arguments
-    /// * `working_dir` - The working directory for the clean action
-    /// * `verbosity_level` - The verbosity level for logging
+    /// * `params` - The `CleanActionParams` struct containing the options of
+    ///   the clean action
     /// * `command_exec` - The command execution provider instance
     /// * `fs` - The file system provider instance
+    /// * `metadata` - The metadata provider instance
     ///
     /// # Returns
     /// * `Result<Self>` - A result containing either a new instance of
@@ -43,28 +71,31 @@ impl<'a> CleanAction<'a> {
     /// * [`anyhow::Error`] - If `working_dir` is not a syntactically valid
     ///   path, e.g. it is empty
     pub fn new(
-        working_dir: &Path,
-        verbosity_level: clap_verbosity_flag::Verbosity,
+        params: &CleanActionParams<'a>,
         command_exec: &'a CommandExec,
         fs: &'a Fs,
+        metadata: &'a Metadata,
     ) -> Result<Self> {
         anyhow::ensure!(
-            !working_dir.as_os_str().is_empty(),
+            !params.working_dir.as_os_str().is_empty(),
             "working_dir must not be empty"
         );
         Ok(Self {
-            working_dir: absolute(working_dir)?,
-            verbosity_level,
+            working_dir: absolute(params.working_dir)?,
+            profile: params.profile,
+            packages_only: params.packages_only,
+            verbosity_level: params.verbosity_level,
             command_exec,
             fs,
+            metadata,
         })
     }
 
     /// Entry point method to execute the clean action flow.
     ///
     /// The detection strategy is:
-    /// 1. If the working directory has a `Cargo.toml`, run `cargo clean`
-    ///    directly (standalone project or workspace root).
+    /// 1. If the working directory has a `Cargo.toml`, clean it directly
+    ///    (standalone project or workspace root).
     /// 2. Otherwise, treat the directory as an emulated workspace: scan
     ///    immediate subdirectories for Rust projects and clean each. NOTE: This
     ///    follows the same logic as the build action.
@@ -76,6 +107,8 @@ pub fn new(
     /// * `CleanActionError::FileIo` - If there is an IO error.
     /// * `CleanActionError::CargoClean` - If there is an error running the
     ///   `cargo clean` command.
+    /// * `CleanActionError::CargoMetadataParse` - If the packages of a project
+    ///   cannot be read with `cargo metadata`.
     /// * `CleanActionError::NoValidRustProjectsInTheDirectory` - If no valid
     ///   Rust projects are found in the working directory.
     /// * `CleanActionError::OneOrMoreRustProjectsFailedToClean` - If one or
@@ -88,11 +121,8 @@ pub fn run(&self) -> Result<(), CleanActionError> {
 
         // Standalone driver/driver workspace support
         if self.fs.exists(&self.working_dir.join("Cargo.toml")) {
-            debug!(
-                "Found Cargo.toml in {}. Running cargo clean.",
-                self.working_dir.display()
-            );
-            return self.run_cargo_clean(&self.working_dir);
+            debug!("Found Cargo.toml in {}", self.working_dir.display());
+            return self.clean_project(&self.working_dir);
         }
 
         // Emulated workspaces support
@@ -124,7 +154,7 @@ pub fn run(&self) -> Result<(), CleanActionError> {
             }
             found_at_least_one_project = true;
             debug!("Cleaning package(s) in dir {package_dir_name}");
-            if let Err(e) = self.run_cargo_clean(&cargo_package_path) {
+            if let Err(e) = self.clean_project(&cargo_package_path) {
                 failed_at_least_one_project = true;
                 err!(
                     "Error cleaning project: {package_dir_name}, error: {:?}",
@@ -153,10 +183,24 @@ pub fn run(&self) -> Result<(), CleanActionError> {
         Ok(())
     }
 
+    /// Cleans the project in the specified directory, removing either its
+    /// driver package folders or all of its build artifacts
+    fn clean_project(&self, working_dir: &Path) -> Result<(), CleanActionError> {
+        if self.packages_only {
+            self.remove_package_folders(working_dir)
+        } else {
+            self.run_cargo_clean(working_dir)
+        }
+    }
+
     /// Runs `cargo clean` in the specified directory.
     fn run_cargo_clean(&self, working_dir: &Path) -> Result<(), CleanActionError> {
         info!("Running cargo clean in {}", working_dir.display());
+        let profile = self.profile.map(ToString::to_string);
         let mut args = vec!["clean"];
+        if let Some(profile) = &profile {
+            args.extend(["--profile", profile.as_str()]);
+        }
         if let Some(flag) = trace::get_cargo_verbose_flags(self.verbosity_level) {
             args.push(flag);
         }
@@ -166,6 +210,86 @@ fn run_cargo_clean(&self, working_dir: &Path) -> Result<(), CleanActionError> {
         info!("Cleaned project at {}", working_dir.display());
         Ok(())
     }
+
+    /// Removes the driver package folders of the driver packages of the
+    /// project in the specified directory. All workspace members are cleaned
+    /// from the workspace root and only the member itself from a member
+    /// directory. Packages without WDK metadata or a cdylib target are
+    /// skipped, since `build` does not package them.
+    fn remove_package_folders(&self, working_dir: &Path) -> Result<(), CleanActionError> {
+        let working_dir_path_trimmed: PathBuf = working_dir
+            .to_string_lossy()
+            .trim_start_matches("\\\\?\\")
+            .into();
+        let cargo_metadata = self.metadata.get_cargo_metadata_at_path(
+            &working_dir_path_trimmed,
+            Vec::new(),
+            &Features::default(),
+        )?;
+        let target_directory = cargo_metadata.target_directory.as_std_path();
+        let is_workspace_root = cargo_metadata.workspace_root.as_std_path() == working_dir;
+        let mut removed_at_least_one_folder = false;
+        for package in cargo_metadata.workspace_packages() {
+            let is_selected = is_workspace_root
+                || package
+                    .manifest_path
+                    .parent()
+                    .is_some_and(|package_dir| package_dir.as_std_path() == working_dir);
+            if !is_selected {
+                continue;
+            }
+            if !is_driver_package(package) {
+                debug!("Skipping non-driver package {}", package.name);
+                continue;
+            }
+            for package_folder in self.package_folders(target_directory, package) {
+                if !self.fs.exists(&package_folder) {
+                    continue;
+                }
+                self.fs.remove_dir_all(&package_folder)?;
+                info!("Removed {}", package_folder.display());
+                removed_at_least_one_folder = true;
+            }
+        }
+        if !removed_at_least_one_folder {
+            info!(
+                "No driver package folders to remove in {}",
+                working_dir.display()
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the folders `build` may have created the driver package of the
+    /// package in, for builds for the host and for each target architecture
+    fn package_folders(&self, target_directory: &Path, package: &Package) -> Vec<PathBuf> {
+        let profile_dir_name = self.profile.map_or("debug", Profile::target_dir_name);
+        let package_name = package.name.as_str().replace('-', "_");
+        [
+            target_directory.to_path_buf(),
+            target_directory.join(X86_64_TARGET_TRIPLE_NAME),
+            target_directory.join(AARCH64_TARGET_TRIPLE_NAME),
+        ]
+        .into_iter()
+        .flat_map(|target_dir| {
+            PACKAGE_FOLDER_SUFFIXES.map(|suffix| {
+                target_dir
+                    .join(profile_dir_name)
+                    .join(format!("{package_name}{suffix}"))
+            })
+        })
+        .collect()
+    }
+}
+
+/// Returns whether `build` packages the package, i.e. it has WDK metadata and
+/// a cdylib target
+fn is_driver_package(package: &Package) -> bool {
+    package.metadata.get("wdk").is_some()
+        && package
+            .targets
+            .iter()
+            .any(|t| t.kind.contains(&TargetKind::CDyLib))
 }
 
 #[cfg(test)]
@@ -179,14 +303,18 @@ mod tests {
 
     use mockall::predicate::eq;
     use mockall_double::double;
-
-    use super::{CleanAction, error::CleanActionError};
-    use crate::providers::{
-        error::{CommandError, FileError},
-        fs::DirEntryInfo,
+    use serde_json::json;
+
+    use super::{CleanAction, CleanActionParams, error::CleanActionError};
+    use crate::{
+        actions::Profile,
+        providers::{
+            error::{CommandError, FileError},
+            fs::DirEntryInfo,
+        },
     };
     #[double]
-    use crate::providers::{exec::CommandExec, fs::Fs};
+    use crate::providers::{exec::CommandExec, fs::Fs, metadata::Metadata};
 
     fn ok_output() -> Output {
         Output {
@@ -241,10 +369,98 @@ fn mock_cargo_clean(exec: &mut CommandExec, dir: &Path, ok: bool) {
             });
     }
 
+    /// Returns the metadata of a workspace at `root_dir` whose members are
+    /// `(name, is_driver)`. Drivers have WDK metadata and a cdylib target.
+    fn workspace_metadata(root_dir: &Path, members: &[(&str, bool)]) -> cargo_metadata::Metadata {
+        let packages: Vec<serde_json::Value> = members
+            .iter()
+            .map(|(name, is_driver)| {
+                let package_dir = root_dir.join(name);
+                let (crate_type, metadata) = if *is_driver {
+                    (
+                        "cdylib",
+                        json!({ "wdk": { "driver-model": { "driver-type": "WDM" } } }),
+                    )
+                } else {
+                    ("lib", serde_json::Value::Null)
+                };
+                json!({
+                    "name": name,
+                    "version": "0.1.0",
+                    "id": format!("{name} 0.1.0"),
+                    "dependencies": [],
+                    "targets": [{
+                        "kind": [crate_type],
+                        "crate_types": [crate_type],
+                        "name": name,
+                        "src_path": package_dir.join("src").join("lib.rs"),
+                        "edition": "2021",
+                        "doc": true,
+                        "doctest": false,
+                        "test": true
+                    }],
+                    "features": {},
+                    "manifest_path": package_dir.join("Cargo.toml"),
+                    "authors": [],
+                    "categories": [],
+                    "keywords": [],
+                    "edition": "2021",
+                    "metadata": metadata
+                })
+            })
+            .collect();
+        let workspace_members: Vec<String> = members
+            .iter()
+            .map(|(name, _)| format!("{name} 0.1.0"))
+            .collect();
+        serde_json::from_value(json!({
+            "packages": packages,
+            "workspace_members": workspace_members,
+            "workspace_root": root_dir,
+            "target_directory": root_dir.join("target"),
+            "metadata": null,
+            "version": 1
+        }))
+        .expect("cargo metadata should be valid")
+    }
+
     fn run_action(cwd: &Path, fs: &Fs, exec: &CommandExec) -> Result<(), CleanActionError> {
-        CleanAction::new(cwd, clap_verbosity_flag::Verbosity::default(), exec, fs)
-            .expect("CleanAction::new should succeed")
-            .run()
+        let metadata = Metadata::default();
+        CleanAction::new(
+            &CleanActionParams {
+                working_dir: cwd,
+                profile: None,
+                packages_only: false,
+                verbosity_level: clap_verbosity_flag::Verbosity::default(),
+            },
+            exec,
+            fs,
+            &metadata,
+        )
+        .expect("CleanAction::new should succeed")
+        .run()
+    }
+
+    fn run_packages_only_action(
+        cwd: &Path,
+        profile: Option<&Profile>,
+        fs: &Fs,
+        metadata: &Metadata,
+    ) -> Result<(), CleanActionError> {
+        let exec = CommandExec::default();
+        CleanAction::new(
+            &CleanActionParams {
+                working_dir: cwd,
+                profile,
+                packages_only: true,
+                verbosity_level: clap_verbosity_flag::Verbosity::default(),
+            },
+            &exec,
+            fs,
+            metadata,
+        )
+        .expect("CleanAction::new should succeed")
+        .run()
     }
 
     #[test]
@@ -252,8 +468,20 @@ fn new_succeeds_for_valid_args() {
         let cwd = PathBuf::from("C:\\tmp");
         let fs = Fs::default();
         let exec = CommandExec::default();
+        let metadata = Metadata::default();
         assert!(
-            CleanAction::new(&cwd, clap_verbosity_flag::Verbosity::default(), &exec, &fs,).is_ok()
+            CleanAction::new(
+                &CleanActionParams {
+                    working_dir: &cwd,
+                    profile: None,
+                    packages_only: false,
+                    verbosity_level: clap_verbosity_flag::Verbosity::default(),
+                },
+                &exec,
+                &fs,
+                &metadata,
+            )
+            .is_ok()
         );
     }
 
@@ -262,9 +490,20 @@ fn new_fails_if_working_dir_is_empty() {
         let cwd = PathBuf::from("");
         let fs = Fs::default();
         let exec = CommandExec::default();
-        let err = CleanAction::new(&cwd, clap_verbosity_flag::Verbosity::default(), &exec, &fs)
-            .err()
-            .expect("CleanAction::new should fail for empty working_dir");
+        let metadata = Metadata::default();
+        let err = CleanAction::new(
+            &CleanActionParams {
+                working_dir: &cwd,
+                profile: None,
+                packages_only: false,
+                verbosity_level: clap_verbosity_flag::Verbosity::default(),
+            },
+            &exec,
+            &fs,
+            &metadata,
+        )
+        .err()
+        .expect("CleanAction::new should fail for empty working_dir");
         assert_eq!(err.to_string(), "working_dir must not be empty");
     }
 
@@ -293,6 +532,35 @@ fn run_returns_error_when_cargo_clean_fails() {
         ));
     }
 
+    #[test]
+    fn run_passes_the_profile_to_cargo_clean() {
+        let cwd = PathBuf::from("C:\\tmp");
+        let mut fs = Fs::default();
+        let mut exec = CommandExec::default();
+        let metadata = Metadata::default();
+        mock_cargo_toml(&mut fs, &cwd, true);
+        exec.expect_run()
+            .withf(|cmd, args, _env, _working_dir| {
+                cmd == "cargo" && args == ["clean", "--profile", "release"]
+            })
+            .once()
+            .returning(|_, _, _, _| Ok(ok_output()));
+        let result = CleanAction::new(
+            &CleanActionParams {
+                working_dir: &cwd,
+                profile: Some(&Profile::Release),
+                packages_only: false,
+                verbosity_level: clap_verbosity_flag::Verbosity::default(),
+            },
+            &exec,
+            &fs,
+            &metadata,
+        )
+        .expect("CleanAction::new should succeed")
+        .run();
+        assert!(result.is_ok());
+    }
+
     // ---- emulated workspace -------------------------------------------------
 
     #[test]
@@ -420,4 +688,59 @@ fn run_returns_error_when_read_dir_entries_fails() {
             Err(CleanActionError::FileIo(_))
         ));
     }
+
+    // ---- driver package folders ---------------------------------------------
+
+    #[test]
+    fn run_removes_the_package_folders_of_the_drivers_of_the_workspace() {
+        let cwd = PathBuf::from("C:\\tmp");
+        let existing_folders = [
+            cwd.join("target\\release\\my_driver_package"),
+            cwd.join("target\\x86_64-pc-windows-msvc\\release\\my_driver_package_checked"),
+            // Not a driver, so it is kept even though the folder exists
+            cwd.join("target\\release\\helper_package"),
+        ];
+        let mut fs = Fs::default();
+        let mut metadata = Metadata::default();
+        let cargo_metadata = workspace_metadata(&cwd, &[("my-driver", true), ("helper", false)]);
+        metadata
+            .expect_get_cargo_metadata_at_path()
+            .return_once(move |_, _, _| Ok(cargo_metadata));
+        let cargo_toml = cwd.join("Cargo.toml");
+        let folders = existing_folders.clone();
+        fs.expect_exists().returning(move |path: &Path| {
+            path == cargo_toml || folders.iter().any(|folder| folder == path)
+        });
+        let removed_folders = existing_folders[..2].to_vec();
+        fs.expect_remove_dir_all()
+            .withf(move |path: &Path| removed_folders.iter().any(|folder| folder == path))
+            .times(2)
+            .returning(|_| Ok(()));
+        assert!(run_packages_only_action(&cwd, Some(&Profile::Release), &fs, &metadata).is_ok());
+    }
+
+    #[test]
+    fn run_removes_only_the_package_folder_of_the_member_it_runs_from() {
+        let root = PathBuf::from("C:\\tmp");
+        let cwd = root.join("my-driver");
+        let mut fs = Fs::default();
+        let mut metadata = Metadata::default();
+        let cargo_metadata =
+            workspace_metadata(&root, &[("my-driver", true), ("other-driver", true)]);
+        metadata
+            .expect_get_cargo_metadata_at_path()
+            .return_once(move |_, _, _| Ok(cargo_metadata));
+        let cargo_toml = cwd.join("Cargo.toml");
+        let package_folder = root.join("target\\debug\\my_driver_package");
+        let other_package_folder = root.join("target\\debug\\other_driver_package");
+        let removed_folder = package_folder.clone();
+        fs.expect_exists().returning(move |path: &Path| {
+            path == cargo_toml || path == package_folder || path == other_package_folder
+        });
+        fs.expect_remove_dir_all()
+            .with(eq(removed_folder))
+            .once()
+            .returning(|_| Ok(()));
+        assert!(run_packages_only_action(&cwd, None, &fs, &metadata).is_ok());
+    }
 }
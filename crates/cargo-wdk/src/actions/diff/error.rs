@@ -0,0 +1,18 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module defines error types for the diff action module.
+
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::providers::error::FileError;
+
+/// Errors for the diff action layer
+#[derive(Error, Debug)]
+pub enum DiffActionError {
+    #[error(transparent)]
+    FileIo(#[from] FileError),
+    #[error("Package directory not found: {0}")]
+    PackageDirNotFound(PathBuf),
+}
@@ -0,0 +1,571 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module contains the `DiffAction` struct and its associated methods
+//! for comparing two driver packages, e.g. to find out why the catalog of a
+//! package changed between two builds.
+mod error;
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf, absolute},
+};
+
+use anyhow::Result;
+use error::DiffActionError;
+use mockall_double::double;
+use sha2::{Digest, Sha256};
+use tracing::{debug, info};
+
+use crate::{actions::inf::InfFile, providers::error::FileError};
+#[double]
+use crate::providers::fs::Fs;
+
+/// Extension of the INF files, which are compared key by key
+const INF_FILE_EXTENSION: &str = "inf";
+/// Number of hex digits of the SHA256 hashes shown in the report
+pub const SHORT_HASH_LEN: usize = 12;
+
+/// Action that compares the files of two driver packages and reports the
+/// files that were added, removed or changed between them.
+pub struct DiffAction<'a> {
+    old_package_dir: PathBuf,
+    new_package_dir: PathBuf,
+
+    // Injected deps
+    fs: &'a Fs,
+}
+
+/// Size and SHA256 hash of a file in a driver package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileSummary {
+    pub size: usize,
+    pub sha256: String,
+}
+
+/// A difference between the files of two driver packages. Paths are relative
+/// to the package directories.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileChange {
+    Added(PathBuf, FileSummary),
+    Removed(PathBuf, FileSummary),
+    Changed {
+        path: PathBuf,
+        old: FileSummary,
+        new: FileSummary,
+        /// Key level changes if the file is an INF
+        inf_changes: Vec<InfChange>,
+    },
+}
+
+/// A difference between the entries of two INF files. Keyed entries are
+/// identified by their section and key, entries without a key by their
+/// section and values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InfChange {
+    Added {
+        section: String,
+        entry: String,
+    },
+    Removed {
+        section: String,
+        entry: String,
+    },
+    Changed {
+        section: String,
+        key: String,
+        old: String,
+        new: String,
+    },
+}
+
+/// Differences between two driver packages, sorted by path
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackageDiff {
+    pub changes: Vec<FileChange>,
+}
+
+impl<'a> DiffAction<'a> {
+    /// Creates a new instance of `DiffAction`.
+    ///
+    /// # Arguments
+    /// * `old_package_dir` - The driver package directory to compare against
+    /// * `new_package_dir` - The driver package directory to compare
+    /// * `fs` - The file system provider instance
+    ///
+    /// # Returns
+    /// * `Result<Self>` - A result containing either a new instance of
+    ///   `DiffAction` on success, or an `anyhow::Error`.
+    ///
+    /// # Errors
+    /// * [`anyhow::Error`] - If either package directory is not a syntactically
+    ///   valid path, e.g. it is empty
+    pub fn new(old_package_dir: &Path, new_package_dir: &Path, fs: &'a Fs) -> Result<Self> {
+        anyhow::ensure!(
+            !old_package_dir.as_os_str().is_empty() && !new_package_dir.as_os_str().is_empty(),
+            "package directories must not be empty"
+        );
+        Ok(Self {
+            old_package_dir: absolute(old_package_dir)?,
+            new_package_dir: absolute(new_package_dir)?,
+            fs,
+        })
+    }
+
+    /// Entry point method to execute the diff action flow. The report of the
+    /// differences is printed to stdout.
+    ///
+    /// # Returns
+    /// `Result<(), DiffActionError>`
+    ///
+    /// # Errors
+    /// * `DiffActionError::PackageDirNotFound` - If a package directory does
+    ///   not exist.
+    /// * `DiffActionError::FileIo` - If there is an IO error.
+    pub fn run(&self) -> Result<(), DiffActionError> {
+        let diff = self.diff()?;
+        if diff.changes.is_empty() {
+            info!(
+                "Packages at {} and {} are identical",
+                self.old_package_dir.display(),
+                self.new_package_dir.display()
+            );
+        } else {
+            print!("{diff}");
+        }
+        Ok(())
+    }
+
+    /// Compares the files of the two package directories and their sub
+    /// folders.
+    ///
+    /// # Errors
+    /// * `DiffActionError::PackageDirNotFound` - If a package directory does
+    ///   not exist.
+    /// * `DiffActionError::FileIo` - If there is an IO error.
+    pub fn diff(&self) -> Result<PackageDiff, DiffActionError> {
+        debug!(
+            "Comparing package at {} with package at {}",
+            self.old_package_dir.display(),
+            self.new_package_dir.display()
+        );
+        let old_files = self.package_files(&self.old_package_dir)?;
+        let mut new_files = self.package_files(&self.new_package_dir)?;
+
+        let mut changes = Vec::new();
+        for (path, old_content) in old_files {
+            let Some(new_content) = new_files.remove(&path) else {
+                changes.push(FileChange::Removed(path, FileSummary::of(&old_content)));
+                continue;
+            };
+            if old_content == new_content {
+                continue;
+            }
+            let inf_changes = if is_inf_file(&path) {
+                diff_infs(&old_content, &new_content)
+            } else {
+                Vec::new()
+            };
+            changes.push(FileChange::Changed {
+                path,
+                old: FileSummary::of(&old_content),
+                new: FileSummary::of(&new_content),
+                inf_changes,
+            });
+        }
+        changes.extend(
+            new_files
+                .into_iter()
+                .map(|(path, content)| FileChange::Added(path, FileSummary::of(&content))),
+        );
+        changes.sort_by(|a, b| a.path().cmp(b.path()));
+        Ok(PackageDiff { changes })
+    }
+
+    /// Reads the files of a package directory, keyed by their path relative
+    /// to it
+    fn package_files(
+        &self,
+        package_dir: &Path,
+    ) -> Result<BTreeMap<PathBuf, Vec<u8>>, DiffActionError> {
+        if !self.fs.exists(package_dir) {
+            return Err(DiffActionError::PackageDirNotFound(package_dir.to_owned()));
+        }
+        Ok(read_package_files(self.fs, package_dir)?)
+    }
+}
+
+/// Reads the files of a package directory and its sub folders, keyed by their
+/// path relative to the package directory
+///
+/// # Errors
+/// * `FileError` - If a directory or file cannot be read.
+pub fn read_package_files(
+    fs: &Fs,
+    package_dir: &Path,
+) -> Result<BTreeMap<PathBuf, Vec<u8>>, FileError> {
+    let mut files = BTreeMap::new();
+    read_files(fs, package_dir, package_dir, &mut files)?;
+    Ok(files)
+}
+
+fn read_files(
+    fs: &Fs,
+    package_dir: &Path,
+    dir: &Path,
+    files: &mut BTreeMap<PathBuf, Vec<u8>>,
+) -> Result<(), FileError> {
+    for entry in fs.read_dir_entries(dir)? {
+        if entry.is_dir {
+            read_files(fs, package_dir, &entry.path, files)?;
+            continue;
+        }
+        let relative_path = entry
+            .path
+            .strip_prefix(package_dir)
+            .unwrap_or(&entry.path)
+            .to_owned();
+        files.insert(relative_path, fs.read_file(&entry.path)?);
+    }
+    Ok(())
+}
+
+impl FileSummary {
+    /// Returns the size and SHA256 hash of the given file content
+    pub fn of(content: &[u8]) -> Self {
+        Self {
+            size: content.len(),
+            sha256: format!("{:x}", Sha256::digest(content)),
+        }
+    }
+
+    /// Returns the first digits of the SHA256 hash shown in reports
+    pub fn short_hash(&self) -> &str {
+        &self.sha256[..SHORT_HASH_LEN]
+    }
+}
+
+impl FileChange {
+    fn path(&self) -> &Path {
+        match self {
+            Self::Added(path, _) | Self::Removed(path, _) | Self::Changed { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for PackageDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            match change {
+                FileChange::Added(path, summary) => writeln!(
+                    f,
+                    "+ {} ({} bytes, sha256 {})",
+                    path.display(),
+                    summary.size,
+                    summary.short_hash()
+                )?,
+                FileChange::Removed(path, summary) => writeln!(
+                    f,
+                    "- {} ({} bytes, sha256 {})",
+                    path.display(),
+                    summary.size,
+                    summary.short_hash()
+                )?,
+                FileChange::Changed {
+                    path,
+                    old,
+                    new,
+                    inf_changes,
+                } => {
+                    writeln!(
+                        f,
+                        "~ {} ({} -> {} bytes, sha256 {} -> {})",
+                        path.display(),
+                        old.size,
+                        new.size,
+                        old.short_hash(),
+                        new.short_hash()
+                    )?;
+                    for inf_change in inf_changes {
+                        writeln!(f, "    {inf_change}")?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for InfChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Added { section, entry } => write!(f, "+ [{section}] {entry}"),
+            Self::Removed { section, entry } => write!(f, "- [{section}] {entry}"),
+            Self::Changed {
+                section,
+                key,
+                old,
+                new,
+            } => write!(f, "~ [{section}] {key}: {old} -> {new}"),
+        }
+    }
+}
+
+fn is_inf_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case(INF_FILE_EXTENSION))
+}
+
+/// Compares the entries of two INF files. Returns no changes if either file
+/// is not valid UTF-8, in which case only the hashes of the files are
+/// reported.
+fn diff_infs(old_content: &[u8], new_content: &[u8]) -> Vec<InfChange> {
+    let (Ok(old_content), Ok(new_content)) = (
+        std::str::from_utf8(old_content),
+        std::str::from_utf8(new_content),
+    ) else {
+        return Vec::new();
+    };
+    let old_entries = inf_entries(&InfFile::parse(old_content));
+    let mut new_entries = inf_entries(&InfFile::parse(new_content));
+
+    let mut changes = Vec::new();
+    for (id, old_entry) in old_entries {
+        match new_entries.remove(&id) {
+            None => changes.push(InfChange::Removed {
+                entry: old_entry.to_string(),
+                section: old_entry.section,
+            }),
+            Some(new_entry) if new_entry.value != old_entry.value => {
+                changes.push(InfChange::Changed {
+                    section: old_entry.section,
+                    key: old_entry.key.unwrap_or_default(),
+                    old: old_entry.value,
+                    new: new_entry.value,
+                });
+            }
+            Some(_) => {}
+        }
+    }
+    changes.extend(new_entries.into_values().map(|new_entry| InfChange::Added {
+        entry: new_entry.to_string(),
+        section: new_entry.section,
+    }));
+    changes
+}
+
+/// An entry of an INF file as compared by [`diff_infs`]
+struct ComparedInfEntry {
+    section: String,
+    key: Option<String>,
+    value: String,
+}
+
+impl fmt::Display for ComparedInfEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.key {
+            Some(key) => write!(f, "{key} = {}", self.value),
+            None => write!(f, "{}", self.value),
+        }
+    }
+}
+
+/// Collects the entries of an INF keyed by their lowercase section name and
+/// their lowercase key, or their values if they have no key. The values of
+/// repeated keys in a section are joined.
+fn inf_entries(inf_file: &InfFile) -> BTreeMap<(String, String), ComparedInfEntry> {
+    let mut entries: BTreeMap<(String, String), ComparedInfEntry> = BTreeMap::new();
+    for section in &inf_file.sections {
+        for entry in &section.entries {
+            let value = entry.values.join(",");
+            let id = (
+                section.name.to_ascii_lowercase(),
+                entry.key.as_deref().unwrap_or(&value).to_ascii_lowercase(),
+            );
+            if let Some(existing) = entries.get_mut(&id) {
+                if entry.key.is_some() {
+                    existing.value = format!("{}; {value}", existing.value);
+                }
+                continue;
+            }
+            entries.insert(
+                id,
+                ComparedInfEntry {
+                    section: section.name.clone(),
+                    key: entry.key.clone(),
+                    value,
+                },
+            );
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::path::{Path, PathBuf};
+
+    use mockall::predicate::eq;
+    use mockall_double::double;
+
+    use super::{DiffAction, FileChange, InfChange, error::DiffActionError};
+    use crate::providers::fs::DirEntryInfo;
+    #[double]
+    use crate::providers::fs::Fs;
+
+    const OLD_INF: &str = r#"[Version]
+Signature = "$WINDOWS NT$"
+DriverVer = 01/01/2024,1.0.0.0
+CatalogFile = driver.cat
+
+[Strings]
+ServiceDesc = "Sample Driver"
+"#;
+
+    const NEW_INF: &str = r#"[Version]
+Signature = "$WINDOWS NT$"
+DriverVer = 02/01/2024,1.0.1.0
+CatalogFile = driver.cat
+
+[Strings]
+DiskName = "Sample Disk"
+"#;
+
+    /// Sets up a package directory with the given `(name relative to dir,
+    /// content)` files. Also used by the tests of the actions that read
+    /// packages with `read_package_files`.
+    pub(crate) fn mock_package(fs: &mut Fs, dir: &Path, files: &[(&str, &[u8])]) {
+        let entries: Vec<DirEntryInfo> = files
+            .iter()
+            .map(|(name, _)| DirEntryInfo {
+                path: dir.join(name),
+                is_dir: false,
+            })
+            .collect();
+        fs.expect_exists()
+            .with(eq(dir.to_owned()))
+            .returning(|_| true);
+        fs.expect_read_dir_entries()
+            .with(eq(dir.to_owned()))
+            .returning(move |_| Ok(entries.clone()));
+        for (name, content) in files {
+            let content = content.to_vec();
+            fs.expect_read_file()
+                .with(eq(dir.join(name)))
+                .returning(move |_| Ok(content.clone()));
+        }
+    }
+
+    #[test]
+    fn diff_reports_added_removed_and_changed_files_with_inf_key_changes() {
+        let old_dir = PathBuf::from("C:\\tmp\\old_package");
+        let new_dir = PathBuf::from("C:\\tmp\\new_package");
+        let mut fs = Fs::default();
+        mock_package(
+            &mut fs,
+            &old_dir,
+            &[
+                ("driver.cat", b"catalog"),
+                ("driver.inf", OLD_INF.as_bytes()),
+                ("driver.sys", b"binary"),
+                ("readme.txt", b"readme"),
+            ],
+        );
+        mock_package(
+            &mut fs,
+            &new_dir,
+            &[
+                ("driver.cat", b"catalog v2"),
+                ("driver.inf", NEW_INF.as_bytes()),
+                ("driver.map", b"map"),
+                ("driver.sys", b"binary"),
+            ],
+        );
+
+        let diff = DiffAction::new(&old_dir, &new_dir, &fs)
+            .expect("DiffAction::new should succeed")
+            .diff()
+            .expect("diff should succeed");
+
+        let paths = diff
+            .changes
+            .iter()
+            .map(|change| match change {
+                FileChange::Added(path, _) => format!("+{}", path.display()),
+                FileChange::Removed(path, _) => format!("-{}", path.display()),
+                FileChange::Changed { path, .. } => format!("~{}", path.display()),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            ["~driver.cat", "~driver.inf", "+driver.map", "-readme.txt"]
+        );
+        let FileChange::Changed { inf_changes, .. } = &diff.changes[1] else {
+            panic!("expected the INF to be changed");
+        };
+        assert_eq!(
+            inf_changes,
+            &[
+                InfChange::Removed {
+                    section: "Strings".to_string(),
+                    entry: "ServiceDesc = Sample Driver".to_string(),
+                },
+                InfChange::Changed {
+                    section: "Version".to_string(),
+                    key: "DriverVer".to_string(),
+                    old: "01/01/2024,1.0.0.0".to_string(),
+                    new: "02/01/2024,1.0.1.0".to_string(),
+                },
+                InfChange::Added {
+                    section: "Strings".to_string(),
+                    entry: "DiskName = Sample Disk".to_string(),
+                },
+            ]
+        );
+        let report = diff.to_string();
+        assert!(report.contains("~ driver.cat (7 -> 10 bytes, sha256 "));
+        assert!(
+            report
+                .contains("    ~ [Version] DriverVer: 01/01/2024,1.0.0.0 -> 02/01/2024,1.0.1.0\n")
+        );
+        assert!(report.contains("+ driver.map (3 bytes, sha256 "));
+    }
+
+    #[test]
+    fn diff_of_identical_packages_is_empty() {
+        let old_dir = PathBuf::from("C:\\tmp\\old_package");
+        let new_dir = PathBuf::from("C:\\tmp\\new_package");
+        let mut fs = Fs::default();
+        let files: &[(&str, &[u8])] = &[
+            ("driver.inf", OLD_INF.as_bytes()),
+            ("driver.sys", b"binary"),
+        ];
+        mock_package(&mut fs, &old_dir, files);
+        mock_package(&mut fs, &new_dir, files);
+
+        let diff = DiffAction::new(&old_dir, &new_dir, &fs)
+            .expect("DiffAction::new should succeed")
+            .diff()
+            .expect("diff should succeed");
+
+        assert!(diff.changes.is_empty());
+    }
+
+    #[test]
+    fn diff_returns_error_when_package_dir_does_not_exist() {
+        let old_dir = PathBuf::from("C:\\tmp\\old_package");
+        let new_dir = PathBuf::from("C:\\tmp\\new_package");
+        let mut fs = Fs::default();
+        mock_package(&mut fs, &old_dir, &[("driver.sys", b"binary")]);
+        fs.expect_exists()
+            .with(eq(new_dir.clone()))
+            .returning(|_| false);
+
+        assert!(matches!(
+            DiffAction::new(&old_dir, &new_dir, &fs)
+                .expect("DiffAction::new should succeed")
+                .diff(),
+            Err(DiffActionError::PackageDirNotFound(dir)) if dir == new_dir
+        ));
+    }
+}
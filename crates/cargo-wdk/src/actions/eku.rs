@@ -0,0 +1,181 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module contains the helpers that check the enhanced key usages (EKUs)
+//! of the certificate that signs a driver package, as printed by `certutil`.
+//! A certificate issued for another purpose produces signatures that
+//! `signtool verify` accepts, but the driver fails to install on production
+//! machines. They are shared by the actions that sign driver packages.
+
+use tracing::debug;
+
+use crate::actions::signtool::SigningCertificate;
+
+/// OID of the code signing EKU, which the certificate signing a driver must
+/// have
+pub const CODE_SIGNING_EKU: &str = "1.3.6.1.5.5.7.3.3";
+
+/// Header of the EKU extension in the output of `certutil -dump`
+const EKU_EXTENSION_HEADER: &str = "Enhanced Key Usage";
+
+/// Returns the `certutil` arguments that print the extensions of the given
+/// signing certificate. Certificates in a store are looked up in the stores
+/// of the current user, where `signtool sign /s` finds them. The password of a
+/// PFX file is passed with `/p` rather than `-p`, so that it is redacted from
+/// the logged command lines like the one of `signtool sign`.
+pub fn certutil_dump_args(certificate: SigningCertificate<'_>) -> Vec<&str> {
+    match certificate {
+        SigningCertificate::Named { store, name } => vec!["-user", "-v", "-store", store, name],
+        SigningCertificate::Thumbprint { store, thumbprint } => {
+            vec!["-user", "-v", "-store", store, thumbprint]
+        }
+        SigningCertificate::Pfx { path, password } => {
+            vec!["-dump", "/p", password.unwrap_or_default(), path]
+        }
+    }
+}
+
+/// Parses the OIDs of the EKU extension from the output of `certutil -dump`.
+/// The usages are listed below the extension header, indented further than
+/// it, either as a friendly name followed by the OID in parentheses, e.g.
+/// `Code Signing (1.3.6.1.5.5.7.3.3)`, or as the bare OID.
+///
+/// Returns `None` if the certificate has no EKU extension, in which case it is
+/// valid for every usage.
+fn parse_certutil_ekus(certutil_output: &str) -> Option<Vec<String>> {
+    let mut lines = certutil_output.lines();
+    let header = lines.find(|line| line.trim() == EKU_EXTENSION_HEADER)?;
+    let header_indent = indent(header);
+    let ekus = lines
+        .take_while(|line| !line.trim().is_empty() && indent(line) > header_indent)
+        .map(|line| {
+            let line = line.trim();
+            line.strip_suffix(')')
+                .and_then(|line| line.rsplit_once('('))
+                .map_or(line, |(_, oid)| oid)
+                .to_string()
+        })
+        .collect();
+    Some(ekus)
+}
+
+/// Returns `true` if a certificate with the given EKUs may sign drivers, i.e.
+/// it has no EKU extension or the extension includes [`CODE_SIGNING_EKU`]
+fn allows_driver_signing(ekus: Option<&[String]>) -> bool {
+    ekus.is_none_or(|ekus| ekus.iter().any(|eku| eku == CODE_SIGNING_EKU))
+}
+
+/// Checks that the certificate printed by `certutil` may sign drivers, see
+/// [`allows_driver_signing`]. Returns the EKUs of the certificate joined by
+/// commas if it may not.
+pub fn check_driver_signing(certutil_output: &str) -> Result<(), String> {
+    let ekus = parse_certutil_ekus(certutil_output);
+    debug!("Signing certificate enhanced key usages: {ekus:?}");
+    if allows_driver_signing(ekus.as_deref()) {
+        return Ok(());
+    }
+    Err(ekus.unwrap_or_default().join(", "))
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{allows_driver_signing, certutil_dump_args, parse_certutil_ekus};
+    use crate::actions::signtool::SigningCertificate;
+
+    const CERTUTIL_DUMP_HEADER: &str = r"X509 Certificate:
+Version: 3
+Serial Number: 5e1c3a0f2b8d4c91
+Issuer:
+    CN=WDRLocalTestCert
+Certificate Extensions: 2
+    2.5.29.37: Flags = 0, Length = 16
+";
+
+    #[test]
+    fn ekus_are_parsed_with_and_without_friendly_names() {
+        let output = format!(
+            "{CERTUTIL_DUMP_HEADER}    Enhanced Key Usage
+        Code Signing (1.3.6.1.5.5.7.3.3)
+        1.3.6.1.4.1.311.10.3.5
+
+    2.5.29.14: Flags = 0, Length = 16
+    Subject Key Identifier
+        3e 0a 9c 41
+"
+        );
+
+        let ekus = parse_certutil_ekus(&output);
+
+        assert_eq!(
+            ekus.as_deref(),
+            Some(
+                ["1.3.6.1.5.5.7.3.3", "1.3.6.1.4.1.311.10.3.5"]
+                    .map(String::from)
+                    .as_slice()
+            )
+        );
+        assert!(allows_driver_signing(ekus.as_deref()));
+    }
+
+    #[test]
+    fn certificate_without_code_signing_eku_cannot_sign_drivers() {
+        let output = format!(
+            "{CERTUTIL_DUMP_HEADER}    Enhanced Key Usage
+        Server Authentication (1.3.6.1.5.5.7.3.1)
+    2.5.29.14: Flags = 0, Length = 16
+    Subject Key Identifier
+        3e 0a 9c 41
+"
+        );
+
+        let ekus = parse_certutil_ekus(&output);
+
+        assert_eq!(
+            ekus.as_deref(),
+            Some([String::from("1.3.6.1.5.5.7.3.1")].as_slice())
+        );
+        assert!(!allows_driver_signing(ekus.as_deref()));
+    }
+
+    #[test]
+    fn certificate_without_eku_extension_can_sign_drivers() {
+        let ekus = parse_certutil_ekus(CERTUTIL_DUMP_HEADER);
+
+        assert_eq!(ekus, None);
+        assert!(allows_driver_signing(ekus.as_deref()));
+    }
+
+    #[test]
+    fn signing_certificate_is_dumped_from_where_signtool_reads_it() {
+        assert_eq!(
+            certutil_dump_args(SigningCertificate::Named {
+                store: "WDRTestCertStore",
+                name: "WDRLocalTestCert",
+            }),
+            ["-user", "-v", "-store", "WDRTestCertStore", "WDRLocalTestCert"]
+        );
+        assert_eq!(
+            certutil_dump_args(SigningCertificate::Thumbprint {
+                store: "My",
+                thumbprint: "0123456789abcdef0123456789abcdef01234567",
+            }),
+            [
+                "-user",
+                "-v",
+                "-store",
+                "My",
+                "0123456789abcdef0123456789abcdef01234567"
+            ]
+        );
+        assert_eq!(
+            certutil_dump_args(SigningCertificate::Pfx {
+                path: "C:/temp/cert.pfx",
+                password: Some("secret"),
+            }),
+            ["-dump", "/p", "secret", "C:/temp/cert.pfx"]
+        );
+    }
+}
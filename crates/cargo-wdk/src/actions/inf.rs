@@ -0,0 +1,641 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module contains a minimal parser of INF files. It reads the sections
+//! and entries of an INF into an [`InfFile`] so that the validation steps of
+//! the build can look up the keys they care about, e.g. the `DriverVer`,
+//! `CatalogFile` and `Provider` of the `[Version]` section, the target OS
+//! decorations of the `[Manufacturer]` section, the device IDs of the models
+//! sections and the files of the `[SourceDisksFiles]` sections, and so that
+//! the diff action can compare two INFs key by key. It is not a full INF
+//! parser, e.g. `%strkey%` tokens are only expanded for the `Provider`.
+
+/// Name of the section with the version information of the INF
+const VERSION_SECTION_NAME: &str = "Version";
+/// Name of the section defining the strings of the `%strkey%` tokens. The
+/// localized `[Strings.<LangID>]` sections are not read.
+const STRINGS_SECTION_NAME: &str = "Strings";
+/// Name of the section listing the models sections of the manufacturers
+const MANUFACTURER_SECTION_NAME: &str = "Manufacturer";
+/// Name of the sections listing the files of the driver package. The section
+/// may be decorated with an architecture, e.g. `[SourceDisksFiles.amd64]`.
+const SOURCE_DISKS_FILES_SECTION_NAME: &str = "SourceDisksFiles";
+/// Suffix of the install sections that register co-installers, e.g.
+/// `[Device_Install.NT.CoInstallers]`
+const CO_INSTALLERS_SECTION_SUFFIX: &str = ".CoInstallers";
+/// Registry value that lists the co-installers of a device
+const CO_INSTALLERS32_VALUE_NAME: &str = "CoInstallers32";
+const DRIVER_VER_KEY: &str = "DriverVer";
+const CATALOG_FILE_KEY: &str = "CatalogFile";
+const CLASS_KEY: &str = "Class";
+const CLASS_GUID_KEY: &str = "ClassGuid";
+const PROVIDER_KEY: &str = "Provider";
+/// Name of the setup class of sample drivers
+const SAMPLE_CLASS_NAME: &str = "Sample";
+/// GUID of the setup class of sample drivers
+const SAMPLE_CLASS_GUID: &str = "{78A1C341-4539-11d3-B88D-00C04FAD5171}";
+
+/// Sections of an INF file in the order they appear in the file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InfFile {
+    pub sections: Vec<InfSection>,
+}
+
+/// A `[name]` section of an INF file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InfSection {
+    pub name: String,
+    pub entries: Vec<InfEntry>,
+}
+
+/// A `key = value, ...` or `value, ...` entry of an INF section. Quotes are
+/// removed from the values.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InfEntry {
+    pub key: Option<String>,
+    pub values: Vec<String>,
+}
+
+impl InfFile {
+    /// Parses the content of an INF file. A UTF-8 byte order mark, comments,
+    /// blank lines and entries before the first section are dropped and lines
+    /// ending with `\` are joined with the next line.
+    #[must_use]
+    pub fn parse(content: &str) -> Self {
+        let mut sections: Vec<InfSection> = Vec::new();
+        let mut continued_line = String::new();
+        let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+        for line in content.lines() {
+            let line = strip_comment(line).trim();
+            if let Some(line) = line.strip_suffix('\\') {
+                continued_line.push_str(line);
+                continue;
+            }
+            let line = if continued_line.is_empty() {
+                line.to_string()
+            } else {
+                std::mem::take(&mut continued_line) + line
+            };
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                sections.push(InfSection {
+                    name: name.trim().to_string(),
+                    entries: Vec::new(),
+                });
+            } else if let Some(section) = sections.last_mut() {
+                section.entries.push(InfEntry::parse(&line));
+            }
+        }
+        Self { sections }
+    }
+
+    /// Returns the first section with the given name. Section names are
+    /// compared case-insensitively.
+    #[must_use]
+    pub fn section(&self, name: &str) -> Option<&InfSection> {
+        self.sections
+            .iter()
+            .find(|section| section.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the values of the first entry with the given key in the given
+    /// section
+    #[must_use]
+    pub fn values(&self, section_name: &str, key: &str) -> Option<&[String]> {
+        self.section(section_name)
+            .and_then(|section| section.values(key))
+    }
+
+    /// Returns the `DriverVer` date and version of the `[Version]` section
+    #[must_use]
+    pub fn driver_ver(&self) -> Option<&[String]> {
+        self.values(VERSION_SECTION_NAME, DRIVER_VER_KEY)
+    }
+
+    /// Returns the `CatalogFile` of the `[Version]` section
+    #[must_use]
+    pub fn catalog_file(&self) -> Option<&str> {
+        self.values(VERSION_SECTION_NAME, CATALOG_FILE_KEY)
+            .and_then(<[String]>::first)
+            .map(String::as_str)
+    }
+
+    /// Returns the `Class` of the `[Version]` section
+    #[must_use]
+    pub fn class(&self) -> Option<&str> {
+        self.values(VERSION_SECTION_NAME, CLASS_KEY)
+            .and_then(<[String]>::first)
+            .map(String::as_str)
+    }
+
+    /// Returns the `ClassGuid` of the `[Version]` section
+    #[must_use]
+    pub fn class_guid(&self) -> Option<&str> {
+        self.values(VERSION_SECTION_NAME, CLASS_GUID_KEY)
+            .and_then(<[String]>::first)
+            .map(String::as_str)
+    }
+
+    /// Returns the `Provider` of the `[Version]` section, with a `%strkey%`
+    /// token expanded from the `[Strings]` section
+    #[must_use]
+    pub fn provider(&self) -> Option<&str> {
+        self.values(VERSION_SECTION_NAME, PROVIDER_KEY)
+            .and_then(<[String]>::first)
+            .map(|provider| self.expand_string_token(provider))
+    }
+
+    /// Returns whether the `[Version]` section declares the `Sample` setup
+    /// class. The `ClassGuid` takes precedence over the `Class` name when
+    /// both are present.
+    #[must_use]
+    pub fn is_sample_class(&self) -> bool {
+        self.class_guid().map_or_else(
+            || {
+                self.class()
+                    .is_some_and(|class| class.eq_ignore_ascii_case(SAMPLE_CLASS_NAME))
+            },
+            |class_guid| class_guid.eq_ignore_ascii_case(SAMPLE_CLASS_GUID),
+        )
+    }
+
+    /// Returns the target OS decorations, e.g. `NTamd64.10.0...16299`, listed
+    /// by the entries of the `[Manufacturer]` section
+    #[must_use]
+    pub fn manufacturer_decorations(&self) -> Vec<&str> {
+        self.manufacturer_entries()
+            .flat_map(|entry| entry.values.iter().skip(1).map(String::as_str))
+            .filter(|decoration| !decoration.is_empty())
+            .collect()
+    }
+
+    /// Returns the names of the models sections of every manufacturer. A
+    /// models section is named by an entry of the `[Manufacturer]` section,
+    /// optionally decorated with the target OS, e.g.
+    /// `[Standard.NTamd64.10.0...16299]`.
+    #[must_use]
+    pub fn models_section_names(&self) -> Vec<String> {
+        self.manufacturer_entries()
+            .filter_map(|entry| {
+                let (base_name, decorations) = entry.values.split_first()?;
+                Some(
+                    std::iter::once(base_name.clone()).chain(
+                        decorations
+                            .iter()
+                            .filter(|decoration| !decoration.is_empty())
+                            .map(|decoration| format!("{base_name}.{decoration}")),
+                    ),
+                )
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// Returns the hardware and compatible IDs of the devices listed in the
+    /// models sections. Models sections list one
+    /// `<description> = <install section>, <hardware id>[, <compatible id>...]`
+    /// entry per device. Duplicate IDs, compared case-insensitively, are
+    /// returned once.
+    #[must_use]
+    pub fn device_ids(&self) -> Vec<String> {
+        let models_section_names = self.models_section_names();
+        let mut device_ids: Vec<String> = Vec::new();
+        for section in self.sections.iter().filter(|section| {
+            models_section_names
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(&section.name))
+        }) {
+            for device_id in section
+                .entries
+                .iter()
+                .filter(|entry| entry.key.is_some())
+                .flat_map(|entry| entry.values.iter().skip(1))
+            {
+                if !device_id.is_empty()
+                    && !device_ids
+                        .iter()
+                        .any(|existing| existing.eq_ignore_ascii_case(device_id))
+                {
+                    device_ids.push(device_id.clone());
+                }
+            }
+        }
+        device_ids
+    }
+
+    /// Returns the keys and values of all entries that are absolute paths,
+    /// e.g. `C:\build\driver.sys` or `\\server\share\driver.sys`, which tie
+    /// the INF to the machine it was built on
+    #[must_use]
+    pub fn absolute_paths(&self) -> Vec<&str> {
+        self.sections
+            .iter()
+            .flat_map(|section| section.entries.iter())
+            .flat_map(|entry| entry.key.iter().chain(&entry.values))
+            .map(String::as_str)
+            .filter(|value| is_absolute_path(value))
+            .collect()
+    }
+
+    /// Returns the files listed by the `[SourceDisksFiles]` sections,
+    /// including the architecture decorated ones. Entries are
+    /// `<file name> = <disk id>[, <subdir>]`, so the path of each file is its
+    /// name joined to the subdirectory of the entry, if any. Files named by
+    /// `%strkey%` tokens are skipped and duplicate paths are returned once.
+    #[must_use]
+    pub fn source_disks_files(&self) -> Vec<String> {
+        let mut files: Vec<String> = Vec::new();
+        for entry in self.source_disks_files_entries() {
+            let (file_name, subdir) = match &entry.key {
+                Some(file_name) => (file_name.as_str(), entry.values.get(1)),
+                None => match entry.values.first() {
+                    Some(file_name) => (file_name.as_str(), None),
+                    None => continue,
+                },
+            };
+            if file_name.is_empty() || file_name.contains('%') {
+                continue;
+            }
+            let file = match subdir.map(|subdir| subdir.trim_matches(['\\', '/'])) {
+                Some(subdir) if !subdir.is_empty() => format!("{subdir}\\{file_name}"),
+                _ => file_name.to_string(),
+            };
+            if !files
+                .iter()
+                .any(|existing| existing.eq_ignore_ascii_case(&file))
+            {
+                files.push(file);
+            }
+        }
+        files
+    }
+
+    /// Returns the co-installer directives of the INF, which universal INFs
+    /// may not use: the `.CoInstallers` install sections and the `AddReg`
+    /// entries that set the `CoInstallers32` registry value, e.g.
+    /// `HKR,,CoInstallers32,0x00010000,"WdfCoInstaller01011.dll,..."`
+    #[must_use]
+    pub fn co_installer_directives(&self) -> Vec<String> {
+        let mut directives = Vec::new();
+        for section in &self.sections {
+            if section
+                .name
+                .len()
+                .checked_sub(CO_INSTALLERS_SECTION_SUFFIX.len())
+                .and_then(|start| section.name.get(start..))
+                .is_some_and(|suffix| suffix.eq_ignore_ascii_case(CO_INSTALLERS_SECTION_SUFFIX))
+            {
+                directives.push(format!("[{}]", section.name));
+            }
+            if section.entries.iter().any(|entry| {
+                entry.key.is_none()
+                    && entry
+                        .values
+                        .get(2)
+                        .is_some_and(|value| value.eq_ignore_ascii_case(CO_INSTALLERS32_VALUE_NAME))
+            }) {
+                directives.push(format!(
+                    "{CO_INSTALLERS32_VALUE_NAME} in [{}]",
+                    section.name
+                ));
+            }
+        }
+        directives
+    }
+
+    /// Returns the string of the `[Strings]` section that `value` refers to if
+    /// it is a `%strkey%` token, or `value` itself otherwise or if the string
+    /// is not defined
+    fn expand_string_token<'s>(&'s self, value: &'s str) -> &'s str {
+        value
+            .strip_prefix('%')
+            .and_then(|token| token.strip_suffix('%'))
+            .filter(|key| !key.is_empty())
+            .and_then(|key| self.values(STRINGS_SECTION_NAME, key))
+            .and_then(<[String]>::first)
+            .map_or(value, String::as_str)
+    }
+
+    fn manufacturer_entries(&self) -> impl Iterator<Item = &InfEntry> {
+        self.sections
+            .iter()
+            .filter(|section| section.name.eq_ignore_ascii_case(MANUFACTURER_SECTION_NAME))
+            .flat_map(|section| section.entries.iter())
+            .filter(|entry| entry.key.is_some())
+    }
+
+    fn source_disks_files_entries(&self) -> impl Iterator<Item = &InfEntry> {
+        self.sections
+            .iter()
+            .filter(|section| {
+                section
+                    .name
+                    .split('.')
+                    .next()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(SOURCE_DISKS_FILES_SECTION_NAME))
+            })
+            .flat_map(|section| section.entries.iter())
+    }
+}
+
+impl InfSection {
+    /// Returns the values of the first entry with the given key. Keys are
+    /// compared case-insensitively.
+    #[must_use]
+    pub fn values(&self, key: &str) -> Option<&[String]> {
+        self.entries
+            .iter()
+            .find(|entry| {
+                entry
+                    .key
+                    .as_deref()
+                    .is_some_and(|entry_key| entry_key.eq_ignore_ascii_case(key))
+            })
+            .map(|entry| entry.values.as_slice())
+    }
+}
+
+impl InfEntry {
+    fn parse(line: &str) -> Self {
+        let (key, value) = match split_unquoted(line, '=').as_slice() {
+            [key, value] => (Some(unquote(key)), *value),
+            _ => (None, line),
+        };
+        Self {
+            key,
+            values: split_unquoted(value, ',')
+                .into_iter()
+                .map(unquote)
+                .collect(),
+        }
+    }
+}
+
+/// Removes the `;` comment at the end of a line, ignoring `;` in quotes
+fn strip_comment(line: &str) -> &str {
+    let mut in_quotes = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Splits `value` at the first `=` or at every `,`, ignoring separators in
+/// quotes
+fn split_unquoted(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+    for (index, c) in value.char_indices() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        } else if c == separator && !in_quotes {
+            parts.push(&value[start..index]);
+            start = index + c.len_utf8();
+            if separator == '=' {
+                break;
+            }
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+/// Returns whether `value` starts with a drive letter path, e.g. `C:\` or
+/// `C:/`, or a UNC path, e.g. `\\server`
+#[must_use]
+pub fn is_absolute_path(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    matches!(bytes, [drive, b':', b'\\' | b'/', ..] if drive.is_ascii_alphabetic())
+        || bytes.starts_with(br"\\")
+}
+
+/// Trims `value` and removes its surrounding quotes
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INF_CONTENT: &str = r#"
+[Version]
+Signature   = "$WINDOWS NT$"
+CatalogFile = driver.cat ; comment
+DriverVer   = 01/02/2024,1.2.3.0
+
+[Manufacturer]
+%ManufacturerName% = Standard,NTamd64.10.0...16299,\
+    NTarm64.10.0...16299
+
+[Standard.NTamd64.10.0...16299]
+%Device.DeviceDesc% = Device_Install, Root\Sample, PCI\VEN_1234&DEV_5678
+
+[Standard.NTarm64.10.0...16299]
+%Device.DeviceDesc% = Device_Install, root\sample
+
+[SourceDisksFiles.amd64]
+driver.sys = 1,,
+
+[Strings]
+Device.DeviceDesc = "Sample; Device, Inc."
+"#;
+
+    #[test]
+    fn parse_reads_sections_and_entries() {
+        let inf_file = InfFile::parse(INF_CONTENT);
+        assert_eq!(
+            inf_file
+                .sections
+                .iter()
+                .map(|section| section.name.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "Version",
+                "Manufacturer",
+                "Standard.NTamd64.10.0...16299",
+                "Standard.NTarm64.10.0...16299",
+                "SourceDisksFiles.amd64",
+                "Strings"
+            ]
+        );
+        assert_eq!(
+            inf_file.values("strings", "Device.DeviceDesc"),
+            Some(["Sample; Device, Inc.".to_string()].as_slice())
+        );
+        assert_eq!(
+            inf_file.values("version", "Signature"),
+            Some(["$WINDOWS NT$".to_string()].as_slice())
+        );
+    }
+
+    #[test]
+    fn byte_order_mark_is_ignored() {
+        assert_eq!(
+            InfFile::parse(&format!("\u{feff}{INF_CONTENT}")),
+            InfFile::parse(INF_CONTENT)
+        );
+        assert_eq!(
+            InfFile::parse("\u{feff}[Version]\r\nClass = Sample\r\n")
+                .values("Version", "Class"),
+            Some(["Sample".to_string()].as_slice())
+        );
+    }
+
+    #[test]
+    fn version_keys_are_read() {
+        let inf_file = InfFile::parse(INF_CONTENT);
+        assert_eq!(
+            inf_file.driver_ver(),
+            Some(["01/02/2024".to_string(), "1.2.3.0".to_string()].as_slice())
+        );
+        assert_eq!(inf_file.catalog_file(), Some("driver.cat"));
+    }
+
+    #[test]
+    fn provider_is_expanded_from_the_strings_section() {
+        assert_eq!(InfFile::parse(INF_CONTENT).provider(), None);
+        for (inf_content, provider) in [
+            ("[Version]\nProvider = Contoso\n", "Contoso"),
+            (
+                "[Version]\nProvider = %ProviderString%\n[Strings]\nproviderstring = \"Contoso, \
+                 Ltd.\"\n",
+                "Contoso, Ltd.",
+            ),
+            (
+                "[Version]\nProvider = %ProviderString%\n[Strings.0407]\nProviderString = \
+                 Contoso\n",
+                "%ProviderString%",
+            ),
+        ] {
+            assert_eq!(
+                InfFile::parse(inf_content).provider(),
+                Some(provider),
+                "{inf_content}"
+            );
+        }
+    }
+
+    #[test]
+    fn sample_class_is_detected_from_the_class_guid_or_name() {
+        assert!(!InfFile::parse(INF_CONTENT).is_sample_class());
+        assert!(
+            InfFile::parse(
+                "[Version]\nClass = Sample\nClassGuid = {78a1c341-4539-11d3-b88d-00c04fad5171}\n"
+            )
+            .is_sample_class()
+        );
+        assert!(InfFile::parse("[Version]\nClass = sample\n").is_sample_class());
+        assert!(
+            !InfFile::parse(
+                "[Version]\nClass = Sample\nClassGuid = {4d36e97d-e325-11ce-bfc1-08002be10318}\n"
+            )
+            .is_sample_class()
+        );
+    }
+
+    #[test]
+    fn manufacturer_decorations_are_read_across_continued_lines() {
+        assert_eq!(
+            InfFile::parse(INF_CONTENT).manufacturer_decorations(),
+            ["NTamd64.10.0...16299", "NTarm64.10.0...16299"]
+        );
+    }
+
+    #[test]
+    fn device_ids_are_read_from_all_models_sections() {
+        assert_eq!(
+            InfFile::parse(INF_CONTENT).device_ids(),
+            [r"Root\Sample", r"PCI\VEN_1234&DEV_5678"]
+        );
+    }
+
+    #[test]
+    fn source_disks_files_are_read_from_all_source_disks_files_sections() {
+        let inf_file = InfFile::parse(
+            r"
+[SourceDisksFiles]
+driver.sys = 1,,
+Strings.ini = 1,lang\en-US
+%FirmwareFile% = 1
+
+[SourceDisksFiles.arm64]
+DRIVER.SYS = 1,,
+",
+        );
+        assert_eq!(
+            InfFile::parse(INF_CONTENT).source_disks_files(),
+            ["driver.sys"]
+        );
+        assert_eq!(
+            inf_file.source_disks_files(),
+            ["driver.sys", r"lang\en-US\Strings.ini"]
+        );
+    }
+
+    #[test]
+    fn co_installer_directives_are_found() {
+        assert!(
+            InfFile::parse(INF_CONTENT)
+                .co_installer_directives()
+                .is_empty()
+        );
+        let inf_file = InfFile::parse(
+            r#"
+[Device_Install.NT.CoInstallers]
+AddReg = Device_CoInstaller_AddReg
+
+[Device_CoInstaller_AddReg]
+HKR,,CoInstallers32,0x00010000,"WdfCoInstaller01011.dll,WdfCoInstaller"
+"#,
+        );
+        assert_eq!(
+            inf_file.co_installer_directives(),
+            [
+                "[Device_Install.NT.CoInstallers]",
+                "CoInstallers32 in [Device_CoInstaller_AddReg]"
+            ]
+        );
+    }
+
+    #[test]
+    fn absolute_paths_are_found_in_keys_and_values() {
+        let inf_file = InfFile::parse(
+            r#"
+[SourceDisksNames]
+1 = %DiskName%,,,"C:\build\driver\target\debug"
+
+[SourceDisksFiles]
+\\server\share\driver.sys = 1,,
+driver.sys = 1,,
+
+[Driver_CopyFiles]
+driver.sys
+
+[Strings]
+DiskName = "Driver Installation Disk"
+LogDir = D:/logs
+"#,
+        );
+        assert_eq!(
+            inf_file.absolute_paths(),
+            [
+                r"C:\build\driver\target\debug",
+                r"\\server\share\driver.sys",
+                "D:/logs"
+            ]
+        );
+        assert!(InfFile::parse(INF_CONTENT).absolute_paths().is_empty());
+    }
+}
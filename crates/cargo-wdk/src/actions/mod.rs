@@ -7,16 +7,33 @@
 //! * `new` - New action module
 //! * `build` - Build action module
 //! * `clean` - Clean action module
+//! * `sign` - Sign action module
+//! * `diff` - Diff action module
+//! * `verify_package` - Verify package action module
+//! * `wdk_root` - WDK root action module
+//! * `trust_cert` - Trust cert action module
+//! * `schema` - Schema action module
+//! * `setup` - Setup action module
 pub mod build;
 pub mod clean;
+pub mod diff;
+mod eku;
+mod inf;
 pub mod new;
+pub mod schema;
+pub mod setup;
+pub mod sign;
+mod signtool;
+pub mod trust_cert;
+pub mod verify_package;
+pub mod wdk_root;
 
 use std::{
     fmt::{self, Display},
     str::FromStr,
 };
 
-use wdk_build::CpuArchitecture;
+use wdk_build::{CpuArchitecture, DriverConfig};
 
 pub const KMDF_STR: &str = "kmdf";
 pub const UMDF_STR: &str = "umdf";
@@ -26,18 +43,45 @@
 /// `aarch64/Arm64` target triple name
 const AARCH64_TARGET_TRIPLE_NAME: &str = "aarch64-pc-windows-msvc";
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Cargo profile used to build the driver
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Profile {
     Dev,
     Release,
+    /// A custom profile defined in a `[profile.<name>]` table of the manifest
+    Custom(String),
+}
+
+impl Profile {
+    /// Returns the name of the directory inside the target directory in which
+    /// cargo places the artifacts built with this profile. Cargo maps the
+    /// `dev` profile to the `debug` directory while every other profile uses
+    /// a directory with the same name as the profile.
+    #[must_use]
+    pub fn target_dir_name(&self) -> &str {
+        match self {
+            Self::Dev => "debug",
+            Self::Release => "release",
+            Self::Custom(name) => name.as_str(),
+        }
+    }
 }
+
 impl FromStr for Profile {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
-            "dev" => std::result::Result::Ok(Self::Dev),
+            // `debug` is accepted as an alias since it is the name of the
+            // directory cargo uses for the `dev` profile
+            "dev" | "debug" => std::result::Result::Ok(Self::Dev),
             "release" => std::result::Result::Ok(Self::Release),
+            _ if !s.is_empty()
+                && s.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') =>
+            {
+                std::result::Result::Ok(Self::Custom(s.to_string()))
+            }
             _ => Err(format!("'{s}' is not a valid profile")),
         }
     }
@@ -47,6 +91,7 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s = match self {
             Self::Dev => "dev",
             Self::Release => "release",
+            Self::Custom(name) => name.as_str(),
         };
         write!(f, "{s}")
     }
@@ -82,6 +127,17 @@ fn from_str(s: &str) -> Result<Self, Self::Err> {
     }
 }
 
+impl From<&DriverConfig> for DriverType {
+    /// Resolves the driver type of the driver model in the WDK metadata
+    fn from(driver_config: &DriverConfig) -> Self {
+        match driver_config {
+            DriverConfig::Kmdf(_) => Self::Kmdf,
+            DriverConfig::Umdf(_) => Self::Umdf,
+            DriverConfig::Wdm => Self::Wdm,
+        }
+    }
+}
+
 impl Display for DriverType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s = match self {
@@ -92,3 +148,47 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{s}")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::Profile;
+
+    #[test]
+    fn profile_from_str_accepts_dev_and_debug_alias() {
+        assert_eq!(Profile::from_str("dev"), Ok(Profile::Dev));
+        assert_eq!(Profile::from_str("debug"), Ok(Profile::Dev));
+        assert_eq!(Profile::from_str("Release"), Ok(Profile::Release));
+    }
+
+    #[test]
+    fn profile_from_str_accepts_custom_profile() {
+        assert_eq!(
+            Profile::from_str("release-lto"),
+            Ok(Profile::Custom("release-lto".to_string()))
+        );
+    }
+
+    #[test]
+    fn profile_from_str_rejects_invalid_profile_name() {
+        assert_eq!(
+            Profile::from_str("not/a/profile"),
+            Err("'not/a/profile' is not a valid profile".to_string())
+        );
+        assert_eq!(
+            Profile::from_str(""),
+            Err("'' is not a valid profile".to_string())
+        );
+    }
+
+    #[test]
+    fn profile_target_dir_name_follows_cargo_rules() {
+        assert_eq!(Profile::Dev.target_dir_name(), "debug");
+        assert_eq!(Profile::Release.target_dir_name(), "release");
+        assert_eq!(
+            Profile::Custom("release-lto".to_string()).target_dir_name(),
+            "release-lto"
+        );
+    }
+}
@@ -3,7 +3,10 @@
 //! This module defines error types for new action module.
 use thiserror::Error;
 
-use crate::providers::error::{CommandError, FileError};
+use crate::{
+    actions::DriverType,
+    providers::error::{CommandError, FileError},
+};
 
 /// Errors for the new action layer
 #[derive(Debug, Error)]
@@ -16,6 +19,16 @@ pub enum NewActionError {
     TemplateNotFound(String),
     #[error("Unable to derive driver crate name from the provided path: {0}")]
     InvalidDriverCrateName(String),
+    #[error(
+        "Version {0} cannot be used as a driver version, the major, minor and patch versions must \
+         not exceed 65535"
+    )]
+    UnsupportedDriverVersion(String),
+    #[error(
+        "A custom panic handler cannot be scaffolded for {0} drivers, which link the standard \
+         library and use its panic handler"
+    )]
+    CustomPanicHandlerNotSupported(DriverType),
     #[error(transparent)]
     Io(#[from] std::io::Error),
 }
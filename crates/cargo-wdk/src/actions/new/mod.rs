@@ -10,11 +10,13 @@
 
 use std::path::{Path, PathBuf};
 
+use cargo_metadata::semver::Version;
 use clap_verbosity_flag::Verbosity;
 use error::NewActionError;
 use include_dir::{Dir, include_dir};
 use mockall_double::double;
 use tracing::{debug, info};
+use wdk_build::CpuArchitecture;
 
 #[double]
 use crate::providers::{exec::CommandExec, fs::Fs};
@@ -23,11 +25,164 @@
 /// Directory containing the templates to be bundled with the utility
 static TEMPLATES_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/templates");
 
+/// Placeholder in the templates that is substituted with the driver's pool tag
+const POOL_TAG_PLACEHOLDER: &str = "##pool_tag_placeholder##";
+/// Pool tags are four character codes
+const POOL_TAG_LENGTH: usize = 4;
+
+/// Placeholder date of the `DriverVer` written for an initial version.
+/// `stampinf` replaces it with the date of the build when packaging.
+const DRIVER_VER_PLACEHOLDER_DATE: &str = "01/01/1970";
+
+/// Placeholder in the templates that is substituted with the driver's crate
+/// name
+const DRIVER_NAME_PLACEHOLDER: &str = "##driver_name_placeholder##";
+
+/// Placeholder in the `.inx` templates that `stampinf` replaces with the
+/// architecture being packaged
+const INF_ARCH_PLACEHOLDER: &str = "$ARCH$";
+/// Name of the INF section listing the manufacturers of the driver
+const INF_MANUFACTURER_SECTION: &str = "Manufacturer";
+/// Name of the INF section listing the source files of the driver package
+const INF_SOURCE_DISKS_FILES_SECTION: &str = "SourceDisksFiles";
+
+/// Templates of the files scaffolded in the `.devcontainer` folder
+const DEVCONTAINER_TEMPLATES: [&str; 2] = ["devcontainer.json", "Dockerfile"];
+
+/// Templates of the files scaffolded in the `tests` folder, as the sub folder
+/// of `tests` they are written to and their file name
+const INTEGRATION_TEST_TEMPLATES: [(Option<&str>, &str); 3] = [
+    (None, "README.md"),
+    (None, "driver_package.rs"),
+    (Some("common"), "mod.rs"),
+];
+
+/// Dependency added to `Cargo.toml` when scaffolding `tracing` based logging
+const TRACING_DEPENDENCY: &str = "tracing = { version = \"0.1\", default-features = false }\n";
+
+/// Dependency line of the crate providing the panic handler of the kernel
+/// mode templates
+const WDK_PANIC_DEPENDENCY: &str = "wdk-panic = ";
+/// Item of the kernel mode `lib.rs` templates that links the panic handler of
+/// the `wdk-panic` crate
+const WDK_PANIC_EXTERN_CRATE: &str = "extern crate wdk_panic;";
+/// Declaration of the panic handler module appended to `lib.rs`
+const PANIC_MODULE_DECLARATION: &str = "\n#[cfg(not(test))]\nmod panic;\n";
+
+/// Byte order mark prepended to the `.inx` file for `InfEncoding::Utf8Bom`
+const UTF8_BOM: &str = "\u{feff}";
+
+/// Logging framework wired into a new driver project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Logging {
+    /// No logging setup is scaffolded.
+    #[default]
+    None,
+    /// WPP software tracing with a trace format control block.
+    Wpp,
+    /// The `tracing` crate with a subscriber printing to the debugger.
+    Tracing,
+}
+
+/// Panic handling scaffolded in a new driver project. Both variants build the
+/// driver with `panic = "abort"`, since a panic cannot unwind out of a driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Panic {
+    /// Kernel mode drivers use the panic handler of the `wdk-panic` crate.
+    #[default]
+    Abort,
+    /// Kernel mode drivers use a panic handler stub in `src/panic.rs` instead
+    /// of the `wdk-panic` crate.
+    Custom,
+}
+
+/// Line endings of the text files scaffolded in a new driver project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEndings {
+    /// LF for the source and configuration files and CRLF for the `.inx`
+    /// file, the line endings the WDK tools and samples use for INF files.
+    #[default]
+    Auto,
+    /// LF for every file.
+    Lf,
+    /// CRLF for every file.
+    Crlf,
+}
+
+impl LineEndings {
+    /// Returns the line ending of the scaffolded source and configuration
+    /// files
+    const fn source_line_ending(self) -> &'static str {
+        match self {
+            Self::Auto | Self::Lf => "\n",
+            Self::Crlf => "\r\n",
+        }
+    }
+
+    /// Returns the line ending of the scaffolded `.inx` file
+    const fn inx_line_ending(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::Auto | Self::Crlf => "\r\n",
+        }
+    }
+}
+
+/// Encoding of the `.inx` file scaffolded in a new driver project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InfEncoding {
+    /// UTF-8 without a byte order mark.
+    #[default]
+    Utf8,
+    /// UTF-8 with a byte order mark, for tools that read INF files without
+    /// one with the ANSI code page.
+    Utf8Bom,
+}
+
+/// Options of the driver project created by `NewAction`.
+pub struct NewActionParams<'a> {
+    /// The path to the new driver project. The last part of the path is used
+    /// as the package name.
+    pub path: &'a Path,
+    /// The type of the driver project to be created.
+    pub driver_type: DriverType,
+    /// The logging framework to wire into the driver project.
+    pub logging: Logging,
+    /// The panic handling to scaffold in the driver project.
+    pub panic: Panic,
+    /// The initial version of the driver project. `None` keeps the `cargo
+    /// new` default.
+    pub version: Option<&'a Version>,
+    /// Whether a `.devcontainer` folder is scaffolded in the driver project.
+    pub with_devcontainer: bool,
+    /// Whether integration tests that install the driver package are
+    /// scaffolded in the `tests` folder of the driver project.
+    pub with_integration_tests: bool,
+    /// The architectures the `.inx` file is decorated for. Empty keeps the
+    /// `NT$ARCH$` decorations that `stampinf` resolves to the architecture
+    /// being packaged.
+    pub target_archs: &'a [CpuArchitecture],
+    /// The line endings of the scaffolded files.
+    pub line_endings: LineEndings,
+    /// The encoding of the scaffolded `.inx` file.
+    pub inf_encoding: InfEncoding,
+    /// The verbosity level for logging.
+    pub verbosity_level: Verbosity,
+}
+
 /// `NewAction` struct and its methods orchestrates the creation of new driver
 /// project based on the specified driver type.
 pub struct NewAction<'a> {
     path: &'a Path,
     driver_type: DriverType,
+    logging: Logging,
+    panic: Panic,
+    version: Option<&'a Version>,
+    with_devcontainer: bool,
+    with_integration_tests: bool,
+    target_archs: &'a [CpuArchitecture],
+    line_endings: LineEndings,
+    inf_encoding: InfEncoding,
     verbosity_level: Verbosity,
     command_exec: &'a CommandExec,
     fs: &'a Fs,
@@ -38,10 +193,8 @@ impl<'a> NewAction<'a> {
     ///
     /// # Arguments
     ///
-    /// * `path` - The path to the new driver project. The last part of the path
-    ///   is used as the package name.
-    /// * `driver_type` - The type of the driver project to be created.
-    /// * `verbosity_level` - The verbosity level for logging.
+    /// * `params` - The `NewActionParams` struct containing the options of the
+    ///   new driver project.
     /// * `command_exec` - The provider for command execution.
     /// * `fs` - The provider for file system operations.
     ///
@@ -49,16 +202,22 @@ impl<'a> NewAction<'a> {
     ///
     /// * `Self` - A new instance of `NewAction`.
     pub const fn new(
-        path: &'a Path,
-        driver_type: DriverType,
-        verbosity_level: Verbosity,
+        params: &NewActionParams<'a>,
         command_exec: &'a CommandExec,
         fs: &'a Fs,
     ) -> Self {
         Self {
-            path,
-            driver_type,
-            verbosity_level,
+            path: params.path,
+            driver_type: params.driver_type,
+            logging: params.logging,
+            panic: params.panic,
+            version: params.version,
+            with_devcontainer: params.with_devcontainer,
+            with_integration_tests: params.with_integration_tests,
+            target_archs: params.target_archs,
+            line_endings: params.line_endings,
+            inf_encoding: params.inf_encoding,
+            verbosity_level: params.verbosity_level,
             command_exec,
             fs,
         }
@@ -73,6 +232,10 @@ pub const fn new(
     ///
     /// # Errors
     ///
+    /// * `NewActionError::UnsupportedDriverVersion` - If the initial version
+    ///   cannot be represented in the `DriverVer` of an INF file.
+    /// * `NewActionError::CustomPanicHandlerNotSupported` - If a custom panic
+    ///   handler is requested for a UMDF driver.
     /// * `NewActionError::CargoNewCommand` - If there is an error running the
     ///   `cargo new` command.
     /// * `NewActionError::TemplateNotFound` - If a template file matching the
@@ -85,12 +248,28 @@ pub fn run(&self) -> Result<(), NewActionError> {
             self.driver_type,
             self.path.display()
         );
+        let driver_version = self.version.map(driver_version).transpose()?;
+        if self.panic == Panic::Custom && self.driver_type == DriverType::Umdf {
+            return Err(NewActionError::CustomPanicHandlerNotSupported(
+                self.driver_type,
+            ));
+        }
         self.run_cargo_new()?;
         self.copy_lib_rs_template()?;
         self.update_cargo_toml()?;
-        self.create_inx_file()?;
+        self.create_inx_file(driver_version.as_deref())?;
         self.copy_build_rs_template()?;
         self.copy_cargo_config()?;
+        self.scaffold_logging()?;
+        if self.panic == Panic::Custom {
+            self.scaffold_panic_handler()?;
+        }
+        if self.with_devcontainer {
+            self.scaffold_devcontainer()?;
+        }
+        if self.with_integration_tests {
+            self.scaffold_integration_tests()?;
+        }
         info!(
             "New {} driver crate created successfully at: {}",
             self.driver_type,
@@ -146,9 +325,16 @@ pub fn copy_lib_rs_template(&self) -> Result<(), NewActionError> {
         let template_file = TEMPLATES_DIR.get_file(&template_path).ok_or_else(|| {
             NewActionError::TemplateNotFound(template_path.to_string_lossy().into_owned())
         })?;
+        let mut lib_rs_content = String::from_utf8_lossy(template_file.contents())
+            .replace(POOL_TAG_PLACEHOLDER, &self.pool_tag());
+        if self.panic == Panic::Custom {
+            lib_rs_content = without_wdk_panic_crate(&lib_rs_content);
+        }
         let lib_rs_path = self.path.join("src").join("lib.rs");
-        self.fs
-            .write_to_file(&lib_rs_path, template_file.contents())?;
+        self.fs.write_to_file(
+            &lib_rs_path,
+            self.with_source_line_endings(&lib_rs_content).as_bytes(),
+        )?;
         Ok(())
     }
 
@@ -171,13 +357,19 @@ pub fn copy_build_rs_template(&self) -> Result<(), NewActionError> {
             "Copying build.rs template for driver type: {}",
             self.driver_type
         );
-        let template_path = PathBuf::from("build.rs.tmp");
+        let template_path = match self.logging {
+            Logging::Wpp => PathBuf::from("logging").join("wpp").join("build.rs.tmp"),
+            Logging::None | Logging::Tracing => PathBuf::from("build.rs.tmp"),
+        };
         let template_file = TEMPLATES_DIR.get_file(&template_path).ok_or_else(|| {
             NewActionError::TemplateNotFound(template_path.to_string_lossy().into_owned())
         })?;
         let build_rs_path = self.path.join("build.rs");
-        self.fs
-            .write_to_file(&build_rs_path, template_file.contents())?;
+        let build_rs_content = String::from_utf8_lossy(template_file.contents());
+        self.fs.write_to_file(
+            &build_rs_path,
+            self.with_source_line_endings(&build_rs_content).as_bytes(),
+        )?;
         Ok(())
     }
 
@@ -199,8 +391,13 @@ pub fn update_cargo_toml(&self) -> Result<(), NewActionError> {
         let cargo_toml_path = self.path.join("Cargo.toml");
         let mut cargo_toml_content = self.fs.read_file_to_string(&cargo_toml_path)?;
         cargo_toml_content = cargo_toml_content.replace("[dependencies]\n", "");
-        self.fs
-            .write_to_file(&cargo_toml_path, cargo_toml_content.as_bytes())?;
+        if let Some(version) = self.version {
+            cargo_toml_content = with_package_version(&cargo_toml_content, version);
+        }
+        self.fs.write_to_file(
+            &cargo_toml_path,
+            self.with_source_line_endings(&cargo_toml_content).as_bytes(),
+        )?;
 
         let template_cargo_toml_path =
             PathBuf::from(&self.driver_type.to_string()).join("Cargo.toml.tmp");
@@ -211,12 +408,49 @@ pub fn update_cargo_toml(&self) -> Result<(), NewActionError> {
                     template_cargo_toml_path.to_string_lossy().into_owned(),
                 )
             })?;
-        self.fs
-            .append_to_file(&cargo_toml_path, template_cargo_toml_file.contents())?;
+        let mut template_cargo_toml_content =
+            String::from_utf8_lossy(template_cargo_toml_file.contents())
+                .replace(POOL_TAG_PLACEHOLDER, &self.pool_tag());
+        if self.panic == Panic::Custom {
+            template_cargo_toml_content = template_cargo_toml_content
+                .split_inclusive('\n')
+                .filter(|line| !line.starts_with(WDK_PANIC_DEPENDENCY))
+                .collect();
+        }
+        self.fs.append_to_file(
+            &cargo_toml_path,
+            self.with_source_line_endings(&template_cargo_toml_content)
+                .as_bytes(),
+        )?;
         Ok(())
     }
 
-    /// Creates the `.inx` file for the driver project.
+    /// Returns the default pool tag of the driver, made of the first four
+    /// alphanumeric characters of the crate name padded with `0`. The KMDF
+    /// templates use it for the `pool-tag` metadata and the `POOL_TAG`
+    /// constant.
+    fn pool_tag(&self) -> String {
+        let crate_name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut pool_tag: String = crate_name
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .take(POOL_TAG_LENGTH)
+            .collect();
+        while pool_tag.len() < POOL_TAG_LENGTH {
+            pool_tag.push('0');
+        }
+        pool_tag
+    }
+
+    /// Creates the `.inx` file for the driver project. If an initial driver
+    /// version is given, e.g. `1.2.3.0`, it is written to the `DriverVer`
+    /// entry of the template. If target architectures are given, the
+    /// `[Manufacturer]` section is decorated and a models section is created
+    /// for each of them.
     ///
     /// # Returns
     ///
@@ -229,7 +463,7 @@ pub fn update_cargo_toml(&self) -> Result<(), NewActionError> {
     ///   file is not bundled with the utility.
     /// * `NewActionError::FileSystem` - If there is an error writing .inx
     ///   template content to the destination .inx file.
-    pub fn create_inx_file(&self) -> Result<(), NewActionError> {
+    pub fn create_inx_file(&self, driver_version: Option<&str>) -> Result<(), NewActionError> {
         let driver_crate_name = self
             .path
             .file_name()
@@ -246,15 +480,27 @@ pub fn create_inx_file(&self) -> Result<(), NewActionError> {
             NewActionError::TemplateNotFound(inx_template_path.to_string_lossy().into_owned())
         })?;
         let inx_content = String::from_utf8_lossy(inx_template_file.contents()).to_string();
-        let substituted_inx_content = inx_content.replace(
-            "##driver_name_placeholder##",
-            &underscored_driver_crate_name,
+        let mut substituted_inx_content =
+            inx_content.replace(DRIVER_NAME_PLACEHOLDER, &underscored_driver_crate_name);
+        if let Some(driver_version) = driver_version {
+            substituted_inx_content = with_driver_ver(&substituted_inx_content, driver_version);
+        }
+        if !self.target_archs.is_empty() {
+            substituted_inx_content =
+                with_arch_decorations(&substituted_inx_content, self.target_archs);
+        }
+        let mut encoded_inx_content = with_line_endings(
+            &substituted_inx_content,
+            self.line_endings.inx_line_ending(),
         );
+        if self.inf_encoding == InfEncoding::Utf8Bom {
+            encoded_inx_content.insert_str(0, UTF8_BOM);
+        }
         let inx_output_path = self
             .path
             .join(format!("{underscored_driver_crate_name}.inx"));
         self.fs
-            .write_to_file(&inx_output_path, substituted_inx_content.as_bytes())?;
+            .write_to_file(&inx_output_path, encoded_inx_content.as_bytes())?;
         Ok(())
     }
 
@@ -284,10 +530,421 @@ pub fn copy_cargo_config(&self) -> Result<(), NewActionError> {
                     cargo_config_template_path.to_string_lossy().into_owned(),
                 )
             })?;
-        self.fs
-            .write_to_file(&cargo_config_path, cargo_config_template_file.contents())?;
+        let cargo_config_content = String::from_utf8_lossy(cargo_config_template_file.contents());
+        self.fs.write_to_file(
+            &cargo_config_path,
+            self.with_source_line_endings(&cargo_config_content)
+                .as_bytes(),
+        )?;
+        Ok(())
+    }
+
+    /// Scaffolds the logging framework selected for the driver project.
+    ///
+    /// For WPP, the trace format control block is written to `src/wpp.rs`.
+    /// For `tracing`, the dependency is added to `Cargo.toml` and a subscriber
+    /// printing to the debugger is written to `src/logging.rs`. In both cases
+    /// the new module is declared in `src/lib.rs`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), NewActionError>` - A result indicating success or failure
+    ///   of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * `NewActionError::TemplateNotFound` - If the matching logging template
+    ///   file is not bundled with the utility.
+    /// * `NewActionError::FileSystem` - If there is an error reading or writing
+    ///   the driver project files.
+    pub fn scaffold_logging(&self) -> Result<(), NewActionError> {
+        let (module_name, template_path) = match self.logging {
+            Logging::None => return Ok(()),
+            Logging::Wpp => (
+                "wpp",
+                PathBuf::from("logging").join("wpp").join("wpp.rs.tmp"),
+            ),
+            Logging::Tracing => {
+                self.add_tracing_dependency()?;
+                (
+                    "logging",
+                    PathBuf::from("logging")
+                        .join("tracing")
+                        .join("logging.rs.tmp"),
+                )
+            }
+        };
+        debug!("Scaffolding {:?} logging", self.logging);
+        let template_file = TEMPLATES_DIR.get_file(&template_path).ok_or_else(|| {
+            NewActionError::TemplateNotFound(template_path.to_string_lossy().into_owned())
+        })?;
+        let module_path = self.path.join("src").join(format!("{module_name}.rs"));
+        let module_content = String::from_utf8_lossy(template_file.contents());
+        self.fs.write_to_file(
+            &module_path,
+            self.with_source_line_endings(&module_content).as_bytes(),
+        )?;
+        let lib_rs_path = self.path.join("src").join("lib.rs");
+        self.fs.append_to_file(
+            &lib_rs_path,
+            self.with_source_line_endings(&format!("\nmod {module_name};\n"))
+                .as_bytes(),
+        )?;
+        Ok(())
+    }
+
+    /// Scaffolds a panic handler stub in `src/panic.rs` and declares the
+    /// module in `src/lib.rs`. The `wdk-panic` crate is left out of the
+    /// `Cargo.toml` and `lib.rs` of the driver project in this case.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), NewActionError>` - A result indicating success or failure
+    ///   of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * `NewActionError::TemplateNotFound` - If the panic handler template
+    ///   file is not bundled with the utility.
+    /// * `NewActionError::FileSystem` - If there is an error writing the driver
+    ///   project files.
+    pub fn scaffold_panic_handler(&self) -> Result<(), NewActionError> {
+        debug!("Scaffolding custom panic handler");
+        let template_path = PathBuf::from("panic").join("panic.rs.tmp");
+        let template_file = TEMPLATES_DIR.get_file(&template_path).ok_or_else(|| {
+            NewActionError::TemplateNotFound(template_path.to_string_lossy().into_owned())
+        })?;
+        let module_path = self.path.join("src").join("panic.rs");
+        let module_content = String::from_utf8_lossy(template_file.contents());
+        self.fs.write_to_file(
+            &module_path,
+            self.with_source_line_endings(&module_content).as_bytes(),
+        )?;
+        let lib_rs_path = self.path.join("src").join("lib.rs");
+        self.fs.append_to_file(
+            &lib_rs_path,
+            self.with_source_line_endings(PANIC_MODULE_DECLARATION)
+                .as_bytes(),
+        )?;
         Ok(())
     }
+
+    /// Scaffolds a `.devcontainer` folder with a `devcontainer.json` and a
+    /// `Dockerfile` of a Windows container with the Rust toolchain, the
+    /// targets of both supported architectures and LLVM. The WDK is not part
+    /// of the image, `devcontainer.json` mounts an eWDK from the host instead.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), NewActionError>` - A result indicating success or failure
+    ///   of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * `NewActionError::TemplateNotFound` - If a devcontainer template file
+    ///   is not bundled with the utility.
+    /// * `NewActionError::FileSystem` - If there is an error creating the
+    ///   `.devcontainer` folder or writing its files.
+    pub fn scaffold_devcontainer(&self) -> Result<(), NewActionError> {
+        debug!("Scaffolding .devcontainer folder");
+        let driver_crate_name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let devcontainer_dir = self.path.join(".devcontainer");
+        self.fs.create_dir_all(&devcontainer_dir)?;
+        for file_name in DEVCONTAINER_TEMPLATES {
+            let template_path = PathBuf::from("devcontainer").join(format!("{file_name}.tmp"));
+            let template_file = TEMPLATES_DIR.get_file(&template_path).ok_or_else(|| {
+                NewActionError::TemplateNotFound(template_path.to_string_lossy().into_owned())
+            })?;
+            let content = String::from_utf8_lossy(template_file.contents())
+                .replace(DRIVER_NAME_PLACEHOLDER, &driver_crate_name);
+            self.fs.write_to_file(
+                &devcontainer_dir.join(file_name),
+                self.with_source_line_endings(&content).as_bytes(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Scaffolds integration tests in the `tests` folder that install the
+    /// driver package built by `cargo wdk build` with `pnputil` and delete it
+    /// again, along with a `README.md` describing how to run them on a test
+    /// machine. The tests are ignored by default since they require
+    /// administrator rights.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), NewActionError>` - A result indicating success or failure
+    ///   of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * `NewActionError::TemplateNotFound` - If an integration test template
+    ///   file is not bundled with the utility.
+    /// * `NewActionError::FileSystem` - If there is an error creating the
+    ///   `tests` folder or writing its files.
+    pub fn scaffold_integration_tests(&self) -> Result<(), NewActionError> {
+        debug!("Scaffolding integration tests");
+        let underscored_driver_crate_name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().replace('-', "_"))
+            .unwrap_or_default();
+        let tests_dir = self.path.join("tests");
+        self.fs.create_dir_all(&tests_dir.join("common"))?;
+        for (sub_dir, file_name) in INTEGRATION_TEST_TEMPLATES {
+            let (template_dir, dest_dir) = match sub_dir {
+                Some(sub_dir) => (
+                    PathBuf::from("integration-tests").join(sub_dir),
+                    tests_dir.join(sub_dir),
+                ),
+                None => (PathBuf::from("integration-tests"), tests_dir.clone()),
+            };
+            let template_path = template_dir.join(format!("{file_name}.tmp"));
+            let template_file = TEMPLATES_DIR.get_file(&template_path).ok_or_else(|| {
+                NewActionError::TemplateNotFound(template_path.to_string_lossy().into_owned())
+            })?;
+            let content = String::from_utf8_lossy(template_file.contents())
+                .replace(DRIVER_NAME_PLACEHOLDER, &underscored_driver_crate_name);
+            self.fs.write_to_file(
+                &dest_dir.join(file_name),
+                self.with_source_line_endings(&content).as_bytes(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Adds the `tracing` dependency to the `[dependencies]` section of the
+    /// driver project's `Cargo.toml`.
+    fn add_tracing_dependency(&self) -> Result<(), NewActionError> {
+        debug!("Adding tracing dependency to Cargo.toml");
+        let cargo_toml_path = self.path.join("Cargo.toml");
+        // `Cargo.toml` is already written with the selected line endings
+        let cargo_toml_content = self
+            .fs
+            .read_file_to_string(&cargo_toml_path)?
+            .replace("\r\n", "\n");
+        let cargo_toml_content = if cargo_toml_content.contains("[dependencies]\n") {
+            cargo_toml_content.replacen(
+                "[dependencies]\n",
+                &format!("[dependencies]\n{TRACING_DEPENDENCY}"),
+                1,
+            )
+        } else {
+            format!("{cargo_toml_content}\n[dependencies]\n{TRACING_DEPENDENCY}")
+        };
+        self.fs.write_to_file(
+            &cargo_toml_path,
+            self.with_source_line_endings(&cargo_toml_content).as_bytes(),
+        )?;
+        Ok(())
+    }
+
+    /// Returns the content of a scaffolded source or configuration file with
+    /// the line endings selected for them
+    fn with_source_line_endings(&self, content: &str) -> String {
+        with_line_endings(content, self.line_endings.source_line_ending())
+    }
+}
+
+/// Replaces the line endings of the content, LF or CRLF, with the given line
+/// ending
+fn with_line_endings(content: &str, line_ending: &str) -> String {
+    content.replace("\r\n", "\n").replace('\n', line_ending)
+}
+
+/// Removes the `extern crate wdk_panic;` item, along with its `cfg` attribute
+/// and the blank line following it, from the content of a `lib.rs` template
+fn without_wdk_panic_crate(lib_rs_content: &str) -> String {
+    let lines: Vec<&str> = lib_rs_content.split_inclusive('\n').collect();
+    let Some(index) = lines
+        .iter()
+        .position(|line| line.trim() == WDK_PANIC_EXTERN_CRATE)
+    else {
+        return lib_rs_content.to_string();
+    };
+    let start = if index > 0 && lines[index - 1].trim().starts_with("#[cfg(") {
+        index - 1
+    } else {
+        index
+    };
+    let end = if lines
+        .get(index + 1)
+        .is_some_and(|line| line.trim().is_empty())
+    {
+        index + 2
+    } else {
+        index + 1
+    };
+    lines[..start].concat() + &lines[end..].concat()
+}
+
+/// Converts a semver version to the four part version of an INF `DriverVer`,
+/// e.g. `1.2.3-beta.1` to `1.2.3.0`. Pre-release and build metadata are
+/// dropped.
+///
+/// # Errors
+///
+/// * `NewActionError::UnsupportedDriverVersion` - If the major, minor or patch
+///   version does not fit in the 16 bits of a `DriverVer` version part.
+fn driver_version(version: &Version) -> Result<String, NewActionError> {
+    let parts = [version.major, version.minor, version.patch];
+    if parts.iter().any(|part| *part > u64::from(u16::MAX)) {
+        return Err(NewActionError::UnsupportedDriverVersion(
+            version.to_string(),
+        ));
+    }
+    Ok(format!(
+        "{}.{}.{}.0",
+        version.major, version.minor, version.patch
+    ))
+}
+
+/// Replaces the value of the first `version` key, i.e. the one of the
+/// `[package]` table `cargo new` generates, with the given version
+fn with_package_version(cargo_toml_content: &str, version: &Version) -> String {
+    let mut replaced = false;
+    cargo_toml_content
+        .split_inclusive('\n')
+        .map(|line| {
+            if !replaced
+                && line
+                    .split_once('=')
+                    .is_some_and(|(key, _)| key.trim() == "version")
+            {
+                replaced = true;
+                let indent = &line[..line.len() - line.trim_start().len()];
+                let line_ending = &line[line.trim_end_matches(['\r', '\n']).len()..];
+                format!("{indent}version = \"{version}\"{line_ending}")
+            } else {
+                line.to_string()
+            }
+        })
+        .collect()
+}
+
+/// Replaces the value of the `DriverVer` entry of an INF with the placeholder
+/// date and the given driver version
+fn with_driver_ver(inx_content: &str, driver_version: &str) -> String {
+    inx_content
+        .split_inclusive('\n')
+        .map(|line| match line.split_once('=') {
+            Some((key, _)) if key.trim().eq_ignore_ascii_case("DriverVer") => {
+                format!(
+                    "{key}= {DRIVER_VER_PLACEHOLDER_DATE},{driver_version}{}",
+                    line_ending(line)
+                )
+            }
+            _ => line.to_string(),
+        })
+        .collect()
+}
+
+/// Expands the `NT$ARCH$` decorations of an INF into one decoration and one
+/// models section per given architecture. If more than one architecture is
+/// given, the `[SourceDisksFiles]` section is also expanded into one section
+/// per architecture pointing to the architecture sub folder of a
+/// multi-architecture package.
+fn with_arch_decorations(inx_content: &str, archs: &[CpuArchitecture]) -> String {
+    let mut sections: Vec<Vec<&str>> = vec![Vec::new()];
+    for line in inx_content.split_inclusive('\n') {
+        if line.trim_start().starts_with('[') {
+            sections.push(Vec::new());
+        }
+        sections
+            .last_mut()
+            .expect("sections is never empty")
+            .push(line);
+    }
+
+    let mut decorated_inx_content = String::with_capacity(inx_content.len());
+    for lines in sections {
+        let section_name = lines
+            .first()
+            .and_then(|line| line.trim().strip_prefix('['))
+            .and_then(|line| line.split_once(']'))
+            .map(|(name, _)| name.trim())
+            .unwrap_or_default();
+        if section_name.contains(INF_ARCH_PLACEHOLDER) {
+            for arch in archs {
+                for line in &lines {
+                    decorated_inx_content
+                        .push_str(&line.replace(INF_ARCH_PLACEHOLDER, &arch.to_string()));
+                }
+            }
+        } else if section_name.eq_ignore_ascii_case(INF_MANUFACTURER_SECTION) {
+            for line in &lines {
+                decorated_inx_content.push_str(&with_expanded_decoration(line, archs));
+            }
+        } else if section_name.eq_ignore_ascii_case(INF_SOURCE_DISKS_FILES_SECTION)
+            && archs.len() > 1
+        {
+            for arch in archs {
+                decorated_inx_content.push_str(&format!(
+                    "[{INF_SOURCE_DISKS_FILES_SECTION}.{arch}]{}",
+                    line_ending(lines[0])
+                ));
+                for line in &lines[1..] {
+                    decorated_inx_content.push_str(&with_source_disks_subdir(line, *arch));
+                }
+            }
+        } else {
+            decorated_inx_content.extend(lines);
+        }
+    }
+    decorated_inx_content
+}
+
+/// Replaces the `NT$ARCH$` decoration of a `[Manufacturer]` entry with one
+/// decoration per given architecture
+fn with_expanded_decoration(line: &str, archs: &[CpuArchitecture]) -> String {
+    let Some(start) = line
+        .find(INF_ARCH_PLACEHOLDER)
+        .and_then(|placeholder_start| {
+            line[..placeholder_start]
+                .rfind(|c: char| c == ',' || c == '=' || c.is_whitespace())
+                .map(|separator| separator + 1)
+        })
+    else {
+        return line.to_string();
+    };
+    let end = line[start..]
+        .find(|c: char| c == ',' || c == ';' || c.is_whitespace())
+        .map_or(line.len(), |length| start + length);
+    let decoration = &line[start..end];
+    let expanded_decorations = archs
+        .iter()
+        .map(|arch| decoration.replace(INF_ARCH_PLACEHOLDER, &arch.to_string()))
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("{}{expanded_decorations}{}", &line[..start], &line[end..])
+}
+
+/// Sets the sub directory of a `<file name> = <disk id>[,<sub dir>]` entry of
+/// the `[SourceDisksFiles]` section to the architecture sub folder
+fn with_source_disks_subdir(line: &str, arch: CpuArchitecture) -> String {
+    let content = line.trim_end_matches(['\r', '\n']);
+    let Some((file_name, location)) = content
+        .split_once('=')
+        .filter(|_| !content.trim_start().starts_with(';'))
+    else {
+        return line.to_string();
+    };
+    let arch = arch.to_string();
+    let mut fields = location.split(',').collect::<Vec<_>>();
+    match fields.get_mut(1) {
+        Some(subdir) => *subdir = &arch,
+        None => fields.push(&arch),
+    }
+    format!("{file_name}={}{}", fields.join(","), line_ending(line))
+}
+
+/// Returns the line ending, if any, of a line split off INF content
+fn line_ending(line: &str) -> &str {
+    &line[line.trim_end_matches(['\r', '\n']).len()..]
 }
 
 #[cfg(test)]
@@ -303,11 +960,26 @@ mod tests {
     };
 
     use clap_verbosity_flag::Verbosity;
+    use wdk_build::CpuArchitecture;
 
     use crate::{
         actions::{
             DriverType,
-            new::{NewAction, NewActionError},
+            new::{
+                InfEncoding,
+                LineEndings,
+                Logging,
+                NewAction,
+                NewActionError,
+                NewActionParams,
+                Panic,
+                driver_version,
+                with_arch_decorations,
+                with_driver_ver,
+                with_line_endings,
+                with_package_version,
+                without_wdk_panic_crate,
+            },
         },
         providers::{
             error::{CommandError, FileError},
@@ -536,6 +1208,362 @@ fn when_copy_cargo_config_fails_then_run_returns_filesystem_error() {
         );
     }
 
+    #[test]
+    fn new_project_with_logging_scaffolds_logging_module() {
+        let cases = [
+            (Logging::Wpp, "wpp", "WPP_CONTROL_GUID"),
+            (Logging::Tracing, "logging", "set_global_default"),
+        ];
+
+        for (logging, module_name, expected_module_content) in cases {
+            let path = Path::new("test_driver_logging");
+            let mut test_setup = TestSetup::new(path).set_expectations_with(None, None);
+
+            let module_path = path.join("src").join(format!("{module_name}.rs"));
+            test_setup
+                .mock_fs
+                .expect_write_to_file()
+                .withf(move |path, content| {
+                    path == module_path
+                        && String::from_utf8_lossy(content).contains(expected_module_content)
+                })
+                .once()
+                .returning(|_, _| Ok(()));
+
+            let lib_rs_path = path.join("src").join("lib.rs");
+            let expected_mod_declaration = format!("mod {module_name};");
+            test_setup
+                .mock_fs
+                .expect_append_to_file()
+                .withf(move |path, content| {
+                    path == lib_rs_path
+                        && String::from_utf8_lossy(content).contains(&expected_mod_declaration)
+                })
+                .once()
+                .returning(|_, _| Ok(()));
+
+            let result = NewAction::new(
+                &NewActionParams {
+                    path,
+                    driver_type: DriverType::Kmdf,
+                    logging,
+                    panic: Panic::Abort,
+                    version: None,
+                    with_devcontainer: false,
+                    with_integration_tests: false,
+                    target_archs: &[],
+                    line_endings: LineEndings::default(),
+                    inf_encoding: InfEncoding::default(),
+                    verbosity_level: Verbosity::default(),
+                },
+                &test_setup.mock_exec,
+                &test_setup.mock_fs,
+            )
+            .run();
+
+            assert!(
+                result.is_ok(),
+                "Expected {logging:?} logging to be scaffolded"
+            );
+        }
+    }
+
+    #[test]
+    fn new_project_with_devcontainer_scaffolds_devcontainer_folder() {
+        let path = Path::new("test_driver_devcontainer");
+        let mut test_setup = TestSetup::new(path).set_expectations_with(None, None);
+
+        let devcontainer_dir = path.join(".devcontainer");
+        let expected_devcontainer_dir = devcontainer_dir.clone();
+        test_setup
+            .mock_fs
+            .expect_create_dir_all()
+            .withf(move |path| path == expected_devcontainer_dir)
+            .once()
+            .returning(|_| Ok(()));
+        for (file_name, expected_content) in [
+            (
+                "devcontainer.json",
+                "\"name\": \"test_driver_devcontainer\"",
+            ),
+            ("Dockerfile", "aarch64-pc-windows-msvc"),
+        ] {
+            let file_path = devcontainer_dir.join(file_name);
+            test_setup
+                .mock_fs
+                .expect_write_to_file()
+                .withf(move |path, content| {
+                    path == file_path && String::from_utf8_lossy(content).contains(expected_content)
+                })
+                .once()
+                .returning(|_, _| Ok(()));
+        }
+
+        let result = NewAction::new(
+            &NewActionParams {
+                path,
+                driver_type: DriverType::Kmdf,
+                logging: Logging::None,
+                panic: Panic::Abort,
+                version: None,
+                with_devcontainer: true,
+                with_integration_tests: false,
+                target_archs: &[],
+                line_endings: LineEndings::default(),
+                inf_encoding: InfEncoding::default(),
+                verbosity_level: Verbosity::default(),
+            },
+            &test_setup.mock_exec,
+            &test_setup.mock_fs,
+        )
+        .run();
+
+        assert!(result.is_ok(), "Expected .devcontainer to be scaffolded");
+    }
+
+    #[test]
+    fn new_project_with_integration_tests_scaffolds_tests_folder() {
+        let path = Path::new("test-driver-tests");
+        let mut test_setup = TestSetup::new(path).set_expectations_with(None, None);
+
+        let tests_dir = path.join("tests");
+        let expected_common_dir = tests_dir.join("common");
+        test_setup
+            .mock_fs
+            .expect_create_dir_all()
+            .withf(move |path| path == expected_common_dir)
+            .once()
+            .returning(|_| Ok(()));
+        for (file_path, expected_content) in [
+            (tests_dir.join("README.md"), "test_driver_tests_package"),
+            (tests_dir.join("driver_package.rs"), "#[ignore"),
+            (
+                tests_dir.join("common").join("mod.rs"),
+                "const DRIVER_NAME: &str = \"test_driver_tests\";",
+            ),
+        ] {
+            test_setup
+                .mock_fs
+                .expect_write_to_file()
+                .withf(move |path, content| {
+                    path == file_path && String::from_utf8_lossy(content).contains(expected_content)
+                })
+                .once()
+                .returning(|_, _| Ok(()));
+        }
+
+        let result = NewAction::new(
+            &NewActionParams {
+                path,
+                driver_type: DriverType::Kmdf,
+                logging: Logging::None,
+                panic: Panic::Abort,
+                version: None,
+                with_devcontainer: false,
+                with_integration_tests: true,
+                target_archs: &[],
+                line_endings: LineEndings::default(),
+                inf_encoding: InfEncoding::default(),
+                verbosity_level: Verbosity::default(),
+            },
+            &test_setup.mock_exec,
+            &test_setup.mock_fs,
+        )
+        .run();
+
+        assert!(result.is_ok(), "Expected integration tests to be scaffolded");
+    }
+
+    #[test]
+    fn new_project_with_custom_panic_scaffolds_panic_handler() {
+        let path = Path::new("test_driver_panic");
+        let mut test_setup = TestSetup::new(path).set_expectations_with(None, None);
+
+        let module_path = path.join("src").join("panic.rs");
+        test_setup
+            .mock_fs
+            .expect_write_to_file()
+            .withf(move |path, content| {
+                path == module_path && String::from_utf8_lossy(content).contains("#[panic_handler]")
+            })
+            .once()
+            .returning(|_, _| Ok(()));
+        let lib_rs_path = path.join("src").join("lib.rs");
+        test_setup
+            .mock_fs
+            .expect_append_to_file()
+            .withf(move |path, content| {
+                path == lib_rs_path && String::from_utf8_lossy(content).contains("mod panic;")
+            })
+            .once()
+            .returning(|_, _| Ok(()));
+
+        let result = NewAction::new(
+            &NewActionParams {
+                path,
+                driver_type: DriverType::Wdm,
+                logging: Logging::None,
+                panic: Panic::Custom,
+                version: None,
+                with_devcontainer: false,
+                with_integration_tests: false,
+                target_archs: &[],
+                line_endings: LineEndings::default(),
+                inf_encoding: InfEncoding::default(),
+                verbosity_level: Verbosity::default(),
+            },
+            &test_setup.mock_exec,
+            &test_setup.mock_fs,
+        )
+        .run();
+
+        assert!(result.is_ok(), "Expected panic handler to be scaffolded");
+    }
+
+    #[test]
+    fn custom_panic_is_rejected_for_umdf_drivers() {
+        let mock_exec = MockCommandExec::new();
+        let mock_fs = MockFs::new();
+        let result = NewAction::new(
+            &NewActionParams {
+                path: Path::new("test_driver"),
+                driver_type: DriverType::Umdf,
+                logging: Logging::None,
+                panic: Panic::Custom,
+                version: None,
+                with_devcontainer: false,
+                with_integration_tests: false,
+                target_archs: &[],
+                line_endings: LineEndings::default(),
+                inf_encoding: InfEncoding::default(),
+                verbosity_level: Verbosity::default(),
+            },
+            &mock_exec,
+            &mock_fs,
+        )
+        .run();
+
+        assert!(matches!(
+            result,
+            Err(NewActionError::CustomPanicHandlerNotSupported(
+                DriverType::Umdf
+            ))
+        ));
+    }
+
+    #[test]
+    fn wdk_panic_crate_is_removed_from_lib_rs() {
+        let lib_rs_content = "#![no_std]\r\n\r\n#[cfg(not(test))]\r\nextern crate \
+                              wdk_panic;\r\n\r\n#[cfg(not(test))]\r\nuse \
+                              wdk_alloc::WdkAllocator;\r\n";
+        assert_eq!(
+            without_wdk_panic_crate(lib_rs_content),
+            "#![no_std]\r\n\r\n#[cfg(not(test))]\r\nuse wdk_alloc::WdkAllocator;\r\n"
+        );
+        assert_eq!(without_wdk_panic_crate("#![no_std]\n"), "#![no_std]\n");
+    }
+
+    #[test]
+    fn pool_tag_is_derived_from_crate_name() {
+        let cases = [("my-driver", "mydr"), ("ab", "ab00"), ("a_b_c_d_e", "abcd")];
+        let mock_exec = MockCommandExec::new();
+        let mock_fs = MockFs::new();
+        for (crate_name, expected_pool_tag) in cases {
+            let path = Path::new("projects").join(crate_name);
+            let action = NewAction::new(
+                &NewActionParams {
+                    path: &path,
+                    driver_type: DriverType::Kmdf,
+                    logging: Logging::None,
+                    panic: Panic::Abort,
+                    version: None,
+                    with_devcontainer: false,
+                    with_integration_tests: false,
+                    target_archs: &[],
+                    line_endings: LineEndings::default(),
+                    inf_encoding: InfEncoding::default(),
+                    verbosity_level: Verbosity::default(),
+                },
+                &mock_exec,
+                &mock_fs,
+            );
+            assert_eq!(action.pool_tag(), expected_pool_tag);
+        }
+    }
+
+    #[test]
+    fn line_endings_of_lf_and_crlf_content_are_replaced() {
+        assert_eq!(with_line_endings("a\nb\r\nc\n", "\r\n"), "a\r\nb\r\nc\r\n");
+        assert_eq!(with_line_endings("a\nb\r\nc\n", "\n"), "a\nb\nc\n");
+    }
+
+    #[test]
+    fn inx_file_and_source_files_are_written_with_the_selected_line_endings_and_encoding() {
+        let cases = [
+            (LineEndings::Auto, InfEncoding::Utf8, "\n", "\r\n"),
+            (LineEndings::Lf, InfEncoding::Utf8Bom, "\n", "\n"),
+            (LineEndings::Crlf, InfEncoding::Utf8Bom, "\r\n", "\r\n"),
+        ];
+        let path = Path::new("test_driver");
+        let mock_exec = MockCommandExec::new();
+        for (line_endings, inf_encoding, source_line_ending, inx_line_ending) in cases {
+            let mut mock_fs = MockFs::new();
+            let inx_path = path.join("test_driver.inx");
+            let has_bom = inf_encoding == InfEncoding::Utf8Bom;
+            mock_fs
+                .expect_write_to_file()
+                .withf(move |path, content| {
+                    let content = String::from_utf8_lossy(content);
+                    path == inx_path
+                        && content.starts_with('\u{feff}') == has_bom
+                        && has_only_line_ending(&content, inx_line_ending)
+                })
+                .once()
+                .returning(|_, _| Ok(()));
+            let lib_rs_path = path.join("src").join("lib.rs");
+            mock_fs
+                .expect_write_to_file()
+                .withf(move |path, content| {
+                    let content = String::from_utf8_lossy(content);
+                    path == lib_rs_path && has_only_line_ending(&content, source_line_ending)
+                })
+                .once()
+                .returning(|_, _| Ok(()));
+
+            let action = NewAction::new(
+                &NewActionParams {
+                    path,
+                    driver_type: DriverType::Kmdf,
+                    logging: Logging::None,
+                    panic: Panic::Abort,
+                    version: None,
+                    with_devcontainer: false,
+                    with_integration_tests: false,
+                    target_archs: &[],
+                    line_endings,
+                    inf_encoding,
+                    verbosity_level: Verbosity::default(),
+                },
+                &mock_exec,
+                &mock_fs,
+            );
+            assert!(action.create_inx_file(None).is_ok(), "{line_endings:?}");
+            assert!(action.copy_lib_rs_template().is_ok(), "{line_endings:?}");
+        }
+    }
+
+    /// Returns whether every line of the content ends with the given line
+    /// ending
+    fn has_only_line_ending(content: &str, line_ending: &str) -> bool {
+        let crlf_count = content.matches("\r\n").count();
+        content.contains('\n')
+            && if line_ending == "\r\n" {
+                crlf_count == content.matches('\n').count()
+            } else {
+                crlf_count == 0
+            }
+    }
+
     /// Helper function to set up mock expectations and assert on the result.
     ///
     /// This function takes a closure to configure the test setup (e.g., mock
@@ -554,9 +1582,19 @@ fn set_up_and_assert(
         let test_setup = set_expectations_fn(test_setup);
 
         let result = NewAction::new(
-            path,
-            driver_type,
-            verbosity_level,
+            &NewActionParams {
+                path,
+                driver_type,
+                logging: Logging::None,
+                panic: Panic::Abort,
+                version: None,
+                with_devcontainer: false,
+                with_integration_tests: false,
+                target_archs: &[],
+                line_endings: LineEndings::default(),
+                inf_encoding: InfEncoding::default(),
+                verbosity_level,
+            },
             &test_setup.mock_exec,
             &test_setup.mock_fs,
         )
@@ -852,4 +1890,65 @@ fn expect_copy_cargo_config(mut self, is_copy_success: bool) -> Self {
             self
         }
     }
+
+    #[test]
+    fn initial_version_is_written_to_cargo_toml_and_driver_ver() {
+        let version = "1.2.3-beta.1".parse().expect("version should be valid");
+        let driver_version = driver_version(&version).expect("version should fit in DriverVer");
+        assert_eq!(driver_version, "1.2.3.0");
+
+        assert_eq!(
+            with_package_version(
+                "[package]\nname = \"driver\"\nversion = \"0.1.0\"\nedition = \"2024\"\n",
+                &version
+            ),
+            "[package]\nname = \"driver\"\nversion = \"1.2.3-beta.1\"\nedition = \"2024\"\n"
+        );
+        assert_eq!(
+            with_driver_ver(
+                "[Version]\r\nDriverVer   = ; TODO: set DriverVer\r\nPnpLockdown = 1\r\n",
+                &driver_version
+            ),
+            "[Version]\r\nDriverVer   = 01/01/1970,1.2.3.0\r\nPnpLockdown = 1\r\n"
+        );
+    }
+
+    #[test]
+    fn version_parts_exceeding_driver_ver_limits_are_rejected() {
+        let version = "1.65536.0".parse().expect("version should be valid");
+        assert!(matches!(
+            driver_version(&version),
+            Err(NewActionError::UnsupportedDriverVersion(_))
+        ));
+    }
+
+    #[test]
+    fn arch_decorations_are_expanded_for_every_target_arch() {
+        let inx_content = "[SourceDisksFiles]\ndriver.sys  = \
+                           1,,\n\n[Manufacturer]\n%ManufacturerName% = \
+                           Standard,NT$ARCH$.10.0...16299 ; build \
+                           16299\n\n[Standard.NT$ARCH$.10.0...16299]\n%DeviceDesc% = \
+                           driver_Device, Root\\driver\n\n[Strings]\n";
+
+        assert_eq!(
+            with_arch_decorations(inx_content, &[CpuArchitecture::Arm64]),
+            "[SourceDisksFiles]\ndriver.sys  = 1,,\n\n[Manufacturer]\n%ManufacturerName% = \
+             Standard,NTarm64.10.0...16299 ; build \
+             16299\n\n[Standard.NTarm64.10.0...16299]\n%DeviceDesc% = driver_Device, \
+             Root\\driver\n\n[Strings]\n"
+        );
+        assert_eq!(
+            with_arch_decorations(
+                inx_content,
+                &[CpuArchitecture::Amd64, CpuArchitecture::Arm64]
+            ),
+            "[SourceDisksFiles.amd64]\ndriver.sys  = \
+             1,amd64,\n\n[SourceDisksFiles.arm64]\ndriver.sys  = \
+             1,arm64,\n\n[Manufacturer]\n%ManufacturerName% = \
+             Standard,NTamd64.10.0...16299,NTarm64.10.0...16299 ; build \
+             16299\n\n[Standard.NTamd64.10.0...16299]\n%DeviceDesc% = driver_Device, \
+             Root\\driver\n\n[Standard.NTarm64.10.0...16299]\n%DeviceDesc% = driver_Device, \
+             Root\\driver\n\n[Strings]\n"
+        );
+    }
 }
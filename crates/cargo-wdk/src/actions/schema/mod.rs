@@ -0,0 +1,172 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module contains the `SchemaAction` struct and its associated methods
+//! for printing a JSON schema of the `Cargo.toml` metadata read by cargo-wdk,
+//! i.e. the `wdk` table that `wdk-build` deserializes and the `cargo-wdk`
+//! table of the packaging settings, so that editors can validate and complete
+//! it.
+
+use serde_json::{Value, json};
+
+use crate::actions::build::PackageSettings;
+
+/// Version of the JSON schema specification the schema conforms to
+const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Action that prints the JSON schema of the driver metadata to stdout
+pub struct SchemaAction;
+
+impl SchemaAction {
+    /// Entry point method to execute the schema action flow. Only the schema
+    /// is printed to stdout, so that it can be redirected to a file.
+    pub fn run() {
+        println!("{:#}", Self::schema());
+    }
+
+    /// Returns the JSON schema of a `Cargo.toml` manifest, describing the
+    /// `wdk` and `cargo-wdk` tables of `[package.metadata]` and the `wdk`
+    /// table of `[workspace.metadata]`. Other keys of the manifest are not
+    /// constrained.
+    #[must_use]
+    pub fn schema() -> Value {
+        let wdk = wdk_metadata_schema();
+        json!({
+            "$schema": JSON_SCHEMA_DIALECT,
+            "title": "cargo-wdk driver metadata",
+            "type": "object",
+            "properties": {
+                "package": {
+                    "type": "object",
+                    "properties": {
+                        "metadata": {
+                            "type": "object",
+                            "properties": {
+                                "wdk": wdk,
+                                "cargo-wdk": PackageSettings::json_schema()
+                            }
+                        }
+                    }
+                },
+                "workspace": {
+                    "type": "object",
+                    "properties": {
+                        "metadata": {
+                            "type": "object",
+                            "properties": { "wdk": wdk }
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Returns the JSON schema of the `wdk` metadata table, mirroring the
+/// kebab-case field names `wdk_build::metadata::Wdk` and
+/// `wdk_build::DriverConfig` are deserialized from. The driver model is
+/// tagged by its `driver-type`.
+fn wdk_metadata_schema() -> Value {
+    let version = json!({ "type": "integer", "minimum": 0, "maximum": u8::MAX });
+    let framework_config = |driver_type: &str, framework: &str| {
+        json!({
+            "type": "object",
+            "properties": {
+                "driver-type": { "const": driver_type },
+                format!("{framework}-version-major"): version,
+                format!("target-{framework}-version-minor"): version,
+                format!("minimum-{framework}-version-minor"): version
+            },
+            "required": [
+                "driver-type",
+                format!("{framework}-version-major"),
+                format!("target-{framework}-version-minor")
+            ],
+            "additionalProperties": false
+        })
+    };
+    json!({
+        "type": "object",
+        "properties": {
+            "driver-model": {
+                "oneOf": [
+                    {
+                        "type": "object",
+                        "properties": { "driver-type": { "const": "WDM" } },
+                        "required": ["driver-type"],
+                        "additionalProperties": false
+                    },
+                    framework_config("KMDF", "kmdf"),
+                    framework_config("UMDF", "umdf")
+                ]
+            }
+        },
+        "required": ["driver-model"],
+        "additionalProperties": false
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+    use wdk_build::{DriverConfig, KmdfConfig, UmdfConfig, metadata::Wdk};
+
+    use super::{SchemaAction, wdk_metadata_schema};
+
+    /// Returns a `wdk` table with every property of the given driver model
+    /// schema set, using `1` for the versions
+    fn wdk_table_from_schema(driver_model_schema: &Value) -> Value {
+        let driver_model = driver_model_schema["properties"]
+            .as_object()
+            .expect("driver model schema should have properties")
+            .iter()
+            .map(|(key, property)| {
+                let value = property.get("const").cloned().unwrap_or_else(|| json!(1));
+                (key.clone(), value)
+            })
+            .collect::<serde_json::Map<_, _>>();
+        json!({ "driver-model": driver_model })
+    }
+
+    #[test]
+    fn wdk_metadata_schema_matches_the_deserialized_driver_models() {
+        let schema = wdk_metadata_schema();
+        let driver_models = schema["properties"]["driver-model"]["oneOf"]
+            .as_array()
+            .expect("driver model schema should list the driver types")
+            .iter()
+            .map(|driver_model_schema| {
+                serde_json::from_value::<Wdk>(wdk_table_from_schema(driver_model_schema))
+                    .expect("table built from the schema should deserialize")
+                    .driver_model
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            driver_models,
+            [
+                DriverConfig::Wdm,
+                DriverConfig::Kmdf(KmdfConfig {
+                    kmdf_version_major: 1,
+                    target_kmdf_version_minor: 1,
+                    minimum_kmdf_version_minor: Some(1),
+                }),
+                DriverConfig::Umdf(UmdfConfig {
+                    umdf_version_major: 1,
+                    target_umdf_version_minor: 1,
+                    minimum_umdf_version_minor: Some(1),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn schema_describes_the_package_and_workspace_metadata() {
+        let schema = SchemaAction::schema();
+        let package_metadata = &schema["properties"]["package"]["properties"]["metadata"];
+        assert_eq!(package_metadata["properties"]["wdk"], wdk_metadata_schema());
+        assert!(package_metadata["properties"]["cargo-wdk"]["properties"]["sign-mode"].is_object());
+        assert_eq!(
+            schema["properties"]["workspace"]["properties"]["metadata"]["properties"]["wdk"],
+            wdk_metadata_schema()
+        );
+    }
+}
@@ -0,0 +1,25 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module defines error types for the setup action module.
+
+use thiserror::Error;
+
+use crate::providers::error::CommandError;
+
+/// Errors for the setup action layer
+#[derive(Error, Debug)]
+pub enum SetupActionError {
+    #[error(
+        "Error listing the installed targets using rustup. Install rustup from https://rustup.rs \
+         to build drivers"
+    )]
+    ListTargetsCommand(#[source] CommandError),
+    #[error("Error adding the {0} target using rustup")]
+    AddTargetCommand(String, #[source] CommandError),
+    #[error(
+        "Prerequisites that cannot be installed by cargo-wdk are missing: {}. See the warnings \
+         above for how to install them",
+        .0.join(", ")
+    )]
+    MissingPrerequisites(Vec<&'static str>),
+}
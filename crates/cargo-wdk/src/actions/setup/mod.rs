@@ -0,0 +1,365 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module contains the `SetupAction` struct and its associated methods
+//! for preparing a machine to build drivers. The Rust targets of the drivers
+//! are installed with `rustup`, while the prerequisites that cannot be
+//! installed by cargo-wdk, e.g. the WDK itself, are reported with where to get
+//! them.
+mod error;
+
+use std::path::PathBuf;
+
+use error::SetupActionError;
+use mockall_double::double;
+use tracing::{debug, info, warn};
+
+use crate::actions::{AARCH64_TARGET_TRIPLE_NAME, X86_64_TARGET_TRIPLE_NAME};
+#[double]
+use crate::providers::{exec::CommandExec, wdk_build::WdkBuild};
+
+/// Targets of the drivers installed for the active toolchain
+const REQUIRED_TARGETS: [&str; 2] = [X86_64_TARGET_TRIPLE_NAME, AARCH64_TARGET_TRIPLE_NAME];
+/// Suffix of the host triples of the MSVC toolchains
+const MSVC_HOST_SUFFIX: &str = "-pc-windows-msvc";
+/// Visual Studio component containing the MSVC compiler and linker
+const MSVC_COMPONENT: &str = "Microsoft.VisualStudio.Component.VC.Tools.x86.x64";
+/// Download page of the Visual Studio Build Tools
+const VISUAL_STUDIO_DOWNLOAD_URL: &str = "https://visualstudio.microsoft.com/downloads/";
+/// Download page of the WDK
+const WDK_DOWNLOAD_URL: &str =
+    "https://learn.microsoft.com/en-us/windows-hardware/drivers/download-the-wdk";
+/// Command installing the version of LLVM whose `libclang` generates the
+/// bindings of `wdk-sys`
+const LLVM_INSTALL_COMMAND: &str = "winget install -i LLVM.LLVM --version 17.0.6 --force";
+
+/// Action that installs the Rust targets of the drivers and checks the other
+/// prerequisites of building drivers
+pub struct SetupAction<'a> {
+    // Injected deps
+    command_exec: &'a CommandExec,
+    wdk_build: &'a WdkBuild,
+}
+
+impl<'a> SetupAction<'a> {
+    /// Creates a new instance of `SetupAction`.
+    ///
+    /// # Arguments
+    /// * `command_exec` - The command execution provider instance
+    /// * `wdk_build` - The WDK build provider instance
+    pub const fn new(command_exec: &'a CommandExec, wdk_build: &'a WdkBuild) -> Self {
+        Self {
+            command_exec,
+            wdk_build,
+        }
+    }
+
+    /// Entry point method to execute the setup action flow. Only the targets
+    /// that are not installed yet are added, so the action can be re-run
+    /// safely, e.g. after installing a missing prerequisite.
+    ///
+    /// # Returns
+    /// `Result<(), SetupActionError>`
+    ///
+    /// # Errors
+    /// * `SetupActionError::ListTargetsCommand` - If the installed targets
+    ///   cannot be listed using rustup.
+    /// * `SetupActionError::AddTargetCommand` - If there is an error adding a
+    ///   target using rustup.
+    /// * `SetupActionError::MissingPrerequisites` - If any of the
+    ///   prerequisites that cannot be installed by cargo-wdk is missing.
+    pub fn run(&self) -> Result<(), SetupActionError> {
+        self.add_missing_targets()?;
+
+        let missing_prerequisites: Vec<&'static str> = [
+            self.check_msvc_host_toolchain(),
+            self.check_msvc_build_tools(),
+            self.check_wdk(),
+            self.check_llvm(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if !missing_prerequisites.is_empty() {
+            return Err(SetupActionError::MissingPrerequisites(missing_prerequisites));
+        }
+        info!("The machine is set up to build drivers");
+        Ok(())
+    }
+
+    /// Adds the targets of the drivers that are not installed for the active
+    /// toolchain using `rustup`
+    fn add_missing_targets(&self) -> Result<(), SetupActionError> {
+        let output = self
+            .command_exec
+            .run("rustup", &["target", "list", "--installed"], None, None)
+            .map_err(SetupActionError::ListTargetsCommand)?;
+        let installed_targets = String::from_utf8_lossy(&output.stdout);
+        for target in REQUIRED_TARGETS {
+            if installed_targets.lines().any(|line| line.trim() == target) {
+                info!("Target {target} is installed");
+                continue;
+            }
+            debug!("Adding target {target}");
+            self.command_exec
+                .run("rustup", &["target", "add", target], None, None)
+                .map_err(|e| SetupActionError::AddTargetCommand(target.to_string(), e))?;
+            info!("Added target {target}");
+        }
+        Ok(())
+    }
+
+    /// Checks that the host of the active toolchain is an MSVC one, since the
+    /// drivers are linked with the MSVC linker. Returns the name of the
+    /// prerequisite if it is missing.
+    fn check_msvc_host_toolchain(&self) -> Option<&'static str> {
+        let host = self
+            .command_exec
+            .run("rustc", &["-vV"], None, None)
+            .ok()
+            .and_then(|output| {
+                String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .find_map(|line| Some(line.strip_prefix("host:")?.trim().to_string()))
+            });
+        match host {
+            Some(host) if host.ends_with(MSVC_HOST_SUFFIX) => {
+                info!("Toolchain host {host} is an MSVC host");
+                None
+            }
+            host => {
+                warn!(
+                    "The host of the active toolchain is {}, not an MSVC host. Switch to an MSVC \
+                     toolchain, e.g. with `rustup default stable-msvc`",
+                    host.as_deref().unwrap_or("unknown")
+                );
+                Some("MSVC toolchain")
+            }
+        }
+    }
+
+    /// Checks that Visual Studio or its Build Tools are installed with the
+    /// MSVC compiler and linker using `vswhere`, which ships with the Visual
+    /// Studio installer. Returns the name of the prerequisite if it is missing.
+    fn check_msvc_build_tools(&self) -> Option<&'static str> {
+        let vswhere_path = vswhere_path();
+        let installation_path = self
+            .command_exec
+            .run(
+                &vswhere_path.to_string_lossy(),
+                &[
+                    "-latest",
+                    "-products",
+                    "*",
+                    "-requires",
+                    MSVC_COMPONENT,
+                    "-property",
+                    "installationPath",
+                ],
+                None,
+                None,
+            )
+            .ok()
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .filter(|installation_path| !installation_path.is_empty());
+        if let Some(installation_path) = installation_path {
+            info!("MSVC build tools found in {installation_path}");
+            return None;
+        }
+        warn!(
+            "The MSVC build tools are not installed. Install the \"Desktop development with C++\" \
+             workload of Visual Studio or its Build Tools from {VISUAL_STUDIO_DOWNLOAD_URL}"
+        );
+        Some("MSVC build tools")
+    }
+
+    /// Checks that the WDK is found the same way `wdk-build` finds it.
+    /// Returns the name of the prerequisite if it is missing.
+    fn check_wdk(&self) -> Option<&'static str> {
+        match self.wdk_build.detect_wdk_content_root() {
+            Ok(wdk_content_root) => {
+                info!("WDK found in {}", wdk_content_root.display());
+                None
+            }
+            Err(e) => {
+                warn!(
+                    "The WDK is not found: {e}. Install it from {WDK_DOWNLOAD_URL}, or run \
+                     cargo-wdk from an eWDK developer prompt"
+                );
+                Some("WDK")
+            }
+        }
+    }
+
+    /// Checks that LLVM, whose `libclang` generates the bindings of
+    /// `wdk-sys`, is on the `PATH`. Returns the name of the prerequisite if it
+    /// is missing.
+    fn check_llvm(&self) -> Option<&'static str> {
+        if self
+            .command_exec
+            .run("clang", &["--version"], None, None)
+            .is_ok()
+        {
+            info!("LLVM found");
+            return None;
+        }
+        warn!(
+            "LLVM is not found on the PATH. Install it with `{LLVM_INSTALL_COMMAND}` and select \
+             the option to add LLVM to the PATH"
+        );
+        Some("LLVM")
+    }
+}
+
+/// Returns the path of `vswhere`, which the Visual Studio installer places at
+/// a fixed location regardless of where Visual Studio is installed
+fn vswhere_path() -> PathBuf {
+    std::env::var_os("ProgramFiles(x86)")
+        .map_or_else(|| PathBuf::from(r"C:\Program Files (x86)"), PathBuf::from)
+        .join(r"Microsoft Visual Studio\Installer\vswhere.exe")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        os::windows::process::ExitStatusExt,
+        path::PathBuf,
+        process::{ExitStatus, Output},
+    };
+
+    use mockall::Sequence;
+    use mockall_double::double;
+
+    use super::{SetupAction, error::SetupActionError};
+    use crate::providers::error::CommandError;
+    #[double]
+    use crate::providers::{exec::CommandExec, wdk_build::WdkBuild};
+
+    fn output(stdout: &str) -> Output {
+        Output {
+            status: ExitStatus::from_raw(0),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: Vec::new(),
+        }
+    }
+
+    fn mock_installed_targets(command_exec: &mut CommandExec, installed_targets: &'static str) {
+        command_exec
+            .expect_run()
+            .withf(|cmd: &str, args: &[&str], _, _| {
+                cmd == "rustup" && args == ["target", "list", "--installed"]
+            })
+            .once()
+            .returning(move |_, _, _, _| Ok(output(installed_targets)));
+    }
+
+    fn mock_prerequisite_checks(
+        command_exec: &mut CommandExec,
+        wdk_build: &mut WdkBuild,
+        are_installed: bool,
+    ) {
+        command_exec
+            .expect_run()
+            .withf(|cmd: &str, args: &[&str], _, _| cmd == "rustc" && args == ["-vV"])
+            .once()
+            .returning(move |_, _, _, _| {
+                Ok(output(if are_installed {
+                    "rustc 1.85.0\nhost: x86_64-pc-windows-msvc\n"
+                } else {
+                    "rustc 1.85.0\nhost: x86_64-pc-windows-gnu\n"
+                }))
+            });
+        command_exec
+            .expect_run()
+            .withf(|cmd: &str, _, _, _| cmd.ends_with("vswhere.exe"))
+            .once()
+            .returning(move |_, _, _, _| {
+                Ok(output(if are_installed {
+                    "C:\\Program Files\\Microsoft Visual Studio\\2022\\BuildTools\r\n"
+                } else {
+                    ""
+                }))
+            });
+        wdk_build
+            .expect_detect_wdk_content_root()
+            .once()
+            .returning(move || {
+                if are_installed {
+                    Ok(PathBuf::from(r"C:\Program Files (x86)\Windows Kits\10"))
+                } else {
+                    Err(wdk_build::ConfigError::WdkContentRootDetectionError)
+                }
+            });
+        command_exec
+            .expect_run()
+            .withf(|cmd: &str, args: &[&str], _, _| cmd == "clang" && args == ["--version"])
+            .once()
+            .returning(move |cmd, args, _, _| {
+                if are_installed {
+                    Ok(output("clang version 17.0.6\n"))
+                } else {
+                    Err(CommandError::from_io_error(
+                        cmd,
+                        args,
+                        std::io::Error::from(std::io::ErrorKind::NotFound),
+                    ))
+                }
+            });
+    }
+
+    #[test]
+    fn run_adds_only_the_targets_that_are_not_installed() {
+        let mut command_exec = CommandExec::default();
+        let mut wdk_build = WdkBuild::default();
+        let mut seq = Sequence::new();
+        command_exec
+            .expect_run()
+            .withf(|cmd: &str, args: &[&str], _, _| {
+                cmd == "rustup" && args == ["target", "list", "--installed"]
+            })
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|_, _, _, _| Ok(output("x86_64-pc-windows-msvc\r\n")));
+        command_exec
+            .expect_run()
+            .withf(|cmd: &str, args: &[&str], _, _| {
+                cmd == "rustup" && args == ["target", "add", "aarch64-pc-windows-msvc"]
+            })
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|_, _, _, _| Ok(output("")));
+        mock_prerequisite_checks(&mut command_exec, &mut wdk_build, true);
+
+        assert!(SetupAction::new(&command_exec, &wdk_build).run().is_ok());
+    }
+
+    #[test]
+    fn run_adds_no_targets_when_they_are_installed() {
+        let mut command_exec = CommandExec::default();
+        let mut wdk_build = WdkBuild::default();
+        mock_installed_targets(
+            &mut command_exec,
+            "aarch64-pc-windows-msvc\nx86_64-pc-windows-msvc\n",
+        );
+        mock_prerequisite_checks(&mut command_exec, &mut wdk_build, true);
+
+        assert!(SetupAction::new(&command_exec, &wdk_build).run().is_ok());
+    }
+
+    #[test]
+    fn run_reports_the_prerequisites_it_cannot_install() {
+        let mut command_exec = CommandExec::default();
+        let mut wdk_build = WdkBuild::default();
+        mock_installed_targets(
+            &mut command_exec,
+            "aarch64-pc-windows-msvc\nx86_64-pc-windows-msvc\n",
+        );
+        mock_prerequisite_checks(&mut command_exec, &mut wdk_build, false);
+
+        let result = SetupAction::new(&command_exec, &wdk_build).run();
+        assert!(matches!(
+            result,
+            Err(SetupActionError::MissingPrerequisites(missing))
+                if missing == ["MSVC toolchain", "MSVC build tools", "WDK", "LLVM"]
+        ));
+    }
+}
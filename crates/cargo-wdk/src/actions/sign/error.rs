@@ -0,0 +1,35 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module defines error types for the sign action module.
+
+use std::{path::PathBuf, string::FromUtf8Error};
+
+use thiserror::Error;
+
+use crate::providers::error::{CommandError, FileError};
+
+/// Errors for the sign action layer
+#[derive(Error, Debug)]
+pub enum SignActionError {
+    #[error(transparent)]
+    FileIo(#[from] FileError),
+    #[error("Package directory not found: {0}")]
+    PackageDirNotFound(PathBuf),
+    #[error("No driver binaries or catalog files found in the package directory: {0}")]
+    NoSignableFiles(PathBuf),
+    #[error("Error reading the signing certificate {0} using certutil")]
+    CertutilDumpCommand(String, #[source] CommandError),
+    #[error("Error reading stdout while reading the signing certificate using certutil")]
+    CertutilDumpInvalidCommandOutput(#[source] FromUtf8Error),
+    #[error(
+        "Signing certificate {0} lacks the Code Signing enhanced key usage (1.3.6.1.5.5.7.3.3) \
+         required to install drivers, found: {1}"
+    )]
+    MissingCodeSigningEku(String, String),
+    #[error("Error removing existing signatures using signtool")]
+    RemoveSignaturesCommand(#[source] CommandError),
+    #[error("Error signing using signtool")]
+    SignCommand(#[source] CommandError),
+    #[error("Error verifying signature using signtool")]
+    VerifyCommand(#[source] CommandError),
+}
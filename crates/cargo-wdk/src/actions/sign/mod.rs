@@ -0,0 +1,447 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module contains the `SignAction` struct and its associated methods
+//! for re-signing an existing driver package with a different certificate.
+mod error;
+
+use std::path::{Path, PathBuf, absolute};
+
+use anyhow::Result;
+use error::SignActionError;
+use mockall_double::double;
+use tracing::{debug, info};
+
+use crate::actions::{
+    eku,
+    signtool::{self, SigningCertificate},
+};
+#[double]
+use crate::providers::{exec::CommandExec, fs::Fs};
+
+/// Extensions of the driver binaries in a driver package
+const DRIVER_BINARY_EXTENSIONS: [&str; 2] = ["sys", "dll"];
+/// Extension of the catalog file in a driver package
+const CAT_FILE_EXTENSION: &str = "cat";
+
+/// Action that strips the existing signatures from the binaries and catalog
+/// file of a driver package and signs them again with the given certificate.
+pub struct SignAction<'a> {
+    package_dir: PathBuf,
+    cert_thumbprint: &'a str,
+    cert_store: &'a str,
+
+    // Injected deps
+    command_exec: &'a CommandExec,
+    fs: &'a Fs,
+}
+
+impl<'a> SignAction<'a> {
+    /// Creates a new instance of `SignAction`.
+    ///
+    /// # Arguments
+    /// * `package_dir` - The driver package directory to re-sign
+    /// * `cert_thumbprint` - SHA1 thumbprint of the signing certificate
+    /// * `cert_store` - Name of the store the signing certificate is in
+    /// * `command_exec` - The command execution provider instance
+    /// * `fs` - The file system provider instance
+    ///
+    /// # Returns
+    /// * `Result<Self>` - A result containing either a new instance of
+    ///   `SignAction` on success, or an `anyhow::Error`.
+    ///
+    /// # Errors
+    /// * [`anyhow::Error`] - If `package_dir` is not a syntactically valid
+    ///   path, e.g. it is empty
+    pub fn new(
+        package_dir: &Path,
+        cert_thumbprint: &'a str,
+        cert_store: &'a str,
+        command_exec: &'a CommandExec,
+        fs: &'a Fs,
+    ) -> Result<Self> {
+        anyhow::ensure!(
+            !package_dir.as_os_str().is_empty(),
+            "package_dir must not be empty"
+        );
+        Ok(Self {
+            package_dir: absolute(package_dir)?,
+            cert_thumbprint,
+            cert_store,
+            command_exec,
+            fs,
+        })
+    }
+
+    /// Entry point method to execute the sign action flow.
+    ///
+    /// The enhanced key usages of the certificate are checked before any
+    /// signature is removed. The driver binaries are signed before the catalog
+    /// file so that the catalog signature is applied last, the same order the
+    /// build action uses. All the files are verified once they are signed.
+    ///
+    /// # Returns
+    /// `Result<(), SignActionError>`
+    ///
+    /// # Errors
+    /// * `SignActionError::PackageDirNotFound` - If the package directory does
+    ///   not exist.
+    /// * `SignActionError::FileIo` - If there is an IO error.
+    /// * `SignActionError::NoSignableFiles` - If the package directory has no
+    ///   driver binaries or catalog files.
+    /// * `SignActionError::CertutilDumpCommand` - If there is an error reading
+    ///   the signing certificate.
+    /// * `SignActionError::CertutilDumpInvalidCommandOutput` - If the certutil
+    ///   output is not valid UTF-8.
+    /// * `SignActionError::MissingCodeSigningEku` - If the signing certificate
+    ///   lacks the code signing EKU.
+    /// * `SignActionError::RemoveSignaturesCommand` - If there is an error
+    ///   removing the existing signatures.
+    /// * `SignActionError::SignCommand` - If there is an error signing a file.
+    /// * `SignActionError::VerifyCommand` - If there is an error verifying the
+    ///   signature of a file.
+    pub fn run(&self) -> Result<(), SignActionError> {
+        debug!(
+            "Attempting to sign package at: {}",
+            self.package_dir.display()
+        );
+        if !self.fs.exists(&self.package_dir) {
+            return Err(SignActionError::PackageDirNotFound(
+                self.package_dir.clone(),
+            ));
+        }
+
+        let (driver_binaries, cat_files) = self.find_signable_files(&self.package_dir)?;
+        if driver_binaries.is_empty() && cat_files.is_empty() {
+            return Err(SignActionError::NoSignableFiles(self.package_dir.clone()));
+        }
+
+        let certificate = SigningCertificate::Thumbprint {
+            store: self.cert_store,
+            thumbprint: self.cert_thumbprint,
+        };
+        self.check_signing_certificate(certificate)?;
+
+        // Catalog files are regenerated from scratch when re-signed, so only the
+        // embedded signatures of the binaries need to be removed
+        for file_path in &driver_binaries {
+            info!("Removing existing signatures from {}", file_path.display());
+            signtool::remove_signatures(self.command_exec, file_path)
+                .map_err(SignActionError::RemoveSignaturesCommand)?;
+        }
+
+        for file_path in driver_binaries.iter().chain(&cat_files) {
+            info!("Signing {} using signtool", file_path.display());
+            signtool::sign(self.command_exec, file_path, certificate, true)
+                .map_err(SignActionError::SignCommand)?;
+        }
+
+        for file_path in driver_binaries.iter().chain(&cat_files) {
+            info!("Verifying {} using signtool", file_path.display());
+            signtool::verify(self.command_exec, file_path)
+                .map_err(SignActionError::VerifyCommand)?;
+        }
+
+        info!(
+            "Signed package at {} successfully",
+            self.package_dir.display()
+        );
+        Ok(())
+    }
+
+    /// Reads the enhanced key usages of the signing certificate using
+    /// `certutil` and fails if they do not include code signing. Such a
+    /// certificate produces signatures that `signtool verify` accepts, but the
+    /// driver fails to install on production machines.
+    fn check_signing_certificate(
+        &self,
+        certificate: SigningCertificate<'_>,
+    ) -> Result<(), SignActionError> {
+        info!("Checking enhanced key usages of {certificate} using certutil");
+        let output = self
+            .command_exec
+            .run("certutil", &eku::certutil_dump_args(certificate), None, None)
+            .map_err(|e| SignActionError::CertutilDumpCommand(certificate.to_string(), e))?;
+        let stdout = String::from_utf8(output.stdout)
+            .map_err(SignActionError::CertutilDumpInvalidCommandOutput)?;
+        eku::check_driver_signing(&stdout)
+            .map_err(|ekus| SignActionError::MissingCodeSigningEku(certificate.to_string(), ekus))
+    }
+
+    /// Recursively collects the driver binaries and catalog files under `dir`.
+    /// Multi-architecture packages keep their binaries in per-architecture sub
+    /// folders, hence the recursion.
+    fn find_signable_files(
+        &self,
+        dir: &Path,
+    ) -> Result<(Vec<PathBuf>, Vec<PathBuf>), SignActionError> {
+        let mut driver_binaries = Vec::new();
+        let mut cat_files = Vec::new();
+        for entry in self.fs.read_dir_entries(dir)? {
+            if entry.is_dir {
+                let (sub_dir_binaries, sub_dir_cat_files) =
+                    self.find_signable_files(&entry.path)?;
+                driver_binaries.extend(sub_dir_binaries);
+                cat_files.extend(sub_dir_cat_files);
+                continue;
+            }
+            let Some(extension) = entry
+                .path
+                .extension()
+                .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            else {
+                continue;
+            };
+            if DRIVER_BINARY_EXTENSIONS.contains(&extension.as_str()) {
+                driver_binaries.push(entry.path);
+            } else if extension == CAT_FILE_EXTENSION {
+                cat_files.push(entry.path);
+            }
+        }
+        Ok((driver_binaries, cat_files))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        os::windows::process::ExitStatusExt,
+        path::{Path, PathBuf},
+        process::{ExitStatus, Output},
+    };
+
+    use mockall::{Sequence, predicate::eq};
+    use mockall_double::double;
+
+    use super::{SignAction, error::SignActionError};
+    use crate::providers::{error::CommandError, fs::DirEntryInfo};
+    #[double]
+    use crate::providers::{exec::CommandExec, fs::Fs};
+
+    const CERT_THUMBPRINT: &str = "0123456789abcdef0123456789abcdef01234567";
+    const CERT_STORE: &str = "My";
+
+    fn ok_output() -> Output {
+        Output {
+            status: ExitStatus::from_raw(0),
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+        }
+    }
+
+    fn signtool_err() -> CommandError {
+        CommandError::CommandFailed {
+            command: "signtool".to_string(),
+            args: vec!["sign".to_string()],
+            stdout: "boom".to_string(),
+        }
+    }
+
+    /// Sets up `Fs::read_dir_entries(<dir>) -> Ok(entries)` where each entry is
+    /// `(name relative to dir, is_dir)`.
+    fn mock_read_dir(fs: &mut Fs, dir: &Path, entries: &[(&str, bool)]) {
+        let entries: Vec<DirEntryInfo> = entries
+            .iter()
+            .map(|(name, is_dir)| DirEntryInfo {
+                path: dir.join(name),
+                is_dir: *is_dir,
+            })
+            .collect();
+        fs.expect_read_dir_entries()
+            .with(eq(dir.to_owned()))
+            .returning(move |_| Ok(entries.clone()));
+    }
+
+    /// Sets up an ordered expectation for `signtool <args> <file>`.
+    fn mock_signtool(
+        exec: &mut CommandExec,
+        seq: &mut Sequence,
+        args: &'static [&'static str],
+        file: &Path,
+        ok: bool,
+    ) {
+        let file = file.to_string_lossy().into_owned();
+        exec.expect_run()
+            .withf(move |cmd, cmd_args, _env, _working_dir| {
+                cmd == "signtool"
+                    && cmd_args.len() == args.len() + 1
+                    && cmd_args[..args.len()] == *args
+                    && cmd_args[args.len()] == file
+            })
+            .once()
+            .in_sequence(seq)
+            .returning(move |_, _, _, _| {
+                if ok {
+                    Ok(ok_output())
+                } else {
+                    Err(signtool_err())
+                }
+            });
+    }
+
+    /// Sets up an ordered expectation for the `certutil` command that prints
+    /// the signing certificate, whose enhanced key usages are `ekus`.
+    fn mock_certutil(exec: &mut CommandExec, seq: &mut Sequence, ekus: &'static str) {
+        exec.expect_run()
+            .withf(|cmd, cmd_args, _env, _working_dir| {
+                cmd == "certutil"
+                    && cmd_args == ["-user", "-v", "-store", CERT_STORE, CERT_THUMBPRINT]
+            })
+            .once()
+            .in_sequence(seq)
+            .returning(move |_, _, _, _| {
+                Ok(Output {
+                    status: ExitStatus::from_raw(0),
+                    stdout: format!("    Enhanced Key Usage\r\n        {ekus}\r\n").into_bytes(),
+                    stderr: Vec::new(),
+                })
+            });
+    }
+
+    const CODE_SIGNING_EKU: &str = "Code Signing (1.3.6.1.5.5.7.3.3)";
+    const REMOVE_ARGS: &[&str] = &["remove", "/v", "/s"];
+    const SIGN_ARGS: &[&str] = &[
+        "sign",
+        "/v",
+        "/s",
+        CERT_STORE,
+        "/sha1",
+        CERT_THUMBPRINT,
+        "/t",
+        "http://timestamp.digicert.com",
+        "/fd",
+        "SHA256",
+    ];
+    const VERIFY_ARGS: &[&str] = &["verify", "/v", "/pa"];
+
+    fn run_action(package_dir: &Path, fs: &Fs, exec: &CommandExec) -> Result<(), SignActionError> {
+        SignAction::new(package_dir, CERT_THUMBPRINT, CERT_STORE, exec, fs)
+            .expect("SignAction::new should succeed")
+            .run()
+    }
+
+    #[test]
+    fn new_fails_if_package_dir_is_empty() {
+        let fs = Fs::default();
+        let exec = CommandExec::default();
+        let err = SignAction::new(Path::new(""), CERT_THUMBPRINT, CERT_STORE, &exec, &fs)
+            .err()
+            .expect("SignAction::new should fail for empty package_dir");
+        assert_eq!(err.to_string(), "package_dir must not be empty");
+    }
+
+    #[test]
+    fn run_removes_signatures_then_signs_and_verifies_binaries_before_cat_file() {
+        let package_dir = PathBuf::from("C:\\tmp\\driver_package");
+        let sys = package_dir.join("driver.sys");
+        let cat = package_dir.join("driver.cat");
+        let mut fs = Fs::default();
+        let mut exec = CommandExec::default();
+        let mut seq = Sequence::new();
+        fs.expect_exists()
+            .with(eq(package_dir.clone()))
+            .returning(|_| true);
+        mock_read_dir(
+            &mut fs,
+            &package_dir,
+            &[
+                ("driver.cat", false),
+                ("driver.inf", false),
+                ("driver.sys", false),
+            ],
+        );
+        mock_certutil(&mut exec, &mut seq, CODE_SIGNING_EKU);
+        mock_signtool(&mut exec, &mut seq, REMOVE_ARGS, &sys, true);
+        mock_signtool(&mut exec, &mut seq, SIGN_ARGS, &sys, true);
+        mock_signtool(&mut exec, &mut seq, SIGN_ARGS, &cat, true);
+        mock_signtool(&mut exec, &mut seq, VERIFY_ARGS, &sys, true);
+        mock_signtool(&mut exec, &mut seq, VERIFY_ARGS, &cat, true);
+        assert!(run_action(&package_dir, &fs, &exec).is_ok());
+    }
+
+    #[test]
+    fn run_signs_binaries_in_architecture_sub_folders() {
+        let package_dir = PathBuf::from("C:\\tmp\\driver_package");
+        let amd64_dir = package_dir.join("amd64");
+        let sys = amd64_dir.join("driver.sys");
+        let cat = package_dir.join("driver.cat");
+        let mut fs = Fs::default();
+        let mut exec = CommandExec::default();
+        let mut seq = Sequence::new();
+        fs.expect_exists()
+            .with(eq(package_dir.clone()))
+            .returning(|_| true);
+        mock_read_dir(
+            &mut fs,
+            &package_dir,
+            &[("amd64", true), ("driver.cat", false)],
+        );
+        mock_read_dir(&mut fs, &amd64_dir, &[("driver.sys", false)]);
+        mock_certutil(&mut exec, &mut seq, CODE_SIGNING_EKU);
+        mock_signtool(&mut exec, &mut seq, REMOVE_ARGS, &sys, true);
+        mock_signtool(&mut exec, &mut seq, SIGN_ARGS, &sys, true);
+        mock_signtool(&mut exec, &mut seq, SIGN_ARGS, &cat, true);
+        mock_signtool(&mut exec, &mut seq, VERIFY_ARGS, &sys, true);
+        mock_signtool(&mut exec, &mut seq, VERIFY_ARGS, &cat, true);
+        assert!(run_action(&package_dir, &fs, &exec).is_ok());
+    }
+
+    #[test]
+    fn run_returns_error_when_package_dir_does_not_exist() {
+        let package_dir = PathBuf::from("C:\\tmp\\driver_package");
+        let mut fs = Fs::default();
+        let exec = CommandExec::default();
+        fs.expect_exists().returning(|_| false);
+        assert!(matches!(
+            run_action(&package_dir, &fs, &exec),
+            Err(SignActionError::PackageDirNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn run_returns_error_when_package_has_no_signable_files() {
+        let package_dir = PathBuf::from("C:\\tmp\\driver_package");
+        let mut fs = Fs::default();
+        let exec = CommandExec::default();
+        fs.expect_exists().returning(|_| true);
+        mock_read_dir(&mut fs, &package_dir, &[("driver.inf", false)]);
+        assert!(matches!(
+            run_action(&package_dir, &fs, &exec),
+            Err(SignActionError::NoSignableFiles(_))
+        ));
+    }
+
+    #[test]
+    fn run_returns_error_before_removing_signatures_when_certificate_cannot_sign_drivers() {
+        let package_dir = PathBuf::from("C:\\tmp\\driver_package");
+        let mut fs = Fs::default();
+        let mut exec = CommandExec::default();
+        let mut seq = Sequence::new();
+        fs.expect_exists().returning(|_| true);
+        mock_read_dir(&mut fs, &package_dir, &[("driver.sys", false)]);
+        mock_certutil(&mut exec, &mut seq, "Server Authentication (1.3.6.1.5.5.7.3.1)");
+        assert!(matches!(
+            run_action(&package_dir, &fs, &exec),
+            Err(SignActionError::MissingCodeSigningEku(ref cert, ref found))
+                if *cert == format!("{CERT_THUMBPRINT} in the {CERT_STORE} store")
+                    && found == "1.3.6.1.5.5.7.3.1"
+        ));
+    }
+
+    #[test]
+    fn run_returns_error_when_signing_fails() {
+        let package_dir = PathBuf::from("C:\\tmp\\driver_package");
+        let sys = package_dir.join("driver.sys");
+        let mut fs = Fs::default();
+        let mut exec = CommandExec::default();
+        let mut seq = Sequence::new();
+        fs.expect_exists().returning(|_| true);
+        mock_read_dir(&mut fs, &package_dir, &[("driver.sys", false)]);
+        mock_certutil(&mut exec, &mut seq, CODE_SIGNING_EKU);
+        mock_signtool(&mut exec, &mut seq, REMOVE_ARGS, &sys, true);
+        mock_signtool(&mut exec, &mut seq, SIGN_ARGS, &sys, false);
+        assert!(matches!(
+            run_action(&package_dir, &fs, &exec),
+            Err(SignActionError::SignCommand(_))
+        ));
+    }
+}
@@ -0,0 +1,283 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module contains the helpers that invoke `signtool` to sign, verify and
+//! remove the signatures of driver binaries and catalog files. They are shared
+//! by the actions that sign driver packages.
+
+use std::{fmt, path::Path};
+
+use mockall_double::double;
+
+use crate::providers::error::CommandError;
+#[double]
+use crate::providers::exec::CommandExec;
+
+/// Timestamp server used when signing files
+const TIMESTAMP_SERVER_URL: &str = "http://timestamp.digicert.com";
+
+/// Header of the certificate chain of a signature in the output of
+/// `signtool verify /v`
+const SIGNING_CERTIFICATE_CHAIN_HEADER: &str = "Signing Certificate Chain:";
+
+/// Certificate used to sign files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigningCertificate<'a> {
+    /// Certificate with the given subject name in the given store
+    Named { store: &'a str, name: &'a str },
+    /// Certificate with the given SHA1 thumbprint in the given store
+    Thumbprint { store: &'a str, thumbprint: &'a str },
+    /// Certificate in the PFX file at the given path, protected by the given
+    /// password if any. The password is redacted from the logged command
+    /// lines, see [`redact_args`](crate::providers::exec::redact_args).
+    Pfx {
+        path: &'a str,
+        password: Option<&'a str>,
+    },
+}
+
+impl fmt::Display for SigningCertificate<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Named { store, name } => write!(f, "{name} in the {store} store"),
+            Self::Thumbprint { store, thumbprint } => {
+                write!(f, "{thumbprint} in the {store} store")
+            }
+            Self::Pfx { path, .. } => write!(f, "{path}"),
+        }
+    }
+}
+
+/// Details of the primary signature of a file, as printed by
+/// `signtool verify /v`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureDetails {
+    /// Subject of the signing certificate
+    pub signer: String,
+    /// SHA1 thumbprint of the signing certificate
+    pub thumbprint: String,
+    /// Time of the timestamp countersignature, `None` if the signature is not
+    /// timestamped
+    pub timestamp: Option<String>,
+    /// Digest algorithm of the signature, e.g. `sha256`
+    pub digest_algorithm: String,
+}
+
+/// Signs the file at `file_path` with the given certificate using
+/// `signtool sign`. When `timestamp` is `true` the signature is timestamped by
+/// [`TIMESTAMP_SERVER_URL`].
+pub fn sign(
+    command_exec: &CommandExec,
+    file_path: &Path,
+    certificate: SigningCertificate<'_>,
+    timestamp: bool,
+) -> Result<(), CommandError> {
+    let file_path = file_path.to_string_lossy();
+    let args = sign_args(&file_path, certificate, timestamp);
+    command_exec.run("signtool", &args, None, None)?;
+    Ok(())
+}
+
+/// Returns the `signtool` arguments that sign the file at `file_path` with
+/// the given certificate, see [`sign`].
+pub fn sign_args<'a>(
+    file_path: &'a str,
+    certificate: SigningCertificate<'a>,
+    timestamp: bool,
+) -> Vec<&'a str> {
+    let mut args = vec!["sign", "/v"];
+    match certificate {
+        SigningCertificate::Named { store, name } => args.extend(["/s", store, "/n", name]),
+        SigningCertificate::Thumbprint { store, thumbprint } => {
+            args.extend(["/s", store, "/sha1", thumbprint]);
+        }
+        SigningCertificate::Pfx { path, password } => {
+            args.extend(["/f", path]);
+            if let Some(password) = password {
+                args.extend(["/p", password]);
+            }
+        }
+    }
+    if timestamp {
+        args.extend(["/t", TIMESTAMP_SERVER_URL]);
+    }
+    args.extend(["/fd", "SHA256", file_path]);
+    args
+}
+
+/// Verifies the signature of the file at `file_path` using `signtool verify`
+/// with the default authentication verification policy.
+pub fn verify(command_exec: &CommandExec, file_path: &Path) -> Result<(), CommandError> {
+    let file_path = file_path.to_string_lossy();
+    command_exec.run("signtool", &verify_args(&file_path), None, None)?;
+    Ok(())
+}
+
+/// Returns the `signtool` arguments that verify the signature of the file at
+/// `file_path`, see [`verify`].
+pub const fn verify_args(file_path: &str) -> [&str; 4] {
+    ["verify", "/v", "/pa", file_path]
+}
+
+/// Returns the `signtool` arguments that verify that the catalog file at
+/// `catalog_file_path` lists the hash of the file at `file_path` and that the
+/// catalog signature is valid
+pub const fn verify_catalog_member_args<'a>(
+    catalog_file_path: &'a str,
+    file_path: &'a str,
+) -> [&'a str; 6] {
+    ["verify", "/v", "/pa", "/c", catalog_file_path, file_path]
+}
+
+/// Removes all the signatures of the file at `file_path` using
+/// `signtool remove`.
+pub fn remove_signatures(command_exec: &CommandExec, file_path: &Path) -> Result<(), CommandError> {
+    let file_path = file_path.to_string_lossy();
+    let args = ["remove", "/v", "/s", &file_path];
+    command_exec.run("signtool", &args, None, None)?;
+    Ok(())
+}
+
+/// Parses the details of the primary signature from the output of
+/// `signtool verify /v`, see [`verify_args`]. The certificates of the signing
+/// chain are listed from the root to the signing certificate, so the signer
+/// is the last one listed.
+///
+/// Returns `None` if the output lacks the digest algorithm or the signing
+/// certificate of the primary signature.
+pub fn parse_verify_output(verify_output: &str) -> Option<SignatureDetails> {
+    let mut signer = None;
+    let mut thumbprint = None;
+    let mut timestamp = None;
+    let mut digest_algorithm = None;
+    let mut in_signing_chain = false;
+    let mut signature_count = 0;
+    for raw_line in verify_output.lines() {
+        let line = raw_line.trim();
+        // The certificates of the chain are indented below its header and
+        // separated by blank lines, so only an unindented line ends the chain
+        if !line.is_empty() && !raw_line.starts_with(char::is_whitespace) {
+            in_signing_chain = false;
+        }
+        if line.starts_with("Signature Index:") {
+            signature_count += 1;
+            if signature_count > 1 {
+                break;
+            }
+        } else if line == SIGNING_CERTIFICATE_CHAIN_HEADER {
+            in_signing_chain = true;
+        } else if let Some(algorithm) = line
+            .strip_prefix("Hash of file (")
+            .and_then(|rest| rest.split_once(')'))
+            .map(|(algorithm, _)| algorithm)
+        {
+            digest_algorithm = Some(algorithm.to_string());
+        } else if let Some(time) = line.strip_prefix("The signature is timestamped:") {
+            timestamp = Some(time.trim().to_string());
+        } else if in_signing_chain {
+            if let Some(subject) = line.strip_prefix("Issued to:") {
+                signer = Some(subject.trim().to_string());
+            } else if let Some(hash) = line.strip_prefix("SHA1 hash:") {
+                thumbprint = Some(hash.trim().to_string());
+            }
+        }
+    }
+    Some(SignatureDetails {
+        signer: signer?,
+        thumbprint: thumbprint?,
+        timestamp,
+        digest_algorithm: digest_algorithm?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SignatureDetails, SigningCertificate, parse_verify_output, sign_args};
+
+    #[test]
+    fn sign_args_select_the_certificate_by_name_thumbprint_or_pfx_file() {
+        for (certificate, expected_selector) in [
+            (
+                SigningCertificate::Named {
+                    store: "WDRTestCertStore",
+                    name: "WDRLocalTestCert",
+                },
+                vec!["/s", "WDRTestCertStore", "/n", "WDRLocalTestCert"],
+            ),
+            (
+                SigningCertificate::Thumbprint {
+                    store: "My",
+                    thumbprint: "0123456789abcdef",
+                },
+                vec!["/s", "My", "/sha1", "0123456789abcdef"],
+            ),
+            (
+                SigningCertificate::Pfx {
+                    path: "C:/temp/cargo-wdk-1.pfx",
+                    password: Some("secret"),
+                },
+                vec!["/f", "C:/temp/cargo-wdk-1.pfx", "/p", "secret"],
+            ),
+            (
+                SigningCertificate::Pfx {
+                    path: "C:/temp/cargo-wdk-1.pfx",
+                    password: None,
+                },
+                vec!["/f", "C:/temp/cargo-wdk-1.pfx"],
+            ),
+        ] {
+            let args = sign_args("driver.sys", certificate, false);
+            assert_eq!(args[..2], ["sign", "/v"]);
+            assert_eq!(args[2..args.len() - 3], expected_selector);
+            assert_eq!(args[args.len() - 3..], ["/fd", "SHA256", "driver.sys"]);
+        }
+    }
+
+    #[test]
+    fn verify_output_is_parsed_for_the_signing_certificate_of_the_primary_signature() {
+        let verify_output = r"Verifying: C:\driver\target\debug\driver_package\driver.cat
+
+Signature Index: 0 (Primary Signature)
+Hash of file (sha256): 3F2A9C0B1D4E5F60718293A4B5C6D7E8F90A1B2C3D4E5F60718293A4B5C6D7E8
+
+Signing Certificate Chain:
+    Issued to: Contoso Root CA
+    Issued by: Contoso Root CA
+    Expires:   Sat Jan 01 00:00:00 2039
+    SHA1 hash: 1111111111111111111111111111111111111111
+
+        Issued to: Contoso Driver Signing
+        Issued by: Contoso Root CA
+        Expires:   Mon Jan 01 00:00:00 2029
+        SHA1 hash: 0123456789ABCDEF0123456789ABCDEF01234567
+
+The signature is timestamped: Tue Jan 02 10:11:12 2024
+Timestamp Verified by:
+    Issued to: DigiCert Timestamp 2023
+    Issued by: DigiCert Trusted G4 RSA4096 SHA256 TimeStamping CA
+    Expires:   Sat Oct 14 00:00:00 2034
+    SHA1 hash: 2222222222222222222222222222222222222222
+
+Signature Index: 1
+Hash of file (sha1): 0000000000000000000000000000000000000000
+
+Successfully verified: C:\driver\target\debug\driver_package\driver.cat
+";
+        assert_eq!(
+            parse_verify_output(verify_output),
+            Some(SignatureDetails {
+                signer: "Contoso Driver Signing".to_string(),
+                thumbprint: "0123456789ABCDEF0123456789ABCDEF01234567".to_string(),
+                timestamp: Some("Tue Jan 02 10:11:12 2024".to_string()),
+                digest_algorithm: "sha256".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn verify_output_without_a_signing_certificate_is_rejected() {
+        assert_eq!(
+            parse_verify_output("Hash of file (sha256): 00\nFile is not timestamped.\n"),
+            None
+        );
+    }
+}
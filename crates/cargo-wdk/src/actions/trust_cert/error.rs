@@ -0,0 +1,28 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module defines error types for the trust cert action module.
+
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::providers::error::CommandError;
+
+/// Errors for the trust cert action layer
+#[derive(Error, Debug)]
+pub enum TrustCertActionError {
+    #[error(
+        "Adding certificates to the machine certificate stores requires elevation. Run the \
+         command from an elevated prompt"
+    )]
+    NotElevated,
+    #[error("Package directory not found: {0}")]
+    PackageDirNotFound(PathBuf),
+    #[error(
+        "Test certificate not found: {0}. Only packages built with --sign-mode test contain a \
+         test certificate"
+    )]
+    CertFileNotFound(PathBuf),
+    #[error("Error adding the test certificate to the {0} store using certutil")]
+    AddToStoreCommand(String, #[source] CommandError),
+}
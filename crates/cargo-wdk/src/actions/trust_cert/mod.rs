@@ -0,0 +1,237 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module contains the `TrustCertAction` struct and its associated methods
+//! for trusting the test certificate of a driver package on a test machine, so
+//! that drivers signed with it can be installed.
+mod error;
+
+use std::path::{Path, PathBuf, absolute};
+
+use anyhow::Result;
+use error::TrustCertActionError;
+use mockall_double::double;
+use tracing::{debug, info};
+use windows::{
+    Win32::{
+        Foundation::{CloseHandle, HANDLE},
+        Security::{GetTokenInformation, TOKEN_ELEVATION, TOKEN_QUERY, TokenElevation},
+        System::Threading::{GetCurrentProcess, OpenProcessToken},
+    },
+    core::Error as WinError,
+};
+
+#[double]
+use crate::providers::{exec::CommandExec, fs::Fs};
+
+/// File name of the test certificate that `build` copies into the package
+/// when signing in test mode
+const TEST_CERT_FILE_NAME: &str = "WDRLocalTestCert.cer";
+/// Machine certificate stores the test certificate is added to. The `Root`
+/// store trusts it as a root authority and the `TrustedPublisher` store lets
+/// drivers signed with it be installed without a prompt.
+const TRUSTED_CERT_STORES: [&str; 2] = ["Root", "TrustedPublisher"];
+
+/// Action that adds the test certificate of a driver package to the
+/// certificate stores of the local machine
+pub struct TrustCertAction<'a> {
+    package_dir: PathBuf,
+    is_elevated: bool,
+
+    // Injected deps
+    command_exec: &'a CommandExec,
+    fs: &'a Fs,
+}
+
+impl<'a> TrustCertAction<'a> {
+    /// Creates a new instance of `TrustCertAction`.
+    ///
+    /// # Arguments
+    /// * `package_dir` - The driver package directory containing the test
+    ///   certificate
+    /// * `is_elevated` - Whether cargo-wdk runs elevated, see [`is_elevated`]
+    /// * `command_exec` - The command execution provider instance
+    /// * `fs` - The file system provider instance
+    ///
+    /// # Returns
+    /// * `Result<Self>` - A result containing either a new instance of
+    ///   `TrustCertAction` on success, or an `anyhow::Error`.
+    ///
+    /// # Errors
+    /// * [`anyhow::Error`] - If `package_dir` is not a syntactically valid
+    ///   path, e.g. it is empty
+    pub fn new(
+        package_dir: &Path,
+        is_elevated: bool,
+        command_exec: &'a CommandExec,
+        fs: &'a Fs,
+    ) -> Result<Self> {
+        anyhow::ensure!(
+            !package_dir.as_os_str().is_empty(),
+            "package_dir must not be empty"
+        );
+        Ok(Self {
+            package_dir: absolute(package_dir)?,
+            is_elevated,
+            command_exec,
+            fs,
+        })
+    }
+
+    /// Entry point method to execute the trust cert action flow. The test
+    /// certificate of the package is added to the `Root` and
+    /// `TrustedPublisher` stores of the local machine using `certutil`, which
+    /// ships with Windows, so the WDK is not needed on the test machine.
+    ///
+    /// # Returns
+    /// `Result<(), TrustCertActionError>`
+    ///
+    /// # Errors
+    /// * `TrustCertActionError::NotElevated` - If cargo-wdk does not run
+    ///   elevated.
+    /// * `TrustCertActionError::PackageDirNotFound` - If the package directory
+    ///   does not exist.
+    /// * `TrustCertActionError::CertFileNotFound` - If the package has no test
+    ///   certificate.
+    /// * `TrustCertActionError::AddToStoreCommand` - If there is an error
+    ///   adding the certificate to a store.
+    pub fn run(&self) -> Result<(), TrustCertActionError> {
+        if !self.is_elevated {
+            return Err(TrustCertActionError::NotElevated);
+        }
+        if !self.fs.exists(&self.package_dir) {
+            return Err(TrustCertActionError::PackageDirNotFound(
+                self.package_dir.clone(),
+            ));
+        }
+        let cert_file_path = self.package_dir.join(TEST_CERT_FILE_NAME);
+        if !self.fs.exists(&cert_file_path) {
+            return Err(TrustCertActionError::CertFileNotFound(cert_file_path));
+        }
+
+        let cert_file_path_str = cert_file_path.to_string_lossy();
+        for store in TRUSTED_CERT_STORES {
+            debug!("Adding {} to the {store} store", cert_file_path.display());
+            self.command_exec
+                .run(
+                    "certutil",
+                    &["-addstore", "-f", store, &cert_file_path_str],
+                    None,
+                    None,
+                )
+                .map_err(|e| TrustCertActionError::AddToStoreCommand(store.to_string(), e))?;
+            info!("Added {} to the {store} store", cert_file_path.display());
+        }
+        info!(
+            "Drivers signed with the test certificate of {} can now be installed",
+            self.package_dir.display()
+        );
+        Ok(())
+    }
+}
+
+/// Returns whether the current process runs elevated, i.e. with the
+/// administrator rights required to add certificates to the machine stores
+///
+/// # Errors
+/// * [`WinError`] - If the token of the current process cannot be queried
+pub fn is_elevated() -> Result<bool, WinError> {
+    let mut token = HANDLE::default();
+    // SAFETY: The pseudo handle of the current process is always valid and
+    // `token` outlives the call
+    unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &raw mut token)? };
+    let mut elevation = TOKEN_ELEVATION::default();
+    let mut returned_length = 0;
+    // SAFETY: The token was opened with `TOKEN_QUERY` above and the buffer is a
+    // `TOKEN_ELEVATION` of the given size
+    let result = unsafe {
+        GetTokenInformation(
+            token,
+            TokenElevation,
+            Some((&raw mut elevation).cast()),
+            u32::try_from(size_of::<TOKEN_ELEVATION>()).expect("TOKEN_ELEVATION fits in a u32"),
+            &raw mut returned_length,
+        )
+    };
+    // SAFETY: The token was opened above and is not used after this
+    unsafe { CloseHandle(token)? };
+    result?;
+    Ok(elevation.TokenIsElevated != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        os::windows::process::ExitStatusExt,
+        path::{Path, PathBuf},
+        process::{ExitStatus, Output},
+    };
+
+    use mockall::{Sequence, predicate::eq};
+    use mockall_double::double;
+
+    use super::{TrustCertAction, error::TrustCertActionError};
+    #[double]
+    use crate::providers::{exec::CommandExec, fs::Fs};
+
+    #[test]
+    fn run_fails_when_not_elevated() {
+        let fs = Fs::default();
+        let exec = CommandExec::default();
+        let result = TrustCertAction::new(Path::new("driver_package"), false, &exec, &fs)
+            .expect("TrustCertAction::new should succeed")
+            .run();
+        assert!(matches!(result, Err(TrustCertActionError::NotElevated)));
+    }
+
+    #[test]
+    fn run_fails_when_package_has_no_test_certificate() {
+        let package_dir = PathBuf::from("C:\\tmp\\driver_package");
+        let mut fs = Fs::default();
+        let exec = CommandExec::default();
+        fs.expect_exists()
+            .with(eq(package_dir.clone()))
+            .returning(|_| true);
+        fs.expect_exists()
+            .with(eq(package_dir.join("WDRLocalTestCert.cer")))
+            .returning(|_| false);
+        let result = TrustCertAction::new(&package_dir, true, &exec, &fs)
+            .expect("TrustCertAction::new should succeed")
+            .run();
+        assert!(matches!(
+            result,
+            Err(TrustCertActionError::CertFileNotFound(path))
+                if path == package_dir.join("WDRLocalTestCert.cer")
+        ));
+    }
+
+    #[test]
+    fn run_adds_test_certificate_to_root_and_trusted_publisher_stores() {
+        let package_dir = PathBuf::from("C:\\tmp\\driver_package");
+        let mut fs = Fs::default();
+        let mut exec = CommandExec::default();
+        let mut seq = Sequence::new();
+        fs.expect_exists().returning(|_| true);
+        for store in ["Root", "TrustedPublisher"] {
+            exec.expect_run()
+                .withf(move |cmd: &str, args: &[&str], _, _| {
+                    cmd == "certutil"
+                        && args.len() == 4
+                        && args[..3] == ["-addstore", "-f", store]
+                        && args[3] == "C:\\tmp\\driver_package\\WDRLocalTestCert.cer"
+                })
+                .once()
+                .in_sequence(&mut seq)
+                .returning(|_, _, _, _| {
+                    Ok(Output {
+                        status: ExitStatus::from_raw(0),
+                        stdout: Vec::new(),
+                        stderr: Vec::new(),
+                    })
+                });
+        }
+        let result = TrustCertAction::new(&package_dir, true, &exec, &fs)
+            .expect("TrustCertAction::new should succeed")
+            .run();
+        assert!(result.is_ok());
+    }
+}
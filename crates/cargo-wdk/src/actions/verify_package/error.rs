@@ -0,0 +1,29 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module defines error types for the verify package action module.
+
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::providers::error::FileError;
+
+/// Errors for the verify package action layer
+#[derive(Error, Debug)]
+pub enum VerifyPackageActionError {
+    #[error(transparent)]
+    FileIo(#[from] FileError),
+    #[error("Package directory not found: {0}")]
+    PackageDirNotFound(PathBuf),
+    #[error("Invalid golden manifest {0}: {1}")]
+    InvalidGoldenManifest(PathBuf, String),
+    #[error(
+        "Package at {package_dir} does not match the golden manifest {golden_manifest}: \
+         {mismatch_count} mismatched files"
+    )]
+    Mismatch {
+        package_dir: PathBuf,
+        golden_manifest: PathBuf,
+        mismatch_count: usize,
+    },
+}
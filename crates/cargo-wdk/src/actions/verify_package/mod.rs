@@ -0,0 +1,492 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module contains the `VerifyPackageAction` struct and its associated
+//! methods for verifying a driver package against a golden manifest, a JSON
+//! file listing the files the package is expected to contain and optionally
+//! their sizes and SHA256 hashes. It serves as a packaging regression check
+//! that does not require rebuilding the package.
+mod error;
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf, absolute},
+};
+
+use anyhow::Result;
+pub use error::VerifyPackageActionError;
+use mockall_double::double;
+use serde_json::{Value, json};
+use tracing::{debug, info};
+
+use crate::actions::diff::{FileSummary, SHORT_HASH_LEN, read_package_files};
+#[double]
+use crate::providers::fs::Fs;
+
+/// Number of hex digits of a SHA256 hash
+const SHA256_HEX_LEN: usize = 64;
+
+/// Action that verifies the files of a driver package against a golden
+/// manifest, or writes the golden manifest from the files of the package.
+pub struct VerifyPackageAction<'a> {
+    package_dir: PathBuf,
+    golden_manifest_path: PathBuf,
+    update: bool,
+
+    // Injected deps
+    fs: &'a Fs,
+}
+
+/// File a golden manifest expects in a driver package. The size and hash are
+/// only compared if the manifest lists them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GoldenFile {
+    pub size: Option<usize>,
+    pub sha256: Option<String>,
+}
+
+/// A difference between a driver package and its golden manifest. Paths are
+/// relative to the package directory and separated by `/`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    /// File listed by the golden manifest that is not in the package
+    Missing(String),
+    /// File in the package that is not listed by the golden manifest
+    Extra(String, FileSummary),
+    /// File whose size or hash differs from the golden manifest
+    Changed {
+        path: String,
+        expected: GoldenFile,
+        actual: FileSummary,
+    },
+}
+
+/// Differences between a driver package and its golden manifest, sorted by
+/// path
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackageVerification {
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl<'a> VerifyPackageAction<'a> {
+    /// Creates a new instance of `VerifyPackageAction`.
+    ///
+    /// # Arguments
+    /// * `package_dir` - The driver package directory to verify
+    /// * `golden_manifest_path` - The golden manifest to verify the package
+    ///   against
+    /// * `update` - Write the golden manifest from the files of the package
+    ///   instead of verifying the package
+    /// * `fs` - The file system provider instance
+    ///
+    /// # Returns
+    /// * `Result<Self>` - A result containing either a new instance of
+    ///   `VerifyPackageAction` on success, or an `anyhow::Error`.
+    ///
+    /// # Errors
+    /// * [`anyhow::Error`] - If either path is not a syntactically valid path,
+    ///   e.g. it is empty
+    pub fn new(
+        package_dir: &Path,
+        golden_manifest_path: &Path,
+        update: bool,
+        fs: &'a Fs,
+    ) -> Result<Self> {
+        anyhow::ensure!(
+            !package_dir.as_os_str().is_empty() && !golden_manifest_path.as_os_str().is_empty(),
+            "package directory and golden manifest paths must not be empty"
+        );
+        Ok(Self {
+            package_dir: absolute(package_dir)?,
+            golden_manifest_path: absolute(golden_manifest_path)?,
+            update,
+            fs,
+        })
+    }
+
+    /// Entry point method to execute the verify package action flow. The
+    /// report of the mismatches is printed to stdout.
+    ///
+    /// # Returns
+    /// `Result<(), VerifyPackageActionError>`
+    ///
+    /// # Errors
+    /// * `VerifyPackageActionError::PackageDirNotFound` - If the package
+    ///   directory does not exist.
+    /// * `VerifyPackageActionError::InvalidGoldenManifest` - If the golden
+    ///   manifest is not valid.
+    /// * `VerifyPackageActionError::Mismatch` - If the package does not match
+    ///   the golden manifest.
+    /// * `VerifyPackageActionError::FileIo` - If there is an IO error.
+    pub fn run(&self) -> Result<(), VerifyPackageActionError> {
+        if self.update {
+            return self.write_golden_manifest();
+        }
+        let verification = self.verify()?;
+        if verification.mismatches.is_empty() {
+            info!(
+                "Package at {} matches the golden manifest {}",
+                self.package_dir.display(),
+                self.golden_manifest_path.display()
+            );
+            return Ok(());
+        }
+        print!("{verification}");
+        Err(VerifyPackageActionError::Mismatch {
+            package_dir: self.package_dir.clone(),
+            golden_manifest: self.golden_manifest_path.clone(),
+            mismatch_count: verification.mismatches.len(),
+        })
+    }
+
+    /// Compares the files of the package directory and its sub folders with
+    /// the files listed by the golden manifest.
+    ///
+    /// # Errors
+    /// * `VerifyPackageActionError::PackageDirNotFound` - If the package
+    ///   directory does not exist.
+    /// * `VerifyPackageActionError::InvalidGoldenManifest` - If the golden
+    ///   manifest is not valid.
+    /// * `VerifyPackageActionError::FileIo` - If there is an IO error.
+    pub fn verify(&self) -> Result<PackageVerification, VerifyPackageActionError> {
+        debug!(
+            "Verifying package at {} against golden manifest {}",
+            self.package_dir.display(),
+            self.golden_manifest_path.display()
+        );
+        let golden_files =
+            parse_golden_manifest(&self.fs.read_file_to_string(&self.golden_manifest_path)?)
+                .map_err(|reason| {
+                    VerifyPackageActionError::InvalidGoldenManifest(
+                        self.golden_manifest_path.clone(),
+                        reason,
+                    )
+                })?;
+        let mut package_files = self.package_files()?;
+
+        let mut mismatches = Vec::new();
+        for (path, expected) in golden_files {
+            let Some(actual) = package_files.remove(&path) else {
+                mismatches.push(Mismatch::Missing(path));
+                continue;
+            };
+            let size_differs = expected.size.is_some_and(|size| size != actual.size);
+            let hash_differs = expected
+                .sha256
+                .as_ref()
+                .is_some_and(|sha256| *sha256 != actual.sha256);
+            if size_differs || hash_differs {
+                mismatches.push(Mismatch::Changed {
+                    path,
+                    expected,
+                    actual,
+                });
+            }
+        }
+        mismatches.extend(
+            package_files
+                .into_iter()
+                .map(|(path, actual)| Mismatch::Extra(path, actual)),
+        );
+        mismatches.sort_by(|a, b| a.path().cmp(b.path()));
+        Ok(PackageVerification { mismatches })
+    }
+
+    /// Writes the golden manifest listing the files of the package with their
+    /// sizes and hashes
+    fn write_golden_manifest(&self) -> Result<(), VerifyPackageActionError> {
+        let package_files = self.package_files()?;
+        self.fs.write_to_file(
+            &self.golden_manifest_path,
+            golden_manifest_content(&package_files).as_bytes(),
+        )?;
+        info!(
+            "Golden manifest of {} files written to {}",
+            package_files.len(),
+            self.golden_manifest_path.display()
+        );
+        Ok(())
+    }
+
+    /// Returns the size and hash of the files of the package directory, keyed
+    /// by their path relative to it with `/` separators
+    fn package_files(&self) -> Result<BTreeMap<String, FileSummary>, VerifyPackageActionError> {
+        if !self.fs.exists(&self.package_dir) {
+            return Err(VerifyPackageActionError::PackageDirNotFound(
+                self.package_dir.clone(),
+            ));
+        }
+        Ok(read_package_files(self.fs, &self.package_dir)?
+            .into_iter()
+            .map(|(path, content)| (manifest_path(&path), FileSummary::of(&content)))
+            .collect())
+    }
+}
+
+impl Mismatch {
+    fn path(&self) -> &str {
+        match self {
+            Self::Missing(path) | Self::Extra(path, _) | Self::Changed { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for PackageVerification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for mismatch in &self.mismatches {
+            match mismatch {
+                Mismatch::Missing(path) => writeln!(f, "- {path} (missing)")?,
+                Mismatch::Extra(path, actual) => writeln!(
+                    f,
+                    "+ {path} ({} bytes, sha256 {})",
+                    actual.size,
+                    actual.short_hash()
+                )?,
+                Mismatch::Changed {
+                    path,
+                    expected,
+                    actual,
+                } => {
+                    let mut details = Vec::new();
+                    if let Some(size) = expected.size {
+                        details.push(format!("{size} -> {} bytes", actual.size));
+                    }
+                    if let Some(sha256) = &expected.sha256 {
+                        details.push(format!(
+                            "sha256 {} -> {}",
+                            &sha256[..SHORT_HASH_LEN],
+                            actual.short_hash()
+                        ));
+                    }
+                    writeln!(f, "~ {path} ({})", details.join(", "))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the relative `path` of a package file with `/` separators
+fn manifest_path(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Renders the golden manifest of the given package files, sorted by path
+fn golden_manifest_content(package_files: &BTreeMap<String, FileSummary>) -> String {
+    let files = package_files
+        .iter()
+        .map(|(path, summary)| {
+            json!({
+                "path": path,
+                "size": summary.size,
+                "sha256": summary.sha256,
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut content = serde_json::to_string_pretty(&json!({ "files": files }))
+        .expect("golden manifest is JSON");
+    content.push('\n');
+    content
+}
+
+/// Parses the files listed by a golden manifest, keyed by their path with `/`
+/// separators. The manifest is a JSON object whose `files` array lists an
+/// object with the `path` and optionally the `size` and `sha256` hash of each
+/// file, in the format written by `--update`.
+///
+/// Returns the reason if the manifest is not valid.
+fn parse_golden_manifest(content: &str) -> Result<BTreeMap<String, GoldenFile>, String> {
+    let manifest = serde_json::from_str::<Value>(content).map_err(|e| e.to_string())?;
+    let files = manifest
+        .get("files")
+        .and_then(Value::as_array)
+        .ok_or("expected a `files` array")?;
+    let mut golden_files = BTreeMap::new();
+    for file in files {
+        let path = file
+            .get("path")
+            .and_then(Value::as_str)
+            .filter(|path| !path.is_empty())
+            .ok_or_else(|| format!("expected a `path` string in {file}"))?;
+        let size = file
+            .get("size")
+            .map(|size| {
+                size.as_u64()
+                    .and_then(|size| usize::try_from(size).ok())
+                    .ok_or_else(|| format!("invalid `size` of `{path}`: {size}"))
+            })
+            .transpose()?;
+        let sha256 = file
+            .get("sha256")
+            .map(|sha256| {
+                sha256
+                    .as_str()
+                    .filter(|sha256| {
+                        sha256.len() == SHA256_HEX_LEN
+                            && sha256.chars().all(|c| c.is_ascii_hexdigit())
+                    })
+                    .map(str::to_ascii_lowercase)
+                    .ok_or_else(|| format!("invalid `sha256` of `{path}`: {sha256}"))
+            })
+            .transpose()?;
+        if golden_files
+            .insert(path.replace('\\', "/"), GoldenFile { size, sha256 })
+            .is_some()
+        {
+            return Err(format!("`{path}` is listed more than once"));
+        }
+    }
+    Ok(golden_files)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::PathBuf,
+        sync::{Arc, Mutex},
+    };
+
+    use mockall::predicate::{always, eq};
+    use mockall_double::double;
+
+    use super::{
+        FileSummary,
+        Mismatch,
+        VerifyPackageAction,
+        error::VerifyPackageActionError,
+        parse_golden_manifest,
+    };
+    use crate::actions::diff::tests::mock_package;
+    #[double]
+    use crate::providers::fs::Fs;
+
+    #[test]
+    fn verify_reports_missing_extra_and_changed_files() {
+        let package_dir = PathBuf::from("C:\\tmp\\driver_package");
+        let golden_manifest_path = PathBuf::from("C:\\tmp\\golden.json");
+        let golden_manifest = format!(
+            r#"{{
+                "files": [
+                    {{ "path": "driver.cat" }},
+                    {{ "path": "driver.inf" }},
+                    {{ "path": "driver.sys", "size": 6, "sha256": "{}" }}
+                ]
+            }}"#,
+            FileSummary::of(b"binary").sha256
+        );
+        let mut fs = Fs::default();
+        mock_package(
+            &mut fs,
+            &package_dir,
+            &[
+                ("driver.inf", b"inf"),
+                ("driver.map", b"map"),
+                ("driver.sys", b"binary v2"),
+            ],
+        );
+        fs.expect_read_file_to_string()
+            .with(eq(golden_manifest_path.clone()))
+            .returning(move |_| Ok(golden_manifest.clone()));
+
+        let action = VerifyPackageAction::new(&package_dir, &golden_manifest_path, false, &fs)
+            .expect("VerifyPackageAction::new should succeed");
+        let verification = action.verify().expect("verify should succeed");
+
+        let paths = verification
+            .mismatches
+            .iter()
+            .map(|mismatch| match mismatch {
+                Mismatch::Missing(path) => format!("-{path}"),
+                Mismatch::Extra(path, _) => format!("+{path}"),
+                Mismatch::Changed { path, .. } => format!("~{path}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["-driver.cat", "+driver.map", "~driver.sys"]);
+        let report = verification.to_string();
+        assert!(report.contains("- driver.cat (missing)\n"));
+        assert!(report.contains("+ driver.map (3 bytes, sha256 "));
+        assert!(report.contains("~ driver.sys (6 -> 9 bytes, sha256 "));
+        assert!(matches!(
+            action.run(),
+            Err(VerifyPackageActionError::Mismatch {
+                mismatch_count: 3,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn package_matches_the_golden_manifest_written_from_it() {
+        let package_dir = PathBuf::from("C:\\tmp\\driver_package");
+        let golden_manifest_path = PathBuf::from("C:\\tmp\\golden.json");
+        let mut fs = Fs::default();
+        mock_package(
+            &mut fs,
+            &package_dir,
+            &[("driver.inf", b"inf"), ("driver.sys", b"binary")],
+        );
+        let written = Arc::new(Mutex::new(String::new()));
+        let written_by_mock = Arc::clone(&written);
+        fs.expect_write_to_file()
+            .with(eq(golden_manifest_path.clone()), always())
+            .once()
+            .returning(move |_, data| {
+                *written_by_mock.lock().expect("lock should not be poisoned") =
+                    String::from_utf8_lossy(data).into_owned();
+                Ok(())
+            });
+        let golden_manifest = Arc::clone(&written);
+        fs.expect_read_file_to_string()
+            .with(eq(golden_manifest_path.clone()))
+            .returning(move |_| {
+                Ok(golden_manifest
+                    .lock()
+                    .expect("lock should not be poisoned")
+                    .clone())
+            });
+
+        VerifyPackageAction::new(&package_dir, &golden_manifest_path, true, &fs)
+            .expect("VerifyPackageAction::new should succeed")
+            .run()
+            .expect("golden manifest should be written");
+        assert!(
+            written
+                .lock()
+                .expect("lock should not be poisoned")
+                .contains(r#""path": "driver.sys""#)
+        );
+        VerifyPackageAction::new(&package_dir, &golden_manifest_path, false, &fs)
+            .expect("VerifyPackageAction::new should succeed")
+            .run()
+            .expect("package should match the golden manifest");
+    }
+
+    #[test]
+    fn invalid_golden_manifests_are_rejected() {
+        for (golden_manifest, reason) in [
+            ("[]", "expected a `files` array"),
+            (r#"{"files": [{"size": 1}]}"#, "expected a `path` string"),
+            (
+                r#"{"files": [{"path": "driver.sys", "size": -1}]}"#,
+                "invalid `size` of `driver.sys`",
+            ),
+            (
+                r#"{"files": [{"path": "driver.sys", "sha256": "abc"}]}"#,
+                "invalid `sha256` of `driver.sys`",
+            ),
+            (
+                r#"{"files": [{"path": "a/driver.sys"}, {"path": "a\\driver.sys"}]}"#,
+                "is listed more than once",
+            ),
+        ] {
+            let parsed = parse_golden_manifest(golden_manifest);
+            assert!(
+                parsed.as_ref().is_err_and(|error| error.contains(reason)),
+                "{golden_manifest}: {parsed:?}"
+            );
+        }
+    }
+}
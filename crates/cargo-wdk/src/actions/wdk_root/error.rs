@@ -0,0 +1,12 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module defines error types for the WDK root action module.
+
+use thiserror::Error;
+
+/// Errors for the WDK root action layer
+#[derive(Error, Debug)]
+pub enum WdkRootActionError {
+    #[error(transparent)]
+    WdkBuildConfig(#[from] wdk_build::ConfigError),
+}
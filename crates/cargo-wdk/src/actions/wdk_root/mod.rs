@@ -0,0 +1,123 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module contains the `WdkRootAction` struct and its associated methods
+//! for printing the WDK content root that the other actions build against, so
+//! that scripts do not need to repeat the WDK discovery of `wdk-build`.
+mod error;
+
+use error::WdkRootActionError;
+use mockall_double::double;
+use tracing::debug;
+
+#[double]
+use crate::providers::wdk_build::WdkBuild;
+
+/// Action that prints the detected WDK content root, or the version of the WDK
+/// in it, to stdout
+pub struct WdkRootAction<'a> {
+    print_version: bool,
+
+    // Injected deps
+    wdk_build: &'a WdkBuild,
+}
+
+impl<'a> WdkRootAction<'a> {
+    /// Creates a new instance of `WdkRootAction`.
+    ///
+    /// # Arguments
+    /// * `print_version` - Print the version of the WDK, e.g. `10.0.26100.0`,
+    ///   instead of its content root
+    /// * `wdk_build` - The WDK build provider instance
+    pub const fn new(print_version: bool, wdk_build: &'a WdkBuild) -> Self {
+        Self {
+            print_version,
+            wdk_build,
+        }
+    }
+
+    /// Entry point method to execute the WDK root action flow. Only the
+    /// detected value is printed to stdout, so that it can be captured by
+    /// scripts.
+    ///
+    /// # Errors
+    /// * `WdkRootActionError::WdkBuildConfig` - If no WDK content root is found
+    ///   or the version of the WDK cannot be detected.
+    pub fn run(&self) -> Result<(), WdkRootActionError> {
+        println!("{}", self.resolve()?);
+        Ok(())
+    }
+
+    /// Detects the WDK content root the same way `wdk-build` does and returns
+    /// it, or the version of the WDK in it if `print_version` is set.
+    ///
+    /// # Errors
+    /// * `WdkRootActionError::WdkBuildConfig` - If no WDK content root is found
+    ///   or the version of the WDK cannot be detected.
+    pub fn resolve(&self) -> Result<String, WdkRootActionError> {
+        let wdk_content_root = self.wdk_build.detect_wdk_content_root()?;
+        debug!("Detected WDK content root: {}", wdk_content_root.display());
+        if !self.print_version {
+            return Ok(wdk_content_root.display().to_string());
+        }
+        Ok(self.wdk_build.detect_wdk_version(&wdk_content_root)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use mockall_double::double;
+
+    use super::{WdkRootAction, error::WdkRootActionError};
+    #[double]
+    use crate::providers::wdk_build::WdkBuild;
+
+    fn wdk_build_with_content_root(wdk_content_root: Option<&'static str>) -> WdkBuild {
+        let mut wdk_build = WdkBuild::default();
+        wdk_build
+            .expect_detect_wdk_content_root()
+            .once()
+            .returning(move || {
+                wdk_content_root
+                    .map(PathBuf::from)
+                    .ok_or(wdk_build::ConfigError::WdkContentRootDetectionError)
+            });
+        wdk_build
+    }
+
+    #[test]
+    fn resolve_returns_the_content_root_or_the_wdk_version() {
+        let wdk_build =
+            wdk_build_with_content_root(Some(r"C:\Program Files (x86)\Windows Kits\10"));
+        assert_eq!(
+            WdkRootAction::new(false, &wdk_build).resolve().unwrap(),
+            r"C:\Program Files (x86)\Windows Kits\10"
+        );
+
+        let mut wdk_build =
+            wdk_build_with_content_root(Some(r"C:\Program Files (x86)\Windows Kits\10"));
+        wdk_build
+            .expect_detect_wdk_version()
+            .withf(|wdk_content_root: &Path| {
+                wdk_content_root == Path::new(r"C:\Program Files (x86)\Windows Kits\10")
+            })
+            .once()
+            .returning(|_| Ok("10.0.26100.0".to_string()));
+        assert_eq!(
+            WdkRootAction::new(true, &wdk_build).resolve().unwrap(),
+            "10.0.26100.0"
+        );
+    }
+
+    #[test]
+    fn resolve_fails_when_no_wdk_is_found() {
+        let wdk_build = wdk_build_with_content_root(None);
+        assert!(matches!(
+            WdkRootAction::new(false, &wdk_build).resolve(),
+            Err(WdkRootActionError::WdkBuildConfig(
+                wdk_build::ConfigError::WdkContentRootDetectionError
+            ))
+        ));
+    }
+}
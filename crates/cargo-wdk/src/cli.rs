@@ -3,10 +3,28 @@
 //! This module defines the top-level CLI layer, its argument types and
 //! structures used for parsing and validating arguments for various
 //! subcommands.
-use std::path::{Path, PathBuf};
+mod wizard;
+
+use std::{
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    str::FromStr,
+    time::Duration,
+};
 
 use anyhow::{Ok, Result};
-use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use cargo_metadata::semver::Version;
+use clap::{
+    ArgGroup,
+    Args,
+    CommandFactory,
+    FromArgMatches,
+    Parser,
+    Subcommand,
+    ValueEnum,
+    error::ErrorKind,
+};
 use clap_cargo::Features;
 use clap_verbosity_flag::Verbosity;
 use mockall_double::double;
@@ -18,16 +36,50 @@
     Profile,
     UMDF_STR,
     WDM_STR,
-    build::{BuildAction, BuildActionParams, SignMode, TargetPlatform},
-    clean::CleanAction,
-    new::NewAction,
+    build::{
+        BuildAction,
+        BuildActionError,
+        BuildActionParams,
+        DriverVerVersion,
+        PrintCargoCommand,
+        ProfileOverride,
+        Sanitizer,
+        SignMode,
+        TargetPlatform,
+        ToolPaths,
+        WdfVersion,
+        WdfVersionOverrides,
+        WdkLockMode,
+    },
+    clean::{CleanAction, CleanActionParams},
+    diff::DiffAction,
+    new::{InfEncoding, LineEndings, Logging, NewAction, NewActionParams, Panic},
+    schema::SchemaAction,
+    setup::SetupAction,
+    sign::SignAction,
+    trust_cert::{self, TrustCertAction},
+    verify_package::VerifyPackageAction,
+    wdk_root::WdkRootAction,
 };
+use crate::trace::ColorChoice;
 #[double]
 use crate::providers::{exec::CommandExec, fs::Fs, metadata::Metadata, wdk_build::WdkBuild};
 
 const ABOUT_STRING: &str = "cargo-wdk is a cargo extension that can be used to create and build \
                             Windows Rust driver projects.";
 const CARGO_WDK_BIN_NAME: &str = "cargo wdk";
+/// Environment variable consulted for the build profile when `--profile` is
+/// not passed
+const CARGO_WDK_PROFILE_ENV_VAR: &str = "CARGO_WDK_PROFILE";
+/// Environment variable consulted for the `DriverVer` date of reproducible
+/// packages when `--source-date-epoch` is not passed
+const SOURCE_DATE_EPOCH_ENV_VAR: &str = "SOURCE_DATE_EPOCH";
+/// Value of `--pdb-altpath` when it is passed without one, which the linker
+/// replaces with the file name of the PDB
+const DEFAULT_PDB_ALTPATH: &str = "%_PDB%";
+/// Exit code when no Rust packages are found to build, which tells "nothing to
+/// do" apart from both a successful and a failed build
+const NO_RUST_PACKAGES_EXIT_CODE: u8 = 3;
 
 /// Driver signing mode
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
@@ -62,6 +114,175 @@ fn from(value: TargetPlatformArg) -> Self {
     }
 }
 
+/// Value of `--target-arch` for the `build` subcommand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetArchArg {
+    /// Detect the architecture of the host toolchain.
+    Native,
+    /// Build for the given architecture.
+    Arch(CpuArchitecture),
+}
+
+impl TargetArchArg {
+    /// Returns the architecture to build for, or `None` if it must be
+    /// detected from the host toolchain
+    const fn cpu_architecture(self) -> Option<CpuArchitecture> {
+        match self {
+            Self::Native => None,
+            Self::Arch(arch) => Some(arch),
+        }
+    }
+}
+
+impl FromStr for TargetArchArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("native") {
+            return Result::<_, String>::Ok(Self::Native);
+        }
+        CpuArchitecture::from_str(s).map(Self::Arch)
+    }
+}
+
+/// Logging framework to scaffold in a new driver project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum LoggingArg {
+    /// No logging setup.
+    #[default]
+    None,
+    /// WPP software tracing.
+    Wpp,
+    /// The `tracing` crate.
+    Tracing,
+}
+
+impl From<LoggingArg> for Logging {
+    fn from(value: LoggingArg) -> Self {
+        match value {
+            LoggingArg::None => Self::None,
+            LoggingArg::Wpp => Self::Wpp,
+            LoggingArg::Tracing => Self::Tracing,
+        }
+    }
+}
+
+/// Panic handling to scaffold in a new driver project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum PanicArg {
+    /// Abort on panic with the panic handler of the `wdk-panic` crate.
+    #[default]
+    Abort,
+    /// Abort on panic with a panic handler stub in the driver crate. Not
+    /// supported for UMDF drivers.
+    Custom,
+}
+
+impl From<PanicArg> for Panic {
+    fn from(value: PanicArg) -> Self {
+        match value {
+            PanicArg::Abort => Self::Abort,
+            PanicArg::Custom => Self::Custom,
+        }
+    }
+}
+
+/// Line endings of the files scaffolded in a new driver project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum LineEndingsArg {
+    /// LF for the source files and CRLF for the INF.
+    #[default]
+    Auto,
+    /// LF for every file.
+    Lf,
+    /// CRLF for every file.
+    Crlf,
+}
+
+impl From<LineEndingsArg> for LineEndings {
+    fn from(value: LineEndingsArg) -> Self {
+        match value {
+            LineEndingsArg::Auto => Self::Auto,
+            LineEndingsArg::Lf => Self::Lf,
+            LineEndingsArg::Crlf => Self::Crlf,
+        }
+    }
+}
+
+/// Encoding of the INF scaffolded in a new driver project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum InfEncodingArg {
+    /// UTF-8 without a byte order mark.
+    #[default]
+    Utf8,
+    /// UTF-8 with a byte order mark.
+    Utf8Bom,
+}
+
+impl From<InfEncodingArg> for InfEncoding {
+    fn from(value: InfEncodingArg) -> Self {
+        match value {
+            InfEncodingArg::Utf8 => Self::Utf8,
+            InfEncodingArg::Utf8Bom => Self::Utf8Bom,
+        }
+    }
+}
+
+/// Value of `--sanitizer` for the `build` subcommand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+#[value(rename_all = "lower")]
+pub enum SanitizerArg {
+    /// Build without a sanitizer.
+    #[default]
+    None,
+    /// Build with AddressSanitizer.
+    Address,
+    /// Build with UndefinedBehaviorSanitizer, which rustc does not provide.
+    Undefined,
+}
+
+/// Value of the global `--color` option
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+#[value(rename_all = "lower")]
+pub enum ColorArg {
+    /// Color output written to a terminal, unless `NO_COLOR` is set.
+    #[default]
+    Auto,
+    /// Always color output.
+    Always,
+    /// Never color output.
+    Never,
+}
+
+impl From<ColorArg> for ColorChoice {
+    fn from(value: ColorArg) -> Self {
+        match value {
+            ColorArg::Auto => Self::Auto,
+            ColorArg::Always => Self::Always,
+            ColorArg::Never => Self::Never,
+        }
+    }
+}
+
+/// Value of `--print-cargo-command` for the `build` subcommand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "lower")]
+pub enum PrintCargoCommandArg {
+    /// Print the cargo commands and run them.
+    Run,
+    /// Print the cargo commands and exit without running them.
+    Only,
+}
+
+impl From<PrintCargoCommandArg> for PrintCargoCommand {
+    fn from(value: PrintCargoCommandArg) -> Self {
+        match value {
+            PrintCargoCommandArg::Run => Self::AndRun,
+            PrintCargoCommandArg::Only => Self::Only,
+        }
+    }
+}
+
 /// Arguments for the `new` subcommand
 #[derive(Debug, Args)]
 #[clap(
@@ -84,12 +305,58 @@ pub struct NewArgs {
     #[arg(long)]
     pub wdm: bool,
 
+    /// Logging framework to wire into the new driver crate
+    #[arg(long, value_enum, ignore_case = true, default_value_t = LoggingArg::None)]
+    pub logging: LoggingArg,
+
+    /// Panic handling to scaffold in the new driver crate
+    #[arg(long, value_enum, ignore_case = true, default_value_t = PanicArg::Abort)]
+    pub panic: PanicArg,
+
+    /// Initial version of the driver crate, written to `Cargo.toml` and the
+    /// `DriverVer` of the INF
+    #[arg(long, value_name = "SEMVER")]
+    pub version: Option<Version>,
+
+    /// Scaffold a `.devcontainer` with the toolchain needed to build the
+    /// driver crate
+    #[arg(long)]
+    pub with_devcontainer: bool,
+
+    /// Scaffold a `tests` folder with integration tests that install the
+    /// driver package with pnputil on a test machine
+    #[arg(long)]
+    pub with_integration_tests: bool,
+
+    /// Decorate the `[Manufacturer]` section of the INF and create a models
+    /// section for each of these architectures instead of the `NT$ARCH$`
+    /// placeholder
+    #[arg(long, ignore_case = true, value_delimiter = ',', value_name = "ARCH")]
+    pub target_arch: Vec<CpuArchitecture>,
+
+    /// Line endings of the scaffolded files. `auto` writes the source files
+    /// with LF and the INF with CRLF
+    #[arg(long, value_enum, ignore_case = true, default_value_t = LineEndingsArg::Auto)]
+    pub line_endings: LineEndingsArg,
+
+    /// Encoding of the scaffolded INF
+    #[arg(long, value_enum, ignore_case = true, default_value_t = InfEncodingArg::Utf8)]
+    pub inf_encoding: InfEncodingArg,
+
     /// Path at which the new driver crate should be created
     #[arg(required = true)]
     pub path: Option<PathBuf>,
 }
 
 impl NewArgs {
+    /// Returns whether one of the `driver_type` flags, `--kmdf`, `--umdf` or
+    /// `--wdm` was passed to the `new` command. It may be omitted when
+    /// `cargo wdk new` is run from a terminal, in which case it is prompted
+    /// for.
+    const fn has_driver_type(&self) -> bool {
+        self.kmdf || self.umdf || self.wdm
+    }
+
     /// Returns the variant of `DriverType` based on which of the `driver_type`
     /// flags, `--kmdf`, `--umdf` or `--wdm` was passed to the `new` command.
     ///
@@ -113,12 +380,18 @@ const fn driver_type(&self) -> DriverType {
 #[derive(Debug, Args)]
 pub struct BuildArgs {
     /// Build artifacts with the specified profile
-    #[arg(long, ignore_case = true)]
+    #[arg(long, ignore_case = true, env = CARGO_WDK_PROFILE_ENV_VAR)]
     pub profile: Option<Profile>,
 
-    /// Build for the target architecture
+    /// Build for the target architecture. `native` detects the architecture
+    /// of the host toolchain, which is also the default
     #[arg(long, ignore_case = true)]
-    pub target_arch: Option<CpuArchitecture>,
+    pub target_arch: Option<TargetArchArg>,
+
+    /// Build for amd64 and arm64 and create a single driver package with one
+    /// catalog covering both architectures
+    #[arg(long, conflicts_with = "target_arch")]
+    pub multi_arch_package: bool,
 
     /// Driver target platform
     #[arg(long, value_enum, ignore_case = true, default_value_t = TargetPlatformArg::Universal)]
@@ -128,9 +401,15 @@ pub struct BuildArgs {
     #[arg(long)]
     pub sample: bool,
 
-    /// Driver signing mode
-    #[arg(long, value_enum, ignore_case = true, default_value_t = SignModeArg::Test)]
-    pub sign_mode: SignModeArg,
+    /// Fail unless the driver packages are of this driver type (kmdf, umdf or
+    /// wdm) in their WDK metadata
+    #[arg(long, ignore_case = true, conflicts_with = "binary_path")]
+    pub driver_type: Option<DriverType>,
+
+    /// Driver signing mode. Defaults to the `sign-mode` packaging setting of
+    /// the profile, or `test` if it is not set
+    #[arg(long, value_enum, ignore_case = true)]
+    pub sign_mode: Option<SignModeArg>,
 
     /// Verify the signature
     #[arg(long)]
@@ -140,30 +419,580 @@ pub struct BuildArgs {
     #[arg(long)]
     pub locked: bool,
 
+    /// Number of parallel jobs of the `cargo build` invocations. Defaults to
+    /// cargo's default, the number of logical CPUs
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub cargo_jobs: Option<u32>,
+
+    /// Fail the build on any compiler warning by adding `-D warnings` to the
+    /// `RUSTFLAGS` of the `cargo build` invocations
+    #[arg(long)]
+    pub deny_compile_warnings: bool,
+
+    /// Run `cargo clippy` with the same options before building each package
+    /// and fail on clippy errors. With `--deny-compile-warnings`, clippy
+    /// warnings also fail the build.
+    #[arg(long)]
+    pub clippy: bool,
+
+    /// Fail if the detected WDK differs from the one recorded in the target
+    /// directory
+    #[arg(long, conflicts_with = "update_wdk_lock")]
+    pub locked_wdk: bool,
+
+    /// Record the detected WDK in the target directory for `--locked-wdk`
+    #[arg(long)]
+    pub update_wdk_lock: bool,
+
+    /// Build driver packages even if the detected WDK is outside of their
+    /// `supported-wdk` range
+    #[arg(long)]
+    pub allow_unsupported_wdk: bool,
+
+    /// Fail if the driver binary imports functions unavailable on the oldest
+    /// Windows build the driver targets
+    #[arg(long)]
+    pub import_check: bool,
+
+    /// Report the minimum OS version the driver binary is linked for and warn
+    /// if the INF targets a different OS version
+    #[arg(long)]
+    pub os_version_check: bool,
+
+    /// Directory for the intermediate files of the packaging tools. Defaults to
+    /// the directory in the `TMP`/`TEMP` environment variables
+    #[arg(long)]
+    pub temp_dir: Option<PathBuf>,
+
+    /// Directory to write the output of the tools run for each package to, as
+    /// `<package name>.log`
+    #[arg(long, value_name = "DIR")]
+    pub log_dir: Option<PathBuf>,
+
+    /// Zip archive to write the build settings, the resolved metadata of the
+    /// packages and the output of the tools to, also if the build fails
+    #[arg(long, value_name = "PATH")]
+    pub diagnostic_bundle: Option<PathBuf>,
+
+    /// Path of the `signtool` executable to run instead of the one found on
+    /// `PATH`
+    #[arg(long, value_name = "PATH")]
+    pub signtool_path: Option<PathBuf>,
+
+    /// Path of the `certmgr` executable to run instead of the one found on
+    /// `PATH`
+    #[arg(long, value_name = "PATH")]
+    pub certmgr_path: Option<PathBuf>,
+
+    /// Path of the `stampinf` executable to run instead of the one found on
+    /// `PATH`
+    #[arg(long, value_name = "PATH")]
+    pub stampinf_path: Option<PathBuf>,
+
+    /// Path of the `inf2cat` executable to run instead of the one found on
+    /// `PATH`
+    #[arg(long, value_name = "PATH")]
+    pub inf2cat_path: Option<PathBuf>,
+
+    /// Fail if the INF or catalog of a driver package contains absolute paths
+    /// of the build machine
+    #[arg(long, conflicts_with = "no_package")]
+    pub relocatable: bool,
+
+    /// Print only the paths of the files of the driver packages, one per line,
+    /// after a successful build. Other output is limited to errors.
+    #[arg(long, conflicts_with = "no_package")]
+    pub print_artifacts: bool,
+
+    /// Clear existing package folders that contain files which are not part
+    /// of the package instead of failing
+    #[arg(long, conflicts_with = "no_package")]
+    pub overwrite_package: bool,
+
+    /// Compile the drivers without creating driver packages
+    #[arg(long, conflicts_with_all = ["verify_signature", "import_check", "os_version_check"])]
+    pub no_package: bool,
+
+    /// Create driver packages that are identical across builds of the same
+    /// source. Signatures are not timestamped.
+    #[arg(long)]
+    pub reproducible: bool,
+
+    /// Seconds since the Unix epoch that the `DriverVer` date of reproducible
+    /// packages is derived from
+    #[arg(long, env = SOURCE_DATE_EPOCH_ENV_VAR)]
+    pub source_date_epoch: Option<u64>,
+
+    /// Package the given pre-built `.sys` driver binary instead of building
+    /// the crate. Requires `--target-arch`
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "target_arch",
+        conflicts_with_all = ["multi_arch_package", "no_package", "reproducible"]
+    )]
+    pub binary_path: Option<PathBuf>,
+
+    /// Write a `.nuspec` manifest referencing the files of each driver package
+    /// for `nuget pack`
+    #[arg(long, conflicts_with_all = ["no_package", "binary_path"])]
+    pub emit_nupkg: bool,
+
+    /// Write `packages.json` to the target directory, listing the name,
+    /// driver type, architectures, version and path of every driver package
+    /// the build produced
+    #[arg(
+        long,
+        conflicts_with_all = ["no_package", "binary_path", "stamp_only", "binary_only"]
+    )]
+    pub emit_index: bool,
+
+    /// Write a markdown summary of the files, registry values and services
+    /// installed by the INFs to each driver package
+    #[arg(long, conflicts_with = "no_package")]
+    pub emit_install_summary: bool,
+
+    /// Write a report of the signer, thumbprint, timestamp and digest
+    /// algorithm of the signed catalog files to each driver package. Cannot
+    /// be used with `--sign-mode off`
+    #[arg(long, conflicts_with = "no_package")]
+    pub emit_catalog_signing_report: bool,
+
+    /// Write `uninstall.ps1` and `uninstall.cmd` scripts to each driver
+    /// package that delete the installed driver package with `pnputil` and
+    /// remove the services created by its INFs
+    #[arg(long, conflicts_with_all = ["no_package", "stamp_only"])]
+    pub emit_uninstall: bool,
+
+    /// Copy the linker map of the driver into the package. Defaults to the
+    /// `include-map` packaging setting of the profile, or `true` if it is not
+    /// set
+    #[arg(long, value_name = "BOOL")]
+    pub include_map: Option<bool>,
+
+    /// Embed the given PDB path in the driver binaries instead of the absolute
+    /// path of the build machine. Defaults to `%_PDB%`, the file name of the
+    /// PDB, if no value is given. The PDB is packaged under the file name of
+    /// the path.
+    #[arg(
+        long,
+        value_name = "PATH",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = DEFAULT_PDB_ALTPATH,
+        value_parser = parse_pdb_altpath,
+        conflicts_with = "binary_path"
+    )]
+    pub pdb_altpath: Option<String>,
+
+    /// Build and package KMDF drivers for this KMDF version, e.g. `1.31`,
+    /// instead of the version in their WDK metadata. It must be installed
+    /// with the WDK
+    #[arg(long, value_name = "VERSION", conflicts_with = "binary_path")]
+    pub kmdf_version: Option<WdfVersion>,
+
+    /// Build and package UMDF drivers for this UMDF version, e.g. `2.31`,
+    /// instead of the version in their WDK metadata. It must be installed
+    /// with the WDK
+    #[arg(long, value_name = "VERSION", conflicts_with = "binary_path")]
+    pub umdf_version: Option<WdfVersion>,
+
+    /// Print the `cargo` commands run for each package with their working
+    /// directory and environment variables. With `only`, the commands are
+    /// printed without running them and nothing is built or packaged
+    #[arg(
+        long,
+        value_name = "WHEN",
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "run",
+        conflicts_with = "binary_path"
+    )]
+    pub print_cargo_command: Option<PrintCargoCommandArg>,
+
+    /// Only stamp the INFs of the packages with `stampinf`, using the
+    /// configured `DriverVer` version and date, and report the stamped
+    /// `DriverVer`. The drivers are not built, cataloged or signed.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "no_package",
+            "binary_path",
+            "multi_arch_package",
+            "clippy",
+            "print_cargo_command"
+        ]
+    )]
+    pub stamp_only: bool,
+
+    /// Package only the signed driver binary, and its PDB unless the
+    /// `include-pdb` packaging setting is `false`, without INFs or catalogs,
+    /// e.g. for installers that provide their own INF
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "no_package",
+            "binary_path",
+            "multi_arch_package",
+            "stamp_only",
+            "relocatable",
+            "emit_nupkg",
+            "emit_install_summary",
+            "emit_catalog_signing_report",
+            "emit_uninstall"
+        ]
+    )]
+    pub binary_only: bool,
+
+    /// Directory to archive the PDBs of the driver binaries to, at the
+    /// `<pdb name>/<GUID><age>/<pdb name>` paths of a symbol store
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with_all = ["no_package", "binary_path", "stamp_only"]
+    )]
+    pub emit_symbols_archive: Option<PathBuf>,
+
+    /// Skip the `pre-build` commands of the packages, e.g. when the generated
+    /// code is already up to date
+    #[arg(long)]
+    pub no_hooks: bool,
+
+    /// Instrument library and user-mode driver packages with a sanitizer for
+    /// host-side tests. Requires a nightly toolchain and is refused for
+    /// kernel-mode driver packages
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = SanitizerArg::None,
+        conflicts_with_all = ["binary_path", "multi_arch_package", "stamp_only"]
+    )]
+    pub sanitizer: SanitizerArg,
+
+    /// Last released `DriverVer` version of the drivers, e.g. `1.2.3.4`. The
+    /// build fails if the version stamped into an INF is not greater, since
+    /// the package would not upgrade the released driver
+    #[arg(
+        long,
+        value_name = "VERSION",
+        conflicts_with_all = ["no_package", "binary_only"]
+    )]
+    pub min_version: Option<DriverVerVersion>,
+
+    /// Check that the test certificate has not expired before signing with
+    /// it, and regenerate an expired one. Generated test certificates are
+    /// valid for this number of days
+    #[arg(
+        long,
+        value_name = "DAYS",
+        value_parser = clap::value_parser!(u32).range(1..),
+        conflicts_with_all = ["no_package", "stamp_only"]
+    )]
+    pub cert_validity_days: Option<u32>,
+
+    /// Environment variable containing a base64 encoded PFX certificate to
+    /// sign with instead of the test certificate, e.g. a CI secret. The
+    /// certificate is decoded into a temporary file that is deleted after the
+    /// build
+    #[arg(
+        long,
+        value_name = "VARNAME",
+        conflicts_with_all = ["no_package", "stamp_only", "cert_validity_days"]
+    )]
+    pub cert_from_env: Option<String>,
+
+    /// Environment variable containing the password of the certificate of
+    /// `--cert-from-env`
+    #[arg(long, value_name = "VARNAME", requires = "cert_from_env")]
+    pub cert_password_env: Option<String>,
+
+    /// Override a setting of the profile being built without editing the
+    /// manifest, e.g. `opt-level=3`. Passed to cargo as `--config
+    /// profile.<profile>.<KEY>=<VALUE>`. May be repeated
+    #[arg(
+        long,
+        value_name = "KEY=VALUE",
+        conflicts_with_all = ["binary_path", "stamp_only"]
+    )]
+    pub profile_override: Vec<ProfileOverride>,
+
+    /// Named pipe to write the events of the build to as lines of JSON while
+    /// it runs, e.g. for an IDE to show its progress. The pipe
+    /// `\\.\pipe\<NAME>` must have been created by its reader
+    #[arg(long, value_name = "NAME")]
+    pub event_pipe: Option<String>,
+
+    /// Also build a checked variant of each driver with the `checked` cfg,
+    /// debug assertions and overflow checks, packaged and signed in
+    /// `<name>_package_checked` next to its package. May also be enabled with
+    /// the `checked` packaging setting
+    #[arg(
+        long,
+        conflicts_with_all = ["no_package", "binary_path", "multi_arch_package", "stamp_only"]
+    )]
+    pub checked: bool,
+
     #[command(flatten)]
     #[clap(next_help_heading = "Feature Selection")]
     pub features: Features,
 }
 
+/// Parses the value of `--pdb-altpath`, which is passed to the linker through
+/// `RUSTFLAGS` and thus cannot contain whitespace
+fn parse_pdb_altpath(value: &str) -> Result<String, String> {
+    if value.is_empty() {
+        return Err("the PDB path cannot be empty".to_string());
+    }
+    if value.chars().any(char::is_whitespace) {
+        return Err("the PDB path cannot contain whitespace".to_string());
+    }
+    Result::<_, String>::Ok(value.to_string())
+}
+
 impl BuildArgs {
+    /// Maps the `--locked-wdk` and `--update-wdk-lock` flags to the respective
+    /// [`WdkLockMode`] variant. The flags conflict with each other, so at most
+    /// one of them is set.
+    const fn wdk_lock_mode(&self) -> WdkLockMode {
+        if self.locked_wdk {
+            WdkLockMode::Locked
+        } else if self.update_wdk_lock {
+            WdkLockMode::Update
+        } else {
+            WdkLockMode::Off
+        }
+    }
+
+    /// Checks the combinations of flags that clap cannot reject on its own
+    /// since they only conflict for some of their values. Runs before any
+    /// action so that the error names the conflicting flags.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if
+    /// * `--target-arch=native` is used together with `--binary-path`, as
+    ///   nothing is built to detect the architecture from.
+    /// * `--verify-signature`, `--emit-catalog-signing-report`,
+    ///   `--cert-validity-days` or `--cert-from-env` is used together with
+    ///   `--sign-mode=off`, as nothing is signed.
+    /// * `--kmdf-version` or `--umdf-version` is used together with a
+    ///   `--driver-type` of another framework, as the version would never
+    ///   apply.
+    /// * `--sanitizer=undefined` is used, as rustc has no such sanitizer, or
+    ///   `--sanitizer` is used together with a kernel-mode `--driver-type` or
+    ///   `--target-arch=arm64`, which the sanitizers do not support.
+    fn validate(&self) -> Result<()> {
+        if self.binary_path.is_some() && self.target_arch == Some(TargetArchArg::Native) {
+            return Err(anyhow::anyhow!(
+                "`--binary-path` requires an explicit `--target-arch` other than `native`."
+            ));
+        }
+        if self.sign_mode == Some(SignModeArg::Off) {
+            for (flag, is_set) in [
+                ("--verify-signature", self.verify_signature),
+                (
+                    "--emit-catalog-signing-report",
+                    self.emit_catalog_signing_report,
+                ),
+                ("--cert-validity-days", self.cert_validity_days.is_some()),
+                ("--cert-from-env", self.cert_from_env.is_some()),
+            ] {
+                if is_set {
+                    return Err(anyhow::anyhow!(
+                        "`{flag}` cannot be used with `--sign-mode=off`."
+                    ));
+                }
+            }
+        }
+        if let Some(driver_type) = self.driver_type {
+            for (flag, is_set, framework) in [
+                (
+                    "--kmdf-version",
+                    self.kmdf_version.is_some(),
+                    DriverType::Kmdf,
+                ),
+                (
+                    "--umdf-version",
+                    self.umdf_version.is_some(),
+                    DriverType::Umdf,
+                ),
+            ] {
+                if is_set && driver_type != framework {
+                    return Err(anyhow::anyhow!(
+                        "`{flag}` cannot be used with `--driver-type={driver_type}`."
+                    ));
+                }
+            }
+        }
+        self.validate_sanitizer()
+    }
+
+    /// Checks that the sanitizer of `--sanitizer` can be applied to the build
+    fn validate_sanitizer(&self) -> Result<()> {
+        match self.sanitizer {
+            SanitizerArg::None => return Ok(()),
+            SanitizerArg::Undefined => {
+                return Err(anyhow::anyhow!(
+                    "`--sanitizer=undefined` is not supported since rustc has no \
+                     UndefinedBehaviorSanitizer. Undefined behavior of unsafe code can be found \
+                     with Miri, and `overflow-checks` catch integer overflows."
+                ));
+            }
+            SanitizerArg::Address => {}
+        }
+        if let Some(driver_type @ (DriverType::Kmdf | DriverType::Wdm)) = self.driver_type {
+            return Err(anyhow::anyhow!(
+                "`--sanitizer` cannot be used with `--driver-type={driver_type}`, since \
+                 kernel-mode drivers cannot be built with a sanitizer."
+            ));
+        }
+        if self.target_arch == Some(TargetArchArg::Arch(CpuArchitecture::Arm64)) {
+            return Err(anyhow::anyhow!(
+                "`--sanitizer` cannot be used with `--target-arch=arm64`, since rustc supports \
+                 AddressSanitizer only for amd64 Windows targets."
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the sanitizer of `--sanitizer`, or `None` to build without one
+    const fn sanitizer(&self) -> Option<Sanitizer> {
+        match self.sanitizer {
+            SanitizerArg::Address => Some(Sanitizer::Address),
+            SanitizerArg::None | SanitizerArg::Undefined => None,
+        }
+    }
+
+    /// Returns the architecture passed in `--target-arch`, or `None` if it is
+    /// absent or `native` so that the architecture of the host toolchain is
+    /// detected.
+    fn target_arch(&self) -> Option<CpuArchitecture> {
+        self.target_arch.and_then(TargetArchArg::cpu_architecture)
+    }
+
+    /// Collects the executables passed in `--signtool-path`,
+    /// `--certmgr-path`, `--stampinf-path` and `--inf2cat-path`
+    fn tool_paths(&self) -> ToolPaths {
+        ToolPaths {
+            signtool: self.signtool_path.clone(),
+            certmgr: self.certmgr_path.clone(),
+            stampinf: self.stampinf_path.clone(),
+            inf2cat: self.inf2cat_path.clone(),
+        }
+    }
+
     /// Maps the `--sign-mode` and `--verify-signature` combination to the
-    /// respective [`SignMode`] variant, or returns an error.
+    /// respective [`SignMode`] variant. The combination of `--verify-signature`
+    /// with `--sign-mode=off` is rejected by [`BuildArgs::validate`]. Returns
+    /// `None` without `--sign-mode`, so that the packaging settings of the
+    /// profile apply. `--verify-signature` is also passed on its own, so that
+    /// packages whose packaging settings turn signing off fail.
+    const fn sign_mode(&self) -> Option<SignMode> {
+        match self.sign_mode {
+            Some(SignModeArg::Off) => Some(SignMode::Off),
+            Some(SignModeArg::Test) => Some(SignMode::Test {
+                verify_signature: self.verify_signature,
+            }),
+            None => None,
+        }
+    }
+
+    /// Returns the source date epoch of reproducible packages if
+    /// `--reproducible` was passed, or `None` otherwise.
     ///
     /// # Errors
     ///
-    /// Returns an error if `--verify-signature` is used together with
-    /// `--sign-mode=off`.
-    fn sign_mode(&self) -> Result<SignMode> {
-        match (self.sign_mode, self.verify_signature) {
-            (SignModeArg::Off, true) => Err(anyhow::anyhow!(
-                "`--verify-signature` cannot be used with `--sign-mode=off`."
-            )),
-            (SignModeArg::Off, false) => Ok(SignMode::Off),
-            (SignModeArg::Test, verify_signature) => Ok(SignMode::Test { verify_signature }),
+    /// Returns an error if `--reproducible` is used without
+    /// `--source-date-epoch` or the `SOURCE_DATE_EPOCH` environment variable.
+    fn source_date_epoch(&self) -> Result<Option<u64>> {
+        if !self.reproducible {
+            return Ok(None);
         }
+        self.source_date_epoch.map(Some).ok_or_else(|| {
+            anyhow::anyhow!(
+                "`--reproducible` requires `--source-date-epoch` or the \
+                 `{SOURCE_DATE_EPOCH_ENV_VAR}` environment variable."
+            )
+        })
     }
 }
 
+/// Arguments for the `sign` subcommand
+#[derive(Debug, Args)]
+pub struct SignArgs {
+    /// Path of the driver package to re-sign
+    pub package_dir: PathBuf,
+
+    /// SHA1 thumbprint of the certificate to sign with
+    #[arg(long, required = true)]
+    pub cert_thumbprint: String,
+
+    /// Certificate store containing the certificate
+    #[arg(long, default_value = "My")]
+    pub cert_store: String,
+}
+
+/// Arguments for the `diff` subcommand
+#[derive(Debug, Args)]
+pub struct DiffArgs {
+    /// Path of the driver package to compare against
+    pub old_package_dir: PathBuf,
+
+    /// Path of the driver package to compare
+    pub new_package_dir: PathBuf,
+}
+
+/// Arguments for the `verify-package` subcommand
+#[derive(Debug, Args)]
+pub struct VerifyPackageArgs {
+    /// Path of the driver package to verify
+    pub package_dir: PathBuf,
+
+    /// Path of the golden manifest listing the files the package is expected
+    /// to contain
+    #[arg(long, value_name = "GOLDEN")]
+    pub against: PathBuf,
+
+    /// Write the golden manifest from the files of the package instead of
+    /// verifying the package against it
+    #[arg(long)]
+    pub update: bool,
+}
+
+/// Arguments for the `clean` subcommand
+#[derive(Debug, Args)]
+pub struct CleanProjectArgs {
+    /// Directory of the project or workspace to clean
+    #[arg(long, default_value = ".")]
+    pub cwd: PathBuf,
+
+    /// Clean the artifacts of the specified profile only
+    #[arg(long, ignore_case = true)]
+    pub profile: Option<Profile>,
+
+    /// Remove only the driver package folders of the drivers, keeping the
+    /// other build artifacts
+    #[arg(long)]
+    pub packages_only: bool,
+}
+
+/// Arguments for the `trust-cert` subcommand
+#[derive(Debug, Args)]
+pub struct TrustCertArgs {
+    /// Path of the driver package containing the test certificate
+    #[arg(long, default_value = ".")]
+    pub package_dir: PathBuf,
+}
+
+/// Arguments for the `wdk-root` subcommand
+#[derive(Debug, Args)]
+pub struct WdkRootArgs {
+    /// Print the version of the detected WDK instead of its content root
+    #[arg(long)]
+    pub wdk_version: bool,
+}
+
 /// Subcommands
 #[derive(Debug, Subcommand)]
 pub enum Subcmd {
@@ -175,7 +1004,39 @@ pub enum Subcmd {
         name = "clean",
         about = "Clean build artifacts of the Windows Driver Kit project"
     )]
-    Clean,
+    Clean(CleanProjectArgs),
+    #[clap(
+        name = "sign",
+        about = "Re-sign an existing driver package with a different certificate"
+    )]
+    Sign(SignArgs),
+    #[clap(name = "diff", about = "Compare the files of two driver packages")]
+    Diff(DiffArgs),
+    #[clap(
+        name = "verify-package",
+        about = "Verify the files of a driver package against a golden manifest"
+    )]
+    VerifyPackage(VerifyPackageArgs),
+    #[clap(
+        name = "wdk-root",
+        about = "Print the WDK content root detected by cargo-wdk"
+    )]
+    WdkRoot(WdkRootArgs),
+    #[clap(
+        name = "trust-cert",
+        about = "Trust the test certificate of a driver package on this machine"
+    )]
+    TrustCert(TrustCertArgs),
+    #[clap(
+        name = "schema",
+        about = "Print the JSON schema of the driver metadata in Cargo.toml"
+    )]
+    Schema,
+    #[clap(
+        name = "setup",
+        about = "Install the Rust targets of drivers and check the other build prerequisites"
+    )]
+    Setup,
 }
 
 /// Top level command line interface for cargo wdk
@@ -194,22 +1055,96 @@ pub struct Cli {
     pub cargo_command: String,
     #[clap(subcommand)]
     pub sub_cmd: Subcmd,
+    /// Kill external commands, e.g. `cargo build` or `signtool`, that run for
+    /// longer than this number of seconds. Commands are not timed out by
+    /// default
+    #[arg(
+        long,
+        value_name = "SECS",
+        global = true,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help_heading = "Execution"
+    )]
+    pub command_timeout: Option<u64>,
     #[command(flatten)]
     #[clap(next_help_heading = "Verbosity")]
     pub verbose: Verbosity,
+    /// Coloring of the output of cargo-wdk and of the diagnostics of `cargo
+    /// build`. `auto` colors output written to a terminal unless the
+    /// `NO_COLOR` environment variable is set
+    #[arg(
+        long,
+        value_name = "WHEN",
+        value_enum,
+        default_value_t = ColorArg::Auto,
+        global = true,
+        help_heading = "Display"
+    )]
+    pub color: ColorArg,
 }
 
 impl Cli {
+    /// Parses the command line arguments, exiting with the usage on invalid
+    /// arguments. When stdin is a terminal, the driver type and the path of
+    /// the `new` subcommand may be omitted, in which case [`Cli::run`] prompts
+    /// for them.
+    pub fn parse_args() -> Self {
+        Self::try_parse().unwrap_or_else(|error| {
+            if error.kind() != ErrorKind::MissingRequiredArgument || !io::stdin().is_terminal() {
+                error.exit();
+            }
+            Self::command()
+                .mut_subcommand("new", |new| {
+                    new.mut_group("driver_type", |group| group.required(false))
+                        .mut_arg("path", |path| path.required(false))
+                })
+                .try_get_matches()
+                .ok()
+                .and_then(|matches| Self::from_arg_matches(&matches).ok())
+                .filter(|cli| matches!(cli.sub_cmd, Subcmd::New(_)))
+                .unwrap_or_else(|| error.exit())
+        })
+    }
+
+    /// Returns the color choice of the output, with `NO_COLOR` applied to
+    /// `--color=auto`
+    pub fn color(&self) -> ColorChoice {
+        ColorChoice::from(self.color).with_no_color_env()
+    }
+
+    /// Returns `true` if only the paths of the built artifacts are to be
+    /// printed, in which case other output is limited to errors
+    pub const fn prints_artifacts(&self) -> bool {
+        matches!(&self.sub_cmd, Subcmd::Build(args) if args.print_artifacts)
+    }
+
+    /// Returns the exit code of a failed run, `3` if no Rust packages were
+    /// found to build and `1` otherwise
+    pub fn exit_code(error: &anyhow::Error) -> ExitCode {
+        match error.downcast_ref::<BuildActionError>() {
+            Some(BuildActionError::NoRustPackagesFound(_)) => {
+                ExitCode::from(NO_RUST_PACKAGES_EXIT_CODE)
+            }
+            _ => ExitCode::FAILURE,
+        }
+    }
+
     /// Entry point method to construct and call actions based on the subcommand
     /// and arguments provided by the user.
     pub fn run(self) -> Result<()> {
         let wdk_build = WdkBuild::default();
-        let command_exec = CommandExec::default();
+        let command_exec = CommandExec::with_timeout(self.command_timeout.map(Duration::from_secs));
         let fs = Fs::default();
         let metadata = Metadata::default();
+        let color = self.color();
 
         match self.sub_cmd {
             Subcmd::New(cli_args) => {
+                let cli_args = if cli_args.has_driver_type() && cli_args.path.is_some() {
+                    cli_args
+                } else {
+                    wizard::complete_new_args(cli_args, &mut io::stdin().lock(), &mut io::stderr())?
+                };
                 // TODO: Support extended path as cargo supports it
                 if let Some(path) = &cli_args.path {
                     const EXTENDED_PATH_PREFIX: &str = r"\\?\";
@@ -226,9 +1161,19 @@ pub fn run(self) -> Result<()> {
                 }
 
                 NewAction::new(
-                    cli_args.path.as_ref().unwrap_or(&std::env::current_dir()?),
-                    cli_args.driver_type(),
-                    self.verbose,
+                    &NewActionParams {
+                        path: cli_args.path.as_ref().unwrap_or(&std::env::current_dir()?),
+                        driver_type: cli_args.driver_type(),
+                        logging: cli_args.logging.into(),
+                        panic: cli_args.panic.into(),
+                        version: cli_args.version.as_ref(),
+                        with_devcontainer: cli_args.with_devcontainer,
+                        with_integration_tests: cli_args.with_integration_tests,
+                        target_archs: &cli_args.target_arch,
+                        line_endings: cli_args.line_endings.into(),
+                        inf_encoding: cli_args.inf_encoding.into(),
+                        verbosity_level: self.verbose,
+                    },
                     &command_exec,
                     &fs,
                 )
@@ -236,18 +1181,67 @@ pub fn run(self) -> Result<()> {
                 Ok(())
             }
             Subcmd::Build(cli_args) => {
-                let sign_mode = cli_args.sign_mode()?;
+                cli_args.validate()?;
+                let target_arch = cli_args.target_arch();
+                let sign_mode = cli_args.sign_mode();
+                let source_date_epoch = cli_args.source_date_epoch()?;
+                let tool_paths = cli_args.tool_paths();
                 BuildAction::new(
                     &BuildActionParams {
                         working_dir: Path::new("."), // Using current dir as working dir
                         profile: cli_args.profile.as_ref(),
-                        target_arch: cli_args.target_arch,
+                        target_arch,
                         sign_mode,
+                        verify_signature: cli_args.verify_signature,
                         is_sample_class: cli_args.sample,
+                        driver_type: cli_args.driver_type,
                         locked: cli_args.locked,
+                        cargo_jobs: cli_args.cargo_jobs,
+                        deny_compile_warnings: cli_args.deny_compile_warnings,
                         target_platform: cli_args.target_platform.into(),
                         features: &cli_args.features,
                         verbosity_level: self.verbose,
+                        color,
+                        import_check: cli_args.import_check,
+                        os_version_check: cli_args.os_version_check,
+                        multi_arch_package: cli_args.multi_arch_package,
+                        wdk_lock_mode: cli_args.wdk_lock_mode(),
+                        allow_unsupported_wdk: cli_args.allow_unsupported_wdk,
+                        temp_dir: cli_args.temp_dir.as_deref(),
+                        no_package: cli_args.no_package,
+                        source_date_epoch,
+                        binary_path: cli_args.binary_path.as_deref(),
+                        emit_nupkg: cli_args.emit_nupkg,
+                        emit_index: cli_args.emit_index,
+                        include_map: cli_args.include_map,
+                        log_dir: cli_args.log_dir.as_deref(),
+                        diagnostic_bundle: cli_args.diagnostic_bundle.as_deref(),
+                        tool_paths: &tool_paths,
+                        relocatable: cli_args.relocatable,
+                        print_artifacts: cli_args.print_artifacts,
+                        clippy: cli_args.clippy,
+                        overwrite_package: cli_args.overwrite_package,
+                        pdb_altpath: cli_args.pdb_altpath.as_deref(),
+                        emit_install_summary: cli_args.emit_install_summary,
+                        wdf_versions: WdfVersionOverrides {
+                            kmdf: cli_args.kmdf_version,
+                            umdf: cli_args.umdf_version,
+                        },
+                        emit_catalog_signing_report: cli_args.emit_catalog_signing_report,
+                        emit_uninstall: cli_args.emit_uninstall,
+                        print_cargo_command: cli_args.print_cargo_command.map(Into::into),
+                        stamp_only: cli_args.stamp_only,
+                        binary_only: cli_args.binary_only,
+                        symbols_archive_dir: cli_args.emit_symbols_archive.as_deref(),
+                        no_hooks: cli_args.no_hooks,
+                        sanitizer: cli_args.sanitizer(),
+                        min_version: cli_args.min_version,
+                        cert_validity_days: cli_args.cert_validity_days,
+                        cert_from_env: cli_args.cert_from_env.as_deref(),
+                        cert_password_env: cli_args.cert_password_env.as_deref(),
+                        profile_overrides: &cli_args.profile_override,
+                        event_pipe: cli_args.event_pipe.as_deref(),
+                        checked: cli_args.checked,
                     },
                     &wdk_build,
                     &command_exec,
@@ -257,8 +1251,67 @@ pub fn run(self) -> Result<()> {
                 .run()?;
                 Ok(())
             }
-            Subcmd::Clean => {
-                CleanAction::new(Path::new("."), self.verbose, &command_exec, &fs)?.run()?;
+            Subcmd::Clean(cli_args) => {
+                CleanAction::new(
+                    &CleanActionParams {
+                        working_dir: &cli_args.cwd,
+                        profile: cli_args.profile.as_ref(),
+                        packages_only: cli_args.packages_only,
+                        verbosity_level: self.verbose,
+                    },
+                    &command_exec,
+                    &fs,
+                    &metadata,
+                )?
+                .run()?;
+                Ok(())
+            }
+            Subcmd::Sign(cli_args) => {
+                SignAction::new(
+                    &cli_args.package_dir,
+                    &cli_args.cert_thumbprint,
+                    &cli_args.cert_store,
+                    &command_exec,
+                    &fs,
+                )?
+                .run()?;
+                Ok(())
+            }
+            Subcmd::Diff(cli_args) => {
+                DiffAction::new(&cli_args.old_package_dir, &cli_args.new_package_dir, &fs)?
+                    .run()?;
+                Ok(())
+            }
+            Subcmd::VerifyPackage(cli_args) => {
+                VerifyPackageAction::new(
+                    &cli_args.package_dir,
+                    &cli_args.against,
+                    cli_args.update,
+                    &fs,
+                )?
+                .run()?;
+                Ok(())
+            }
+            Subcmd::WdkRoot(cli_args) => {
+                WdkRootAction::new(cli_args.wdk_version, &wdk_build).run()?;
+                Ok(())
+            }
+            Subcmd::TrustCert(cli_args) => {
+                TrustCertAction::new(
+                    &cli_args.package_dir,
+                    trust_cert::is_elevated()?,
+                    &command_exec,
+                    &fs,
+                )?
+                .run()?;
+                Ok(())
+            }
+            Subcmd::Schema => {
+                SchemaAction::run();
+                Ok(())
+            }
+            Subcmd::Setup => {
+                SetupAction::new(&command_exec, &wdk_build).run()?;
                 Ok(())
             }
         }
@@ -267,11 +1320,32 @@ pub fn run(self) -> Result<()> {
 
 #[cfg(test)]
 mod tests {
+    use clap::Parser;
     use clap_cargo::Features;
+    use wdk_build::CpuArchitecture;
 
     use crate::{
-        actions::DriverType,
-        cli::{BuildArgs, Cli, NewArgs, SignModeArg, Subcmd, TargetPlatformArg},
+        actions::{
+            DriverType,
+            Profile,
+            build::{PrintCargoCommand, Sanitizer, WdfVersion, WdkLockMode},
+        },
+        cli::{
+            BuildArgs,
+            CARGO_WDK_PROFILE_ENV_VAR,
+            Cli,
+            ColorArg,
+            InfEncodingArg,
+            LineEndingsArg,
+            LoggingArg,
+            NewArgs,
+            PanicArg,
+            SanitizerArg,
+            SignModeArg,
+            Subcmd,
+            TargetArchArg,
+            TargetPlatformArg,
+        },
     };
 
     #[test]
@@ -280,6 +1354,14 @@ fn new_args_driver_type_kmdf() {
             kmdf: true,
             umdf: false,
             wdm: false,
+            logging: LoggingArg::None,
+            panic: PanicArg::Abort,
+            version: None,
+            with_devcontainer: false,
+            with_integration_tests: false,
+            target_arch: vec![],
+            line_endings: LineEndingsArg::Auto,
+            inf_encoding: InfEncodingArg::Utf8,
             path: None,
         };
         assert_eq!(args.driver_type(), DriverType::Kmdf);
@@ -291,6 +1373,14 @@ fn new_args_driver_type_umdf() {
             kmdf: false,
             umdf: true,
             wdm: false,
+            logging: LoggingArg::None,
+            panic: PanicArg::Abort,
+            version: None,
+            with_devcontainer: false,
+            with_integration_tests: false,
+            target_arch: vec![],
+            line_endings: LineEndingsArg::Auto,
+            inf_encoding: InfEncodingArg::Utf8,
             path: None,
         };
         assert_eq!(args.driver_type(), DriverType::Umdf);
@@ -302,6 +1392,14 @@ fn new_args_driver_type_wdm() {
             kmdf: false,
             umdf: false,
             wdm: true,
+            logging: LoggingArg::None,
+            panic: PanicArg::Abort,
+            version: None,
+            with_devcontainer: false,
+            with_integration_tests: false,
+            target_arch: vec![],
+            line_endings: LineEndingsArg::Auto,
+            inf_encoding: InfEncodingArg::Utf8,
             path: None,
         };
         assert_eq!(args.driver_type(), DriverType::Wdm);
@@ -317,9 +1415,19 @@ fn verbatim_path_is_rejected() {
                 kmdf: true,
                 umdf: false,
                 wdm: false,
+                logging: LoggingArg::None,
+                panic: PanicArg::Abort,
+                version: None,
+                with_devcontainer: false,
+                with_integration_tests: false,
+                target_arch: vec![],
+                line_endings: LineEndingsArg::Auto,
+                inf_encoding: InfEncodingArg::Utf8,
                 path: Some(PathBuf::from(r"\\?\C:\some\path")),
             }),
+            command_timeout: None,
             verbose: clap_verbosity_flag::Verbosity::default(),
+            color: ColorArg::Auto,
         };
 
         let result = cli.run();
@@ -337,14 +1445,62 @@ fn build_rejects_verify_signature_when_sign_mode_is_off() {
             sub_cmd: Subcmd::Build(BuildArgs {
                 profile: None,
                 target_arch: None,
+                multi_arch_package: false,
                 verify_signature: true,
-                sign_mode: SignModeArg::Off,
+                sign_mode: Some(SignModeArg::Off),
                 sample: false,
+                driver_type: None,
                 target_platform: TargetPlatformArg::Universal,
                 locked: false,
+                cargo_jobs: None,
+                deny_compile_warnings: false,
+                clippy: false,
+                locked_wdk: false,
+                update_wdk_lock: false,
+                allow_unsupported_wdk: false,
                 features: Features::default(),
+                import_check: false,
+                os_version_check: false,
+                temp_dir: None,
+                log_dir: None,
+                diagnostic_bundle: None,
+                signtool_path: None,
+                certmgr_path: None,
+                stampinf_path: None,
+                inf2cat_path: None,
+                relocatable: false,
+                print_artifacts: false,
+                overwrite_package: false,
+                no_package: false,
+                reproducible: false,
+                source_date_epoch: None,
+                binary_path: None,
+                emit_nupkg: false,
+                emit_index: false,
+                emit_install_summary: false,
+                emit_catalog_signing_report: false,
+                emit_uninstall: false,
+                print_cargo_command: None,
+                stamp_only: false,
+                binary_only: false,
+                emit_symbols_archive: None,
+                no_hooks: false,
+                sanitizer: SanitizerArg::None,
+                min_version: None,
+                cert_validity_days: None,
+                cert_from_env: None,
+                cert_password_env: None,
+                profile_override: Vec::new(),
+                event_pipe: None,
+                checked: false,
+                include_map: None,
+                pdb_altpath: None,
+                kmdf_version: None,
+                umdf_version: None,
             }),
+            command_timeout: None,
             verbose: clap_verbosity_flag::Verbosity::default(),
+            color: ColorArg::Auto,
         };
 
         let result = cli.run();
@@ -372,4 +1528,722 @@ fn target_platform_arg_maps_to_target_platform() {
             TargetPlatform::WindowsDriver
         );
     }
+
+    fn parse_build_args(args: &[&str]) -> BuildArgs {
+        match Cli::try_parse_from(args)
+            .expect("build arguments should parse")
+            .sub_cmd
+        {
+            Subcmd::Build(build_args) => build_args,
+            _ => panic!("expected the build subcommand"),
+        }
+    }
+
+    #[test]
+    fn build_target_arch_native_resolves_to_the_detected_host_arch() {
+        let build_args = parse_build_args(&["cargo", "wdk", "build", "--target-arch", "Native"]);
+
+        assert_eq!(build_args.target_arch, Some(TargetArchArg::Native));
+        // No architecture is passed to the build action, which then detects
+        // the host architecture with `cargo rustc -- --print cfg`
+        assert_eq!(build_args.target_arch(), None);
+
+        let build_args = parse_build_args(&["cargo", "wdk", "build", "--target-arch", "arm64"]);
+
+        assert_eq!(build_args.target_arch(), Some(CpuArchitecture::Arm64));
+    }
+
+    #[test]
+    fn build_target_arch_native_is_rejected_with_binary_path() {
+        let build_args = parse_build_args(&[
+            "cargo",
+            "wdk",
+            "build",
+            "--binary-path",
+            "driver.sys",
+            "--target-arch",
+            "native",
+        ]);
+
+        assert_eq!(
+            build_args.validate().unwrap_err().to_string(),
+            "`--binary-path` requires an explicit `--target-arch` other than `native`."
+        );
+        assert!(Cli::try_parse_from(["cargo", "wdk", "build", "--target-arch", "x86"]).is_err());
+    }
+
+    fn parse_build_profile_with_env(
+        args: &[&str],
+        env_val: Option<&str>,
+    ) -> Result<Option<Profile>, String> {
+        crate::test_utils::with_env(&[(CARGO_WDK_PROFILE_ENV_VAR, env_val)], || {
+            Cli::try_parse_from(args)
+                .map_err(|e| e.to_string())
+                .map(|cli| match cli.sub_cmd {
+                    Subcmd::Build(build_args) => build_args.profile,
+                    _ => None,
+                })
+        })
+    }
+
+    #[test]
+    fn build_profile_is_read_from_env_var_when_flag_is_absent() {
+        let profile =
+            parse_build_profile_with_env(&["cargo", "wdk", "build"], Some("release")).unwrap();
+        assert_eq!(profile, Some(Profile::Release));
+    }
+
+    #[test]
+    fn build_profile_flag_takes_precedence_over_env_var() {
+        let profile = parse_build_profile_with_env(
+            &["cargo", "wdk", "build", "--profile", "dev"],
+            Some("release"),
+        )
+        .unwrap();
+        assert_eq!(profile, Some(Profile::Dev));
+    }
+
+    #[test]
+    fn build_profile_is_none_when_flag_and_env_var_are_absent() {
+        let profile = parse_build_profile_with_env(&["cargo", "wdk", "build"], None).unwrap();
+        assert_eq!(profile, None);
+    }
+
+    #[test]
+    fn build_profile_env_var_with_invalid_value_is_rejected() {
+        let env_error = parse_build_profile_with_env(&["cargo", "wdk", "build"], Some("not/valid"))
+            .unwrap_err();
+        let flag_error = parse_build_profile_with_env(
+            &["cargo", "wdk", "build", "--profile", "not/valid"],
+            None,
+        )
+        .unwrap_err();
+        assert!(env_error.contains("'not/valid' is not a valid profile"));
+        assert!(flag_error.contains("'not/valid' is not a valid profile"));
+    }
+
+    #[test]
+    fn sign_requires_cert_thumbprint_and_defaults_cert_store() {
+        assert!(Cli::try_parse_from(["cargo", "wdk", "sign", "driver_package"]).is_err());
+
+        let cli = Cli::try_parse_from([
+            "cargo",
+            "wdk",
+            "sign",
+            "driver_package",
+            "--cert-thumbprint",
+            "0123456789abcdef",
+        ])
+        .expect("sign arguments should parse");
+        let Subcmd::Sign(args) = cli.sub_cmd else {
+            panic!("expected the sign subcommand");
+        };
+        assert_eq!(args.package_dir, std::path::PathBuf::from("driver_package"));
+        assert_eq!(args.cert_thumbprint, "0123456789abcdef");
+        assert_eq!(args.cert_store, "My");
+    }
+
+    #[test]
+    fn verify_package_requires_a_golden_manifest() {
+        assert!(Cli::try_parse_from(["cargo", "wdk", "verify-package", "driver_package"]).is_err());
+
+        let cli = Cli::try_parse_from([
+            "cargo",
+            "wdk",
+            "verify-package",
+            "driver_package",
+            "--against",
+            "golden.json",
+            "--update",
+        ])
+        .expect("verify-package arguments should parse");
+        let Subcmd::VerifyPackage(args) = cli.sub_cmd else {
+            panic!("expected the verify-package subcommand");
+        };
+        assert_eq!(args.package_dir, std::path::PathBuf::from("driver_package"));
+        assert_eq!(args.against, std::path::PathBuf::from("golden.json"));
+        assert!(args.update);
+    }
+
+    #[test]
+    fn diff_requires_two_package_dirs() {
+        assert!(Cli::try_parse_from(["cargo", "wdk", "diff", "old_package"]).is_err());
+
+        let cli = Cli::try_parse_from(["cargo", "wdk", "diff", "old_package", "new_package"])
+            .expect("diff arguments should parse");
+        let Subcmd::Diff(args) = cli.sub_cmd else {
+            panic!("expected the diff subcommand");
+        };
+        assert_eq!(
+            args.old_package_dir,
+            std::path::PathBuf::from("old_package")
+        );
+        assert_eq!(
+            args.new_package_dir,
+            std::path::PathBuf::from("new_package")
+        );
+    }
+
+    #[test]
+    fn color_is_a_global_option_that_defaults_to_auto() {
+        let cli = Cli::try_parse_from(["cargo", "wdk", "build", "--color", "never"])
+            .expect("build arguments should parse");
+        assert_eq!(cli.color, ColorArg::Never);
+
+        let cli = Cli::try_parse_from(["cargo", "wdk", "--color=always", "clean"])
+            .expect("clean arguments should parse");
+        assert_eq!(cli.color, ColorArg::Always);
+
+        let cli = Cli::try_parse_from(["cargo", "wdk", "clean"]).expect("clean should parse");
+        assert_eq!(cli.color, ColorArg::Auto);
+
+        assert!(Cli::try_parse_from(["cargo", "wdk", "build", "--color", "sometimes"]).is_err());
+    }
+
+    #[test]
+    fn command_timeout_is_a_positive_number_of_seconds_on_any_subcommand() {
+        let cli = Cli::try_parse_from(["cargo", "wdk", "build", "--command-timeout", "600"])
+            .expect("build arguments should parse");
+        assert_eq!(cli.command_timeout, Some(600));
+
+        let cli = Cli::try_parse_from(["cargo", "wdk", "--command-timeout", "30", "clean"])
+            .expect("clean arguments should parse");
+        assert_eq!(cli.command_timeout, Some(30));
+
+        let cli = Cli::try_parse_from(["cargo", "wdk", "clean"]).expect("clean should parse");
+        assert_eq!(cli.command_timeout, None);
+
+        assert!(Cli::try_parse_from(["cargo", "wdk", "build", "--command-timeout", "0"]).is_err());
+    }
+
+    #[test]
+    fn trust_cert_package_dir_defaults_to_the_current_dir() {
+        let cli = Cli::try_parse_from(["cargo", "wdk", "trust-cert"])
+            .expect("trust-cert arguments should parse");
+        let Subcmd::TrustCert(args) = cli.sub_cmd else {
+            panic!("expected the trust-cert subcommand");
+        };
+        assert_eq!(args.package_dir, std::path::PathBuf::from("."));
+
+        let cli = Cli::try_parse_from([
+            "cargo",
+            "wdk",
+            "trust-cert",
+            "--package-dir",
+            "driver_package",
+        ])
+        .expect("trust-cert arguments should parse");
+        let Subcmd::TrustCert(args) = cli.sub_cmd else {
+            panic!("expected the trust-cert subcommand");
+        };
+        assert_eq!(args.package_dir, std::path::PathBuf::from("driver_package"));
+    }
+
+    #[test]
+    fn clean_parses_the_cwd_profile_and_packages_only_flags() {
+        let cli = Cli::try_parse_from(["cargo", "wdk", "clean"]).expect("clean should parse");
+        let Subcmd::Clean(args) = cli.sub_cmd else {
+            panic!("expected the clean subcommand");
+        };
+        assert_eq!(args.cwd, std::path::PathBuf::from("."));
+        assert_eq!(args.profile, None);
+        assert!(!args.packages_only);
+
+        let cli = Cli::try_parse_from([
+            "cargo",
+            "wdk",
+            "clean",
+            "--cwd",
+            "drivers",
+            "--profile",
+            "Release",
+            "--packages-only",
+        ])
+        .expect("clean should parse");
+        let Subcmd::Clean(args) = cli.sub_cmd else {
+            panic!("expected the clean subcommand");
+        };
+        assert_eq!(args.cwd, std::path::PathBuf::from("drivers"));
+        assert_eq!(args.profile, Some(Profile::Release));
+        assert!(args.packages_only);
+    }
+
+    #[test]
+    fn wdk_root_parses_the_wdk_version_flag() {
+        let cli = Cli::try_parse_from(["cargo", "wdk", "wdk-root"])
+            .expect("wdk-root arguments should parse");
+        let Subcmd::WdkRoot(args) = cli.sub_cmd else {
+            panic!("expected the wdk-root subcommand");
+        };
+        assert!(!args.wdk_version);
+
+        let cli = Cli::try_parse_from(["cargo", "wdk", "wdk-root", "--wdk-version"])
+            .expect("wdk-root arguments should parse");
+        let Subcmd::WdkRoot(args) = cli.sub_cmd else {
+            panic!("expected the wdk-root subcommand");
+        };
+        assert!(args.wdk_version);
+    }
+
+    #[test]
+    fn build_wdf_version_flags_are_parsed_as_major_dot_minor() {
+        let build_args = parse_build_args(&[
+            "cargo",
+            "wdk",
+            "build",
+            "--kmdf-version",
+            "1.31",
+            "--umdf-version",
+            "2.15",
+        ]);
+
+        assert_eq!(
+            build_args.kmdf_version,
+            Some(WdfVersion {
+                major: 1,
+                minor: 31
+            })
+        );
+        assert_eq!(
+            build_args.umdf_version,
+            Some(WdfVersion {
+                major: 2,
+                minor: 15
+            })
+        );
+        assert!(Cli::try_parse_from(["cargo", "wdk", "build", "--kmdf-version", "1"]).is_err());
+    }
+
+    #[test]
+    fn build_wdk_lock_flags_map_to_wdk_lock_mode() {
+        let cases = [
+            (vec![], WdkLockMode::Off),
+            (vec!["--locked-wdk"], WdkLockMode::Locked),
+            (vec!["--update-wdk-lock"], WdkLockMode::Update),
+        ];
+        for (flags, expected_mode) in cases {
+            let cli = Cli::try_parse_from(["cargo", "wdk", "build"].into_iter().chain(flags))
+                .expect("build arguments should parse");
+            let Subcmd::Build(args) = cli.sub_cmd else {
+                panic!("expected the build subcommand");
+            };
+            assert_eq!(args.wdk_lock_mode(), expected_mode);
+        }
+
+        assert!(
+            Cli::try_parse_from(["cargo", "wdk", "build", "--locked-wdk", "--update-wdk-lock"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn build_no_package_conflicts_with_package_only_flags() {
+        let cli = Cli::try_parse_from(["cargo", "wdk", "build", "--no-package"])
+            .expect("build arguments should parse");
+        let Subcmd::Build(args) = cli.sub_cmd else {
+            panic!("expected the build subcommand");
+        };
+        assert!(args.no_package);
+
+        for flag in ["--verify-signature", "--import-check"] {
+            assert!(Cli::try_parse_from(["cargo", "wdk", "build", "--no-package", flag]).is_err());
+        }
+    }
+
+    #[test]
+    fn build_stamp_only_conflicts_with_flags_that_build_the_drivers() {
+        let cli = Cli::try_parse_from(["cargo", "wdk", "build", "--stamp-only"])
+            .expect("build arguments should parse");
+        let Subcmd::Build(args) = cli.sub_cmd else {
+            panic!("expected the build subcommand");
+        };
+        assert!(args.stamp_only);
+
+        for flags in [
+            vec!["--no-package"],
+            vec!["--multi-arch-package"],
+            vec!["--clippy"],
+            vec!["--print-cargo-command"],
+            vec!["--binary-path", "driver.sys", "--target-arch", "arm64"],
+        ] {
+            let args = ["cargo", "wdk", "build", "--stamp-only"]
+                .into_iter()
+                .chain(flags.iter().copied());
+            assert!(Cli::try_parse_from(args).is_err(), "{flags:?}");
+        }
+    }
+
+    #[test]
+    fn build_binary_only_conflicts_with_flags_that_package_the_inf() {
+        let cli = Cli::try_parse_from(["cargo", "wdk", "build", "--binary-only"])
+            .expect("build arguments should parse");
+        let Subcmd::Build(args) = cli.sub_cmd else {
+            panic!("expected the build subcommand");
+        };
+        assert!(args.binary_only);
+
+        for flags in [
+            vec!["--no-package"],
+            vec!["--multi-arch-package"],
+            vec!["--stamp-only"],
+            vec!["--relocatable"],
+            vec!["--emit-nupkg"],
+            vec!["--emit-install-summary"],
+            vec!["--emit-catalog-signing-report"],
+            vec!["--emit-uninstall"],
+            vec!["--binary-path", "driver.sys", "--target-arch", "arm64"],
+        ] {
+            let args = ["cargo", "wdk", "build", "--binary-only"]
+                .into_iter()
+                .chain(flags.iter().copied());
+            assert!(Cli::try_parse_from(args).is_err(), "{flags:?}");
+        }
+    }
+
+    #[test]
+    fn build_validate_rejects_sanitizers_that_cannot_be_applied() {
+        for (flags, expected_error) in [
+            (
+                vec!["--sanitizer", "undefined"],
+                "`--sanitizer=undefined` is not supported since rustc has no \
+                 UndefinedBehaviorSanitizer. Undefined behavior of unsafe code can be found with \
+                 Miri, and `overflow-checks` catch integer overflows.",
+            ),
+            (
+                vec!["--sanitizer", "address", "--driver-type", "kmdf"],
+                "`--sanitizer` cannot be used with `--driver-type=kmdf`, since kernel-mode \
+                 drivers cannot be built with a sanitizer.",
+            ),
+            (
+                vec!["--sanitizer", "address", "--target-arch", "arm64"],
+                "`--sanitizer` cannot be used with `--target-arch=arm64`, since rustc supports \
+                 AddressSanitizer only for amd64 Windows targets.",
+            ),
+        ] {
+            let build_args = parse_build_args(
+                &["cargo", "wdk", "build"]
+                    .into_iter()
+                    .chain(flags)
+                    .collect::<Vec<_>>(),
+            );
+            assert_eq!(
+                build_args.validate().unwrap_err().to_string(),
+                expected_error
+            );
+        }
+
+        for (flags, expected_sanitizer) in [
+            (vec![], None),
+            (vec!["--sanitizer", "none"], None),
+            (vec!["--sanitizer", "address"], Some(Sanitizer::Address)),
+            (
+                vec!["--sanitizer", "address", "--driver-type", "umdf"],
+                Some(Sanitizer::Address),
+            ),
+            (
+                vec!["--sanitizer", "address", "--target-arch", "amd64"],
+                Some(Sanitizer::Address),
+            ),
+        ] {
+            let build_args = parse_build_args(
+                &["cargo", "wdk", "build"]
+                    .into_iter()
+                    .chain(flags)
+                    .collect::<Vec<_>>(),
+            );
+            assert!(build_args.validate().is_ok());
+            assert_eq!(build_args.sanitizer(), expected_sanitizer);
+        }
+        assert!(
+            Cli::try_parse_from(["cargo", "wdk", "build", "--sanitizer", "address", "--stamp-only"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn build_cert_password_env_requires_cert_from_env() {
+        let build_args = parse_build_args(&[
+            "cargo",
+            "wdk",
+            "build",
+            "--cert-from-env",
+            "SIGNING_PFX",
+            "--cert-password-env",
+            "SIGNING_PFX_PASSWORD",
+        ]);
+        assert_eq!(build_args.cert_from_env.as_deref(), Some("SIGNING_PFX"));
+        assert_eq!(
+            build_args.cert_password_env.as_deref(),
+            Some("SIGNING_PFX_PASSWORD")
+        );
+
+        for flags in [
+            vec!["--cert-password-env", "SIGNING_PFX_PASSWORD"],
+            vec!["--cert-from-env", "SIGNING_PFX", "--cert-validity-days", "30"],
+            vec!["--cert-from-env", "SIGNING_PFX", "--no-package"],
+        ] {
+            let args = ["cargo", "wdk", "build"]
+                .into_iter()
+                .chain(flags.iter().copied());
+            assert!(Cli::try_parse_from(args).is_err(), "{flags:?}");
+        }
+    }
+
+    #[test]
+    fn build_profile_override_is_repeatable_and_validated() {
+        let build_args = parse_build_args(&[
+            "cargo",
+            "wdk",
+            "build",
+            "--profile-override",
+            "opt-level=3",
+            "--profile-override",
+            "debug-assertions=true",
+        ]);
+        assert_eq!(
+            build_args
+                .profile_override
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["opt-level=3", "debug-assertions=true"]
+        );
+
+        for flags in [
+            vec!["--profile-override", "opt-levels=3"],
+            vec!["--profile-override", "opt-level=fast"],
+            vec!["--profile-override", "opt-level=3", "--stamp-only"],
+        ] {
+            let args = ["cargo", "wdk", "build"]
+                .into_iter()
+                .chain(flags.iter().copied());
+            assert!(Cli::try_parse_from(args).is_err(), "{flags:?}");
+        }
+    }
+
+    #[test]
+    fn build_checked_conflicts_with_flags_that_do_not_build_a_single_arch_package() {
+        assert!(parse_build_args(&["cargo", "wdk", "build", "--checked"]).checked);
+
+        for flags in [
+            vec!["--no-package"],
+            vec!["--multi-arch-package"],
+            vec!["--stamp-only"],
+            vec!["--binary-path", "driver.sys", "--target-arch", "arm64"],
+        ] {
+            let args = ["cargo", "wdk", "build", "--checked"]
+                .into_iter()
+                .chain(flags.iter().copied());
+            assert!(Cli::try_parse_from(args).is_err(), "{flags:?}");
+        }
+    }
+
+    #[test]
+    fn build_validate_rejects_flags_conflicting_for_their_values() {
+        for (flags, expected_error) in [
+            (
+                vec!["--sign-mode", "off", "--verify-signature"],
+                "`--verify-signature` cannot be used with `--sign-mode=off`.",
+            ),
+            (
+                vec!["--sign-mode", "off", "--emit-catalog-signing-report"],
+                "`--emit-catalog-signing-report` cannot be used with `--sign-mode=off`.",
+            ),
+            (
+                vec!["--sign-mode", "off", "--cert-from-env", "SIGNING_PFX"],
+                "`--cert-from-env` cannot be used with `--sign-mode=off`.",
+            ),
+            (
+                vec!["--driver-type", "umdf", "--kmdf-version", "1.33"],
+                "`--kmdf-version` cannot be used with `--driver-type=umdf`.",
+            ),
+            (
+                vec!["--driver-type", "wdm", "--umdf-version", "2.33"],
+                "`--umdf-version` cannot be used with `--driver-type=wdm`.",
+            ),
+        ] {
+            let build_args = parse_build_args(
+                &["cargo", "wdk", "build"]
+                    .into_iter()
+                    .chain(flags)
+                    .collect::<Vec<_>>(),
+            );
+            assert_eq!(
+                build_args.validate().unwrap_err().to_string(),
+                expected_error
+            );
+        }
+
+        for flags in [
+            vec!["--sign-mode", "test", "--verify-signature"],
+            vec!["--emit-catalog-signing-report"],
+            vec!["--driver-type", "kmdf", "--kmdf-version", "1.33"],
+            vec!["--kmdf-version", "1.33", "--umdf-version", "2.33"],
+        ] {
+            let build_args = parse_build_args(
+                &["cargo", "wdk", "build"]
+                    .into_iter()
+                    .chain(flags)
+                    .collect::<Vec<_>>(),
+            );
+            assert!(build_args.validate().is_ok());
+        }
+    }
+
+    #[test]
+    fn build_reproducible_requires_source_date_epoch() {
+        let parse = |args: &[&str], env_value: Option<&str>| {
+            crate::test_utils::with_env(&[(SOURCE_DATE_EPOCH_ENV_VAR, env_value)], || {
+                let cli = Cli::try_parse_from(
+                    ["cargo", "wdk", "build"]
+                        .into_iter()
+                        .chain(args.iter().copied()),
+                )
+                .expect("build arguments should parse");
+                let Subcmd::Build(args) = cli.sub_cmd else {
+                    panic!("expected the build subcommand");
+                };
+                args.source_date_epoch().map_err(|e| e.to_string())
+            })
+        };
+
+        assert_eq!(parse(&[], Some("1700000000")), Ok(None));
+        assert_eq!(
+            parse(&["--reproducible"], Some("1700000000")),
+            Ok(Some(1_700_000_000))
+        );
+        assert_eq!(
+            parse(
+                &["--reproducible", "--source-date-epoch", "42"],
+                Some("1700000000")
+            ),
+            Ok(Some(42))
+        );
+        assert_eq!(
+            parse(&["--reproducible"], None),
+            Err(
+                "`--reproducible` requires `--source-date-epoch` or the `SOURCE_DATE_EPOCH` \
+                 environment variable."
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn build_include_map_takes_a_bool_value() {
+        for (flags, expected_include_map) in [
+            (vec![], None),
+            (vec!["--include-map", "false"], Some(false)),
+            (vec!["--include-map", "true"], Some(true)),
+        ] {
+            let cli = Cli::try_parse_from(["cargo", "wdk", "build"].into_iter().chain(flags))
+                .expect("build arguments should parse");
+            let Subcmd::Build(args) = cli.sub_cmd else {
+                panic!("expected the build subcommand");
+            };
+            assert_eq!(args.include_map, expected_include_map);
+        }
+
+        assert!(Cli::try_parse_from(["cargo", "wdk", "build", "--include-map", "no"]).is_err());
+    }
+
+    #[test]
+    fn build_pdb_altpath_defaults_to_the_pdb_file_name() {
+        for (flags, expected_pdb_altpath) in [
+            (vec![], None),
+            (vec!["--pdb-altpath"], Some("%_PDB%")),
+            (vec!["--pdb-altpath=driver.pdb"], Some("driver.pdb")),
+        ] {
+            let cli = Cli::try_parse_from(["cargo", "wdk", "build"].into_iter().chain(flags))
+                .expect("build arguments should parse");
+            let Subcmd::Build(args) = cli.sub_cmd else {
+                panic!("expected the build subcommand");
+            };
+            assert_eq!(args.pdb_altpath.as_deref(), expected_pdb_altpath);
+        }
+
+        assert!(
+            Cli::try_parse_from(["cargo", "wdk", "build", "--pdb-altpath=C:/my symbols/a.pdb"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn build_print_cargo_command_runs_the_commands_unless_only_is_passed() {
+        for (flags, expected_print_cargo_command) in [
+            (vec![], None),
+            (
+                vec!["--print-cargo-command"],
+                Some(PrintCargoCommand::AndRun),
+            ),
+            (
+                vec!["--print-cargo-command=run"],
+                Some(PrintCargoCommand::AndRun),
+            ),
+            (
+                vec!["--print-cargo-command=only"],
+                Some(PrintCargoCommand::Only),
+            ),
+        ] {
+            let cli = Cli::try_parse_from(["cargo", "wdk", "build"].into_iter().chain(flags))
+                .expect("build arguments should parse");
+            let Subcmd::Build(args) = cli.sub_cmd else {
+                panic!("expected the build subcommand");
+            };
+            assert_eq!(
+                args.print_cargo_command.map(PrintCargoCommand::from),
+                expected_print_cargo_command
+            );
+        }
+
+        assert!(
+            Cli::try_parse_from(["cargo", "wdk", "build", "--print-cargo-command=never"]).is_err()
+        );
+    }
+
+    #[test]
+    fn new_version_must_be_semver() {
+        let parse_version = |version: &str| {
+            Cli::try_parse_from([
+                "cargo",
+                "wdk",
+                "new",
+                "--kmdf",
+                "--version",
+                version,
+                "driver",
+            ])
+            .map(|cli| match cli.sub_cmd {
+                Subcmd::New(new_args) => new_args.version,
+                _ => None,
+            })
+        };
+        assert_eq!(parse_version("1.2.3").unwrap(), Some(Version::new(1, 2, 3)));
+        assert!(parse_version("1.2").is_err());
+    }
+
+    #[test]
+    fn new_line_endings_and_inf_encoding_default_to_auto_and_utf8() {
+        let parse_new_args = |flags: &[&str]| {
+            let cli = Cli::try_parse_from(
+                ["cargo", "wdk", "new", "--kmdf", "driver"]
+                    .into_iter()
+                    .chain(flags.iter().copied()),
+            )
+            .expect("new arguments should parse");
+            let Subcmd::New(args) = cli.sub_cmd else {
+                panic!("expected the new subcommand");
+            };
+            (args.line_endings, args.inf_encoding)
+        };
+
+        assert_eq!(
+            parse_new_args(&[]),
+            (LineEndingsArg::Auto, InfEncodingArg::Utf8)
+        );
+        assert_eq!(
+            parse_new_args(&["--line-endings", "CRLF", "--inf-encoding", "utf8-bom"]),
+            (LineEndingsArg::Crlf, InfEncodingArg::Utf8Bom)
+        );
+    }
 }
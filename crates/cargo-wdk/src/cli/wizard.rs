@@ -0,0 +1,214 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+//! This module implements the interactive prompt of the `new` subcommand. It
+//! asks for the arguments that were not passed on the command line when
+//! `cargo wdk new` is run from a terminal without a driver type.
+
+use std::{
+    io::{BufRead, Write},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use anyhow::{Result, bail};
+use clap::ValueEnum;
+use wdk_build::CpuArchitecture;
+
+use super::{LoggingArg, NewArgs};
+use crate::actions::DriverType;
+
+/// Prompts for the driver type, the path, the target architectures and the
+/// logging framework of the new driver crate, skipping the ones that were
+/// passed on the command line.
+///
+/// # Arguments
+///
+/// * `args` - The arguments of the `new` subcommand parsed from the command
+///   line
+/// * `input` - Reader the answers are read from, usually stdin
+/// * `output` - Writer the questions are written to, usually stderr
+///
+/// # Errors
+///
+/// * `anyhow::Error` - If the input ends before all questions are answered, or
+///   if reading the input or writing the output fails.
+pub fn complete_new_args(
+    mut args: NewArgs,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> Result<NewArgs> {
+    if !args.has_driver_type() {
+        let driver_type = prompt(
+            input,
+            output,
+            "Driver type (kmdf, umdf, wdm)",
+            None,
+            DriverType::from_str,
+        )?;
+        args.kmdf = driver_type == DriverType::Kmdf;
+        args.umdf = driver_type == DriverType::Umdf;
+        args.wdm = driver_type == DriverType::Wdm;
+    }
+    if args.path.is_none() {
+        let path = prompt(input, output, "Driver crate name", None, |answer| {
+            if answer.is_empty() {
+                Err("The driver crate name cannot be empty".to_string())
+            } else {
+                Ok(PathBuf::from(answer))
+            }
+        })?;
+        args.path = Some(path);
+    }
+    if args.target_arch.is_empty() {
+        args.target_arch = prompt(
+            input,
+            output,
+            "Target architectures, comma separated (amd64, arm64)",
+            Some(""),
+            |answer| {
+                answer
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|arch| !arch.is_empty())
+                    .map(CpuArchitecture::from_str)
+                    .collect()
+            },
+        )?;
+    }
+    if args.logging == LoggingArg::None {
+        args.logging = prompt(
+            input,
+            output,
+            "Logging (none, wpp, tracing)",
+            Some("none"),
+            |answer| LoggingArg::from_str(answer, true),
+        )?;
+    }
+    Ok(args)
+}
+
+/// Asks `question` until the answer is accepted by `parse`. An empty answer
+/// is replaced by `default` if there is one.
+fn prompt<T>(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    question: &str,
+    default: Option<&str>,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> Result<T> {
+    loop {
+        match default {
+            Some(default) if !default.is_empty() => write!(output, "{question} [{default}]: ")?,
+            _ => write!(output, "{question}: ")?,
+        }
+        output.flush()?;
+
+        let mut answer = String::new();
+        if input.read_line(&mut answer)? == 0 {
+            bail!("No answer was given for: {question}");
+        }
+        let answer = match (answer.trim(), default) {
+            ("", Some(default)) => default,
+            (answer, _) => answer,
+        };
+        match parse(answer) {
+            Ok(value) => return Ok(value),
+            Err(e) => writeln!(output, "{e}")?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, path::PathBuf};
+
+    use wdk_build::CpuArchitecture;
+
+    use super::complete_new_args;
+    use crate::cli::{InfEncodingArg, LineEndingsArg, LoggingArg, NewArgs, PanicArg};
+
+    fn new_args() -> NewArgs {
+        NewArgs {
+            kmdf: false,
+            umdf: false,
+            wdm: false,
+            logging: LoggingArg::None,
+            panic: PanicArg::Abort,
+            version: None,
+            with_devcontainer: false,
+            with_integration_tests: false,
+            target_arch: vec![],
+            line_endings: LineEndingsArg::Auto,
+            inf_encoding: InfEncodingArg::Utf8,
+            path: None,
+        }
+    }
+
+    #[test]
+    fn every_missing_argument_is_prompted_for() {
+        let mut input = Cursor::new("umdf\nmy_driver\namd64, ARM64\nwpp\n");
+        let mut output = Vec::new();
+
+        let args = complete_new_args(new_args(), &mut input, &mut output).unwrap();
+
+        assert!(args.umdf && !args.kmdf && !args.wdm);
+        assert_eq!(args.path, Some(PathBuf::from("my_driver")));
+        assert_eq!(
+            args.target_arch,
+            vec![CpuArchitecture::Amd64, CpuArchitecture::Arm64]
+        );
+        assert_eq!(args.logging, LoggingArg::Wpp);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Driver type (kmdf, umdf, wdm): Driver crate name: Target architectures, comma \
+             separated (amd64, arm64): Logging (none, wpp, tracing) [none]: "
+        );
+    }
+
+    #[test]
+    fn invalid_answers_are_asked_again_and_empty_answers_use_the_default() {
+        let mut input = Cursor::new("kmdf2\nkmdf\n\nmy_driver\n\n\n");
+        let mut output = Vec::new();
+
+        let args = complete_new_args(new_args(), &mut input, &mut output).unwrap();
+
+        assert!(args.kmdf);
+        assert_eq!(args.path, Some(PathBuf::from("my_driver")));
+        assert!(args.target_arch.is_empty());
+        assert_eq!(args.logging, LoggingArg::None);
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("'kmdf2' is not a valid driver type"));
+        assert!(output.contains("The driver crate name cannot be empty"));
+    }
+
+    #[test]
+    fn arguments_passed_on_the_command_line_are_not_prompted_for() {
+        let args = NewArgs {
+            wdm: true,
+            logging: LoggingArg::Tracing,
+            target_arch: vec![CpuArchitecture::Arm64],
+            ..new_args()
+        };
+        let mut input = Cursor::new("my_driver\n");
+        let mut output = Vec::new();
+
+        let args = complete_new_args(args, &mut input, &mut output).unwrap();
+
+        assert!(args.wdm);
+        assert_eq!(args.path, Some(PathBuf::from("my_driver")));
+        assert_eq!(String::from_utf8(output).unwrap(), "Driver crate name: ");
+    }
+
+    #[test]
+    fn end_of_input_is_an_error() {
+        let mut input = Cursor::new("kmdf\n");
+        let mut output = Vec::new();
+
+        let result = complete_new_args(new_args(), &mut input, &mut output);
+
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "No answer was given for: Driver crate name"
+        );
+    }
+}
@@ -13,7 +13,6 @@
 
 use std::process::ExitCode;
 
-use clap::Parser;
 use cli::Cli;
 use tracing::error;
 
@@ -29,13 +28,14 @@
 /// # Returns
 ///
 /// * [`ExitCode::SUCCESS`] on success,
-/// * [`ExitCode::FAILURE`] on error.
+/// * `3` if no Rust packages were found to build,
+/// * [`ExitCode::FAILURE`] on any other error.
 fn main() -> ExitCode {
-    let cli: Cli = Cli::parse();
-    trace::init_tracing(cli.verbose);
+    let cli: Cli = Cli::parse_args();
+    trace::init_tracing(cli.verbose, cli.prints_artifacts(), cli.color());
     if let Err(e) = cli.run() {
         error!("{e:#}");
-        return ExitCode::FAILURE;
+        return Cli::exit_code(&e);
     }
     ExitCode::SUCCESS
 }
@@ -14,8 +14,11 @@
 
 use std::{
     collections::HashMap,
+    io::Read,
     path::Path,
-    process::{Command, Output, Stdio},
+    process::{Child, Command, Output, Stdio},
+    thread,
+    time::{Duration, Instant},
 };
 
 use anyhow::Result;
@@ -24,9 +27,100 @@
 
 use super::error::CommandError;
 
+/// Interval at which a command with a timeout is polled for its exit
+const TIMEOUT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Flags whose values are secret, e.g. the password of the PFX file that
+/// `signtool sign /f` signs with
+const SECRET_VALUE_FLAGS: [&str; 1] = ["/p"];
+/// Replacement of the secret values in logged command lines
+const REDACTED: &str = "<redacted>";
+
+/// Returns `args` with the values of secret flags replaced, so that the
+/// command line can be logged or reported in errors
+pub fn redact_args<'a>(args: &[&'a str]) -> Vec<&'a str> {
+    let mut redacted = Vec::with_capacity(args.len());
+    let mut is_secret = false;
+    for arg in args {
+        redacted.push(if is_secret { REDACTED } else { *arg });
+        is_secret = SECRET_VALUE_FLAGS.contains(&arg.to_ascii_lowercase().as_str());
+    }
+    redacted
+}
+
 /// Provides limited access to `std::process::Command` methods
 #[derive(Debug, Default)]
-pub struct CommandExec {}
+pub struct CommandExec {
+    /// Time after which a running command is killed. Commands run until they
+    /// exit if this is `None`.
+    timeout: Option<Duration>,
+}
+
+impl CommandExec {
+    /// Creates a `CommandExec` that kills commands that run for longer than
+    /// the given timeout
+    pub const fn with_timeout(timeout: Option<Duration>) -> Self {
+        Self { timeout }
+    }
+
+    /// Waits for the child to exit and collects its stdout, killing it if it
+    /// is still running when the timeout expires
+    fn wait_with_timeout(
+        mut child: Child,
+        timeout: Duration,
+        command: &str,
+        args: &[&str],
+    ) -> Result<Output, CommandError> {
+        // stdout is read on a separate thread so that a child filling the pipe
+        // does not block while being polled
+        let stdout_reader = child.stdout.take().map(|mut stdout| {
+            thread::spawn(move || {
+                let mut buffer = Vec::new();
+                stdout.read_to_end(&mut buffer).map(|_| buffer)
+            })
+        });
+
+        let deadline = Instant::now() + timeout;
+        let status = loop {
+            if let Some(status) = child
+                .try_wait()
+                .map_err(|e| CommandError::from_io_error(command, args, e))?
+            {
+                break status;
+            }
+            if Instant::now() >= deadline {
+                debug!("Killing {command} after {timeout:?}");
+                child
+                    .kill()
+                    .and_then(|()| child.wait())
+                    .map_err(|e| CommandError::from_io_error(command, args, e))?;
+                return Err(CommandError::from_timeout(command, args, timeout));
+            }
+            thread::sleep(TIMEOUT_POLL_INTERVAL);
+        };
+
+        let stdout = match stdout_reader {
+            Some(reader) => reader
+                .join()
+                .unwrap_or_else(|_| Ok(Vec::new()))
+                .map_err(|e| CommandError::from_io_error(command, args, e))?,
+            None => Vec::new(),
+        };
+        Ok(Output {
+            status,
+            stdout,
+            stderr: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+impl MockCommandExec {
+    /// Mirrors `CommandExec::with_timeout` so that the mock can be constructed
+    /// the same way. The timeout has no effect on the expectations of the mock.
+    pub fn with_timeout(_timeout: Option<Duration>) -> Self {
+        Self::default()
+    }
+}
 
 #[automock]
 impl CommandExec {
@@ -40,7 +134,7 @@ pub fn run<'a>(
         env_vars: Option<&'a HashMap<&'a str, &'a str>>,
         working_dir: Option<&'a Path>,
     ) -> Result<Output, CommandError> {
-        debug!("Running: {} {:?}", command, args);
+        debug!("Running: {} {:?}", command, redact_args(args));
 
         let mut cmd = Command::new(command);
         cmd.args(args);
@@ -55,11 +149,16 @@ pub fn run<'a>(
             cmd.current_dir(working_dir);
         }
 
-        let output = cmd
+        let child = cmd
             .stdout(Stdio::piped())
             .spawn()
-            .and_then(std::process::Child::wait_with_output)
             .map_err(|e| CommandError::from_io_error(command, args, e))?;
+        let output = match self.timeout {
+            Some(timeout) => Self::wait_with_timeout(child, timeout, command, args)?,
+            None => child
+                .wait_with_output()
+                .map_err(|e| CommandError::from_io_error(command, args, e))?,
+        };
 
         if !output.status.success() {
             return Err(CommandError::from_output(command, args, &output));
@@ -68,10 +167,49 @@ pub fn run<'a>(
         debug!(
             "COMMAND: {}\n ARGS:{:?}\n OUTPUT: {}\n",
             command,
-            args,
+            redact_args(args),
             String::from_utf8_lossy(&output.stdout)
         );
 
         Ok(output)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{CommandExec, redact_args};
+    use crate::providers::error::CommandError;
+
+    #[test]
+    fn values_of_secret_flags_are_redacted() {
+        assert_eq!(
+            redact_args(&["sign", "/f", "C:/cert.pfx", "/P", "secret", "/fd", "SHA256"]),
+            ["sign", "/f", "C:/cert.pfx", "/P", "<redacted>", "/fd", "SHA256"]
+        );
+        assert_eq!(redact_args(&["build", "-p", "driver"]), ["build", "-p", "driver"]);
+    }
+
+    #[test]
+    fn commands_running_longer_than_the_timeout_are_killed() {
+        let command_exec = CommandExec::with_timeout(Some(Duration::from_secs(1)));
+        let start = Instant::now();
+        let result = command_exec.run("ping", &["-n", "30", "127.0.0.1"], None, None);
+        assert!(matches!(
+            result,
+            Err(CommandError::Timeout(command, _, timeout))
+                if command == "ping" && timeout == Duration::from_secs(1)
+        ));
+        assert!(start.elapsed() < Duration::from_secs(20));
+    }
+
+    #[test]
+    fn commands_finishing_within_the_timeout_return_their_output() {
+        let command_exec = CommandExec::with_timeout(Some(Duration::from_secs(30)));
+        let output = command_exec
+            .run("cmd", &["/C", "echo cargo-wdk"], None, None)
+            .expect("echo should finish within the timeout");
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "cargo-wdk");
+    }
+}
@@ -11,7 +11,17 @@
 #![allow(clippy::unused_self)]
 
 use std::{
-    fs::{File, OpenOptions, copy, create_dir, create_dir_all, read_dir, rename},
+    fs::{
+        File,
+        OpenOptions,
+        copy,
+        create_dir,
+        create_dir_all,
+        read_dir,
+        remove_dir_all,
+        remove_file,
+        rename,
+    },
     io::{Read, Write},
     path::{Path, PathBuf},
 };
@@ -78,6 +88,14 @@ pub fn rename(&self, src: &Path, dest: &Path) -> Result<(), FileError> {
         rename(src, dest).map_err(|e| FileError::RenameError(src.to_owned(), dest.to_owned(), e))
     }
 
+    pub fn remove_file(&self, path: &Path) -> Result<(), FileError> {
+        remove_file(path).map_err(|e| FileError::RemoveError(path.to_owned(), e))
+    }
+
+    pub fn remove_dir_all(&self, path: &Path) -> Result<(), FileError> {
+        remove_dir_all(path).map_err(|e| FileError::RemoveDirError(path.to_owned(), e))
+    }
+
     pub fn read_file_to_string(&self, path: &Path) -> Result<String, FileError> {
         if !path.exists() {
             return Err(FileError::NotFound(path.to_owned()));
@@ -89,6 +107,17 @@ pub fn read_file_to_string(&self, path: &Path) -> Result<String, FileError> {
         Ok(content)
     }
 
+    pub fn read_file(&self, path: &Path) -> Result<Vec<u8>, FileError> {
+        if !path.exists() {
+            return Err(FileError::NotFound(path.to_owned()));
+        }
+        let mut content = Vec::new();
+        let mut file = File::open(path).map_err(|e| FileError::OpenError(path.to_owned(), e))?;
+        file.read_to_end(&mut content)
+            .map_err(|e| FileError::ReadError(path.to_owned(), e))?;
+        Ok(content)
+    }
+
     pub fn write_to_file(&self, path: &Path, data: &[u8]) -> Result<(), FileError> {
         let mut file = File::create(path).map_err(|e| FileError::WriteError(path.to_owned(), e))?;
         file.write_all(data)
@@ -105,4 +134,13 @@ pub fn append_to_file(&self, path: &Path, data: &[u8]) -> Result<(), FileError>
             .map_err(|e| FileError::WriteError(path.to_owned(), e))?;
         Ok(())
     }
+
+    /// Opens an existing file for writing without truncating it, e.g. a named
+    /// pipe that is written to while it is open
+    pub fn open_for_writing(&self, path: &Path) -> Result<File, FileError> {
+        OpenOptions::new()
+            .write(true)
+            .open(path)
+            .map_err(|e| FileError::WriteError(path.to_owned(), e))
+    }
 }
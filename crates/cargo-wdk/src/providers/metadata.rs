@@ -15,6 +15,12 @@
 use clap_cargo::Features;
 use mockall::automock;
 
+/// Key of the driver type in the `driver-model` table of the WDK metadata
+const DRIVER_TYPE_KEY: &str = "driver-type";
+/// Driver types whose configuration keys in the `driver-model` table contain
+/// their lowercase name, e.g. `kmdf-version-major`
+const CONFIGURED_DRIVER_TYPES: [&str; 2] = ["KMDF", "UMDF"];
+
 #[derive(Default)]
 pub struct Metadata {}
 
@@ -59,3 +65,99 @@ pub fn get_cargo_metadata_at_path(
         cmd.exec()
     }
 }
+
+/// Returns the keys of the `wdk.driver-model` table of the `metadata` of a
+/// package or workspace that declare different driver types, e.g.
+/// `driver-type = "KMDF"` and `umdf-version-major` copied from a UMDF driver.
+/// The list is empty if the keys declare at most one driver type.
+#[must_use]
+pub fn conflicting_driver_type_keys(metadata: &serde_json::Value) -> Vec<String> {
+    let Some(driver_model) = metadata
+        .get("wdk")
+        .and_then(|wdk| wdk.get("driver-model"))
+        .and_then(serde_json::Value::as_object)
+    else {
+        return Vec::new();
+    };
+    let declarations: Vec<(String, String)> = driver_model
+        .iter()
+        .filter_map(|(key, value)| {
+            if key == DRIVER_TYPE_KEY {
+                return value.as_str().map(|driver_type| {
+                    (
+                        format!("{key} = \"{driver_type}\""),
+                        driver_type.to_ascii_uppercase(),
+                    )
+                });
+            }
+            CONFIGURED_DRIVER_TYPES
+                .iter()
+                .find(|driver_type| {
+                    key.split('-')
+                        .any(|part| part.eq_ignore_ascii_case(driver_type))
+                })
+                .map(|driver_type| (key.clone(), (*driver_type).to_string()))
+        })
+        .collect();
+    let Some((_, first_driver_type)) = declarations.first() else {
+        return Vec::new();
+    };
+    if declarations
+        .iter()
+        .all(|(_, driver_type)| driver_type == first_driver_type)
+    {
+        return Vec::new();
+    }
+    declarations.into_iter().map(|(key, _)| key).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::conflicting_driver_type_keys;
+
+    #[test]
+    fn keys_of_a_single_driver_type_do_not_conflict() {
+        for metadata in [
+            json!(null),
+            json!({ "wdk": {} }),
+            json!({ "wdk": { "driver-model": { "driver-type": "WDM" } } }),
+            json!({ "wdk": { "driver-model": {
+                "driver-type": "KMDF",
+                "kmdf-version-major": 1,
+                "target-kmdf-version-minor": 33
+            } } }),
+        ] {
+            assert!(conflicting_driver_type_keys(&metadata).is_empty(), "{metadata}");
+        }
+    }
+
+    #[test]
+    fn keys_of_different_driver_types_are_listed() {
+        let metadata = json!({ "wdk": { "driver-model": {
+            "driver-type": "KMDF",
+            "kmdf-version-major": 1,
+            "umdf-version-major": 2,
+            "target-umdf-version-minor": 33
+        } } });
+        assert_eq!(
+            conflicting_driver_type_keys(&metadata),
+            [
+                "driver-type = \"KMDF\"",
+                "kmdf-version-major",
+                "target-umdf-version-minor",
+                "umdf-version-major",
+            ]
+        );
+
+        let metadata = json!({ "wdk": { "driver-model": {
+            "driver-type": "WDM",
+            "kmdf-version-major": 1
+        } } });
+        assert_eq!(
+            conflicting_driver_type_keys(&metadata),
+            ["driver-type = \"WDM\"", "kmdf-version-major"]
+        );
+    }
+}
@@ -15,7 +15,7 @@
 pub mod wdk_build;
 
 pub mod error {
-    use std::{io, path::PathBuf, process::Output};
+    use std::{io, path::PathBuf, process::Output, time::Duration};
 
     /// Error type for `std::process::command` execution failures
     #[derive(Debug, thiserror::Error)]
@@ -28,26 +28,40 @@ pub enum CommandError {
         },
         #[error("Command '{0}' with args {1:?} IO error")]
         IoError(String, Vec<String>, #[source] io::Error),
+        #[error("Command '{0}' with args {1:?} timed out after {2:?} and was killed")]
+        Timeout(String, Vec<String>, Duration),
     }
 
     impl CommandError {
         pub fn from_output(command: &str, args: &[&str], output: &Output) -> Self {
             Self::CommandFailed {
                 command: command.to_string(),
-                args: args.iter().map(|&s| s.to_string()).collect(),
+                args: redacted_args(args),
                 stdout: String::from_utf8_lossy(&output.stdout).to_string(),
             }
         }
 
         pub fn from_io_error(command: &str, args: &[&str], e: io::Error) -> Self {
-            Self::IoError(
+            Self::IoError(command.to_string(), redacted_args(args), e)
+        }
+
+        pub fn from_timeout(command: &str, args: &[&str], timeout: Duration) -> Self {
+            Self::Timeout(
                 command.to_string(),
-                args.iter().map(|&s| s.to_string()).collect(),
-                e,
+                redacted_args(args),
+                timeout,
             )
         }
     }
 
+    // Secret arguments, e.g. passwords, are never part of the error messages
+    fn redacted_args(args: &[&str]) -> Vec<String> {
+        super::exec::redact_args(args)
+            .into_iter()
+            .map(str::to_string)
+            .collect()
+    }
+
     /// Error type for `std::file` operations
     #[derive(Debug, thiserror::Error)]
     pub enum FileError {
@@ -73,5 +87,9 @@ pub enum FileError {
         ReadDirError(PathBuf, #[source] io::Error),
         #[error("Failed to read directory entries for {0}")]
         ReadDirEntriesError(PathBuf, #[source] io::Error),
+        #[error("Failed to remove file {0}")]
+        RemoveError(PathBuf, #[source] io::Error),
+        #[error("Failed to remove directory {0}")]
+        RemoveDirError(PathBuf, #[source] io::Error),
     }
 }
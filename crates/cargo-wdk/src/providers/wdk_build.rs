@@ -1,7 +1,8 @@
 // Copyright (c) Microsoft Corporation
 // License: MIT OR Apache-2.0
 //! This module provides a wrapper around the `wdk-build` crate methods,
-//! focusing on the functionality required for detecting the WDK build number.
+//! focusing on the functionality required for detecting the WDK, its build
+//! number and the framework versions installed with it.
 //! It leverages the `mockall` crate to enable mocking of the `WdkBuild` struct
 //! for improved testability in unit tests.
 
@@ -9,7 +10,10 @@
 // The intellisense confusion seems to come from automock
 #![allow(dead_code)]
 #![allow(clippy::unused_self)]
+use std::path::{Path, PathBuf};
+
 use mockall::automock;
+use wdk_build::TwoPartVersion;
 
 /// Provides limited access to wdk-build crate methods
 #[derive(Default)]
@@ -17,7 +21,26 @@ pub struct WdkBuild {}
 
 #[automock]
 impl WdkBuild {
+    pub fn detect_wdk_content_root(&self) -> Result<PathBuf, wdk_build::ConfigError> {
+        wdk_build::detect_wdk_content_root()
+    }
+
+    pub fn detect_wdk_version(
+        &self,
+        wdk_content_root: &Path,
+    ) -> Result<String, wdk_build::ConfigError> {
+        wdk_build::detect_wdk_version(wdk_content_root)
+    }
+
     pub fn detect_wdk_build_number(&self) -> Result<u32, wdk_build::ConfigError> {
         wdk_build::detect_wdk_build_number()
     }
+
+    pub fn detect_kmdf_versions(&self) -> Result<Vec<TwoPartVersion>, wdk_build::ConfigError> {
+        wdk_build::detect_kmdf_versions()
+    }
+
+    pub fn detect_umdf_versions(&self) -> Result<Vec<TwoPartVersion>, wdk_build::ConfigError> {
+        wdk_build::detect_umdf_versions()
+    }
 }
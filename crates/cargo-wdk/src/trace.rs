@@ -8,15 +8,69 @@
 //!   levels.
 //! - A function to map clap verbosity levels to corresponding cargo verbose
 //!   flags.
+//! - The color choice of the output and a function to map it to the
+//!   corresponding cargo color flag.
 
-use std::io::IsTerminal;
+use std::{ffi::OsStr, io::IsTerminal};
 
 use tracing::level_filters::LevelFilter;
 use tracing_subscriber::EnvFilter;
 
+/// Environment variable that turns off colored output when it is set to a
+/// non-empty value, see <https://no-color.org>
+const NO_COLOR_ENV_VAR: &str = "NO_COLOR";
+
+/// Whether the output of cargo-wdk and of the `cargo` commands it runs is
+/// colored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    /// Color output written to a terminal
+    #[default]
+    Auto,
+    /// Always color output
+    Always,
+    /// Never color output
+    Never,
+}
+
+impl ColorChoice {
+    /// Returns the choice with `Auto` turned into `Never` if the `NO_COLOR`
+    /// environment variable is set to a non-empty value
+    #[must_use]
+    pub fn with_no_color_env(self) -> Self {
+        self.with_no_color(std::env::var_os(NO_COLOR_ENV_VAR).as_deref())
+    }
+
+    /// Returns the choice with `Auto` turned into `Never` if `no_color`, the
+    /// value of `NO_COLOR`, is set and not empty. `Always` and `Never` are
+    /// explicit choices and take precedence over `NO_COLOR`.
+    fn with_no_color(self, no_color: Option<&OsStr>) -> Self {
+        match self {
+            Self::Auto if no_color.is_some_and(|value| !value.is_empty()) => Self::Never,
+            choice => choice,
+        }
+    }
+
+    /// Returns `true` if output written to a stream is colored, given whether
+    /// the stream is a terminal
+    #[must_use]
+    pub const fn is_enabled(self, is_terminal: bool) -> bool {
+        match self {
+            Self::Auto => is_terminal,
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+}
+
 /// Initializes the tracing subscriber with a filter based on clap's verbosity
-/// level.
-pub fn init_tracing(verbosity_level: clap_verbosity_flag::Verbosity) {
+/// level. Only errors are logged if `errors_only` is set, whatever the
+/// verbosity level. The output is colored according to `color`.
+pub fn init_tracing(
+    verbosity_level: clap_verbosity_flag::Verbosity,
+    errors_only: bool,
+    color: ColorChoice,
+) {
     // Change default log level to
     // * INFO if no verbosity level is set
     // * Debug level when -v is set
@@ -27,6 +81,11 @@ pub fn init_tracing(verbosity_level: clap_verbosity_flag::Verbosity) {
         clap_verbosity_flag::VerbosityFilter::Warn => LevelFilter::DEBUG,
         _ => LevelFilter::TRACE,
     };
+    let level = if errors_only {
+        level.min(LevelFilter::ERROR)
+    } else {
+        level
+    };
 
     let tracing_filter = EnvFilter::default().add_directive(level.into());
 
@@ -37,7 +96,7 @@ pub fn init_tracing(verbosity_level: clap_verbosity_flag::Verbosity) {
         .with_file(false)
         .with_writer(std::io::stderr)
         .with_env_filter(tracing_filter)
-        .with_ansi(std::io::stderr().is_terminal())
+        .with_ansi(color.is_enabled(std::io::stderr().is_terminal()))
         .init();
 }
 
@@ -62,10 +121,25 @@ pub fn get_cargo_verbose_flags<'a>(
     }
 }
 
+/// Gets the value of the `--color` flag of cargo commands for the color
+/// choice. Returns `None` for `ColorChoice::Auto`, in which case cargo detects
+/// a terminal itself.
+pub const fn get_cargo_color_flag<'a>(color: ColorChoice) -> Option<&'a str> {
+    match color {
+        ColorChoice::Auto => None,
+        ColorChoice::Always => Some("always"),
+        ColorChoice::Never => Some("never"),
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::ffi::OsStr;
+
     use clap_verbosity_flag::Verbosity;
 
+    use super::ColorChoice;
+
     #[test]
     fn map_input_clap_verbosity_flags_to_cargo_flags() {
         // (incoming verbosity, expected cargo flag Option)
@@ -91,4 +165,30 @@ fn map_input_clap_verbosity_flags_to_cargo_flags() {
             assert_eq!(actual, expected_flag, "Unexpected cargo flag mapping");
         }
     }
+
+    #[test]
+    fn no_color_env_turns_off_only_automatic_color() {
+        // (choice, NO_COLOR, expected choice, expected cargo flag)
+        let cases = [
+            (ColorChoice::Auto, None, ColorChoice::Auto, None),
+            (ColorChoice::Auto, Some(""), ColorChoice::Auto, None),
+            (ColorChoice::Auto, Some("1"), ColorChoice::Never, Some("never")),
+            (ColorChoice::Always, Some("1"), ColorChoice::Always, Some("always")),
+            (ColorChoice::Never, None, ColorChoice::Never, Some("never")),
+        ];
+
+        for (choice, no_color, expected_choice, expected_flag) in cases {
+            let resolved = choice.with_no_color(no_color.map(OsStr::new));
+            assert_eq!(resolved, expected_choice, "{choice:?} with NO_COLOR={no_color:?}");
+            assert_eq!(super::get_cargo_color_flag(resolved), expected_flag);
+        }
+    }
+
+    #[test]
+    fn automatic_color_is_enabled_only_for_terminals() {
+        assert!(ColorChoice::Auto.is_enabled(true));
+        assert!(!ColorChoice::Auto.is_enabled(false));
+        assert!(ColorChoice::Always.is_enabled(false));
+        assert!(!ColorChoice::Never.is_enabled(true));
+    }
 }
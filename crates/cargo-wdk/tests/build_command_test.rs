@@ -8,6 +8,7 @@
 };
 
 use assert_cmd::prelude::*;
+use assert_fs::TempDir;
 use sha2::{Digest, Sha256};
 use test_utils::{create_cargo_wdk_cmd, with_mutex};
 
@@ -413,6 +414,20 @@ fn sign_mode_off_with_verify_signature_is_rejected() {
     );
 }
 
+/// A directory without any Rust packages fails with a dedicated exit code
+/// instead of reporting a successful or failed build
+#[test]
+fn empty_directory_fails_with_no_rust_packages_exit_code() {
+    let empty_dir = TempDir::new().expect("failed to create a temp dir");
+    let mut cmd = create_cargo_wdk_cmd("build", None, None, Some(empty_dir.path()));
+    let assertion = cmd.assert().code(3);
+    let stderr = String::from_utf8_lossy(&assertion.get_output().stderr).to_string();
+    assert!(
+        stderr.contains("No Rust packages found at"),
+        "expected the no packages error, got: {stderr}"
+    );
+}
+
 #[allow(clippy::too_many_arguments)]
 fn clean_build_and_verify_project(
     driver_type: &str,
@@ -35,6 +35,18 @@
 
 use crate::utils::detect_windows_sdk_version;
 
+/// Environment variable that overrides the KMDF version of the `driver-model`
+/// in the WDK metadata, in the `MAJOR.MINOR` format
+pub const KMDF_VERSION_OVERRIDE_ENV_VAR: &str = "WDK_BUILD_KMDF_VERSION";
+/// Environment variable that overrides the UMDF version of the `driver-model`
+/// in the WDK metadata, in the `MAJOR.MINOR` format
+pub const UMDF_VERSION_OVERRIDE_ENV_VAR: &str = "WDK_BUILD_UMDF_VERSION";
+
+/// Major version of every KMDF release
+const KMDF_VERSION_MAJOR: u8 = 1;
+/// Major version of every UMDF release supported by the WDK
+const UMDF_VERSION_MAJOR: u8 = 2;
+
 /// Configuration parameters for a build dependent on the WDK
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Config {
@@ -256,6 +268,43 @@ pub enum ConfigError {
         error_source: semver::Error,
     },
 
+    /// Error returned when the value of [`KMDF_VERSION_OVERRIDE_ENV_VAR`] or
+    /// [`UMDF_VERSION_OVERRIDE_ENV_VAR`] is not a valid WDF version
+    #[error("{env_var} is not a valid WDF version in the 'major.minor' format: {value}")]
+    InvalidWdfVersionOverride {
+        /// Name of the environment variable
+        env_var: &'static str,
+        /// Value of the environment variable
+        value: String,
+    },
+
+    /// Error returned when the major version of an overriding KMDF or UMDF
+    /// version is not the major version of the framework, i.e. 1 for KMDF and
+    /// 2 for UMDF
+    #[error("{framework} version {version} is not supported, expected version {expected_major}.x")]
+    UnsupportedWdfVersionMajor {
+        /// Name of the framework, i.e. `KMDF` or `UMDF`
+        framework: &'static str,
+        /// Overriding version of the framework
+        version: WdfVersion,
+        /// Major version of the framework
+        expected_major: u8,
+    },
+
+    /// Error returned when an overriding KMDF or UMDF version is lower than the
+    /// minimum version of the framework that the driver requires
+    #[error(
+        "WDF version {version} is lower than the minimum version {}.{minimum_minor} required by \
+         the driver",
+        version.major
+    )]
+    WdfVersionBelowMinimum {
+        /// Overriding version of the framework
+        version: WdfVersion,
+        /// Minimum minor version of the framework that the driver requires
+        minimum_minor: u8,
+    },
+
     /// Error returned when a [`metadata::Wdk`] fails to be parsed from a Cargo
     /// Manifest
     #[error(transparent)]
@@ -315,6 +364,106 @@ pub enum ConfigError {
     SerdeError(#[from] metadata::Error),
 }
 
+impl DriverConfig {
+    /// Replaces the KMDF version of a KMDF driver with `kmdf_version` or the
+    /// UMDF version of a UMDF driver with `umdf_version`, if set, e.g. to
+    /// build a driver against several framework versions without editing its
+    /// manifest. The minimum version the driver requires is kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConfigError::UnsupportedWdfVersionMajor`] if the major version
+    /// of the overriding version is not the one of its framework, or
+    /// [`ConfigError::WdfVersionBelowMinimum`] if the overriding version is
+    /// lower than the minimum version the driver requires
+    pub fn with_wdf_version_overrides(
+        self,
+        kmdf_version: Option<WdfVersion>,
+        umdf_version: Option<WdfVersion>,
+    ) -> Result<Self, ConfigError> {
+        Ok(match self {
+            Self::Wdm => Self::Wdm,
+            Self::Kmdf(mut kmdf_config) => {
+                if let Some(version) = kmdf_version {
+                    check_wdf_version(
+                        version,
+                        "KMDF",
+                        KMDF_VERSION_MAJOR,
+                        kmdf_config.minimum_kmdf_version_minor,
+                    )?;
+                    kmdf_config.kmdf_version_major = version.major;
+                    kmdf_config.target_kmdf_version_minor = version.minor;
+                }
+                Self::Kmdf(kmdf_config)
+            }
+            Self::Umdf(mut umdf_config) => {
+                if let Some(version) = umdf_version {
+                    check_wdf_version(
+                        version,
+                        "UMDF",
+                        UMDF_VERSION_MAJOR,
+                        umdf_config.minimum_umdf_version_minor,
+                    )?;
+                    umdf_config.umdf_version_major = version.major;
+                    umdf_config.target_umdf_version_minor = version.minor;
+                }
+                Self::Umdf(umdf_config)
+            }
+        })
+    }
+
+    /// Replaces the KMDF or UMDF version with the one set in
+    /// [`KMDF_VERSION_OVERRIDE_ENV_VAR`] or [`UMDF_VERSION_OVERRIDE_ENV_VAR`],
+    /// if any, see [`DriverConfig::with_wdf_version_overrides`]
+    fn with_wdf_version_overrides_from_env(self) -> Result<Self, ConfigError> {
+        for env_var in [KMDF_VERSION_OVERRIDE_ENV_VAR, UMDF_VERSION_OVERRIDE_ENV_VAR] {
+            println!("cargo:rerun-if-env-changed={env_var}");
+        }
+        self.with_wdf_version_overrides(
+            wdf_version_override(KMDF_VERSION_OVERRIDE_ENV_VAR)?,
+            wdf_version_override(UMDF_VERSION_OVERRIDE_ENV_VAR)?,
+        )
+    }
+}
+
+/// Returns an error if the major version of the overriding `version` is not
+/// the `major` version of its `framework`, or if its minor version is lower
+/// than the minimum minor version the driver requires, if any
+const fn check_wdf_version(
+    version: WdfVersion,
+    framework: &'static str,
+    major: u8,
+    minimum_minor: Option<u8>,
+) -> Result<(), ConfigError> {
+    if version.major != major {
+        return Err(ConfigError::UnsupportedWdfVersionMajor {
+            framework,
+            version,
+            expected_major: major,
+        });
+    }
+    match minimum_minor {
+        Some(minimum_minor) if version.minor < minimum_minor => {
+            Err(ConfigError::WdfVersionBelowMinimum {
+                version,
+                minimum_minor,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Reads the `MAJOR.MINOR` WDF version set in `env_var`, if any
+fn wdf_version_override(env_var: &'static str) -> Result<Option<WdfVersion>, ConfigError> {
+    let Ok(value) = env::var(env_var) else {
+        return Ok(None);
+    };
+    match value.parse() {
+        Ok(version) => Ok(Some(version)),
+        Err(_) => Err(ConfigError::InvalidWdfVersionOverride { env_var, value }),
+    }
+}
+
 /// Subset of APIs in the Windows Driver Kit
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum ApiSubset {
@@ -388,6 +537,55 @@ fn from_str(s: &str) -> Result<Self, Self::Err> {
     }
 }
 
+/// `MAJOR.MINOR` version of the KMDF or UMDF, e.g. as set in
+/// [`KMDF_VERSION_OVERRIDE_ENV_VAR`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WdfVersion {
+    /// Major version of the framework
+    pub major: u8,
+    /// Minor version of the framework
+    pub minor: u8,
+}
+
+/// Error when parsing a [`WdfVersion`]
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("'{0}' is not a valid framework version, expected 'major.minor'")]
+pub struct WdfVersionError(pub String);
+
+/// Parses a string of the form `MAJOR.MINOR` into a [`WdfVersion`], see
+/// [`TwoPartVersion`].
+///
+/// # Errors
+/// - Returns [`WdfVersionError`] if the string is not a [`TwoPartVersion`] or
+///   either component does not fit in a `u8`.
+impl FromStr for WdfVersion {
+    type Err = WdfVersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<TwoPartVersion>()
+            .ok()
+            .and_then(|TwoPartVersion(major, minor)| {
+                Some(Self {
+                    major: u8::try_from(major).ok()?,
+                    minor: u8::try_from(minor).ok()?,
+                })
+            })
+            .ok_or_else(|| WdfVersionError(s.to_string()))
+    }
+}
+
+impl fmt::Display for WdfVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl From<WdfVersion> for TwoPartVersion {
+    fn from(version: WdfVersion) -> Self {
+        Self(u32::from(version.major), u32::from(version.minor))
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -421,6 +619,8 @@ pub fn new() -> Self {
     /// * the parsing of [`metadata::Wdk`] from any of the Cargo manifests fail
     /// * multiple conflicting [`metadata::Wdk`] configurations are detected
     /// * no [`metadata::Wdk`] configurations are detected
+    /// * [`KMDF_VERSION_OVERRIDE_ENV_VAR`] or [`UMDF_VERSION_OVERRIDE_ENV_VAR`]
+    ///   is set to an invalid version
     ///
     /// # Panics
     ///
@@ -461,7 +661,9 @@ pub fn from_env_auto() -> Result<Self, ConfigError> {
         }
 
         Ok(Self {
-            driver_config: wdk_metadata.driver_model,
+            driver_config: wdk_metadata
+                .driver_model
+                .with_wdf_version_overrides_from_env()?,
             ..Default::default()
         })
     }
@@ -1304,7 +1506,7 @@ impl Default for KmdfConfig {
     fn default() -> Self {
         // FIXME: determine default values from TargetVersion and _NT_TARGET_VERSION
         Self {
-            kmdf_version_major: 1,
+            kmdf_version_major: KMDF_VERSION_MAJOR,
             target_kmdf_version_minor: 33,
             minimum_kmdf_version_minor: None,
         }
@@ -1323,7 +1525,7 @@ impl Default for UmdfConfig {
     fn default() -> Self {
         // FIXME: determine default values from TargetVersion and _NT_TARGET_VERSION
         Self {
-            umdf_version_major: 2,
+            umdf_version_major: UMDF_VERSION_MAJOR,
             target_umdf_version_minor: 33,
             minimum_umdf_version_minor: None,
         }
@@ -1501,6 +1703,30 @@ pub fn configure_wdk_binary_build() -> Result<(), ConfigError> {
 static EXPORTED_CFG_SETTINGS: LazyLock<Vec<(&'static str, Vec<&'static str>)>> =
     LazyLock::new(|| vec![("DRIVER_MODEL-DRIVER_TYPE", vec!["WDM", "KMDF", "UMDF"])]);
 
+/// Detects the `WDKContentRoot` directory of the WDK, e.g. from the
+/// environment of an eWDK prompt or from the registry of an installed WDK.
+/// Diagnostics about the candidates that were rejected are printed to stderr.
+///
+/// # Errors
+///
+/// Returns a `ConfigError::WdkContentRootDetectionError` if no WDK content
+/// root is found.
+pub fn detect_wdk_content_root() -> Result<PathBuf, ConfigError> {
+    utils::detect_wdk_content_root().ok_or(ConfigError::WdkContentRootDetectionError)
+}
+
+/// Detects the version of the WDK in the given content root, e.g.
+/// `10.0.26100.0`, from the `Version_Number` environment variable or the
+/// latest version in the `Lib` directory of the content root
+///
+/// # Errors
+///
+/// Returns a `ConfigError::DirectoryNotFound` error if the `Lib` directory of
+/// the content root has no version directory.
+pub fn detect_wdk_version(wdk_content_root: &Path) -> Result<String, ConfigError> {
+    detect_windows_sdk_version(wdk_content_root)
+}
+
 /// Detects the WDK build number.
 ///
 /// Detects the Windows Driver Kit (WDK) build number by locating
@@ -1526,9 +1752,8 @@ pub fn configure_wdk_binary_build() -> Result<(), ConfigError> {
 /// the version string.
 #[tracing::instrument(level = "debug")]
 pub fn detect_wdk_build_number() -> Result<u32, ConfigError> {
-    let wdk_content_root =
-        utils::detect_wdk_content_root().ok_or(ConfigError::WdkContentRootDetectionError)?;
-    let detected_sdk_version = detect_windows_sdk_version(&wdk_content_root)?;
+    let wdk_content_root = detect_wdk_content_root()?;
+    let detected_sdk_version = detect_wdk_version(&wdk_content_root)?;
 
     if !utils::validate_wdk_version_format(&detected_sdk_version) {
         return Err(ConfigError::WdkVersionStringFormatError {
@@ -1544,6 +1769,35 @@ pub fn detect_wdk_build_number() -> Result<u32, ConfigError> {
     Ok(wdk_build_number)
 }
 
+/// Detects the KMDF versions whose headers are installed in the WDK, in
+/// ascending order
+///
+/// # Errors
+///
+/// This function returns an error if the WDK content root cannot be detected
+/// or the KMDF headers directory of the WDK cannot be read.
+pub fn detect_kmdf_versions() -> Result<Vec<TwoPartVersion>, ConfigError> {
+    detect_wdf_versions("kmdf")
+}
+
+/// Detects the UMDF versions whose headers are installed in the WDK, in
+/// ascending order
+///
+/// # Errors
+///
+/// This function returns an error if the WDK content root cannot be detected
+/// or the UMDF headers directory of the WDK cannot be read.
+pub fn detect_umdf_versions() -> Result<Vec<TwoPartVersion>, ConfigError> {
+    detect_wdf_versions("umdf")
+}
+
+fn detect_wdf_versions(framework: &str) -> Result<Vec<TwoPartVersion>, ConfigError> {
+    let wdk_content_root = detect_wdk_content_root()?;
+    Ok(utils::find_versions_in_directory(
+        wdk_content_root.join("Include/wdf").join(framework),
+    )?)
+}
+
 #[cfg(test)]
 mod tests {
     #[cfg(assert_matches_stabilized)]
@@ -1937,6 +2191,26 @@ fn test_try_from_cargo_str() {
         assert_eq!(CpuArchitecture::try_from_cargo_str("arm"), None);
     }
 
+    mod wdf_version {
+        use super::*;
+
+        #[test]
+        fn versions_are_parsed_as_major_dot_minor() {
+            assert_eq!(
+                "1.31".parse::<WdfVersion>(),
+                Ok(WdfVersion {
+                    major: 1,
+                    minor: 31
+                })
+            );
+            assert_eq!(
+                "1".parse::<WdfVersion>(),
+                Err(WdfVersionError("1".to_string()))
+            );
+            assert!("1.256".parse::<WdfVersion>().is_err());
+        }
+    }
+
     mod bindgen_header_contents {
         use super::*;
         use crate::{KmdfConfig, UmdfConfig};
@@ -2001,6 +2275,123 @@ fn umdf() {
             );
         }
     }
+    mod with_wdf_version_overrides_from_env {
+        use super::*;
+        use crate::{KmdfConfig, UmdfConfig};
+
+        const KMDF_CONFIG: KmdfConfig = KmdfConfig {
+            kmdf_version_major: 1,
+            target_kmdf_version_minor: 33,
+            minimum_kmdf_version_minor: Some(31),
+        };
+
+        #[test]
+        fn kmdf_version_is_replaced() {
+            let driver_config = with_env(
+                &[
+                    (KMDF_VERSION_OVERRIDE_ENV_VAR, Some("1.31")),
+                    (UMDF_VERSION_OVERRIDE_ENV_VAR, Some("2.15")),
+                ],
+                || DriverConfig::Kmdf(KMDF_CONFIG).with_wdf_version_overrides_from_env(),
+            );
+
+            assert_eq!(
+                driver_config.unwrap(),
+                DriverConfig::Kmdf(KmdfConfig {
+                    kmdf_version_major: 1,
+                    target_kmdf_version_minor: 31,
+                    minimum_kmdf_version_minor: Some(31),
+                })
+            );
+        }
+
+        #[test]
+        fn umdf_version_is_unchanged_without_override() {
+            let umdf_config = UmdfConfig {
+                umdf_version_major: 2,
+                target_umdf_version_minor: 33,
+                minimum_umdf_version_minor: None,
+            };
+            let driver_config = with_env(
+                &[
+                    (KMDF_VERSION_OVERRIDE_ENV_VAR, Some("1.31")),
+                    (UMDF_VERSION_OVERRIDE_ENV_VAR, None),
+                ],
+                || DriverConfig::Umdf(umdf_config).with_wdf_version_overrides_from_env(),
+            );
+
+            assert_eq!(driver_config.unwrap(), DriverConfig::Umdf(umdf_config));
+        }
+
+        #[test]
+        fn version_below_the_minimum_is_rejected() {
+            let driver_config = with_env(&[(KMDF_VERSION_OVERRIDE_ENV_VAR, Some("1.29"))], || {
+                DriverConfig::Kmdf(KMDF_CONFIG).with_wdf_version_overrides_from_env()
+            });
+
+            assert!(matches!(
+                driver_config,
+                Err(ConfigError::WdfVersionBelowMinimum {
+                    version: WdfVersion {
+                        major: 1,
+                        minor: 29,
+                    },
+                    minimum_minor: 31,
+                })
+            ));
+        }
+
+        #[test]
+        fn version_with_another_major_is_rejected() {
+            let driver_config = with_env(&[(KMDF_VERSION_OVERRIDE_ENV_VAR, Some("2.33"))], || {
+                DriverConfig::Kmdf(KMDF_CONFIG).with_wdf_version_overrides_from_env()
+            });
+
+            assert!(matches!(
+                driver_config,
+                Err(ConfigError::UnsupportedWdfVersionMajor {
+                    framework: "KMDF",
+                    version: WdfVersion {
+                        major: 2,
+                        minor: 33,
+                    },
+                    expected_major: 1,
+                })
+            ));
+
+            let driver_config = with_env(&[(UMDF_VERSION_OVERRIDE_ENV_VAR, Some("1.33"))], || {
+                DriverConfig::Umdf(UmdfConfig::default()).with_wdf_version_overrides_from_env()
+            });
+
+            assert!(matches!(
+                driver_config,
+                Err(ConfigError::UnsupportedWdfVersionMajor {
+                    framework: "UMDF",
+                    version: WdfVersion {
+                        major: 1,
+                        minor: 33,
+                    },
+                    expected_major: 2,
+                })
+            ));
+        }
+
+        #[test]
+        fn invalid_version_is_rejected() {
+            let driver_config = with_env(&[(KMDF_VERSION_OVERRIDE_ENV_VAR, Some("1.256"))], || {
+                DriverConfig::Kmdf(KMDF_CONFIG).with_wdf_version_overrides_from_env()
+            });
+
+            assert!(matches!(
+                driver_config,
+                Err(ConfigError::InvalidWdfVersionOverride {
+                    env_var: KMDF_VERSION_OVERRIDE_ENV_VAR,
+                    ref value,
+                }) if value == "1.256"
+            ));
+        }
+    }
+
     mod compute_wdffunctions_symbol_name {
         use super::*;
         use crate::{KmdfConfig, UmdfConfig};
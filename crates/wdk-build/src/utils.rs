@@ -350,6 +350,22 @@ pub fn find_max_version_in_directory<P: AsRef<Path>>(
         })
 }
 
+/// Finds the versions in a directory where subdirectories are named with
+/// version format "x.y", in ascending order
+pub fn find_versions_in_directory<P: AsRef<Path>>(
+    directory_path: P,
+) -> Result<Vec<TwoPartVersion>, IoError> {
+    let directory_path = directory_path.as_ref();
+    let mut versions = std::fs::read_dir(directory_path)
+        .map_err(|source| IoError::with_path(directory_path, source))?
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|ft| ft.is_dir()))
+        .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+        .collect::<Vec<TwoPartVersion>>();
+    versions.sort_unstable();
+    Ok(versions)
+}
+
 /// Safely sets an environment variable. Will not compile if crate is not
 /// targeted for Windows.
 ///